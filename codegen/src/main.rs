@@ -26,6 +26,7 @@ fn main() {
         &PathBuf::from("src/generated/grpc_health_v1_fds.rs"),
         true,
         true,
+        false,
     );
 
     // tonic-reflection
@@ -40,6 +41,7 @@ fn main() {
         &PathBuf::from("src/generated/reflection_v1_fds.rs"),
         true,
         true,
+        false,
     );
     codegen(
         &PathBuf::from(std::env!("CARGO_MANIFEST_DIR"))
@@ -52,6 +54,7 @@ fn main() {
         &PathBuf::from("src/generated/reflection_v1alpha1_fds.rs"),
         true,
         true,
+        false,
     );
 
     // tonic-types
@@ -66,6 +69,7 @@ fn main() {
         &PathBuf::from("src/generated/types_fds.rs"),
         false,
         false,
+        false,
     );
 
     // grpc
@@ -80,10 +84,51 @@ fn main() {
         &PathBuf::from("src/generated/echo_fds.rs"),
         true,
         true,
+        false,
+    );
+    codegen(
+        &PathBuf::from(std::env!("CARGO_MANIFEST_DIR"))
+            .parent()
+            .unwrap()
+            .join("grpc"),
+        &["proto/echo/echo.proto"],
+        &["proto"],
+        &PathBuf::from("src/generated"),
+        &PathBuf::from("src/generated/echo_fds.rs"),
+        true,
+        true,
+        true,
+    );
+    codegen(
+        &PathBuf::from(std::env!("CARGO_MANIFEST_DIR"))
+            .parent()
+            .unwrap()
+            .join("grpc"),
+        &["proto/benchmark/benchmark_service.proto"],
+        &["proto"],
+        &PathBuf::from("src/generated"),
+        &PathBuf::from("src/generated/benchmark_fds.rs"),
+        true,
+        true,
+        false,
+    );
+    codegen(
+        &PathBuf::from(std::env!("CARGO_MANIFEST_DIR"))
+            .parent()
+            .unwrap()
+            .join("grpc"),
+        &["proto/benchmark/benchmark_service.proto"],
+        &["proto"],
+        &PathBuf::from("src/generated"),
+        &PathBuf::from("src/generated/benchmark_fds.rs"),
+        true,
+        true,
+        true,
     );
     println!("Codgen completed: {}ms", start.elapsed().as_millis());
 }
 
+#[allow(clippy::too_many_arguments)]
 fn codegen(
     root_dir: &Path,
     iface_files: &[&str],
@@ -92,6 +137,7 @@ fn codegen(
     file_descriptor_set_path: &Path,
     build_client: bool,
     build_server: bool,
+    grpc_stack: bool,
 ) {
     let tempdir = tempfile::Builder::new()
         .prefix("tonic-codegen-")
@@ -111,6 +157,7 @@ fn codegen(
         .build_client(build_client)
         .build_server(build_server)
         .build_transport(false)
+        .grpc_stack(grpc_stack)
         .out_dir(&tempdir)
         .compile_fds(fds)
         .unwrap();
@@ -125,7 +172,7 @@ fn codegen(
                 .strip_suffix(".rs")
                 .unwrap()
                 .replace('.', "_")
-                + ".rs",
+                + if grpc_stack { "_grpc.rs" } else { ".rs" },
         );
         std::fs::copy(&path, &to).unwrap();
     }
@@ -0,0 +1,139 @@
+//! End-to-end benchmarks of calls over the inmemory transport, covering the
+//! channel, pick and server paths.
+//!
+//! cargo bench -p grpc --bench channel
+
+use std::sync::Arc;
+
+use bencher::{benchmark_group, benchmark_main, Bencher};
+use bytes::Bytes;
+use grpc::client::call::CallOptions;
+use grpc::client::{Channel, ChannelOptions};
+use grpc::codegen::BoxStream;
+use grpc::inmemory;
+use grpc::server::{Router, Server};
+use tokio::runtime::Runtime;
+use tokio::task::JoinSet;
+use tokio_stream::StreamExt;
+
+const PAYLOAD_SIZE: usize = 1024;
+const CONCURRENT_CALLS: usize = 64;
+const CHANNELS: usize = 8;
+const STREAMED_MESSAGES: usize = 100;
+
+fn runtime() -> Runtime {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("runtime")
+}
+
+fn payload() -> Bytes {
+    Bytes::from(vec![0; PAYLOAD_SIZE])
+}
+
+// Serves echo methods on an inmemory listener, returning its target.  The
+// server runs until the runtime is dropped.
+fn serve(rt: &Runtime) -> String {
+    inmemory::reg();
+    let mut router = Router::new();
+    router
+        .unary("/bench.Echo/Unary", |req: Bytes| async move { Ok(req) })
+        .bidi("/bench.Echo/Bidi", |reqs: BoxStream<Bytes>| async move {
+            Ok(reqs)
+        });
+    let lis = inmemory::Listener::new();
+    let mut srv = Server::new();
+    srv.set_handler(router);
+    let target = lis.target();
+    rt.spawn(async move { srv.serve(&lis).await });
+    target
+}
+
+// Returns a channel to target, with a connection established.
+fn connect(rt: &Runtime, target: &str) -> Channel {
+    let chan = Channel::new(target, None, ChannelOptions::default());
+    rt.block_on(unary_call(&chan));
+    chan
+}
+
+async fn unary_call(chan: &Channel) {
+    let res: Bytes = chan
+        .unary("/bench.Echo/Unary", payload(), CallOptions::default())
+        .await
+        .expect("unary call");
+    assert_eq!(res.len(), PAYLOAD_SIZE);
+}
+
+fn unary(b: &mut Bencher) {
+    let rt = runtime();
+    let target = serve(&rt);
+    let chan = connect(&rt, &target);
+    b.bytes = PAYLOAD_SIZE as u64;
+    b.iter(|| rt.block_on(unary_call(&chan)));
+}
+
+fn unary_concurrent(b: &mut Bencher) {
+    let rt = runtime();
+    let target = serve(&rt);
+    let chan = connect(&rt, &target);
+    b.bytes = (PAYLOAD_SIZE * CONCURRENT_CALLS) as u64;
+    b.iter(|| {
+        rt.block_on(async {
+            let mut calls = JoinSet::new();
+            for _ in 0..CONCURRENT_CALLS {
+                let chan = chan.clone();
+                calls.spawn(async move { unary_call(&chan).await });
+            }
+            calls.join_all().await;
+        })
+    });
+}
+
+fn concurrent_channels(b: &mut Bencher) {
+    let rt = runtime();
+    let target = serve(&rt);
+    let chans: Arc<[Channel]> = (0..CHANNELS).map(|_| connect(&rt, &target)).collect();
+    b.bytes = (PAYLOAD_SIZE * CHANNELS) as u64;
+    b.iter(|| {
+        rt.block_on(async {
+            let mut calls = JoinSet::new();
+            for i in 0..CHANNELS {
+                let chans = chans.clone();
+                calls.spawn(async move { unary_call(&chans[i]).await });
+            }
+            calls.join_all().await;
+        })
+    });
+}
+
+// Measures the messages echoed on an established bidirectional stream.
+fn streaming(b: &mut Bencher) {
+    let rt = runtime();
+    let target = serve(&rt);
+    let chan = connect(&rt, &target);
+    // The call is performed on a task of the runtime.
+    let (sink, mut stream) = {
+        let _guard = rt.enter();
+        chan.bidi_streaming::<Bytes, Bytes>("/bench.Echo/Bidi", CallOptions::default())
+    };
+    b.bytes = (PAYLOAD_SIZE * STREAMED_MESSAGES) as u64;
+    b.iter(|| {
+        rt.block_on(async {
+            for _ in 0..STREAMED_MESSAGES {
+                sink.send(payload()).await.expect("send");
+                let res = stream.next().await.expect("response").expect("message");
+                assert_eq!(res.len(), PAYLOAD_SIZE);
+            }
+        })
+    });
+}
+
+benchmark_group!(
+    benches,
+    unary,
+    unary_concurrent,
+    concurrent_channels,
+    streaming
+);
+benchmark_main!(benches);
@@ -0,0 +1,43 @@
+//! Measures the latency of picks with the public picker benchmark harness,
+//! as authors of LB policies would for their own pickers.
+//!
+//! cargo bench -p grpc --bench picker
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use grpc::ext::load_balancing::{Pick, PickResult, Picker, PickerBenchmark, Subchannel};
+use grpc::Request;
+use tonic::metadata::MetadataMap;
+
+// Picks the subchannels in turn.
+struct RoundRobinPicker {
+    subchannels: Vec<Arc<dyn Subchannel>>,
+    next: AtomicUsize,
+}
+
+impl Picker for RoundRobinPicker {
+    fn pick(&self, _: &Request) -> PickResult {
+        let i = self.next.fetch_add(1, Ordering::Relaxed);
+        PickResult::Pick(Pick {
+            subchannel: self.subchannels[i % self.subchannels.len()].clone(),
+            metadata: MetadataMap::new(),
+            on_complete: None,
+        })
+    }
+}
+
+fn main() {
+    for subchannels in [1, 16, 1024] {
+        let picker = Arc::new(RoundRobinPicker {
+            subchannels: PickerBenchmark::subchannels(subchannels),
+            next: AtomicUsize::new(0),
+        });
+        for threads in [1, 4] {
+            let result = PickerBenchmark::default()
+                .threads(threads)
+                .run(picker.clone());
+            println!("round robin, {subchannels} subchannels, {threads} threads: {result}");
+        }
+    }
+}
@@ -0,0 +1,51 @@
+//! Runs the benchmark workload in-process over the new Channel and Server,
+//! using both the code generated for this crate and by tonic-build.
+//!
+//! cargo run --release --example benchmark --features benchmark
+
+use std::time::Duration;
+
+use grpc::benchmark::{classic, proto, run, BenchmarkServer, ClientConfig, RpcType};
+use grpc::client::{Channel, ChannelOptions};
+use grpc::inmemory;
+use grpc::server::{Server, TonicAdapter};
+use grpc::service::Service;
+
+fn serve(handler: impl Service + 'static) -> Channel {
+    let lis = inmemory::Listener::new();
+    let mut srv = Server::new();
+    srv.set_handler(handler);
+    let lis_clone = lis.clone();
+    tokio::task::spawn(async move {
+        srv.serve(&lis_clone).await;
+    });
+    Channel::new(lis.target().as_str(), None, ChannelOptions::default())
+}
+
+#[tokio::main]
+async fn main() {
+    inmemory::reg();
+
+    let chan = serve(
+        proto::benchmark_service_server::BenchmarkServiceServer::new(BenchmarkServer::default()),
+    );
+    let grpc_client = proto::benchmark_service_client::BenchmarkServiceClient::new(chan);
+    let chan = serve(TonicAdapter::new(
+        classic::benchmark_service_server::BenchmarkServiceServer::new(BenchmarkServer::default()),
+    ));
+    let tonic_client = classic::benchmark_service_client::BenchmarkServiceClient::new(chan);
+
+    for rpc_type in [RpcType::Unary, RpcType::Streaming] {
+        for size in [0, 1024, 64 * 1024] {
+            let config = ClientConfig::default()
+                .rpc_type(rpc_type)
+                .payload_sizes(size, size)
+                .outstanding_rpcs(16)
+                .duration(Duration::from_secs(5));
+            let result = run(grpc_client.clone(), &config).await;
+            println!("grpc  {rpc_type:?} {size}B: {result}");
+            let result = run(tonic_client.clone(), &config).await;
+            println!("tonic {rpc_type:?} {size}B: {result}");
+        }
+    }
+}
@@ -0,0 +1,68 @@
+//! Compares the allocations made to encode a stream of benchmark messages
+//! with and without a BufferPool, keeping a window of messages in flight as a
+//! transport would.
+//!
+//! cargo run --release --example buffer_pool --features benchmark
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+use grpc::benchmark::proto::{Payload, SimpleRequest, SimpleResponse};
+use grpc::codegen::{BufferPool, Bytes, Codec, ProstCodec};
+
+// Counts the allocations made by the process.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const MESSAGES: usize = 100_000;
+const IN_FLIGHT: usize = 64;
+
+fn measure(name: &str, size: usize, mut encode: impl FnMut(&SimpleRequest) -> Bytes) {
+    let msg = SimpleRequest {
+        response_size: 0,
+        payload: Some(Payload {
+            body: vec![0; size],
+        }),
+    };
+    let mut window = VecDeque::with_capacity(IN_FLIGHT);
+    let start = Instant::now();
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..MESSAGES {
+        if window.len() == IN_FLIGHT {
+            window.pop_front();
+        }
+        window.push_back(encode(&msg));
+    }
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    println!(
+        "{name:>8} {size:>6}B: {:.3} allocations/message, {:?}/message",
+        allocations as f64 / MESSAGES as f64,
+        start.elapsed() / MESSAGES as u32,
+    );
+}
+
+fn main() {
+    let codec = ProstCodec::<SimpleRequest, SimpleResponse>::default();
+    for size in [16, 256, 4096] {
+        measure("encode", size, |msg| codec.encode(msg));
+        let mut pool = BufferPool::new();
+        measure("pooled", size, |msg| codec.encode_pooled(msg, &mut pool));
+    }
+}
@@ -0,0 +1,387 @@
+//! A load balancing policy implemented outside of gRPC, using only the APIs in
+//! `grpc::ext`.
+//!
+//! The `least_loaded` policy connects to every address produced by the name
+//! resolver and routes each call to the ready subchannel with the fewest calls
+//! in flight.  Its config caps the calls in flight per subchannel; calls over
+//! the cap are queued until a call completes, which reports back to the policy
+//! through the completion callback of its pick and the work scheduler.
+//!
+//! A static resolver provides the addresses of three in-memory servers, and
+//! both are registered into registries local to the channel.
+
+use std::any::Any;
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use grpc::ext::load_balancing::{
+    ChannelController, LbConfig, LbPolicy, LbPolicyBuilder, LbPolicyConfig, LbPolicyOptions,
+    LbPolicyRegistry, LbState, ParsedJsonLbConfig, Pick, PickResult, Picker, Subchannel,
+    SubchannelState, WorkScheduler,
+};
+use grpc::ext::name_resolution::{
+    self as resolution, Address, Endpoint, Resolver, ResolverBuilder, ResolverOptions,
+    ResolverRegistry, ResolverUpdate, Target,
+};
+use grpc::service::{Message, Request, Response, Service};
+use grpc::{inmemory, ChannelOptions, ConnectivityState, Status};
+use serde::Deserialize;
+use tokio::task::JoinSet;
+use tokio_stream::StreamExt;
+use tonic::async_trait;
+use tonic::metadata::MetadataMap;
+
+const POLICY_NAME: &str = "least_loaded";
+
+/// The parsed config of the policy.
+struct LeastLoadedConfig {
+    max_calls_per_backend: usize,
+}
+
+impl LbPolicyConfig for LeastLoadedConfig {}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonConfig {
+    max_calls_per_backend: Option<usize>,
+}
+
+struct LeastLoadedBuilder;
+
+impl LbPolicyBuilder for LeastLoadedBuilder {
+    fn build(&self, options: LbPolicyOptions) -> Box<dyn LbPolicy> {
+        Box::new(LeastLoadedPolicy {
+            work_scheduler: options.work_scheduler,
+            backends: Vec::new(),
+            max_calls_per_backend: usize::MAX,
+            saturated: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        POLICY_NAME
+    }
+
+    fn parse_config(
+        &self,
+        config: &ParsedJsonLbConfig,
+    ) -> Result<Option<LbConfig>, Box<dyn Error + Send + Sync>> {
+        let cfg: JsonConfig = config.convert_to()?;
+        let max_calls_per_backend = cfg.max_calls_per_backend.unwrap_or(usize::MAX);
+        if max_calls_per_backend == 0 {
+            return Err("maxCallsPerBackend must be positive".into());
+        }
+        Ok(Some(
+            LeastLoadedConfig {
+                max_calls_per_backend,
+            }
+            .into_lb_config(),
+        ))
+    }
+}
+
+/// A subchannel of the policy, with the number of calls in flight on it.
+struct Backend {
+    subchannel: Arc<dyn Subchannel>,
+    state: ConnectivityState,
+    in_flight: Arc<AtomicUsize>,
+}
+
+struct LeastLoadedPolicy {
+    work_scheduler: Arc<dyn WorkScheduler>,
+    backends: Vec<Backend>,
+    max_calls_per_backend: usize,
+    // Set by pickers which queued a call because every backend was at the
+    // cap, so that the next completed call asks for a new picker.
+    saturated: Arc<AtomicBool>,
+}
+
+impl LeastLoadedPolicy {
+    // Publishes a picker reflecting the current states of the backends.
+    fn update_picker(&self, channel_controller: &mut dyn ChannelController) {
+        let state = ConnectivityState::aggregate(self.backends.iter().map(|b| b.state));
+        let picker: Arc<dyn Picker> = match state {
+            ConnectivityState::Ready => Arc::new(LeastLoadedPicker {
+                ready: self
+                    .backends
+                    .iter()
+                    .filter(|b| b.state == ConnectivityState::Ready)
+                    .map(|b| (b.subchannel.clone(), b.in_flight.clone()))
+                    .collect(),
+                max_calls_per_backend: self.max_calls_per_backend,
+                next: AtomicUsize::new(0),
+                saturated: self.saturated.clone(),
+                work_scheduler: self.work_scheduler.clone(),
+            }),
+            ConnectivityState::TransientFailure => Arc::new(FailingPicker),
+            _ => Arc::new(QueuingPicker),
+        };
+        channel_controller.update_picker(LbState {
+            connectivity_state: state,
+            picker,
+        });
+    }
+}
+
+impl LbPolicy for LeastLoadedPolicy {
+    fn resolver_update(
+        &mut self,
+        update: ResolverUpdate,
+        config: Option<&LbConfig>,
+        channel_controller: &mut dyn ChannelController,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let config = LeastLoadedConfig::from_lb_config(config)?;
+        self.max_calls_per_backend = config.max_calls_per_backend;
+        let addresses: Vec<Address> = match update.endpoints {
+            Ok(endpoints) => endpoints.into_iter().flat_map(|e| e.addresses).collect(),
+            // Keep using the previous addresses, if any.
+            Err(err) if !self.backends.is_empty() => return Err(err.into()),
+            Err(err) => {
+                channel_controller.update_picker(LbState {
+                    connectivity_state: ConnectivityState::TransientFailure,
+                    picker: Arc::new(FailingPicker),
+                });
+                return Err(err.into());
+            }
+        };
+
+        // Keep the subchannels of addresses which remain, and create the rest.
+        // Dropping a subchannel releases its connection.
+        let mut old = std::mem::take(&mut self.backends);
+        for address in addresses {
+            match old.iter().position(|b| b.subchannel.address() == address) {
+                Some(i) => self.backends.push(old.swap_remove(i)),
+                None => {
+                    let subchannel = channel_controller.new_subchannel(&address);
+                    subchannel.connect();
+                    self.backends.push(Backend {
+                        subchannel,
+                        state: ConnectivityState::Idle,
+                        in_flight: Arc::default(),
+                    });
+                }
+            }
+        }
+        self.update_picker(channel_controller);
+        Ok(())
+    }
+
+    fn subchannel_update(
+        &mut self,
+        subchannel: Arc<dyn Subchannel>,
+        state: &SubchannelState,
+        channel_controller: &mut dyn ChannelController,
+    ) {
+        let Some(backend) = self
+            .backends
+            .iter_mut()
+            .find(|b| *b.subchannel == *subchannel)
+        else {
+            // The subchannel was removed by a resolver update.
+            return;
+        };
+        backend.state = state.connectivity_state;
+        match state.connectivity_state {
+            // Stay connected to every backend.
+            ConnectivityState::Idle => subchannel.connect(),
+            // The address may be stale.
+            ConnectivityState::TransientFailure => channel_controller.request_resolution(),
+            _ => {}
+        }
+        self.update_picker(channel_controller);
+    }
+
+    fn work(&mut self, channel_controller: &mut dyn ChannelController) {
+        // A call completed while calls were queued; publish a new picker so
+        // the channel picks them again.
+        self.update_picker(channel_controller);
+    }
+
+    fn exit_idle(&mut self, _channel_controller: &mut dyn ChannelController) {
+        for backend in &self.backends {
+            if backend.state == ConnectivityState::Idle {
+                backend.subchannel.connect();
+            }
+        }
+    }
+}
+
+struct LeastLoadedPicker {
+    ready: Vec<(Arc<dyn Subchannel>, Arc<AtomicUsize>)>,
+    max_calls_per_backend: usize,
+    // Rotates the starting point of the search, to spread calls among equally
+    // loaded backends.
+    next: AtomicUsize,
+    saturated: Arc<AtomicBool>,
+    work_scheduler: Arc<dyn WorkScheduler>,
+}
+
+impl Picker for LeastLoadedPicker {
+    fn pick(&self, _request: &Request) -> PickResult {
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        let (subchannel, in_flight) = (0..self.ready.len())
+            .map(|i| &self.ready[(start + i) % self.ready.len()])
+            .min_by_key(|(_, in_flight)| in_flight.load(Ordering::Relaxed))
+            .unwrap();
+        if in_flight.fetch_add(1, Ordering::AcqRel) >= self.max_calls_per_backend {
+            in_flight.fetch_sub(1, Ordering::AcqRel);
+            self.saturated.store(true, Ordering::Release);
+            return PickResult::Queue;
+        }
+
+        let in_flight = in_flight.clone();
+        let saturated = self.saturated.clone();
+        let work_scheduler = self.work_scheduler.clone();
+        PickResult::Pick(Pick {
+            subchannel: subchannel.clone(),
+            metadata: MetadataMap::new(),
+            on_complete: Some(Box::new(move |outcome| {
+                in_flight.fetch_sub(1, Ordering::AcqRel);
+                if saturated.swap(false, Ordering::AcqRel) {
+                    work_scheduler.schedule_work();
+                }
+                if outcome.status.code() != tonic::Code::Ok {
+                    println!("call failed: {}", outcome.status);
+                }
+            })),
+        })
+    }
+}
+
+struct QueuingPicker;
+
+impl Picker for QueuingPicker {
+    fn pick(&self, _request: &Request) -> PickResult {
+        PickResult::Queue
+    }
+}
+
+struct FailingPicker;
+
+impl Picker for FailingPicker {
+    fn pick(&self, _request: &Request) -> PickResult {
+        PickResult::Fail(Status::unavailable("no backend is reachable"))
+    }
+}
+
+/// Resolves "static:" targets to a fixed list of addresses.
+struct StaticResolverBuilder {
+    addresses: Vec<Address>,
+}
+
+impl ResolverBuilder for StaticResolverBuilder {
+    fn build(&self, _target: &Target, options: ResolverOptions) -> Box<dyn Resolver> {
+        options.work_scheduler.schedule_work();
+        Box::new(StaticResolver {
+            addresses: self.addresses.clone(),
+        })
+    }
+
+    fn scheme(&self) -> &str {
+        "static"
+    }
+
+    fn is_valid_uri(&self, _target: &Target) -> bool {
+        true
+    }
+}
+
+struct StaticResolver {
+    addresses: Vec<Address>,
+}
+
+impl Resolver for StaticResolver {
+    fn work(&mut self, channel_controller: &mut dyn resolution::ChannelController) {
+        let endpoints = self.addresses.iter().map(|address| {
+            Endpoint::builder()
+                .address(address.clone())
+                .build()
+                .unwrap()
+        });
+        let update = ResolverUpdate::builder().endpoints(endpoints).build();
+        let _ = channel_controller.update(update);
+    }
+
+    fn resolve_now(&mut self) {}
+}
+
+/// Replies with the ID of the server after a short delay, so that calls
+/// overlap.
+struct Handler {
+    id: String,
+}
+
+#[derive(Debug)]
+struct Reply(String);
+
+#[async_trait]
+impl Service for Handler {
+    async fn call(&self, _method: String, _request: Request) -> Response {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let reply = Box::new(Reply(self.id.clone())) as Box<dyn Message>;
+        Response::new(Box::pin(tokio_stream::once(Ok(reply))))
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    inmemory::reg();
+
+    let mut listeners = Vec::new();
+    for _ in 0..3 {
+        let lis = inmemory::Listener::new();
+        let mut srv = grpc::server::Server::new();
+        srv.set_handler(Handler { id: lis.id() });
+        let lis_clone = lis.clone();
+        tokio::task::spawn(async move { srv.serve(&lis_clone).await });
+        listeners.push(lis);
+    }
+
+    let resolvers = ResolverRegistry::new();
+    resolvers.add_builder(Box::new(StaticResolverBuilder {
+        addresses: listeners
+            .iter()
+            .map(|lis| Address::new("inmemory", lis.id()))
+            .collect(),
+    }));
+    let policies = LbPolicyRegistry::new();
+    policies.add_builder(LeastLoadedBuilder);
+
+    let options = ChannelOptions::default()
+        .name_resolver_registry(resolvers)
+        .lb_policy_registry(policies)
+        .default_service_config(format!(
+            r#"{{"loadBalancingConfig": [{{"{POLICY_NAME}": {{"maxCallsPerBackend": 2}}}}]}}"#
+        ));
+    let chan = grpc::Channel::new("static:///servers", None, options);
+
+    // Twelve concurrent calls are spread over the three servers, two at a
+    // time each; the rest wait for earlier calls to complete.
+    let mut calls = JoinSet::new();
+    for i in 0..12 {
+        let chan = chan.clone();
+        calls.spawn(async move {
+            let request = Request::new(Box::pin(tokio_stream::once(
+                Box::new(format!("request {i}")) as Box<dyn Message>,
+            )));
+            let mut response = chan
+                .call("/example/Call".to_string(), request)
+                .await
+                .into_inner();
+            // Reading the stream to its end completes the call.
+            while let Some(reply) = response.next().await {
+                let reply = (reply.unwrap() as Box<dyn Any>)
+                    .downcast::<Reply>()
+                    .unwrap();
+                println!("request {i} was served by {}", reply.0);
+            }
+        });
+    }
+    calls.join_all().await;
+
+    for lis in listeners {
+        lis.close().await;
+    }
+}
@@ -0,0 +1,193 @@
+//! A name resolver whose addresses are pushed to it by the application.
+//!
+//! The `watch` resolver is backed by a tokio watch channel of addresses.  A
+//! task wakes the resolver through its work scheduler whenever the addresses
+//! change, and the resolver delivers them to the channel from `work`, so a
+//! live channel follows every change.  The example moves a channel between
+//! in-memory servers, removes the server it is using, and asks the channel to
+//! re-resolve.
+
+use std::any::Any;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use grpc::ext::name_resolution::{
+    Address, ChannelController, Endpoint, Resolver, ResolverBuilder, ResolverOptions,
+    ResolverRegistry, ResolverUpdate, Target, WorkScheduler,
+};
+use grpc::service::{Message, Request, Response, Service};
+use grpc::{inmemory, Channel, ChannelOptions};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio_stream::StreamExt;
+use tonic::async_trait;
+
+/// Builds resolvers for "watch:" targets, which all follow the same list of
+/// addresses.
+struct WatchResolverBuilder {
+    addresses: watch::Receiver<Vec<Address>>,
+}
+
+impl ResolverBuilder for WatchResolverBuilder {
+    fn build(&self, _target: &Target, options: ResolverOptions) -> Box<dyn Resolver> {
+        let mut changes = self.addresses.clone();
+        let work_scheduler = options.work_scheduler.clone();
+        let watcher = tokio::spawn(async move {
+            while changes.changed().await.is_ok() {
+                work_scheduler.schedule_work();
+            }
+        });
+        // Deliver the current addresses right away.
+        options.work_scheduler.schedule_work();
+        Box::new(WatchResolver {
+            addresses: self.addresses.clone(),
+            work_scheduler: options.work_scheduler,
+            watcher,
+        })
+    }
+
+    fn scheme(&self) -> &str {
+        "watch"
+    }
+
+    fn is_valid_uri(&self, _target: &Target) -> bool {
+        true
+    }
+}
+
+struct WatchResolver {
+    addresses: watch::Receiver<Vec<Address>>,
+    work_scheduler: Arc<dyn WorkScheduler>,
+    watcher: JoinHandle<()>,
+}
+
+impl Resolver for WatchResolver {
+    fn resolve_now(&mut self) {
+        // Updates are pushed, so there is nothing to look up; deliver the
+        // current addresses again.
+        println!("resolver: re-resolution requested");
+        self.work_scheduler.schedule_work();
+    }
+
+    fn work(&mut self, channel_controller: &mut dyn ChannelController) {
+        let addresses = self.addresses.borrow_and_update().clone();
+        let update = if addresses.is_empty() {
+            ResolverUpdate::builder().endpoints_error("no addresses were pushed")
+        } else {
+            ResolverUpdate::builder().endpoints(
+                addresses
+                    .into_iter()
+                    .map(|address| Endpoint::builder().address(address).build().unwrap()),
+            )
+        };
+        if let Err(err) = channel_controller.update(update.build()) {
+            // A push-based resolver has nothing better to offer; the next
+            // push produces a new update.
+            println!("resolver: channel rejected the update: {err}");
+        }
+    }
+}
+
+impl Drop for WatchResolver {
+    // Resolvers must release their resources when the channel drops them.
+    fn drop(&mut self) {
+        self.watcher.abort();
+    }
+}
+
+/// Replies with the ID of the server.
+struct Handler {
+    id: String,
+}
+
+#[derive(Debug)]
+struct Reply(String);
+
+#[async_trait]
+impl Service for Handler {
+    async fn call(&self, _method: String, _request: Request) -> Response {
+        let reply = Box::new(Reply(self.id.clone())) as Box<dyn Message>;
+        Response::new(Box::pin(tokio_stream::once(Ok(reply))))
+    }
+}
+
+// Returns the ID of the server which handled a call.
+async fn call(chan: &Channel) -> String {
+    let request = Request::new(Box::pin(tokio_stream::once(
+        Box::new("hello".to_string()) as Box<dyn Message>
+    )));
+    let mut response = chan
+        .call("/example/Call".to_string(), request)
+        .await
+        .into_inner();
+    let mut server = String::new();
+    while let Some(reply) = response.next().await {
+        server = (reply.unwrap() as Box<dyn Any>)
+            .downcast::<Reply>()
+            .unwrap()
+            .0;
+    }
+    server
+}
+
+// Makes calls until one is handled by server.  The channel applies pushed
+// addresses asynchronously, so calls started right after a push may still use
+// the previous ones.
+async fn wait_for_server(chan: &Channel, server: &str) {
+    loop {
+        let handled_by = call(chan).await;
+        if handled_by == server {
+            println!("call handled by server {handled_by}");
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    inmemory::reg();
+
+    let mut listeners = Vec::new();
+    for _ in 0..3 {
+        let lis = inmemory::Listener::new();
+        let mut srv = grpc::server::Server::new();
+        srv.set_handler(Handler { id: lis.id() });
+        let lis_clone = lis.clone();
+        tokio::task::spawn(async move { srv.serve(&lis_clone).await });
+        listeners.push(lis);
+    }
+    let address = |i: usize| Address::new("inmemory", listeners[i].id());
+
+    let (tx, rx) = watch::channel(vec![address(0)]);
+    let resolvers = ResolverRegistry::new();
+    resolvers.add_builder(Box::new(WatchResolverBuilder { addresses: rx }));
+    let options = ChannelOptions::default().name_resolver_registry(resolvers);
+    let chan = Channel::new("watch:///backends", None, options);
+    wait_for_server(&chan, &listeners[0].id()).await;
+
+    // Replace the addresses.  The channel's default policy, pick_first, uses
+    // the first one.
+    println!("pushing servers 1 and 2");
+    tx.send_replace(vec![address(1), address(2)]);
+    wait_for_server(&chan, &listeners[1].id()).await;
+
+    // Remove the server in use; the channel moves to the remaining one, and
+    // the removed server can be shut down.
+    println!("removing server 1");
+    tx.send_replace(vec![address(2)]);
+    wait_for_server(&chan, &listeners[2].id()).await;
+    listeners[1].close().await;
+
+    // Re-resolution requests reach the resolver, e.g. when an application
+    // learns out of band that the backends changed.
+    let deadline = Instant::now() + Duration::from_secs(1);
+    match chan.reresolve_now(deadline).await {
+        Ok(()) => println!("re-resolution accepted"),
+        Err(err) => println!("re-resolution failed: {err}"),
+    }
+    wait_for_server(&chan, &listeners[2].id()).await;
+
+    listeners[0].close().await;
+    listeners[2].close().await;
+}
@@ -1,7 +1,10 @@
 use std::any::Any;
 
 use grpc::service::{Message, Request, Response, Service};
-use grpc::{client::ChannelOptions, inmemory};
+use grpc::{
+    client::{call::CallOptions, ChannelOptions},
+    inmemory,
+};
 use tokio_stream::StreamExt;
 use tonic::async_trait;
 
@@ -67,5 +70,16 @@ async fn main() {
                 .0,
         );
     }
+
+    // Unary calls can use the typed helper instead.
+    let res: MyResMessage = chan
+        .unary(
+            "/some/method",
+            MyReqMessage("My Request 4".to_string()),
+            CallOptions::default(),
+        )
+        .await
+        .unwrap();
+    println!("UNARY RESPONSE: {}", res.0);
     lis.close().await;
 }
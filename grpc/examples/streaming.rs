@@ -0,0 +1,68 @@
+use std::any::Any;
+
+use bytes::Bytes;
+use grpc::service::{Message, Request, Response, Service};
+use grpc::{client::ChannelOptions, inmemory};
+use tokio_stream::StreamExt;
+use tonic::async_trait;
+
+// Replies to each request with a countdown from the number it holds, so each
+// request produces a stream of responses.
+struct Countdown {}
+
+#[async_trait]
+impl Service for Countdown {
+    async fn call(&self, _method: String, request: Request) -> Response {
+        let mut stream = request.into_inner();
+        let output = async_stream::try_stream! {
+            while let Some(req) = stream.next().await {
+                let req = (req as Box<dyn Any>).downcast::<Bytes>().unwrap();
+                let n: u32 = std::str::from_utf8(&req).unwrap().parse().unwrap();
+                for i in (0..=n).rev() {
+                    yield Box::new(Bytes::from(format!("{n}: {i}"))) as Box<dyn Message>;
+                }
+            }
+        };
+
+        Response::new(Box::pin(output))
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    inmemory::reg();
+
+    // Spawn the server.
+    let lis = inmemory::Listener::new();
+    let mut srv = grpc::server::Server::new();
+    srv.set_handler(Countdown {});
+    let lis_clone = lis.clone();
+    tokio::task::spawn(async move {
+        srv.serve(&lis_clone).await;
+    });
+
+    let chan = grpc::client::Channel::new(lis.target().as_str(), None, ChannelOptions::default());
+
+    // Responses are received while later requests are still being sent.
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let req = Request::new(Box::pin(
+        tokio_stream::wrappers::UnboundedReceiverStream::new(rx),
+    ));
+    let mut res = chan
+        .call("/example/Countdown".to_string(), req)
+        .await
+        .into_inner();
+
+    for n in [2, 3] {
+        tx.send(Box::new(Bytes::from(n.to_string())) as Box<dyn Message>)
+            .unwrap();
+        for _ in 0..=n {
+            let resp = res.next().await.unwrap().unwrap();
+            let resp = (resp as Box<dyn Any>).downcast::<Bytes>().unwrap();
+            println!("CALL RESPONSE: {}", std::str::from_utf8(&resp).unwrap());
+        }
+    }
+    drop(tx);
+    assert!(res.next().await.is_none());
+    lis.close().await;
+}
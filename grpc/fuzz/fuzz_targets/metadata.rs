@@ -0,0 +1,10 @@
+//! Feeds arbitrary bytes to the parsers of metadata values, including
+//! base64 encoded binary values.
+//!
+//! cargo +nightly fuzz run metadata
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| grpc::fuzzing::metadata(data));
@@ -0,0 +1,9 @@
+//! Feeds arbitrary bytes to the JSON service config parser.
+//!
+//! cargo +nightly fuzz run service_config
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| grpc::fuzzing::service_config(data));
@@ -0,0 +1,9 @@
+//! Feeds arbitrary bytes to the channel target parser.
+//!
+//! cargo +nightly fuzz run target
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| grpc::fuzzing::target(data));
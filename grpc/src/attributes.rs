@@ -22,7 +22,130 @@
  *
  */
 
+use std::{
+    any::Any,
+    cmp::Ordering,
+    collections::BTreeMap,
+    fmt::{self, Debug, Formatter},
+    marker::PhantomData,
+    sync::Arc,
+};
+
+/// A typed key used to store and retrieve values in [`Attributes`].  Keys are
+/// identified by their name, which should be unique across all components;
+/// keys are typically declared as constants by the component that consumes
+/// the value.
+pub struct AttributeKey<T> {
+    name: &'static str,
+    _phantom: PhantomData<fn() -> T>,
+}
+
+impl<T> AttributeKey<T> {
+    /// Creates a new key with the provided name.
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the name of the key.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+impl<T> Debug for AttributeKey<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "AttributeKey({})", self.name)
+    }
+}
+
 /// A key-value store for arbitrary configuration data between multiple
 /// pluggable components.
-#[derive(Debug, Default, Clone, PartialEq, PartialOrd, Eq, Ord)]
-pub struct Attributes;
+///
+/// Attributes are immutable once shared and cheap to clone.  Two sets of
+/// attributes are considered equal if they contain the same keys mapped to
+/// the same (identical, not merely equal) values.
+#[derive(Default, Clone)]
+pub struct Attributes {
+    map: BTreeMap<&'static str, Arc<dyn Any + Send + Sync>>,
+}
+
+impl Attributes {
+    /// Returns a copy of these attributes with `key` set to `value`,
+    /// replacing any previous value for `key`.
+    pub fn add<T: Send + Sync + 'static>(mut self, key: &AttributeKey<T>, value: T) -> Self {
+        self.map.insert(key.name, Arc::new(value));
+        self
+    }
+
+    /// Returns the value stored for `key`, if any.
+    pub fn get<T: Send + Sync + 'static>(&self, key: &AttributeKey<T>) -> Option<&T> {
+        self.map.get(key.name)?.downcast_ref()
+    }
+
+    /// Returns true if no values are stored.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    fn entries(&self) -> impl Iterator<Item = (&'static str, *const ())> + '_ {
+        self.map
+            .iter()
+            .map(|(k, v)| (*k, Arc::as_ptr(v) as *const ()))
+    }
+}
+
+impl Debug for Attributes {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.map.keys()).finish()
+    }
+}
+
+impl PartialEq for Attributes {
+    fn eq(&self, other: &Self) -> bool {
+        self.entries().eq(other.entries())
+    }
+}
+
+impl Eq for Attributes {}
+
+impl PartialOrd for Attributes {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Attributes {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.entries().cmp(other.entries())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const NAME: AttributeKey<String> = AttributeKey::new("test.name");
+    const COUNT: AttributeKey<u32> = AttributeKey::new("test.count");
+
+    #[test]
+    fn add_and_get() {
+        let attrs = Attributes::default()
+            .add(&NAME, "foo".to_string())
+            .add(&COUNT, 3);
+        assert_eq!(attrs.get(&NAME).map(String::as_str), Some("foo"));
+        assert_eq!(attrs.get(&COUNT), Some(&3));
+        let attrs = attrs.add(&COUNT, 4);
+        assert_eq!(attrs.get(&COUNT), Some(&4));
+    }
+
+    #[test]
+    fn equality_is_identity() {
+        let a = Attributes::default().add(&COUNT, 3);
+        assert_eq!(a, a.clone());
+        assert_ne!(a, Attributes::default().add(&COUNT, 3));
+        assert_eq!(Attributes::default(), Attributes::default());
+    }
+}
@@ -0,0 +1,465 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+use std::{
+    fmt::{self, Display, Formatter},
+    pin::Pin,
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+use http_body::Body as HttpBody;
+use tokio::{sync::mpsc, task::JoinSet};
+use tokio_stream::{wrappers::ReceiverStream, Stream, StreamExt};
+use tonic::{async_trait, body::Body, client::GrpcService, Status};
+
+use super::{classic, proto};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// The kind of calls made by a benchmark.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RpcType {
+    /// Each operation is a unary call.
+    Unary,
+    /// Each operation is a request followed by a response on a streaming
+    /// call which lasts for the whole benchmark.
+    Streaming,
+}
+
+/// Configures the workload of a benchmark client.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ClientConfig {
+    pub rpc_type: RpcType,
+    /// The size of the payload of each request.
+    pub request_size: usize,
+    /// The size of the payload of each response.
+    pub response_size: usize,
+    /// The number of operations in flight at once.
+    pub outstanding_rpcs: usize,
+    /// The total rate of operations to attempt across all outstanding RPCs.
+    /// None issues operations as fast as possible (closed loop).
+    pub target_qps: Option<f64>,
+    /// Operations started during warmup are not measured.
+    pub warmup: Duration,
+    /// How long operations are measured for, after warmup.
+    pub duration: Duration,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            rpc_type: RpcType::Unary,
+            request_size: 0,
+            response_size: 0,
+            outstanding_rpcs: 1,
+            target_qps: None,
+            warmup: Duration::from_secs(1),
+            duration: Duration::from_secs(10),
+        }
+    }
+}
+
+impl ClientConfig {
+    pub fn rpc_type(self, rpc_type: RpcType) -> Self {
+        Self { rpc_type, ..self }
+    }
+
+    pub fn payload_sizes(self, request_size: usize, response_size: usize) -> Self {
+        Self {
+            request_size,
+            response_size,
+            ..self
+        }
+    }
+
+    pub fn outstanding_rpcs(self, outstanding_rpcs: usize) -> Self {
+        Self {
+            outstanding_rpcs,
+            ..self
+        }
+    }
+
+    pub fn target_qps(self, target_qps: f64) -> Self {
+        Self {
+            target_qps: Some(target_qps),
+            ..self
+        }
+    }
+
+    pub fn warmup(self, warmup: Duration) -> Self {
+        Self { warmup, ..self }
+    }
+
+    pub fn duration(self, duration: Duration) -> Self {
+        Self { duration, ..self }
+    }
+}
+
+/// A streaming call on which each operation is a request followed by a
+/// response.
+pub struct PingPong {
+    requests: mpsc::Sender<()>,
+    responses: Pin<Box<dyn Stream<Item = Result<(), Status>> + Send>>,
+}
+
+impl PingPong {
+    /// Sends a request and waits for its response.
+    pub async fn ping(&mut self) -> Result<(), Status> {
+        if self.requests.send(()).await.is_err() {
+            return Err(Status::unavailable("the call ended"));
+        }
+        match self.responses.next().await {
+            Some(result) => result,
+            None => Err(Status::unavailable("the call ended")),
+        }
+    }
+}
+
+/// A client of the benchmark service, implemented by the generated clients.
+#[async_trait]
+pub trait BenchmarkClient: Clone + Send + Sync + 'static {
+    /// Performs a unary call.
+    async fn unary_call(&self, request_size: usize, response_size: usize) -> Result<(), Status>;
+
+    /// Starts a streaming call on which each ping sends a request and waits
+    /// for its response.
+    async fn streaming_call(
+        &self,
+        request_size: usize,
+        response_size: usize,
+    ) -> Result<PingPong, Status>;
+}
+
+#[async_trait]
+impl BenchmarkClient for proto::benchmark_service_client::BenchmarkServiceClient {
+    async fn unary_call(&self, request_size: usize, response_size: usize) -> Result<(), Status> {
+        let request = proto::SimpleRequest {
+            response_size: response_size as i32,
+            payload: Some(proto::Payload {
+                body: vec![0; request_size],
+            }),
+        };
+        self.unary_call(request).await.map(|_| ())
+    }
+
+    async fn streaming_call(
+        &self,
+        request_size: usize,
+        response_size: usize,
+    ) -> Result<PingPong, Status> {
+        let (tx, rx) = mpsc::channel(1);
+        let requests = ReceiverStream::new(rx).map(move |()| proto::SimpleRequest {
+            response_size: response_size as i32,
+            payload: Some(proto::Payload {
+                body: vec![0; request_size],
+            }),
+        });
+        let responses = self.streaming_call(requests).await?.into_inner();
+        Ok(PingPong {
+            requests: tx,
+            responses: Box::pin(responses.map(|res| res.map(|_| ()))),
+        })
+    }
+}
+
+#[async_trait]
+impl<T> BenchmarkClient for classic::benchmark_service_client::BenchmarkServiceClient<T>
+where
+    T: GrpcService<Body> + Clone + Send + Sync + 'static,
+    T::Error: Into<BoxError>,
+    T::Future: Send,
+    T::ResponseBody: HttpBody<Data = Bytes> + Send + 'static,
+    <T::ResponseBody as HttpBody>::Error: Into<BoxError> + Send,
+{
+    async fn unary_call(&self, request_size: usize, response_size: usize) -> Result<(), Status> {
+        let request = classic::SimpleRequest {
+            response_size: response_size as i32,
+            payload: Some(classic::Payload {
+                body: vec![0; request_size],
+            }),
+        };
+        let mut client = self.clone();
+        Self::unary_call(&mut client, request).await.map(|_| ())
+    }
+
+    async fn streaming_call(
+        &self,
+        request_size: usize,
+        response_size: usize,
+    ) -> Result<PingPong, Status> {
+        let (tx, rx) = mpsc::channel(1);
+        let requests = ReceiverStream::new(rx).map(move |()| classic::SimpleRequest {
+            response_size: response_size as i32,
+            payload: Some(classic::Payload {
+                body: vec![0; request_size],
+            }),
+        });
+        let mut client = self.clone();
+        let responses = Self::streaming_call(&mut client, requests)
+            .await?
+            .into_inner();
+        Ok(PingPong {
+            requests: tx,
+            responses: Box::pin(responses.map(|res| res.map(|_| ()))),
+        })
+    }
+}
+
+/// The measurements of a benchmark.
+#[derive(Debug, Clone)]
+pub struct BenchmarkResult {
+    // Sorted in increasing order.
+    latencies: Vec<Duration>,
+    errors: u64,
+    duration: Duration,
+}
+
+impl BenchmarkResult {
+    /// Returns the number of successful operations measured.
+    pub fn operations(&self) -> u64 {
+        self.latencies.len() as u64
+    }
+
+    /// Returns the number of failed operations measured.
+    pub fn errors(&self) -> u64 {
+        self.errors
+    }
+
+    /// Returns the rate of successful operations.
+    pub fn qps(&self) -> f64 {
+        self.operations() as f64 / self.duration.as_secs_f64()
+    }
+
+    /// Returns the latency below which the given percentage (0 to 100) of
+    /// successful operations completed, or None if there were none.
+    pub fn percentile(&self, percent: f64) -> Option<Duration> {
+        let last = self.latencies.len().checked_sub(1)?;
+        let rank = (percent.clamp(0.0, 100.0) / 100.0 * last as f64).round() as usize;
+        Some(self.latencies[rank])
+    }
+}
+
+impl Display for BenchmarkResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ops ({} errors) in {:?}: {:.1} qps",
+            self.operations(),
+            self.errors,
+            self.duration,
+            self.qps()
+        )?;
+        for percent in [50.0, 90.0, 99.0, 99.9] {
+            if let Some(latency) = self.percentile(percent) {
+                write!(f, ", p{percent}: {latency:?}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Runs the workload described by config using client, and returns its
+/// measurements.
+pub async fn run(client: impl BenchmarkClient, config: &ClientConfig) -> BenchmarkResult {
+    let outstanding = config.outstanding_rpcs.max(1);
+    // Each outstanding RPC issues an equal share of the target rate.
+    let interval = config
+        .target_qps
+        .map(|qps| Duration::from_secs_f64(outstanding as f64 / qps));
+    let measure_from = Instant::now() + config.warmup;
+    let end = measure_from + config.duration;
+
+    let mut workers = JoinSet::new();
+    for _ in 0..outstanding {
+        workers.spawn(worker(
+            client.clone(),
+            config.clone(),
+            interval,
+            measure_from,
+            end,
+        ));
+    }
+    let mut latencies = Vec::new();
+    let mut errors = 0;
+    while let Some(result) = workers.join_next().await {
+        let (l, e) = result.expect("benchmark worker panicked");
+        latencies.extend(l);
+        errors += e;
+    }
+    latencies.sort();
+    BenchmarkResult {
+        latencies,
+        errors,
+        duration: config.duration,
+    }
+}
+
+// Performs operations until end, returning the latencies of the successful
+// ones and the number of failed ones started after measure_from.
+async fn worker(
+    client: impl BenchmarkClient,
+    config: ClientConfig,
+    interval: Option<Duration>,
+    measure_from: Instant,
+    end: Instant,
+) -> (Vec<Duration>, u64) {
+    let mut latencies = Vec::new();
+    let mut errors = 0;
+    let mut ping_pong = None;
+    let mut next = Instant::now();
+    loop {
+        if let Some(interval) = interval {
+            tokio::time::sleep_until(next.into()).await;
+            next += interval;
+        }
+        let start = Instant::now();
+        if start >= end {
+            break;
+        }
+        let result = match config.rpc_type {
+            RpcType::Unary => {
+                client
+                    .unary_call(config.request_size, config.response_size)
+                    .await
+            }
+            RpcType::Streaming => {
+                let call = match ping_pong.take() {
+                    Some(call) => Ok(call),
+                    None => {
+                        client
+                            .streaming_call(config.request_size, config.response_size)
+                            .await
+                    }
+                };
+                match call {
+                    Ok(mut call) => {
+                        let result = call.ping().await;
+                        // After a failure, a new call is started for the
+                        // next operation.
+                        if result.is_ok() {
+                            ping_pong = Some(call);
+                        }
+                        result
+                    }
+                    Err(status) => Err(status),
+                }
+            }
+        };
+        if start < measure_from {
+            continue;
+        }
+        match result {
+            Ok(()) => latencies.push(start.elapsed()),
+            Err(_) => errors += 1,
+        }
+    }
+    (latencies, errors)
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{run, BenchmarkResult, ClientConfig, RpcType};
+    use crate::benchmark::{classic, proto, BenchmarkServer};
+    use crate::client::{Channel, ChannelOptions};
+    use crate::inmemory;
+    use crate::server::{Server, TonicAdapter};
+
+    fn config(rpc_type: RpcType) -> ClientConfig {
+        ClientConfig::default()
+            .rpc_type(rpc_type)
+            .payload_sizes(16, 1024)
+            .outstanding_rpcs(2)
+            .warmup(Duration::from_millis(10))
+            .duration(Duration::from_millis(100))
+    }
+
+    #[tokio::test]
+    async fn benchmarks_new_stack() {
+        inmemory::reg();
+        let lis = inmemory::Listener::new();
+        let mut srv = Server::new();
+        srv.set_handler(proto::benchmark_service_server::BenchmarkServiceServer::new(
+            BenchmarkServer::default(),
+        ));
+        let lis_clone = lis.clone();
+        tokio::task::spawn(async move {
+            srv.serve(&lis_clone).await;
+        });
+        let chan = Channel::new(lis.target().as_str(), None, ChannelOptions::default());
+        let client = proto::benchmark_service_client::BenchmarkServiceClient::new(chan);
+
+        for rpc_type in [RpcType::Unary, RpcType::Streaming] {
+            let result = run(client.clone(), &config(rpc_type)).await;
+            assert!(result.operations() > 0, "{rpc_type:?}: {result}");
+            assert_eq!(result.errors(), 0, "{rpc_type:?}: {result}");
+        }
+    }
+
+    #[tokio::test]
+    async fn benchmarks_tonic_generated_code() {
+        inmemory::reg();
+        let lis = inmemory::Listener::new();
+        let mut srv = Server::new();
+        srv.set_handler(TonicAdapter::new(
+            classic::benchmark_service_server::BenchmarkServiceServer::new(
+                BenchmarkServer::default(),
+            ),
+        ));
+        let lis_clone = lis.clone();
+        tokio::task::spawn(async move {
+            srv.serve(&lis_clone).await;
+        });
+        let chan = Channel::new(lis.target().as_str(), None, ChannelOptions::default());
+        let client = classic::benchmark_service_client::BenchmarkServiceClient::new(chan);
+
+        let result = run(client, &config(RpcType::Streaming).target_qps(100.0)).await;
+        assert_eq!(result.errors(), 0, "{result}");
+        // 100 qps for 100ms.
+        assert!((5..=15).contains(&result.operations()), "{result}");
+    }
+
+    #[test]
+    fn percentiles() {
+        let result = BenchmarkResult {
+            latencies: (1..=100).map(Duration::from_millis).collect(),
+            errors: 0,
+            duration: Duration::from_secs(2),
+        };
+        assert_eq!(result.qps(), 50.0);
+        assert_eq!(result.percentile(0.0), Some(Duration::from_millis(1)));
+        assert_eq!(result.percentile(50.0), Some(Duration::from_millis(51)));
+        assert_eq!(result.percentile(100.0), Some(Duration::from_millis(100)));
+
+        let empty = BenchmarkResult {
+            latencies: vec![],
+            errors: 1,
+            duration: Duration::from_secs(1),
+        };
+        assert_eq!(empty.percentile(50.0), None);
+    }
+}
@@ -0,0 +1,58 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! A benchmark service, mirroring gRPC's `grpc.testing.BenchmarkService`, and
+//! a client which drives it to measure the throughput and latency of the
+//! whole stack.
+//!
+//! [`BenchmarkServer`] implements the service for both the generated code of
+//! this crate ([`proto`]) and the code generated by tonic-build
+//! ([`classic`]).  [`run`] drives any [`BenchmarkClient`]; it is implemented
+//! by both generated clients, so the same workload can be run over a
+//! [`Channel`](crate::client::Channel) or over tonic's own transport and the
+//! results compared.
+
+mod client;
+mod server;
+
+pub use client::{run, BenchmarkClient, BenchmarkResult, ClientConfig, PingPong, RpcType};
+pub use server::BenchmarkServer;
+
+/// The benchmark messages and service, generated for this crate.
+#[allow(missing_docs)]
+pub mod proto {
+    include!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/generated/grpc_testing_grpc.rs"
+    ));
+}
+
+/// The benchmark messages and service, generated by tonic-build.
+#[allow(missing_docs)]
+pub mod classic {
+    include!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/generated/grpc_testing.rs"
+    ));
+}
@@ -0,0 +1,196 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+use std::pin::Pin;
+
+use tokio_stream::{Stream, StreamExt};
+use tonic::{async_trait, Request, Response, Status, Streaming};
+
+use super::{classic, proto};
+use crate::codegen::BoxStream;
+
+/// Serves the benchmark service.  Every response carries a payload of the
+/// size requested by the client, and the streaming methods which are
+/// unbounded in gRPC's benchmarks respond until the client cancels the call.
+#[derive(Debug, Default, Clone)]
+pub struct BenchmarkServer {}
+
+fn body(response_size: i32) -> Vec<u8> {
+    vec![0; response_size.max(0) as usize]
+}
+
+fn response(response_size: i32) -> proto::SimpleResponse {
+    proto::SimpleResponse {
+        payload: Some(proto::Payload {
+            body: body(response_size),
+        }),
+    }
+}
+
+fn classic_response(response_size: i32) -> classic::SimpleResponse {
+    classic::SimpleResponse {
+        payload: Some(classic::Payload {
+            body: body(response_size),
+        }),
+    }
+}
+
+// Repeats response until the stream is dropped, while discarding the
+// messages of requests.
+fn repeat<T, R>(requests: R, response: T) -> impl Stream<Item = Result<T, Status>>
+where
+    T: Clone + Send + 'static,
+    R: Stream + Send + 'static,
+{
+    let responses = tokio_stream::iter(std::iter::repeat(response).map(Ok));
+    requests
+        .filter_map(|_| None::<Result<T, Status>>)
+        .merge(responses)
+}
+
+#[async_trait]
+impl proto::benchmark_service_server::BenchmarkService for BenchmarkServer {
+    async fn unary_call(
+        &self,
+        request: Request<proto::SimpleRequest>,
+    ) -> Result<Response<proto::SimpleResponse>, Status> {
+        Ok(Response::new(response(request.get_ref().response_size)))
+    }
+
+    async fn streaming_call(
+        &self,
+        request: Request<BoxStream<proto::SimpleRequest>>,
+    ) -> Result<Response<BoxStream<proto::SimpleResponse>>, Status> {
+        let responses = request
+            .into_inner()
+            .map(|req| req.map(|req| response(req.response_size)));
+        Ok(Response::new(Box::pin(responses)))
+    }
+
+    async fn streaming_from_client(
+        &self,
+        request: Request<BoxStream<proto::SimpleRequest>>,
+    ) -> Result<Response<proto::SimpleResponse>, Status> {
+        let mut requests = request.into_inner();
+        let mut response_size = 0;
+        while let Some(req) = requests.next().await {
+            response_size = req?.response_size;
+        }
+        Ok(Response::new(response(response_size)))
+    }
+
+    async fn streaming_from_server(
+        &self,
+        request: Request<proto::SimpleRequest>,
+    ) -> Result<Response<BoxStream<proto::SimpleResponse>>, Status> {
+        let response = response(request.get_ref().response_size);
+        Ok(Response::new(Box::pin(repeat(
+            tokio_stream::empty::<()>(),
+            response,
+        ))))
+    }
+
+    async fn streaming_both_ways(
+        &self,
+        request: Request<BoxStream<proto::SimpleRequest>>,
+    ) -> Result<Response<BoxStream<proto::SimpleResponse>>, Status> {
+        let mut requests = request.into_inner();
+        let response_size = match requests.next().await {
+            Some(req) => req?.response_size,
+            None => return Err(Status::invalid_argument("no request received")),
+        };
+        Ok(Response::new(Box::pin(repeat(
+            requests,
+            response(response_size),
+        ))))
+    }
+}
+
+type ClassicStream = Pin<Box<dyn Stream<Item = Result<classic::SimpleResponse, Status>> + Send>>;
+
+#[async_trait]
+impl classic::benchmark_service_server::BenchmarkService for BenchmarkServer {
+    async fn unary_call(
+        &self,
+        request: Request<classic::SimpleRequest>,
+    ) -> Result<Response<classic::SimpleResponse>, Status> {
+        Ok(Response::new(classic_response(
+            request.get_ref().response_size,
+        )))
+    }
+
+    type StreamingCallStream = ClassicStream;
+
+    async fn streaming_call(
+        &self,
+        request: Request<Streaming<classic::SimpleRequest>>,
+    ) -> Result<Response<ClassicStream>, Status> {
+        let responses = request
+            .into_inner()
+            .map(|req| req.map(|req| classic_response(req.response_size)));
+        Ok(Response::new(Box::pin(responses)))
+    }
+
+    async fn streaming_from_client(
+        &self,
+        request: Request<Streaming<classic::SimpleRequest>>,
+    ) -> Result<Response<classic::SimpleResponse>, Status> {
+        let mut requests = request.into_inner();
+        let mut response_size = 0;
+        while let Some(req) = requests.next().await {
+            response_size = req?.response_size;
+        }
+        Ok(Response::new(classic_response(response_size)))
+    }
+
+    type StreamingFromServerStream = ClassicStream;
+
+    async fn streaming_from_server(
+        &self,
+        request: Request<classic::SimpleRequest>,
+    ) -> Result<Response<ClassicStream>, Status> {
+        let response = classic_response(request.get_ref().response_size);
+        Ok(Response::new(Box::pin(repeat(
+            tokio_stream::empty::<()>(),
+            response,
+        ))))
+    }
+
+    type StreamingBothWaysStream = ClassicStream;
+
+    async fn streaming_both_ways(
+        &self,
+        request: Request<Streaming<classic::SimpleRequest>>,
+    ) -> Result<Response<ClassicStream>, Status> {
+        let mut requests = request.into_inner();
+        let response_size = match requests.next().await {
+            Some(req) => req?.response_size,
+            None => return Err(Status::invalid_argument("no request received")),
+        };
+        Ok(Response::new(Box::pin(repeat(
+            requests,
+            classic_response(response_size),
+        ))))
+    }
+}
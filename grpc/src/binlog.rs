@@ -0,0 +1,693 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! Binary logging of RPCs.
+//!
+//! A [`BinaryLogger`] configured on a channel or server records the headers,
+//! messages and trailers of the RPCs to matching methods as [`LogEntry`]s,
+//! which it writes to a [`BinaryLogSink`].  Methods are matched by a filter
+//! in the syntax of the `GRPC_BINARY_LOG_FILTER` environment variable:
+//!
+//! ```text
+//! *{h:256;m:128},pkg.Service/*,-pkg.Service/Secret,pkg.Other/Get{m}
+//! ```
+//!
+//! Each comma-separated entry names every method (`*`), every method of a
+//! service (`pkg.Service/*`) or a single method (`pkg.Service/Get`).  An
+//! entry for a method takes precedence over one for its service, which takes
+//! precedence over `*`.  Methods prefixed with `-` are not logged.  The
+//! options in braces limit the bytes of metadata (`h`) and of each message
+//! (`m`) that are logged; `{h}` and `{m}` log only metadata or only
+//! messages, without limit.  Without options, everything is logged.
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    io::Write,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::SystemTime,
+};
+
+use bytes::Bytes;
+use tokio_stream::Stream;
+use tonic::{metadata::MetadataMap, Code, Status};
+
+use crate::service::{Message, Request, Response};
+
+/// The environment variable holding the filter used by
+/// [`BinaryLogger::from_env`].
+pub const FILTER_ENV_VAR: &str = "GRPC_BINARY_LOG_FILTER";
+
+/// Receives the entries logged by a [`BinaryLogger`].  Sinks are called
+/// synchronously on the RPC's path, so they must not block for long.
+pub trait BinaryLogSink: Send + Sync {
+    fn write(&self, entry: LogEntry);
+}
+
+/// A sink writing each entry as a line of text.
+pub struct TextSink<W> {
+    writer: Mutex<W>,
+}
+
+impl<W: Write + Send> TextSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+        }
+    }
+}
+
+impl<W: Write + Send> BinaryLogSink for TextSink<W> {
+    fn write(&self, entry: LogEntry) {
+        // TODO: report write errors.
+        let _ = writeln!(self.writer.lock().unwrap(), "{entry}");
+    }
+}
+
+/// Whether an entry was logged by a client or a server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Client,
+    Server,
+}
+
+/// A record of one step of an RPC.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct LogEntry {
+    pub timestamp: SystemTime,
+    /// Identifies the RPC among those logged by the same logger.
+    pub call_id: u64,
+    /// The position of the entry among those of the RPC, starting at 1.
+    pub sequence_id: u64,
+    pub logger: Side,
+    /// The full method name, e.g. "/pkg.Service/Method".
+    pub method: String,
+    pub event: LogEvent,
+    /// Whether metadata or message data was omitted due to the limits of the
+    /// method's filter.
+    pub payload_truncated: bool,
+}
+
+/// The step of an RPC recorded by a [`LogEntry`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum LogEvent {
+    /// The headers sent by the client.
+    ClientHeader { metadata: Vec<(String, Bytes)> },
+    /// The headers sent by the server.
+    ServerHeader { metadata: Vec<(String, Bytes)> },
+    /// A message sent by the client.  Only the contents of serialized
+    /// messages are known, so length is None for other messages.
+    ClientMessage { length: Option<usize>, data: Bytes },
+    /// A message sent by the server.
+    ServerMessage { length: Option<usize>, data: Bytes },
+    /// The client finished sending messages.
+    ClientHalfClose,
+    /// The status and trailers sent by the server.
+    ServerTrailer {
+        code: Code,
+        message: String,
+        metadata: Vec<(String, Bytes)>,
+    },
+    /// The RPC was abandoned before the server's trailers.
+    Cancel,
+}
+
+impl Display for LogEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let side = match self.logger {
+            Side::Client => "client",
+            Side::Server => "server",
+        };
+        write!(
+            f,
+            "[{side} call {} #{}] {} ",
+            self.call_id, self.sequence_id, self.method
+        )?;
+        let metadata = |f: &mut Formatter<'_>, metadata: &[(String, Bytes)]| {
+            for (key, value) in metadata {
+                write!(f, " {key}={}", value.escape_ascii())?;
+            }
+            Ok(())
+        };
+        match &self.event {
+            LogEvent::ClientHeader { metadata: md } => {
+                write!(f, "client header:")?;
+                metadata(f, md)?;
+            }
+            LogEvent::ServerHeader { metadata: md } => {
+                write!(f, "server header:")?;
+                metadata(f, md)?;
+            }
+            LogEvent::ClientMessage { length, data } | LogEvent::ServerMessage { length, data } => {
+                let from = match self.event {
+                    LogEvent::ClientMessage { .. } => "client",
+                    _ => "server",
+                };
+                match length {
+                    Some(length) => write!(f, "{from} message ({length} bytes): ")?,
+                    None => write!(f, "{from} message: ")?,
+                }
+                write!(f, "{}", data.escape_ascii())?;
+            }
+            LogEvent::ClientHalfClose => write!(f, "client half close")?,
+            LogEvent::ServerTrailer {
+                code,
+                message,
+                metadata: md,
+            } => {
+                write!(f, "server trailer: {code:?} {message:?}")?;
+                metadata(f, md)?;
+            }
+            LogEvent::Cancel => write!(f, "cancel")?,
+        }
+        if self.payload_truncated {
+            write!(f, " (truncated)")?;
+        }
+        Ok(())
+    }
+}
+
+/// The bytes of metadata and of each message logged for a method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Limits {
+    header: usize,
+    message: usize,
+}
+
+const UNLIMITED: Limits = Limits {
+    header: usize::MAX,
+    message: usize::MAX,
+};
+
+/// Selects the methods logged, and how much of each RPC is logged.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct MethodFilter {
+    all: Option<Limits>,
+    services: HashMap<String, Limits>,
+    // None for excluded methods.
+    methods: HashMap<String, Option<Limits>>,
+}
+
+impl MethodFilter {
+    fn parse(filter: &str) -> Result<Self, String> {
+        let mut parsed = Self::default();
+        for entry in filter.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (pattern, limits) = match entry.split_once('{') {
+                Some((pattern, options)) => {
+                    let options = options
+                        .strip_suffix('}')
+                        .ok_or_else(|| format!("unterminated options in {entry:?}"))?;
+                    (pattern, Some(parse_limits(options)?))
+                }
+                None => (entry, None),
+            };
+            let duplicate = || format!("duplicate binary log filter entry {pattern:?}");
+            if let Some(method) = pattern.strip_prefix('-') {
+                if limits.is_some() || !is_method(method) {
+                    return Err(format!("invalid exclusion {entry:?}"));
+                }
+                if parsed.methods.insert(method.to_string(), None).is_some() {
+                    return Err(duplicate());
+                }
+                continue;
+            }
+            let limits = limits.unwrap_or(UNLIMITED);
+            if pattern == "*" {
+                if parsed.all.replace(limits).is_some() {
+                    return Err(duplicate());
+                }
+            } else if let Some(service) = pattern.strip_suffix("/*") {
+                if service.is_empty() || service.contains('/') {
+                    return Err(format!("invalid binary log filter entry {entry:?}"));
+                }
+                if parsed
+                    .services
+                    .insert(service.to_string(), limits)
+                    .is_some()
+                {
+                    return Err(duplicate());
+                }
+            } else if is_method(pattern) {
+                if parsed
+                    .methods
+                    .insert(pattern.to_string(), Some(limits))
+                    .is_some()
+                {
+                    return Err(duplicate());
+                }
+            } else {
+                return Err(format!("invalid binary log filter entry {entry:?}"));
+            }
+        }
+        Ok(parsed)
+    }
+
+    // Returns the limits of method, a path of the form "/service/method", or
+    // None if it is not logged.
+    fn limits(&self, method: &str) -> Option<Limits> {
+        let method = method.strip_prefix('/').unwrap_or(method);
+        if let Some(limits) = self.methods.get(method) {
+            return *limits;
+        }
+        let service = method.rsplit_once('/').map_or("", |(s, _)| s);
+        self.services.get(service).copied().or(self.all)
+    }
+}
+
+fn is_method(pattern: &str) -> bool {
+    pattern
+        .split_once('/')
+        .is_some_and(|(s, m)| !s.is_empty() && !m.is_empty() && m != "*" && !m.contains('/'))
+}
+
+// Parses the options of a filter entry, e.g. "h:256;m:128".
+fn parse_limits(options: &str) -> Result<Limits, String> {
+    let mut limits = Limits {
+        header: 0,
+        message: 0,
+    };
+    for option in options.split(';') {
+        let (name, limit) = match option.split_once(':') {
+            Some((name, limit)) => (
+                name,
+                limit
+                    .parse()
+                    .map_err(|e| format!("invalid limit in {option:?}: {e}"))?,
+            ),
+            None => (option, usize::MAX),
+        };
+        match name {
+            "h" => limits.header = limit,
+            "m" => limits.message = limit,
+            _ => return Err(format!("unknown binary log option {option:?}")),
+        }
+    }
+    Ok(limits)
+}
+
+/// Logs the RPCs to the methods matched by its filter.
+pub struct BinaryLogger {
+    filter: MethodFilter,
+    sink: Arc<dyn BinaryLogSink>,
+    next_call_id: AtomicU64,
+}
+
+impl BinaryLogger {
+    /// Creates a logger writing the RPCs matched by filter to sink.  Returns
+    /// an error if filter is invalid.
+    pub fn new(filter: &str, sink: Arc<dyn BinaryLogSink>) -> Result<Self, String> {
+        Ok(Self {
+            filter: MethodFilter::parse(filter)?,
+            sink,
+            next_call_id: AtomicU64::new(1),
+        })
+    }
+
+    /// Creates a logger using the filter in the `GRPC_BINARY_LOG_FILTER`
+    /// environment variable, or returns None if it is unset.
+    pub fn from_env(sink: Arc<dyn BinaryLogSink>) -> Option<Result<Self, String>> {
+        let filter = std::env::var(FILTER_ENV_VAR).ok()?;
+        Some(Self::new(&filter, sink))
+    }
+
+    /// Starts logging an RPC to method, or returns None if it is not logged.
+    pub(crate) fn start(self: &Arc<Self>, method: &str, side: Side) -> Option<Arc<CallLog>> {
+        let limits = self.filter.limits(method)?;
+        Some(Arc::new(CallLog {
+            logger: self.clone(),
+            limits,
+            call_id: self.next_call_id.fetch_add(1, Ordering::Relaxed),
+            sequence_id: AtomicU64::new(1),
+            side,
+            method: method.to_string(),
+            ended: AtomicBool::new(false),
+        }))
+    }
+}
+
+/// Logs the entries of one RPC.  Logs a cancellation if dropped before the
+/// server's trailers were logged.
+pub(crate) struct CallLog {
+    logger: Arc<BinaryLogger>,
+    limits: Limits,
+    call_id: u64,
+    sequence_id: AtomicU64,
+    side: Side,
+    method: String,
+    ended: AtomicBool,
+}
+
+impl CallLog {
+    fn log(&self, event: LogEvent, payload_truncated: bool) {
+        self.logger.sink.write(LogEntry {
+            timestamp: SystemTime::now(),
+            call_id: self.call_id,
+            sequence_id: self.sequence_id.fetch_add(1, Ordering::Relaxed),
+            logger: self.side,
+            method: self.method.clone(),
+            event,
+            payload_truncated,
+        });
+    }
+
+    // Returns the entries of metadata within the header limit, and whether
+    // any were omitted.
+    fn metadata(&self, metadata: &MetadataMap) -> (Vec<(String, Bytes)>, bool) {
+        let mut entries = vec![];
+        let mut size = 0usize;
+        let mut truncated = false;
+        for (key, value) in metadata.clone().into_headers().iter() {
+            size = size.saturating_add(key.as_str().len() + value.len());
+            if size > self.limits.header {
+                truncated = true;
+                break;
+            }
+            entries.push((
+                key.as_str().to_string(),
+                Bytes::copy_from_slice(value.as_bytes()),
+            ));
+        }
+        (entries, truncated)
+    }
+
+    fn message(&self, msg: &dyn Message, from: Side) {
+        let (length, data) = match (msg as &dyn std::any::Any).downcast_ref::<Bytes>() {
+            Some(bytes) => (Some(bytes.len()), bytes.clone()),
+            None => (None, Bytes::new()),
+        };
+        let truncated = data.len() > self.limits.message;
+        let data = data.slice(..data.len().min(self.limits.message));
+        let event = match from {
+            Side::Client => LogEvent::ClientMessage { length, data },
+            Side::Server => LogEvent::ServerMessage { length, data },
+        };
+        self.log(event, truncated);
+    }
+
+    fn trailer(&self, status: &Status) {
+        if self.ended.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let (metadata, truncated) = self.metadata(status.metadata());
+        self.log(
+            LogEvent::ServerTrailer {
+                code: status.code(),
+                message: status.message().to_string(),
+                metadata,
+            },
+            truncated,
+        );
+    }
+
+    /// Logs the headers and messages of request.
+    pub(crate) fn request(self: &Arc<Self>, request: Request) -> Request {
+        let (metadata, truncated) = self.metadata(request.metadata());
+        self.log(LogEvent::ClientHeader { metadata }, truncated);
+        let log = self.clone();
+        request.map(|inner| {
+            Box::pin(RequestLogStream {
+                inner,
+                log,
+                done: false,
+            }) as Pin<Box<dyn Stream<Item = Box<dyn Message>> + Send + Sync>>
+        })
+    }
+
+    /// Logs the headers, messages and trailers of response.
+    pub(crate) fn response(self: &Arc<Self>, response: Response) -> Response {
+        let (metadata, truncated) = self.metadata(response.metadata());
+        self.log(LogEvent::ServerHeader { metadata }, truncated);
+        let log = self.clone();
+        response.map(|inner| {
+            Box::pin(ResponseLogStream { inner, log })
+                as Pin<Box<dyn Stream<Item = Result<Box<dyn Message>, Status>> + Send>>
+        })
+    }
+}
+
+impl Drop for CallLog {
+    fn drop(&mut self) {
+        if !self.ended.load(Ordering::Acquire) {
+            self.log(LogEvent::Cancel, false);
+        }
+    }
+}
+
+pin_project_lite::pin_project! {
+    // Logs the messages of a request stream, and the half close when it
+    // ends.
+    struct RequestLogStream<S> {
+        #[pin]
+        inner: S,
+        log: Arc<CallLog>,
+        done: bool,
+    }
+}
+
+impl<S: Stream<Item = Box<dyn Message>>> Stream for RequestLogStream<S> {
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let item = this.inner.poll_next(cx);
+        match &item {
+            Poll::Ready(Some(msg)) => this.log.message(msg.as_ref(), Side::Client),
+            Poll::Ready(None) if !*this.done => {
+                *this.done = true;
+                this.log.log(LogEvent::ClientHalfClose, false);
+            }
+            _ => {}
+        }
+        item
+    }
+}
+
+pin_project_lite::pin_project! {
+    // Logs the messages of a response stream, and the trailers when it ends.
+    struct ResponseLogStream<S> {
+        #[pin]
+        inner: S,
+        log: Arc<CallLog>,
+    }
+}
+
+impl<S: Stream<Item = Result<Box<dyn Message>, Status>>> Stream for ResponseLogStream<S> {
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let item = this.inner.poll_next(cx);
+        match &item {
+            Poll::Ready(Some(Ok(msg))) => this.log.message(msg.as_ref(), Side::Server),
+            Poll::Ready(Some(Err(status))) => this.log.trailer(status),
+            Poll::Ready(None) => this.log.trailer(&Status::new(Code::Ok, "")),
+            Poll::Pending => {}
+        }
+        item
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        any::Any,
+        sync::{Arc, Mutex},
+    };
+
+    use bytes::Bytes;
+    use tokio_stream::StreamExt;
+    use tonic::async_trait;
+
+    use super::{
+        BinaryLogSink, BinaryLogger, Limits, LogEntry, LogEvent, MethodFilter, Side, TextSink,
+        UNLIMITED,
+    };
+    use crate::{
+        client::{Channel, ChannelOptions},
+        inmemory,
+        server::Server,
+        service::{Message, Request, Response, Service},
+    };
+
+    #[test]
+    fn filter_precedence() {
+        let filter =
+            MethodFilter::parse("*{h:10},pkg.Svc/*{m:5},-pkg.Svc/Secret,pkg.Svc/Get{h;m}").unwrap();
+        let limits = |header, message| Some(Limits { header, message });
+        assert_eq!(filter.limits("/other.Svc/Get"), limits(10, 0));
+        assert_eq!(filter.limits("/pkg.Svc/Put"), limits(0, 5));
+        assert_eq!(filter.limits("/pkg.Svc/Secret"), None);
+        assert_eq!(filter.limits("/pkg.Svc/Get"), Some(UNLIMITED));
+
+        let filter = MethodFilter::parse("pkg.Svc/Get").unwrap();
+        assert_eq!(filter.limits("/pkg.Svc/Get"), Some(UNLIMITED));
+        assert_eq!(filter.limits("/pkg.Svc/Put"), None);
+    }
+
+    #[test]
+    fn invalid_filters() {
+        for filter in [
+            "*,*",
+            "-*",
+            "-pkg.Svc/*",
+            "-pkg.Svc/Get{h}",
+            "pkg.Svc",
+            "/Get",
+            "*{h:ten}",
+            "*{x}",
+            "*{h",
+            "pkg.Svc/Get,pkg.Svc/Get",
+        ] {
+            assert!(MethodFilter::parse(filter).is_err(), "{filter}");
+        }
+    }
+
+    #[derive(Default)]
+    struct MemorySink {
+        entries: Mutex<Vec<LogEntry>>,
+    }
+
+    impl BinaryLogSink for MemorySink {
+        fn write(&self, entry: LogEntry) {
+            self.entries.lock().unwrap().push(entry);
+        }
+    }
+
+    impl MemorySink {
+        // Returns the entries logged by side, formatted without their call
+        // ids.
+        fn entries(&self, side: Side) -> Vec<String> {
+            self.entries
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|e| e.logger == side)
+                .map(|e| {
+                    let line = e.to_string();
+                    line[line.find(']').unwrap() + 2..].to_string()
+                })
+                .collect()
+        }
+    }
+
+    struct Echo {}
+
+    #[async_trait]
+    impl Service for Echo {
+        async fn call(&self, _: String, request: Request) -> Response {
+            Response::new(Box::pin(request.into_inner().map(Ok)))
+        }
+    }
+
+    #[tokio::test]
+    async fn logs_client_and_server_calls() {
+        inmemory::reg();
+        let lis = inmemory::Listener::new();
+        let sink = Arc::new(MemorySink::default());
+        let logger = Arc::new(BinaryLogger::new("svc/*{m:3},-svc/Quiet", sink.clone()).unwrap());
+        let mut srv = Server::new();
+        srv.set_handler(Echo {});
+        srv.set_binary_logger(logger.clone());
+        let serve = tokio::spawn({
+            let lis = lis.clone();
+            async move { srv.serve(&lis).await }
+        });
+
+        let chan = Channel::new(
+            lis.target().as_str(),
+            None,
+            ChannelOptions::default().binary_logger(logger),
+        );
+        for method in ["/svc/Echo", "/svc/Quiet"] {
+            let msg: Box<dyn Message> = Box::new(Bytes::from_static(b"hello"));
+            let mut req = Request::new(Box::pin(tokio_stream::once(msg)));
+            req.metadata_mut().insert("key", "value".parse().unwrap());
+            let mut res = chan.call(method.to_string(), req).await.into_inner();
+            let msg = res.next().await.unwrap().unwrap();
+            assert_eq!(
+                (msg.as_ref() as &dyn Any).downcast_ref::<Bytes>().unwrap(),
+                "hello"
+            );
+            assert!(res.next().await.is_none());
+        }
+
+        let mut expected = [
+            "/svc/Echo client header: (truncated)",
+            "/svc/Echo client message (5 bytes): hel (truncated)",
+            "/svc/Echo client half close",
+            "/svc/Echo server header:",
+            "/svc/Echo server message (5 bytes): hel (truncated)",
+            "/svc/Echo server trailer: Ok \"\"",
+        ];
+        expected.sort();
+        for side in [Side::Client, Side::Server] {
+            // The client's and the server's entries may interleave.
+            let mut entries = sink.entries(side);
+            assert_eq!(entries[0], "/svc/Echo client header: (truncated)");
+            assert_eq!(entries[5], "/svc/Echo server trailer: Ok \"\"");
+            entries.sort();
+            assert_eq!(entries, expected);
+        }
+        lis.close().await;
+        serve.await.unwrap();
+    }
+
+    #[test]
+    fn abandoned_calls_are_cancelled() {
+        let sink = Arc::new(MemorySink::default());
+        let logger = Arc::new(BinaryLogger::new("*", sink.clone()).unwrap());
+        let log = logger.start("/svc/Watch", Side::Client).unwrap();
+        let mut req = Request::new(Box::pin(tokio_stream::empty()));
+        req.metadata_mut().insert("key", "value".parse().unwrap());
+        drop(log.request(req));
+        drop(log);
+        assert_eq!(
+            sink.entries(Side::Client),
+            ["/svc/Watch client header: key=value", "/svc/Watch cancel"]
+        );
+    }
+
+    #[test]
+    fn text_sink_writes_lines() {
+        let sink = TextSink::new(Vec::new());
+        sink.write(LogEntry {
+            timestamp: std::time::SystemTime::now(),
+            call_id: 7,
+            sequence_id: 2,
+            logger: Side::Server,
+            method: "/svc/Get".to_string(),
+            event: LogEvent::ClientHalfClose,
+            payload_truncated: false,
+        });
+        let text = String::from_utf8(sink.writer.into_inner().unwrap()).unwrap();
+        assert_eq!(text, "[server call 7 #2] /svc/Get client half close\n");
+    }
+}
@@ -46,6 +46,16 @@ impl Deref for ByteStr {
     }
 }
 
+impl From<&str> for ByteStr {
+    #[inline]
+    fn from(src: &str) -> ByteStr {
+        ByteStr {
+            // Invariant: src is a str so contains valid UTF-8.
+            bytes: Bytes::copy_from_slice(src.as_bytes()),
+        }
+    }
+}
+
 impl From<String> for ByteStr {
     #[inline]
     fn from(src: String) -> ByteStr {
@@ -0,0 +1,559 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! Typed helpers for making calls on a channel without generated code.
+//!
+//! [`Channel::unary`] sends a single message and waits for the single
+//! response, converting the failures of the call into its status.  The
+//! streaming helpers return a [`RequestSink`] to send messages with and a
+//! [`ResponseStream`] of the messages received, which ends with an error if
+//! the call fails.  The messages are passed to the channel as they are, so
+//! their types must match those the server's handler expects.
+
+use std::{
+    any::Any,
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use tokio::sync::{mpsc, oneshot};
+use tokio_stream::{wrappers::ReceiverStream, Stream, StreamExt};
+use tonic::{metadata::MetadataMap, Status};
+
+use super::{channel::WaitForReady, Channel};
+use crate::rt::{BoxedTaskHandle, Sleep};
+use crate::service::{Message, Request, Response};
+
+/// The number of messages a [`RequestSink`] buffers before
+/// [`send`](RequestSink::send) waits for the call to consume them.
+const REQUEST_BUFFER_SIZE: usize = 16;
+
+/// Settings of a single call made with the typed helpers.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct CallOptions {
+    /// The time allowed for the call, after which it fails with
+    /// DEADLINE_EXCEEDED.  None uses the timeout of the method's service
+    /// config, if any.
+    pub timeout: Option<Duration>,
+    /// Whether the call waits for the channel to become ready instead of
+    /// failing fast.  None uses the method's service config.
+    pub wait_for_ready: Option<bool>,
+    /// Metadata sent with the call.
+    pub metadata: MetadataMap,
+}
+
+impl CallOptions {
+    pub fn timeout(self, timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    pub fn wait_for_ready(self, wait_for_ready: bool) -> Self {
+        Self {
+            wait_for_ready: Some(wait_for_ready),
+            ..self
+        }
+    }
+
+    pub fn metadata(self, metadata: MetadataMap) -> Self {
+        Self { metadata, ..self }
+    }
+
+    // Applies the options to request.
+    pub(crate) fn apply(self, request: &mut Request) {
+        // The timeout is sent as metadata, so it is set after the metadata is
+        // replaced.
+        *request.metadata_mut() = self.metadata;
+        if let Some(timeout) = self.timeout {
+            request.set_timeout(timeout);
+        }
+        if let Some(wait_for_ready) = self.wait_for_ready {
+            request
+                .extensions_mut()
+                .insert(WaitForReady(wait_for_ready));
+        }
+    }
+}
+
+impl Channel {
+    /// Performs a unary call of method (e.g. "/pkg.Service/Method"), sending
+    /// request and returning the single response message.
+    ///
+    /// Fails with the status of the call if it fails, with DEADLINE_EXCEEDED
+    /// if the timeout of options expires first, and with INTERNAL if the
+    /// server does not respond with exactly one message of type Res.
+    pub async fn unary<Req: Message, Res: Message>(
+        &self,
+        method: impl Into<String>,
+        request: Req,
+        options: CallOptions,
+    ) -> Result<Res, Status> {
+        let timeout = options.timeout;
+        let mut request = Request::new(Box::pin(tokio_stream::once(
+            Box::new(request) as Box<dyn Message>
+        )));
+        options.apply(&mut request);
+        let call = async {
+            let response = self.call(method.into(), request).await;
+            single(response.into_inner().map(|msg| downcast(msg?))).await
+        };
+        let Some(timeout) = timeout else {
+            return call.await;
+        };
+        let sleep = self.runtime().sleep(timeout);
+        tokio::select! {
+            result = call => result,
+            _ = sleep => Err(Status::deadline_exceeded("deadline exceeded waiting for the response")),
+        }
+    }
+
+    /// Starts a server streaming call of method, sending request.  The call
+    /// is cancelled if the returned stream is dropped before it ends.
+    pub fn server_streaming<Req: Message, Res: Message>(
+        &self,
+        method: impl Into<String>,
+        request: Req,
+        options: CallOptions,
+    ) -> ResponseStream<Res> {
+        let request = Request::new(Box::pin(tokio_stream::once(
+            Box::new(request) as Box<dyn Message>
+        )));
+        self.start(method.into(), request, options)
+    }
+
+    /// Starts a client streaming call of method.  Messages are sent with the
+    /// returned call, whose [`finish`](ClientStreamingCall::finish) returns
+    /// the single response message.
+    pub fn client_streaming<Req: Message, Res: Message>(
+        &self,
+        method: impl Into<String>,
+        options: CallOptions,
+    ) -> ClientStreamingCall<Req, Res> {
+        let (sink, response) = self.bidi_streaming(method, options);
+        ClientStreamingCall { sink, response }
+    }
+
+    /// Starts a bidirectional streaming call of method.  Dropping the sink
+    /// half-closes the call; dropping the stream before it ends cancels it.
+    pub fn bidi_streaming<Req: Message, Res: Message>(
+        &self,
+        method: impl Into<String>,
+        options: CallOptions,
+    ) -> (RequestSink<Req>, ResponseStream<Res>) {
+        let (tx, rx) = mpsc::channel(REQUEST_BUFFER_SIZE);
+        let request = Request::new(Box::pin(ReceiverStream::new(rx)));
+        let sink = RequestSink {
+            tx,
+            _marker: PhantomData,
+        };
+        (sink, self.start(method.into(), request, options))
+    }
+
+    // Performs the call on a task, so that it progresses while the caller is
+    // only sending messages.
+    fn start<Res: Message>(
+        &self,
+        method: String,
+        mut request: Request,
+        options: CallOptions,
+    ) -> ResponseStream<Res> {
+        let deadline = options.timeout.map(|timeout| self.runtime().sleep(timeout));
+        options.apply(&mut request);
+        let (tx, rx) = oneshot::channel();
+        let channel = self.clone();
+        let task = self.runtime().spawn(Box::pin(async move {
+            let _ = tx.send(channel.call(method, request).await);
+        }));
+        ResponseStream {
+            state: StreamState::Pending(rx),
+            metadata: MetadataMap::new(),
+            deadline,
+            task,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// Sends the messages of a streaming call.
+pub struct RequestSink<Req> {
+    tx: mpsc::Sender<Box<dyn Message>>,
+    _marker: PhantomData<fn(Req)>,
+}
+
+impl<Req: Message> RequestSink<Req> {
+    /// Sends msg, waiting while too many messages are buffered.  Fails once
+    /// the call has completed, whose status is reported by its response.
+    pub async fn send(&self, msg: Req) -> Result<(), Status> {
+        self.tx
+            .send(Box::new(msg))
+            .await
+            .map_err(|_| Status::cancelled("the call has completed"))
+    }
+
+    /// Half-closes the call, indicating that no more messages will be sent.
+    /// Equivalent to dropping the sink.
+    pub fn close(self) {}
+}
+
+/// A client streaming call started by [`Channel::client_streaming`].
+pub struct ClientStreamingCall<Req, Res> {
+    sink: RequestSink<Req>,
+    response: ResponseStream<Res>,
+}
+
+impl<Req: Message, Res: Message> ClientStreamingCall<Req, Res> {
+    /// Sends msg.  See [`RequestSink::send`].
+    pub async fn send(&self, msg: Req) -> Result<(), Status> {
+        self.sink.send(msg).await
+    }
+
+    /// Half-closes the call and returns the single response message.
+    pub async fn finish(self) -> Result<Res, Status> {
+        drop(self.sink);
+        single(self.response).await
+    }
+}
+
+// The message stream of a Response.
+type MessageStream = Pin<Box<dyn Stream<Item = Result<Box<dyn Message>, Status>> + Send>>;
+
+enum StreamState {
+    // Waiting for the response headers.
+    Pending(oneshot::Receiver<Response>),
+    Streaming(MessageStream),
+    Done,
+}
+
+/// The messages received by a streaming call.  The stream ends after the
+/// last message if the call succeeds, or with the call's status if it fails.
+/// Messages of a type other than Res fail the call with INTERNAL.
+pub struct ResponseStream<Res> {
+    state: StreamState,
+    metadata: MetadataMap,
+    // Fails the call with DEADLINE_EXCEEDED when its timeout expires.
+    deadline: Option<Pin<Box<dyn Sleep>>>,
+    // Performs the call until the response headers are received.
+    task: BoxedTaskHandle,
+    _marker: PhantomData<fn() -> Res>,
+}
+
+impl<Res> ResponseStream<Res> {
+    /// Waits for the response headers of the call and returns them.  Fails
+    /// with the status of the call if it fails without sending headers.
+    pub async fn metadata(&mut self) -> Result<&MetadataMap, Status> {
+        std::future::poll_fn(|cx| self.poll_headers(cx)).await?;
+        Ok(&self.metadata)
+    }
+
+    fn poll_headers(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Status>> {
+        if let Some(deadline) = &mut self.deadline {
+            if deadline.as_mut().poll(cx).is_ready() {
+                self.state = StreamState::Done;
+                self.deadline = None;
+                return Poll::Ready(Err(Status::deadline_exceeded(
+                    "deadline exceeded waiting for the response",
+                )));
+            }
+        }
+        let StreamState::Pending(rx) = &mut self.state else {
+            return Poll::Ready(Ok(()));
+        };
+        match Pin::new(rx).poll(cx) {
+            Poll::Ready(Ok(response)) => {
+                let (metadata, stream, _) = response.into_parts();
+                self.metadata = metadata;
+                self.state = StreamState::Streaming(stream);
+                Poll::Ready(Ok(()))
+            }
+            Poll::Ready(Err(_)) => {
+                self.state = StreamState::Done;
+                Poll::Ready(Err(Status::cancelled("the call was abandoned")))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<Res: Message> Stream for ResponseStream<Res> {
+    type Item = Result<Res, Status>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if matches!(this.state, StreamState::Done) {
+            return Poll::Ready(None);
+        }
+        if let Err(status) = std::task::ready!(this.poll_headers(cx)) {
+            return Poll::Ready(Some(Err(status)));
+        }
+        let StreamState::Streaming(stream) = &mut this.state else {
+            return Poll::Ready(None);
+        };
+        let item = match std::task::ready!(stream.as_mut().poll_next(cx)) {
+            Some(msg) => msg.and_then(downcast),
+            None => {
+                this.state = StreamState::Done;
+                return Poll::Ready(None);
+            }
+        };
+        if item.is_err() {
+            // Nothing follows the status of a failed call.
+            this.state = StreamState::Done;
+        }
+        Poll::Ready(Some(item))
+    }
+}
+
+impl<Res> Drop for ResponseStream<Res> {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+// Returns the single message of stream, which the call completes after.
+async fn single<Res>(
+    mut stream: impl Stream<Item = Result<Res, Status>> + Unpin,
+) -> Result<Res, Status> {
+    let msg = stream
+        .next()
+        .await
+        .ok_or_else(|| Status::internal("missing response message"))??;
+    // A failure after the message fails the call.
+    if let Some(next) = stream.next().await {
+        next?;
+        return Err(Status::internal("received more than one response message"));
+    }
+    Ok(msg)
+}
+
+// Converts a message to the type the caller expects.
+pub(crate) fn downcast<T: Message>(msg: Box<dyn Message>) -> Result<T, Status> {
+    (msg as Box<dyn Any>)
+        .downcast::<T>()
+        .map(|msg| *msg)
+        .map_err(|_| {
+            Status::internal(format!(
+                "response message is not a {}",
+                std::any::type_name::<T>()
+            ))
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use std::{any::Any, sync::Arc, time::Duration};
+
+    use tokio::task::JoinHandle;
+    use tokio_stream::StreamExt;
+    use tonic::{async_trait, metadata::MetadataMap, Code, Status};
+
+    use super::CallOptions;
+    use crate::client::{Channel, ChannelOptions};
+    use crate::inmemory;
+    use crate::server::Server;
+    use crate::service::{Message, Request, Response, Service};
+
+    // Responds to requests for "/svc/Echo" with the message of the request,
+    // and to "/svc/Meta" with the value of its "key" metadata.  Other methods
+    // respond as their name says.
+    struct Handler {}
+
+    #[async_trait]
+    impl Service for Handler {
+        async fn call(&self, method: String, request: Request) -> Response {
+            let key = request.metadata().get("key").cloned();
+            let mut stream = request.into_inner();
+            match method.as_str() {
+                // Echoes every message as it is received.
+                "/svc/Bidi" => return Response::new(Box::pin(stream.map(Ok))),
+                // Responds with the sum of the u32s received.
+                "/svc/Sum" => {
+                    let mut sum = 0;
+                    while let Some(msg) = stream.next().await {
+                        sum += *(msg as Box<dyn Any>).downcast::<u32>().unwrap();
+                    }
+                    return Response::new(Box::pin(tokio_stream::once(Ok(
+                        Box::new(sum) as Box<dyn Message>
+                    ))));
+                }
+                _ => {}
+            }
+            let msg = stream.next().await.unwrap();
+            let messages: Vec<Result<Box<dyn Message>, Status>> = match method.as_str() {
+                "/svc/Echo" => vec![Ok(msg)],
+                "/svc/Meta" => vec![Ok(Box::new(key.unwrap().to_str().unwrap().to_string()))],
+                "/svc/Count" => {
+                    let n = *(msg as Box<dyn Any>).downcast::<u32>().unwrap();
+                    (0..n)
+                        .map(|i| Ok(Box::new(i) as Box<dyn Message>))
+                        .collect()
+                }
+                "/svc/Twice" => vec![Ok(Box::new(1u32)), Ok(Box::new(2u32))],
+                "/svc/Fail" => vec![Ok(Box::new(1u32)), Err(Status::aborted("failed"))],
+                "/svc/Hang" => {
+                    return Response::new(Box::pin(tokio_stream::pending()));
+                }
+                _ => vec![],
+            };
+            Response::new(Box::pin(tokio_stream::iter(messages)))
+        }
+    }
+
+    // Serves Handler, returning a channel to it.
+    async fn serve() -> (
+        Channel,
+        Arc<Server>,
+        Arc<inmemory::Listener>,
+        JoinHandle<()>,
+    ) {
+        inmemory::reg();
+        let lis = inmemory::Listener::new();
+        let mut srv = Server::new();
+        srv.set_handler(Handler {});
+        let srv = Arc::new(srv);
+        let serve = tokio::spawn({
+            let srv = srv.clone();
+            let lis = lis.clone();
+            async move { srv.serve(&lis).await }
+        });
+        let chan = Channel::new(lis.target().as_str(), None, ChannelOptions::default());
+        (chan, srv, lis, serve)
+    }
+
+    #[test]
+    fn options_keep_timeout_with_metadata() {
+        let mut metadata = MetadataMap::new();
+        metadata.insert("key", "value".parse().unwrap());
+        let options = CallOptions::default()
+            .timeout(Duration::from_secs(1))
+            .metadata(metadata);
+        let mut request = Request::new(Box::pin(tokio_stream::empty()));
+        options.apply(&mut request);
+        assert_eq!(request.metadata().get("key").unwrap(), "value");
+        assert!(request.metadata().get("grpc-timeout").is_some());
+    }
+
+    #[tokio::test]
+    async fn unary_calls() {
+        let (chan, srv, lis, serve) = serve().await;
+        let options = CallOptions::default;
+
+        let res: String = chan
+            .unary("/svc/Echo", "hello".to_string(), options())
+            .await
+            .unwrap();
+        assert_eq!(res, "hello");
+
+        let mut metadata = MetadataMap::new();
+        metadata.insert("key", "value".parse().unwrap());
+        let res: String = chan
+            .unary("/svc/Meta", (), options().metadata(metadata))
+            .await
+            .unwrap();
+        assert_eq!(res, "value");
+
+        let status = chan
+            .unary::<_, u32>("/svc/Echo", "hello".to_string(), options())
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::Internal);
+        assert!(status.message().contains("u32"), "{status:?}");
+
+        for (method, code) in [
+            ("/svc/Twice", Code::Internal),
+            ("/svc/Fail", Code::Aborted),
+            ("/svc/Empty", Code::Internal),
+        ] {
+            let status = chan
+                .unary::<_, u32>(method, (), options())
+                .await
+                .unwrap_err();
+            assert_eq!(status.code(), code, "{method}: {status:?}");
+        }
+
+        let status = chan
+            .unary::<_, u32>(
+                "/svc/Hang",
+                (),
+                options().timeout(Duration::from_millis(10)),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::DeadlineExceeded);
+
+        srv.graceful_shutdown().await;
+        serve.await.unwrap();
+        lis.close().await;
+    }
+
+    #[tokio::test]
+    async fn streaming_calls() {
+        let (chan, srv, lis, serve) = serve().await;
+        let options = CallOptions::default;
+
+        let stream = chan.server_streaming::<_, u32>("/svc/Count", 3u32, options());
+        let got: Vec<_> = stream.map(Result::unwrap).collect().await;
+        assert_eq!(got, [0, 1, 2]);
+
+        let mut stream = chan.server_streaming::<_, u32>("/svc/Fail", (), options());
+        assert_eq!(stream.next().await.unwrap().unwrap(), 1);
+        let status = stream.next().await.unwrap().unwrap_err();
+        assert_eq!(status.code(), Code::Aborted);
+        assert!(stream.next().await.is_none());
+
+        let call = chan.client_streaming::<u32, u32>("/svc/Sum", options());
+        for i in 1..=20 {
+            call.send(i).await.unwrap();
+        }
+        assert_eq!(call.finish().await.unwrap(), 210);
+
+        // Messages are received while the call is in progress.
+        let (sink, mut stream) = chan.bidi_streaming::<String, String>("/svc/Bidi", options());
+        stream.metadata().await.unwrap();
+        for msg in ["a", "b"] {
+            sink.send(msg.to_string()).await.unwrap();
+            assert_eq!(stream.next().await.unwrap().unwrap(), msg);
+        }
+        sink.close();
+        assert!(stream.next().await.is_none());
+
+        let mut stream = chan.server_streaming::<_, u32>(
+            "/svc/Hang",
+            (),
+            options().timeout(Duration::from_millis(10)),
+        );
+        let status = stream.next().await.unwrap().unwrap_err();
+        assert_eq!(status.code(), Code::DeadlineExceeded);
+        assert!(stream.next().await.is_none());
+
+        srv.graceful_shutdown().await;
+        serve.await.unwrap();
+        lis.close().await;
+    }
+}
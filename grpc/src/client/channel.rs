@@ -30,31 +30,65 @@ use std::{
     fmt::Display,
     mem,
     ops::Add,
+    panic::{catch_unwind, AssertUnwindSafe},
     str::FromStr,
-    sync::{Arc, Mutex, Weak},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, Weak,
+    },
     time::{Duration, Instant},
     vec,
 };
 
-use tokio::sync::{mpsc, oneshot, watch, Notify};
+use tokio::sync::{mpsc, oneshot, watch};
 
 use serde_json::json;
-use tonic::async_trait;
+use tonic::metadata::{KeyAndValueRef, MetadataMap};
+use tonic::{async_trait, Code, Status};
 use url::Url; // NOTE: http::Uri requires non-empty authority portion of URI
 
 use crate::attributes::Attributes;
+#[cfg(feature = "zstd")]
+use crate::compression::{
+    zstd_dictionary::DictionaryRegistry, CompressionPolicy, CompressionStats, MessageCompression,
+};
+use crate::http2::Http2Options;
+use crate::leak_detector::LeakTracker;
 use crate::rt;
-use crate::service::{Request, Response, Service};
+use crate::service::{details, status_response, Request, Response, Service};
+use crate::trace_context::TracePropagation;
+use crate::{
+    binlog::{BinaryLogger, Side},
+    credentials::Credentials,
+    rt::default_runtime,
+    stats::{RpcInfo, RpcStats, StatsHandler},
+};
 use crate::{client::ConnectivityState, rt::Runtime};
-use crate::{credentials::Credentials, rt::default_runtime};
 
-use super::service_config::ServiceConfig;
-use super::transport::{TransportRegistry, GLOBAL_TRANSPORT_REGISTRY};
+use super::deadline::{self, CallPhase, CallPhases, DeadlineStats, DeadlineStatsRecorder};
+use super::debug_state::{ChannelDebugState, SubchannelDebugState};
+use super::error::{ChannelError, ConnectError, ConnectErrorKind, ResolveError, ResolveErrorKind};
+use super::fault_injection::FaultInjection;
+use super::labels::{SubchannelStats, SubchannelStatsRecorder};
+use super::priority::{self, CallLimits, CallStats, Priority, PriorityLimiter};
+use super::request_hash::RequestHashPolicy;
+use super::reresolution::{ResolutionThrottle, Throttled};
+use super::resolution_cache::ResolutionCache;
+use super::retry::{self, PolicyRetries, ReplayableRequest, Unprocessed};
+use super::retry_throttling;
+use super::service_config::{LbPolicySelection, ServiceConfig, ServiceConfigSelector};
+use super::session_affinity::SessionCookieConfig;
+use super::transport::{
+    RegisteredTransport, TransportOptions, TransportRegistry, GLOBAL_TRANSPORT_REGISTRY,
+};
+use super::watchdog::{ConnectingWatchdog, ConnectingWatchdogMonitor, StuckConnecting};
+use super::work_queue::{panic_message, WorkItemKind, WorkQueueMonitor};
 use super::{
     load_balancing::{
-        self, pick_first, ExternalSubchannel, LbPolicy, LbPolicyBuilder, LbPolicyOptions,
-        LbPolicyRegistry, LbState, ParsedJsonLbConfig, PickResult, Picker, Subchannel,
-        SubchannelState, WorkScheduler, GLOBAL_LB_REGISTRY,
+        self, pick_first, CompletionRecorder, ExternalSubchannel, Failing, LbPolicy,
+        LbPolicyBuilder, LbPolicyOptions, LbPolicyRegistry, LbState, ParsedJsonLbConfig,
+        PickResult, Picker, QueuingPicker, ScheduledWork, Subchannel, SubchannelState,
+        WorkScheduler, GLOBAL_LB_REGISTRY,
     },
     subchannel::{
         InternalSubchannel, InternalSubchannelPool, NopBackoff, SubchannelKey,
@@ -63,25 +97,107 @@ use super::{
 };
 use super::{
     name_resolution::{
-        self, global_registry, Address, ResolverBuilder, ResolverOptions, ResolverUpdate,
+        self, backoff::DEFAULT_EXPONENTIAL_CONFIG, global_registry, Address, ResolverBuilder,
+        ResolverOptions, ResolverRegistry, ResolverUpdate,
     },
     subchannel,
 };
 
 #[non_exhaustive]
 pub struct ChannelOptions {
-    pub transport_options: Attributes, // ?
+    /// Settings read by the channel's transports.
+    pub transport_options: Attributes,
+    /// Settings read by the channel's name resolver.
+    pub resolver_args: Attributes,
     pub override_authority: Option<String>,
     pub connection_backoff: Option<TODO>,
+    /// The service config used when the name resolver does not provide one,
+    /// in JSON.  Configs written in other formats can be converted using a
+    /// [`ServiceConfigFormat`](super::service_config::ServiceConfigFormat).
+    /// Use [`Channel::try_new`] to reject invalid configs.
     pub default_service_config: Option<String>,
     pub disable_proxy: bool,
     pub disable_service_config_lookup: bool,
+    /// Rejects resolver updates without a service config instead of using
+    /// the default service config, which is ignored.
+    pub require_resolver_service_config: bool,
     pub disable_health_checks: bool,
-    pub max_retry_memory: u32, // ?
+    /// The maximum total size of the messages buffered by each call so that
+    /// it may be retried after they were sent.
+    pub max_retry_memory: u32,
     pub idle_timeout: Duration,
-    // TODO: pub transport_registry: Option<TransportRegistry>,
-    // TODO: pub name_resolver_registry: Option<ResolverRegistry>,
-    // TODO: pub lb_policy_registry: Option<LbPolicyRegistry>,
+    /// Limits applied to every update produced by the name resolver.  Updates
+    /// that exceed these limits are rejected and the channel keeps using the
+    /// last accepted update.
+    pub resolver_update_limits: ResolverUpdateLimits,
+    /// Determines the hash of each request used by hash-based LB policies.
+    /// May be overridden per call through the request's extensions.
+    pub request_hash_policy: Option<RequestHashPolicy>,
+    /// If set, calls carrying this session cookie are routed to the address
+    /// it names by the `override_host` LB policy, and responses set it when
+    /// the address changes.
+    pub session_cookie: Option<SessionCookieConfig>,
+    /// Items on the channel's work queue (resolver and LB policy callbacks)
+    /// that take longer than this to execute are logged as slow.  None
+    /// disables the warning.
+    pub slow_work_item_threshold: Option<Duration>,
+    /// If set, an LB policy whose callback panicked is replaced by a new one,
+    /// built from the next resolver update, which the channel requests.
+    /// Either way, the channel fails calls with UNAVAILABLE until the LB
+    /// policy produces a new picker.
+    pub rebuild_lb_policy_on_panic: bool,
+    /// Limits the number of concurrent calls on the channel, taking each
+    /// call's priority into account.  None means unlimited.
+    pub call_limits: Option<CallLimits>,
+    /// The minimum interval between re-resolutions requested by the LB
+    /// policy.  Requests made sooner are delayed, and repeated requests while
+    /// delayed are coalesced.  Re-resolution is further delayed with
+    /// exponential backoff while resolver updates are rejected.  Explicit
+    /// requests through Channel::reresolve_now are not throttled.
+    pub min_reresolution_interval: Duration,
+    /// Decides which outgoing messages are compressed when an encoding is
+    /// negotiated.  By default every message is compressed.
+    #[cfg(feature = "zstd")]
+    pub compression_policy: CompressionPolicy,
+    /// The zstd dictionaries the channel compresses messages with, per method
+    /// or for all methods.  Their IDs are advertised to servers, and a
+    /// dictionary is only used for requests once the server has advertised
+    /// it on the same connection.
+    #[cfg(feature = "zstd")]
+    pub zstd_dictionaries: Option<Arc<DictionaryRegistry>>,
+    /// HTTP/2 flow control and frame settings of the channel's connections.
+    pub http2_options: Http2Options,
+    /// Reports subchannels which stay CONNECTING for much longer than the
+    /// connect timeout, and optionally resets them.  None disables the
+    /// watchdog.
+    pub connecting_watchdog: Option<ConnectingWatchdog>,
+    /// Notified of the events of every call attempt made on the channel, and
+    /// of the connection events of its subchannels.
+    pub stats_handlers: Vec<Arc<dyn StatsHandler>>,
+    /// Logs the calls to the methods matched by its filter.
+    pub binary_logger: Option<Arc<BinaryLogger>>,
+    /// The maximum size of the serialized details of the statuses of calls.
+    /// Larger details are dropped.
+    pub max_status_details_size: usize,
+    /// If set, the channel starts from the cached resolver update for its
+    /// target, and caches the updates it accepts.
+    pub resolution_cache: Option<Arc<ResolutionCache>>,
+    /// If set, faults are injected into the calls made on the channel.
+    pub fault_injection: Option<Arc<FaultInjection>>,
+    /// If set, the trace context of each call is added to its metadata.
+    pub trace_propagation: Option<TracePropagation>,
+    /// If set, the TCP addresses produced by the resolver are connected to
+    /// with the gRPC-Web transport, which speaks HTTP/1.1 instead of HTTP/2.
+    /// The transport must be registered with
+    /// [`grpc_web::reg`](crate::client::grpc_web::reg).
+    #[cfg(feature = "grpc-web")]
+    pub grpc_web: bool,
+    /// Transports consulted before the global registry.
+    pub(crate) transport_registry: Option<TransportRegistry>,
+    /// Name resolvers consulted before the global registry.
+    pub name_resolver_registry: Option<ResolverRegistry>,
+    /// LB policies consulted before the global registry.
+    pub lb_policy_registry: Option<LbPolicyRegistry>,
 
     // Typically we allow settings at the channel level that impact all RPCs,
     // but can also be set per-RPC.  E.g.s:
@@ -105,23 +221,62 @@ pub struct ChannelOptions {
 impl Default for ChannelOptions {
     fn default() -> Self {
         Self {
-            transport_options: Attributes {},
+            transport_options: Attributes::default(),
+            resolver_args: Attributes::default(),
             override_authority: None,
             connection_backoff: None,
             default_service_config: None,
             disable_proxy: false,
             disable_service_config_lookup: false,
+            require_resolver_service_config: false,
             disable_health_checks: false,
             max_retry_memory: 8 * 1024 * 1024, // 8MB -- ???
             idle_timeout: Duration::from_secs(30 * 60),
+            resolver_update_limits: ResolverUpdateLimits::default(),
+            request_hash_policy: None,
+            session_cookie: None,
+            slow_work_item_threshold: Some(Duration::from_millis(100)),
+            rebuild_lb_policy_on_panic: false,
+            call_limits: None,
+            min_reresolution_interval: Duration::from_secs(1),
+            #[cfg(feature = "zstd")]
+            compression_policy: CompressionPolicy::default(),
+            #[cfg(feature = "zstd")]
+            zstd_dictionaries: None,
+            http2_options: Http2Options::default(),
+            connecting_watchdog: Some(ConnectingWatchdog::default()),
+            stats_handlers: vec![],
+            binary_logger: None,
+            max_status_details_size: details::DEFAULT_MAX_STATUS_DETAILS_SIZE,
+            resolution_cache: None,
+            fault_injection: None,
+            trace_propagation: None,
+            #[cfg(feature = "grpc-web")]
+            grpc_web: false,
+            transport_registry: None,
+            name_resolver_registry: None,
+            lb_policy_registry: None,
             default_request_extensions: vec![],
         }
     }
 }
 
 impl ChannelOptions {
-    pub fn transport_options(self, transport_options: TODO) -> Self {
-        todo!(); // add to existing options.
+    /// Sets the attributes given to the channel's transports, which read the
+    /// settings they define, e.g. [`crate::inmemory::NETWORK`].
+    pub fn transport_options(self, transport_options: Attributes) -> Self {
+        Self {
+            transport_options,
+            ..self
+        }
+    }
+    /// Sets the attributes given to the channel's name resolver, which reads
+    /// the settings it defines.
+    pub fn resolver_args(self, resolver_args: Attributes) -> Self {
+        Self {
+            resolver_args,
+            ..self
+        }
     }
     pub fn override_authority(self, authority: String) -> Self {
         Self {
@@ -129,9 +284,245 @@ impl ChannelOptions {
             ..self
         }
     }
+    pub fn default_service_config(self, config: String) -> Self {
+        Self {
+            default_service_config: Some(config),
+            ..self
+        }
+    }
+    pub fn require_resolver_service_config(self, require: bool) -> Self {
+        Self {
+            require_resolver_service_config: require,
+            ..self
+        }
+    }
+    pub fn resolver_update_limits(self, limits: ResolverUpdateLimits) -> Self {
+        Self {
+            resolver_update_limits: limits,
+            ..self
+        }
+    }
+    pub fn request_hash_policy(self, policy: RequestHashPolicy) -> Self {
+        Self {
+            request_hash_policy: Some(policy),
+            ..self
+        }
+    }
+    pub fn session_cookie(self, config: SessionCookieConfig) -> Self {
+        Self {
+            session_cookie: Some(config),
+            ..self
+        }
+    }
+    pub fn slow_work_item_threshold(self, threshold: Option<Duration>) -> Self {
+        Self {
+            slow_work_item_threshold: threshold,
+            ..self
+        }
+    }
+    pub fn rebuild_lb_policy_on_panic(self, rebuild: bool) -> Self {
+        Self {
+            rebuild_lb_policy_on_panic: rebuild,
+            ..self
+        }
+    }
+    pub fn call_limits(self, limits: CallLimits) -> Self {
+        Self {
+            call_limits: Some(limits),
+            ..self
+        }
+    }
+    pub fn min_reresolution_interval(self, interval: Duration) -> Self {
+        Self {
+            min_reresolution_interval: interval,
+            ..self
+        }
+    }
+    #[cfg(feature = "zstd")]
+    pub fn compression_policy(self, policy: CompressionPolicy) -> Self {
+        Self {
+            compression_policy: policy,
+            ..self
+        }
+    }
+    #[cfg(feature = "zstd")]
+    pub fn zstd_dictionaries(self, dictionaries: Arc<DictionaryRegistry>) -> Self {
+        Self {
+            zstd_dictionaries: Some(dictionaries),
+            ..self
+        }
+    }
+    /// Adds a handler notified of the events of every call attempt.
+    pub fn stats_handler(mut self, handler: Arc<dyn StatsHandler>) -> Self {
+        self.stats_handlers.push(handler);
+        self
+    }
+    pub fn binary_logger(self, logger: Arc<BinaryLogger>) -> Self {
+        Self {
+            binary_logger: Some(logger),
+            ..self
+        }
+    }
+    pub fn max_status_details_size(self, size: usize) -> Self {
+        Self {
+            max_status_details_size: size,
+            ..self
+        }
+    }
+    pub fn resolution_cache(self, cache: Arc<ResolutionCache>) -> Self {
+        Self {
+            resolution_cache: Some(cache),
+            ..self
+        }
+    }
+
+    pub fn fault_injection(self, faults: Arc<FaultInjection>) -> Self {
+        Self {
+            fault_injection: Some(faults),
+            ..self
+        }
+    }
+
+    pub fn trace_propagation(self, propagation: TracePropagation) -> Self {
+        Self {
+            trace_propagation: Some(propagation),
+            ..self
+        }
+    }
+
+    #[cfg(feature = "grpc-web")]
+    pub fn grpc_web(self, grpc_web: bool) -> Self {
+        Self { grpc_web, ..self }
+    }
+
+    /// Sets the registry of name resolvers consulted before the global
+    /// registry, e.g. to use a custom resolver for this channel only.
+    pub fn name_resolver_registry(self, registry: ResolverRegistry) -> Self {
+        Self {
+            name_resolver_registry: Some(registry),
+            ..self
+        }
+    }
+
+    pub(crate) fn transport_registry(self, registry: TransportRegistry) -> Self {
+        Self {
+            transport_registry: Some(registry),
+            ..self
+        }
+    }
+
+    pub fn lb_policy_registry(self, registry: LbPolicyRegistry) -> Self {
+        Self {
+            lb_policy_registry: Some(registry),
+            ..self
+        }
+    }
+
+    pub fn connecting_watchdog(self, watchdog: Option<ConnectingWatchdog>) -> Self {
+        Self {
+            connecting_watchdog: watchdog,
+            ..self
+        }
+    }
     // etc
 }
 
+/// Upper bounds on the size of resolver updates accepted by a channel.
+///
+/// These protect the channel from misbehaving control planes: an update which
+/// exceeds any limit is reported back to the resolver as an error (so it may
+/// back off and re-resolve) instead of being forwarded to the LB policy, which
+/// could otherwise attempt to create tens of thousands of subchannels.  A limit
+/// of None means unlimited.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct ResolverUpdateLimits {
+    /// The maximum number of endpoints in a single update.
+    pub max_endpoints: Option<usize>,
+    /// The maximum number of addresses in any single endpoint.
+    pub max_addresses_per_endpoint: Option<usize>,
+    /// The maximum number of addresses across all endpoints in an update.
+    pub max_total_addresses: Option<usize>,
+}
+
+impl ResolverUpdateLimits {
+    pub fn max_endpoints(self, max: usize) -> Self {
+        Self {
+            max_endpoints: Some(max),
+            ..self
+        }
+    }
+
+    pub fn max_addresses_per_endpoint(self, max: usize) -> Self {
+        Self {
+            max_addresses_per_endpoint: Some(max),
+            ..self
+        }
+    }
+
+    pub fn max_total_addresses(self, max: usize) -> Self {
+        Self {
+            max_total_addresses: Some(max),
+            ..self
+        }
+    }
+
+    /// Returns an error describing the first limit exceeded by update, if any.
+    /// Updates carrying an endpoint error are not checked.
+    pub(crate) fn check(&self, update: &ResolverUpdate) -> Result<(), String> {
+        let Ok(endpoints) = &update.endpoints else {
+            return Ok(());
+        };
+        if let Some(max) = self.max_endpoints {
+            if endpoints.len() > max {
+                return Err(format!(
+                    "resolver update contains {} endpoints, exceeding the limit of {max}",
+                    endpoints.len()
+                ));
+            }
+        }
+        let mut total = 0;
+        for endpoint in endpoints {
+            let n = endpoint.addresses.len();
+            if let Some(max) = self.max_addresses_per_endpoint {
+                if n > max {
+                    return Err(format!(
+                        "resolver update contains an endpoint with {n} addresses, exceeding the limit of {max}"
+                    ));
+                }
+            }
+            total += n;
+        }
+        if let Some(max) = self.max_total_addresses {
+            if total > max {
+                return Err(format!(
+                    "resolver update contains {total} addresses, exceeding the limit of {max}"
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether a call waits for the channel to become ready instead of failing
+/// while the channel is in TRANSIENT_FAILURE.  Calls that wait are queued
+/// like calls made while the channel is connecting, until a picker routes
+/// them or their deadline expires.  Queued calls fail regardless if the
+/// channel shuts down (with CANCELLED) or enters idle (with UNAVAILABLE).
+///
+/// Insert this into a request's extensions to choose the behavior of that
+/// call.  Calls without one use the `waitForReady` setting of their method in
+/// the service config, and fail fast if it is unset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WaitForReady(pub bool);
+
+impl WaitForReady {
+    /// Returns the setting attached to request, if any.
+    pub fn from_request(request: &Request) -> Option<Self> {
+        request.extensions().get::<Self>().copied()
+    }
+}
+
 // All of Channel needs to be thread-safe.  Arc<inner>?  Or give out
 // Arc<Channel> from constructor?
 #[derive(Clone)]
@@ -143,7 +534,7 @@ impl Channel {
     /// Constructs a new gRPC channel.  A gRPC channel is a virtual, persistent
     /// connection to a service.  Channel creation cannot fail, but if the
     /// target string is invalid, the returned channel will never connect, and
-    /// will fail all RPCs.
+    /// will fail all RPCs.  An invalid default service config is ignored.
     // TODO: should this return a Result instead?
     pub fn new(
         target: &str,
@@ -151,35 +542,87 @@ impl Channel {
         options: ChannelOptions,
     ) -> Self {
         pick_first::reg();
+        let default_service_config = parse_default_service_config(&options).unwrap_or_else(|err| {
+            eprintln!("warning: ignoring default service config: {err}");
+            None
+        });
+        Self::with_default_service_config(target, credentials, options, default_service_config)
+    }
+
+    /// Constructs a new gRPC channel like [`Channel::new`], but fails if the
+    /// default service config of options is invalid.
+    pub fn try_new(
+        target: &str,
+        credentials: Option<Box<dyn Credentials>>,
+        options: ChannelOptions,
+    ) -> Result<Self, ChannelError> {
+        pick_first::reg();
+        let default_service_config =
+            parse_default_service_config(&options).map_err(ChannelError::InvalidConfig)?;
+        Ok(Self::with_default_service_config(
+            target,
+            credentials,
+            options,
+            default_service_config,
+        ))
+    }
+
+    fn with_default_service_config(
+        target: &str,
+        credentials: Option<Box<dyn Credentials>>,
+        options: ChannelOptions,
+        default_service_config: Option<ServiceConfig>,
+    ) -> Self {
         Self {
             inner: Arc::new(PersistentChannel::new(
                 target,
                 credentials,
                 default_runtime(),
                 options,
+                default_service_config,
             )),
         }
     }
 
-    // TODO: enter_idle(&self) and graceful_stop()?
+    /// Shuts down the channel.  RPCs already in progress are allowed to
+    /// complete, but new RPCs fail immediately and the channel's state becomes
+    /// Shutdown permanently.  Dropping the last clone of a channel also shuts
+    /// it down.
+    pub fn graceful_stop(&self) {
+        self.inner.shutdown();
+    }
 
-    /// Returns the current state of the channel.
-    pub fn state(&mut self, connect: bool) -> ConnectivityState {
-        let ac = if !connect {
-            // If !connect and we have no active channel already, return idle.
-            let ac = self.inner.active_channel.lock().unwrap();
-            if ac.is_none() {
-                return ConnectivityState::Idle;
-            }
-            ac.as_ref().unwrap().clone()
-        } else {
-            // Otherwise, get or create the active channel.
-            self.get_or_create_active_channel()
-        };
-        if let Some(s) = ac.connectivity_state.cur() {
-            return s;
+    /// Moves the channel into the Idle state, dropping its LB policy, name
+    /// resolver and connections.  The channel exits idle again on the next RPC
+    /// or call to [`connect`](Channel::connect).  RPCs waiting to be routed fail with
+    /// UNAVAILABLE, while those already routed are allowed to complete.
+    pub fn enter_idle(&self) {
+        // Drop outside the lock: dropping the active channel aborts its work
+        // queue, which drops the LB policy and resolver.
+        let ac = self.inner.active_channel.lock().unwrap().take();
+        if let Some(ac) = ac {
+            ac.abandon();
+        }
+    }
+
+    /// Returns the current state of the channel, without changing it.
+    pub fn state(&self) -> ConnectivityState {
+        if self.inner.is_shut_down() {
+            return ConnectivityState::Shutdown;
+        }
+        let ac = self.inner.active_channel.lock().unwrap().clone();
+        ac.and_then(|ac| ac.connectivity_state.cur())
+            .unwrap_or(ConnectivityState::Idle)
+    }
+
+    /// Exits idle and asks the LB policy to connect, then waits until the
+    /// channel is READY.  Fails if the channel enters TRANSIENT_FAILURE, shuts
+    /// down or enters idle first.
+    pub async fn connect(&self) -> Result<(), ChannelError> {
+        if self.inner.is_shut_down() {
+            return Err(ChannelError::Shutdown);
         }
-        ConnectivityState::Idle
+        self.get_or_create_active_channel()?.connect().await
     }
 
     /// Waits for the state of the channel to change from source.  Times out and
@@ -192,33 +635,223 @@ impl Channel {
         todo!()
     }
 
-    fn get_or_create_active_channel(&self) -> Arc<ActiveChannel> {
+    /// Asks the name resolver to re-resolve the target, e.g. after the
+    /// application learns that the backends changed, and waits until the
+    /// channel processes the next resolver update.  Returns Ok if the channel
+    /// accepted that update, or an error if it was rejected, or if no update was
+    /// processed before the deadline.
+    ///
+    /// Resolvers may rate-limit re-resolution, and an update for a resolution
+    /// already in progress may satisfy the request.
+    pub async fn reresolve_now(&self, deadline: Instant) -> Result<(), ChannelError> {
+        if self.inner.is_shut_down() {
+            return Err(ChannelError::Shutdown);
+        }
+        let rx = self.get_or_create_active_channel()?.reresolve_now();
+        let timeout = self
+            .inner
+            .runtime
+            .sleep(deadline.saturating_duration_since(Instant::now()));
+        tokio::select! {
+            result = rx => match result {
+                Ok(result) => result
+                    .map_err(|err| ResolveError::new(ResolveErrorKind::Rejected, err).into()),
+                Err(_) => Err(ChannelError::Cancelled(
+                    "channel entered idle before a resolver update was processed".to_string(),
+                )),
+            },
+            _ = timeout => Err(ChannelError::DeadlineExceeded(
+                "timed out waiting for a resolver update".to_string(),
+            )),
+        }
+    }
+
+    pub(super) fn runtime(&self) -> &Arc<dyn Runtime> {
+        &self.inner.runtime
+    }
+
+    // Fails if the target of the channel is invalid.
+    fn get_or_create_active_channel(&self) -> Result<Arc<ActiveChannel>, ChannelError> {
+        let url = self.inner.url.as_ref().map_err(|err| err.clone())?;
         let mut s = self.inner.active_channel.lock().unwrap();
         if s.is_none() {
             *s = Some(ActiveChannel::new(
-                self.inner.target.clone(),
+                url.clone(),
+                self.inner.channel_id,
                 &self.inner.options,
+                self.inner.subchannel_stats.clone(),
+                self.inner.service_config.clone(),
+                self.inner.connecting_watchdog.clone(),
                 self.inner.runtime.clone(),
             ));
         }
-        s.clone().unwrap()
+        Ok(s.clone().unwrap())
+    }
+
+    pub async fn call(&self, method: String, mut request: Request) -> Response {
+        if let Some(propagation) = &self.inner.options.trace_propagation {
+            propagation.inject(&mut request);
+        }
+        let log = self
+            .inner
+            .options
+            .binary_logger
+            .as_ref()
+            .and_then(|logger| logger.start(&method, Side::Client));
+        match log {
+            Some(log) => log.response(self.call_unlogged(method, log.request(request)).await),
+            None => self.call_unlogged(method, request).await,
+        }
+    }
+
+    async fn call_unlogged(&self, method: String, mut request: Request) -> Response {
+        if self.inner.is_shut_down() {
+            return shutdown_response();
+        }
+        let service_config = self.inner.service_config.current();
+        let method_config = service_config.config.method_config(&method);
+        if let Some(timeout) = method_config.and_then(|mc| mc.timeout) {
+            deadline::apply_default_timeout(&mut request, timeout);
+        }
+        let wait_for_ready = WaitForReady::from_request(&request)
+            .map(|w| w.0)
+            .or_else(|| method_config.and_then(|mc| mc.wait_for_ready))
+            .unwrap_or(false);
+        if let Some(faults) = &self.inner.options.fault_injection {
+            if let Err(status) = faults.apply(&request).await {
+                return status_response(status);
+            }
+        }
+        let mut phases = CallPhases::start(&request);
+        let permit = match self.inner.limiter.acquire(Priority::of(&request)).await {
+            Ok(permit) => permit,
+            Err(status) => return status_response(status),
+        };
+        let ac = match self.get_or_create_active_channel() {
+            Ok(ac) => ac,
+            Err(err) => return status_response(Status::unavailable(err.to_string())),
+        };
+        let retries = method_config
+            .and_then(|mc| mc.retry_policy.clone())
+            .map(|policy| PolicyRetries::new(policy, service_config.retry_throttler.clone()));
+        let response = ac
+            .call(
+                method.clone(),
+                request,
+                wait_for_ready,
+                retries,
+                &mut phases,
+            )
+            .await;
+        let response = details::normalize_response_details(
+            response,
+            self.inner.options.max_status_details_size,
+        );
+        let response = priority::hold_until_complete(response, permit);
+        let response = match &service_config.retry_throttler {
+            Some(throttler) => {
+                let failure_codes = method_config
+                    .map(|mc| {
+                        let retryable = mc
+                            .retry_policy
+                            .as_ref()
+                            .map(|p| &p.retryable_status_codes[..])
+                            .unwrap_or_default();
+                        [retryable, &mc.non_fatal_status_codes[..]].concat()
+                    })
+                    .unwrap_or_default();
+                retry_throttling::record_on_complete(response, failure_codes, throttler.clone())
+            }
+            None => response,
+        };
+        deadline::record_on_complete(response, method, phases, self.inner.deadline_stats.clone())
+    }
+
+    /// Returns the number of messages compressed, and skipped by the
+    /// channel's compression policy.
+    #[cfg(feature = "zstd")]
+    pub fn compression_stats(&self) -> CompressionStats {
+        self.inner.options.compression_policy.stats()
+    }
+
+    /// Returns statistics about the connection attempts made by this channel's
+    /// subchannels, labeled by subchannel labels.
+    pub fn subchannel_stats(&self) -> SubchannelStats {
+        self.inner.subchannel_stats.stats()
     }
 
-    pub async fn call(&self, method: String, request: Request) -> Response {
-        let ac = self.get_or_create_active_channel();
-        ac.call(method, request).await
+    /// Returns statistics about the calls made on this channel, labeled by
+    /// priority class.
+    pub fn call_stats(&self) -> CallStats {
+        self.inner.limiter.stats()
+    }
+
+    /// Returns statistics about the deadlines of the calls made on this
+    /// channel, labeled by method.
+    pub fn deadline_stats(&self) -> DeadlineStats {
+        self.inner.deadline_stats.stats()
+    }
+
+    /// Returns the most recent reports of subchannels found stuck in
+    /// CONNECTING by the channel's watchdog, oldest first.
+    pub fn stuck_connecting_reports(&self) -> Vec<StuckConnecting> {
+        self.inner.connecting_watchdog.reports()
+    }
+
+    /// Returns a snapshot of the channel's configuration and state, for
+    /// logging and support tooling.  Does not cause the channel to exit idle.
+    pub async fn debug_state(&self) -> ChannelDebugState {
+        let mut state = ChannelDebugState {
+            target: self.inner.target.clone(),
+            resolver_scheme: self
+                .inner
+                .url
+                .as_ref()
+                .map_or_else(|_| String::new(), |url| url.scheme().to_string()),
+            connectivity_state: self.state(),
+            lb_policy: None,
+            lb_config: None,
+            subchannels: Vec::new(),
+            picker: None,
+            queued_calls: 0,
+            callback_panics: 0,
+        };
+        let ac = self.inner.active_channel.lock().unwrap().clone();
+        if let Some(ac) = ac {
+            ac.debug_state(&mut state).await;
+        }
+        state
     }
 }
 
+fn parse_default_service_config(options: &ChannelOptions) -> Result<Option<ServiceConfig>, String> {
+    let lb_registry = options.lb_policy_registry.clone().unwrap_or_default();
+    options
+        .default_service_config
+        .as_deref()
+        .map(|config| ServiceConfig::parse_with_lb_registry(config, &lb_registry))
+        .transpose()
+}
+
 // A PersistentChannel represents the static configuration of a channel and an
 // optional Arc of an ActiveChannel.  An ActiveChannel exists whenever the
 // PersistentChannel is not IDLE.  Every channel is IDLE at creation, or after
 // some configurable timeout elapses without any any RPC activity.
 struct PersistentChannel {
-    target: Url,
+    target: String,
+    // The parsed target, or the error failing every call if it is invalid.
+    url: Result<Url, ResolveError>,
+    // A random identifier used by the ChannelId request hash policy.
+    channel_id: u64,
     options: ChannelOptions,
     active_channel: Mutex<Option<Arc<ActiveChannel>>>,
     runtime: Arc<dyn Runtime>,
+    shut_down: AtomicBool,
+    limiter: PriorityLimiter,
+    subchannel_stats: Arc<SubchannelStatsRecorder>,
+    deadline_stats: Arc<DeadlineStatsRecorder>,
+    connecting_watchdog: Arc<ConnectingWatchdogMonitor>,
+    service_config: Arc<ServiceConfigSelector>,
 }
 
 impl PersistentChannel {
@@ -229,122 +862,589 @@ impl PersistentChannel {
         _credentials: Option<Box<dyn Credentials>>,
         runtime: Arc<dyn rt::Runtime>,
         options: ChannelOptions,
+        default_service_config: Option<ServiceConfig>,
     ) -> Self {
+        let service_config = Arc::new(ServiceConfigSelector::new(
+            default_service_config,
+            options.require_resolver_service_config,
+        ));
         Self {
-            target: Url::from_str(target).unwrap(), // TODO handle err
+            target: target.to_string(),
+            url: Url::from_str(target).map_err(|err| {
+                ResolveError::new(
+                    ResolveErrorKind::InvalidTarget,
+                    format!("invalid target {target:?}: {err}"),
+                )
+            }),
+            channel_id: rand::random(),
             active_channel: Mutex::default(),
+            limiter: PriorityLimiter::new(options.call_limits.clone()),
+            subchannel_stats: Arc::new(SubchannelStatsRecorder::new(
+                target.to_string(),
+                options.stats_handlers.iter().cloned().collect(),
+            )),
+            deadline_stats: Arc::default(),
+            connecting_watchdog: Arc::new(ConnectingWatchdogMonitor::new(
+                options.connecting_watchdog.clone(),
+            )),
+            service_config,
             options,
             runtime,
+            shut_down: AtomicBool::new(false),
+        }
+    }
+
+    fn is_shut_down(&self) -> bool {
+        self.shut_down.load(Ordering::Acquire)
+    }
+
+    fn shutdown(&self) {
+        if self.shut_down.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        // In-progress RPCs hold their own references to the active channel,
+        // keeping it alive until they complete.
+        if let Some(ac) = self.active_channel.lock().unwrap().take() {
+            ac.shutdown();
+        }
+    }
+}
+
+impl Drop for PersistentChannel {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+// Returns the response for an RPC started on a channel after it was shut down.
+fn shutdown_response() -> Response {
+    status_response(Status::cancelled("channel is shut down"))
+}
+
+// Adds the metadata of a pick to the request of an attempt.  Its values are
+// appended after those the application set for the same keys, so servers
+// receive both.
+fn merge_pick_metadata(request: &mut Request, metadata: &MetadataMap) {
+    let outgoing = request.metadata_mut();
+    for entry in metadata.iter() {
+        match entry {
+            KeyAndValueRef::Ascii(key, value) => {
+                outgoing.append(key.clone(), value.clone());
+            }
+            KeyAndValueRef::Binary(key, value) => {
+                outgoing.append_bin(key.clone(), value.clone());
+            }
         }
     }
 }
 
+// Returns the status for an RPC failed by a pick.  LB policies may not produce
+// codes which are reserved for the application, which are converted to
+// INTERNAL per gRFC A54.
+fn pick_status(status: Status) -> Status {
+    match status.code() {
+        Code::Ok
+        | Code::InvalidArgument
+        | Code::NotFound
+        | Code::AlreadyExists
+        | Code::FailedPrecondition
+        | Code::Aborted
+        | Code::OutOfRange
+        | Code::DataLoss => Status::internal(format!(
+            "LB policy produced illegal status code {:?}: {}",
+            status.code(),
+            status.message()
+        )),
+        _ => status,
+    }
+}
+
 struct ActiveChannel {
+    work_queue_tx: WorkQueueTx,
     cur_state: Mutex<ConnectivityState>,
     abort_handle: Box<dyn rt::TaskHandle>,
     picker: Arc<Watcher<Arc<dyn Picker>>>,
     connectivity_state: Arc<Watcher<ConnectivityState>>,
     runtime: Arc<dyn Runtime>,
+    channel_id: u64,
+    request_hash_policy: Option<RequestHashPolicy>,
+    session_cookie: Option<SessionCookieConfig>,
+    max_retry_memory: usize,
+    stats_handlers: Arc<[Arc<dyn StatsHandler>]>,
+    // Set once the channel entered idle and replaced this active channel, so
+    // no picker will route the RPCs queued on it.
+    abandoned: AtomicBool,
+    // The number of calls waiting for a picker to route them.
+    queued_calls: AtomicUsize,
+    work_queue_monitor: Arc<WorkQueueMonitor>,
+    _leak_tracker: LeakTracker,
 }
 
 impl ActiveChannel {
-    fn new(target: Url, options: &ChannelOptions, runtime: Arc<dyn Runtime>) -> Arc<Self> {
+    fn new(
+        target: Url,
+        channel_id: u64,
+        options: &ChannelOptions,
+        subchannel_stats: Arc<SubchannelStatsRecorder>,
+        service_config: Arc<ServiceConfigSelector>,
+        connecting_watchdog: Arc<ConnectingWatchdogMonitor>,
+        runtime: Arc<dyn Runtime>,
+    ) -> Arc<Self> {
         let (tx, mut rx) = mpsc::unbounded_channel::<WorkQueueItem>();
-        let transport_registry = GLOBAL_TRANSPORT_REGISTRY.clone();
+        let transport_registry = options.transport_registry.clone().unwrap_or_default();
 
-        let resolve_now = Arc::new(Notify::new());
         let connectivity_state = Arc::new(Watcher::new());
         let picker = Arc::new(Watcher::new());
         let mut channel_controller = InternalChannelController::new(
             transport_registry,
-            resolve_now.clone(),
+            options.lb_policy_registry.clone().unwrap_or_default(),
+            options.resolver_update_limits.clone(),
+            ResolutionThrottle::new(
+                options.min_reresolution_interval,
+                DEFAULT_EXPONENTIAL_CONFIG,
+            ),
             tx.clone(),
             picker.clone(),
             connectivity_state.clone(),
+            subchannel_stats,
+            service_config,
+            Arc::new(TransportOptions {
+                attributes: options.transport_options.clone(),
+                #[cfg(feature = "zstd")]
+                compression: MessageCompression {
+                    policy: options.compression_policy.clone(),
+                    dictionaries: options.zstd_dictionaries.clone(),
+                    ..MessageCompression::default()
+                },
+                ..TransportOptions::with_http2(&options.http2_options)
+            }),
+            connecting_watchdog,
             runtime.clone(),
         );
 
+        if let Some(cache) = &options.resolution_cache {
+            let key = target.to_string();
+            // Apply the cached update before the resolver is built, so it is
+            // processed before any update from the resolver.
+            if let Some(update) = cache.get(&key) {
+                let _ = tx.send(WorkQueueItem::Closure(
+                    WorkItemKind::ResolverUpdate,
+                    Box::new(|c: &mut InternalChannelController| {
+                        let _ = c.apply_resolver_update(update);
+                    }),
+                ));
+            }
+            channel_controller.resolution_cache = Some((cache.clone(), key));
+        }
+        channel_controller.rebuild_lb_policy_on_panic = options.rebuild_lb_policy_on_panic;
+        #[cfg(feature = "grpc-web")]
+        {
+            channel_controller.grpc_web = options.grpc_web;
+        }
+
         let resolver_helper = Box::new(tx.clone());
 
         // TODO(arjan-bal): Return error here instead of panicking.
-        let rb = global_registry().get(target.scheme()).unwrap();
+        let rb = options
+            .name_resolver_registry
+            .as_ref()
+            .and_then(|registry| registry.get(target.scheme()))
+            .or_else(|| global_registry().get(target.scheme()))
+            .unwrap();
         let target = name_resolution::Target::from(target);
-        let authority = target.authority_host_port();
-        let authority = if authority.is_empty() {
-            rb.default_authority(&target).to_owned()
-        } else {
-            authority
+        let authority = match &options.override_authority {
+            Some(authority) => authority.clone(),
+            None => {
+                let authority = target.authority_host_port();
+                if authority.is_empty() {
+                    rb.default_authority(&target).to_owned()
+                } else {
+                    authority
+                }
+            }
         };
-        let work_scheduler = Arc::new(ResolverWorkScheduler { wqtx: tx });
+        let work_scheduler = Arc::new(ResolverWorkScheduler { wqtx: tx.clone() });
         let resolver_opts = name_resolution::ResolverOptions {
             authority,
             work_scheduler,
             runtime: runtime.clone(),
+            disable_service_config_lookup: options.disable_service_config_lookup,
+            args: options.resolver_args.clone(),
         };
         let resolver = rb.build(&target, resolver_opts);
 
+        let work_queue_monitor = Arc::new(WorkQueueMonitor::new(options.slow_work_item_threshold));
+        let monitor = work_queue_monitor.clone();
         let jh = runtime.spawn(Box::pin(async move {
             let mut resolver = resolver;
             while let Some(w) = rx.recv().await {
-                match w {
-                    WorkQueueItem::Closure(func) => func(&mut channel_controller),
+                let kind = w.kind();
+                let start = Instant::now();
+                // A panicking callback must not end the task, which would
+                // leave every future call of the channel waiting.
+                let result = catch_unwind(AssertUnwindSafe(|| match w {
+                    WorkQueueItem::Closure(_, func) => func(&mut channel_controller),
                     WorkQueueItem::ScheduleResolver => resolver.work(&mut channel_controller),
+                    WorkQueueItem::ResolveNow(waiter) => {
+                        channel_controller.resolution_waiters.extend(waiter);
+                        resolver.resolve_now();
+                    }
+                }));
+                let policy_name = channel_controller.lb.policy_name();
+                monitor.record(kind, start.elapsed(), policy_name);
+                if let Err(payload) = result {
+                    let message = panic_message(&*payload);
+                    monitor.record_panic(kind, message, policy_name);
+                    channel_controller.handle_panic(kind, message);
                 }
             }
         }));
 
         Arc::new(Self {
+            work_queue_tx: tx,
             cur_state: Mutex::new(ConnectivityState::Connecting),
             abort_handle: jh,
             picker: picker.clone(),
             connectivity_state: connectivity_state.clone(),
             runtime,
+            channel_id,
+            request_hash_policy: options.request_hash_policy.clone(),
+            session_cookie: options.session_cookie.clone(),
+            max_retry_memory: options.max_retry_memory as usize,
+            stats_handlers: options.stats_handlers.iter().cloned().collect(),
+            abandoned: AtomicBool::new(false),
+            queued_calls: AtomicUsize::new(0),
+            work_queue_monitor,
+            _leak_tracker: LeakTracker::new("ActiveChannel"),
         })
     }
 
-    async fn call(&self, method: String, request: Request) -> Response {
-        // TODO: pre-pick tasks (e.g. deadlines, interceptors, retry)
-        let mut i = self.picker.iter();
+    // Shuts down the LB policy and moves the channel into the Shutdown state.
+    // Queued RPCs are woken and fail.
+    fn shutdown(&self) {
+        let _ = self.work_queue_tx.send(WorkQueueItem::Closure(
+            WorkItemKind::Cleanup,
+            Box::new(|c: &mut InternalChannelController| {
+                c.lb.shutdown();
+                c.connectivity_state.update(ConnectivityState::Shutdown);
+                c.picker.update(Arc::new(QueuingPicker {}));
+            }),
+        ));
+    }
+
+    // Fails the RPCs queued on this channel after the channel replaced it.
+    // RPCs in progress keep it alive until they complete.
+    fn abandon(&self) {
+        self.abandoned.store(true, Ordering::Release);
+        // Wake the queued RPCs, which may be waiting for a first picker, and
+        // calls to connect.
+        self.picker.update(Arc::new(QueuingPicker {}));
+        self.connectivity_state.update(ConnectivityState::Idle);
+    }
+
+    // Waits until the channel is READY, asking the LB policy to connect
+    // whenever it is idle.
+    async fn connect(&self) -> Result<(), ChannelError> {
+        let mut states = self.connectivity_state.iter();
+        self.exit_idle();
         loop {
-            if let Some(p) = i.next().await {
-                let result = &p.pick(&request);
-                // TODO: handle picker errors (queue or fail RPC)
-                match result {
-                    PickResult::Pick(pr) => {
-                        if let Some(sc) = (pr.subchannel.as_ref() as &dyn Any)
-                            .downcast_ref::<ExternalSubchannel>()
-                        {
-                            return sc.isc.as_ref().unwrap().call(method, request).await;
-                        } else {
-                            panic!("picked subchannel is not an implementation provided by the channel");
-                        }
-                    }
-                    PickResult::Queue => {
-                        // Continue and retry the RPC with the next picker.
-                    }
-                    PickResult::Fail(status) => {
-                        panic!("failed pick: {}", status);
-                    }
-                    PickResult::Drop(status) => {
-                        panic!("dropped pick: {}", status);
-                    }
+            let state = states.next().await;
+            if self.abandoned.load(Ordering::Acquire) {
+                return Err(ChannelError::Cancelled(
+                    "channel entered idle before it connected".to_string(),
+                ));
+            }
+            match state {
+                Some(ConnectivityState::Ready) => return Ok(()),
+                Some(ConnectivityState::Idle) => self.exit_idle(),
+                Some(ConnectivityState::Connecting) => {}
+                Some(ConnectivityState::TransientFailure) => {
+                    return Err(self.transient_failure_error())
                 }
+                None | Some(ConnectivityState::Shutdown) => return Err(ChannelError::Shutdown),
             }
         }
     }
-}
 
-impl Drop for ActiveChannel {
-    fn drop(&mut self) {
-        self.abort_handle.abort();
+    // Returns the error of a channel in TRANSIENT_FAILURE, with the status its
+    // picker fails RPCs with if any.
+    fn transient_failure_error(&self) -> ChannelError {
+        let request = Request::new(Box::pin(tokio_stream::empty()));
+        let message = match self.picker.cur().map(|p| p.pick(&request)) {
+            Some(PickResult::Fail(status)) => {
+                format!("channel is in TRANSIENT_FAILURE: {}", status.message())
+            }
+            _ => "channel is in TRANSIENT_FAILURE".to_string(),
+        };
+        ChannelError::Connect(ConnectError::new(ConnectErrorKind::Other, message))
     }
-}
 
-struct ResolverWorkScheduler {
-    wqtx: WorkQueueTx,
-}
+    // Asks the resolver to re-resolve.  The returned receiver yields the
+    // channel's response to the next resolver update.
+    fn reresolve_now(&self) -> oneshot::Receiver<Result<(), String>> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.work_queue_tx.send(WorkQueueItem::ResolveNow(Some(tx)));
+        rx
+    }
 
-pub(super) type WorkQueueTx = mpsc::UnboundedSender<WorkQueueItem>;
+    // Fills in the parts of state owned by this active channel.  The LB policy
+    // and subchannels are read on the work queue, so that the snapshot does
+    // not race with updates to them.
+    async fn debug_state(&self, state: &mut ChannelDebugState) {
+        state.picker = self.picker.cur().map(|p| p.type_name().to_string());
+        state.queued_calls = self.queued_calls.load(Ordering::Relaxed);
+        state.callback_panics = self.work_queue_monitor.panics();
+        let (tx, rx) = oneshot::channel();
+        let _ = self.work_queue_tx.send(WorkQueueItem::Closure(
+            WorkItemKind::Work,
+            Box::new(move |c: &mut InternalChannelController| {
+                let subchannels = c
+                    .subchannel_pool
+                    .subchannels()
+                    .iter()
+                    .map(|isc| {
+                        let address = isc.address();
+                        SubchannelDebugState {
+                            address: address.address.to_string(),
+                            network_type: address.network_type,
+                            connectivity_state: isc.connectivity_state(),
+                        }
+                    })
+                    .collect();
+                let _ = tx.send((
+                    c.lb.policy_name().map(str::to_string),
+                    c.lb.policy_config.lock().unwrap().clone(),
+                    subchannels,
+                ));
+            }),
+        ));
+        // The work queue is gone once the channel shuts down.
+        if let Ok((lb_policy, lb_config, subchannels)) = rx.await {
+            state.lb_policy = lb_policy;
+            state.lb_config = lb_config;
+            state.subchannels = subchannels;
+        }
+    }
+
+    // Asks the LB policy to start connecting if it is idle.
+    fn exit_idle(&self) {
+        let _ = self.work_queue_tx.send(WorkQueueItem::Closure(
+            WorkItemKind::ExitIdle,
+            Box::new(|c: &mut InternalChannelController| {
+                c.lb.clone().exit_idle(c);
+            }),
+        ));
+    }
+
+    async fn call(
+        &self,
+        method: String,
+        mut request: Request,
+        wait_for_ready: bool,
+        mut retries: Option<PolicyRetries>,
+        phases: &mut CallPhases,
+    ) -> Response {
+        RequestHashPolicy::apply(
+            self.request_hash_policy.as_ref(),
+            &mut request,
+            self.channel_id,
+        );
+        let override_host = self
+            .session_cookie
+            .as_ref()
+            .and_then(|c| c.apply_request(&mut request));
+        // TODO: pre-pick tasks (e.g. interceptors, retry)
+        let replay = ReplayableRequest::new(request, self.max_retry_memory);
+        let mut attempt = replay.attempt();
+        let mut refused_retries = 0;
+        let mut attempts = 0;
+        let mut i = self.picker.iter();
+        // The picker to use again immediately for a transparent retry.
+        let mut retry_picker: Option<Arc<dyn Picker>> = None;
+        // Tracks the RPC while it is waiting for a picker that can route it.
+        let mut _queued: Option<QueuedCall> = None;
+        loop {
+            let state = self.connectivity_state.cur();
+            if state == Some(ConnectivityState::Shutdown) {
+                return shutdown_response();
+            }
+            phases.enter(match state {
+                None | Some(ConnectivityState::Connecting) => CallPhase::Connecting,
+                _ => CallPhase::Queuing,
+            });
+            let next = match (retry_picker.take(), phases.deadline()) {
+                (Some(p), _) => Some(p),
+                (None, Some(deadline)) => {
+                    let timeout = self
+                        .runtime
+                        .sleep(deadline.saturating_duration_since(Instant::now()));
+                    tokio::select! {
+                        p = i.next() => p,
+                        _ = timeout => {
+                            return status_response(Status::deadline_exceeded(
+                                "deadline exceeded while waiting for a picker to route the call",
+                            ));
+                        }
+                    }
+                }
+                (None, None) => i.next().await,
+            };
+            if self.abandoned.load(Ordering::Acquire) {
+                return status_response(Status::unavailable(
+                    "channel entered idle before the call was routed",
+                ));
+            }
+            // The picker can only be closed if the channel is torn down.
+            let Some(p) = next else {
+                return status_response(Status::unavailable(
+                    "channel closed before the call was routed",
+                ));
+            };
+            let request = attempt.as_ref().unwrap();
+            let result = p.pick(request);
+            // TODO: handle picker errors (queue or fail RPC)
+            match result {
+                PickResult::Pick(pr) => {
+                    if let Some(sc) =
+                        (pr.subchannel.as_ref() as &dyn Any).downcast_ref::<ExternalSubchannel>()
+                    {
+                        phases.enter(CallPhase::Server);
+                        attempts += 1;
+                        let stats = RpcStats::begin(
+                            &self.stats_handlers,
+                            RpcInfo {
+                                method: method.clone(),
+                                is_client: true,
+                                attempt: attempts,
+                            },
+                        );
+                        let completion = pr.on_complete.map(CompletionRecorder::new);
+                        let mut request = attempt.take().unwrap();
+                        merge_pick_metadata(&mut request, &pr.metadata);
+                        if let Some(completion) = &completion {
+                            request = completion.request(request);
+                        }
+                        if let Some(stats) = &stats {
+                            request = stats.request(request, true);
+                        }
+                        let mut response =
+                            sc.isc.as_ref().unwrap().call(method.clone(), request).await;
+                        if let Some(completion) = &completion {
+                            response = completion.response(response);
+                        }
+                        if let Some(stats) = &stats {
+                            response = stats.response(response, false);
+                        }
+                        if let Some(cookie) = &self.session_cookie {
+                            cookie.apply_response(
+                                override_host.as_ref(),
+                                &sc.address(),
+                                &mut response,
+                            );
+                        }
+                        let (kind, status) = match retry::check_response(response) {
+                            Ok(response) => {
+                                let Some(retries) = &mut retries else {
+                                    return response;
+                                };
+                                let (delay, status) = match retries.check(response) {
+                                    Ok(response) => return response,
+                                    Err(retry) => retry,
+                                };
+                                attempt = replay.attempt();
+                                if attempt.is_none()
+                                    || phases
+                                        .deadline()
+                                        .is_some_and(|d| Instant::now() + delay >= d)
+                                {
+                                    return status_response(status);
+                                }
+                                self.runtime.sleep(delay).await;
+                                // The picker may have been replaced during
+                                // the backoff.
+                                retry_picker = self.picker.cur().or(Some(p));
+                                continue;
+                            }
+                            Err(unprocessed) => unprocessed,
+                        };
+                        if kind == Unprocessed::Refused {
+                            refused_retries += 1;
+                            if refused_retries > retry::MAX_REFUSED_RETRIES {
+                                return status_response(status);
+                            }
+                            // The server is reachable, so pick again
+                            // without waiting for a new picker.
+                            retry_picker = Some(p);
+                        }
+                        attempt = replay.attempt();
+                        if attempt.is_none() {
+                            return status_response(status);
+                        }
+                    } else {
+                        panic!(
+                            "picked subchannel is not an implementation provided by the channel"
+                        );
+                    }
+                }
+                PickResult::Queue => {
+                    _queued.get_or_insert_with(|| QueuedCall::new(&self.queued_calls));
+                    // Continue and retry the RPC with the next picker.
+                }
+                PickResult::Fail(_) if wait_for_ready => {
+                    _queued.get_or_insert_with(|| QueuedCall::new(&self.queued_calls));
+                    // Continue and retry the RPC with the next picker.
+                }
+                PickResult::Fail(status) => {
+                    let status = Status::with_details_and_metadata(
+                        Code::Unavailable,
+                        status.message(),
+                        status.details().to_vec().into(),
+                        status.metadata().clone(),
+                    );
+                    return status_response(status);
+                }
+                PickResult::Drop(status) => {
+                    return status_response(pick_status(status));
+                }
+            }
+        }
+    }
+}
+
+impl Drop for ActiveChannel {
+    fn drop(&mut self) {
+        self.abort_handle.abort();
+    }
+}
+
+// Counts a call in its channel's queued calls while it is waiting for a
+// picker that can route it.
+struct QueuedCall<'a> {
+    count: &'a AtomicUsize,
+    _leak_tracker: LeakTracker,
+}
+
+impl<'a> QueuedCall<'a> {
+    fn new(count: &'a AtomicUsize) -> Self {
+        count.fetch_add(1, Ordering::Relaxed);
+        Self {
+            count,
+            _leak_tracker: LeakTracker::new("QueuedCall"),
+        }
+    }
+}
+
+impl Drop for QueuedCall<'_> {
+    fn drop(&mut self) {
+        self.count.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+struct ResolverWorkScheduler {
+    wqtx: WorkQueueTx,
+}
+
+pub(super) type WorkQueueTx = mpsc::UnboundedSender<WorkQueueItem>;
 
 impl name_resolution::WorkScheduler for ResolverWorkScheduler {
     fn schedule_work(&self) {
@@ -355,21 +1455,45 @@ impl name_resolution::WorkScheduler for ResolverWorkScheduler {
 pub(crate) struct InternalChannelController {
     pub(super) lb: Arc<GracefulSwitchBalancer>, // called and passes mutable parent to it, so must be Arc.
     transport_registry: TransportRegistry,
+    lb_policy_registry: LbPolicyRegistry,
+    resolver_update_limits: ResolverUpdateLimits,
     pub(super) subchannel_pool: Arc<InternalSubchannelPool>,
-    resolve_now: Arc<Notify>,
+    resolution_throttle: ResolutionThrottle,
     wqtx: WorkQueueTx,
     picker: Arc<Watcher<Arc<dyn Picker>>>,
     connectivity_state: Arc<Watcher<ConnectivityState>>,
+    subchannel_stats: Arc<SubchannelStatsRecorder>,
+    service_config: Arc<ServiceConfigSelector>,
+    transport_options: Arc<TransportOptions>,
+    connecting_watchdog: Arc<ConnectingWatchdogMonitor>,
     runtime: Arc<dyn Runtime>,
+    // Notified with the result of the next resolver update; see
+    // Channel::reresolve_now.
+    resolution_waiters: Vec<oneshot::Sender<Result<(), String>>>,
+    // The cache receiving the resolver updates accepted for the target, which
+    // is the key of its entry.
+    resolution_cache: Option<(Arc<ResolutionCache>, String)>,
+    // Whether the LB policy is replaced after one of its callbacks panicked.
+    rebuild_lb_policy_on_panic: bool,
+    // Whether TCP addresses are connected to with the gRPC-Web transport.
+    #[cfg(feature = "grpc-web")]
+    grpc_web: bool,
 }
 
 impl InternalChannelController {
+    #[allow(clippy::too_many_arguments)]
     fn new(
         transport_registry: TransportRegistry,
-        resolve_now: Arc<Notify>,
+        lb_policy_registry: LbPolicyRegistry,
+        resolver_update_limits: ResolverUpdateLimits,
+        resolution_throttle: ResolutionThrottle,
         wqtx: WorkQueueTx,
         picker: Arc<Watcher<Arc<dyn Picker>>>,
         connectivity_state: Arc<Watcher<ConnectivityState>>,
+        subchannel_stats: Arc<SubchannelStatsRecorder>,
+        service_config: Arc<ServiceConfigSelector>,
+        transport_options: Arc<TransportOptions>,
+        connecting_watchdog: Arc<ConnectingWatchdogMonitor>,
         runtime: Arc<dyn Runtime>,
     ) -> Self {
         let lb = Arc::new(GracefulSwitchBalancer::new(wqtx.clone(), runtime.clone()));
@@ -377,33 +1501,124 @@ impl InternalChannelController {
         Self {
             lb,
             transport_registry,
+            lb_policy_registry,
+            resolver_update_limits,
             subchannel_pool: Arc::new(InternalSubchannelPool::new()),
-            resolve_now,
+            resolution_throttle,
             wqtx,
             picker,
             connectivity_state,
+            subchannel_stats,
+            service_config,
+            transport_options,
+            connecting_watchdog,
             runtime,
+            resolution_waiters: Vec::new(),
+            resolution_cache: None,
+            rebuild_lb_policy_on_panic: false,
+            #[cfg(feature = "grpc-web")]
+            grpc_web: false,
         }
     }
 
     fn new_esc_for_isc(&self, isc: Arc<InternalSubchannel>) -> Arc<dyn Subchannel> {
         let sc = Arc::new(ExternalSubchannel::new(isc.clone(), self.wqtx.clone()));
-        let watcher = Arc::new(SubchannelStateWatcher::new(sc.clone(), self.wqtx.clone()));
+        let watcher = Arc::new(SubchannelStateWatcher::new(
+            sc.clone(),
+            self.wqtx.clone(),
+            self.lb.generation(),
+        ));
         sc.set_watcher(watcher.clone());
         isc.register_connectivity_state_watcher(watcher.clone());
         sc
     }
+
+    // Fails calls after a resolver or LB policy callback panicked, leaving the
+    // state of the LB policy unknown.
+    fn handle_panic(&mut self, kind: WorkItemKind, message: &str) {
+        if self.connectivity_state.cur() == Some(ConnectivityState::Shutdown) {
+            return;
+        }
+        if self.rebuild_lb_policy_on_panic {
+            self.lb = Arc::new(GracefulSwitchBalancer::new(
+                self.wqtx.clone(),
+                self.runtime.clone(),
+            ));
+            load_balancing::ChannelController::request_resolution(self);
+        } else {
+            self.lb.clear_poison();
+        }
+        self.picker.update(Arc::new(Failing {
+            error: format!("channel callback panicked during {kind}: {message}"),
+        }));
+        self.connectivity_state
+            .update_if_changed(ConnectivityState::TransientFailure);
+    }
+
+    fn apply_resolver_update(&mut self, update: ResolverUpdate) -> Result<(), String> {
+        // Reject oversized updates before the LB policy sees them so that the
+        // last accepted update remains in effect.
+        if let Err(err) = self.resolver_update_limits.check(&update) {
+            eprintln!("rejecting resolver update: {err}");
+            return Err(err);
+        }
+        let service_config = self
+            .service_config
+            .choose(&update.service_config)
+            .inspect_err(|err| eprintln!("rejecting resolver update: {err}"))?;
+        let lb_policy = match &service_config {
+            Some(config) => config.lb_policy.clone(),
+            None => self.service_config.current().config.lb_policy.clone(),
+        };
+        #[cfg(feature = "grpc-web")]
+        let update = {
+            let mut update = update;
+            if self.grpc_web {
+                super::transport::grpc_web::use_grpc_web(&mut update);
+            }
+            update
+        };
+        #[cfg(feature = "quic")]
+        let update = {
+            let mut update = update;
+            if self
+                .transport_registry
+                .get_transport_or_global(super::transport::quic::QUIC_NETWORK_TYPE)
+                .is_ok()
+            {
+                super::transport::quic::prefer_http3(&mut update);
+            }
+            update
+        };
+        let mut update = update;
+        self.transport_registry.mark_supported(&mut update);
+        let lb = self.lb.clone();
+        lb.handle_resolver_update(update, lb_policy, self)
+            .map_err(|err| err.to_string())?;
+        self.service_config.select(service_config);
+        Ok(())
+    }
 }
 
 impl name_resolution::ChannelController for InternalChannelController {
     fn update(&mut self, update: ResolverUpdate) -> Result<(), String> {
-        let lb = self.lb.clone();
-        lb.handle_resolver_update(update, self)
-            .map_err(|err| err.to_string())
+        let cached = self.resolution_cache.as_ref().map(|_| update.clone());
+        let result = self.apply_resolver_update(update);
+        if let (Ok(()), Some((cache, key)), Some(update)) =
+            (&result, &self.resolution_cache, cached)
+        {
+            cache.insert(key, &update);
+        }
+        self.resolution_throttle
+            .update_result(result.is_ok(), Instant::now());
+        for waiter in self.resolution_waiters.drain(..) {
+            let _ = waiter.send(result.clone());
+        }
+        result
     }
 
     fn parse_service_config(&self, config: &str) -> Result<ServiceConfig, String> {
-        Err("service configs not supported".to_string())
+        ServiceConfig::parse_with_lb_registry(config, &self.lb_policy_registry)
     }
 }
 
@@ -420,10 +1635,9 @@ impl load_balancing::ChannelController for InternalChannelController {
         //    its internal subchannel has been dropped but hasn't been
         //    unregistered yet.
 
-        let transport = self
-            .transport_registry
-            .get_transport(address.network_type)
-            .unwrap();
+        // The transport is selected by the address's network type when
+        // connecting, so that addresses without one only fail to connect.
+        let transport = Arc::new(RegisteredTransport::new(self.transport_registry.clone()));
         let scp = self.subchannel_pool.clone();
         let isc = InternalSubchannel::new(
             key.clone(),
@@ -432,6 +1646,9 @@ impl load_balancing::ChannelController for InternalChannelController {
             Box::new(move |k: SubchannelKey| {
                 scp.unregister_subchannel(&k);
             }),
+            self.subchannel_stats.clone(),
+            self.transport_options.clone(),
+            self.connecting_watchdog.clone(),
             self.runtime.clone(),
         );
         let _ = self.subchannel_pool.register_subchannel(&key, isc.clone());
@@ -439,16 +1656,60 @@ impl load_balancing::ChannelController for InternalChannelController {
     }
 
     fn update_picker(&mut self, update: LbState) {
+        if self.connectivity_state.cur() == Some(ConnectivityState::Shutdown) {
+            // Shutdown is terminal.
+            return;
+        }
         println!(
             "update picker called with state: {:?}",
             update.connectivity_state
         );
-        self.picker.update(update.picker);
-        self.connectivity_state.update(update.connectivity_state);
+        if let Err(err) = update.validate() {
+            if cfg!(debug_assertions) {
+                panic!("invalid picker update from LB policy: {err}");
+            }
+            eprintln!("warning: invalid picker update from LB policy: {err}");
+            if update.connectivity_state == ConnectivityState::Shutdown {
+                // Only the channel may shut itself down.
+                return;
+            }
+        }
+        // Queued picks wake for every new picker, so skip updates which change
+        // nothing.
+        let unchanged_picker = self
+            .picker
+            .cur()
+            .is_some_and(|picker| Arc::ptr_eq(&picker, &update.picker));
+        if !unchanged_picker {
+            self.picker.update(update.picker);
+        }
+        self.connectivity_state
+            .update_if_changed(update.connectivity_state);
     }
 
     fn request_resolution(&mut self) {
-        self.resolve_now.notify_one();
+        match self.resolution_throttle.request(Instant::now()) {
+            Throttled::Now => {
+                let _ = self.wqtx.send(WorkQueueItem::ResolveNow(None));
+            }
+            Throttled::After(delay) => {
+                let wqtx = self.wqtx.clone();
+                let sleep = self.runtime.sleep(delay);
+                // The task is detached; if the channel goes away first, the send
+                // fails harmlessly.
+                let _ = self.runtime.spawn(Box::pin(async move {
+                    sleep.await;
+                    let _ = wqtx.send(WorkQueueItem::Closure(
+                        WorkItemKind::ResolverUpdate,
+                        Box::new(|c: &mut InternalChannelController| {
+                            c.resolution_throttle.fire(Instant::now());
+                            let _ = c.wqtx.send(WorkQueueItem::ResolveNow(None));
+                        }),
+                    ));
+                }));
+            }
+            Throttled::Pending => {}
+        }
     }
 }
 
@@ -456,102 +1717,207 @@ impl load_balancing::ChannelController for InternalChannelController {
 pub(super) struct GracefulSwitchBalancer {
     pub(super) policy: Mutex<Option<Box<dyn LbPolicy>>>,
     policy_builder: Mutex<Option<Arc<dyn LbPolicyBuilder>>>,
+    // The JSON config of the policy, if the service config selected it.
+    policy_config: Mutex<Option<String>>,
+    // Incremented whenever a policy is built.  Subchannels record the
+    // generation of the policy which created them, so that their updates are
+    // never delivered to a policy which replaced it.
+    generation: AtomicU64,
     work_scheduler: WorkQueueTx,
     pending: Mutex<bool>,
     runtime: Arc<dyn Runtime>,
+    shut_down: AtomicBool,
+}
+
+// The work scheduler of the channel's LB policy.  The balancer owns the
+// policy, so this only holds a weak reference to it; otherwise the two would
+// keep each other alive after the channel is dropped.
+struct PolicyWorkScheduler {
+    lb: Weak<GracefulSwitchBalancer>,
+    runtime: Arc<dyn Runtime>,
+}
+
+impl WorkScheduler for PolicyWorkScheduler {
+    fn schedule_work(&self) {
+        if let Some(lb) = self.lb.upgrade() {
+            lb.schedule_work();
+        }
+    }
+
+    fn schedule_work_after(&self, delay: Duration) -> ScheduledWork {
+        let lb = self.lb.clone();
+        ScheduledWork::spawn(&*self.runtime, delay, move || {
+            if let Some(lb) = lb.upgrade() {
+                lb.schedule_work();
+            }
+        })
+    }
 }
 
-impl WorkScheduler for GracefulSwitchBalancer {
+impl GracefulSwitchBalancer {
     fn schedule_work(&self) {
         if mem::replace(&mut *self.pending.lock().unwrap(), true) {
             // Already had a pending call scheduled.
             return;
         }
-        let _ = self.work_scheduler.send(WorkQueueItem::Closure(Box::new(
-            |c: &mut InternalChannelController| {
+        let _ = self.work_scheduler.send(WorkQueueItem::Closure(
+            WorkItemKind::Work,
+            Box::new(|c: &mut InternalChannelController| {
                 *c.lb.pending.lock().unwrap() = false;
-                c.lb.clone()
-                    .policy
-                    .lock()
-                    .unwrap()
-                    .as_mut()
-                    .unwrap()
-                    .work(c);
-            },
-        )));
+                if let Some(p) = c.lb.clone().policy.lock().unwrap().as_mut() {
+                    p.work(c);
+                }
+            }),
+        ));
     }
-}
 
-impl GracefulSwitchBalancer {
     fn new(work_scheduler: WorkQueueTx, runtime: Arc<dyn Runtime>) -> Self {
         Self {
             policy_builder: Mutex::default(),
+            policy_config: Mutex::default(),
             policy: Mutex::default(), // new(None::<Box<dyn LbPolicy>>),
+            generation: AtomicU64::new(0),
             work_scheduler,
             pending: Mutex::default(),
             runtime,
+            shut_down: AtomicBool::new(false),
         }
     }
 
+    // Drops the LB policy.  No further updates are delivered to a policy after
+    // shutdown.
+    fn shutdown(&self) {
+        self.shut_down.store(true, Ordering::Release);
+        let policy = self.policy.lock().unwrap().take();
+        drop(policy);
+    }
+
+    // Delivers update to the policy chosen by the service config, or to
+    // pick_first if it chose none, replacing the current policy if it differs.
     fn handle_resolver_update(
         self: &Arc<Self>,
         update: ResolverUpdate,
+        lb_policy: Option<LbPolicySelection>,
         controller: &mut InternalChannelController,
     ) -> Result<(), Box<dyn Error + Send + Sync>> {
-        if update.service_config.as_ref().is_ok_and(|sc| sc.is_some()) {
-            return Err("can't do service configs yet".into());
+        if self.shut_down.load(Ordering::Acquire) {
+            return Err("channel is shut down".into());
         }
-        let policy_name = pick_first::POLICY_NAME;
+        let (builder, config, json) = match lb_policy {
+            Some(selection) => (
+                selection.builder,
+                selection.config,
+                Some(selection.json.to_string()),
+            ),
+            None => (
+                controller
+                    .lb_policy_registry
+                    .get_policy_or_global(pick_first::POLICY_NAME)
+                    .ok_or("pick_first is not registered")?,
+                None,
+                None,
+            ),
+        };
+        let policy_name = builder.name();
         let mut p = self.policy.lock().unwrap();
-        if p.is_none() {
-            let builder = GLOBAL_LB_REGISTRY.get_policy(policy_name).unwrap();
+        let switching = self
+            .policy_builder
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|b| b.name() != policy_name);
+        if p.is_none() || switching {
+            // Replacing the policy drops the old one, which shuts it down.
+            // TODO: keep the old policy until the new one is ready.
             let newpol = builder.build(LbPolicyOptions {
-                work_scheduler: self.clone(),
+                work_scheduler: Arc::new(PolicyWorkScheduler {
+                    lb: Arc::downgrade(self),
+                    runtime: self.runtime.clone(),
+                }),
                 runtime: self.runtime.clone(),
             });
             *self.policy_builder.lock().unwrap() = Some(builder);
             *p = Some(newpol);
+            self.generation.fetch_add(1, Ordering::AcqRel);
         }
+        *self.policy_config.lock().unwrap() = json;
 
-        // TODO: config should come from ServiceConfig.
-        let builder = self.policy_builder.lock().unwrap();
-        let config = match builder
-            .as_ref()
+        p.as_mut()
             .unwrap()
-            .parse_config(&ParsedJsonLbConfig::from_value(
-                json!({"shuffleAddressList": true, "unknown_field": false}),
-            )) {
-            Ok(cfg) => cfg,
-            Err(e) => {
-                return Err(e);
-            }
-        };
+            .resolver_update(update, config.as_deref(), controller)
+    }
 
-        p.as_mut()
+    // Returns the name of the current LB policy, if any.
+    fn policy_name(&self) -> Option<&'static str> {
+        self.policy_builder
+            .lock()
             .unwrap()
-            .resolver_update(update, config.as_ref(), controller)
+            .as_ref()
+            .map(|b| b.name())
+    }
 
-        // TODO: close old LB policy gracefully vs. drop?
+    // Clears the poison left by a policy callback which panicked while the
+    // policy was locked, so that the channel can keep using the policy.
+    pub(super) fn clear_poison(&self) {
+        self.policy.clear_poison();
+        self.policy_builder.clear_poison();
+        self.policy_config.clear_poison();
+        self.pending.clear_poison();
+    }
+
+    pub(super) fn exit_idle(&self, channel_controller: &mut dyn load_balancing::ChannelController) {
+        if let Some(p) = self.policy.lock().unwrap().as_mut() {
+            p.exit_idle(channel_controller);
+        }
     }
+
+    // Returns the generation of the current policy, which subchannels created
+    // now belong to.
+    pub(super) fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    // Delivers the update of a subchannel to the policy of generation, which
+    // created it.  The update is dropped if that policy was replaced.
     pub(super) fn subchannel_update(
         &self,
+        generation: u64,
         subchannel: Arc<dyn Subchannel>,
         state: &SubchannelState,
         channel_controller: &mut dyn load_balancing::ChannelController,
     ) {
         let mut p = self.policy.lock().unwrap();
-
-        p.as_mut()
-            .unwrap()
-            .subchannel_update(subchannel, state, channel_controller);
+        if generation != self.generation() {
+            return;
+        }
+        if let Some(p) = p.as_mut() {
+            p.subchannel_update(subchannel, state, channel_controller);
+        }
     }
 }
 
 pub(super) enum WorkQueueItem {
     // Execute the closure.
-    Closure(Box<dyn FnOnce(&mut InternalChannelController) + Send + Sync>),
+    Closure(
+        WorkItemKind,
+        Box<dyn FnOnce(&mut InternalChannelController) + Send + Sync>,
+    ),
     // Call the resolver to do work.
     ScheduleResolver,
+    // Ask the resolver to re-resolve, and report the result of the next update
+    // to the sender, if any.
+    ResolveNow(Option<oneshot::Sender<Result<(), String>>>),
+}
+
+impl WorkQueueItem {
+    fn kind(&self) -> WorkItemKind {
+        match self {
+            WorkQueueItem::Closure(kind, _) => *kind,
+            WorkQueueItem::ScheduleResolver | WorkQueueItem::ResolveNow(_) => {
+                WorkItemKind::ResolverUpdate
+            }
+        }
+    }
 }
 
 pub struct TODO;
@@ -588,6 +1954,20 @@ impl<T: Clone> Watcher<T> {
     }
 }
 
+impl<T: Clone + PartialEq> Watcher<T> {
+    // Updates the value unless it is equal to the current value, so consumers
+    // are not woken without a change.
+    fn update_if_changed(&self, item: T) {
+        self.tx.send_if_modified(|cur| {
+            if cur.as_ref() == Some(&item) {
+                return false;
+            }
+            *cur = Some(item);
+            true
+        });
+    }
+}
+
 pub(crate) struct WatcherIter<T> {
     rx: watch::Receiver<Option<T>>,
 }
@@ -607,3 +1987,1537 @@ impl<T: Clone> WatcherIter<T> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+    use tokio::sync::oneshot;
+    use tokio_stream::StreamExt;
+    use tonic::{async_trait, Code, Status};
+
+    use std::{
+        any::Any,
+        error::Error,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex, Weak,
+        },
+        time::{Duration, Instant},
+    };
+
+    use serde::Deserialize;
+
+    use super::{
+        Channel, ChannelError, ChannelOptions, ResolverUpdateLimits, WaitForReady, Watcher,
+    };
+    use crate::{
+        attributes::{AttributeKey, Attributes},
+        client::{
+            deadline::CallPhase,
+            error::{ConnectError, DisconnectReason, ErrorCategory, ResolveErrorKind},
+            fault_injection::{FaultAbort, FaultInjection, FaultInjectionPolicy},
+            load_balancing::{
+                self, pick_first, test_utils::new_request, LbPolicy, LbPolicyBuilder,
+                LbPolicyOptions, LbPolicyRegistry, LbState, ParsedJsonLbConfig, PickResult, Picker,
+                Subchannel, SubchannelState, GLOBAL_LB_REGISTRY,
+            },
+            name_resolution::{
+                global_registry, testing::StaticResolverBuilder, Address, Endpoint,
+                ResolverRegistry, ResolverUpdate,
+            },
+            resolution_cache::ResolutionCache,
+            service_config::LbConfig,
+            transport::{
+                ConnectedTransport, SecurityLevel, Transport, TransportInfo, TransportOptions,
+                TransportRegistry, GLOBAL_TRANSPORT_REGISTRY,
+            },
+            ConnectivityState,
+        },
+        inmemory,
+        rt::Runtime,
+        server::Server,
+        service::{status_response, Message, Request, Response, Service},
+    };
+
+    fn update_with(addresses_per_endpoint: &[usize]) -> ResolverUpdate {
+        let endpoints = addresses_per_endpoint
+            .iter()
+            .map(|&n| Endpoint {
+                addresses: (0..n)
+                    .map(|i| Address {
+                        address: format!("127.0.0.1:{i}").into(),
+                        ..Default::default()
+                    })
+                    .collect(),
+                ..Default::default()
+            })
+            .collect();
+        ResolverUpdate {
+            endpoints: Ok(endpoints),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn resolver_update_limits() {
+        struct TestCase {
+            limits: ResolverUpdateLimits,
+            update: ResolverUpdate,
+            want_err: bool,
+        }
+        let test_cases = vec![
+            TestCase {
+                limits: ResolverUpdateLimits::default(),
+                update: update_with(&[100; 100]),
+                want_err: false,
+            },
+            TestCase {
+                limits: ResolverUpdateLimits::default().max_endpoints(2),
+                update: update_with(&[1, 1]),
+                want_err: false,
+            },
+            TestCase {
+                limits: ResolverUpdateLimits::default().max_endpoints(2),
+                update: update_with(&[1, 1, 1]),
+                want_err: true,
+            },
+            TestCase {
+                limits: ResolverUpdateLimits::default().max_addresses_per_endpoint(2),
+                update: update_with(&[1, 3]),
+                want_err: true,
+            },
+            TestCase {
+                limits: ResolverUpdateLimits::default().max_total_addresses(4),
+                update: update_with(&[2, 2]),
+                want_err: false,
+            },
+            TestCase {
+                limits: ResolverUpdateLimits::default().max_total_addresses(4),
+                update: update_with(&[2, 2, 1]),
+                want_err: true,
+            },
+            TestCase {
+                limits: ResolverUpdateLimits::default().max_endpoints(0),
+                update: ResolverUpdate {
+                    endpoints: Err("resolver error".into()),
+                    ..Default::default()
+                },
+                want_err: false,
+            },
+        ];
+        for tc in test_cases {
+            assert_eq!(tc.limits.check(&tc.update).is_err(), tc.want_err);
+        }
+    }
+
+    #[tokio::test]
+    async fn graceful_stop_is_terminal() {
+        let channel = Channel::new("dns:///localhost:1234", None, ChannelOptions::default());
+        let clone = channel.clone();
+        assert_eq!(channel.state(), ConnectivityState::Idle);
+        clone.graceful_stop();
+        assert_eq!(channel.state(), ConnectivityState::Shutdown);
+        assert!(matches!(
+            channel.connect().await,
+            Err(ChannelError::Shutdown)
+        ));
+        assert_eq!(channel.state(), ConnectivityState::Shutdown);
+
+        let response = channel.call("/svc/method".to_string(), new_request()).await;
+        let status = response.into_inner().next().await.unwrap().unwrap_err();
+        assert_eq!(status.code(), Code::Cancelled);
+    }
+
+    #[tokio::test]
+    async fn invalid_targets_fail_calls() {
+        let channel = Channel::new("not a target", None, ChannelOptions::default());
+        assert_eq!(channel.state(), ConnectivityState::Idle);
+        let Err(ChannelError::Resolve(err)) = channel.connect().await else {
+            panic!("connecting to an invalid target succeeded");
+        };
+        assert_eq!(err.kind(), ResolveErrorKind::InvalidTarget);
+        let response = channel.call("/svc/method".to_string(), new_request()).await;
+        let status = response.into_inner().next().await.unwrap().unwrap_err();
+        assert_eq!(status.code(), Code::Unavailable);
+        assert!(status.message().contains("not a target"), "{status:?}");
+        assert_eq!(channel.debug_state().await.target, "not a target");
+    }
+
+    // Resolves to no endpoints, which pick_first rejects, on creation and, if
+    // respond is set, on each resolve_now.
+    fn counting_channel(scheme: &'static str, respond: bool) -> (Channel, StaticResolverBuilder) {
+        let mut resolver = StaticResolverBuilder::new(scheme, []);
+        if respond {
+            resolver = resolver.reresolve();
+        }
+        global_registry().add_builder(Box::new(resolver.clone()));
+        let channel = Channel::new(
+            &format!("{scheme}:///target"),
+            None,
+            ChannelOptions::default(),
+        );
+        (channel, resolver)
+    }
+
+    #[tokio::test]
+    async fn reresolve_now_reports_update_result() {
+        let (channel, resolver) = counting_channel("reresolve-responds", true);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        for i in 1..=2 {
+            let err = channel.reresolve_now(deadline).await.unwrap_err();
+            assert!(matches!(err, ChannelError::Resolve(_)), "{err}");
+            assert!(err.to_string().contains("no endpoints"), "{err}");
+            assert_eq!(resolver.resolve_now_calls(), i);
+        }
+    }
+
+    #[tokio::test]
+    async fn reresolve_now_times_out() {
+        let (channel, resolver) = counting_channel("reresolve-silent", false);
+        // The initial update is processed before the request, so nothing
+        // satisfies it.
+        let deadline = Instant::now() + Duration::from_millis(50);
+        let err = channel.reresolve_now(deadline).await.unwrap_err();
+        assert!(matches!(err, ChannelError::DeadlineExceeded(_)), "{err}");
+        assert_eq!(resolver.resolve_now_calls(), 1);
+
+        channel.graceful_stop();
+        let err = channel.reresolve_now(deadline).await.unwrap_err();
+        assert!(matches!(err, ChannelError::Shutdown), "{err}");
+    }
+
+    // Never produces an update, so the channel stays connecting.
+    fn silent_resolver() -> Box<StaticResolverBuilder> {
+        Box::new(StaticResolverBuilder::new("deadline-silent", []).service_configs([]))
+    }
+
+    #[tokio::test]
+    async fn queued_calls_fail_when_channel_idles_or_stops() {
+        global_registry().add_builder(silent_resolver());
+        let channel = Channel::new("deadline-silent:///target", None, ChannelOptions::default());
+        let queued_call = |channel: &Channel| {
+            let channel = channel.clone();
+            tokio::spawn(async move {
+                let mut request = new_request();
+                request.extensions_mut().insert(WaitForReady(true));
+                let response = channel.call("/svc/method".to_string(), request).await;
+                response.into_inner().next().await.unwrap().unwrap_err()
+            })
+        };
+
+        let call = queued_call(&channel);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!call.is_finished());
+        channel.enter_idle();
+        let status = call.await.unwrap();
+        assert_eq!(status.code(), Code::Unavailable, "{status}");
+
+        let call = queued_call(&channel);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!call.is_finished());
+        channel.graceful_stop();
+        let status = call.await.unwrap();
+        assert_eq!(status.code(), Code::Cancelled, "{status}");
+    }
+
+    #[tokio::test]
+    async fn service_config_timeout_applied() {
+        global_registry().add_builder(silent_resolver());
+        let options = ChannelOptions::default().default_service_config(
+            r#"{"methodConfig":[{"name":[{"service":"svc"}],"timeout":"0.05s"}]}"#.to_string(),
+        );
+        let channel = Channel::new("deadline-silent:///target", None, options);
+
+        let start = Instant::now();
+        let response = channel.call("/svc/method".to_string(), new_request()).await;
+        let status = response.into_inner().next().await.unwrap().unwrap_err();
+        assert_eq!(status.code(), Code::DeadlineExceeded);
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        // A shorter timeout set by the caller takes precedence.
+        let mut request = new_request();
+        request.set_timeout(Duration::from_millis(10));
+        let response = channel.call("/svc/method".to_string(), request).await;
+        let status = response.into_inner().next().await.unwrap().unwrap_err();
+        assert_eq!(status.code(), Code::DeadlineExceeded);
+
+        let stats = channel.deadline_stats().get("/svc/method");
+        assert_eq!(stats.calls, 2);
+        assert_eq!(channel.deadline_stats().get("/other/method").calls, 0);
+    }
+
+    #[tokio::test]
+    async fn fault_injection_aborts_calls() {
+        global_registry().add_builder(silent_resolver());
+        let faults = FaultInjection::new(
+            FaultInjectionPolicy::default().abort(FaultAbort::fixed(Code::Unavailable, 1_000_000)),
+        );
+        let options = ChannelOptions::default().fault_injection(Arc::new(faults));
+        let channel = Channel::new("deadline-silent:///target", None, options);
+
+        // The call fails without waiting for the silent resolver.
+        let response = channel.call("/svc/method".to_string(), new_request()).await;
+        let status = response.into_inner().next().await.unwrap().unwrap_err();
+        assert_eq!(status.code(), Code::Unavailable);
+    }
+
+    #[tokio::test]
+    async fn deadline_exceeded_while_connecting() {
+        global_registry().add_builder(silent_resolver());
+        let channel = Channel::new("deadline-silent:///target", None, ChannelOptions::default());
+
+        let mut request = new_request();
+        request.set_timeout(Duration::from_millis(50));
+        let response = channel.call("/svc/method".to_string(), request).await;
+        let status = response.into_inner().next().await.unwrap().unwrap_err();
+        assert_eq!(status.code(), Code::DeadlineExceeded);
+
+        let stats = channel.deadline_stats().get("/svc/method");
+        assert_eq!(stats.calls, 1);
+        assert_eq!(stats.timeouts(CallPhase::Connecting), 1);
+        assert_eq!(stats.timeouts(CallPhase::Queuing), 0);
+        assert_eq!(stats.timeouts(CallPhase::Server), 0);
+    }
+
+    // Resolves to a single address whose network type is the scheme, so each
+    // test can register its own transport.
+    fn single_address_resolver(scheme: &'static str) -> StaticResolverBuilder {
+        let endpoint = Endpoint::builder()
+            .addresses([Address::new(scheme, "backend")])
+            .build()
+            .unwrap();
+        StaticResolverBuilder::new(scheme, [endpoint])
+    }
+
+    #[tokio::test]
+    async fn endpoints_may_mix_address_types() {
+        let scheme = "mixed-addresses";
+        let resolvers = ResolverRegistry::new();
+        // The first address has a network type without a transport.
+        let endpoint = Endpoint::builder()
+            .addresses([
+                Address::new("unregistered", "backend"),
+                Address::new(scheme, "backend"),
+            ])
+            .build()
+            .unwrap();
+        resolvers.add_builder(Box::new(StaticResolverBuilder::new(scheme, [endpoint])));
+        let transports = TransportRegistry::new();
+        transports.add_transport(
+            scheme,
+            FlakyTransport {
+                failures: AtomicUsize::new(0),
+            },
+        );
+        let options = ChannelOptions::default()
+            .name_resolver_registry(resolvers)
+            .transport_registry(transports);
+        let channel = Channel::new(&format!("{scheme}:///target"), None, options);
+
+        // pick_first fails to connect to the address without a transport, and
+        // falls back to the next one.
+        channel.connect().await.unwrap();
+        let ready: Vec<_> = channel
+            .debug_state()
+            .await
+            .subchannels
+            .into_iter()
+            .filter(|sc| sc.connectivity_state == ConnectivityState::Ready)
+            .map(|sc| sc.network_type)
+            .collect();
+        assert_eq!(ready, [scheme]);
+        let response = channel.call("/svc/method".to_string(), new_request()).await;
+        assert!(response.into_inner().next().await.unwrap().is_ok());
+    }
+
+    const TEST_ARG: AttributeKey<String> = AttributeKey::new("test.resolver_arg");
+
+    #[tokio::test]
+    async fn resolver_options_carry_channel_settings() {
+        let scheme = "resolver-options";
+        let resolver = single_address_resolver(scheme);
+        let resolvers = ResolverRegistry::new();
+        resolvers.add_builder(Box::new(resolver.clone()));
+        let transports = TransportRegistry::new();
+        transports.add_transport(
+            scheme,
+            FlakyTransport {
+                failures: AtomicUsize::new(0),
+            },
+        );
+        let options = || {
+            ChannelOptions::default()
+                .name_resolver_registry(resolvers.clone())
+                .transport_registry(transports.clone())
+        };
+
+        let channel =
+            Channel::try_new(&format!("{scheme}://host:1234/target"), None, options()).unwrap();
+        channel.connect().await.unwrap();
+        let options = options()
+            .override_authority("override.example.com".to_string())
+            .resolver_args(Attributes::default().add(&TEST_ARG, "value".to_string()));
+        let channel = Channel::try_new(&format!("{scheme}:///target"), None, options).unwrap();
+        channel.connect().await.unwrap();
+        let seen: Vec<_> = resolver
+            .built_with()
+            .into_iter()
+            .map(|(authority, args)| (authority, args.get(&TEST_ARG).cloned()))
+            .collect();
+        assert_eq!(
+            seen,
+            [
+                ("host:1234".to_string(), None),
+                (
+                    "override.example.com".to_string(),
+                    Some("value".to_string())
+                )
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn resolution_cache_reuses_updates() {
+        let scheme = "resolution-cache";
+        GLOBAL_TRANSPORT_REGISTRY.add_transport(
+            scheme,
+            RefusingTransport {
+                refusals: 0,
+                failures: 0,
+                calls: Arc::default(),
+            },
+        );
+        // Only the first resolver built produces an update.
+        global_registry().add_builder(Box::new(single_address_resolver(scheme).first_only()));
+        let cache = Arc::new(ResolutionCache::new(Duration::from_secs(60)));
+        let options = || ChannelOptions::default().resolution_cache(cache.clone());
+        let target = format!("{scheme}:///target");
+        let call = |channel: Channel| async move {
+            let mut request = bytes_request("hello");
+            request.set_timeout(Duration::from_secs(1));
+            response_completes_request(&channel, "/svc/method", request).await
+        };
+
+        assert!(call(Channel::new(&target, None, options())).await);
+        // Only the cache provides an update for later channels.
+        assert!(call(Channel::new(&target, None, options())).await);
+
+        cache.invalidate(&target);
+        assert!(!call(Channel::new(&target, None, options())).await);
+    }
+
+    // Refuses the streams of the first calls with REFUSED_STREAM, and echoes
+    // the requests of later calls.
+    struct RefusingTransport {
+        refusals: usize,
+        failures: usize,
+        calls: Arc<AtomicUsize>,
+    }
+
+    struct RefusingService {
+        refusals: usize,
+        failures: usize,
+        calls: Arc<AtomicUsize>,
+        _disconnect: oneshot::Sender<DisconnectReason>,
+    }
+
+    #[async_trait]
+    impl Transport for RefusingTransport {
+        async fn connect(
+            &self,
+            address: &Address,
+            _: Arc<dyn Runtime>,
+            _: &TransportOptions,
+        ) -> Result<ConnectedTransport, ConnectError> {
+            let (tx, rx) = oneshot::channel();
+            Ok(ConnectedTransport {
+                service: Box::new(RefusingService {
+                    refusals: self.refusals,
+                    failures: self.failures,
+                    calls: self.calls.clone(),
+                    _disconnect: tx,
+                }),
+                disconnection_listener: rx,
+                info: TransportInfo::new(
+                    "h2",
+                    SecurityLevel::NoSecurity,
+                    address.address.to_string(),
+                ),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl Service for RefusingService {
+        async fn call(&self, _: String, request: Request) -> Response {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            if call < self.refusals {
+                let mut status = Status::unavailable("refused");
+                status.set_source(Arc::new(h2::Error::from(h2::Reason::REFUSED_STREAM)));
+                return status_response(status);
+            }
+            if call < self.refusals + self.failures {
+                return status_response(Status::unavailable("failed by the server"));
+            }
+            Response::new(Box::pin(request.into_inner().map(Ok)))
+        }
+    }
+
+    fn refusing_channel(scheme: &'static str, refusals: usize) -> (Channel, Arc<AtomicUsize>) {
+        server_failing_channel(scheme, refusals, 0, ChannelOptions::default())
+    }
+
+    // Returns a channel whose first calls are refused refusals times, and then
+    // failed by the server failures times.
+    fn server_failing_channel(
+        scheme: &'static str,
+        refusals: usize,
+        failures: usize,
+        options: ChannelOptions,
+    ) -> (Channel, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        GLOBAL_TRANSPORT_REGISTRY.add_transport(
+            scheme,
+            RefusingTransport {
+                refusals,
+                failures,
+                calls: calls.clone(),
+            },
+        );
+        global_registry().add_builder(Box::new(single_address_resolver(scheme)));
+        let channel = Channel::new(&format!("{scheme}:///target"), None, options);
+        (channel, calls)
+    }
+
+    fn bytes_request(msg: &'static str) -> Request {
+        let msg: Box<dyn Message> = Box::new(Bytes::from_static(msg.as_bytes()));
+        Request::new(Box::pin(tokio_stream::once(msg)))
+    }
+
+    #[tokio::test]
+    async fn refused_calls_are_retried_transparently() {
+        let (channel, calls) = refusing_channel("refused-once", 1);
+        let response = channel
+            .call("/svc/method".to_string(), bytes_request("hello"))
+            .await;
+        let msg = response.into_inner().next().await.unwrap().unwrap();
+        let msg = (msg.as_ref() as &dyn Any).downcast_ref::<Bytes>().unwrap();
+        assert_eq!(msg, "hello");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn refused_calls_are_retried_once() {
+        let (channel, calls) = refusing_channel("refused-twice", 2);
+        let response = channel
+            .call("/svc/method".to_string(), bytes_request("hello"))
+            .await;
+        let status = response.into_inner().next().await.unwrap().unwrap_err();
+        assert_eq!(status.code(), Code::Unavailable);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    // Returns options retrying UNAVAILABLE calls to "svc" up to max_attempts
+    // times, throttled by a bucket of max_tokens.
+    fn retry_options(max_attempts: u32, max_tokens: u32) -> ChannelOptions {
+        ChannelOptions::default().default_service_config(format!(
+            r#"{{
+                "methodConfig": [{{
+                    "name": [{{"service": "svc"}}],
+                    "retryPolicy": {{
+                        "maxAttempts": {max_attempts},
+                        "initialBackoff": "0.01s",
+                        "maxBackoff": "0.01s",
+                        "backoffMultiplier": 1,
+                        "retryableStatusCodes": ["UNAVAILABLE"]
+                    }}
+                }}],
+                "retryThrottling": {{"maxTokens": {max_tokens}, "tokenRatio": 0.5}}
+            }}"#
+        ))
+    }
+
+    #[tokio::test]
+    async fn failed_calls_are_retried_by_policy() {
+        let (channel, calls) =
+            server_failing_channel("failed-retried", 0, 2, retry_options(3, 100));
+        assert!(response_completes(&channel, "/svc/method").await);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        // Methods without a retry policy are not retried.
+        let (channel, calls) = server_failing_channel("failed-other", 0, 2, retry_options(3, 100));
+        assert!(!response_completes(&channel, "/other/method").await);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Nor are calls past their maximum attempts.
+        let (channel, calls) =
+            server_failing_channel("failed-exhausted", 0, 2, retry_options(2, 100));
+        assert!(!response_completes(&channel, "/svc/method").await);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn failed_calls_throttle_retries() {
+        let (channel, calls) =
+            server_failing_channel("failed-throttled", 0, 3, retry_options(5, 4));
+        let throttler = channel
+            .inner
+            .service_config
+            .current()
+            .retry_throttler
+            .clone()
+            .unwrap();
+        // The first failure is retried, leaving 3 tokens; the second would
+        // leave the bucket half full, so the call fails.
+        assert!(!response_completes(&channel, "/svc/method").await);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(throttler.tokens(), 2.0);
+        // Retries stay throttled until calls succeed.
+        assert!(!response_completes(&channel, "/svc/method").await);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert_eq!(throttler.tokens(), 1.0);
+
+        // Successful calls to any method add tokens.
+        assert!(response_completes(&channel, "/other/method").await);
+        assert_eq!(throttler.tokens(), 1.5);
+        assert!(response_completes(&channel, "/svc/method").await);
+        assert_eq!(throttler.tokens(), 2.0);
+    }
+
+    // Delegates to inner, and replaces itself with replacement in the
+    // channel when it is first used.
+    struct ReplacingPicker {
+        inner: Arc<dyn Picker>,
+        watcher: Weak<Watcher<Arc<dyn Picker>>>,
+        replacement: Mutex<Option<Arc<dyn Picker>>>,
+    }
+
+    impl Picker for ReplacingPicker {
+        fn pick(&self, request: &Request) -> PickResult {
+            if let (Some(watcher), Some(replacement)) = (
+                self.watcher.upgrade(),
+                self.replacement.lock().unwrap().take(),
+            ) {
+                watcher.update(replacement);
+            }
+            self.inner.pick(request)
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_use_the_picker_updated_during_backoff() {
+        let (channel, calls) =
+            server_failing_channel("retry-new-picker", 0, 1, retry_options(2, 100));
+        channel.connect().await.unwrap();
+        let ac = channel
+            .inner
+            .active_channel
+            .lock()
+            .unwrap()
+            .clone()
+            .unwrap();
+        ac.picker.update(Arc::new(ReplacingPicker {
+            inner: ac.picker.cur().unwrap(),
+            watcher: Arc::downgrade(&ac.picker),
+            replacement: Mutex::new(Some(Arc::new(load_balancing::Failing {
+                error: "replaced".to_string(),
+            }))),
+        }));
+
+        // The first attempt is picked by the replaced picker and fails on the
+        // server; the retry fails with the replacement instead of reaching
+        // the server again.
+        assert!(!response_completes(&channel, "/svc/method").await);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    async fn response_completes(channel: &Channel, method: &str) -> bool {
+        response_completes_request(channel, method, bytes_request("hello")).await
+    }
+
+    async fn response_completes_request(channel: &Channel, method: &str, request: Request) -> bool {
+        let mut stream = channel.call(method.to_string(), request).await.into_inner();
+        while let Some(item) = stream.next().await {
+            if item.is_err() {
+                return false;
+            }
+        }
+        true
+    }
+
+    // Fails the first connection attempts, then connects to services which
+    // echo requests.
+    struct FlakyTransport {
+        failures: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Transport for FlakyTransport {
+        async fn connect(
+            &self,
+            address: &Address,
+            runtime: Arc<dyn Runtime>,
+            options: &TransportOptions,
+        ) -> Result<ConnectedTransport, ConnectError> {
+            let remaining = self.failures.load(Ordering::SeqCst);
+            if remaining > 0 {
+                self.failures.store(remaining - 1, Ordering::SeqCst);
+                return Err("connection refused".into());
+            }
+            RefusingTransport {
+                refusals: 0,
+                failures: 0,
+                calls: Arc::default(),
+            }
+            .connect(address, runtime, options)
+            .await
+        }
+    }
+
+    fn flaky_channel(scheme: &'static str, failures: usize, options: ChannelOptions) -> Channel {
+        GLOBAL_TRANSPORT_REGISTRY.add_transport(
+            scheme,
+            FlakyTransport {
+                failures: AtomicUsize::new(failures),
+            },
+        );
+        global_registry().add_builder(Box::new(single_address_resolver(scheme)));
+        Channel::new(&format!("{scheme}:///target"), None, options)
+    }
+
+    fn wait_for_ready_request(wait_for_ready: bool) -> Request {
+        let mut request = bytes_request("hello");
+        request
+            .extensions_mut()
+            .insert(WaitForReady(wait_for_ready));
+        request.set_timeout(Duration::from_secs(5));
+        request
+    }
+
+    #[tokio::test]
+    async fn fail_fast_calls_fail_in_transient_failure() {
+        let channel = flaky_channel("wfr-fail-fast", 1, ChannelOptions::default());
+        let response = channel
+            .call("/svc/method".to_string(), bytes_request("hello"))
+            .await;
+        let status = response.into_inner().next().await.unwrap().unwrap_err();
+        assert_eq!(status.code(), Code::Unavailable);
+    }
+
+    #[tokio::test]
+    async fn wait_for_ready_calls_queue_in_transient_failure() {
+        let channel = flaky_channel("wfr-per-call", 3, ChannelOptions::default());
+        assert!(
+            response_completes_request(&channel, "/svc/method", wait_for_ready_request(true)).await
+        );
+    }
+
+    #[tokio::test]
+    async fn wait_for_ready_from_service_config() {
+        let config =
+            r#"{"methodConfig":[{"name":[{"service":"svc"}],"waitForReady":true}]}"#.to_string();
+        let channel = flaky_channel(
+            "wfr-config",
+            3,
+            ChannelOptions::default().default_service_config(config.clone()),
+        );
+        assert!(response_completes(&channel, "/svc/method").await);
+
+        // The setting of the call takes precedence.
+        let channel = flaky_channel(
+            "wfr-config-override",
+            1,
+            ChannelOptions::default().default_service_config(config),
+        );
+        assert!(
+            !response_completes_request(&channel, "/svc/method", wait_for_ready_request(false))
+                .await
+        );
+    }
+
+    fn config_channel(
+        scheme: &'static str,
+        config: Option<&'static str>,
+        options: ChannelOptions,
+    ) -> Channel {
+        GLOBAL_TRANSPORT_REGISTRY.add_transport(
+            scheme,
+            FlakyTransport {
+                failures: AtomicUsize::new(0),
+            },
+        );
+        // Updates carry config as their service config, and re-resolution
+        // requests produce an update.
+        let resolver = single_address_resolver(scheme)
+            .service_configs([config])
+            .reresolve();
+        global_registry().add_builder(Box::new(resolver));
+        Channel::new(&format!("{scheme}:///target"), None, options)
+    }
+
+    fn method_timeout(channel: &Channel) -> Option<Duration> {
+        channel
+            .inner
+            .service_config
+            .current()
+            .config
+            .method_config("/svc/method")
+            .and_then(|mc| mc.timeout)
+    }
+
+    #[tokio::test]
+    async fn connect_waits_for_ready() {
+        let channel = config_channel("connect-ready", None, ChannelOptions::default());
+        assert_eq!(channel.state(), ConnectivityState::Idle);
+        channel.connect().await.unwrap();
+        assert_eq!(channel.state(), ConnectivityState::Ready);
+
+        // Connecting after entering idle starts a new active channel.
+        channel.enter_idle();
+        assert_eq!(channel.state(), ConnectivityState::Idle);
+        channel.connect().await.unwrap();
+        assert_eq!(channel.state(), ConnectivityState::Ready);
+    }
+
+    #[tokio::test]
+    async fn resolver_service_config_replaces_default() {
+        let options = || {
+            ChannelOptions::default()
+                .default_service_config(r#"{"methodConfig":[{"name":[{}],"timeout":"1s"}]}"#.into())
+        };
+        let channel = config_channel(
+            "sc-resolved",
+            Some(r#"{"methodConfig":[{"name":[{}],"timeout":"2s"}]}"#),
+            options(),
+        );
+        assert_eq!(method_timeout(&channel), Some(Duration::from_secs(1)));
+        let deadline = Instant::now() + Duration::from_secs(5);
+        channel.reresolve_now(deadline).await.unwrap();
+        assert_eq!(method_timeout(&channel), Some(Duration::from_secs(2)));
+
+        // The default config is used when the resolver provides none.
+        let channel = config_channel("sc-default", None, options());
+        channel.reresolve_now(deadline).await.unwrap();
+        assert_eq!(method_timeout(&channel), Some(Duration::from_secs(1)));
+    }
+
+    #[tokio::test]
+    async fn resolver_service_config_can_be_required() {
+        let options = ChannelOptions::default()
+            .default_service_config(r#"{"methodConfig":[{"name":[{}],"timeout":"1s"}]}"#.into())
+            .require_resolver_service_config(true);
+        let channel = config_channel("sc-required", None, options);
+        assert_eq!(method_timeout(&channel), None);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let err = channel.reresolve_now(deadline).await.unwrap_err();
+        assert!(err.to_string().contains("no service config"), "{err}");
+    }
+
+    // An LB policy which records the tags of the configs it receives, and
+    // otherwise behaves like pick_first.
+    struct TaggedBuilder {
+        tags: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[derive(Deserialize)]
+    struct TaggedConfig {
+        tag: String,
+    }
+
+    struct TaggedPolicy {
+        tags: Arc<Mutex<Vec<String>>>,
+        tag: String,
+        child: Box<dyn LbPolicy>,
+    }
+
+    // Adds the tag of its policy to the picks of its child as an "lb-token".
+    struct TaggingController<'a> {
+        inner: &'a mut dyn load_balancing::ChannelController,
+        tag: &'a str,
+    }
+
+    impl load_balancing::ChannelController for TaggingController<'_> {
+        fn new_subchannel(&mut self, address: &Address) -> Arc<dyn Subchannel> {
+            self.inner.new_subchannel(address)
+        }
+
+        fn update_picker(&mut self, update: LbState) {
+            self.inner.update_picker(LbState {
+                picker: Arc::new(TaggingPicker {
+                    tag: self.tag.to_string(),
+                    child: update.picker,
+                }),
+                ..update
+            });
+        }
+
+        fn request_resolution(&mut self) {
+            self.inner.request_resolution();
+        }
+    }
+
+    struct TaggingPicker {
+        tag: String,
+        child: Arc<dyn Picker>,
+    }
+
+    impl Picker for TaggingPicker {
+        fn pick(&self, request: &Request) -> PickResult {
+            match self.child.pick(request) {
+                PickResult::Pick(mut pick) => {
+                    pick.metadata.insert("lb-token", self.tag.parse().unwrap());
+                    PickResult::Pick(pick)
+                }
+                result => result,
+            }
+        }
+    }
+
+    impl LbPolicyBuilder for TaggedBuilder {
+        fn build(&self, options: LbPolicyOptions) -> Box<dyn LbPolicy> {
+            let child = GLOBAL_LB_REGISTRY
+                .get_policy(pick_first::POLICY_NAME)
+                .unwrap()
+                .build(options);
+            Box::new(TaggedPolicy {
+                tags: self.tags.clone(),
+                tag: String::new(),
+                child,
+            })
+        }
+
+        fn name(&self) -> &'static str {
+            "test_tagged"
+        }
+
+        fn parse_config(
+            &self,
+            config: &ParsedJsonLbConfig,
+        ) -> Result<Option<LbConfig>, Box<dyn Error + Send + Sync>> {
+            let config: TaggedConfig = config.convert_to()?;
+            Ok(Some(LbConfig::new(config.tag)))
+        }
+    }
+
+    impl LbPolicy for TaggedPolicy {
+        fn resolver_update(
+            &mut self,
+            update: ResolverUpdate,
+            config: Option<&LbConfig>,
+            channel_controller: &mut dyn load_balancing::ChannelController,
+        ) -> Result<(), Box<dyn Error + Send + Sync>> {
+            let tag = config
+                .and_then(|c| c.get::<String>())
+                .ok_or("missing tag")?;
+            self.tags.lock().unwrap().push(tag.clone());
+            self.tag = tag.clone();
+            let mut controller = TaggingController {
+                inner: channel_controller,
+                tag: &self.tag,
+            };
+            self.child.resolver_update(update, None, &mut controller)
+        }
+
+        fn subchannel_update(
+            &mut self,
+            subchannel: Arc<dyn Subchannel>,
+            state: &SubchannelState,
+            channel_controller: &mut dyn load_balancing::ChannelController,
+        ) {
+            let mut controller = TaggingController {
+                inner: channel_controller,
+                tag: &self.tag,
+            };
+            self.child
+                .subchannel_update(subchannel, state, &mut controller);
+        }
+
+        fn work(&mut self, channel_controller: &mut dyn load_balancing::ChannelController) {
+            let mut controller = TaggingController {
+                inner: channel_controller,
+                tag: &self.tag,
+            };
+            self.child.work(&mut controller);
+        }
+
+        fn exit_idle(&mut self, channel_controller: &mut dyn load_balancing::ChannelController) {
+            let mut controller = TaggingController {
+                inner: channel_controller,
+                tag: &self.tag,
+            };
+            self.child.exit_idle(&mut controller);
+        }
+    }
+
+    #[tokio::test]
+    async fn resolver_service_config_selects_lb_policy() {
+        let tags = Arc::new(Mutex::new(Vec::new()));
+        GLOBAL_LB_REGISTRY.add_builder(TaggedBuilder { tags: tags.clone() });
+        let channel = config_channel(
+            "sc-lb-policy",
+            Some(
+                r#"{"loadBalancingConfig":[{"unregistered":{}},{"test_tagged":{}},{"test_tagged":{"tag":"valid"}}]}"#,
+            ),
+            ChannelOptions::default(),
+        );
+        let deadline = Instant::now() + Duration::from_secs(5);
+        channel.reresolve_now(deadline).await.unwrap();
+        assert!(response_completes(&channel, "/svc/method").await);
+        assert_eq!(tags.lock().unwrap()[0], "valid");
+
+        // Configs without a usable policy are rejected.
+        let channel = config_channel(
+            "sc-no-lb-policy",
+            Some(r#"{"loadBalancingConfig":[{"unregistered":{}}]}"#),
+            ChannelOptions::default(),
+        );
+        let err = channel.reresolve_now(deadline).await.unwrap_err();
+        assert!(err.to_string().contains("no registered LB policy"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn per_channel_registries_are_consulted_first() {
+        let scheme = "per-channel-registries";
+        let resolvers = ResolverRegistry::new();
+        resolvers.add_builder(Box::new(single_address_resolver(scheme)));
+        let transports = TransportRegistry::new();
+        transports.add_transport(
+            scheme,
+            FlakyTransport {
+                failures: AtomicUsize::new(0),
+            },
+        );
+        let tags = Arc::new(Mutex::new(Vec::new()));
+        let policies = LbPolicyRegistry::new();
+        policies.add_builder(TaggedBuilder { tags: tags.clone() });
+        let options = ChannelOptions::default()
+            .name_resolver_registry(resolvers)
+            .transport_registry(transports)
+            .lb_policy_registry(policies)
+            .default_service_config(
+                r#"{"loadBalancingConfig":[{"test_tagged":{"tag":"local"}}]}"#.to_string(),
+            );
+
+        let channel = Channel::try_new(&format!("{scheme}:///target"), None, options).unwrap();
+        assert!(response_completes(&channel, "/svc/method").await);
+        assert_eq!(tags.lock().unwrap()[0], "local");
+        assert!(global_registry().get(scheme).is_none());
+        assert!(GLOBAL_TRANSPORT_REGISTRY.get_transport(scheme).is_err());
+    }
+
+    // An LB policy whose pickers fail every pick, dropping the calls instead
+    // when configured to.
+    struct FailingBuilder;
+
+    #[derive(Deserialize)]
+    struct FailingConfig {
+        drop: bool,
+    }
+
+    struct FailingPolicy;
+
+    struct FailingPicker {
+        drop: bool,
+    }
+
+    impl Picker for FailingPicker {
+        fn pick(&self, _: &Request) -> PickResult {
+            if self.drop {
+                PickResult::Drop(Status::resource_exhausted("dropped"))
+            } else {
+                PickResult::Fail(Status::internal("backends unreachable"))
+            }
+        }
+    }
+
+    impl LbPolicyBuilder for FailingBuilder {
+        fn build(&self, _: LbPolicyOptions) -> Box<dyn LbPolicy> {
+            Box::new(FailingPolicy)
+        }
+
+        fn name(&self) -> &'static str {
+            "test_failing"
+        }
+
+        fn parse_config(
+            &self,
+            config: &ParsedJsonLbConfig,
+        ) -> Result<Option<LbConfig>, Box<dyn Error + Send + Sync>> {
+            let config: FailingConfig = config.convert_to()?;
+            Ok(Some(LbConfig::new(config.drop)))
+        }
+    }
+
+    impl LbPolicy for FailingPolicy {
+        fn resolver_update(
+            &mut self,
+            _: ResolverUpdate,
+            config: Option<&LbConfig>,
+            channel_controller: &mut dyn load_balancing::ChannelController,
+        ) -> Result<(), Box<dyn Error + Send + Sync>> {
+            let drop = config.and_then(|c| c.get::<bool>()).ok_or("missing drop")?;
+            channel_controller.update_picker(LbState {
+                connectivity_state: ConnectivityState::TransientFailure,
+                picker: Arc::new(FailingPicker { drop: *drop }),
+            });
+            Ok(())
+        }
+
+        fn subchannel_update(
+            &mut self,
+            _: Arc<dyn Subchannel>,
+            _: &SubchannelState,
+            _: &mut dyn load_balancing::ChannelController,
+        ) {
+        }
+
+        fn work(&mut self, _: &mut dyn load_balancing::ChannelController) {}
+
+        fn exit_idle(&mut self, _: &mut dyn load_balancing::ChannelController) {}
+    }
+
+    fn failing_channel(scheme: &'static str, drop: bool) -> Channel {
+        let policies = LbPolicyRegistry::new();
+        policies.add_builder(FailingBuilder);
+        let config = format!(r#"{{"loadBalancingConfig":[{{"test_failing":{{"drop":{drop}}}}}]}}"#);
+        let options = ChannelOptions::default()
+            .lb_policy_registry(policies)
+            .default_service_config(config);
+        config_channel(scheme, None, options)
+    }
+
+    // Builds FailingPolicy instances, of which the first panics on its first
+    // resolver update.
+    struct PanickingBuilder {
+        builds: Arc<AtomicUsize>,
+    }
+
+    struct PanickingPolicy {
+        panic: bool,
+    }
+
+    impl LbPolicyBuilder for PanickingBuilder {
+        fn build(&self, _: LbPolicyOptions) -> Box<dyn LbPolicy> {
+            let builds = self.builds.fetch_add(1, Ordering::SeqCst);
+            Box::new(PanickingPolicy { panic: builds == 0 })
+        }
+
+        fn name(&self) -> &'static str {
+            "test_panicking"
+        }
+    }
+
+    impl LbPolicy for PanickingPolicy {
+        fn resolver_update(
+            &mut self,
+            update: ResolverUpdate,
+            _: Option<&LbConfig>,
+            channel_controller: &mut dyn load_balancing::ChannelController,
+        ) -> Result<(), Box<dyn Error + Send + Sync>> {
+            if self.panic {
+                panic!("policy bug");
+            }
+            FailingPolicy.resolver_update(update, Some(&LbConfig::new(false)), channel_controller)
+        }
+
+        fn subchannel_update(
+            &mut self,
+            _: Arc<dyn Subchannel>,
+            _: &SubchannelState,
+            _: &mut dyn load_balancing::ChannelController,
+        ) {
+        }
+
+        fn work(&mut self, _: &mut dyn load_balancing::ChannelController) {}
+
+        fn exit_idle(&mut self, _: &mut dyn load_balancing::ChannelController) {}
+    }
+
+    fn panicking_channel(scheme: &'static str, rebuild: bool) -> (Channel, Arc<AtomicUsize>) {
+        let builds = Arc::new(AtomicUsize::new(0));
+        let policies = LbPolicyRegistry::new();
+        policies.add_builder(PanickingBuilder {
+            builds: builds.clone(),
+        });
+        let options = ChannelOptions::default()
+            .lb_policy_registry(policies)
+            .default_service_config(
+                r#"{"loadBalancingConfig":[{"test_panicking":{}}]}"#.to_string(),
+            )
+            .rebuild_lb_policy_on_panic(rebuild);
+        (config_channel(scheme, None, options), builds)
+    }
+
+    #[tokio::test]
+    async fn panicking_lb_policies_fail_calls() {
+        let (channel, builds) = panicking_channel("panicking-lb", false);
+        // Wait for the first resolver update, so the calls cannot time out
+        // before reaching a picker.
+        assert!(channel.connect().await.is_err());
+        for _ in 0..2 {
+            let status = call_status(&channel, false).await;
+            assert_eq!(status.code(), Code::Unavailable);
+            assert!(status.message().contains("policy bug"), "{status:?}");
+        }
+        assert_eq!(channel.state(), ConnectivityState::TransientFailure);
+        assert_eq!(builds.load(Ordering::SeqCst), 1);
+        assert_eq!(channel.debug_state().await.callback_panics, 1);
+    }
+
+    #[tokio::test]
+    async fn panicking_lb_policies_are_rebuilt() {
+        let (channel, builds) = panicking_channel("panicking-lb-rebuilt", true);
+        assert!(channel.connect().await.is_err());
+        // Calls fail until the policy built from the requested resolver update
+        // produces its picker.
+        loop {
+            let status = call_status(&channel, false).await;
+            assert_eq!(status.code(), Code::Unavailable);
+            if status.message() == "backends unreachable" {
+                break;
+            }
+            assert!(status.message().contains("policy bug"), "{status:?}");
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        assert_eq!(builds.load(Ordering::SeqCst), 2);
+        assert_eq!(channel.debug_state().await.callback_panics, 1);
+    }
+
+    async fn call_status(channel: &Channel, wait_for_ready: bool) -> Status {
+        let mut request = bytes_request("hello");
+        request
+            .extensions_mut()
+            .insert(WaitForReady(wait_for_ready));
+        request.set_timeout(Duration::from_millis(100));
+        let response = channel.call("/svc/method".to_string(), request).await;
+        response.into_inner().next().await.unwrap().unwrap_err()
+    }
+
+    #[tokio::test]
+    async fn failed_picks_spare_wait_for_ready_calls() {
+        let channel = failing_channel("pick-fail", false);
+        let status = call_status(&channel, false).await;
+        assert_eq!(status.code(), Code::Unavailable);
+        assert_eq!(status.message(), "backends unreachable");
+        let status = call_status(&channel, true).await;
+        assert_eq!(status.code(), Code::DeadlineExceeded);
+    }
+
+    #[tokio::test]
+    async fn dropped_picks_fail_wait_for_ready_calls() {
+        let channel = failing_channel("pick-drop", true);
+        for wait_for_ready in [false, true] {
+            let status = call_status(&channel, wait_for_ready).await;
+            assert_eq!(status.code(), Code::ResourceExhausted);
+            assert_eq!(status.message(), "dropped");
+        }
+    }
+
+    #[tokio::test]
+    async fn debug_state_describes_the_channel() {
+        let channel = failing_channel("debug-state", false);
+        let state = channel.debug_state().await;
+        assert_eq!(state.target, "debug-state:///target");
+        assert_eq!(state.resolver_scheme, "debug-state");
+        assert_eq!(state.connectivity_state, ConnectivityState::Idle);
+        assert_eq!(state.lb_policy, None);
+
+        let queued = tokio::spawn({
+            let channel = channel.clone();
+            async move { call_status(&channel, true).await }
+        });
+        let state = loop {
+            let state = channel.debug_state().await;
+            if state.queued_calls == 1 {
+                break state;
+            }
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        };
+        assert_eq!(
+            state.connectivity_state,
+            ConnectivityState::TransientFailure
+        );
+        assert_eq!(state.lb_policy.as_deref(), Some("test_failing"));
+        assert_eq!(state.lb_config.as_deref(), Some(r#"{"drop":false}"#));
+        assert!(state.picker.unwrap().ends_with("FailingPicker"));
+        assert!(state.subchannels.is_empty());
+        queued.await.unwrap();
+        assert_eq!(channel.debug_state().await.queued_calls, 0);
+
+        let channel = flaky_channel("debug-state-sc", 0, ChannelOptions::default());
+        channel.connect().await.unwrap();
+        let state = channel.debug_state().await;
+        assert_eq!(state.lb_policy.as_deref(), Some(pick_first::POLICY_NAME));
+        assert_eq!(state.lb_config, None);
+        assert_eq!(state.subchannels.len(), 1);
+        assert_eq!(state.subchannels[0].network_type, "debug-state-sc");
+        assert_eq!(
+            state.subchannels[0].connectivity_state,
+            ConnectivityState::Ready
+        );
+        let text = state.to_string();
+        let lines: Vec<_> = text.lines().collect();
+        assert_eq!(
+            lines[0],
+            "channel for debug-state-sc:///target (debug-state-sc resolver): Ready"
+        );
+        assert_eq!(lines[1], "  lb policy: pick_first config: none");
+        assert!(lines[2].starts_with("  picker: "));
+        assert!(lines[2].ends_with("OneSubchannelPicker queued calls: 0 callback panics: 0"));
+        assert_eq!(lines[3], "  subchannel debug-state-sc:backend: Ready");
+        assert_eq!(lines.len(), 4);
+    }
+
+    #[test]
+    fn pick_results_compare_statuses() {
+        let fail = || PickResult::Fail(Status::unavailable("down"));
+        assert!(fail() == fail());
+        assert!(fail() != PickResult::Fail(Status::unavailable("other")));
+        assert!(fail() != PickResult::Drop(Status::unavailable("down")));
+        assert!(PickResult::Drop(Status::internal("x")) == PickResult::Drop(Status::internal("x")));
+    }
+
+    // An LB policy which behaves like pick_first, and records updates for
+    // subchannels it did not create.
+    struct OwnershipBuilder {
+        name: &'static str,
+        updates: Arc<AtomicUsize>,
+        foreign_updates: Arc<AtomicUsize>,
+    }
+
+    struct OwnershipPolicy {
+        owned: Vec<Arc<dyn Subchannel>>,
+        updates: Arc<AtomicUsize>,
+        foreign_updates: Arc<AtomicUsize>,
+        child: Box<dyn LbPolicy>,
+    }
+
+    // Records the subchannels created through it.
+    struct OwningController<'a> {
+        inner: &'a mut dyn load_balancing::ChannelController,
+        owned: &'a mut Vec<Arc<dyn Subchannel>>,
+    }
+
+    impl load_balancing::ChannelController for OwningController<'_> {
+        fn new_subchannel(&mut self, address: &Address) -> Arc<dyn Subchannel> {
+            let sc = self.inner.new_subchannel(address);
+            self.owned.push(sc.clone());
+            sc
+        }
+
+        fn update_picker(&mut self, update: LbState) {
+            self.inner.update_picker(update);
+        }
+
+        fn request_resolution(&mut self) {
+            self.inner.request_resolution();
+        }
+    }
+
+    impl LbPolicyBuilder for OwnershipBuilder {
+        fn build(&self, options: LbPolicyOptions) -> Box<dyn LbPolicy> {
+            let child = GLOBAL_LB_REGISTRY
+                .get_policy(pick_first::POLICY_NAME)
+                .unwrap()
+                .build(options);
+            Box::new(OwnershipPolicy {
+                owned: Vec::new(),
+                updates: self.updates.clone(),
+                foreign_updates: self.foreign_updates.clone(),
+                child,
+            })
+        }
+
+        fn name(&self) -> &'static str {
+            self.name
+        }
+    }
+
+    impl LbPolicy for OwnershipPolicy {
+        fn resolver_update(
+            &mut self,
+            update: ResolverUpdate,
+            _: Option<&LbConfig>,
+            channel_controller: &mut dyn load_balancing::ChannelController,
+        ) -> Result<(), Box<dyn Error + Send + Sync>> {
+            let mut controller = OwningController {
+                inner: channel_controller,
+                owned: &mut self.owned,
+            };
+            self.child.resolver_update(update, None, &mut controller)
+        }
+
+        fn subchannel_update(
+            &mut self,
+            subchannel: Arc<dyn Subchannel>,
+            state: &SubchannelState,
+            channel_controller: &mut dyn load_balancing::ChannelController,
+        ) {
+            self.updates.fetch_add(1, Ordering::SeqCst);
+            if !self
+                .owned
+                .iter()
+                .any(|sc| std::ptr::addr_eq(Arc::as_ptr(sc), Arc::as_ptr(&subchannel)))
+            {
+                self.foreign_updates.fetch_add(1, Ordering::SeqCst);
+            }
+            let mut controller = OwningController {
+                inner: channel_controller,
+                owned: &mut self.owned,
+            };
+            self.child
+                .subchannel_update(subchannel, state, &mut controller);
+        }
+
+        fn work(&mut self, channel_controller: &mut dyn load_balancing::ChannelController) {
+            let mut controller = OwningController {
+                inner: channel_controller,
+                owned: &mut self.owned,
+            };
+            self.child.work(&mut controller);
+        }
+
+        fn exit_idle(&mut self, channel_controller: &mut dyn load_balancing::ChannelController) {
+            let mut controller = OwningController {
+                inner: channel_controller,
+                owned: &mut self.owned,
+            };
+            self.child.exit_idle(&mut controller);
+        }
+    }
+
+    #[tokio::test]
+    async fn replaced_policies_subchannel_updates_are_dropped() {
+        let scheme = "switch-ordering";
+        let updates = Arc::new(AtomicUsize::new(0));
+        let foreign_updates = Arc::new(AtomicUsize::new(0));
+        let policies = LbPolicyRegistry::new();
+        for name in ["test_ownership_a", "test_ownership_b"] {
+            policies.add_builder(OwnershipBuilder {
+                name,
+                updates: updates.clone(),
+                foreign_updates: foreign_updates.clone(),
+            });
+        }
+        let resolvers = ResolverRegistry::new();
+        // Sends two updates for each resolution, with the first and second
+        // config.
+        let resolver = single_address_resolver(scheme).service_configs([
+            Some(r#"{"loadBalancingConfig":[{"test_ownership_a":{}}]}"#),
+            Some(r#"{"loadBalancingConfig":[{"test_ownership_b":{}}]}"#),
+        ]);
+        resolvers.add_builder(Box::new(resolver));
+        let transports = TransportRegistry::new();
+        transports.add_transport(
+            scheme,
+            FlakyTransport {
+                failures: AtomicUsize::new(0),
+            },
+        );
+        let options = ChannelOptions::default()
+            .name_resolver_registry(resolvers)
+            .transport_registry(transports)
+            .lb_policy_registry(policies);
+
+        // The first policy creates a subchannel, whose initial state is queued
+        // behind the update which replaces the policy.
+        let channel = Channel::try_new(&format!("{scheme}:///target"), None, options).unwrap();
+        assert!(response_completes(&channel, "/svc/method").await);
+        assert!(updates.load(Ordering::SeqCst) > 0);
+        assert_eq!(foreign_updates.load(Ordering::SeqCst), 0);
+    }
+
+    // Records the "lb-token" values of the requests it serves.
+    #[derive(Default)]
+    struct TokenRecorder {
+        tokens: Mutex<Vec<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl Service for Arc<TokenRecorder> {
+        async fn call(&self, _method: String, request: Request) -> Response {
+            let tokens = request
+                .metadata()
+                .get_all("lb-token")
+                .iter()
+                .map(|v| v.to_str().unwrap().to_string())
+                .collect();
+            self.tokens.lock().unwrap().push(tokens);
+            Response::new(Box::pin(tokio_stream::empty()))
+        }
+    }
+
+    #[tokio::test]
+    async fn pick_metadata_is_sent_after_application_metadata() {
+        inmemory::reg();
+        let lis = inmemory::Listener::new();
+        let recorder = Arc::new(TokenRecorder::default());
+        let mut srv = Server::new();
+        srv.set_handler(recorder.clone());
+        let srv = Arc::new(srv);
+        let serve = tokio::spawn({
+            let srv = srv.clone();
+            let lis = lis.clone();
+            async move { srv.serve(&lis).await }
+        });
+
+        let policies = LbPolicyRegistry::new();
+        policies.add_builder(TaggedBuilder {
+            tags: Arc::default(),
+        });
+        let options = ChannelOptions::default()
+            .lb_policy_registry(policies)
+            .default_service_config(
+                r#"{"loadBalancingConfig":[{"test_tagged":{"tag":"picked"}}]}"#.to_string(),
+            );
+        let channel = Channel::try_new(&lis.target(), None, options).unwrap();
+        assert!(response_completes(&channel, "/svc/method").await);
+        let mut request = bytes_request("hello");
+        request
+            .metadata_mut()
+            .insert("lb-token", "app".parse().unwrap());
+        assert!(response_completes_request(&channel, "/svc/method", request).await);
+        assert_eq!(
+            *recorder.tokens.lock().unwrap(),
+            [vec!["picked"], vec!["app", "picked"]]
+        );
+
+        srv.graceful_shutdown().await;
+        serve.await.unwrap();
+        lis.close().await;
+    }
+
+    #[test]
+    fn try_new_rejects_invalid_default_service_config() {
+        let options = ChannelOptions::default().default_service_config("{".to_string());
+        let Err(err) = Channel::try_new("dns:///target", None, options) else {
+            panic!("invalid default service config accepted");
+        };
+        assert!(matches!(err, ChannelError::InvalidConfig(_)), "{err}");
+        assert_eq!(err.category(), ErrorCategory::Configuration);
+    }
+
+    #[tokio::test]
+    async fn watcher_suppresses_unchanged_values() {
+        let watcher = super::Watcher::new();
+        let mut iter = watcher.iter();
+        watcher.update_if_changed(ConnectivityState::Connecting);
+        assert_eq!(iter.next().await, Some(ConnectivityState::Connecting));
+
+        watcher.update_if_changed(ConnectivityState::Connecting);
+        assert!(!iter.rx.has_changed().unwrap());
+        watcher.update_if_changed(ConnectivityState::Ready);
+        assert_eq!(iter.next().await, Some(ConnectivityState::Ready));
+    }
+
+    #[test]
+    fn pick_status_restricts_codes() {
+        let status = super::pick_status(Status::not_found("no such backend"));
+        assert_eq!(status.code(), Code::Internal);
+        assert!(status.message().contains("no such backend"));
+
+        let status = super::pick_status(Status::resource_exhausted("over quota"));
+        assert_eq!(status.code(), Code::ResourceExhausted);
+        assert_eq!(status.message(), "over quota");
+    }
+}
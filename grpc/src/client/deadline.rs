@@ -0,0 +1,413 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! Deadline statistics.
+//!
+//! A call's deadline is set by its `grpc-timeout` metadata, e.g. using
+//! `Request::set_timeout`.  For each method, the channel records how much of
+//! their deadline calls consumed, and for calls which fail with
+//! DEADLINE_EXCEEDED, the [`CallPhase`] the deadline expired in.  The phase
+//! is found from the times at which the call entered each phase, so that
+//! operators can tell whether to raise timeouts, add capacity or look at the
+//! servers.
+
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use tokio_stream::Stream;
+use tonic::{Code, Status};
+
+use crate::service::{Message, Request, Response};
+
+const GRPC_TIMEOUT_HEADER: &str = "grpc-timeout";
+
+/// A phase of a call on a channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CallPhase {
+    /// Waiting for capacity on the channel, or for a picker able to route the
+    /// call while the channel is not connecting.
+    Queuing,
+    /// Waiting for a picker able to route the call while the channel is
+    /// establishing connections.
+    Connecting,
+    /// Sent on a connection and waiting for the server.
+    Server,
+}
+
+/// The upper bounds, in percent of the deadline, of the buckets of a
+/// [`DeadlineHistogram`].
+pub const CONSUMED_BUCKETS_PERCENT: [u32; 6] = [10, 25, 50, 75, 90, 100];
+
+/// A histogram of the portion of their deadline calls consumed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DeadlineHistogram {
+    counts: [u64; CONSUMED_BUCKETS_PERCENT.len() + 1],
+}
+
+impl DeadlineHistogram {
+    /// Returns the number of calls in each bucket.  The count at index i is
+    /// the number of calls which consumed at most
+    /// `CONSUMED_BUCKETS_PERCENT[i]` percent of their deadline, and more than
+    /// the previous bound.  The last count is the number of calls which ran
+    /// past their deadline.
+    pub fn counts(&self) -> &[u64] {
+        &self.counts
+    }
+
+    fn record(&mut self, percent: f64) {
+        let bucket = CONSUMED_BUCKETS_PERCENT
+            .iter()
+            .position(|&bound| percent <= bound as f64)
+            .unwrap_or(CONSUMED_BUCKETS_PERCENT.len());
+        self.counts[bucket] += 1;
+    }
+}
+
+/// Deadline statistics of the calls to one method.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct MethodDeadlineStats {
+    /// The number of completed calls which had a deadline.
+    pub calls: u64,
+    /// The portion of their deadline the calls consumed.
+    pub consumed: DeadlineHistogram,
+    /// Calls which timed out while in the [`CallPhase::Queuing`] phase.
+    pub queuing_timeouts: u64,
+    /// Calls which timed out while in the [`CallPhase::Connecting`] phase.
+    pub connecting_timeouts: u64,
+    /// Calls which timed out while in the [`CallPhase::Server`] phase.
+    pub server_timeouts: u64,
+}
+
+impl MethodDeadlineStats {
+    /// Returns the number of calls which timed out while in phase.
+    pub fn timeouts(&self, phase: CallPhase) -> u64 {
+        match phase {
+            CallPhase::Queuing => self.queuing_timeouts,
+            CallPhase::Connecting => self.connecting_timeouts,
+            CallPhase::Server => self.server_timeouts,
+        }
+    }
+}
+
+/// Deadline statistics of a channel labeled by method.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeadlineStats {
+    by_method: HashMap<String, MethodDeadlineStats>,
+}
+
+impl DeadlineStats {
+    /// Returns the statistics of calls to method (e.g. "/pkg.Service/Method").
+    pub fn get(&self, method: &str) -> MethodDeadlineStats {
+        self.by_method.get(method).copied().unwrap_or_default()
+    }
+
+    /// Returns an iterator over the recorded methods and their statistics.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &MethodDeadlineStats)> {
+        self.by_method.iter().map(|(k, v)| (k.as_str(), v))
+    }
+}
+
+/// Records the deadline statistics of a channel's calls.
+#[derive(Default)]
+pub(crate) struct DeadlineStatsRecorder {
+    stats: Mutex<DeadlineStats>,
+}
+
+impl DeadlineStatsRecorder {
+    pub(crate) fn stats(&self) -> DeadlineStats {
+        self.stats.lock().unwrap().clone()
+    }
+
+    fn record(&self, method: &str, phases: &CallPhases, timed_out: bool, now: Instant) {
+        let Some(timeout) = phases.timeout else {
+            return;
+        };
+        let percent = if timeout.is_zero() {
+            f64::INFINITY
+        } else {
+            (now - phases.start).as_secs_f64() / timeout.as_secs_f64() * 100.0
+        };
+        let mut stats = self.stats.lock().unwrap();
+        let s = stats.by_method.entry(method.to_string()).or_default();
+        s.calls += 1;
+        s.consumed.record(percent);
+        if timed_out {
+            match phases.phase_at(phases.start + timeout) {
+                CallPhase::Queuing => s.queuing_timeouts += 1,
+                CallPhase::Connecting => s.connecting_timeouts += 1,
+                CallPhase::Server => s.server_timeouts += 1,
+            }
+        }
+    }
+}
+
+/// The deadline of a call and the times at which it entered each phase.
+pub(crate) struct CallPhases {
+    start: Instant,
+    timeout: Option<Duration>,
+    phases: Vec<(Instant, CallPhase)>,
+}
+
+impl CallPhases {
+    /// Starts timing a call in the Queuing phase.
+    pub(crate) fn start(request: &Request) -> Self {
+        let start = Instant::now();
+        let timeout = request_timeout(request);
+        Self {
+            start,
+            timeout,
+            phases: vec![(start, CallPhase::Queuing)],
+        }
+    }
+
+    /// Returns the call's deadline, if it has one.
+    pub(crate) fn deadline(&self) -> Option<Instant> {
+        self.timeout.map(|timeout| self.start + timeout)
+    }
+
+    /// Records that the call entered phase.
+    pub(crate) fn enter(&mut self, phase: CallPhase) {
+        if self.phases.last().map(|(_, p)| *p) != Some(phase) {
+            self.phases.push((Instant::now(), phase));
+        }
+    }
+
+    fn phase_at(&self, t: Instant) -> CallPhase {
+        self.phases
+            .iter()
+            .take_while(|(start, _)| *start <= t)
+            .last()
+            .map_or(CallPhase::Queuing, |(_, phase)| *phase)
+    }
+}
+
+/// Sets the timeout of request to timeout unless it already has an earlier
+/// one.
+pub(crate) fn apply_default_timeout(request: &mut Request, timeout: Duration) {
+    let current = request_timeout(request);
+    if current.is_none_or(|current| current > timeout) {
+        request.set_timeout(timeout);
+    }
+}
+
+/// Returns the timeout set by the `grpc-timeout` metadata of request, if any.
+pub(crate) fn request_timeout(request: &Request) -> Option<Duration> {
+    request
+        .metadata()
+        .get(GRPC_TIMEOUT_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_timeout)
+}
+
+// Parses a grpc-timeout value, e.g. "100m" for 100 milliseconds.
+fn parse_timeout(value: &str) -> Option<Duration> {
+    let (digits, unit) = value.split_at_checked(value.len().checked_sub(1)?)?;
+    if digits.is_empty() || digits.len() > 8 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let n: u64 = digits.parse().ok()?;
+    match unit {
+        "H" => Some(Duration::from_secs(n * 60 * 60)),
+        "M" => Some(Duration::from_secs(n * 60)),
+        "S" => Some(Duration::from_secs(n)),
+        "m" => Some(Duration::from_millis(n)),
+        "u" => Some(Duration::from_micros(n)),
+        "n" => Some(Duration::from_nanos(n)),
+        _ => None,
+    }
+}
+
+pin_project_lite::pin_project! {
+    // Records the deadline statistics of a call when its response stream
+    // completes.
+    struct DeadlineStream<S> {
+        #[pin]
+        inner: S,
+        call: Option<(String, CallPhases)>,
+        recorder: Arc<DeadlineStatsRecorder>,
+    }
+}
+
+impl<S: Stream<Item = Result<Box<dyn Message>, Status>>> Stream for DeadlineStream<S> {
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let item = this.inner.poll_next(cx);
+        let timed_out = match &item {
+            Poll::Ready(Some(Err(status))) => status.code() == Code::DeadlineExceeded,
+            Poll::Ready(None) => false,
+            _ => return item,
+        };
+        if let Some((method, phases)) = this.call.take() {
+            this.recorder
+                .record(&method, &phases, timed_out, Instant::now());
+        }
+        item
+    }
+}
+
+/// Records the deadline statistics of the call to method when the message
+/// stream of response completes.  Calls without a deadline are not recorded.
+pub(crate) fn record_on_complete(
+    response: Response,
+    method: String,
+    phases: CallPhases,
+    recorder: Arc<DeadlineStatsRecorder>,
+) -> Response {
+    if phases.timeout.is_none() {
+        return response;
+    }
+    response.map(|inner| {
+        Box::pin(DeadlineStream {
+            inner,
+            call: Some((method, phases)),
+            recorder,
+        }) as Pin<Box<dyn Stream<Item = Result<Box<dyn Message>, Status>> + Send>>
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+
+    use tokio_stream::StreamExt;
+    use tonic::{Code, Status};
+
+    use super::{
+        apply_default_timeout, parse_timeout, record_on_complete, CallPhase, CallPhases,
+        DeadlineStatsRecorder,
+    };
+    use crate::client::load_balancing::test_utils::new_request;
+    use crate::service::{status_response, Message, Response};
+
+    #[test]
+    fn parses_grpc_timeout() {
+        assert_eq!(parse_timeout("1H"), Some(Duration::from_secs(3600)));
+        assert_eq!(parse_timeout("2M"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_timeout("3S"), Some(Duration::from_secs(3)));
+        assert_eq!(parse_timeout("100m"), Some(Duration::from_millis(100)));
+        assert_eq!(parse_timeout("5u"), Some(Duration::from_micros(5)));
+        assert_eq!(parse_timeout("7n"), Some(Duration::from_nanos(7)));
+        assert_eq!(parse_timeout(""), None);
+        assert_eq!(parse_timeout("m"), None);
+        assert_eq!(parse_timeout("+1m"), None);
+        assert_eq!(parse_timeout("123456789m"), None);
+        assert_eq!(parse_timeout("10x"), None);
+    }
+
+    #[test]
+    fn default_timeout_only_shortens_deadlines() {
+        let mut request = new_request();
+        apply_default_timeout(&mut request, Duration::from_millis(100));
+        assert_eq!(
+            CallPhases::start(&request).timeout,
+            Some(Duration::from_millis(100))
+        );
+        apply_default_timeout(&mut request, Duration::from_millis(200));
+        assert_eq!(
+            CallPhases::start(&request).timeout,
+            Some(Duration::from_millis(100))
+        );
+        apply_default_timeout(&mut request, Duration::from_millis(50));
+        assert_eq!(
+            CallPhases::start(&request).timeout,
+            Some(Duration::from_millis(50))
+        );
+    }
+
+    fn phases_with_timeout(timeout: Duration) -> CallPhases {
+        let mut request = new_request();
+        request.set_timeout(timeout);
+        CallPhases::start(&request)
+    }
+
+    #[test]
+    fn timeouts_attributed_to_phase_at_deadline() {
+        let recorder = DeadlineStatsRecorder::default();
+        let mut phases = phases_with_timeout(Duration::from_millis(100));
+        let start = phases.start;
+        phases.phases = vec![
+            (start, CallPhase::Queuing),
+            (start + Duration::from_millis(20), CallPhase::Connecting),
+            (start + Duration::from_millis(60), CallPhase::Server),
+        ];
+        recorder.record("/svc/A", &phases, true, start + Duration::from_millis(150));
+        phases.phases.pop();
+        recorder.record("/svc/A", &phases, true, start + Duration::from_millis(100));
+        recorder.record("/svc/A", &phases, false, start + Duration::from_millis(20));
+
+        let stats = recorder.stats().get("/svc/A");
+        assert_eq!(stats.calls, 3);
+        assert_eq!(stats.timeouts(CallPhase::Server), 1);
+        assert_eq!(stats.timeouts(CallPhase::Connecting), 1);
+        assert_eq!(stats.timeouts(CallPhase::Queuing), 0);
+        // 20% of the deadline, exactly 100% of it, and past it.
+        assert_eq!(stats.consumed.counts(), &[0, 1, 0, 0, 0, 1, 1]);
+        assert_eq!(recorder.stats().get("/svc/B").calls, 0);
+    }
+
+    #[tokio::test]
+    async fn records_when_response_completes() {
+        let recorder = Arc::new(DeadlineStatsRecorder::default());
+
+        let mut phases = phases_with_timeout(Duration::from_secs(10));
+        phases.enter(CallPhase::Server);
+        let msg: Box<dyn Message> = Box::new(());
+        let response = Response::new(Box::pin(tokio_stream::once(Ok(msg))));
+        let response = record_on_complete(response, "/svc/A".to_string(), phases, recorder.clone());
+        assert_eq!(recorder.stats().get("/svc/A").calls, 0);
+        let results: Vec<_> = response.into_inner().collect().await;
+        assert_eq!(results.len(), 1);
+        let stats = recorder.stats().get("/svc/A");
+        assert_eq!(stats.calls, 1);
+        assert_eq!(stats.consumed.counts()[0], 1);
+
+        let mut phases = phases_with_timeout(Duration::from_secs(10));
+        phases.enter(CallPhase::Server);
+        let response = status_response(Status::deadline_exceeded("too slow"));
+        let response = record_on_complete(response, "/svc/A".to_string(), phases, recorder.clone());
+        let status = response.into_inner().next().await.unwrap().unwrap_err();
+        assert_eq!(status.code(), Code::DeadlineExceeded);
+        let stats = recorder.stats().get("/svc/A");
+        assert_eq!(stats.calls, 2);
+        assert_eq!(stats.timeouts(CallPhase::Server), 1);
+
+        // Calls without a deadline are not recorded.
+        let phases = CallPhases::start(&new_request());
+        let response = status_response(Status::deadline_exceeded("too slow"));
+        let response = record_on_complete(response, "/svc/B".to_string(), phases, recorder.clone());
+        let _: Vec<_> = response.into_inner().collect().await;
+        assert_eq!(recorder.stats().get("/svc/B").calls, 0);
+    }
+}
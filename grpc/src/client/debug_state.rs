@@ -0,0 +1,102 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! A snapshot of a channel's configuration and state, for logging and support
+//! tooling.
+
+use std::fmt::{self, Display, Formatter};
+
+use super::ConnectivityState;
+
+/// The state of a channel, as returned by
+/// [`Channel::debug_state`](super::Channel::debug_state).
+///
+/// Idle channels have no LB policy or subchannels.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct ChannelDebugState {
+    /// The target URI of the channel.
+    pub target: String,
+    /// The URI scheme selecting the channel's name resolver.
+    pub resolver_scheme: String,
+    /// The connectivity state of the channel.
+    pub connectivity_state: ConnectivityState,
+    /// The name of the active LB policy.
+    pub lb_policy: Option<String>,
+    /// The JSON config of the active LB policy, if the service config
+    /// selected one.
+    pub lb_config: Option<String>,
+    /// The subchannels of the channel, ordered by address.
+    pub subchannels: Vec<SubchannelDebugState>,
+    /// The type name of the current picker.
+    pub picker: Option<String>,
+    /// The number of calls waiting for a picker to route them.
+    pub queued_calls: usize,
+    /// The number of resolver and LB policy callbacks which panicked.
+    pub callback_panics: u64,
+}
+
+/// The state of a subchannel of a channel.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct SubchannelDebugState {
+    /// The address of the subchannel.
+    pub address: String,
+    /// The network type of the address, which selects the transport.
+    pub network_type: &'static str,
+    /// The connectivity state of the subchannel.
+    pub connectivity_state: ConnectivityState,
+}
+
+impl Display for ChannelDebugState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "channel for {} ({} resolver): {}",
+            self.target, self.resolver_scheme, self.connectivity_state
+        )?;
+        let none = || "none".to_string();
+        writeln!(
+            f,
+            "  lb policy: {} config: {}",
+            self.lb_policy.clone().unwrap_or_else(none),
+            self.lb_config.clone().unwrap_or_else(none)
+        )?;
+        writeln!(
+            f,
+            "  picker: {} queued calls: {} callback panics: {}",
+            self.picker.clone().unwrap_or_else(none),
+            self.queued_calls,
+            self.callback_panics
+        )?;
+        for sc in &self.subchannels {
+            writeln!(
+                f,
+                "  subchannel {}:{}: {}",
+                sc.network_type, sc.address, sc.connectivity_state
+            )?;
+        }
+        Ok(())
+    }
+}
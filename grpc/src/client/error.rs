@@ -0,0 +1,396 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! Errors produced while resolving names, connecting and routing RPCs.
+//!
+//! Resolver and transport failures are surfaced to LB policies and callers as
+//! [`ResolveError`] and [`ConnectError`], which carry a kind describing the
+//! failure, a message, and optionally the underlying error.  [`ChannelError`]
+//! is returned by channel operations and wraps either of them.
+//!
+//! As in other gRPC implementations, RPCs failing because of resolution or
+//! connection errors fail with UNAVAILABLE regardless of the kind; the kind is
+//! meant for logging, metrics and LB policy decisions.
+
+use std::{error::Error, fmt, sync::Arc};
+
+use tonic::{Code, Status};
+
+type Source = Arc<dyn Error + Send + Sync>;
+
+/// A coarse classification of errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorCategory {
+    /// The condition may clear up by itself, so trying again later may
+    /// succeed.
+    Transient,
+    /// The channel's configuration, e.g. its target, is invalid.  Trying
+    /// again will not succeed.
+    Configuration,
+    /// The operation was cancelled, e.g. because the channel shut down.
+    Cancelled,
+    /// The operation did not complete in time.
+    Timeout,
+}
+
+/// The kind of a [`ConnectError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConnectErrorKind {
+    /// The address could not be interpreted by the transport.
+    InvalidAddress,
+    /// The peer could not be reached or refused the connection.
+    Refused,
+    /// The connection was not established in time.
+    Timeout,
+    /// The connection was established, but the transport handshake failed.
+    Handshake,
+    /// An established connection was lost; see
+    /// [`ConnectError::disconnect_reason`].
+    Disconnected,
+    /// Any other failure.
+    Other,
+}
+
+/// The kind of a [`ResolveError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ResolveErrorKind {
+    /// The target could not be parsed by the resolver.
+    InvalidTarget,
+    /// The lookup failed.
+    Lookup,
+    /// The lookup did not complete in time.
+    Timeout,
+    /// The resolver produced an update which the channel rejected.
+    Rejected,
+    /// Any other failure.
+    Other,
+}
+
+/// The failure of a transport to connect to an address.
+#[derive(Debug, Clone)]
+pub struct ConnectError {
+    kind: ConnectErrorKind,
+    message: String,
+    source: Option<Source>,
+}
+
+/// The failure of a resolver to resolve its target.
+#[derive(Debug, Clone)]
+pub struct ResolveError {
+    kind: ResolveErrorKind,
+    message: String,
+    source: Option<Source>,
+}
+
+macro_rules! impl_error {
+    ($name:ident, $kind:ident) => {
+        impl $name {
+            /// Creates an error of the provided kind.
+            pub fn new(kind: $kind, message: impl Into<String>) -> Self {
+                Self {
+                    kind,
+                    message: message.into(),
+                    source: None,
+                }
+            }
+
+            /// Records the underlying error, returned by `source()`.
+            pub fn with_source(self, source: impl Error + Send + Sync + 'static) -> Self {
+                Self {
+                    source: Some(Arc::new(source)),
+                    ..self
+                }
+            }
+
+            /// Returns the kind of the error.
+            pub fn kind(&self) -> $kind {
+                self.kind
+            }
+
+            /// Returns the status RPCs failing because of this error fail with.
+            pub fn to_status(&self) -> Status {
+                Status::new(Code::Unavailable, self.to_string())
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}", self.message)?;
+                if let Some(source) = &self.source {
+                    write!(f, ": {source}")?;
+                }
+                Ok(())
+            }
+        }
+
+        impl Error for $name {
+            fn source(&self) -> Option<&(dyn Error + 'static)> {
+                self.source.as_deref().map(|e| e as &(dyn Error + 'static))
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(message: String) -> Self {
+                Self::new($kind::Other, message)
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(message: &str) -> Self {
+                Self::new($kind::Other, message)
+            }
+        }
+    };
+}
+
+impl_error!(ConnectError, ConnectErrorKind);
+impl_error!(ResolveError, ResolveErrorKind);
+
+impl ConnectError {
+    /// Creates an error describing why an established connection was lost.
+    pub fn disconnected(reason: DisconnectReason) -> Self {
+        Self::new(ConnectErrorKind::Disconnected, "connection lost").with_source(reason)
+    }
+
+    /// Returns why the connection was lost, for errors of kind
+    /// [`ConnectErrorKind::Disconnected`].
+    pub fn disconnect_reason(&self) -> Option<&DisconnectReason> {
+        self.source.as_deref()?.downcast_ref()
+    }
+
+    /// Returns the category of the error.
+    pub fn category(&self) -> ErrorCategory {
+        match self.kind {
+            ConnectErrorKind::InvalidAddress => ErrorCategory::Configuration,
+            ConnectErrorKind::Timeout => ErrorCategory::Timeout,
+            _ => ErrorCategory::Transient,
+        }
+    }
+}
+
+impl ResolveError {
+    /// Returns the category of the error.
+    pub fn category(&self) -> ErrorCategory {
+        match self.kind {
+            ResolveErrorKind::InvalidTarget | ResolveErrorKind::Rejected => {
+                ErrorCategory::Configuration
+            }
+            ResolveErrorKind::Timeout => ErrorCategory::Timeout,
+            _ => ErrorCategory::Transient,
+        }
+    }
+}
+
+/// Why an established connection was closed, as reported by its transport.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DisconnectReason {
+    /// The connection was closed without an error, e.g. after the peer
+    /// gracefully shut it down.
+    Closed,
+    /// The peer closed the connection by sending an HTTP/2 GOAWAY frame with
+    /// an error code.
+    GoAway {
+        error_code: u32,
+        /// The debug data of the frame, which servers use to explain the
+        /// error, e.g. "too_many_pings".
+        debug_data: String,
+    },
+    /// The peer did not acknowledge a keepalive ping in time.
+    KeepaliveTimeout,
+    /// The connection failed, e.g. because it was reset or violated the
+    /// protocol.
+    Reset(String),
+}
+
+impl fmt::Display for DisconnectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Closed => write!(f, "connection closed"),
+            Self::GoAway {
+                error_code,
+                debug_data,
+            } => {
+                write!(f, "GOAWAY received with error code {error_code}")?;
+                if !debug_data.is_empty() {
+                    write!(f, " ({debug_data})")?;
+                }
+                Ok(())
+            }
+            Self::KeepaliveTimeout => write!(f, "keepalive ping not acknowledged"),
+            Self::Reset(msg) => write!(f, "connection reset: {msg}"),
+        }
+    }
+}
+
+impl Error for DisconnectReason {}
+
+/// The failure of an operation on a channel.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum ChannelError {
+    /// The channel was shut down.
+    Shutdown,
+    /// The operation was abandoned, e.g. because the channel entered idle.
+    Cancelled(String),
+    /// The operation did not complete before its deadline.
+    DeadlineExceeded(String),
+    /// Name resolution failed.
+    Resolve(ResolveError),
+    /// Connecting failed.
+    Connect(ConnectError),
+    /// The configuration of the channel, e.g. its default service config, is
+    /// invalid.
+    InvalidConfig(String),
+}
+
+impl ChannelError {
+    /// Returns the category of the error.
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            Self::Shutdown | Self::Cancelled(_) => ErrorCategory::Cancelled,
+            Self::DeadlineExceeded(_) => ErrorCategory::Timeout,
+            Self::Resolve(e) => e.category(),
+            Self::Connect(e) => e.category(),
+            Self::InvalidConfig(_) => ErrorCategory::Configuration,
+        }
+    }
+
+    /// Returns the status RPCs failing because of this error fail with.
+    pub fn to_status(&self) -> Status {
+        match self {
+            Self::Shutdown | Self::Cancelled(_) => Status::cancelled(self.to_string()),
+            Self::DeadlineExceeded(_) => Status::deadline_exceeded(self.to_string()),
+            Self::Resolve(e) => e.to_status(),
+            Self::Connect(e) => e.to_status(),
+            Self::InvalidConfig(_) => Status::invalid_argument(self.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for ChannelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Shutdown => write!(f, "channel is shut down"),
+            Self::Cancelled(msg) | Self::DeadlineExceeded(msg) => write!(f, "{msg}"),
+            Self::Resolve(e) => write!(f, "name resolution failed: {e}"),
+            Self::Connect(e) => write!(f, "connection failed: {e}"),
+            Self::InvalidConfig(msg) => write!(f, "invalid channel configuration: {msg}"),
+        }
+    }
+}
+
+impl Error for ChannelError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Resolve(e) => Some(e),
+            Self::Connect(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ResolveError> for ChannelError {
+    fn from(e: ResolveError) -> Self {
+        Self::Resolve(e)
+    }
+}
+
+impl From<ConnectError> for ChannelError {
+    fn from(e: ConnectError) -> Self {
+        Self::Connect(e)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{error::Error, io};
+
+    use tonic::Code;
+
+    use super::{
+        ChannelError, ConnectError, ConnectErrorKind, DisconnectReason, ErrorCategory,
+        ResolveError, ResolveErrorKind,
+    };
+
+    #[test]
+    fn source_chain() {
+        let io_err = io::Error::new(io::ErrorKind::ConnectionRefused, "refused");
+        let err: ChannelError = ConnectError::new(ConnectErrorKind::Refused, "dial 1.2.3.4:80")
+            .with_source(io_err)
+            .into();
+        assert_eq!(
+            err.to_string(),
+            "connection failed: dial 1.2.3.4:80: refused"
+        );
+        let connect = err.source().unwrap();
+        assert_eq!(connect.to_string(), "dial 1.2.3.4:80: refused");
+        let io = connect.source().unwrap();
+        assert!(io.downcast_ref::<io::Error>().is_some());
+        assert!(io.source().is_none());
+    }
+
+    #[test]
+    fn categories_and_codes() {
+        let invalid = ResolveError::new(ResolveErrorKind::InvalidTarget, "bad target");
+        assert_eq!(invalid.category(), ErrorCategory::Configuration);
+        assert_eq!(invalid.to_status().code(), Code::Unavailable);
+
+        let timeout: ChannelError = ConnectError::new(ConnectErrorKind::Timeout, "slow").into();
+        assert_eq!(timeout.category(), ErrorCategory::Timeout);
+        assert_eq!(timeout.to_status().code(), Code::Unavailable);
+
+        assert_eq!(ChannelError::Shutdown.to_status().code(), Code::Cancelled);
+        let deadline = ChannelError::DeadlineExceeded("late".to_string());
+        assert_eq!(deadline.to_status().code(), Code::DeadlineExceeded);
+
+        let other = ResolveError::from("boom");
+        assert_eq!(other.kind(), ResolveErrorKind::Other);
+        assert_eq!(other.category(), ErrorCategory::Transient);
+    }
+
+    #[test]
+    fn disconnect_reasons() {
+        let reason = DisconnectReason::GoAway {
+            error_code: 11,
+            debug_data: "too_many_pings".to_string(),
+        };
+        let err = ConnectError::disconnected(reason.clone());
+        assert_eq!(err.kind(), ConnectErrorKind::Disconnected);
+        assert_eq!(err.category(), ErrorCategory::Transient);
+        assert_eq!(err.disconnect_reason(), Some(&reason));
+        assert_eq!(
+            err.to_string(),
+            "connection lost: GOAWAY received with error code 11 (too_many_pings)"
+        );
+
+        let err = ConnectError::new(ConnectErrorKind::Refused, "refused")
+            .with_source(DisconnectReason::Closed);
+        assert_eq!(err.disconnect_reason(), Some(&DisconnectReason::Closed));
+        assert_eq!(ConnectError::from("boom").disconnect_reason(), None);
+    }
+}
@@ -0,0 +1,412 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+//! A cache of resolver results shared by channels.
+
+//! Fault injection for chaos testing.
+//!
+//! A [`FaultInjection`] delays and aborts a fraction of the calls it is
+//! applied to, following the semantics of the xDS HTTP fault filter: delays
+//! are injected before aborts, the delay and abort status may be read from
+//! the `x-envoy-fault-*` request headers, and the number of calls being
+//! faulted at once may be capped.  It can be set on a channel with
+//! `ChannelOptions::fault_injection`, or wrap any [`Service`] using
+//! [`FaultInjectingService`], so that the behavior of clients under slow and
+//! failing servers can be tested without a proxy.
+//!
+//! Fractions are expressed per million calls.
+
+use std::{
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use tonic::{async_trait, Code, Status};
+
+use super::deadline;
+use crate::rt::{default_runtime, Runtime};
+use crate::service::{status_response, Request, Response, Service};
+
+/// Sets the delay, in milliseconds, of calls using [`FaultDelay::from_headers`].
+pub const DELAY_HEADER: &str = "x-envoy-fault-delay-request";
+/// Lowers the fraction, per million, of calls delayed using
+/// [`FaultDelay::from_headers`].
+pub const DELAY_PERCENTAGE_HEADER: &str = "x-envoy-fault-delay-request-percentage";
+/// Sets the numeric status code of calls aborted using
+/// [`FaultAbort::from_headers`].
+pub const ABORT_HEADER: &str = "x-envoy-fault-abort-grpc-request";
+/// Lowers the fraction, per million, of calls aborted using
+/// [`FaultAbort::from_headers`].
+pub const ABORT_PERCENTAGE_HEADER: &str = "x-envoy-fault-abort-request-percentage";
+
+const MILLION: u32 = 1_000_000;
+
+/// The faults injected into calls.
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct FaultInjectionPolicy {
+    pub delay: Option<FaultDelay>,
+    pub abort: Option<FaultAbort>,
+    /// The maximum number of calls being delayed at once.  Calls made while
+    /// this many are delayed are not faulted.
+    pub max_active_faults: Option<u32>,
+}
+
+impl FaultInjectionPolicy {
+    pub fn delay(self, delay: FaultDelay) -> Self {
+        Self {
+            delay: Some(delay),
+            ..self
+        }
+    }
+
+    pub fn abort(self, abort: FaultAbort) -> Self {
+        Self {
+            abort: Some(abort),
+            ..self
+        }
+    }
+
+    pub fn max_active_faults(self, max_active_faults: u32) -> Self {
+        Self {
+            max_active_faults: Some(max_active_faults),
+            ..self
+        }
+    }
+}
+
+/// Delays a fraction of calls before they are started.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FaultDelay {
+    /// The delay, or None to read it from the [`DELAY_HEADER`] of each call.
+    /// Calls without a valid header are not delayed.
+    pub delay: Option<Duration>,
+    /// The fraction of calls delayed, per million.
+    pub per_million: u32,
+}
+
+impl FaultDelay {
+    /// Delays per_million of every million calls by delay.
+    pub fn fixed(delay: Duration, per_million: u32) -> Self {
+        Self {
+            delay: Some(delay),
+            per_million,
+        }
+    }
+
+    /// Delays calls by the delay set in their headers.  The fraction of calls
+    /// delayed is per_million, or the [`DELAY_PERCENTAGE_HEADER`] of the call
+    /// if lower.
+    pub fn from_headers(per_million: u32) -> Self {
+        Self {
+            delay: None,
+            per_million,
+        }
+    }
+
+    // Returns the delay to inject into request, if any.
+    fn pick(&self, request: &Request) -> Option<Duration> {
+        let (delay, per_million) = match self.delay {
+            Some(delay) => (delay, self.per_million),
+            None => (
+                Duration::from_millis(header(request, DELAY_HEADER)?),
+                header_fraction(request, DELAY_PERCENTAGE_HEADER, self.per_million),
+            ),
+        };
+        roll(per_million).then_some(delay)
+    }
+}
+
+/// Fails a fraction of calls without sending them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FaultAbort {
+    /// The status code of aborted calls, or None to read it from the
+    /// [`ABORT_HEADER`] of each call.  Calls without a valid header are not
+    /// aborted.
+    pub code: Option<Code>,
+    /// The fraction of calls aborted, per million.
+    pub per_million: u32,
+}
+
+impl FaultAbort {
+    /// Fails per_million of every million calls with code.
+    pub fn fixed(code: Code, per_million: u32) -> Self {
+        Self {
+            code: Some(code),
+            per_million,
+        }
+    }
+
+    /// Fails calls with the status code set in their headers.  The fraction
+    /// of calls aborted is per_million, or the [`ABORT_PERCENTAGE_HEADER`] of
+    /// the call if lower.
+    pub fn from_headers(per_million: u32) -> Self {
+        Self {
+            code: None,
+            per_million,
+        }
+    }
+
+    // Returns the status to fail request with, if any.
+    fn pick(&self, request: &Request) -> Option<Status> {
+        let (code, per_million) = match self.code {
+            Some(code) => (code, self.per_million),
+            None => (
+                Code::from_i32(header(request, ABORT_HEADER)?),
+                header_fraction(request, ABORT_PERCENTAGE_HEADER, self.per_million),
+            ),
+        };
+        if code == Code::Ok || !roll(per_million) {
+            return None;
+        }
+        Some(Status::new(code, "RPC aborted by fault injection"))
+    }
+}
+
+fn header<T: std::str::FromStr>(request: &Request, key: &str) -> Option<T> {
+    request.metadata().get(key)?.to_str().ok()?.parse().ok()
+}
+
+fn header_fraction(request: &Request, key: &str, per_million: u32) -> u32 {
+    header(request, key).map_or(per_million, |h: u32| h.min(per_million))
+}
+
+fn roll(per_million: u32) -> bool {
+    per_million >= MILLION || rand::random_range(0..MILLION) < per_million
+}
+
+/// Injects the faults of a policy into calls.
+///
+/// The number of active faults is counted across all the calls the
+/// injection is applied to, so sharing one between channels caps their
+/// faults together.
+pub struct FaultInjection {
+    policy: FaultInjectionPolicy,
+    active: AtomicU32,
+    runtime: Arc<dyn Runtime>,
+}
+
+impl FaultInjection {
+    pub fn new(policy: FaultInjectionPolicy) -> Self {
+        Self {
+            policy,
+            active: AtomicU32::new(0),
+            runtime: default_runtime(),
+        }
+    }
+
+    /// Applies the policy to request, waiting out its delay.  Returns the
+    /// status the call should fail with if it is aborted, or if its deadline
+    /// expires while delayed.
+    pub async fn apply(&self, request: &Request) -> Result<(), Status> {
+        let Some(_active) = self.start_fault() else {
+            return Ok(());
+        };
+        if let Some(delay) = self.policy.delay.and_then(|d| d.pick(request)) {
+            match deadline::request_timeout(request) {
+                Some(timeout) if timeout <= delay => {
+                    self.runtime.sleep(timeout).await;
+                    return Err(Status::deadline_exceeded(
+                        "deadline exceeded while delayed by fault injection",
+                    ));
+                }
+                _ => self.runtime.sleep(delay).await,
+            }
+        }
+        match self.policy.abort.and_then(|a| a.pick(request)) {
+            Some(status) => Err(status),
+            None => Ok(()),
+        }
+    }
+
+    // Counts a call as faulted, unless max_active_faults are already active.
+    fn start_fault(&self) -> Option<ActiveFault<'_>> {
+        let max = self.policy.max_active_faults.unwrap_or(u32::MAX);
+        self.active
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |active| {
+                (active < max).then_some(active + 1)
+            })
+            .ok()?;
+        Some(ActiveFault {
+            active: &self.active,
+        })
+    }
+}
+
+struct ActiveFault<'a> {
+    active: &'a AtomicU32,
+}
+
+impl Drop for ActiveFault<'_> {
+    fn drop(&mut self) {
+        self.active.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// A [`Service`] injecting faults into the calls made on the service it
+/// wraps.
+pub struct FaultInjectingService<S> {
+    faults: Arc<FaultInjection>,
+    inner: S,
+}
+
+impl<S> FaultInjectingService<S> {
+    pub fn new(faults: Arc<FaultInjection>, inner: S) -> Self {
+        Self { faults, inner }
+    }
+}
+
+#[async_trait]
+impl<S: Service> Service for FaultInjectingService<S> {
+    async fn call(&self, method: String, request: Request) -> Response {
+        if let Err(status) = self.faults.apply(&request).await {
+            return status_response(status);
+        }
+        self.inner.call(method, request).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    use tokio_stream::StreamExt;
+    use tonic::{async_trait, Code};
+
+    use super::{
+        FaultAbort, FaultDelay, FaultInjectingService, FaultInjection, FaultInjectionPolicy,
+        ABORT_HEADER, ABORT_PERCENTAGE_HEADER, DELAY_HEADER, MILLION,
+    };
+    use crate::service::{Request, Response, Service};
+
+    struct Succeed;
+
+    #[async_trait]
+    impl Service for Succeed {
+        async fn call(&self, _method: String, _request: Request) -> Response {
+            Response::new(Box::pin(tokio_stream::empty()))
+        }
+    }
+
+    fn service(policy: FaultInjectionPolicy) -> FaultInjectingService<Succeed> {
+        FaultInjectingService::new(Arc::new(FaultInjection::new(policy)), Succeed)
+    }
+
+    fn request(headers: &[(&'static str, &str)]) -> Request {
+        let mut request = Request::new(Box::pin(tokio_stream::empty()));
+        for (key, value) in headers {
+            request.metadata_mut().insert(*key, value.parse().unwrap());
+        }
+        request
+    }
+
+    async fn code(service: &impl Service, request: Request) -> Code {
+        let response = service.call("/svc/method".to_string(), request).await;
+        match response.into_inner().next().await {
+            Some(Err(status)) => status.code(),
+            _ => Code::Ok,
+        }
+    }
+
+    #[tokio::test]
+    async fn aborts_calls() {
+        let svc = service(
+            FaultInjectionPolicy::default().abort(FaultAbort::fixed(Code::Unavailable, MILLION)),
+        );
+        assert_eq!(code(&svc, request(&[])).await, Code::Unavailable);
+
+        let svc =
+            service(FaultInjectionPolicy::default().abort(FaultAbort::fixed(Code::Unavailable, 0)));
+        assert_eq!(code(&svc, request(&[])).await, Code::Ok);
+    }
+
+    #[tokio::test]
+    async fn aborts_calls_from_headers() {
+        let svc = service(FaultInjectionPolicy::default().abort(FaultAbort::from_headers(MILLION)));
+        assert_eq!(code(&svc, request(&[])).await, Code::Ok);
+        assert_eq!(
+            code(&svc, request(&[(ABORT_HEADER, "14")])).await,
+            Code::Unavailable
+        );
+        assert_eq!(
+            code(
+                &svc,
+                request(&[(ABORT_HEADER, "14"), (ABORT_PERCENTAGE_HEADER, "0")])
+            )
+            .await,
+            Code::Ok
+        );
+        assert_eq!(code(&svc, request(&[(ABORT_HEADER, "0")])).await, Code::Ok);
+    }
+
+    #[tokio::test]
+    async fn delays_calls() {
+        let svc = service(
+            FaultInjectionPolicy::default()
+                .delay(FaultDelay::fixed(Duration::from_millis(50), MILLION)),
+        );
+        let start = Instant::now();
+        assert_eq!(code(&svc, request(&[])).await, Code::Ok);
+        assert!(start.elapsed() >= Duration::from_millis(50));
+
+        let svc = service(FaultInjectionPolicy::default().delay(FaultDelay::from_headers(MILLION)));
+        let start = Instant::now();
+        assert_eq!(code(&svc, request(&[(DELAY_HEADER, "50")])).await, Code::Ok);
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn delays_do_not_outlast_deadlines() {
+        let svc = service(
+            FaultInjectionPolicy::default()
+                .delay(FaultDelay::fixed(Duration::from_secs(60), MILLION)),
+        );
+        let mut req = request(&[]);
+        req.set_timeout(Duration::from_millis(10));
+        assert_eq!(code(&svc, req).await, Code::DeadlineExceeded);
+    }
+
+    #[tokio::test]
+    async fn caps_active_faults() {
+        let svc = Arc::new(service(
+            FaultInjectionPolicy::default()
+                .delay(FaultDelay::fixed(Duration::from_secs(60), MILLION))
+                .max_active_faults(1),
+        ));
+        let delayed = tokio::spawn({
+            let svc = svc.clone();
+            async move { code(&*svc, request(&[])).await }
+        });
+        while svc.faults.active.load(std::sync::atomic::Ordering::Acquire) == 0 {
+            tokio::task::yield_now().await;
+        }
+        let start = Instant::now();
+        assert_eq!(code(&*svc, request(&[])).await, Code::Ok);
+        assert!(start.elapsed() < Duration::from_secs(5));
+        delayed.abort();
+    }
+}
@@ -0,0 +1,282 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! Labels attached to subchannels for use by the stats layer.
+//!
+//! Resolvers and LB policies label a subchannel by setting attributes on the
+//! address they create it for.  Only attributes whose keys are registered as
+//! labels are used, and their values are truncated to
+//! [`MAX_LABEL_VALUE_LEN`] bytes, which bounds the number of distinct label
+//! sets the stats layer records.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, LazyLock, Mutex, RwLock},
+};
+
+use crate::attributes::{AttributeKey, Attributes};
+use crate::stats::{ConnectionEvent, ConnectionInfo, StatsHandler};
+
+/// The cluster the subchannel's address belongs to.
+pub(crate) static CLUSTER: AttributeKey<String> = AttributeKey::new("grpc.lb.cluster");
+
+/// The locality the subchannel's address belongs to.
+pub(crate) static LOCALITY: AttributeKey<String> = AttributeKey::new("grpc.lb.locality");
+
+/// The priority the subchannel's address was assigned by the LB policy.
+pub(crate) static PRIORITY: AttributeKey<String> = AttributeKey::new("grpc.lb.priority");
+
+/// The maximum length in bytes of a label value.  Longer values are
+/// truncated.
+pub const MAX_LABEL_VALUE_LEN: usize = 64;
+
+/// The set of attribute keys permitted as subchannel labels.
+pub(crate) struct LabelRegistry {
+    keys: RwLock<BTreeMap<&'static str, &'static AttributeKey<String>>>,
+}
+
+impl LabelRegistry {
+    /// Creates a registry permitting the built-in labels.
+    pub(crate) fn new() -> Self {
+        let registry = Self {
+            keys: RwLock::default(),
+        };
+        registry.register(&CLUSTER);
+        registry.register(&LOCALITY);
+        registry.register(&PRIORITY);
+        registry
+    }
+
+    /// Permits `key` to be used as a label.
+    pub(crate) fn register(&self, key: &'static AttributeKey<String>) {
+        self.keys.write().unwrap().insert(key.name(), key);
+    }
+
+    /// Returns the labels found in `attributes`, ignoring attributes which
+    /// are not registered.
+    pub(crate) fn labels(&self, attributes: &Attributes) -> SubchannelLabels {
+        let keys = self.keys.read().unwrap();
+        let labels = keys
+            .iter()
+            .filter_map(|(name, key)| {
+                let value = attributes.get(*key)?;
+                Some((*name, truncate(value).to_string()))
+            })
+            .collect();
+        SubchannelLabels { labels }
+    }
+}
+
+fn truncate(value: &str) -> &str {
+    if value.len() <= MAX_LABEL_VALUE_LEN {
+        return value;
+    }
+    let mut end = MAX_LABEL_VALUE_LEN;
+    while !value.is_char_boundary(end) {
+        end -= 1;
+    }
+    &value[..end]
+}
+
+/// The registry used by all channels.
+pub(crate) static GLOBAL_LABEL_REGISTRY: LazyLock<LabelRegistry> =
+    LazyLock::new(LabelRegistry::new);
+
+/// The labels of a subchannel, keyed by label name.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SubchannelLabels {
+    labels: BTreeMap<&'static str, String>,
+}
+
+impl SubchannelLabels {
+    /// Returns the value of the label with the given name.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.labels.get(name).map(String::as_str)
+    }
+
+    /// Returns an iterator over the labels, ordered by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &str)> {
+        self.labels.iter().map(|(k, v)| (*k, v.as_str()))
+    }
+
+    /// Returns true if there are no labels.
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+}
+
+/// Connection attempt counters of a set of subchannels.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConnectionStats {
+    /// The number of connection attempts started.
+    pub attempts: u64,
+    /// The number of connection attempts which succeeded.
+    pub succeeded: u64,
+    /// The number of connection attempts which failed.
+    pub failed: u64,
+}
+
+/// Connection statistics of a channel's subchannels, labeled by subchannel
+/// labels.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SubchannelStats {
+    by_labels: BTreeMap<SubchannelLabels, ConnectionStats>,
+}
+
+impl SubchannelStats {
+    /// Returns the statistics of the subchannels with the given labels.
+    pub fn get(&self, labels: &SubchannelLabels) -> ConnectionStats {
+        self.by_labels.get(labels).copied().unwrap_or_default()
+    }
+
+    /// Returns an iterator over the recorded label sets and their statistics.
+    pub fn iter(&self) -> impl Iterator<Item = (&SubchannelLabels, &ConnectionStats)> {
+        self.by_labels.iter()
+    }
+}
+
+/// Records connection attempts of subchannels, and reports their connection
+/// events to the stats handlers of the channel.  Shared by a channel and its
+/// subchannels.
+#[derive(Default)]
+pub(crate) struct SubchannelStatsRecorder {
+    stats: Mutex<SubchannelStats>,
+    target: String,
+    handlers: Arc<[Arc<dyn StatsHandler>]>,
+}
+
+impl SubchannelStatsRecorder {
+    pub(crate) fn new(target: String, handlers: Arc<[Arc<dyn StatsHandler>]>) -> Self {
+        Self {
+            stats: Mutex::default(),
+            target,
+            handlers,
+        }
+    }
+
+    /// Returns the description of the subchannel with the given address and
+    /// labels used in connection events.
+    pub(crate) fn connection_info(
+        &self,
+        address: String,
+        labels: &SubchannelLabels,
+    ) -> ConnectionInfo {
+        ConnectionInfo {
+            target: self.target.clone(),
+            address,
+            labels: labels.clone(),
+        }
+    }
+
+    pub(crate) fn emit(&self, info: &ConnectionInfo, event: &ConnectionEvent<'_>) {
+        for handler in self.handlers.iter() {
+            handler.handle_connection(info, event);
+        }
+    }
+
+    pub(crate) fn record_attempt(&self, labels: &SubchannelLabels) {
+        self.update(labels, |s| s.attempts += 1);
+    }
+
+    pub(crate) fn record_success(&self, labels: &SubchannelLabels) {
+        self.update(labels, |s| s.succeeded += 1);
+    }
+
+    pub(crate) fn record_failure(&self, labels: &SubchannelLabels) {
+        self.update(labels, |s| s.failed += 1);
+    }
+
+    pub(crate) fn stats(&self) -> SubchannelStats {
+        self.stats.lock().unwrap().clone()
+    }
+
+    fn update(&self, labels: &SubchannelLabels, f: impl FnOnce(&mut ConnectionStats)) {
+        let mut stats = self.stats.lock().unwrap();
+        if let Some(s) = stats.by_labels.get_mut(labels) {
+            f(s);
+            return;
+        }
+        let mut s = ConnectionStats::default();
+        f(&mut s);
+        stats.by_labels.insert(labels.clone(), s);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{LabelRegistry, SubchannelStatsRecorder, CLUSTER, LOCALITY, MAX_LABEL_VALUE_LEN};
+    use crate::attributes::{AttributeKey, Attributes};
+
+    static SHARD: AttributeKey<String> = AttributeKey::new("test.shard");
+    static REQUEST_ID: AttributeKey<String> = AttributeKey::new("test.request_id");
+
+    #[test]
+    fn only_registered_attributes_are_labels() {
+        let registry = LabelRegistry::new();
+        let attrs = Attributes::default()
+            .add(&CLUSTER, "c1".to_string())
+            .add(&SHARD, "s1".to_string())
+            .add(&REQUEST_ID, "r1".to_string());
+        let labels = registry.labels(&attrs);
+        assert_eq!(labels.get(CLUSTER.name()), Some("c1"));
+        assert_eq!(labels.get(SHARD.name()), None);
+        assert_eq!(labels.iter().count(), 1);
+
+        registry.register(&SHARD);
+        let labels = registry.labels(&attrs);
+        assert_eq!(labels.get(SHARD.name()), Some("s1"));
+        assert_eq!(labels.get(REQUEST_ID.name()), None);
+    }
+
+    #[test]
+    fn long_values_are_truncated() {
+        let registry = LabelRegistry::new();
+        let value = "é".repeat(MAX_LABEL_VALUE_LEN);
+        let attrs = Attributes::default().add(&LOCALITY, value);
+        let labels = registry.labels(&attrs);
+        let got = labels.get(LOCALITY.name()).unwrap();
+        assert!(got.len() <= MAX_LABEL_VALUE_LEN);
+        assert_eq!(got.chars().count(), MAX_LABEL_VALUE_LEN / 2);
+    }
+
+    #[test]
+    fn stats_are_keyed_by_labels() {
+        let registry = LabelRegistry::new();
+        let a = registry.labels(&Attributes::default().add(&CLUSTER, "a".to_string()));
+        let b = registry.labels(&Attributes::default().add(&CLUSTER, "b".to_string()));
+        let recorder = SubchannelStatsRecorder::default();
+        recorder.record_attempt(&a);
+        recorder.record_failure(&a);
+        recorder.record_attempt(&a);
+        recorder.record_success(&a);
+        recorder.record_attempt(&b);
+
+        let stats = recorder.stats();
+        let got = stats.get(&a);
+        assert_eq!((got.attempts, got.succeeded, got.failed), (2, 1, 1));
+        assert_eq!(stats.get(&b).attempts, 1);
+        assert_eq!(stats.get(&Default::default()).attempts, 0);
+        assert_eq!(stats.iter().count(), 2);
+    }
+}
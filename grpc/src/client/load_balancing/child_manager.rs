@@ -24,20 +24,41 @@
 
 //! A utility which helps parent LB policies manage multiple children for the
 //! purposes of forwarding channel updates.
+//!
+//! Semantics:
+//!
+//! - Children: on every resolver update, the ResolverUpdateSharder splits the
+//!   update into one ChildUpdate per child.  Children are identified by their
+//!   identifier; a child whose identifier appears in consecutive updates is
+//!   kept (along with its subchannels, state and pending work), new
+//!   identifiers create new children, and children whose identifiers are
+//!   absent are torn down by dropping them, per the LbPolicy Drop contract.
+//! - Subchannel routing: every subchannel is owned by the child that created
+//!   it, and subchannel updates are delivered only to that child.  Updates for
+//!   subchannels whose child has been removed are ignored.
+//! - Pickers: children's picker updates are recorded but never forwarded to
+//!   the channel.  The parent policy inspects them with child_states() or
+//!   child_state() (or aggregate_connectivity_state()) after each operation and
+//!   decides what to report.
+//! - Work: a child's call to schedule_work schedules work on the parent; the
+//!   parent's call to work() then calls work() on each child that requested
+//!   it.
 
 // TODO: This is mainly provided as a fairly complex example of the current LB
 // policy in use.  Complete tests must be written before it can be used in
-// production.  Also, support for the work scheduler is missing.
+// production.
 
 use std::collections::HashSet;
-use std::sync::Mutex;
+use std::sync::{Mutex, Weak};
+use std::time::Duration;
 use std::{collections::HashMap, error::Error, hash::Hash, mem, sync::Arc};
 
 use crate::client::load_balancing::{
     ChannelController, LbConfig, LbPolicy, LbPolicyBuilder, LbPolicyOptions, LbState,
-    WeakSubchannel, WorkScheduler,
+    ScheduledWork, WeakSubchannel, WorkScheduler,
 };
 use crate::client::name_resolution::{Address, ResolverUpdate};
+use crate::client::ConnectivityState;
 use crate::rt::Runtime;
 
 use super::{Subchannel, SubchannelState};
@@ -48,6 +69,7 @@ pub struct ChildManager<T> {
     children: Vec<Child<T>>,
     update_sharder: Box<dyn ResolverUpdateSharder<T>>,
     pending_work: Arc<Mutex<HashSet<usize>>>,
+    work_scheduler: Arc<dyn WorkScheduler>,
     runtime: Arc<dyn Runtime>,
 }
 
@@ -82,9 +104,11 @@ pub trait ResolverUpdateSharder<T>: Send {
 
 impl<T> ChildManager<T> {
     /// Creates a new ChildManager LB policy.  shard_update is called whenever a
-    /// resolver_update operation occurs.
+    /// resolver_update operation occurs.  work_scheduler is the parent's work
+    /// scheduler, used whenever a child requests a call to its work method.
     pub fn new(
         update_sharder: Box<dyn ResolverUpdateSharder<T>>,
+        work_scheduler: Arc<dyn WorkScheduler>,
         runtime: Arc<dyn Runtime>,
     ) -> Self {
         Self {
@@ -92,17 +116,40 @@ impl<T> ChildManager<T> {
             subchannel_child_map: Default::default(),
             children: Default::default(),
             pending_work: Default::default(),
+            work_scheduler,
             runtime,
         }
     }
 
     /// Returns data for all current children.
-    pub fn child_states(&mut self) -> impl Iterator<Item = (&T, &LbState)> {
+    pub fn child_states(&self) -> impl Iterator<Item = (&T, &LbState)> {
         self.children
             .iter()
             .map(|child| (&child.identifier, &child.state))
     }
 
+    /// Returns the most recent state reported by the child with the given
+    /// identifier, if it exists.
+    pub fn child_state(&self, identifier: &T) -> Option<&LbState>
+    where
+        T: PartialEq,
+    {
+        self.children
+            .iter()
+            .find(|child| child.identifier == *identifier)
+            .map(|child| &child.state)
+    }
+
+    /// Returns the connectivity state of the children in aggregate.  See
+    /// ConnectivityState::aggregate.
+    pub fn aggregate_connectivity_state(&self) -> ConnectivityState {
+        ConnectivityState::aggregate(
+            self.children
+                .iter()
+                .map(|child| child.state.connectivity_state),
+        )
+    }
+
     // Called to update all accounting in the ChildManager from operations
     // performed by a child policy on the WrappedController that was created for
     // it.  child_idx is an index into the children map for the relevant child.
@@ -197,9 +244,12 @@ impl<T: PartialEq + Hash + Eq + Send + Sync + 'static> LbPolicy for ChildManager
                     work_scheduler,
                 });
             } else {
-                let work_scheduler = Arc::new(ChildWorkScheduler {
+                let work_scheduler = Arc::new_cyclic(|me| ChildWorkScheduler {
+                    me: me.clone(),
                     pending_work: self.pending_work.clone(),
                     idx: Mutex::new(Some(new_idx)),
+                    parent: self.work_scheduler.clone(),
+                    runtime: self.runtime.clone(),
                 });
                 let policy = builder.build(LbPolicyOptions {
                     work_scheduler: work_scheduler.clone(),
@@ -246,11 +296,14 @@ impl<T: PartialEq + Hash + Eq + Send + Sync + 'static> LbPolicy for ChildManager
         state: &SubchannelState,
         channel_controller: &mut dyn ChannelController,
     ) {
-        // Determine which child created this subchannel.
-        let child_idx = *self
+        // Determine which child created this subchannel.  It may have been
+        // removed since the update was produced.
+        let Some(&child_idx) = self
             .subchannel_child_map
             .get(&WeakSubchannel::new(&subchannel))
-            .unwrap();
+        else {
+            return;
+        };
         let policy = &mut self.children[child_idx].policy;
         // Wrap the channel_controller to track the child's operations.
         let mut channel_controller = WrappedController::new(channel_controller);
@@ -270,8 +323,14 @@ impl<T: PartialEq + Hash + Eq + Send + Sync + 'static> LbPolicy for ChildManager
         }
     }
 
-    fn exit_idle(&mut self, _channel_controller: &mut dyn ChannelController) {
-        todo!("implement exit_idle")
+    fn exit_idle(&mut self, channel_controller: &mut dyn ChannelController) {
+        for child_idx in 0..self.children.len() {
+            let mut channel_controller = WrappedController::new(channel_controller);
+            self.children[child_idx]
+                .policy
+                .exit_idle(&mut channel_controller);
+            self.resolve_child_controller(channel_controller, child_idx);
+        }
     }
 }
 
@@ -308,8 +367,11 @@ impl ChannelController for WrappedController<'_> {
 }
 
 struct ChildWorkScheduler {
+    me: Weak<ChildWorkScheduler>,
     pending_work: Arc<Mutex<HashSet<usize>>>, // Must be taken first for correctness
     idx: Mutex<Option<usize>>,                // None if the child is deleted.
+    parent: Arc<dyn WorkScheduler>,
+    runtime: Arc<dyn Runtime>,
 }
 
 impl WorkScheduler for ChildWorkScheduler {
@@ -317,6 +379,291 @@ impl WorkScheduler for ChildWorkScheduler {
         let mut pending_work = self.pending_work.lock().unwrap();
         if let Some(idx) = *self.idx.lock().unwrap() {
             pending_work.insert(idx);
+            drop(pending_work);
+            self.parent.schedule_work();
+        }
+    }
+
+    fn schedule_work_after(&self, delay: Duration) -> ScheduledWork {
+        // The child must be marked as pending when the delay elapses, not now,
+        // so the parent's scheduler can't be used directly.
+        let me = self.me.clone();
+        ScheduledWork::spawn(&*self.runtime, delay, move || {
+            if let Some(me) = me.upgrade() {
+                me.schedule_work();
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        error::Error,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use tokio::sync::mpsc;
+
+    use crate::{
+        client::{
+            load_balancing::{
+                test_utils::{TestChannelController, TestEvent, TestWorkScheduler},
+                ChannelController, LbConfig, LbPolicy, LbPolicyBuilder, LbPolicyOptions, LbState,
+                QueuingPicker, Subchannel, SubchannelState, WorkScheduler,
+            },
+            name_resolution::{Address, Endpoint, ResolverUpdate},
+            ConnectivityState,
+        },
+        rt::tokio::TokioRuntime,
+    };
+
+    use super::{ChildManager, ChildUpdate, ResolverUpdateSharder};
+
+    // Creates one child per endpoint, identified by its first address.
+    struct EndpointSharder {
+        builder: Arc<dyn LbPolicyBuilder>,
+    }
+
+    impl ResolverUpdateSharder<String> for EndpointSharder {
+        fn shard_update(
+            &self,
+            update: ResolverUpdate,
+        ) -> Result<Box<dyn Iterator<Item = ChildUpdate<String>>>, Box<dyn Error + Send + Sync>>
+        {
+            let builder = self.builder.clone();
+            let updates: Vec<_> = update
+                .endpoints?
+                .into_iter()
+                .map(|endpoint| ChildUpdate {
+                    child_identifier: endpoint.addresses[0].address.to_string(),
+                    child_policy_builder: builder.clone(),
+                    child_update: ResolverUpdate::builder().endpoint(endpoint).build(),
+                })
+                .collect();
+            Ok(Box::new(updates.into_iter()))
+        }
+    }
+
+    // A child policy which creates a subchannel for its address on its first
+    // update, requests a call to work, and reports the subchannel's state (or
+    // Idle from work) as its own.  Counts live instances to verify teardown.
+    struct TestBuilder {
+        live: Arc<AtomicUsize>,
+    }
+
+    impl LbPolicyBuilder for TestBuilder {
+        fn build(&self, options: LbPolicyOptions) -> Box<dyn LbPolicy> {
+            self.live.fetch_add(1, Ordering::SeqCst);
+            Box::new(TestPolicy {
+                live: self.live.clone(),
+                work_scheduler: options.work_scheduler,
+                subchannel: None,
+            })
+        }
+
+        fn name(&self) -> &'static str {
+            "test"
+        }
+    }
+
+    struct TestPolicy {
+        live: Arc<AtomicUsize>,
+        work_scheduler: Arc<dyn WorkScheduler>,
+        subchannel: Option<Arc<dyn Subchannel>>,
+    }
+
+    impl Drop for TestPolicy {
+        fn drop(&mut self) {
+            self.live.fetch_sub(1, Ordering::SeqCst);
         }
     }
+
+    fn report(channel_controller: &mut dyn ChannelController, state: ConnectivityState) {
+        channel_controller.update_picker(LbState {
+            connectivity_state: state,
+            picker: Arc::new(QueuingPicker {}),
+        });
+    }
+
+    impl LbPolicy for TestPolicy {
+        fn resolver_update(
+            &mut self,
+            update: ResolverUpdate,
+            _: Option<&LbConfig>,
+            channel_controller: &mut dyn ChannelController,
+        ) -> Result<(), Box<dyn Error + Send + Sync>> {
+            if self.subchannel.is_none() {
+                self.subchannel =
+                    Some(channel_controller.new_subchannel(&update.endpoints?[0].addresses[0]));
+                report(channel_controller, ConnectivityState::Connecting);
+                self.work_scheduler.schedule_work();
+            }
+            Ok(())
+        }
+
+        fn subchannel_update(
+            &mut self,
+            _: Arc<dyn Subchannel>,
+            state: &SubchannelState,
+            channel_controller: &mut dyn ChannelController,
+        ) {
+            report(channel_controller, state.connectivity_state);
+        }
+
+        fn work(&mut self, channel_controller: &mut dyn ChannelController) {
+            report(channel_controller, ConnectivityState::Idle);
+        }
+
+        fn exit_idle(&mut self, _: &mut dyn ChannelController) {}
+    }
+
+    struct Fixture {
+        child_manager: ChildManager<String>,
+        channel_controller: TestChannelController,
+        rx_events: mpsc::UnboundedReceiver<TestEvent>,
+        live: Arc<AtomicUsize>,
+    }
+
+    fn setup() -> Fixture {
+        let (tx_events, rx_events) = mpsc::unbounded_channel();
+        let live = Arc::new(AtomicUsize::new(0));
+        let child_manager = ChildManager::new(
+            Box::new(EndpointSharder {
+                builder: Arc::new(TestBuilder { live: live.clone() }),
+            }),
+            Arc::new(TestWorkScheduler {
+                tx_events: tx_events.clone(),
+            }),
+            Arc::new(TokioRuntime {}),
+        );
+        Fixture {
+            child_manager,
+            channel_controller: TestChannelController { tx_events },
+            rx_events,
+            live,
+        }
+    }
+
+    fn update(addrs: &[&str]) -> ResolverUpdate {
+        let endpoints = addrs.iter().map(|addr| {
+            Endpoint::builder()
+                .address(Address::new("test", *addr))
+                .build()
+                .unwrap()
+        });
+        ResolverUpdate::builder().endpoints(endpoints).build()
+    }
+
+    fn ready() -> SubchannelState {
+        SubchannelState {
+            connectivity_state: ConnectivityState::Ready,
+            ..Default::default()
+        }
+    }
+
+    // Returns the subchannels created since the last call, ignoring other
+    // events.
+    fn new_subchannels(
+        rx_events: &mut mpsc::UnboundedReceiver<TestEvent>,
+    ) -> Vec<Arc<dyn Subchannel>> {
+        let mut subchannels = vec![];
+        while let Ok(event) = rx_events.try_recv() {
+            match event {
+                TestEvent::NewSubchannel(sc) => subchannels.push(sc),
+                TestEvent::UpdatePicker(_) => panic!("child picker forwarded to channel"),
+                _ => {}
+            }
+        }
+        subchannels
+    }
+
+    fn state(f: &Fixture, id: &str) -> Option<ConnectivityState> {
+        f.child_manager
+            .child_state(&id.to_string())
+            .map(|s| s.connectivity_state)
+    }
+
+    #[test]
+    fn routes_subchannel_updates_to_owner() {
+        let mut f = setup();
+        f.child_manager
+            .resolver_update(update(&["a", "b"]), None, &mut f.channel_controller)
+            .unwrap();
+        let subchannels = new_subchannels(&mut f.rx_events);
+        assert_eq!(subchannels.len(), 2);
+        assert_eq!(
+            f.child_manager.aggregate_connectivity_state(),
+            ConnectivityState::Connecting
+        );
+
+        f.child_manager.subchannel_update(
+            subchannels[1].clone(),
+            &ready(),
+            &mut f.channel_controller,
+        );
+        assert_eq!(state(&f, "a"), Some(ConnectivityState::Connecting));
+        assert_eq!(state(&f, "b"), Some(ConnectivityState::Ready));
+        assert_eq!(
+            f.child_manager.aggregate_connectivity_state(),
+            ConnectivityState::Ready
+        );
+    }
+
+    #[test]
+    fn tears_down_removed_children() {
+        let mut f = setup();
+        f.child_manager
+            .resolver_update(update(&["a", "b"]), None, &mut f.channel_controller)
+            .unwrap();
+        let subchannels = new_subchannels(&mut f.rx_events);
+        f.child_manager.subchannel_update(
+            subchannels[1].clone(),
+            &ready(),
+            &mut f.channel_controller,
+        );
+        assert_eq!(f.live.load(Ordering::SeqCst), 2);
+
+        // Child b is kept along with its state; child a is dropped.
+        f.child_manager
+            .resolver_update(update(&["b", "c"]), None, &mut f.channel_controller)
+            .unwrap();
+        assert_eq!(new_subchannels(&mut f.rx_events).len(), 1);
+        assert_eq!(f.live.load(Ordering::SeqCst), 2);
+        assert_eq!(state(&f, "a"), None);
+        assert_eq!(state(&f, "b"), Some(ConnectivityState::Ready));
+
+        // Updates for subchannels of removed children are ignored.
+        f.child_manager.subchannel_update(
+            subchannels[0].clone(),
+            &ready(),
+            &mut f.channel_controller,
+        );
+        assert_eq!(state(&f, "c"), Some(ConnectivityState::Connecting));
+
+        drop(f.child_manager);
+        assert_eq!(f.live.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn forwards_work_to_requesting_children() {
+        let mut f = setup();
+        f.child_manager
+            .resolver_update(update(&["a", "b"]), None, &mut f.channel_controller)
+            .unwrap();
+        assert_eq!(
+            f.child_manager.aggregate_connectivity_state(),
+            ConnectivityState::Connecting
+        );
+        f.child_manager.work(&mut f.channel_controller);
+        assert_eq!(state(&f, "a"), Some(ConnectivityState::Idle));
+        assert_eq!(state(&f, "b"), Some(ConnectivityState::Idle));
+        assert_eq!(
+            f.child_manager.aggregate_connectivity_state(),
+            ConnectivityState::Idle
+        );
+    }
 }
@@ -0,0 +1,416 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! Caps the number of calls in flight to a target or cluster.
+//!
+//! A [`CircuitBreaker`] holds counters which are shared by every picker it
+//! wraps, so that calls routed by stale pickers still count against the limit.
+//! Picks made while the limit is reached are dropped with UNAVAILABLE, failing
+//! even wait-for-ready calls, and counted as drops.
+//!
+//! The breaker can wrap the pickers of any policy; the `circuit_breaking`
+//! policy in this module applies it to a child policy.
+
+use std::{
+    error::Error,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use serde::Deserialize;
+use tonic::Status;
+
+use crate::client::{name_resolution::ResolverUpdate, service_config::LbPolicyConfig};
+use crate::service::Request;
+
+use super::{
+    child_manager::{ChildManager, ChildUpdate, ResolverUpdateSharder},
+    ChannelController, LbConfig, LbPolicy, LbPolicyBuilder, LbPolicyOptions, LbState,
+    ParsedJsonLbConfig, PickResult, Picker, Subchannel, SubchannelState, GLOBAL_LB_REGISTRY,
+};
+
+pub static POLICY_NAME: &str = "circuit_breaking";
+
+/// The limit used when the config does not set one, matching the default of
+/// xDS clusters.
+pub const DEFAULT_MAX_REQUESTS: u32 = 1024;
+
+/// Counts the calls in flight to a target or cluster, and drops picks which
+/// would exceed the limit.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    max_requests: AtomicU32,
+    in_flight: AtomicU32,
+    dropped: AtomicU64,
+}
+
+impl CircuitBreaker {
+    /// Creates a circuit breaker allowing up to max_requests calls in flight.
+    pub fn new(max_requests: u32) -> Arc<Self> {
+        Arc::new(Self {
+            max_requests: AtomicU32::new(max_requests),
+            in_flight: AtomicU32::new(0),
+            dropped: AtomicU64::new(0),
+        })
+    }
+
+    /// Changes the limit.  Calls already in flight are not affected, even if
+    /// they exceed the new limit.
+    pub fn set_max_requests(&self, max_requests: u32) {
+        self.max_requests.store(max_requests, Ordering::Release);
+    }
+
+    /// Returns the number of calls in flight.
+    pub fn in_flight(&self) -> u32 {
+        self.in_flight.load(Ordering::Acquire)
+    }
+
+    /// Returns the number of picks dropped because the limit was reached.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Acquire)
+    }
+
+    /// Returns a picker which applies this breaker to the picks of child.
+    pub fn picker(self: &Arc<Self>, child: Arc<dyn Picker>) -> Arc<dyn Picker> {
+        Arc::new(CircuitBreakingPicker {
+            breaker: self.clone(),
+            child,
+        })
+    }
+
+    fn try_acquire(&self) -> bool {
+        let max = self.max_requests.load(Ordering::Acquire);
+        self.in_flight
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                (n < max).then_some(n + 1)
+            })
+            .is_ok()
+    }
+}
+
+// Releases a call's slot in the breaker when the call completes, or when the
+// pick is dropped without being used.
+struct Slot {
+    breaker: Arc<CircuitBreaker>,
+    released: AtomicBool,
+}
+
+impl Slot {
+    fn release(&self) {
+        if !self.released.swap(true, Ordering::AcqRel) {
+            self.breaker.in_flight.fetch_sub(1, Ordering::AcqRel);
+        }
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+struct CircuitBreakingPicker {
+    breaker: Arc<CircuitBreaker>,
+    child: Arc<dyn Picker>,
+}
+
+impl Picker for CircuitBreakingPicker {
+    fn pick(&self, request: &Request) -> PickResult {
+        let mut pick = match self.child.pick(request) {
+            PickResult::Pick(pick) => pick,
+            result => return result,
+        };
+        if !self.breaker.try_acquire() {
+            self.breaker.dropped.fetch_add(1, Ordering::AcqRel);
+            return PickResult::Drop(Status::unavailable(
+                "circuit breaker: too many calls in flight",
+            ));
+        }
+        let slot = Slot {
+            breaker: self.breaker.clone(),
+            released: AtomicBool::new(false),
+        };
+        let child_callback = pick.on_complete.take();
+        pick.on_complete = Some(Box::new(move |outcome| {
+            slot.release();
+            if let Some(callback) = &child_callback {
+                callback(outcome);
+            }
+        }));
+        PickResult::Pick(pick)
+    }
+}
+
+/// The parsed configuration of the circuit breaking policy.
+pub(crate) struct CircuitBreakingConfig {
+    /// The maximum number of calls in flight.
+    pub(crate) max_requests: u32,
+    /// The policy whose picks are limited.
+    pub(crate) child_policy: Arc<dyn LbPolicyBuilder>,
+}
+
+impl LbPolicyConfig for CircuitBreakingConfig {}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonConfig {
+    max_requests: Option<u32>,
+    child_policy: String,
+}
+
+struct Builder {}
+
+impl LbPolicyBuilder for Builder {
+    fn build(&self, options: LbPolicyOptions) -> Box<dyn LbPolicy> {
+        let sharder = Arc::new(Sharder::default());
+        Box::new(CircuitBreakingPolicy {
+            child_manager: ChildManager::new(
+                Box::new(sharder.clone()),
+                options.work_scheduler,
+                options.runtime,
+            ),
+            sharder,
+            breaker: CircuitBreaker::new(DEFAULT_MAX_REQUESTS),
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        POLICY_NAME
+    }
+
+    fn parse_config(
+        &self,
+        config: &ParsedJsonLbConfig,
+    ) -> Result<Option<LbConfig>, Box<dyn Error + Send + Sync>> {
+        let cfg: JsonConfig = config.convert_to()?;
+        let child_policy = config
+            .lookup_policy(&cfg.child_policy)
+            .ok_or_else(|| format!("unknown child policy {:?}", cfg.child_policy))?;
+        Ok(Some(
+            CircuitBreakingConfig {
+                max_requests: cfg.max_requests.unwrap_or(DEFAULT_MAX_REQUESTS),
+                child_policy,
+            }
+            .into_lb_config(),
+        ))
+    }
+}
+
+pub fn reg() {
+    GLOBAL_LB_REGISTRY.add_builder(Builder {})
+}
+
+#[derive(Default)]
+struct Sharder {
+    child_policy: Mutex<Option<Arc<dyn LbPolicyBuilder>>>,
+}
+
+impl ResolverUpdateSharder<&'static str> for Arc<Sharder> {
+    fn shard_update(
+        &self,
+        resolver_update: ResolverUpdate,
+    ) -> Result<Box<dyn Iterator<Item = ChildUpdate<&'static str>>>, Box<dyn Error + Send + Sync>>
+    {
+        let child_policy = self
+            .child_policy
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or("circuit breaking policy received no config")?;
+        Ok(Box::new(std::iter::once(ChildUpdate {
+            child_identifier: child_policy.name(),
+            child_policy_builder: child_policy,
+            child_update: resolver_update,
+        })))
+    }
+}
+
+struct CircuitBreakingPolicy {
+    child_manager: ChildManager<&'static str>,
+    sharder: Arc<Sharder>,
+    // Outlives the pickers of the child, so that the count of calls in flight
+    // is kept across picker updates.
+    breaker: Arc<CircuitBreaker>,
+}
+
+impl CircuitBreakingPolicy {
+    // Forwards the state of the only child to the channel, limiting the picks
+    // of its picker.
+    fn update_state(&mut self, channel_controller: &mut dyn ChannelController) {
+        if let Some((_, state)) = self.child_manager.child_states().next() {
+            let picker = self.breaker.picker(state.picker.clone());
+            channel_controller.update_picker(LbState {
+                connectivity_state: state.connectivity_state,
+                picker,
+            });
+        }
+    }
+}
+
+impl LbPolicy for CircuitBreakingPolicy {
+    fn resolver_update(
+        &mut self,
+        update: ResolverUpdate,
+        config: Option<&LbConfig>,
+        channel_controller: &mut dyn ChannelController,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let config = CircuitBreakingConfig::from_lb_config(config)?;
+        self.breaker.set_max_requests(config.max_requests);
+        *self.sharder.child_policy.lock().unwrap() = Some(config.child_policy.clone());
+        // TODO: support configuration of the child policy.
+        self.child_manager
+            .resolver_update(update, None, channel_controller)?;
+        self.update_state(channel_controller);
+        Ok(())
+    }
+
+    fn subchannel_update(
+        &mut self,
+        subchannel: Arc<dyn Subchannel>,
+        state: &SubchannelState,
+        channel_controller: &mut dyn ChannelController,
+    ) {
+        self.child_manager
+            .subchannel_update(subchannel, state, channel_controller);
+        self.update_state(channel_controller);
+    }
+
+    fn work(&mut self, channel_controller: &mut dyn ChannelController) {
+        self.child_manager.work(channel_controller);
+        self.update_state(channel_controller);
+    }
+
+    fn exit_idle(&mut self, channel_controller: &mut dyn ChannelController) {
+        self.child_manager.exit_idle(channel_controller);
+        self.update_state(channel_controller);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use tokio::sync::mpsc;
+    use tonic::{metadata::MetadataMap, Code, Status};
+
+    use crate::client::load_balancing::{
+        test_utils::{new_request, TestChannelController},
+        CallOutcome, ChannelController, Pick, PickResult, Picker, Subchannel,
+    };
+    use crate::client::name_resolution::Address;
+
+    use super::CircuitBreaker;
+
+    // Picks the same subchannel every time, counting completed calls.
+    struct FixedPicker {
+        subchannel: Arc<dyn Subchannel>,
+        completed: Arc<AtomicUsize>,
+    }
+
+    impl Picker for FixedPicker {
+        fn pick(&self, _: &crate::service::Request) -> PickResult {
+            let completed = self.completed.clone();
+            PickResult::Pick(Pick {
+                subchannel: self.subchannel.clone(),
+                metadata: MetadataMap::new(),
+                on_complete: Some(Box::new(move |_| {
+                    completed.fetch_add(1, Ordering::SeqCst);
+                })),
+            })
+        }
+    }
+
+    fn fixed_picker(completed: Arc<AtomicUsize>) -> Arc<dyn Picker> {
+        let (tx_events, _rx_events) = mpsc::unbounded_channel();
+        let mut controller = TestChannelController { tx_events };
+        Arc::new(FixedPicker {
+            subchannel: controller.new_subchannel(&Address::default()),
+            completed,
+        })
+    }
+
+    fn complete(result: PickResult) {
+        let PickResult::Pick(pick) = result else {
+            panic!("got {result}, want a pick");
+        };
+        let status = Status::ok("");
+        let headers = MetadataMap::new();
+        (pick.on_complete.unwrap())(&CallOutcome {
+            status: &status,
+            headers: &headers,
+            bytes_sent: 0,
+            bytes_received: 0,
+            latency: Default::default(),
+        });
+    }
+
+    #[test]
+    fn picks_over_the_limit_are_dropped() {
+        let breaker = CircuitBreaker::new(2);
+        let completed = Arc::new(AtomicUsize::new(0));
+        let picker = breaker.picker(fixed_picker(completed.clone()));
+        let first = picker.pick(&new_request());
+        let second = picker.pick(&new_request());
+        assert_eq!(breaker.in_flight(), 2);
+
+        let PickResult::Drop(status) = picker.pick(&new_request()) else {
+            panic!("pick over the limit was not dropped");
+        };
+        assert_eq!(status.code(), Code::Unavailable);
+        assert_eq!(breaker.dropped(), 1);
+
+        // Completing a call frees its slot and still reports to the child.
+        complete(first);
+        assert_eq!(breaker.in_flight(), 1);
+        assert_eq!(completed.load(Ordering::SeqCst), 1);
+        let third = picker.pick(&new_request());
+        assert!(matches!(third, PickResult::Pick(_)));
+
+        // Picks which are discarded free their slots too.
+        drop(second);
+        drop(third);
+        assert_eq!(breaker.in_flight(), 0);
+        assert_eq!(breaker.dropped(), 1);
+    }
+
+    #[test]
+    fn counters_are_shared_across_pickers() {
+        let breaker = CircuitBreaker::new(1);
+        let old = breaker.picker(fixed_picker(Arc::default()));
+        let new = breaker.picker(fixed_picker(Arc::default()));
+        let pick = old.pick(&new_request());
+        assert!(matches!(new.pick(&new_request()), PickResult::Drop(_)));
+
+        // Raising the limit applies to existing pickers.
+        breaker.set_max_requests(2);
+        assert!(matches!(old.pick(&new_request()), PickResult::Pick(_)));
+        drop(pick);
+        assert_eq!(breaker.in_flight(), 0);
+    }
+}
@@ -0,0 +1,276 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! Reports the outcomes of calls to the LB policies which picked their
+//! subchannels, through the [`CompletionCallback`] of each pick.
+
+use std::{
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use tokio_stream::{Stream, StreamExt};
+use tonic::{metadata::MetadataMap, Code, Status};
+
+use super::CompletionCallback;
+use crate::codec::ResponseTrailers;
+use crate::service::{Message, Request, Response};
+use crate::stats::message_size;
+
+/// The outcome of a call, passed to the [`CompletionCallback`] of the pick
+/// which routed it.  Each attempt of a call is reported separately.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct CallOutcome<'a> {
+    /// The status the call completed with.  Its metadata hold the trailers
+    /// of the response, if the transport received any.
+    pub status: &'a Status,
+    /// The headers of the response.
+    pub headers: &'a MetadataMap,
+    /// The total size of the serialized request messages sent.
+    pub bytes_sent: usize,
+    /// The total size of the serialized response messages received.
+    pub bytes_received: usize,
+    /// The time from the call being started on the subchannel until it
+    /// completed.
+    pub latency: Duration,
+}
+
+/// Invokes the completion callback of a pick once the call it routed
+/// completes, or with CANCELLED if the response is dropped before then.
+pub(crate) struct CompletionRecorder {
+    callback: CompletionCallback,
+    start: Instant,
+    headers: Mutex<MetadataMap>,
+    trailers: Mutex<Option<ResponseTrailers>>,
+    // Shared with the request stream, which may outlive the call.
+    bytes_sent: Arc<AtomicUsize>,
+    bytes_received: AtomicUsize,
+    completed: AtomicBool,
+}
+
+impl CompletionRecorder {
+    pub(crate) fn new(callback: CompletionCallback) -> Arc<Self> {
+        Arc::new(Self {
+            callback,
+            start: Instant::now(),
+            headers: Mutex::default(),
+            trailers: Mutex::default(),
+            bytes_sent: Arc::default(),
+            bytes_received: AtomicUsize::new(0),
+            completed: AtomicBool::new(false),
+        })
+    }
+
+    /// Counts the bytes of the messages of request as they are sent.
+    pub(crate) fn request(&self, request: Request) -> Request {
+        let bytes_sent = self.bytes_sent.clone();
+        request.map(|inner| {
+            Box::pin(inner.map(move |msg| {
+                let size = message_size(msg.as_ref()).unwrap_or(0);
+                bytes_sent.fetch_add(size, Ordering::Relaxed);
+                msg
+            })) as Pin<Box<dyn Stream<Item = Box<dyn Message>> + Send + Sync>>
+        })
+    }
+
+    /// Counts the bytes of the messages of response, and reports the outcome
+    /// of the call once its stream completes.
+    pub(crate) fn response(self: &Arc<Self>, response: Response) -> Response {
+        *self.headers.lock().unwrap() = response.metadata().clone();
+        *self.trailers.lock().unwrap() = response.extensions().get::<ResponseTrailers>().cloned();
+        let recorder = self.clone();
+        response.map(|inner| {
+            Box::pin(CompletionStream { inner, recorder })
+                as Pin<Box<dyn Stream<Item = Result<Box<dyn Message>, Status>> + Send>>
+        })
+    }
+
+    fn complete(&self, status: &Status) {
+        if self.completed.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        (self.callback)(&CallOutcome {
+            status,
+            headers: &self.headers.lock().unwrap(),
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+            latency: self.start.elapsed(),
+        });
+    }
+}
+
+impl Drop for CompletionRecorder {
+    fn drop(&mut self) {
+        self.complete(&Status::cancelled("call abandoned before completion"));
+    }
+}
+
+pin_project_lite::pin_project! {
+    // Counts the messages of a response stream, and reports the outcome of
+    // the call when the stream completes.
+    struct CompletionStream<S> {
+        #[pin]
+        inner: S,
+        recorder: Arc<CompletionRecorder>,
+    }
+}
+
+impl<S: Stream<Item = Result<Box<dyn Message>, Status>>> Stream for CompletionStream<S> {
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let item = this.inner.poll_next(cx);
+        match &item {
+            Poll::Ready(Some(Ok(msg))) => {
+                let size = message_size(msg.as_ref()).unwrap_or(0);
+                this.recorder
+                    .bytes_received
+                    .fetch_add(size, Ordering::Relaxed);
+            }
+            Poll::Ready(Some(Err(status))) => this.recorder.complete(status),
+            Poll::Ready(None) => {
+                let mut status = Status::new(Code::Ok, "");
+                if let Some(trailers) = this.recorder.trailers.lock().unwrap().as_ref() {
+                    *status.metadata_mut() = trailers.get().cloned().unwrap_or_default();
+                }
+                this.recorder.complete(&status);
+            }
+            Poll::Pending => {}
+        }
+        item
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use bytes::Bytes;
+    use tokio_stream::StreamExt;
+    use tonic::{metadata::MetadataValue, Code, Status};
+
+    use super::CompletionRecorder;
+    use crate::codec::trailers_from_status;
+    use crate::service::{Message, Request, Response};
+
+    // The code, "x-load" header and sizes of each reported outcome.
+    type Outcomes = Arc<Mutex<Vec<(Code, Option<String>, usize, usize)>>>;
+
+    fn recorder(outcomes: &Outcomes) -> Arc<CompletionRecorder> {
+        let outcomes = outcomes.clone();
+        CompletionRecorder::new(Box::new(move |outcome| {
+            let load = outcome
+                .headers
+                .get("x-load")
+                .map(|v| v.to_str().unwrap().to_string());
+            outcomes.lock().unwrap().push((
+                outcome.status.code(),
+                load,
+                outcome.bytes_sent,
+                outcome.bytes_received,
+            ));
+        }))
+    }
+
+    fn bytes(data: &'static [u8]) -> Box<dyn Message> {
+        Box::new(Bytes::from_static(data))
+    }
+
+    #[tokio::test]
+    async fn reports_outcome_when_response_completes() {
+        let outcomes = Outcomes::default();
+        let recorder = recorder(&outcomes);
+        let request = Request::new(Box::pin(tokio_stream::iter([bytes(b"abc"), bytes(b"de")])));
+        let mut request = recorder.request(request);
+        while request.get_mut().next().await.is_some() {}
+
+        let mut response = Response::new(Box::pin(tokio_stream::iter([
+            Ok(bytes(b"hello")),
+            Err(Status::unavailable("gone")),
+        ])));
+        response
+            .metadata_mut()
+            .insert("x-load", MetadataValue::from_static("7"));
+        let mut response = recorder.response(response).into_inner();
+        drop(recorder);
+        assert!(response.next().await.unwrap().is_ok());
+        assert!(outcomes.lock().unwrap().is_empty());
+        assert!(response.next().await.unwrap().is_err());
+        assert_eq!(
+            *outcomes.lock().unwrap(),
+            [(Code::Unavailable, Some("7".to_string()), 5, 5)]
+        );
+
+        // Each call is only reported once.
+        drop(response);
+        assert_eq!(outcomes.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn reports_trailers_of_successful_calls() {
+        let trailers = Arc::new(Mutex::new(None));
+        let recorder = CompletionRecorder::new(Box::new({
+            let trailers = trailers.clone();
+            move |outcome| {
+                assert_eq!(outcome.status.code(), Code::Ok);
+                *trailers.lock().unwrap() = Some(outcome.status.metadata().clone());
+            }
+        }));
+
+        // A server sends trailers as a final OK status.
+        let mut status = Status::new(Code::Ok, "");
+        status
+            .metadata_mut()
+            .insert("x-load", MetadataValue::from_static("3"));
+        let response = Response::new(Box::pin(tokio_stream::iter([
+            Ok(bytes(b"hello")),
+            Err(status),
+        ])));
+        let response = recorder.response(trailers_from_status(response));
+        drop(recorder);
+        let items: Vec<_> = response.into_inner().collect().await;
+        assert_eq!(items.len(), 1);
+        assert!(items[0].is_ok());
+        let trailers = trailers.lock().unwrap().take().unwrap();
+        assert_eq!(trailers.get("x-load").unwrap(), "3");
+    }
+
+    #[tokio::test]
+    async fn reports_abandoned_calls_as_cancelled() {
+        let outcomes = Outcomes::default();
+        let recorder = recorder(&outcomes);
+        let response = recorder.response(Response::new(Box::pin(tokio_stream::pending())));
+        drop(recorder);
+        assert!(outcomes.lock().unwrap().is_empty());
+        drop(response);
+        assert_eq!(*outcomes.lock().unwrap(), [(Code::Cancelled, None, 0, 0)]);
+    }
+}
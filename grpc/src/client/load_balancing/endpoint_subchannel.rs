@@ -0,0 +1,254 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! A helper which treats all the addresses of an Endpoint as one logical
+//! connection target.
+//!
+//! Policies which balance load across endpoints (e.g. round_robin) delegate
+//! to a pick_first-like policy per endpoint to select one of its addresses.
+//! EndpointSubchannel implements that selection so such policies don't need
+//! to reimplement address iteration.
+
+use std::sync::Arc;
+
+use crate::client::{name_resolution::Endpoint, ConnectivityState};
+
+use super::{ChannelController, Subchannel, SubchannelState};
+
+/// Connects to the addresses of an Endpoint in order, and uses the first one
+/// which becomes Ready.
+///
+/// The endpoint's state is:
+///
+/// - Idle until connect() is called, and again after its connection is lost.
+/// - Connecting while attempting its addresses in order.
+/// - Ready once one of its addresses is connected.
+/// - TransientFailure once every address failed.  The endpoint then remains
+///   in TransientFailure, reconnecting to its addresses as their backoff
+///   expires, until one of them becomes Ready.
+pub struct EndpointSubchannel {
+    endpoint: Endpoint,
+    subchannels: Vec<Arc<dyn Subchannel>>,
+    // The index of the address currently being attempted while Connecting.
+    attempt: usize,
+    state: ConnectivityState,
+    ready: Option<usize>,
+}
+
+impl EndpointSubchannel {
+    /// Creates subchannels for all the addresses of endpoint.  The endpoint
+    /// starts Idle and does not connect until connect() is called.
+    pub fn new(endpoint: Endpoint, channel_controller: &mut dyn ChannelController) -> Self {
+        let subchannels = endpoint
+            .addresses
+            .iter()
+            .map(|address| channel_controller.new_subchannel(address))
+            .collect();
+        Self {
+            endpoint,
+            subchannels,
+            attempt: 0,
+            state: ConnectivityState::Idle,
+            ready: None,
+        }
+    }
+
+    /// Returns the endpoint.
+    pub fn endpoint(&self) -> &Endpoint {
+        &self.endpoint
+    }
+
+    /// Returns the current state of the endpoint.
+    pub fn connectivity_state(&self) -> ConnectivityState {
+        self.state
+    }
+
+    /// Returns the connected subchannel, if the endpoint is Ready.
+    pub fn ready_subchannel(&self) -> Option<&Arc<dyn Subchannel>> {
+        self.ready.map(|i| &self.subchannels[i])
+    }
+
+    /// Returns true if subchannel belongs to this endpoint.
+    pub fn owns(&self, subchannel: &Arc<dyn Subchannel>) -> bool {
+        self.index_of(subchannel).is_some()
+    }
+
+    /// Starts connecting to the endpoint's first address if it is Idle.
+    pub fn connect(&mut self) {
+        if self.state != ConnectivityState::Idle || self.subchannels.is_empty() {
+            return;
+        }
+        self.state = ConnectivityState::Connecting;
+        self.attempt = 0;
+        self.subchannels[0].connect();
+    }
+
+    /// Handles a state change of one of the endpoint's subchannels.  Returns
+    /// true if the endpoint's state changed, in which case the caller should
+    /// produce a new picker.  Updates for subchannels not owned by the endpoint
+    /// are ignored.
+    pub fn subchannel_update(
+        &mut self,
+        subchannel: &Arc<dyn Subchannel>,
+        state: &SubchannelState,
+    ) -> bool {
+        let Some(idx) = self.index_of(subchannel) else {
+            return false;
+        };
+        let old_state = self.state;
+        match state.connectivity_state {
+            ConnectivityState::Ready => {
+                self.ready = Some(idx);
+                self.state = ConnectivityState::Ready;
+            }
+            ConnectivityState::TransientFailure => {
+                if self.state == ConnectivityState::Connecting && idx == self.attempt {
+                    self.attempt += 1;
+                    if self.attempt < self.subchannels.len() {
+                        self.subchannels[self.attempt].connect();
+                    } else {
+                        self.state = ConnectivityState::TransientFailure;
+                    }
+                }
+            }
+            ConnectivityState::Idle => {
+                if self.ready == Some(idx) {
+                    // The connection was lost.
+                    self.ready = None;
+                    self.state = ConnectivityState::Idle;
+                } else if self.state == ConnectivityState::TransientFailure {
+                    // Keep trying addresses whose backoff expired.
+                    subchannel.connect();
+                }
+            }
+            ConnectivityState::Connecting | ConnectivityState::Shutdown => {}
+        }
+        self.state != old_state
+    }
+
+    fn index_of(&self, subchannel: &Arc<dyn Subchannel>) -> Option<usize> {
+        self.subchannels
+            .iter()
+            .position(|sc| Arc::as_ptr(sc) as *const () == Arc::as_ptr(subchannel) as *const ())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use tokio::sync::mpsc;
+
+    use crate::client::{
+        load_balancing::{
+            test_utils::{TestChannelController, TestEvent},
+            Subchannel, SubchannelState,
+        },
+        name_resolution::{Address, Endpoint},
+        ConnectivityState::{self, *},
+    };
+
+    use super::EndpointSubchannel;
+
+    fn state(connectivity_state: ConnectivityState) -> SubchannelState {
+        SubchannelState {
+            connectivity_state,
+            ..Default::default()
+        }
+    }
+
+    fn setup() -> (
+        EndpointSubchannel,
+        Vec<Arc<dyn Subchannel>>,
+        mpsc::UnboundedReceiver<TestEvent>,
+    ) {
+        let (tx_events, mut rx_events) = mpsc::unbounded_channel();
+        let mut channel_controller = TestChannelController { tx_events };
+        let endpoint = Endpoint::builder()
+            .address(Address::new("test", "a"))
+            .address(Address::new("test", "b"))
+            .build()
+            .unwrap();
+        let esc = EndpointSubchannel::new(endpoint, &mut channel_controller);
+        let mut subchannels = vec![];
+        while let Ok(event) = rx_events.try_recv() {
+            if let TestEvent::NewSubchannel(sc) = event {
+                subchannels.push(sc);
+            }
+        }
+        (esc, subchannels, rx_events)
+    }
+
+    fn connects(rx_events: &mut mpsc::UnboundedReceiver<TestEvent>) -> Vec<String> {
+        let mut addrs = vec![];
+        while let Ok(event) = rx_events.try_recv() {
+            if let TestEvent::Connect(addr) = event {
+                addrs.push(addr.address.to_string());
+            }
+        }
+        addrs
+    }
+
+    #[test]
+    fn tries_addresses_in_order() {
+        let (mut esc, scs, mut rx_events) = setup();
+        assert_eq!(scs.len(), 2);
+        assert_eq!(esc.connectivity_state(), Idle);
+        assert!(connects(&mut rx_events).is_empty());
+
+        esc.connect();
+        assert_eq!(esc.connectivity_state(), Connecting);
+        assert_eq!(connects(&mut rx_events), ["a"]);
+
+        assert!(!esc.subchannel_update(&scs[0], &state(TransientFailure)));
+        assert_eq!(connects(&mut rx_events), ["b"]);
+
+        assert!(esc.subchannel_update(&scs[1], &state(Ready)));
+        assert_eq!(esc.connectivity_state(), Ready);
+        assert!(Arc::ptr_eq(esc.ready_subchannel().unwrap(), &scs[1]));
+
+        // Losing the connection makes the endpoint idle.
+        assert!(esc.subchannel_update(&scs[1], &state(Idle)));
+        assert_eq!(esc.connectivity_state(), Idle);
+        assert!(esc.ready_subchannel().is_none());
+    }
+
+    #[test]
+    fn transient_failure_is_sticky() {
+        let (mut esc, scs, mut rx_events) = setup();
+        esc.connect();
+        esc.subchannel_update(&scs[0], &state(TransientFailure));
+        assert!(esc.subchannel_update(&scs[1], &state(TransientFailure)));
+        assert_eq!(esc.connectivity_state(), TransientFailure);
+        connects(&mut rx_events);
+
+        // Addresses are retried after backoff without leaving TF.
+        assert!(!esc.subchannel_update(&scs[0], &state(Idle)));
+        assert_eq!(connects(&mut rx_events), ["a"]);
+        assert!(!esc.subchannel_update(&scs[0], &state(Connecting)));
+        assert_eq!(esc.connectivity_state(), TransientFailure);
+        assert!(esc.subchannel_update(&scs[0], &state(Ready)));
+        assert_eq!(esc.connectivity_state(), Ready);
+    }
+}
@@ -0,0 +1,553 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! An LB policy which prefers a primary child policy, but fails over to a
+//! fallback child policy when the primary has not been READY for a
+//! configurable amount of time.
+//!
+//! This replaces the fallback behavior that grpclb provided, in a form that
+//! can be combined with any pair of child policies.  The fallback child can be
+//! given a static list of addresses to use in place of the resolver's
+//! addresses.
+
+use std::{
+    error::Error,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use serde::Deserialize;
+
+use crate::client::{
+    name_resolution::{Address, Endpoint, ResolverUpdate, TCP_IP_NETWORK_TYPE},
+    service_config::{parse_duration, LbPolicyConfig},
+    ConnectivityState,
+};
+
+use super::{
+    child_manager::{ChildManager, ChildUpdate, ResolverUpdateSharder},
+    ChannelController, LbConfig, LbPolicy, LbPolicyBuilder, LbPolicyOptions, LbState,
+    ParsedJsonLbConfig, ScheduledWork, Subchannel, SubchannelState, WorkScheduler,
+    GLOBAL_LB_REGISTRY,
+};
+
+pub static POLICY_NAME: &str = "fallback_experimental";
+
+const DEFAULT_FALLBACK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Identifies the children of the fallback policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum FallbackChild {
+    Primary,
+    Fallback,
+}
+
+/// The parsed configuration of the fallback policy.
+pub(crate) struct FallbackConfig {
+    pub(crate) primary: Arc<dyn LbPolicyBuilder>,
+    pub(crate) fallback: Arc<dyn LbPolicyBuilder>,
+    /// How long the primary may go without being READY before failing over.
+    pub(crate) fallback_timeout: Duration,
+    /// If set, these endpoints are given to the fallback child instead of the
+    /// resolver's endpoints.
+    pub(crate) fallback_endpoints: Option<Vec<Endpoint>>,
+}
+
+impl LbPolicyConfig for FallbackConfig {}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonConfig {
+    primary_policy: String,
+    fallback_policy: String,
+    fallback_timeout: Option<String>,
+    fallback_addresses: Option<Vec<String>>,
+}
+
+struct Builder {}
+
+impl LbPolicyBuilder for Builder {
+    fn build(&self, options: LbPolicyOptions) -> Box<dyn LbPolicy> {
+        let sharder = Arc::new(Sharder::default());
+        Box::new(FallbackPolicy {
+            child_manager: ChildManager::new(
+                Box::new(sharder.clone()),
+                options.work_scheduler.clone(),
+                options.runtime,
+            ),
+            sharder,
+            work_scheduler: options.work_scheduler,
+            fallback_timeout: DEFAULT_FALLBACK_TIMEOUT,
+            timer: None,
+            in_fallback: false,
+            last_update: None,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        POLICY_NAME
+    }
+
+    fn parse_config(
+        &self,
+        config: &ParsedJsonLbConfig,
+    ) -> Result<Option<LbConfig>, Box<dyn Error + Send + Sync>> {
+        let cfg: JsonConfig = config.convert_to()?;
+        let lookup = |name: &str| {
+            config
+                .lookup_policy(name)
+                .ok_or_else(|| format!("unknown child policy {name:?}"))
+        };
+        let fallback_timeout = match cfg.fallback_timeout {
+            Some(t) => parse_duration(&t)?,
+            None => DEFAULT_FALLBACK_TIMEOUT,
+        };
+        let fallback_endpoints = cfg
+            .fallback_addresses
+            .map(|addrs| {
+                addrs
+                    .into_iter()
+                    .map(|addr| {
+                        Endpoint::builder()
+                            .address(Address::new(TCP_IP_NETWORK_TYPE, addr))
+                            .build()
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?;
+        Ok(Some(
+            FallbackConfig {
+                primary: lookup(&cfg.primary_policy)?,
+                fallback: lookup(&cfg.fallback_policy)?,
+                fallback_timeout,
+                fallback_endpoints,
+            }
+            .into_lb_config(),
+        ))
+    }
+}
+
+pub fn reg() {
+    GLOBAL_LB_REGISTRY.add_builder(Builder {})
+}
+
+// Shards each resolver update into one update per child.  The child policies
+// come from the most recent config, which the policy stores here before
+// passing the update to the ChildManager.  The fallback child is only
+// included while the policy is falling back, so that it does not connect
+// while the primary is healthy.
+#[derive(Default)]
+struct Sharder {
+    config: Mutex<Option<Arc<FallbackConfig>>>,
+    fallback_active: AtomicBool,
+}
+
+impl ResolverUpdateSharder<FallbackChild> for Arc<Sharder> {
+    fn shard_update(
+        &self,
+        resolver_update: ResolverUpdate,
+    ) -> Result<Box<dyn Iterator<Item = ChildUpdate<FallbackChild>>>, Box<dyn Error + Send + Sync>>
+    {
+        let config = self
+            .config
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or("fallback policy received no config")?;
+        let fallback_update = self.fallback_active.load(Ordering::Relaxed).then(|| {
+            let mut fallback_update = resolver_update.clone();
+            if let Some(endpoints) = &config.fallback_endpoints {
+                fallback_update.endpoints = Ok(endpoints.clone());
+            }
+            fallback_update
+        });
+        let mut updates = vec![ChildUpdate {
+            child_identifier: FallbackChild::Primary,
+            child_policy_builder: config.primary.clone(),
+            child_update: resolver_update,
+        }];
+        if let Some(fallback_update) = fallback_update {
+            updates.push(ChildUpdate {
+                child_identifier: FallbackChild::Fallback,
+                child_policy_builder: config.fallback.clone(),
+                child_update: fallback_update,
+            });
+        }
+        Ok(Box::new(updates.into_iter()))
+    }
+}
+
+pub(crate) struct FallbackPolicy {
+    child_manager: ChildManager<FallbackChild>,
+    sharder: Arc<Sharder>,
+    work_scheduler: Arc<dyn WorkScheduler>,
+    fallback_timeout: Duration,
+    // Running while the primary is not READY and we are not yet in fallback.
+    timer: Option<ScheduledWork>,
+    in_fallback: bool,
+    // Replayed to create the fallback child when falling back.
+    last_update: Option<ResolverUpdate>,
+}
+
+impl FallbackPolicy {
+    fn child_state(&self, id: FallbackChild) -> Option<LbState> {
+        self.child_manager.child_state(&id).cloned()
+    }
+
+    fn start_timer(&mut self) {
+        if self.timer.is_some() {
+            return;
+        }
+        self.timer = Some(
+            self.work_scheduler
+                .schedule_work_after(self.fallback_timeout),
+        );
+    }
+
+    fn stop_timer(&mut self) {
+        self.timer = None;
+    }
+
+    // Creates the fallback child from the most recent resolver update.
+    fn start_fallback(&mut self, channel_controller: &mut dyn ChannelController) {
+        self.sharder.fallback_active.store(true, Ordering::Relaxed);
+        if let Some(update) = self.last_update.clone() {
+            // Any error was already returned when the update was received.
+            let _ = self
+                .child_manager
+                .resolver_update(update, None, channel_controller);
+        }
+    }
+
+    // Decides which child to use and reports its state to the channel.
+    fn update_state(&mut self, channel_controller: &mut dyn ChannelController) {
+        let Some(primary) = self.child_state(FallbackChild::Primary) else {
+            return;
+        };
+        if primary.connectivity_state == ConnectivityState::Ready {
+            self.stop_timer();
+            self.in_fallback = false;
+            // The fallback child is removed on the next resolver update.
+            self.sharder.fallback_active.store(false, Ordering::Relaxed);
+            channel_controller.update_picker(primary);
+            return;
+        }
+        let failed = primary.connectivity_state == ConnectivityState::TransientFailure;
+        if !self.in_fallback && (failed || self.timer.as_ref().is_some_and(|t| t.has_fired())) {
+            self.timer = None;
+            self.in_fallback = true;
+            self.start_fallback(channel_controller);
+        }
+        if self.in_fallback {
+            if let Some(fallback) = self.child_state(FallbackChild::Fallback) {
+                channel_controller.update_picker(fallback);
+            }
+            return;
+        }
+        self.start_timer();
+        channel_controller.update_picker(primary);
+    }
+}
+
+impl LbPolicy for FallbackPolicy {
+    fn resolver_update(
+        &mut self,
+        update: ResolverUpdate,
+        config: Option<&LbConfig>,
+        channel_controller: &mut dyn ChannelController,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let config = FallbackConfig::from_lb_config(config)?;
+        self.fallback_timeout = config.fallback_timeout;
+        *self.sharder.config.lock().unwrap() = Some(config);
+        self.last_update = Some(update.clone());
+        // TODO: support configuration of the child policies.
+        self.child_manager
+            .resolver_update(update, None, channel_controller)?;
+        self.update_state(channel_controller);
+        Ok(())
+    }
+
+    fn subchannel_update(
+        &mut self,
+        subchannel: Arc<dyn Subchannel>,
+        state: &SubchannelState,
+        channel_controller: &mut dyn ChannelController,
+    ) {
+        self.child_manager
+            .subchannel_update(subchannel, state, channel_controller);
+        self.update_state(channel_controller);
+    }
+
+    fn work(&mut self, channel_controller: &mut dyn ChannelController) {
+        self.child_manager.work(channel_controller);
+        self.update_state(channel_controller);
+    }
+
+    fn exit_idle(&mut self, channel_controller: &mut dyn ChannelController) {
+        self.child_manager.exit_idle(channel_controller);
+        self.update_state(channel_controller);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{error::Error, sync::Arc, time::Duration};
+
+    use tokio::sync::mpsc;
+
+    use crate::{
+        client::{
+            load_balancing::{
+                test_utils::{TestChannelController, TestEvent, TestWorkScheduler},
+                ChannelController, LbConfig, LbPolicy, LbPolicyBuilder, LbPolicyOptions,
+                LbPolicyRegistry, LbState, ParsedJsonLbConfig, QueuingPicker, Subchannel,
+                SubchannelState,
+            },
+            name_resolution::{Address, Endpoint, ResolverUpdate},
+            ConnectivityState,
+        },
+        rt::tokio::TokioRuntime,
+    };
+
+    use super::{Builder, FallbackConfig};
+
+    // A child policy which connects to the first address it is given and
+    // reports the state of that subchannel as its own.  Later updates reuse
+    // the subchannel.
+    struct StubBuilder {}
+
+    impl LbPolicyBuilder for StubBuilder {
+        fn build(&self, _: LbPolicyOptions) -> Box<dyn LbPolicy> {
+            Box::new(StubPolicy { subchannel: None })
+        }
+
+        fn name(&self) -> &'static str {
+            "stub"
+        }
+    }
+
+    struct StubPolicy {
+        subchannel: Option<Arc<dyn Subchannel>>,
+    }
+
+    fn report(channel_controller: &mut dyn ChannelController, state: ConnectivityState) {
+        channel_controller.update_picker(LbState {
+            connectivity_state: state,
+            picker: Arc::new(QueuingPicker {}),
+        });
+    }
+
+    impl LbPolicy for StubPolicy {
+        fn resolver_update(
+            &mut self,
+            update: ResolverUpdate,
+            _: Option<&LbConfig>,
+            channel_controller: &mut dyn ChannelController,
+        ) -> Result<(), Box<dyn Error + Send + Sync>> {
+            if self.subchannel.is_none() {
+                let address = &update.endpoints.unwrap()[0].addresses[0];
+                self.subchannel = Some(channel_controller.new_subchannel(address));
+            }
+            report(channel_controller, ConnectivityState::Connecting);
+            Ok(())
+        }
+
+        fn subchannel_update(
+            &mut self,
+            _: Arc<dyn Subchannel>,
+            state: &SubchannelState,
+            channel_controller: &mut dyn ChannelController,
+        ) {
+            report(channel_controller, state.connectivity_state);
+        }
+
+        fn work(&mut self, _: &mut dyn ChannelController) {}
+
+        fn exit_idle(&mut self, _: &mut dyn ChannelController) {}
+    }
+
+    fn endpoint(addr: &str) -> Endpoint {
+        Endpoint {
+            addresses: vec![Address {
+                address: addr.to_string().into(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn ready() -> SubchannelState {
+        SubchannelState {
+            connectivity_state: ConnectivityState::Ready,
+            ..Default::default()
+        }
+    }
+
+    fn transient_failure() -> SubchannelState {
+        SubchannelState {
+            connectivity_state: ConnectivityState::TransientFailure,
+            ..Default::default()
+        }
+    }
+
+    async fn next_subchannel(
+        rx: &mut mpsc::UnboundedReceiver<TestEvent>,
+        addr: &str,
+    ) -> Arc<dyn Subchannel> {
+        loop {
+            if let TestEvent::NewSubchannel(sc) = rx.recv().await.unwrap() {
+                if &*sc.address().address == addr {
+                    return sc;
+                }
+            }
+        }
+    }
+
+    fn build_policy(
+        tx_events: mpsc::UnboundedSender<TestEvent>,
+        fallback_timeout: Duration,
+    ) -> (Box<dyn LbPolicy>, LbConfig) {
+        let policy = Builder {}.build(LbPolicyOptions {
+            work_scheduler: Arc::new(TestWorkScheduler { tx_events }),
+            runtime: Arc::new(TokioRuntime {}),
+        });
+        let config = LbConfig::new(FallbackConfig {
+            primary: Arc::new(StubBuilder {}),
+            fallback: Arc::new(StubBuilder {}),
+            fallback_timeout,
+            fallback_endpoints: Some(vec![endpoint("fallback:1")]),
+        });
+        (policy, config)
+    }
+
+    async fn next_state(rx: &mut mpsc::UnboundedReceiver<TestEvent>) -> ConnectivityState {
+        loop {
+            if let TestEvent::UpdatePicker(state) = rx.recv().await.unwrap() {
+                return state.connectivity_state;
+            }
+        }
+    }
+
+    #[test]
+    fn child_policies_are_looked_up_in_channel_registry() {
+        let config = serde_json::json!({"primaryPolicy": "stub", "fallbackPolicy": "stub"});
+        let registry = LbPolicyRegistry::new();
+        let err = Builder {}
+            .parse_config(&ParsedJsonLbConfig::from_value(config.clone(), &registry))
+            .unwrap_err();
+        assert!(err.to_string().contains("unknown child policy"));
+
+        registry.add_builder(StubBuilder {});
+        let parsed = Builder {}
+            .parse_config(&ParsedJsonLbConfig::from_value(config, &registry))
+            .unwrap()
+            .unwrap();
+        let parsed = parsed.convert_to::<FallbackConfig>().unwrap();
+        assert_eq!(parsed.primary.name(), "stub");
+        assert_eq!(parsed.fallback.name(), "stub");
+    }
+
+    #[tokio::test]
+    async fn falls_back_after_timeout() {
+        let (tx_events, mut rx_events) = mpsc::unbounded_channel();
+        let mut channel_controller = TestChannelController {
+            tx_events: tx_events.clone(),
+        };
+        let (mut policy, config) = build_policy(tx_events, Duration::from_millis(10));
+        let update = ResolverUpdate {
+            endpoints: Ok(vec![endpoint("primary:1")]),
+            ..Default::default()
+        };
+        policy
+            .resolver_update(update, Some(&config), &mut channel_controller)
+            .unwrap();
+
+        let primary = next_subchannel(&mut rx_events, "primary:1").await;
+        assert_eq!(
+            next_state(&mut rx_events).await,
+            ConnectivityState::Connecting
+        );
+
+        // Once the timer fires, the fallback child is created and its state
+        // is used.
+        loop {
+            if let TestEvent::ScheduleWork = rx_events.recv().await.unwrap() {
+                break;
+            }
+        }
+        policy.work(&mut channel_controller);
+        let fallback = next_subchannel(&mut rx_events, "fallback:1").await;
+        assert_eq!(
+            next_state(&mut rx_events).await,
+            ConnectivityState::Connecting
+        );
+        policy.subchannel_update(fallback, &ready(), &mut channel_controller);
+        assert_eq!(next_state(&mut rx_events).await, ConnectivityState::Ready);
+
+        // The primary becoming ready switches back to it.
+        policy.subchannel_update(primary, &ready(), &mut channel_controller);
+        assert_eq!(next_state(&mut rx_events).await, ConnectivityState::Ready);
+    }
+
+    #[tokio::test]
+    async fn fallback_is_created_only_when_primary_fails() {
+        let (tx_events, mut rx_events) = mpsc::unbounded_channel();
+        let mut channel_controller = TestChannelController {
+            tx_events: tx_events.clone(),
+        };
+        let (mut policy, config) = build_policy(tx_events, Duration::from_secs(3600));
+        let update = ResolverUpdate {
+            endpoints: Ok(vec![endpoint("primary:1")]),
+            ..Default::default()
+        };
+        policy
+            .resolver_update(update.clone(), Some(&config), &mut channel_controller)
+            .unwrap();
+        let primary = next_subchannel(&mut rx_events, "primary:1").await;
+        policy.subchannel_update(primary.clone(), &ready(), &mut channel_controller);
+        policy
+            .resolver_update(update, Some(&config), &mut channel_controller)
+            .unwrap();
+
+        // While the primary is healthy, the fallback creates no subchannels.
+        while let Ok(event) = rx_events.try_recv() {
+            if let TestEvent::NewSubchannel(sc) = event {
+                assert_eq!(&*sc.address().address, "primary:1");
+            }
+        }
+
+        // The primary failing falls back without waiting for the timer.
+        policy.subchannel_update(primary, &transient_failure(), &mut channel_controller);
+        let fallback = next_subchannel(&mut rx_events, "fallback:1").await;
+        assert_eq!(
+            next_state(&mut rx_events).await,
+            ConnectivityState::Connecting
+        );
+        policy.subchannel_update(fallback, &ready(), &mut channel_controller);
+        assert_eq!(next_state(&mut rx_events).await, ConnectivityState::Ready);
+    }
+}
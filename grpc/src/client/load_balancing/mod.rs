@@ -32,16 +32,17 @@ use std::{
     hash::{Hash, Hasher},
     ops::{Add, Sub},
     sync::{
-        atomic::{AtomicI64, Ordering::Relaxed},
+        atomic::{AtomicBool, AtomicI64, Ordering::Relaxed},
         Arc, Mutex, Weak,
     },
+    time::Duration,
 };
 use tokio::sync::{mpsc::Sender, Notify};
 use tonic::{metadata::MetadataMap, Status};
 
 use crate::{
     client::channel::WorkQueueTx,
-    rt::Runtime,
+    rt::{BoxedTaskHandle, Runtime},
     service::{Request, Response, Service},
 };
 
@@ -49,17 +50,31 @@ use crate::client::{
     channel::{InternalChannelController, WorkQueueItem},
     name_resolution::{Address, ResolverUpdate},
     subchannel::InternalSubchannel,
+    transport::TransportInfo,
+    work_queue::WorkItemKind,
     ConnectivityState,
 };
 
 pub mod child_manager;
+pub mod circuit_breaking;
+mod completion;
+pub mod endpoint_subchannel;
+pub mod fallback;
+pub mod override_host;
 pub mod pick_first;
+mod picker_benchmark;
+pub mod subsetting;
 #[cfg(test)]
 pub mod test_utils;
 
 pub(crate) mod registry;
 use super::{service_config::LbConfig, subchannel::SubchannelStateWatcher};
-pub(crate) use registry::{LbPolicyRegistry, GLOBAL_LB_REGISTRY};
+pub use completion::CallOutcome;
+pub(crate) use completion::CompletionRecorder;
+pub use endpoint_subchannel::EndpointSubchannel;
+pub use picker_benchmark::{PickerBenchmark, PickerBenchmarkResult};
+pub use registry::LbPolicyRegistry;
+pub(crate) use registry::GLOBAL_LB_REGISTRY;
 
 /// A collection of data configured on the channel that is constructing this
 /// LbPolicy.
@@ -67,7 +82,8 @@ pub struct LbPolicyOptions {
     /// A hook into the channel's work scheduler that allows the LbPolicy to
     /// request the ability to perform operations on the ChannelController.
     pub work_scheduler: Arc<dyn WorkScheduler>,
-    pub runtime: Arc<dyn Runtime>,
+    // TODO: make this public along with the runtime API.
+    pub(crate) runtime: Arc<dyn Runtime>,
 }
 
 /// Used to asynchronously request a call into the LbPolicy's work method if
@@ -78,27 +94,102 @@ pub trait WorkScheduler: Send + Sync {
     // pending work call that has not yet started, this may not schedule another
     // call.
     fn schedule_work(&self);
+
+    // Schedules a call into the LbPolicy's work method once delay has
+    // elapsed.  The call is cancelled if the returned handle is cancelled or
+    // dropped before then.  Policies should use this instead of spawning
+    // their own timer tasks.
+    fn schedule_work_after(&self, delay: Duration) -> ScheduledWork;
+}
+
+/// A handle to a delayed call into an LbPolicy's work method, returned by
+/// [`WorkScheduler::schedule_work_after`].  Dropping the handle cancels the
+/// call if it has not yet been scheduled.
+pub struct ScheduledWork {
+    fired: Arc<AtomicBool>,
+    task: Option<BoxedTaskHandle>,
+}
+
+impl ScheduledWork {
+    /// Spawns a task on runtime which calls schedule once delay has elapsed.
+    pub(crate) fn spawn(
+        runtime: &dyn Runtime,
+        delay: Duration,
+        schedule: impl FnOnce() + Send + 'static,
+    ) -> Self {
+        let fired = Arc::new(AtomicBool::new(false));
+        let sleep = runtime.sleep(delay);
+        let fired_clone = fired.clone();
+        let task = runtime.spawn(Box::pin(async move {
+            sleep.await;
+            fired_clone.store(true, Relaxed);
+            schedule();
+        }));
+        Self {
+            fired,
+            task: Some(task),
+        }
+    }
+
+    /// Returns true once the delay has elapsed and the call into the work
+    /// method has been scheduled.  Allows a policy with several timers to
+    /// determine which of them caused a call to work.
+    pub fn has_fired(&self) -> bool {
+        self.fired.load(Relaxed)
+    }
+
+    /// Cancels the call if it has not yet been scheduled.
+    pub fn cancel(self) {}
+}
+
+impl Drop for ScheduledWork {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+    }
+}
+
+impl Debug for ScheduledWork {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScheduledWork")
+            .field("fired", &self.has_fired())
+            .finish()
+    }
 }
 
 /// Abstract representation of the configuration for any LB policy, stored as
 /// JSON.  Hides internal storage details and includes a method to deserialize
 /// the JSON into a concrete policy struct.
-#[derive(Debug)]
 pub struct ParsedJsonLbConfig {
     value: serde_json::Value,
+    // The registry of the channel whose service config is being parsed.
+    registry: LbPolicyRegistry,
 }
 
 impl ParsedJsonLbConfig {
-    /// Creates a new ParsedJsonLbConfig from the provided JSON string.
+    /// Creates a new ParsedJsonLbConfig from the provided JSON string.  Child
+    /// policies named by the config are looked up in the global registry.
     pub fn new(json: &str) -> Result<Self, String> {
         match serde_json::from_str(json) {
-            Ok(value) => Ok(ParsedJsonLbConfig { value }),
+            Ok(value) => Ok(Self::from_value(value, &LbPolicyRegistry::new())),
             Err(e) => Err(format!("failed to parse LB config JSON: {e}")),
         }
     }
 
-    pub(crate) fn from_value(value: serde_json::Value) -> Self {
-        Self { value }
+    pub(crate) fn from_value(value: serde_json::Value, registry: &LbPolicyRegistry) -> Self {
+        Self {
+            value,
+            registry: registry.clone(),
+        }
+    }
+
+    /// Returns the builder of the policy registered under name, for policies
+    /// whose configuration names child policies.  Policies are looked up in
+    /// the registry of the channel the configuration is for, and then in the
+    /// global registry.
+    pub fn lookup_policy(&self, name: &str) -> Option<Arc<dyn LbPolicyBuilder>> {
+        self.registry.get_policy_or_global(name)
     }
 
     /// Converts the JSON configuration into a concrete type that represents the
@@ -119,9 +210,17 @@ impl ParsedJsonLbConfig {
     }
 }
 
+impl Debug for ParsedJsonLbConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ParsedJsonLbConfig")
+            .field("value", &self.value)
+            .finish_non_exhaustive()
+    }
+}
+
 /// An LB policy factory that produces LbPolicy instances used by the channel
 /// to manage connections and pick connections for RPCs.
-pub(crate) trait LbPolicyBuilder: Send + Sync {
+pub trait LbPolicyBuilder: Send + Sync {
     /// Builds and returns a new LB policy instance.
     ///
     /// Note that build must not fail.  Any optional configuration is delivered
@@ -151,6 +250,13 @@ pub(crate) trait LbPolicyBuilder: Send + Sync {
 /// LB policies are responsible for creating connections (modeled as
 /// Subchannels) and producing Picker instances for picking connections for
 /// RPCs.
+///
+/// The channel shuts an LB policy down by dropping it, which happens when the
+/// channel enters idle, when it switches to a different policy, and when the
+/// channel itself is dropped.  Implementations must release all resources they
+/// hold when dropped: pending timers and spawned tasks must be cancelled, and
+/// subchannels dropped.  No further calls are made into a policy, and any work
+/// it scheduled is discarded, once it is dropped.
 pub trait LbPolicy: Send {
     /// Called by the channel when the name resolver produces a new set of
     /// resolved addresses or a new service config.
@@ -163,6 +269,13 @@ pub trait LbPolicy: Send {
 
     /// Called by the channel when any subchannel created by the LB policy
     /// changes state.
+    ///
+    /// Updates are delivered in the order the channel's work queue observes
+    /// them: a policy never receives an update for a subchannel before the
+    /// call in which it created the subchannel, and never receives updates
+    /// for subchannels created by a policy it replaced.  Changes which arrive
+    /// while an update is still queued are coalesced, so a policy may only see
+    /// the latest state of a subchannel.
     fn subchannel_update(
         &mut self,
         subchannel: Arc<dyn Subchannel>,
@@ -176,6 +289,8 @@ pub trait LbPolicy: Send {
 
     /// Called by the channel when an LbPolicy goes idle and the channel
     /// wants it to start connecting to subchannels again.
+    ///
+    /// Policies which are not idle may ignore this call.
     fn exit_idle(&mut self, channel_controller: &mut dyn ChannelController);
 }
 
@@ -193,15 +308,73 @@ pub trait ChannelController: Send + Sync {
     fn request_resolution(&mut self);
 }
 
+impl ConnectivityState {
+    /// Returns the aggregate connectivity state of a set of children (e.g.
+    /// subchannels or child policies) per the gRPC rules: Ready if any child is
+    /// Ready, otherwise Connecting if any child is Connecting, otherwise Idle if
+    /// any child is Idle, and otherwise TransientFailure.  Children which are
+    /// Shutdown are ignored, and an empty set is TransientFailure.
+    pub fn aggregate(states: impl IntoIterator<Item = ConnectivityState>) -> ConnectivityState {
+        let rank = |state: &ConnectivityState| match state {
+            ConnectivityState::Ready => 3,
+            ConnectivityState::Connecting => 2,
+            ConnectivityState::Idle => 1,
+            ConnectivityState::TransientFailure | ConnectivityState::Shutdown => 0,
+        };
+        states
+            .into_iter()
+            .filter(|state| !state.is_terminal())
+            .max_by_key(rank)
+            .unwrap_or(ConnectivityState::TransientFailure)
+    }
+}
+
 /// Represents the current state of a Subchannel.
+///
+/// New fields may be added in the future; use the accessor methods to read
+/// the state.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct SubchannelState {
     /// The connectivity state of the subchannel.  See SubChannel for a
     /// description of the various states and their valid transitions.
     pub connectivity_state: ConnectivityState,
     // Set if connectivity state is TransientFailure to describe the most recent
-    // connection error.  None for any other connectivity_state value.
+    // connection error, or if it is Idle or Connecting after a connection was
+    // lost to describe why (see ConnectError::disconnect_reason).  None
+    // otherwise.
     pub last_connection_error: Option<Arc<dyn Error + Send + Sync>>,
+    // Set if connectivity state is Ready to describe the connected transport.
+    // None for any other connectivity_state value.
+    pub(crate) transport_info: Option<Arc<TransportInfo>>,
+    // Set if connectivity state is Ready to the address the subchannel
+    // connected to.  None for any other connectivity_state value.
+    pub(crate) connected_address: Option<Address>,
+}
+
+impl SubchannelState {
+    /// Returns the connectivity state of the subchannel.
+    pub fn connectivity_state(&self) -> ConnectivityState {
+        self.connectivity_state
+    }
+
+    /// Returns the most recent connection error if the subchannel is in
+    /// TransientFailure.
+    pub fn last_connection_error(&self) -> Option<&Arc<dyn Error + Send + Sync>> {
+        self.last_connection_error.as_ref()
+    }
+
+    /// Returns metadata about the connected transport if the subchannel is
+    /// Ready.
+    pub fn transport_info(&self) -> Option<&TransportInfo> {
+        self.transport_info.as_deref()
+    }
+
+    /// Returns the address the subchannel connected to if the subchannel is
+    /// Ready, including the attributes provided by the name resolver.
+    pub fn connected_address(&self) -> Option<&Address> {
+        self.connected_address.as_ref()
+    }
 }
 
 impl Default for SubchannelState {
@@ -209,6 +382,8 @@ impl Default for SubchannelState {
         Self {
             connectivity_state: ConnectivityState::Idle,
             last_connection_error: None,
+            transport_info: None,
+            connected_address: None,
         }
     }
 }
@@ -216,6 +391,9 @@ impl Default for SubchannelState {
 impl Display for SubchannelState {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "connectivity_state: {}", self.connectivity_state)?;
+        if let Some(address) = &self.connected_address {
+            write!(f, ", connected_address: {address}")?;
+        }
         if let Some(err) = &self.last_connection_error {
             write!(f, ", last_connection_error: {err}")?;
         }
@@ -242,9 +420,12 @@ impl Display for SubchannelState {
 /// If the ConnectivityState is Ready, the Picker should return a Ready
 /// Subchannel.
 ///
-/// If the ConnectivityState is TransientFailure, the Picker should return an
-/// Err with an error that describes why connections are failing.
-pub trait Picker: Send + Sync {
+/// If the ConnectivityState is TransientFailure, the Picker should return a
+/// Fail result with a status that describes why connections are failing.
+///
+/// Independent of the ConnectivityState, a Picker may return a Drop result to
+/// fail a request outright, e.g. to enforce a drop or circuit breaking policy.
+pub trait Picker: Any + Send + Sync {
     /// Picks a connection to use for the request.
     ///
     /// This function should not block.  If the Picker needs to do blocking or
@@ -252,6 +433,11 @@ pub trait Picker: Send + Sync {
     /// the Pick call will be repeated by the channel when a new Picker is
     /// produced by the LbPolicy.
     fn pick(&self, request: &Request) -> PickResult;
+
+    /// Returns the name of the picker's type, for debugging.
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
 }
 
 pub enum PickResult {
@@ -278,7 +464,7 @@ pub enum PickResult {
 impl PickResult {
     pub fn unwrap_pick(self) -> Pick {
         let PickResult::Pick(pick) = self else {
-            panic!("Called `PickResult::unwrap_pick` on a `Queue` or `Err` value");
+            panic!("Called `PickResult::unwrap_pick` on a `Queue`, `Fail` or `Drop` value");
         };
         pick
     }
@@ -292,14 +478,20 @@ impl PartialEq for PickResult {
                 _ => false,
             },
             PickResult::Queue => matches!(other, PickResult::Queue),
-            PickResult::Fail(status) => {
-                // TODO: implement me.
-                false
-            }
-            PickResult::Drop(status) => {
-                // TODO: implement me.
-                false
-            }
+            PickResult::Fail(status) => match other {
+                PickResult::Fail(other_status) => {
+                    status.code() == other_status.code()
+                        && status.message() == other_status.message()
+                }
+                _ => false,
+            },
+            PickResult::Drop(status) => match other {
+                PickResult::Drop(other_status) => {
+                    status.code() == other_status.code()
+                        && status.message() == other_status.message()
+                }
+                _ => false,
+            },
         }
     }
 }
@@ -330,18 +522,46 @@ impl LbState {
             picker: Arc::new(QueuingPicker {}),
         }
     }
+
+    /// Checks that the picker is consistent with the connectivity state, as
+    /// described by [`Picker`].  Only the pickers provided by this module can
+    /// be checked; other pickers are assumed to be consistent.
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        let picker = self.picker.as_ref() as &dyn Any;
+        match self.connectivity_state {
+            ConnectivityState::Shutdown => Err("LB policies must not report SHUTDOWN".to_string()),
+            ConnectivityState::Ready | ConnectivityState::TransientFailure
+                if picker.is::<QueuingPicker>() =>
+            {
+                Err(format!(
+                    "{} reported with a picker that queues every pick",
+                    self.connectivity_state
+                ))
+            }
+            ConnectivityState::Idle | ConnectivityState::Connecting if picker.is::<Failing>() => {
+                Err(format!(
+                    "{} reported with a picker that fails every pick",
+                    self.connectivity_state
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
 /// Type alias for the completion callback function.
-pub type CompletionCallback = Box<dyn Fn(&Response) + Send + Sync>;
+pub type CompletionCallback = Box<dyn Fn(&CallOutcome<'_>) + Send + Sync>;
 
 /// A collection of data used by the channel for routing a request.
 pub struct Pick {
     /// The Subchannel for the request.
     pub subchannel: Arc<dyn Subchannel>,
-    // Metadata to be added to existing outgoing metadata.
+    /// Metadata to add to the request, e.g. a token identifying the pick to a
+    /// look-aside balancer.  Values are sent after any the application set for
+    /// the same keys.
     pub metadata: MetadataMap,
-    // Callback to be invoked once the RPC completes.
+    /// Callback invoked once with the outcome of the attempt routed by the
+    /// pick, e.g. to track the load of the subchannel.
     pub on_complete: Option<CompletionCallback>,
 }
 
@@ -465,10 +685,10 @@ impl Hash for WeakSubchannel {
 
 impl PartialEq for WeakSubchannel {
     fn eq(&self, other: &Self) -> bool {
-        if let Some(strong) = self.upgrade() {
-            return strong.dyn_eq(&Box::new(other as &dyn Any));
+        match (self.upgrade(), other.upgrade()) {
+            (Some(strong), Some(other)) => strong.as_ref().dyn_eq(&(other.as_ref() as &dyn Any)),
+            _ => false,
         }
-        false
     }
 }
 
@@ -527,16 +747,17 @@ impl Drop for ExternalSubchannel {
         let watcher = self.watcher.lock().unwrap().take();
         let address = self.address().address.clone();
         let isc = self.isc.take();
-        let _ = self.work_scheduler.send(WorkQueueItem::Closure(Box::new(
-            move |c: &mut InternalChannelController| {
+        let _ = self.work_scheduler.send(WorkQueueItem::Closure(
+            WorkItemKind::Cleanup,
+            Box::new(move |c: &mut InternalChannelController| {
                 println!("unregistering connectivity state watcher for {address:?}");
                 isc.as_ref()
                     .unwrap()
                     .unregister_connectivity_state_watcher(watcher.unwrap());
-            },
+            }),
             // The internal subchannel is dropped from here (i.e., from inside
             // the work serializer), if this is the last reference to it.
-        )));
+        ));
     }
 }
 
@@ -593,3 +814,100 @@ impl Picker for Failing {
         PickResult::Fail(Status::unavailable(self.error.clone()))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use tokio::sync::mpsc;
+
+    use std::sync::Arc;
+
+    use super::{
+        test_utils::{TestEvent, TestWorkScheduler},
+        Failing, LbState, Picker, QueuingPicker, WorkScheduler,
+    };
+    use crate::client::ConnectivityState::{self, *};
+
+    #[test]
+    fn aggregate_connectivity_state() {
+        let cases: &[(&[ConnectivityState], ConnectivityState)] = &[
+            (&[], TransientFailure),
+            (&[TransientFailure, TransientFailure], TransientFailure),
+            (&[TransientFailure, Idle], Idle),
+            (&[Idle, Connecting, TransientFailure], Connecting),
+            (&[Connecting, Ready, Idle], Ready),
+            (&[Ready], Ready),
+            (&[Shutdown, Idle], Idle),
+            (&[Shutdown], TransientFailure),
+        ];
+        for (states, want) in cases {
+            assert_eq!(
+                ConnectivityState::aggregate(states.iter().copied()),
+                *want,
+                "{states:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn connectivity_state_transitions() {
+        assert!(Idle.can_transition_to(Connecting));
+        assert!(!Idle.can_transition_to(Ready));
+        assert!(Connecting.can_transition_to(TransientFailure));
+        assert!(Ready.can_transition_to(Idle));
+        assert!(TransientFailure.can_transition_to(Connecting));
+        for state in [Idle, Connecting, Ready, TransientFailure] {
+            assert!(state.can_transition_to(Shutdown));
+            assert!(!Shutdown.can_transition_to(state));
+            assert!(!state.is_terminal());
+        }
+        assert!(Shutdown.is_terminal());
+    }
+
+    #[test]
+    fn validate_lb_state() {
+        let queuing = || Arc::new(QueuingPicker {}) as Arc<dyn Picker>;
+        let failing = || {
+            Arc::new(Failing {
+                error: "connection refused".to_string(),
+            }) as Arc<dyn Picker>
+        };
+        let cases = [
+            (Idle, queuing(), true),
+            (Connecting, queuing(), true),
+            (Ready, queuing(), false),
+            (TransientFailure, queuing(), false),
+            (Idle, failing(), false),
+            (Connecting, failing(), false),
+            (TransientFailure, failing(), true),
+            (Shutdown, queuing(), false),
+        ];
+        for (connectivity_state, picker, valid) in cases {
+            let state = LbState {
+                connectivity_state,
+                picker,
+            };
+            assert_eq!(state.validate().is_ok(), valid, "{connectivity_state}");
+        }
+    }
+
+    #[tokio::test]
+    async fn schedule_work_after_fires_unless_cancelled() {
+        let (tx_events, mut rx_events) = mpsc::unbounded_channel();
+        let work_scheduler = TestWorkScheduler { tx_events };
+
+        let cancelled = work_scheduler.schedule_work_after(Duration::from_millis(10));
+        let timer = work_scheduler.schedule_work_after(Duration::from_millis(20));
+        assert!(!timer.has_fired());
+        cancelled.cancel();
+
+        assert!(matches!(
+            rx_events.recv().await.unwrap(),
+            TestEvent::ScheduleWork
+        ));
+        assert!(timer.has_fired());
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(rx_events.try_recv().is_err());
+    }
+}
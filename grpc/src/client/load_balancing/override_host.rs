@@ -0,0 +1,313 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! Routes calls carrying an [`OverrideHost`] to the subchannel for that
+//! address, for stateful session affinity.
+//!
+//! The `override_host` policy wraps a child policy and tracks the states of
+//! the subchannels it creates.  Calls whose host has a READY subchannel are
+//! routed to it directly; an IDLE one is asked to connect.  All other calls,
+//! including those whose host is unknown or failing, are picked by the child.
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    sync::{Arc, Mutex},
+};
+
+use serde::Deserialize;
+use tonic::metadata::MetadataMap;
+
+use crate::client::{
+    name_resolution::ResolverUpdate, service_config::LbPolicyConfig,
+    session_affinity::OverrideHost, ConnectivityState,
+};
+use crate::service::Request;
+
+use super::{
+    child_manager::{ChildManager, ChildUpdate, ResolverUpdateSharder},
+    ChannelController, LbConfig, LbPolicy, LbPolicyBuilder, LbPolicyOptions, LbState,
+    ParsedJsonLbConfig, Pick, PickResult, Picker, Subchannel, SubchannelState, WeakSubchannel,
+    GLOBAL_LB_REGISTRY,
+};
+
+pub static POLICY_NAME: &str = "override_host";
+
+/// Routes calls to the subchannel of their override host, if it is ready, and
+/// the remaining calls with child.
+pub(crate) struct OverrideHostPicker {
+    // The live subchannels, by address.
+    hosts: HashMap<String, (Arc<dyn Subchannel>, ConnectivityState)>,
+    child: Arc<dyn Picker>,
+}
+
+impl Picker for OverrideHostPicker {
+    fn pick(&self, request: &Request) -> PickResult {
+        let host = OverrideHost::from_request(request).and_then(|h| self.hosts.get(&h.0));
+        match host {
+            Some((subchannel, ConnectivityState::Ready)) => PickResult::Pick(Pick {
+                subchannel: subchannel.clone(),
+                metadata: MetadataMap::new(),
+                on_complete: None,
+            }),
+            Some((subchannel, ConnectivityState::Idle)) => {
+                subchannel.connect();
+                self.child.pick(request)
+            }
+            _ => self.child.pick(request),
+        }
+    }
+}
+
+/// The parsed configuration of the override host policy.
+pub(crate) struct OverrideHostConfig {
+    /// The policy which picks calls without a usable override host.
+    pub(crate) child_policy: Arc<dyn LbPolicyBuilder>,
+}
+
+impl LbPolicyConfig for OverrideHostConfig {}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonConfig {
+    child_policy: String,
+}
+
+struct Builder {}
+
+impl LbPolicyBuilder for Builder {
+    fn build(&self, options: LbPolicyOptions) -> Box<dyn LbPolicy> {
+        let sharder = Arc::new(Sharder::default());
+        Box::new(OverrideHostPolicy {
+            child_manager: ChildManager::new(
+                Box::new(sharder.clone()),
+                options.work_scheduler,
+                options.runtime,
+            ),
+            sharder,
+            subchannels: HashMap::new(),
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        POLICY_NAME
+    }
+
+    fn parse_config(
+        &self,
+        config: &ParsedJsonLbConfig,
+    ) -> Result<Option<LbConfig>, Box<dyn Error + Send + Sync>> {
+        let cfg: JsonConfig = config.convert_to()?;
+        let child_policy = config
+            .lookup_policy(&cfg.child_policy)
+            .ok_or_else(|| format!("unknown child policy {:?}", cfg.child_policy))?;
+        Ok(Some(OverrideHostConfig { child_policy }.into_lb_config()))
+    }
+}
+
+pub fn reg() {
+    GLOBAL_LB_REGISTRY.add_builder(Builder {})
+}
+
+#[derive(Default)]
+struct Sharder {
+    child_policy: Mutex<Option<Arc<dyn LbPolicyBuilder>>>,
+}
+
+impl ResolverUpdateSharder<&'static str> for Arc<Sharder> {
+    fn shard_update(
+        &self,
+        resolver_update: ResolverUpdate,
+    ) -> Result<Box<dyn Iterator<Item = ChildUpdate<&'static str>>>, Box<dyn Error + Send + Sync>>
+    {
+        let child_policy = self
+            .child_policy
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or("override host policy received no config")?;
+        Ok(Box::new(std::iter::once(ChildUpdate {
+            child_identifier: child_policy.name(),
+            child_policy_builder: child_policy,
+            child_update: resolver_update,
+        })))
+    }
+}
+
+struct OverrideHostPolicy {
+    child_manager: ChildManager<&'static str>,
+    sharder: Arc<Sharder>,
+    // The last state of each subchannel of the child, by address.  Weak, so
+    // that subchannels are still released when the child drops them.
+    subchannels: HashMap<String, (WeakSubchannel, ConnectivityState)>,
+}
+
+impl OverrideHostPolicy {
+    // Forwards the state of the only child to the channel, honoring override
+    // hosts in its picks.
+    fn update_state(&mut self, channel_controller: &mut dyn ChannelController) {
+        let mut hosts = HashMap::new();
+        self.subchannels.retain(|address, (weak, state)| {
+            let Some(subchannel) = weak.upgrade() else {
+                return false;
+            };
+            hosts.insert(address.clone(), (subchannel, *state));
+            true
+        });
+        if let Some((_, state)) = self.child_manager.child_states().next() {
+            channel_controller.update_picker(LbState {
+                connectivity_state: state.connectivity_state,
+                picker: Arc::new(OverrideHostPicker {
+                    hosts,
+                    child: state.picker.clone(),
+                }),
+            });
+        }
+    }
+}
+
+impl LbPolicy for OverrideHostPolicy {
+    fn resolver_update(
+        &mut self,
+        update: ResolverUpdate,
+        config: Option<&LbConfig>,
+        channel_controller: &mut dyn ChannelController,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let config = OverrideHostConfig::from_lb_config(config)?;
+        *self.sharder.child_policy.lock().unwrap() = Some(config.child_policy.clone());
+        // TODO: support configuration of the child policy.
+        self.child_manager
+            .resolver_update(update, None, channel_controller)?;
+        self.update_state(channel_controller);
+        Ok(())
+    }
+
+    fn subchannel_update(
+        &mut self,
+        subchannel: Arc<dyn Subchannel>,
+        state: &SubchannelState,
+        channel_controller: &mut dyn ChannelController,
+    ) {
+        self.subchannels.insert(
+            subchannel.address().address.to_string(),
+            (WeakSubchannel::new(&subchannel), state.connectivity_state),
+        );
+        self.child_manager
+            .subchannel_update(subchannel, state, channel_controller);
+        self.update_state(channel_controller);
+    }
+
+    fn work(&mut self, channel_controller: &mut dyn ChannelController) {
+        self.child_manager.work(channel_controller);
+        self.update_state(channel_controller);
+    }
+
+    fn exit_idle(&mut self, channel_controller: &mut dyn ChannelController) {
+        self.child_manager.exit_idle(channel_controller);
+        self.update_state(channel_controller);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::HashMap, sync::Arc};
+
+    use tokio::sync::mpsc;
+    use tonic::metadata::MetadataMap;
+
+    use crate::client::load_balancing::{
+        test_utils::{new_request, TestChannelController, TestEvent},
+        ChannelController, Pick, PickResult, Picker, Subchannel,
+    };
+    use crate::client::name_resolution::{Address, TCP_IP_NETWORK_TYPE};
+    use crate::client::session_affinity::OverrideHost;
+    use crate::client::ConnectivityState;
+    use crate::service::Request;
+
+    use super::OverrideHostPicker;
+
+    // Picks the same subchannel every time.
+    struct FixedPicker(Arc<dyn Subchannel>);
+
+    impl Picker for FixedPicker {
+        fn pick(&self, _: &Request) -> PickResult {
+            PickResult::Pick(Pick {
+                subchannel: self.0.clone(),
+                metadata: MetadataMap::new(),
+                on_complete: None,
+            })
+        }
+    }
+
+    fn request_for(host: &str) -> Request {
+        let mut request = new_request();
+        request
+            .extensions_mut()
+            .insert(OverrideHost(host.to_string()));
+        request
+    }
+
+    #[test]
+    fn honors_ready_override_hosts() {
+        let (tx_events, mut rx_events) = mpsc::unbounded_channel();
+        let mut controller = TestChannelController { tx_events };
+        let mut new_subchannel = |address: &str| {
+            controller.new_subchannel(&Address::new(TCP_IP_NETWORK_TYPE, address.to_string()))
+        };
+        let ready = new_subchannel("ready:1");
+        let idle = new_subchannel("idle:1");
+        let failing = new_subchannel("failing:1");
+        let fallback = new_subchannel("fallback:1");
+        while rx_events.try_recv().is_ok() {}
+
+        let picker = OverrideHostPicker {
+            hosts: HashMap::from([
+                (
+                    "ready:1".to_string(),
+                    (ready.clone(), ConnectivityState::Ready),
+                ),
+                ("idle:1".to_string(), (idle, ConnectivityState::Idle)),
+                (
+                    "failing:1".to_string(),
+                    (failing, ConnectivityState::TransientFailure),
+                ),
+            ]),
+            child: Arc::new(FixedPicker(fallback.clone())),
+        };
+        let picked = |request: &Request| picker.pick(request).unwrap_pick().subchannel;
+
+        assert!(*picked(&request_for("ready:1")) == *ready);
+        assert!(*picked(&request_for("failing:1")) == *fallback);
+        assert!(*picked(&request_for("unknown:1")) == *fallback);
+        assert!(*picked(&new_request()) == *fallback);
+        assert!(rx_events.try_recv().is_err());
+
+        // An idle host is connected, but the call is not held for it.
+        assert!(*picked(&request_for("idle:1")) == *fallback);
+        let Ok(TestEvent::Connect(address)) = rx_events.try_recv() else {
+            panic!("idle override host was not connected");
+        };
+        assert_eq!(&*address.address, "idle:1");
+    }
+}
@@ -12,13 +12,13 @@ use crate::{
         name_resolution::{Address, ResolverUpdate},
         subchannel, ConnectivityState,
     },
-    rt::Runtime,
+    leak_detector::LeakTracker,
     service::Request,
 };
 
 use super::{
-    ChannelController, LbConfig, LbPolicyOptions, Pick, PickResult, Picker, Subchannel,
-    SubchannelState, WorkScheduler,
+    ChannelController, Failing, LbConfig, LbPolicyOptions, Pick, PickResult, Picker, ScheduledWork,
+    Subchannel, SubchannelState, WorkScheduler,
 };
 
 pub static POLICY_NAME: &str = "pick_first";
@@ -30,8 +30,11 @@ impl LbPolicyBuilder for Builder {
         Box::new(PickFirstPolicy {
             work_scheduler: options.work_scheduler,
             subchannel: None,
+            addresses: Vec::default(),
             next_addresses: Vec::default(),
-            runtime: options.runtime,
+            timer: None,
+            failing: false,
+            last_disconnect: None,
         })
     }
 
@@ -47,8 +50,17 @@ pub fn reg() {
 struct PickFirstPolicy {
     work_scheduler: Arc<dyn WorkScheduler>,
     subchannel: Option<Arc<dyn Subchannel>>,
+    // The addresses of the endpoint, in the order they are tried in.
+    addresses: Vec<Address>,
+    // The addresses not yet tried in the current pass, in reverse order.
     next_addresses: Vec<Address>,
-    runtime: Arc<dyn Runtime>,
+    // Dropping the policy drops the timer, which cancels it.
+    timer: Option<ScheduledWork>,
+    // Set while the subchannel is failing to connect, until it becomes Ready.
+    failing: bool,
+    // Why the subchannel's last connection was lost, reported along with the
+    // failures to reconnect, until it becomes Ready.
+    last_disconnect: Option<String>,
 }
 
 impl LbPolicy for PickFirstPolicy {
@@ -58,28 +70,24 @@ impl LbPolicy for PickFirstPolicy {
         config: Option<&LbConfig>,
         channel_controller: &mut dyn ChannelController,
     ) -> Result<(), Box<dyn Error + Send + Sync>> {
-        let mut addresses = update
+        let addresses = update
             .endpoints
             .unwrap()
             .into_iter()
             .next()
             .ok_or("no endpoints")?
             .addresses;
+        if addresses.is_empty() {
+            return Err("no addresses".into());
+        }
+        self.addresses = addresses;
+        self.start_pass(channel_controller);
 
-        let address = addresses.pop().ok_or("no addresses")?;
-
-        let sc = channel_controller.new_subchannel(&address);
-        sc.connect();
-        self.subchannel = Some(sc);
-
-        self.next_addresses = addresses;
-        let work_scheduler = self.work_scheduler.clone();
-        let runtime = self.runtime.clone();
-        // TODO: Implement Drop that cancels this task.
-        self.runtime.spawn(Box::pin(async move {
-            runtime.sleep(Duration::from_millis(200)).await;
-            work_scheduler.schedule_work();
-        }));
+        // Replacing the timer cancels the previous one.
+        self.timer = Some(
+            self.work_scheduler
+                .schedule_work_after(Duration::from_millis(200)),
+        );
         // TODO: return a picker that queues RPCs.
         Ok(())
     }
@@ -90,26 +98,95 @@ impl LbPolicy for PickFirstPolicy {
         state: &SubchannelState,
         channel_controller: &mut dyn ChannelController,
     ) {
-        // Assume the update is for our subchannel.
-        if state.connectivity_state == ConnectivityState::Ready {
-            channel_controller.update_picker(LbState {
-                connectivity_state: ConnectivityState::Ready,
-                picker: Arc::new(OneSubchannelPicker {
-                    sc: self.subchannel.as_ref().unwrap().clone(),
-                }),
-            });
+        // Ignore updates for subchannels replaced by a later address.
+        if self
+            .subchannel
+            .as_ref()
+            .is_none_or(|sc| **sc != *subchannel)
+        {
+            return;
+        }
+        match state.connectivity_state {
+            ConnectivityState::Ready => {
+                self.failing = false;
+                self.last_disconnect = None;
+                channel_controller.update_picker(LbState {
+                    connectivity_state: ConnectivityState::Ready,
+                    picker: Arc::new(OneSubchannelPicker {
+                        sc: self.subchannel.as_ref().unwrap().clone(),
+                        _leak_tracker: LeakTracker::new("Picker"),
+                    }),
+                });
+            }
+            // Fall back to the next address, e.g. one of another network type
+            // if this one has no transport, before reporting the failure.
+            ConnectivityState::TransientFailure if !self.next_addresses.is_empty() => {
+                let address = self.next_addresses.pop().unwrap();
+                let sc = channel_controller.new_subchannel(&address);
+                sc.connect();
+                self.subchannel = Some(sc);
+            }
+            ConnectivityState::TransientFailure => {
+                self.failing = true;
+                let mut error = state
+                    .last_connection_error
+                    .as_ref()
+                    .map(|err| err.to_string())
+                    .unwrap_or_default();
+                if let Some(disconnect) = &self.last_disconnect {
+                    error = format!("{error} (after {disconnect})");
+                }
+                channel_controller.update_picker(LbState {
+                    connectivity_state: ConnectivityState::TransientFailure,
+                    picker: Arc::new(Failing { error }),
+                });
+            }
+            // Reconnect once the backoff after a failure expires, starting
+            // over from the first address so that every address is retried.
+            // The channel remains in TRANSIENT_FAILURE until a subchannel is
+            // Ready.
+            ConnectivityState::Idle if self.failing => {
+                if self.addresses.len() == 1 {
+                    subchannel.connect();
+                } else {
+                    self.start_pass(channel_controller);
+                }
+            }
+            ConnectivityState::Idle | ConnectivityState::Connecting => {
+                if let Some(err) = &state.last_connection_error {
+                    self.last_disconnect = Some(err.to_string());
+                }
+            }
+            _ => {}
         }
     }
 
     fn work(&mut self, channel_controller: &mut dyn ChannelController) {}
 
     fn exit_idle(&mut self, _channel_controller: &mut dyn ChannelController) {
-        todo!("implement exit_idle")
+        if let Some(sc) = &self.subchannel {
+            sc.connect();
+        }
+    }
+}
+
+impl PickFirstPolicy {
+    // Connects to the first address, leaving the others to be tried in order
+    // as the previous one fails.
+    fn start_pass(&mut self, channel_controller: &mut dyn ChannelController) {
+        // The remaining addresses are kept in reverse, and the next is popped
+        // from the end.
+        self.next_addresses = self.addresses.iter().rev().cloned().collect();
+        let address = self.next_addresses.pop().unwrap();
+        let sc = channel_controller.new_subchannel(&address);
+        sc.connect();
+        self.subchannel = Some(sc);
     }
 }
 
 struct OneSubchannelPicker {
     sc: Arc<dyn Subchannel>,
+    _leak_tracker: LeakTracker,
 }
 
 impl Picker for OneSubchannelPicker {
@@ -121,3 +198,250 @@ impl Picker for OneSubchannelPicker {
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use tokio::sync::mpsc;
+
+    use super::PickFirstPolicy;
+    use crate::client::{
+        error::{ConnectError, DisconnectReason},
+        load_balancing::{
+            test_utils::{new_request, TestChannelController, TestEvent, TestWorkScheduler},
+            LbPolicy, PickResult, SubchannelState,
+        },
+        name_resolution::{Address, Endpoint, ResolverUpdate},
+        ConnectivityState,
+    };
+
+    fn state(
+        connectivity_state: ConnectivityState,
+        error: Option<ConnectError>,
+    ) -> SubchannelState {
+        SubchannelState {
+            connectivity_state,
+            last_connection_error: error.map(|err| Arc::new(err) as _),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn failing_picker_explains_lost_connections() {
+        let (tx_events, mut rx_events) = mpsc::unbounded_channel();
+        let mut controller = TestChannelController {
+            tx_events: tx_events.clone(),
+        };
+        let mut policy = PickFirstPolicy {
+            work_scheduler: Arc::new(TestWorkScheduler { tx_events }),
+            subchannel: None,
+            addresses: Vec::default(),
+            next_addresses: Vec::default(),
+            timer: None,
+            failing: false,
+            last_disconnect: None,
+        };
+        let endpoint = Endpoint::builder()
+            .addresses([Address::new("tcp", "127.0.0.1:1234")])
+            .build()
+            .unwrap();
+        let update = ResolverUpdate::builder().endpoint(endpoint).build();
+        policy
+            .resolver_update(update, None, &mut controller)
+            .unwrap();
+        let sc = loop {
+            if let TestEvent::NewSubchannel(sc) = rx_events.recv().await.unwrap() {
+                break sc;
+            }
+        };
+
+        policy.subchannel_update(
+            sc.clone(),
+            &state(ConnectivityState::Ready, None),
+            &mut controller,
+        );
+        let disconnect = || {
+            Some(ConnectError::disconnected(DisconnectReason::GoAway {
+                error_code: 11,
+                debug_data: "too_many_pings".to_string(),
+            }))
+        };
+        policy.subchannel_update(
+            sc.clone(),
+            &state(ConnectivityState::Idle, disconnect()),
+            &mut controller,
+        );
+        policy.subchannel_update(
+            sc.clone(),
+            &state(ConnectivityState::Connecting, disconnect()),
+            &mut controller,
+        );
+        policy.subchannel_update(
+            sc,
+            &state(
+                ConnectivityState::TransientFailure,
+                Some("connection refused".into()),
+            ),
+            &mut controller,
+        );
+
+        let picker = loop {
+            if let TestEvent::UpdatePicker(update) = rx_events.recv().await.unwrap() {
+                if update.connectivity_state == ConnectivityState::TransientFailure {
+                    break update.picker;
+                }
+            }
+        };
+        let PickResult::Fail(status) = picker.pick(&new_request()) else {
+            panic!("picker did not fail");
+        };
+        assert_eq!(
+            status.message(),
+            "connection refused (after connection lost: GOAWAY received with error code 11 (too_many_pings))"
+        );
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_next_address() {
+        let (tx_events, mut rx_events) = mpsc::unbounded_channel();
+        let mut controller = TestChannelController {
+            tx_events: tx_events.clone(),
+        };
+        let mut policy = PickFirstPolicy {
+            work_scheduler: Arc::new(TestWorkScheduler { tx_events }),
+            subchannel: None,
+            addresses: Vec::default(),
+            next_addresses: Vec::default(),
+            timer: None,
+            failing: false,
+            last_disconnect: None,
+        };
+        // The addresses of an endpoint may have different network types.
+        let endpoint = Endpoint::builder()
+            .addresses([
+                Address::new("unsupported", "backend"),
+                Address::new("tcp", "127.0.0.1:1234"),
+            ])
+            .build()
+            .unwrap();
+        let update = ResolverUpdate::builder().endpoint(endpoint).build();
+        policy
+            .resolver_update(update, None, &mut controller)
+            .unwrap();
+        let mut next_subchannel = async || loop {
+            if let TestEvent::NewSubchannel(sc) = rx_events.recv().await.unwrap() {
+                return sc;
+            }
+        };
+        let first = next_subchannel().await;
+        assert_eq!(first.address().network_type, "unsupported");
+
+        policy.subchannel_update(
+            first.clone(),
+            &state(
+                ConnectivityState::TransientFailure,
+                Some("no transport".into()),
+            ),
+            &mut controller,
+        );
+        let second = next_subchannel().await;
+        assert_eq!(second.address().network_type, "tcp");
+
+        // Late updates for the first subchannel are ignored.
+        policy.subchannel_update(
+            first,
+            &state(ConnectivityState::Ready, None),
+            &mut controller,
+        );
+        while let Ok(event) = rx_events.try_recv() {
+            assert!(
+                !matches!(event, TestEvent::UpdatePicker(_)),
+                "stale update produced a picker"
+            );
+        }
+        policy.subchannel_update(
+            second.clone(),
+            &state(ConnectivityState::Ready, None),
+            &mut controller,
+        );
+        let picker = loop {
+            if let TestEvent::UpdatePicker(update) = rx_events.recv().await.unwrap() {
+                assert_eq!(update.connectivity_state, ConnectivityState::Ready);
+                break update.picker;
+            }
+        };
+        let pick = picker.pick(&new_request()).unwrap_pick();
+        assert!(*pick.subchannel == *second);
+    }
+
+    #[tokio::test]
+    async fn retries_every_address_after_failures() {
+        let (tx_events, mut rx_events) = mpsc::unbounded_channel();
+        let mut controller = TestChannelController {
+            tx_events: tx_events.clone(),
+        };
+        let mut policy = PickFirstPolicy {
+            work_scheduler: Arc::new(TestWorkScheduler { tx_events }),
+            subchannel: None,
+            addresses: Vec::default(),
+            next_addresses: Vec::default(),
+            timer: None,
+            failing: false,
+            last_disconnect: None,
+        };
+        let endpoint = Endpoint::builder()
+            .addresses([
+                Address::new("tcp", "127.0.0.1:1"),
+                Address::new("tcp", "127.0.0.1:2"),
+            ])
+            .build()
+            .unwrap();
+        let update = ResolverUpdate::builder().endpoint(endpoint).build();
+        policy
+            .resolver_update(update, None, &mut controller)
+            .unwrap();
+        let mut next_subchannel = async || loop {
+            if let TestEvent::NewSubchannel(sc) = rx_events.recv().await.unwrap() {
+                return sc;
+            }
+        };
+        let refused = || {
+            state(
+                ConnectivityState::TransientFailure,
+                Some("connection refused".into()),
+            )
+        };
+
+        // Both addresses fail in the first pass.
+        let first = next_subchannel().await;
+        policy.subchannel_update(first, &refused(), &mut controller);
+        let second = next_subchannel().await;
+        assert_eq!(&*second.address().address, "127.0.0.1:2");
+        policy.subchannel_update(second.clone(), &refused(), &mut controller);
+
+        // Once the backoff expires, the next pass starts with the first
+        // address, which has recovered.
+        policy.subchannel_update(
+            second,
+            &state(ConnectivityState::Idle, None),
+            &mut controller,
+        );
+        let retried = next_subchannel().await;
+        assert_eq!(&*retried.address().address, "127.0.0.1:1");
+        policy.subchannel_update(
+            retried.clone(),
+            &state(ConnectivityState::Ready, None),
+            &mut controller,
+        );
+        let picker = loop {
+            if let TestEvent::UpdatePicker(update) = rx_events.recv().await.unwrap() {
+                if update.connectivity_state == ConnectivityState::Ready {
+                    break update.picker;
+                }
+            }
+        };
+        let pick = picker.pick(&new_request()).unwrap_pick();
+        assert!(*pick.subchannel == *retried);
+    }
+}
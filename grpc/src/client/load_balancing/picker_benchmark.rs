@@ -0,0 +1,294 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! A harness measuring the latency of [`Picker::pick`], which is on the path of
+//! every call.
+//!
+//! Authors of LB policies build their picker over the subchannels returned by
+//! [`PickerBenchmark::subchannels`], which never connect, and run it with
+//! [`PickerBenchmark::run`].  Picks are made in a loop on one or more threads
+//! sharing the picker, each reusing a request built once, so that the results
+//! only reflect the cost of the picker and of its contention.
+
+use std::{
+    fmt::{self, Display, Formatter},
+    hash::{Hash, Hasher},
+    hint::black_box,
+    sync::{Arc, Barrier},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    client::name_resolution::{Address, TCP_IP_NETWORK_TYPE},
+    service::Request,
+};
+
+use super::{ForwardingSubchannel, PickResult, Picker, Subchannel};
+
+type RequestFn = Arc<dyn Fn() -> Request + Send + Sync>;
+
+/// Configures a benchmark of a picker.
+#[derive(Clone)]
+#[non_exhaustive]
+pub struct PickerBenchmark {
+    /// The number of picks measured on each thread.
+    pub picks: u64,
+    /// The number of picks made on each thread before measuring.
+    pub warmup_picks: u64,
+    /// The number of threads picking concurrently.
+    pub threads: usize,
+    /// Builds the request each thread picks for, e.g. with the metadata a
+    /// hash-based picker reads.
+    pub request: RequestFn,
+}
+
+impl Default for PickerBenchmark {
+    fn default() -> Self {
+        Self {
+            picks: 1_000_000,
+            warmup_picks: 10_000,
+            threads: 1,
+            request: Arc::new(|| Request::new(Box::pin(tokio_stream::empty()))),
+        }
+    }
+}
+
+impl PickerBenchmark {
+    pub fn picks(self, picks: u64) -> Self {
+        Self { picks, ..self }
+    }
+
+    pub fn warmup_picks(self, warmup_picks: u64) -> Self {
+        Self {
+            warmup_picks,
+            ..self
+        }
+    }
+
+    pub fn threads(self, threads: usize) -> Self {
+        Self { threads, ..self }
+    }
+
+    pub fn request(self, request: impl Fn() -> Request + Send + Sync + 'static) -> Self {
+        Self {
+            request: Arc::new(request),
+            ..self
+        }
+    }
+
+    /// Returns count subchannels with distinct addresses, for building the
+    /// picker under test.  They ignore requests to connect.
+    pub fn subchannels(count: usize) -> Vec<Arc<dyn Subchannel>> {
+        (0..count)
+            .map(|i| {
+                let address = Address::new(
+                    TCP_IP_NETWORK_TYPE,
+                    format!("10.{}.{}.{}:443", i >> 16 & 0xff, i >> 8 & 0xff, i & 0xff),
+                );
+                Arc::new(BenchmarkSubchannel { address }) as Arc<dyn Subchannel>
+            })
+            .collect()
+    }
+
+    /// Measures the picks of picker, blocking until every thread is done.
+    pub fn run(&self, picker: Arc<dyn Picker>) -> PickerBenchmarkResult {
+        let threads = self.threads.max(1);
+        let start = Barrier::new(threads);
+        let results: Vec<_> = thread::scope(|s| {
+            let handles: Vec<_> = (0..threads)
+                .map(|_| s.spawn(|| self.run_thread(picker.as_ref(), &start)))
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("picker panicked"))
+                .collect()
+        });
+        results
+            .into_iter()
+            .fold(PickerBenchmarkResult::default(), |total, result| {
+                total.merge(result)
+            })
+    }
+
+    fn run_thread(&self, picker: &dyn Picker, start: &Barrier) -> PickerBenchmarkResult {
+        let request = (self.request)();
+        for _ in 0..self.warmup_picks {
+            black_box(picker.pick(black_box(&request)));
+        }
+        let mut result = PickerBenchmarkResult::default();
+        start.wait();
+        let begin = Instant::now();
+        for _ in 0..self.picks {
+            match black_box(picker.pick(black_box(&request))) {
+                PickResult::Pick(_) => result.completed += 1,
+                PickResult::Queue => result.queued += 1,
+                PickResult::Fail(_) => result.failed += 1,
+                PickResult::Drop(_) => result.dropped += 1,
+            }
+        }
+        result.elapsed = begin.elapsed();
+        result
+    }
+}
+
+/// The outcome of a [`PickerBenchmark`], summed over its threads.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[non_exhaustive]
+pub struct PickerBenchmarkResult {
+    /// The number of picks which chose a subchannel.
+    pub completed: u64,
+    /// The number of picks which queued the request.
+    pub queued: u64,
+    /// The number of picks which failed the request.
+    pub failed: u64,
+    /// The number of picks which dropped the request.
+    pub dropped: u64,
+    /// The time the threads spent picking, summed.
+    pub elapsed: Duration,
+}
+
+impl PickerBenchmarkResult {
+    /// Returns the number of picks measured.
+    pub fn picks(&self) -> u64 {
+        self.completed + self.queued + self.failed + self.dropped
+    }
+
+    /// Returns the mean latency of a pick.
+    pub fn mean_latency(&self) -> Duration {
+        match self.picks() {
+            0 => Duration::ZERO,
+            picks => self.elapsed.div_f64(picks as f64),
+        }
+    }
+
+    fn merge(self, other: Self) -> Self {
+        Self {
+            completed: self.completed + other.completed,
+            queued: self.queued + other.queued,
+            failed: self.failed + other.failed,
+            dropped: self.dropped + other.dropped,
+            elapsed: self.elapsed + other.elapsed,
+        }
+    }
+}
+
+impl Display for PickerBenchmarkResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} picks, {:?}/pick ({} completed, {} queued, {} failed, {} dropped)",
+            self.picks(),
+            self.mean_latency(),
+            self.completed,
+            self.queued,
+            self.failed,
+            self.dropped
+        )
+    }
+}
+
+// A subchannel which never connects.
+#[derive(PartialEq, Eq, Hash)]
+struct BenchmarkSubchannel {
+    address: Address,
+}
+
+impl ForwardingSubchannel for BenchmarkSubchannel {
+    fn delegate(&self) -> Arc<dyn Subchannel> {
+        unreachable!("benchmark subchannels have no delegate")
+    }
+
+    fn address(&self) -> Address {
+        self.address.clone()
+    }
+
+    fn connect(&self) {}
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use tonic::{metadata::MetadataMap, Status};
+
+    use super::PickerBenchmark;
+    use crate::client::load_balancing::{Pick, PickResult, Picker, Subchannel};
+    use crate::service::Request;
+
+    // Picks the subchannels in turn, failing requests without metadata.
+    struct RoundRobinPicker {
+        subchannels: Vec<Arc<dyn Subchannel>>,
+        next: AtomicUsize,
+    }
+
+    impl Picker for RoundRobinPicker {
+        fn pick(&self, request: &Request) -> PickResult {
+            if request.metadata().is_empty() {
+                return PickResult::Fail(Status::unavailable("no metadata"));
+            }
+            let i = self.next.fetch_add(1, Ordering::Relaxed);
+            PickResult::Pick(Pick {
+                subchannel: self.subchannels[i % self.subchannels.len()].clone(),
+                metadata: MetadataMap::new(),
+                on_complete: None,
+            })
+        }
+    }
+
+    #[test]
+    fn counts_the_picks_of_every_thread() {
+        let subchannels = PickerBenchmark::subchannels(300);
+        assert_eq!(subchannels.len(), 300);
+        assert_ne!(subchannels[1].address(), subchannels[257].address());
+        let picker = Arc::new(RoundRobinPicker {
+            subchannels,
+            next: AtomicUsize::new(0),
+        });
+
+        let benchmark = PickerBenchmark::default()
+            .picks(1000)
+            .warmup_picks(10)
+            .threads(4);
+        let result = benchmark.run(picker.clone());
+        assert_eq!(result.failed, 4000);
+        assert_eq!(result.picks(), 4000);
+
+        let result = benchmark
+            .request(|| {
+                let mut request = Request::new(Box::pin(tokio_stream::empty()));
+                request
+                    .metadata_mut()
+                    .insert("key", "value".parse().unwrap());
+                request
+            })
+            .run(picker.clone());
+        assert_eq!(result.completed, 4000);
+        assert_eq!(picker.next.load(Ordering::Relaxed), 4040);
+        assert!(result.to_string().starts_with("4000 picks, "), "{result}");
+    }
+}
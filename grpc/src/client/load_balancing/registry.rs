@@ -1,14 +1,27 @@
 use std::{
-    collections::HashMap,
+    any::TypeId,
+    collections::{BTreeSet, HashMap},
     sync::{Arc, LazyLock, Mutex},
 };
 
 use super::LbPolicyBuilder;
 
+struct Entry {
+    builder: Arc<dyn LbPolicyBuilder>,
+    // Distinguishes re-registrations of the same builder, e.g. by calling a
+    // policy's reg() more than once, from conflicting ones.
+    type_id: TypeId,
+}
+
 /// A registry to store and retrieve LB policies.  LB policies are indexed by
 /// their names.
+///
+/// Channels can be given their own registry, which shadows the global one:
+/// policies are looked up in it first, and in the global registry if not
+/// found.
+#[derive(Clone)]
 pub struct LbPolicyRegistry {
-    m: Arc<Mutex<HashMap<String, Arc<dyn LbPolicyBuilder>>>>,
+    m: Arc<Mutex<HashMap<String, Entry>>>,
 }
 
 impl LbPolicyRegistry {
@@ -16,16 +29,64 @@ impl LbPolicyRegistry {
     pub fn new() -> Self {
         Self { m: Arc::default() }
     }
-    /// Add a LB policy into the registry.
-    pub(crate) fn add_builder(&self, builder: impl LbPolicyBuilder + 'static) {
-        self.m
-            .lock()
-            .unwrap()
-            .insert(builder.name().to_string(), Arc::new(builder));
+    /// Add a LB policy into the registry, replacing any policy registered with
+    /// the same name.  Replacing a policy with a builder of a different type
+    /// logs a warning, since one of the two registrations is likely a mistake.
+    pub fn add_builder<B: LbPolicyBuilder + 'static>(&self, builder: B) {
+        let name = builder.name();
+        let previous = self.m.lock().unwrap().insert(
+            name.to_string(),
+            Entry {
+                builder: Arc::new(builder),
+                type_id: TypeId::of::<B>(),
+            },
+        );
+        if previous.is_some_and(|e| e.type_id != TypeId::of::<B>()) {
+            eprintln!("warning: LB policy {name:?} was registered again by a different builder");
+        }
+    }
+    /// Add a LB policy into the registry, failing if a policy is already
+    /// registered with the same name.
+    pub fn try_add_builder<B: LbPolicyBuilder + 'static>(&self, builder: B) -> Result<(), String> {
+        let mut m = self.m.lock().unwrap();
+        let name = builder.name();
+        if m.contains_key(name) {
+            return Err(format!("LB policy {name:?} is already registered"));
+        }
+        m.insert(
+            name.to_string(),
+            Entry {
+                builder: Arc::new(builder),
+                type_id: TypeId::of::<B>(),
+            },
+        );
+        Ok(())
     }
     /// Retrieve a LB policy from the registry, or None if not found.
     pub(crate) fn get_policy(&self, name: &str) -> Option<Arc<dyn LbPolicyBuilder>> {
-        self.m.lock().unwrap().get(name).cloned()
+        self.m.lock().unwrap().get(name).map(|e| e.builder.clone())
+    }
+    /// Retrieve a LB policy from the registry, or from the global registry if
+    /// not found.
+    pub(crate) fn get_policy_or_global(&self, name: &str) -> Option<Arc<dyn LbPolicyBuilder>> {
+        self.get_policy(name)
+            .or_else(|| GLOBAL_LB_REGISTRY.get_policy(name))
+    }
+    /// Returns the sorted names of the policies in the registry.
+    pub(crate) fn policy_names(&self) -> Vec<String> {
+        let mut names: Vec<_> = self.m.lock().unwrap().keys().cloned().collect();
+        names.sort();
+        names
+    }
+    /// Returns the sorted names of the policies available to channels using
+    /// this registry, including those of the global registry.
+    pub(crate) fn policy_names_or_global(&self) -> Vec<String> {
+        let names: BTreeSet<_> = self
+            .policy_names()
+            .into_iter()
+            .chain(GLOBAL_LB_REGISTRY.policy_names())
+            .collect();
+        names.into_iter().collect()
     }
 }
 
@@ -38,3 +99,86 @@ impl Default for LbPolicyRegistry {
 /// The registry used if a local registry is not provided to a channel or if it
 /// does not exist in the local registry.
 pub static GLOBAL_LB_REGISTRY: LazyLock<LbPolicyRegistry> = LazyLock::new(LbPolicyRegistry::new);
+
+#[cfg(test)]
+mod test {
+    use std::error::Error;
+
+    use super::{LbPolicyRegistry, GLOBAL_LB_REGISTRY};
+    use crate::client::load_balancing::{
+        LbConfig, LbPolicy, LbPolicyBuilder, LbPolicyOptions, ParsedJsonLbConfig,
+    };
+
+    // Builders which are only registered, never built.
+    struct First(&'static str);
+    struct Second(&'static str);
+
+    impl LbPolicyBuilder for First {
+        fn build(&self, _: LbPolicyOptions) -> Box<dyn LbPolicy> {
+            unimplemented!()
+        }
+
+        fn name(&self) -> &'static str {
+            self.0
+        }
+    }
+
+    impl LbPolicyBuilder for Second {
+        fn build(&self, _: LbPolicyOptions) -> Box<dyn LbPolicy> {
+            unimplemented!()
+        }
+
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        fn parse_config(
+            &self,
+            _: &ParsedJsonLbConfig,
+        ) -> Result<Option<LbConfig>, Box<dyn Error + Send + Sync>> {
+            Err("second".into())
+        }
+    }
+
+    fn is_second(registry: &LbPolicyRegistry, name: &str) -> bool {
+        let config = ParsedJsonLbConfig::new("{}").unwrap();
+        registry
+            .get_policy_or_global(name)
+            .unwrap()
+            .parse_config(&config)
+            .is_err()
+    }
+
+    #[test]
+    fn duplicate_registrations() {
+        let registry = LbPolicyRegistry::new();
+        registry.add_builder(First("dup"));
+        registry.add_builder(First("dup"));
+        assert!(!is_second(&registry, "dup"));
+
+        // add_builder replaces the registered policy; try_add_builder keeps it.
+        let err = registry.try_add_builder(Second("dup")).unwrap_err();
+        assert!(err.contains("already registered"), "{err}");
+        assert!(!is_second(&registry, "dup"));
+        registry.add_builder(Second("dup"));
+        assert!(is_second(&registry, "dup"));
+        assert_eq!(registry.policy_names(), ["dup"]);
+    }
+
+    #[test]
+    fn local_registries_shadow_the_global_one() {
+        GLOBAL_LB_REGISTRY.add_builder(First("test_shadowed"));
+        let registry = LbPolicyRegistry::new();
+        assert!(!is_second(&registry, "test_shadowed"));
+        registry.add_builder(Second("test_shadowed"));
+        assert!(is_second(&registry, "test_shadowed"));
+        assert!(!is_second(&GLOBAL_LB_REGISTRY, "test_shadowed"));
+
+        let names = registry.policy_names_or_global();
+        assert_eq!(
+            names.iter().filter(|n| *n == "test_shadowed").count(),
+            1,
+            "{names:?}"
+        );
+    }
+}
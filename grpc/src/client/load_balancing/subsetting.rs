@@ -0,0 +1,310 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! An LB policy which connects to a stable subset of the resolved endpoints,
+//! chosen deterministically from the client's index, and delegates to a child
+//! policy for the endpoints in the subset.
+//!
+//! Clients are grouped into rounds of `ceil(endpoints / subset_size)`
+//! clients.  Every round shuffles the endpoints, ordering them by the XXH64
+//! hash of their addresses seeded with the round number, and gives each
+//! client in the round a disjoint slice of the shuffled list, so that
+//! connections are spread evenly across the backends while each client only
+//! connects to subset_size of them.
+
+use std::{
+    error::Error,
+    sync::{Arc, Mutex},
+};
+
+use serde::Deserialize;
+
+use crate::{
+    client::{
+        name_resolution::{Endpoint, ResolverUpdate},
+        service_config::LbPolicyConfig,
+    },
+    xxhash::xxh64,
+};
+
+use super::{
+    child_manager::{ChildManager, ChildUpdate, ResolverUpdateSharder},
+    ChannelController, LbConfig, LbPolicy, LbPolicyBuilder, LbPolicyOptions, ParsedJsonLbConfig,
+    Subchannel, SubchannelState, GLOBAL_LB_REGISTRY,
+};
+
+pub static POLICY_NAME: &str = "deterministic_subsetting";
+
+/// The parsed configuration of the subsetting policy.
+pub(crate) struct SubsettingConfig {
+    /// The index of this client among all clients of the service.
+    pub(crate) client_index: u32,
+    /// The number of endpoints each client connects to.
+    pub(crate) subset_size: u32,
+    /// The policy which manages the endpoints in the subset.
+    pub(crate) child_policy: Arc<dyn LbPolicyBuilder>,
+}
+
+impl LbPolicyConfig for SubsettingConfig {}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonConfig {
+    client_index: u32,
+    subset_size: u32,
+    child_policy: String,
+}
+
+struct Builder {}
+
+impl LbPolicyBuilder for Builder {
+    fn build(&self, options: LbPolicyOptions) -> Box<dyn LbPolicy> {
+        let sharder = Arc::new(Sharder::default());
+        Box::new(SubsettingPolicy {
+            child_manager: ChildManager::new(
+                Box::new(sharder.clone()),
+                options.work_scheduler,
+                options.runtime,
+            ),
+            sharder,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        POLICY_NAME
+    }
+
+    fn parse_config(
+        &self,
+        config: &ParsedJsonLbConfig,
+    ) -> Result<Option<LbConfig>, Box<dyn Error + Send + Sync>> {
+        let cfg: JsonConfig = config.convert_to()?;
+        if cfg.subset_size == 0 {
+            return Err("subsetSize must be greater than 0".into());
+        }
+        let child_policy = config
+            .lookup_policy(&cfg.child_policy)
+            .ok_or_else(|| format!("unknown child policy {:?}", cfg.child_policy))?;
+        Ok(Some(
+            SubsettingConfig {
+                client_index: cfg.client_index,
+                subset_size: cfg.subset_size,
+                child_policy,
+            }
+            .into_lb_config(),
+        ))
+    }
+}
+
+pub fn reg() {
+    GLOBAL_LB_REGISTRY.add_builder(Builder {})
+}
+
+/// Returns the subset of endpoints used by the client with client_index.
+pub(crate) fn subset(
+    mut endpoints: Vec<Endpoint>,
+    client_index: u32,
+    subset_size: u32,
+) -> Vec<Endpoint> {
+    let subset_size = subset_size as usize;
+    if endpoints.len() <= subset_size {
+        return endpoints;
+    }
+    let subset_count = endpoints.len().div_ceil(subset_size);
+    let round = client_index as usize / subset_count;
+    let subset_id = client_index as usize % subset_count;
+
+    // Sort first so that the result does not depend on the resolver's order,
+    // then shuffle deterministically based on the round.  The shuffle must
+    // not depend on the build, as all clients must agree on it.
+    endpoints.sort_by(|a, b| a.addresses.cmp(&b.addresses));
+    endpoints.sort_by_cached_key(|e| {
+        let addresses: Vec<_> = e.addresses.iter().map(|a| a.address.to_string()).collect();
+        xxh64(addresses.join(",").as_bytes(), round as u64)
+    });
+
+    let start = subset_id * subset_size;
+    // The last subset of a round may be short; wrap around to fill it.
+    endpoints
+        .iter()
+        .cycle()
+        .skip(start)
+        .take(subset_size)
+        .cloned()
+        .collect()
+}
+
+#[derive(Default)]
+struct Sharder {
+    config: Mutex<Option<Arc<SubsettingConfig>>>,
+}
+
+impl ResolverUpdateSharder<&'static str> for Arc<Sharder> {
+    fn shard_update(
+        &self,
+        mut resolver_update: ResolverUpdate,
+    ) -> Result<Box<dyn Iterator<Item = ChildUpdate<&'static str>>>, Box<dyn Error + Send + Sync>>
+    {
+        let config = self
+            .config
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or("subsetting policy received no config")?;
+        if let Ok(endpoints) = resolver_update.endpoints {
+            resolver_update.endpoints =
+                Ok(subset(endpoints, config.client_index, config.subset_size));
+        }
+        // The child is identified by its policy name, so that changing the
+        // child policy replaces the child.
+        Ok(Box::new(std::iter::once(ChildUpdate {
+            child_identifier: config.child_policy.name(),
+            child_policy_builder: config.child_policy.clone(),
+            child_update: resolver_update,
+        })))
+    }
+}
+
+struct SubsettingPolicy {
+    child_manager: ChildManager<&'static str>,
+    sharder: Arc<Sharder>,
+}
+
+impl SubsettingPolicy {
+    // Forwards the state of the only child to the channel.
+    fn update_state(&mut self, channel_controller: &mut dyn ChannelController) {
+        if let Some((_, state)) = self.child_manager.child_states().next() {
+            let state = state.clone();
+            channel_controller.update_picker(state);
+        }
+    }
+}
+
+impl LbPolicy for SubsettingPolicy {
+    fn resolver_update(
+        &mut self,
+        update: ResolverUpdate,
+        config: Option<&LbConfig>,
+        channel_controller: &mut dyn ChannelController,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let config = SubsettingConfig::from_lb_config(config)?;
+        *self.sharder.config.lock().unwrap() = Some(config);
+        // TODO: support configuration of the child policy.
+        self.child_manager
+            .resolver_update(update, None, channel_controller)?;
+        self.update_state(channel_controller);
+        Ok(())
+    }
+
+    fn subchannel_update(
+        &mut self,
+        subchannel: Arc<dyn Subchannel>,
+        state: &SubchannelState,
+        channel_controller: &mut dyn ChannelController,
+    ) {
+        self.child_manager
+            .subchannel_update(subchannel, state, channel_controller);
+        self.update_state(channel_controller);
+    }
+
+    fn work(&mut self, channel_controller: &mut dyn ChannelController) {
+        self.child_manager.work(channel_controller);
+        self.update_state(channel_controller);
+    }
+
+    fn exit_idle(&mut self, channel_controller: &mut dyn ChannelController) {
+        self.child_manager.exit_idle(channel_controller);
+        self.update_state(channel_controller);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use crate::client::name_resolution::{Address, Endpoint};
+
+    use super::subset;
+
+    fn endpoints(n: usize) -> Vec<Endpoint> {
+        (0..n)
+            .map(|i| Endpoint {
+                addresses: vec![Address {
+                    address: format!("10.0.0.{i}:443").into(),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn addresses(endpoints: &[Endpoint]) -> Vec<String> {
+        endpoints
+            .iter()
+            .map(|e| e.addresses[0].address.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn small_endpoint_list_is_unchanged() {
+        assert_eq!(subset(endpoints(3), 7, 5).len(), 3);
+    }
+
+    #[test]
+    fn deterministic_and_order_independent() {
+        let mut reversed = endpoints(10);
+        reversed.reverse();
+        assert_eq!(
+            addresses(&subset(endpoints(10), 3, 4)),
+            addresses(&subset(reversed, 3, 4))
+        );
+    }
+
+    #[test]
+    fn shuffle_is_stable() {
+        // Clients built by other versions or platforms must agree on subsets.
+        assert_eq!(
+            addresses(&subset(endpoints(6), 0, 3)),
+            ["10.0.0.2:443", "10.0.0.5:443", "10.0.0.1:443"]
+        );
+    }
+
+    #[test]
+    fn clients_in_a_round_are_balanced() {
+        // 12 endpoints with subsets of 4 gives rounds of 3 clients, which
+        // together should use every endpoint exactly once.
+        for round in 0..3 {
+            let mut counts: HashMap<String, usize> = HashMap::new();
+            for client in round * 3..round * 3 + 3 {
+                let s = subset(endpoints(12), client, 4);
+                assert_eq!(s.len(), 4);
+                for addr in addresses(&s) {
+                    *counts.entry(addr).or_default() += 1;
+                }
+            }
+            assert_eq!(counts.len(), 12);
+            assert!(counts.values().all(|&c| c == 1));
+        }
+    }
+}
@@ -23,11 +23,14 @@
  */
 
 use crate::client::load_balancing::{
-    ChannelController, ExternalSubchannel, ForwardingSubchannel, LbState, Subchannel, WorkScheduler,
+    ChannelController, ExternalSubchannel, ForwardingSubchannel, LbState, ScheduledWork,
+    Subchannel, WorkScheduler,
 };
 use crate::client::name_resolution::Address;
+use crate::rt::tokio::TokioRuntime;
 use crate::service::{Message, Request, Response, Service};
 use std::hash::{Hash, Hasher};
+use std::time::Duration;
 use std::{fmt::Debug, ops::Add, sync::Arc};
 use tokio::sync::{mpsc, Notify};
 use tokio::task::AbortHandle;
@@ -101,7 +104,7 @@ impl Debug for TestEvent {
             Self::NewSubchannel(sc) => write!(f, "NewSubchannel({})", sc.address()),
             Self::UpdatePicker(state) => write!(f, "UpdatePicker({})", state.connectivity_state),
             Self::RequestResolution => write!(f, "RequestResolution"),
-            Self::Connect(addr) => write!(f, "Connect({})", addr.address.to_string()),
+            Self::Connect(addr) => write!(f, "Connect({})", &*addr.address),
             Self::ScheduleWork => write!(f, "ScheduleWork"),
         }
     }
@@ -144,4 +147,11 @@ impl WorkScheduler for TestWorkScheduler {
     fn schedule_work(&self) {
         self.tx_events.send(TestEvent::ScheduleWork).unwrap();
     }
+
+    fn schedule_work_after(&self, delay: Duration) -> ScheduledWork {
+        let tx_events = self.tx_events.clone();
+        ScheduledWork::spawn(&TokioRuntime {}, delay, move || {
+            let _ = tx_events.send(TestEvent::ScheduleWork);
+        })
+    }
 }
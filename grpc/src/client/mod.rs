@@ -24,15 +24,54 @@
 
 use std::fmt::Display;
 
+pub mod call;
 pub mod channel;
+pub mod deadline;
+pub mod debug_state;
+pub mod error;
+pub mod fault_injection;
+pub mod labels;
 pub(crate) mod load_balancing;
 pub(crate) mod name_resolution;
+pub mod priority;
+pub mod request_hash;
+mod reresolution;
+pub mod resolution_cache;
+mod retry;
+mod retry_throttling;
 pub mod service_config;
+pub mod session_affinity;
 mod subchannel;
+mod tonic_adapter;
 pub(crate) mod transport;
+pub mod watchdog;
+mod work_queue;
 pub use channel::Channel;
 pub use channel::ChannelOptions;
 
+/// Registers the DNS resolver and the TCP transport, allowing channels to be
+/// created for "dns:" targets.
+///
+/// It must be called only at application startup, before any channels are
+/// created.
+#[cfg(feature = "_runtime-tokio")]
+pub fn reg() {
+    name_resolution::dns::reg();
+    transport::tonic::reg();
+}
+
+/// A transport carrying gRPC-Web over HTTP/1.1 connections.
+#[cfg(feature = "grpc-web")]
+pub mod grpc_web {
+    pub use super::transport::grpc_web::{reg, GRPC_WEB_NETWORK_TYPE};
+}
+
+/// An experimental transport carrying gRPC over HTTP/3 on QUIC connections.
+#[cfg(feature = "quic")]
+pub mod quic {
+    pub use super::transport::quic::{reg, QuicOptions, HTTP3_PORT, QUIC_NETWORK_TYPE};
+}
+
 /// A representation of the current state of a gRPC channel, also used for the
 /// state of subchannels (individual connections within the channel).
 ///
@@ -44,12 +83,41 @@ pub use channel::ChannelOptions;
 ///
 /// Channels may re-enter the Idle state if they are unused for longer than
 /// their configured idleness timeout.
+///
+/// Once a channel is shut down, its state is Shutdown, which is terminal: no
+/// further state changes occur.
 #[derive(Copy, Clone, PartialEq, Debug)]
 pub enum ConnectivityState {
     Idle,
     Connecting,
     Ready,
     TransientFailure,
+    Shutdown,
+}
+
+impl ConnectivityState {
+    /// Returns true if no further state changes may occur.
+    pub fn is_terminal(self) -> bool {
+        self == ConnectivityState::Shutdown
+    }
+
+    /// Returns whether a transition from this state to next is allowed, per
+    /// <https://github.com/grpc/grpc/blob/master/doc/connectivity-semantics-and-api.md>.
+    /// Any state may transition to Shutdown, and nothing may leave Shutdown.
+    /// A transition to the same state is always allowed.
+    pub fn can_transition_to(self, next: ConnectivityState) -> bool {
+        use ConnectivityState::*;
+        match (self, next) {
+            (Shutdown, _) => false,
+            (_, Shutdown) => true,
+            (a, b) if a == b => true,
+            (Idle, Connecting) => true,
+            (Connecting, Ready | TransientFailure | Idle) => true,
+            (Ready, Idle | TransientFailure) => true,
+            (TransientFailure, Connecting | Ready | Idle) => true,
+            _ => false,
+        }
+    }
 }
 
 impl Display for ConnectivityState {
@@ -59,6 +127,7 @@ impl Display for ConnectivityState {
             ConnectivityState::Connecting => write!(f, "Connecting"),
             ConnectivityState::Ready => write!(f, "Ready"),
             ConnectivityState::TransientFailure => write!(f, "TransientFailure"),
+            ConnectivityState::Shutdown => write!(f, "Shutdown"),
         }
     }
 }
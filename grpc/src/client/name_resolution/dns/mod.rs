@@ -39,19 +39,24 @@ use tokio::sync::Notify;
 use url::Host;
 
 use crate::{
-    byte_str::ByteStr,
+    client::error::{ResolveError, ResolveErrorKind},
     client::name_resolution::{global_registry, ChannelController, ResolverBuilder, Target},
+    leak_detector::LeakTracker,
     rt::{self, BoxedTaskHandle},
 };
 
 use super::{
     backoff::{BackoffConfig, ExponentialBackoff, DEFAULT_EXPONENTIAL_CONFIG},
-    Address, Endpoint, NopResolver, Resolver, ResolverOptions, ResolverUpdate, TCP_IP_NETWORK_TYPE,
+    Endpoint, NopResolver, Resolver, ResolverOptions, ResolverUpdate,
 };
 
+mod service_config;
+mod srv;
 #[cfg(test)]
 mod test;
 
+pub use srv::{SrvInfo, SRV_ENDPOINTS, SRV_INFO};
+
 const DEFAULT_PORT: u16 = 443;
 const DEFAULT_DNS_PORT: u16 = 53;
 
@@ -112,6 +117,9 @@ struct DnsOptions {
     backoff_config: BackoffConfig,
     host: String,
     port: u16,
+    disable_service_config_lookup: bool,
+    // The service to look up SRV records for, if enabled in the target.
+    srv_service: Option<String>,
 }
 
 impl DnsResolver {
@@ -122,6 +130,8 @@ impl DnsResolver {
     ) -> Self {
         let state = Arc::new(Mutex::new(InternalState {
             addrs: Ok(Vec::new()),
+            service_config: Ok(None),
+            srv_endpoints: None,
             channel_response: None,
         }));
         let state_copy = state.clone();
@@ -136,28 +146,71 @@ impl DnsResolver {
             let mut backoff = ExponentialBackoff::new(dns_opts.backoff_config.clone())
                 .expect("default exponential config must be valid");
             let state = state_copy;
+            let txt_name = service_config::txt_record_name(&dns_opts.host);
+            let hostname = service_config::local_hostname();
+            let srv_name = dns_opts
+                .srv_service
+                .as_ref()
+                .map(|service| srv::srv_record_name(service, &dns_opts.host));
             loop {
-                let mut lookup_fut = dns_client.lookup_host_name(&dns_opts.host);
-                let mut timeout_fut = runtime.sleep(dns_opts.resolving_timeout);
-                let addrs = tokio::select! {
-                    result = &mut lookup_fut => {
-                        match result {
-                            Ok(ips) => {
-                                let addrs = ips
-                                    .into_iter()
-                                    .map(|ip| SocketAddr::new(ip, dns_opts.port))
-                                    .collect();
-                                Ok(addrs)
-                            }
-                            Err(err) => Err(err),
+                let mut lookup_fut = Box::pin(async {
+                    let txt_fut = async {
+                        if dns_opts.disable_service_config_lookup {
+                            return Ok(Vec::new());
                         }
+                        dns_client.lookup_txt(&txt_name).await
+                    };
+                    let srv_fut = async {
+                        let name = srv_name.as_ref()?;
+                        // Failing to look up SRV records is not an error for
+                        // the resolution of the target's own addresses.
+                        Some(
+                            srv::lookup_srv_endpoints(dns_client.as_ref(), name)
+                                .await
+                                .unwrap_or_default(),
+                        )
+                    };
+                    tokio::join!(
+                        dns_client.lookup_host_name(&dns_opts.host),
+                        txt_fut,
+                        srv_fut
+                    )
+                });
+                let mut timeout_fut = runtime.sleep(dns_opts.resolving_timeout);
+                let (addrs, service_config, srv_endpoints) = tokio::select! {
+                    (result, txt_result, srv_endpoints) = &mut lookup_fut => {
+                        // Failing to look up TXT records is not an error; the
+                        // target simply has no service config.
+                        let service_config = match txt_result {
+                            Ok(records) => service_config::choose_service_config(
+                                &records,
+                                hostname.as_deref(),
+                                rand::random_range(1..=100),
+                            ),
+                            Err(_) => Ok(None),
+                        };
+                        let addrs = result.map_err(|err| {
+                            ResolveError::new(ResolveErrorKind::Lookup, err)
+                        }).map(|ips| {
+                            ips.into_iter()
+                                .map(|ip| SocketAddr::new(ip, dns_opts.port))
+                                .collect()
+                        });
+                        (addrs, service_config, srv_endpoints)
                     }
                     _ = &mut timeout_fut => {
-                        Err("Timed out waiting for DNS resolution".to_string())
+                        let err = ResolveError::new(
+                            ResolveErrorKind::Timeout,
+                            "Timed out waiting for DNS resolution",
+                        );
+                        (Err(err), Ok(None), None)
                     }
                 };
                 {
-                    state.lock().addrs = addrs;
+                    let mut state = state.lock();
+                    state.addrs = addrs;
+                    state.service_config = service_config;
+                    state.srv_endpoints = srv_endpoints;
                 }
                 work_scheduler.schedule_work();
                 channel_updated_rx.notified().await;
@@ -190,6 +243,7 @@ impl DnsResolver {
             task_handle: handle,
             resolve_now_notifier: resolve_now_notify,
             channel_update_notifier: channel_updated_notify,
+            _leak_tracker: LeakTracker::new("DnsResolver"),
         }
     }
 }
@@ -198,7 +252,10 @@ impl ResolverBuilder for Builder {
     fn build(&self, target: &Target, options: ResolverOptions) -> Box<dyn Resolver> {
         let parsed = match parse_endpoint_and_authority(target) {
             Ok(res) => res,
-            Err(err) => return nop_resolver_for_err(err.to_string(), options),
+            Err(err) => {
+                let err = ResolveError::new(ResolveErrorKind::InvalidTarget, err);
+                return nop_resolver_for_err(err, options);
+            }
         };
         let endpoint = parsed.endpoint;
         let host = match endpoint.host {
@@ -211,11 +268,18 @@ impl ResolverBuilder for Builder {
             }
         };
         let authority = parsed.authority;
+        let srv_service = match srv::parse_srv_service(target) {
+            Ok(service) => service,
+            Err(err) => {
+                let err = ResolveError::new(ResolveErrorKind::InvalidTarget, err);
+                return nop_resolver_for_err(err, options);
+            }
+        };
         let dns_client = match options.runtime.get_dns_resolver(rt::ResolverOptions {
             server_addr: authority,
         }) {
             Ok(dns) => dns,
-            Err(err) => return nop_resolver_for_err(err.to_string(), options),
+            Err(err) => return nop_resolver_for_err(ResolveError::from(err), options),
         };
         let dns_opts = DnsOptions {
             min_resolution_interval: get_min_resolution_interval(),
@@ -223,6 +287,8 @@ impl ResolverBuilder for Builder {
             backoff_config: DEFAULT_EXPONENTIAL_CONFIG,
             host,
             port: endpoint.port,
+            disable_service_config_lookup: options.disable_service_config_lookup,
+            srv_service,
         };
         Box::new(DnsResolver::new(dns_client, options, dns_opts))
     }
@@ -232,7 +298,9 @@ impl ResolverBuilder for Builder {
     }
 
     fn is_valid_uri(&self, target: &Target) -> bool {
-        if let Err(err) = parse_endpoint_and_authority(target) {
+        let result =
+            parse_endpoint_and_authority(target).and_then(|_| srv::parse_srv_service(target));
+        if let Err(err) = result {
             eprintln!("{err}");
             false
         } else {
@@ -246,10 +314,15 @@ struct DnsResolver {
     task_handle: BoxedTaskHandle,
     resolve_now_notifier: Arc<Notify>,
     channel_update_notifier: Arc<Notify>,
+    _leak_tracker: LeakTracker,
 }
 
 struct InternalState {
-    addrs: Result<Vec<SocketAddr>, String>,
+    addrs: Result<Vec<SocketAddr>, ResolveError>,
+    // The JSON service config selected from the TXT records, if any.
+    service_config: Result<Option<String>, String>,
+    // Endpoints discovered through SRV records, if SRV lookups are enabled.
+    srv_endpoints: Option<Vec<Endpoint>>,
     // Error from the latest call to channel_controller.update().
     channel_response: Option<String>,
 }
@@ -261,27 +334,22 @@ impl Resolver for DnsResolver {
 
     fn work(&mut self, channel_controller: &mut dyn ChannelController) {
         let mut state = self.state.lock();
-        let endpoint_result = match &state.addrs {
+        let update = match &state.addrs {
             Ok(addrs) => {
-                let endpoints: Vec<_> = addrs
-                    .iter()
-                    .map(|a| Endpoint {
-                        addresses: vec![Address {
-                            network_type: TCP_IP_NETWORK_TYPE,
-                            address: ByteStr::from(a.to_string()),
-                            ..Default::default()
-                        }],
-                        ..Default::default()
-                    })
-                    .collect();
-                Ok(endpoints)
+                ResolverUpdate::builder().endpoints(Endpoint::from_tcp_addrs(addrs.iter().copied()))
             }
-            Err(err) => Err(err.to_string()),
+            Err(err) => ResolverUpdate::builder().endpoints_error(err.clone()),
         };
-        let update = ResolverUpdate {
-            endpoints: endpoint_result,
-            ..Default::default()
+        let service_config = match &state.service_config {
+            Ok(Some(config)) => channel_controller.parse_service_config(config).map(Some),
+            Ok(None) => Ok(None),
+            Err(err) => Err(err.clone()),
         };
+        let mut update = update.service_config(service_config);
+        if let Some(srv_endpoints) = &state.srv_endpoints {
+            update = update.attr(&SRV_ENDPOINTS, srv_endpoints.clone());
+        }
+        let update = update.build();
         let status = channel_controller.update(update);
         state.channel_response = status.err();
         self.channel_update_notifier.notify_one();
@@ -370,26 +438,15 @@ fn parse_host_port(host_and_port: &str, default_port: u16) -> Result<Option<Host
 fn nop_resolver_for_ip(ip: IpAddr, port: u16, options: ResolverOptions) -> Box<dyn Resolver> {
     options.work_scheduler.schedule_work();
     Box::new(NopResolver {
-        update: ResolverUpdate {
-            endpoints: Ok(vec![Endpoint {
-                addresses: vec![Address {
-                    network_type: TCP_IP_NETWORK_TYPE,
-                    address: ByteStr::from(SocketAddr::new(ip, port).to_string()),
-                    ..Default::default()
-                }],
-                ..Default::default()
-            }]),
-            ..Default::default()
-        },
+        update: ResolverUpdate::builder()
+            .endpoints(Endpoint::from_tcp_addrs([SocketAddr::new(ip, port)]))
+            .build(),
     })
 }
 
-fn nop_resolver_for_err(err: String, options: ResolverOptions) -> Box<dyn Resolver> {
+fn nop_resolver_for_err(err: ResolveError, options: ResolverOptions) -> Box<dyn Resolver> {
     options.work_scheduler.schedule_work();
     Box::new(NopResolver {
-        update: ResolverUpdate {
-            endpoints: Err(err),
-            ..Default::default()
-        },
+        update: ResolverUpdate::builder().endpoints_error(err).build(),
     })
 }
@@ -0,0 +1,193 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! Selection of a service config from the DNS TXT records of a target, as
+//! described in gRFC A2:
+//! <https://github.com/grpc/proposal/blob/master/A2-service-configs-in-dns.md>
+
+use serde::Deserialize;
+
+/// The prefix of the TXT record containing the service config choices.
+const TXT_ATTRIBUTE_PREFIX: &str = "grpc_config=";
+
+/// The name used to match the clientLanguage selector.
+const CLIENT_LANGUAGE: &str = "rust";
+
+/// Returns the name to query for the service config TXT records of host.
+pub(super) fn txt_record_name(host: &str) -> String {
+    format!("_grpc_config.{host}")
+}
+
+/// Returns the name of the local machine, used to evaluate the clientHostname
+/// selector.
+pub(super) fn local_hostname() -> Option<String> {
+    std::env::var("HOSTNAME").ok().filter(|h| !h.is_empty())
+}
+
+/// One entry of the list of service config choices.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct Choice {
+    client_language: Option<Vec<String>>,
+    percentage: Option<u32>,
+    client_hostname: Option<Vec<String>>,
+    service_config: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Choice {
+    // Returns true if the choice applies to this client.  roll is a random
+    // value in [1, 100] used to evaluate the percentage selector.
+    fn matches(&self, hostname: Option<&str>, roll: u32) -> bool {
+        if let Some(langs) = &self.client_language {
+            if !langs
+                .iter()
+                .any(|l| l.eq_ignore_ascii_case(CLIENT_LANGUAGE))
+            {
+                return false;
+            }
+        }
+        if let Some(percentage) = self.percentage {
+            if roll > percentage {
+                return false;
+            }
+        }
+        if let Some(hostnames) = &self.client_hostname {
+            if !hostname.is_some_and(|h| hostnames.iter().any(|c| c == h)) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Picks the service config applicable to this client from the TXT records of
+/// a target.  Returns Ok(None) if the records contain no service config or no
+/// choice matches, and an error if the choice list is malformed.
+///
+/// hostname is the name of the local machine, used to evaluate the
+/// clientHostname selector; choices using it never match if it is unknown.
+/// roll is a random value in [1, 100] used to evaluate the percentage selector.
+pub(super) fn choose_service_config(
+    records: &[String],
+    hostname: Option<&str>,
+    roll: u32,
+) -> Result<Option<String>, String> {
+    let Some(choices) = records
+        .iter()
+        .find_map(|r| r.strip_prefix(TXT_ATTRIBUTE_PREFIX))
+    else {
+        return Ok(None);
+    };
+    let choices: Vec<Choice> = serde_json::from_str(choices)
+        .map_err(|e| format!("failed to parse service config choices: {e}"))?;
+    for choice in choices {
+        if let Some(percentage) = choice.percentage {
+            if percentage > 100 {
+                return Err(format!(
+                    "service config choice has invalid percentage {percentage}"
+                ));
+            }
+        }
+        if choice.matches(hostname, roll) {
+            return serde_json::to_string(&choice.service_config)
+                .map(Some)
+                .map_err(|e| e.to_string());
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{choose_service_config, txt_record_name};
+
+    fn records(choices: &str) -> Vec<String> {
+        vec![
+            "unrelated=record".to_string(),
+            format!("grpc_config={choices}"),
+        ]
+    }
+
+    #[test]
+    fn record_name() {
+        assert_eq!(txt_record_name("grpc.io"), "_grpc_config.grpc.io");
+    }
+
+    #[test]
+    fn no_config() {
+        assert_eq!(choose_service_config(&[], None, 1), Ok(None));
+        let recs = vec!["v=spf1 -all".to_string()];
+        assert_eq!(choose_service_config(&recs, None, 1), Ok(None));
+    }
+
+    #[test]
+    fn selects_first_matching_choice() {
+        let recs = records(
+            r#"[
+                {"clientLanguage": ["go", "java"], "serviceConfig": {"a": 1}},
+                {"clientHostname": ["other"], "serviceConfig": {"a": 2}},
+                {"percentage": 10, "serviceConfig": {"a": 3}},
+                {"clientLanguage": ["RUST"], "serviceConfig": {"a": 4}},
+                {"serviceConfig": {"a": 5}}
+            ]"#,
+        );
+        assert_eq!(
+            choose_service_config(&recs, Some("me"), 50),
+            Ok(Some(r#"{"a":4}"#.to_string()))
+        );
+        // The percentage selector applies when the roll is within it.
+        assert_eq!(
+            choose_service_config(&recs, Some("me"), 10),
+            Ok(Some(r#"{"a":3}"#.to_string()))
+        );
+        // So does the hostname selector when the hostname matches.
+        assert_eq!(
+            choose_service_config(&recs, Some("other"), 50),
+            Ok(Some(r#"{"a":2}"#.to_string()))
+        );
+    }
+
+    #[test]
+    fn no_matching_choice() {
+        let recs = records(r#"[{"percentage": 0, "serviceConfig": {}}]"#);
+        assert_eq!(choose_service_config(&recs, None, 1), Ok(None));
+    }
+
+    #[test]
+    fn malformed_choices() {
+        for choices in [
+            "not json",
+            r#"{"serviceConfig": {}}"#,
+            r#"[{"clientLanguage": ["rust"]}]"#,
+            r#"[{"unknownField": 1, "serviceConfig": {}}]"#,
+            r#"[{"percentage": 101, "serviceConfig": {}}]"#,
+            r#"[{"serviceConfig": "not an object"}]"#,
+        ] {
+            assert!(
+                choose_service_config(&records(choices), None, 1).is_err(),
+                "{choices}"
+            );
+        }
+    }
+}
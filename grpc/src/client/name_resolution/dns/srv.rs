@@ -0,0 +1,121 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! Optional SRV lookups for DNS targets.  These discover endpoints which are
+//! not backends of the target itself, e.g. look-aside balancers, and report
+//! them to the LB policy through the attributes of the resolver update.
+
+use std::net::SocketAddr;
+
+use crate::{
+    attributes::AttributeKey,
+    client::name_resolution::{Address, Endpoint, Target},
+    rt,
+};
+
+/// The query parameter of a DNS target which enables SRV lookups for the named
+/// service.  For example, "dns:///example.com?srv=grpclb" looks up the SRV
+/// records of "_grpclb._tcp.example.com".
+const SRV_QUERY_PARAM: &str = "srv";
+
+/// Attribute of a ResolverUpdate from the DNS resolver containing one endpoint
+/// per SRV record of the target.  Present only if SRV lookups are enabled for
+/// the target; lookup failures result in an empty list.
+pub const SRV_ENDPOINTS: AttributeKey<Vec<Endpoint>> = AttributeKey::new("grpc.dns.srv_endpoints");
+
+/// Attribute of each endpoint in SRV_ENDPOINTS describing the SRV record it
+/// was created from.
+pub const SRV_INFO: AttributeKey<SrvInfo> = AttributeKey::new("grpc.dns.srv_info");
+
+/// The fields of an SRV record which are not represented by the addresses of
+/// the endpoint created from it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SrvInfo {
+    /// Lower values are preferred.
+    pub priority: u16,
+    /// The relative weight among records with the same priority.
+    pub weight: u16,
+    /// The host name of the record, which was resolved to the endpoint's
+    /// addresses.
+    pub target: String,
+}
+
+/// Returns the service name to look up SRV records for, if enabled in the
+/// target's query string.
+pub(super) fn parse_srv_service(target: &Target) -> Result<Option<String>, String> {
+    let Some(service) = target.query_param(SRV_QUERY_PARAM) else {
+        return Ok(None);
+    };
+    if service.is_empty()
+        || !service
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-')
+    {
+        return Err(format!(
+            "Invalid SRV service name {service:?} in target {target}"
+        ));
+    }
+    Ok(Some(service))
+}
+
+/// Returns the name to query for the SRV records of service on host.
+pub(super) fn srv_record_name(service: &str, host: &str) -> String {
+    format!("_{service}._tcp.{host}")
+}
+
+/// Looks up the SRV records named name and resolves their targets.  Returns
+/// one endpoint per record whose target could be resolved, ordered by
+/// priority.
+pub(super) async fn lookup_srv_endpoints(
+    dns_client: &dyn rt::DnsResolver,
+    name: &str,
+) -> Result<Vec<Endpoint>, String> {
+    let mut records = dns_client.lookup_srv(name).await?;
+    records.sort_by_key(|r| r.priority);
+    let mut endpoints = Vec::with_capacity(records.len());
+    for record in records {
+        let Ok(ips) = dns_client.lookup_host_name(&record.target).await else {
+            continue;
+        };
+        let endpoint = Endpoint::builder()
+            .addresses(
+                ips.into_iter()
+                    .map(|ip| Address::tcp(SocketAddr::new(ip, record.port))),
+            )
+            .attr(
+                &SRV_INFO,
+                SrvInfo {
+                    priority: record.priority,
+                    weight: record.weight,
+                    target: record.target,
+                },
+            )
+            .build();
+        // Targets without addresses are skipped.
+        if let Ok(endpoint) = endpoint {
+            endpoints.push(endpoint);
+        }
+    }
+    Ok(endpoints)
+}
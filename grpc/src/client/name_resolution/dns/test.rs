@@ -24,19 +24,20 @@
 
 use std::{future::Future, pin::Pin, sync::Arc, time::Duration};
 
-use tokio::sync::mpsc::{self, UnboundedSender};
 use url::Host;
 
 use crate::{
     client::{
+        error::ResolveErrorKind,
         name_resolution::{
             backoff::{BackoffConfig, DEFAULT_EXPONENTIAL_CONFIG},
             dns::{
                 get_min_resolution_interval, get_resolving_timeout, parse_endpoint_and_authority,
-                reg, DnsResolver, HostPort,
+                reg, DnsResolver, HostPort, SrvInfo, SRV_ENDPOINTS, SRV_INFO,
             },
-            global_registry, ChannelController, Resolver, ResolverOptions, ResolverUpdate, Target,
-            WorkScheduler,
+            global_registry,
+            testing::ResolverTester,
+            Address, Target,
         },
         service_config::ServiceConfig,
     },
@@ -163,59 +164,14 @@ pub fn target_parsing() {
     }
 }
 
-struct FakeWorkScheduler {
-    work_tx: UnboundedSender<()>,
-}
-
-impl WorkScheduler for FakeWorkScheduler {
-    fn schedule_work(&self) {
-        self.work_tx.send(()).unwrap();
-    }
-}
-
-struct FakeChannelController {
-    update_result: Result<(), String>,
-    update_tx: UnboundedSender<ResolverUpdate>,
-}
-
-impl ChannelController for FakeChannelController {
-    fn update(&mut self, update: ResolverUpdate) -> Result<(), String> {
-        println!("Received resolver update: {:?}", &update);
-        self.update_tx.send(update).unwrap();
-        self.update_result.clone()
-    }
-
-    fn parse_service_config(&self, _: &str) -> Result<ServiceConfig, String> {
-        Err("Unimplemented".to_string())
-    }
-}
-
 #[tokio::test]
 pub async fn dns_basic() {
     reg();
     let builder = global_registry().get("dns").unwrap();
-    let target = &"dns:///localhost:1234".parse().unwrap();
-    let (work_tx, mut work_rx) = mpsc::unbounded_channel();
-    let work_scheduler = Arc::new(FakeWorkScheduler {
-        work_tx: work_tx.clone(),
-    });
-    let opts = ResolverOptions {
-        authority: "ignored".to_string(),
-        runtime: Arc::new(TokioRuntime {}),
-        work_scheduler: work_scheduler.clone(),
-    };
-    let mut resolver = builder.build(target, opts);
-
-    // Wait for schedule work to be called.
-    work_rx.recv().await.unwrap();
-    let (update_tx, mut update_rx) = mpsc::unbounded_channel();
-    let mut channel_controller = FakeChannelController {
-        update_tx,
-        update_result: Ok(()),
-    };
-    resolver.work(&mut channel_controller);
+    let mut tester = ResolverTester::new(builder.as_ref(), "dns:///localhost:1234");
+
     // A successful endpoint update should be received.
-    let update = update_rx.recv().await.unwrap();
+    let update = tester.next_update().await;
     assert!(update.endpoints.unwrap().len() > 1);
 }
 
@@ -223,39 +179,21 @@ pub async fn dns_basic() {
 pub async fn invalid_target() {
     reg();
     let builder = global_registry().get("dns").unwrap();
-    let target = &"dns:///:1234".parse().unwrap();
-    let (work_tx, mut work_rx) = mpsc::unbounded_channel();
-    let work_scheduler = Arc::new(FakeWorkScheduler {
-        work_tx: work_tx.clone(),
-    });
-    let opts = ResolverOptions {
-        authority: "ignored".to_string(),
-        runtime: Arc::new(TokioRuntime {}),
-        work_scheduler: work_scheduler.clone(),
-    };
-    let mut resolver = builder.build(target, opts);
-
-    // Wait for schedule work to be called.
-    work_rx.recv().await.unwrap();
-    let (update_tx, mut update_rx) = mpsc::unbounded_channel();
-    let mut channel_controller = FakeChannelController {
-        update_tx,
-        update_result: Ok(()),
-    };
-    resolver.work(&mut channel_controller);
+    let mut tester = ResolverTester::new(builder.as_ref(), "dns:///:1234");
+
     // An error endpoint update should be received.
-    let update = update_rx.recv().await.unwrap();
-    assert!(update
-        .endpoints
-        .err()
-        .unwrap()
-        .contains(&target.to_string()));
+    let update = tester.next_update().await;
+    let err = update.endpoints.err().unwrap();
+    assert_eq!(err.kind(), ResolveErrorKind::InvalidTarget);
+    assert!(err.to_string().contains("dns:///:1234"));
 }
 
 #[derive(Clone)]
 struct FakeDns {
     latency: Duration,
     lookup_result: Result<Vec<std::net::IpAddr>, String>,
+    txt_result: Result<Vec<String>, String>,
+    srv_result: Result<Vec<rt::SrvRecord>, String>,
 }
 
 #[tonic::async_trait]
@@ -265,8 +203,14 @@ impl rt::DnsResolver for FakeDns {
         self.lookup_result.clone()
     }
 
-    async fn lookup_txt(&self, _: &str) -> Result<Vec<String>, String> {
-        Err("unimplemented".to_string())
+    async fn lookup_txt(&self, name: &str) -> Result<Vec<String>, String> {
+        assert_eq!(name, "_grpc_config.grpc.io");
+        self.txt_result.clone()
+    }
+
+    async fn lookup_srv(&self, name: &str) -> Result<Vec<rt::SrvRecord>, String> {
+        assert_eq!(name, "_grpclb._tcp.grpc.io");
+        self.srv_result.clone()
     }
 }
 
@@ -304,186 +248,124 @@ impl rt::Runtime for FakeRuntime {
 pub async fn dns_lookup_error() {
     reg();
     let builder = global_registry().get("dns").unwrap();
-    let target = &"dns:///grpc.io:1234".parse().unwrap();
-    let (work_tx, mut work_rx) = mpsc::unbounded_channel();
-    let work_scheduler = Arc::new(FakeWorkScheduler {
-        work_tx: work_tx.clone(),
-    });
     let runtime = FakeRuntime {
         inner: TokioRuntime {},
         dns: FakeDns {
             latency: Duration::from_secs(0),
             lookup_result: Err("test_error".to_string()),
+            txt_result: Err("unimplemented".to_string()),
+            srv_result: Err("unimplemented".to_string()),
         },
     };
-    let opts = ResolverOptions {
-        authority: "ignored".to_string(),
-        runtime: Arc::new(runtime),
-        work_scheduler: work_scheduler.clone(),
-    };
-    let mut resolver = builder.build(target, opts);
-
-    // Wait for schedule work to be called.
-    work_rx.recv().await.unwrap();
-    let (update_tx, mut update_rx) = mpsc::unbounded_channel();
-    let mut channel_controller = FakeChannelController {
-        update_tx,
-        update_result: Ok(()),
-    };
-    resolver.work(&mut channel_controller);
+    let mut tester = ResolverTester::with_runtime(
+        builder.as_ref(),
+        "dns:///grpc.io:1234",
+        Arc::new(runtime),
+        |_| {},
+    );
+
     // An error endpoint update should be received.
-    let update = update_rx.recv().await.unwrap();
-    assert!(update.endpoints.err().unwrap().contains("test_error"));
+    let update = tester.next_update().await;
+    assert!(update
+        .endpoints
+        .err()
+        .unwrap()
+        .to_string()
+        .contains("test_error"));
 }
 
 #[tokio::test]
 pub async fn dns_lookup_timeout() {
-    let (work_tx, mut work_rx) = mpsc::unbounded_channel();
-    let work_scheduler = Arc::new(FakeWorkScheduler {
-        work_tx: work_tx.clone(),
-    });
     let runtime = FakeRuntime {
         inner: TokioRuntime {},
         dns: FakeDns {
             latency: Duration::from_secs(20),
             lookup_result: Ok(Vec::new()),
+            txt_result: Err("unimplemented".to_string()),
+            srv_result: Err("unimplemented".to_string()),
         },
     };
     let dns_client = runtime.dns.clone();
-    let opts = ResolverOptions {
-        authority: "ignored".to_string(),
-        runtime: Arc::new(runtime),
-        work_scheduler: work_scheduler.clone(),
-    };
     let dns_opts = DnsOptions {
         min_resolution_interval: get_min_resolution_interval(),
         resolving_timeout: DEFAULT_TEST_SHORT_TIMEOUT,
         backoff_config: DEFAULT_EXPONENTIAL_CONFIG,
         host: "grpc.io".to_string(),
         port: 1234,
+        disable_service_config_lookup: false,
+        srv_service: None,
     };
-    let mut resolver = DnsResolver::new(Box::new(dns_client), opts, dns_opts);
-
-    // Wait for schedule work to be called.
-    work_rx.recv().await.unwrap();
-    let (update_tx, mut update_rx) = mpsc::unbounded_channel();
-    let mut channel_controller = FakeChannelController {
-        update_tx,
-        update_result: Ok(()),
-    };
-    resolver.work(&mut channel_controller);
+    let mut tester = ResolverTester::from_fn(Arc::new(runtime), |opts| {
+        Box::new(DnsResolver::new(Box::new(dns_client), opts, dns_opts))
+    });
 
     // An error endpoint update should be received.
-    let update = update_rx.recv().await.unwrap();
-    assert!(update.endpoints.err().unwrap().contains("Timed out"));
+    let update = tester.next_update().await;
+    let err = update.endpoints.err().unwrap();
+    assert_eq!(err.kind(), ResolveErrorKind::Timeout);
+    assert!(err.to_string().contains("Timed out"));
+}
+
+// Creates a tester for a DNS resolver which uses the system's DNS resolver to
+// resolve localhost.
+fn localhost_resolver(dns_opts: DnsOptions) -> ResolverTester {
+    ResolverTester::from_fn(Arc::new(TokioRuntime {}), |opts| {
+        let dns_client = opts
+            .runtime
+            .get_dns_resolver(rt::ResolverOptions { server_addr: None })
+            .unwrap();
+        Box::new(DnsResolver::new(dns_client, opts, dns_opts))
+    })
 }
 
 #[tokio::test]
 pub async fn rate_limit() {
-    let (work_tx, mut work_rx) = mpsc::unbounded_channel();
-    let work_scheduler = Arc::new(FakeWorkScheduler {
-        work_tx: work_tx.clone(),
-    });
-    let opts = ResolverOptions {
-        authority: "ignored".to_string(),
-        runtime: Arc::new(TokioRuntime {}),
-        work_scheduler: work_scheduler.clone(),
-    };
-    let dns_client = opts
-        .runtime
-        .get_dns_resolver(rt::ResolverOptions { server_addr: None })
-        .unwrap();
-    let dns_opts = DnsOptions {
+    let mut tester = localhost_resolver(DnsOptions {
         min_resolution_interval: Duration::from_secs(20),
         resolving_timeout: get_resolving_timeout(),
         backoff_config: DEFAULT_EXPONENTIAL_CONFIG,
         host: "localhost".to_string(),
         port: 1234,
-    };
-    let mut resolver = DnsResolver::new(dns_client, opts, dns_opts);
-
-    // Wait for schedule work to be called.
-    work_rx.recv().await.unwrap();
-    let (update_tx, mut update_rx) = mpsc::unbounded_channel();
-    let mut channel_controller = FakeChannelController {
-        update_tx,
-        update_result: Ok(()),
-    };
-    resolver.work(&mut channel_controller);
+        disable_service_config_lookup: false,
+        srv_service: None,
+    });
+
     // A successful endpoint update should be received.
-    let update = update_rx.recv().await.unwrap();
+    let update = tester.next_update().await;
     assert!(update.endpoints.unwrap().len() > 1);
 
     // Call resolve_now repeatedly, new updates should not be produced.
     for _ in 0..5 {
-        resolver.resolve_now();
-        tokio::select! {
-            _ = work_rx.recv() => {
-                panic!("Received unexpected work request from resolver");
-            }
-            _ = tokio::time::sleep(DEFAULT_TEST_SHORT_TIMEOUT) => {
-                println!("No work requested from resolver.");
-            }
-        };
+        tester.resolve_now();
+        tester.expect_no_work(DEFAULT_TEST_SHORT_TIMEOUT).await;
     }
 }
 
 #[tokio::test]
 pub async fn re_resolution_after_success() {
-    let (work_tx, mut work_rx) = mpsc::unbounded_channel();
-    let work_scheduler = Arc::new(FakeWorkScheduler {
-        work_tx: work_tx.clone(),
-    });
-    let opts = ResolverOptions {
-        authority: "ignored".to_string(),
-        runtime: Arc::new(TokioRuntime {}),
-        work_scheduler: work_scheduler.clone(),
-    };
-    let dns_opts = DnsOptions {
+    let mut tester = localhost_resolver(DnsOptions {
         min_resolution_interval: Duration::from_millis(1),
         resolving_timeout: get_resolving_timeout(),
         backoff_config: DEFAULT_EXPONENTIAL_CONFIG,
         host: "localhost".to_string(),
         port: 1234,
-    };
-    let dns_client = opts
-        .runtime
-        .get_dns_resolver(rt::ResolverOptions { server_addr: None })
-        .unwrap();
-    let mut resolver = DnsResolver::new(dns_client, opts, dns_opts);
-
-    // Wait for schedule work to be called.
-    work_rx.recv().await.unwrap();
-    let (update_tx, mut update_rx) = mpsc::unbounded_channel();
-    let mut channel_controller = FakeChannelController {
-        update_tx,
-        update_result: Ok(()),
-    };
-    resolver.work(&mut channel_controller);
+        disable_service_config_lookup: false,
+        srv_service: None,
+    });
+
     // A successful endpoint update should be received.
-    let update = update_rx.recv().await.unwrap();
+    let update = tester.next_update().await;
     assert!(update.endpoints.unwrap().len() > 1);
 
     // Call resolve_now, a new update should be produced.
-    resolver.resolve_now();
-    work_rx.recv().await.unwrap();
-    resolver.work(&mut channel_controller);
-    let update = update_rx.recv().await.unwrap();
+    tester.resolve_now();
+    let update = tester.next_update().await;
     assert!(update.endpoints.unwrap().len() > 1);
 }
 
 #[tokio::test]
 pub async fn backoff_on_error() {
-    let (work_tx, mut work_rx) = mpsc::unbounded_channel();
-    let work_scheduler = Arc::new(FakeWorkScheduler {
-        work_tx: work_tx.clone(),
-    });
-    let opts = ResolverOptions {
-        authority: "ignored".to_string(),
-        runtime: Arc::new(TokioRuntime {}),
-        work_scheduler: work_scheduler.clone(),
-    };
-    let dns_opts = DnsOptions {
+    let mut tester = localhost_resolver(DnsOptions {
         min_resolution_interval: Duration::from_millis(1),
         resolving_timeout: get_resolving_timeout(),
         // Speed up the backoffs to make the test run faster.
@@ -495,44 +377,151 @@ pub async fn backoff_on_error() {
         },
         host: "localhost".to_string(),
         port: 1234,
-    };
-    let dns_client = opts
-        .runtime
-        .get_dns_resolver(rt::ResolverOptions { server_addr: None })
-        .unwrap();
-
-    let mut resolver = DnsResolver::new(dns_client, opts, dns_opts);
-
-    let (update_tx, mut update_rx) = mpsc::unbounded_channel();
-    let mut channel_controller = FakeChannelController {
-        update_tx,
-        update_result: Err("test_error".to_string()),
-    };
+        disable_service_config_lookup: false,
+        srv_service: None,
+    });
+    tester.controller.update_result = Err("test_error".to_string());
 
     // As the channel returned an error to the resolver, the resolver will
     // backoff and re-attempt resolution.
     for _ in 0..5 {
-        work_rx.recv().await.unwrap();
-        resolver.work(&mut channel_controller);
-        let update = update_rx.recv().await.unwrap();
+        let update = tester.next_update().await;
         assert!(update.endpoints.unwrap().len() > 1);
     }
 
     // This time the channel accepts the resolver update.
-    channel_controller.update_result = Ok(());
-    work_rx.recv().await.unwrap();
-    resolver.work(&mut channel_controller);
-    let update = update_rx.recv().await.unwrap();
+    tester.controller.update_result = Ok(());
+    let update = tester.next_update().await;
     assert!(update.endpoints.unwrap().len() > 1);
 
     // Since the channel controller returns Ok(), the resolver will stop
     // producing more updates.
-    tokio::select! {
-        _ = work_rx.recv() => {
-            panic!("Received unexpected work request from resolver.");
-        }
-        _ = tokio::time::sleep(DEFAULT_TEST_SHORT_TIMEOUT) => {
-            println!("No event received from resolver.");
-        }
+    tester.expect_no_work(DEFAULT_TEST_SHORT_TIMEOUT).await;
+}
+
+// Resolves grpc.io with the given TXT lookup result, and returns the service
+// config from the update along with the configs the resolver asked the channel
+// to parse.
+async fn resolve_service_config(
+    txt_result: Result<Vec<String>, String>,
+    disable_service_config_lookup: bool,
+) -> (Result<Option<ServiceConfig>, String>, Vec<String>) {
+    reg();
+    let builder = global_registry().get("dns").unwrap();
+    let runtime = FakeRuntime {
+        inner: TokioRuntime {},
+        dns: FakeDns {
+            latency: Duration::from_secs(0),
+            lookup_result: Ok(vec!["1.2.3.4".parse().unwrap()]),
+            txt_result,
+            srv_result: Err("unimplemented".to_string()),
+        },
     };
+    let mut tester = ResolverTester::with_runtime(
+        builder.as_ref(),
+        "dns:///grpc.io:1234",
+        Arc::new(runtime),
+        |opts| opts.disable_service_config_lookup = disable_service_config_lookup,
+    );
+
+    let update = tester.next_update().await;
+    assert_eq!(update.endpoints.unwrap().len(), 1);
+    (update.service_config, tester.controller.service_configs())
+}
+
+#[tokio::test]
+pub async fn dns_service_config_lookup() {
+    let records = Ok(vec![
+        r#"grpc_config=[{"serviceConfig":{"loadBalancingConfig":[]}}]"#.to_string(),
+    ]);
+
+    // The selected config is passed to the channel for parsing.
+    let (got, parsed) = resolve_service_config(records.clone(), false).await;
+    assert!(matches!(got, Ok(Some(_))));
+    assert_eq!(parsed, [r#"{"loadBalancingConfig":[]}"#]);
+
+    // The lookup is skipped when disabled.
+    let (got, parsed) = resolve_service_config(records, true).await;
+    assert!(matches!(got, Ok(None)));
+    assert!(parsed.is_empty());
+
+    // A missing TXT record means there is no service config.
+    let (got, _) = resolve_service_config(Err("NXDOMAIN".to_string()), false).await;
+    assert!(matches!(got, Ok(None)));
+
+    // Malformed choices are reported as a service config error.
+    let (got, parsed) =
+        resolve_service_config(Ok(vec!["grpc_config=[{}]".to_string()]), false).await;
+    assert!(got.err().unwrap().contains("service config choices"));
+    assert!(parsed.is_empty());
+}
+
+#[tokio::test]
+pub async fn dns_srv_lookup() {
+    reg();
+    let builder = global_registry().get("dns").unwrap();
+    assert!(!builder.is_valid_uri(&"dns:///grpc.io?srv=_bad".parse().unwrap()));
+
+    let target = "dns:///grpc.io:1234?srv=grpclb";
+    assert!(builder.is_valid_uri(&target.parse().unwrap()));
+    let runtime = FakeRuntime {
+        inner: TokioRuntime {},
+        dns: FakeDns {
+            latency: Duration::from_secs(0),
+            lookup_result: Ok(vec!["1.2.3.4".parse().unwrap()]),
+            txt_result: Err("unimplemented".to_string()),
+            srv_result: Ok(vec![
+                rt::SrvRecord {
+                    priority: 20,
+                    weight: 1,
+                    port: 5678,
+                    target: "lb2.grpc.io.".to_string(),
+                },
+                rt::SrvRecord {
+                    priority: 10,
+                    weight: 2,
+                    port: 5679,
+                    target: "lb1.grpc.io.".to_string(),
+                },
+            ]),
+        },
+    };
+    let mut tester =
+        ResolverTester::with_runtime(builder.as_ref(), target, Arc::new(runtime), |_| {});
+
+    let update = tester.next_update().await;
+    assert_eq!(update.endpoints.unwrap().len(), 1);
+
+    // The SRV endpoints are ordered by priority.
+    let srv_endpoints = update.attributes.get(&SRV_ENDPOINTS).unwrap();
+    let got: Vec<_> = srv_endpoints
+        .iter()
+        .map(|ep| {
+            (
+                ep.addresses.clone(),
+                ep.attributes.get(&SRV_INFO).unwrap().clone(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        got,
+        vec![
+            (
+                vec![Address::tcp("1.2.3.4:5679".parse().unwrap())],
+                SrvInfo {
+                    priority: 10,
+                    weight: 2,
+                    target: "lb1.grpc.io.".to_string(),
+                },
+            ),
+            (
+                vec![Address::tcp("1.2.3.4:5678".parse().unwrap())],
+                SrvInfo {
+                    priority: 20,
+                    weight: 1,
+                    target: "lb2.grpc.io.".to_string(),
+                },
+            ),
+        ]
+    );
 }
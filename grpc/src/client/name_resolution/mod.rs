@@ -30,18 +30,29 @@
 use core::fmt;
 
 use super::service_config::ServiceConfig;
-use crate::{attributes::Attributes, byte_str::ByteStr, rt::Runtime};
+use crate::{
+    attributes::{AttributeKey, Attributes},
+    byte_str::ByteStr,
+    client::error::ResolveError,
+    rt::Runtime,
+};
 use std::{
+    cmp::Ordering,
+    collections::HashSet,
     fmt::{Display, Formatter},
     hash::{Hash, Hasher},
+    net::SocketAddr,
     str::FromStr,
     sync::Arc,
 };
 
-mod backoff;
-mod dns;
+pub(crate) mod backoff;
+pub(crate) mod dns;
 mod registry;
-pub use registry::global_registry;
+#[cfg(test)]
+pub(crate) mod testing;
+pub use dns::{SrvInfo, SRV_ENDPOINTS, SRV_INFO};
+pub use registry::{global_registry, ResolverRegistry};
 use url::Url;
 
 /// Target represents a target for gRPC, as specified in:
@@ -117,6 +128,15 @@ impl Target {
     pub fn path(&self) -> &str {
         self.url.path()
     }
+
+    /// Returns the value of the first query parameter named key, if present.
+    /// Resolvers may use query parameters as resolver-specific options.
+    pub fn query_param(&self, key: &str) -> Option<String> {
+        self.url
+            .query_pairs()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.into_owned())
+    }
 }
 
 impl Display for Target {
@@ -176,28 +196,46 @@ pub struct ResolverOptions {
     pub authority: String,
 
     /// The runtime which provides utilities to do async work.
-    pub runtime: Arc<dyn Runtime>,
+    // TODO: make this public along with the runtime API.
+    pub(crate) runtime: Arc<dyn Runtime>,
 
     /// A hook into the channel's work scheduler that allows the Resolver to
     /// request the ability to perform operations on the ChannelController.
     pub work_scheduler: Arc<dyn WorkScheduler>,
+
+    /// If set, the resolver should not look up service configs, e.g. from DNS
+    /// TXT records.  The channel uses its default service config instead.
+    pub disable_service_config_lookup: bool,
+
+    /// Settings for the resolver set by the application with
+    /// [`ChannelOptions::resolver_args`](crate::client::ChannelOptions::resolver_args).
+    /// Resolvers define the keys they read.
+    pub args: Attributes,
 }
 
 /// Used to asynchronously request a call into the Resolver's work method.
 pub trait WorkScheduler: Send + Sync {
-    // Schedules a call into the Resolver's work method.  If there is already a
-    // pending work call that has not yet started, this may not schedule another
-    // call.
+    /// Schedules a call into the Resolver's work method.  If there is already
+    /// a pending work call that has not yet started, this may not schedule
+    /// another call.
     fn schedule_work(&self);
 }
 
 /// Resolver watches for the updates on the specified target.
 /// Updates include address updates and service config updates.
-// This trait may not need the Sync sub-trait if the channel implementation can
-// ensure that the resolver is accessed serially. The sub-trait can be removed
-// in that case.
-pub trait Resolver: Send + Sync {
+///
+/// The channel calls a resolver serially, from its work queue, and none of its
+/// methods may block.  Resolvers which need to do async work, e.g. DNS
+/// lookups or watching a control plane, spawn tasks on the runtime of their
+/// [`ResolverOptions`] and own them, cancelling them when dropped.  A task
+/// with a result to report stores it and calls
+/// [`WorkScheduler::schedule_work`]; the channel then calls
+/// [`work`](Resolver::work) with a [`ChannelController`], through which the
+/// resolver pushes the update and parses service configs.
+pub trait Resolver: Send {
     /// Asks the resolver to obtain an updated resolver result, if applicable.
+    /// This is a hint: it must not block, and any resulting update is
+    /// delivered from a later call to [`work`](Resolver::work).
     ///
     /// This is useful for polling resolvers to decide when to re-resolve.
     /// However, the implementation is not required to re-resolve immediately
@@ -239,7 +277,7 @@ pub struct ResolverUpdate {
 
     /// A list of endpoints which each identify a logical host serving the
     /// service indicated by the target URI.
-    pub endpoints: Result<Vec<Endpoint>, String>,
+    pub endpoints: Result<Vec<Endpoint>, ResolveError>,
 
     /// The service config which the client should use for communicating with
     /// the service. If it is None, it indicates no service config is present or
@@ -266,6 +304,66 @@ impl Default for ResolverUpdate {
     }
 }
 
+impl ResolverUpdate {
+    /// Returns a builder for a ResolverUpdate.  By default the update contains
+    /// no endpoints and no service config.
+    pub fn builder() -> ResolverUpdateBuilder {
+        ResolverUpdateBuilder::default()
+    }
+}
+
+/// A builder for [`ResolverUpdate`].
+#[derive(Debug, Default)]
+pub struct ResolverUpdateBuilder {
+    update: ResolverUpdate,
+}
+
+impl ResolverUpdateBuilder {
+    /// Appends an endpoint to the update.  Has no effect if the endpoints
+    /// were previously set to an error.
+    pub fn endpoint(mut self, endpoint: Endpoint) -> Self {
+        if let Ok(endpoints) = &mut self.update.endpoints {
+            endpoints.push(endpoint);
+        }
+        self
+    }
+
+    /// Sets the endpoints of the update, replacing any previously added.
+    pub fn endpoints(mut self, endpoints: impl IntoIterator<Item = Endpoint>) -> Self {
+        self.update.endpoints = Ok(endpoints.into_iter().collect());
+        self
+    }
+
+    /// Reports an error producing the endpoints instead of a list.
+    pub fn endpoints_error(mut self, err: impl Into<ResolveError>) -> Self {
+        self.update.endpoints = Err(err.into());
+        self
+    }
+
+    /// Sets the service config of the update.
+    pub fn service_config(mut self, config: Result<Option<ServiceConfig>, String>) -> Self {
+        self.update.service_config = config;
+        self
+    }
+
+    /// Sets an attribute of the update.
+    pub fn attr<T: Send + Sync + 'static>(mut self, key: &AttributeKey<T>, value: T) -> Self {
+        self.update.attributes = self.update.attributes.add(key, value);
+        self
+    }
+
+    /// Sets the resolution note of the update.
+    pub fn resolution_note(mut self, note: impl Into<String>) -> Self {
+        self.update.resolution_note = Some(note.into());
+        self
+    }
+
+    /// Returns the built update.
+    pub fn build(self) -> ResolverUpdate {
+        self.update
+    }
+}
+
 /// An Endpoint is an address or a collection of addresses which reference one
 /// logical server.  Multiple addresses may be used if there are multiple ways
 /// which the server can be reached, e.g. via IPv4 and IPv6 addresses.
@@ -286,9 +384,79 @@ impl Hash for Endpoint {
     }
 }
 
+impl Endpoint {
+    /// Returns a builder for an Endpoint.
+    pub fn builder() -> EndpointBuilder {
+        EndpointBuilder::default()
+    }
+
+    /// Returns one single-address endpoint per TCP socket address, in order.
+    pub fn from_tcp_addrs(addrs: impl IntoIterator<Item = SocketAddr>) -> Vec<Endpoint> {
+        addrs
+            .into_iter()
+            .map(|addr| Endpoint {
+                addresses: vec![Address::tcp(addr)],
+                attributes: Attributes::default(),
+            })
+            .collect()
+    }
+}
+
+/// A builder for [`Endpoint`].
+#[derive(Debug, Default)]
+pub struct EndpointBuilder {
+    addresses: Vec<Address>,
+    attributes: Attributes,
+}
+
+impl EndpointBuilder {
+    /// Appends an address to the endpoint.
+    pub fn address(mut self, address: Address) -> Self {
+        self.addresses.push(address);
+        self
+    }
+
+    /// Appends several addresses to the endpoint.
+    pub fn addresses(mut self, addresses: impl IntoIterator<Item = Address>) -> Self {
+        self.addresses.extend(addresses);
+        self
+    }
+
+    /// Sets an attribute of the endpoint.
+    pub fn attr<T: Send + Sync + 'static>(mut self, key: &AttributeKey<T>, value: T) -> Self {
+        self.attributes = self.attributes.add(key, value);
+        self
+    }
+
+    /// Validates and returns the endpoint.  An endpoint must contain at least
+    /// one address, every address must have a network type and a non-empty
+    /// address, and no address may appear more than once.
+    pub fn build(self) -> Result<Endpoint, String> {
+        if self.addresses.is_empty() {
+            return Err("endpoint has no addresses".to_string());
+        }
+        let mut seen = HashSet::new();
+        for addr in &self.addresses {
+            if addr.network_type.is_empty() {
+                return Err(format!("address {:?} has no network type", &*addr.address));
+            }
+            if addr.address.is_empty() {
+                return Err(format!("empty {} address", addr.network_type));
+            }
+            if !seen.insert(addr) {
+                return Err(format!("duplicate address {addr}"));
+            }
+        }
+        Ok(Endpoint {
+            addresses: self.addresses,
+            attributes: self.attributes,
+        })
+    }
+}
+
 /// An Address is an identifier that indicates how to connect to a server.
 #[non_exhaustive]
-#[derive(Debug, Clone, Default, Ord, PartialOrd)]
+#[derive(Debug, Clone, Default)]
 pub struct Address {
     /// The network type is used to identify what kind of transport to create
     /// when connecting to this address.  Typically TCP_IP_ADDRESS_TYPE.
@@ -303,6 +471,30 @@ pub struct Address {
     pub attributes: Attributes,
 }
 
+impl Address {
+    /// Creates an address of the provided network type.
+    pub fn new(network_type: &'static str, address: impl Into<ByteStr>) -> Self {
+        Self {
+            network_type,
+            address: address.into(),
+            attributes: Attributes::default(),
+        }
+    }
+
+    /// Creates a TCP/IP address for the provided socket address.
+    pub fn tcp(addr: SocketAddr) -> Self {
+        Self::new(TCP_IP_NETWORK_TYPE, addr.to_string())
+    }
+
+    /// Sets an attribute of the address.
+    pub fn with_attr<T: Send + Sync + 'static>(self, key: &AttributeKey<T>, value: T) -> Self {
+        Self {
+            attributes: self.attributes.add(key, value),
+            ..self
+        }
+    }
+}
+
 impl Eq for Address {}
 
 impl PartialEq for Address {
@@ -311,6 +503,20 @@ impl PartialEq for Address {
     }
 }
 
+// Like equality, ordering ignores the attributes, which may only be compared
+// by identity.
+impl PartialOrd for Address {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Address {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.network_type, &self.address).cmp(&(other.network_type, &other.address))
+    }
+}
+
 impl Hash for Address {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         self.network_type.hash(state);
@@ -346,7 +552,8 @@ impl Resolver for NopResolver {
 
 #[cfg(test)]
 mod test {
-    use super::Target;
+    use super::{Address, Endpoint, ResolverUpdate, Target, TCP_IP_NETWORK_TYPE};
+    use crate::attributes::AttributeKey;
 
     #[test]
     pub fn parse_target() {
@@ -409,4 +616,62 @@ mod test {
             assert_eq!(&target.to_string(), tc.want_str);
         }
     }
+
+    #[test]
+    fn endpoint_builder() {
+        const WEIGHT: AttributeKey<u32> = AttributeKey::new("test.weight");
+        let addr = "127.0.0.1:8080".parse().unwrap();
+        let ep = Endpoint::builder()
+            .address(Address::tcp(addr))
+            .address(Address::new("test", "foo"))
+            .attr(&WEIGHT, 7)
+            .build()
+            .unwrap();
+        assert_eq!(ep.addresses[0].network_type, TCP_IP_NETWORK_TYPE);
+        assert_eq!(&*ep.addresses[0].address, "127.0.0.1:8080");
+        assert_eq!(ep.attributes.get(&WEIGHT), Some(&7));
+
+        assert!(Endpoint::builder().build().is_err());
+        assert!(Endpoint::builder()
+            .address(Address::new(TCP_IP_NETWORK_TYPE, ""))
+            .build()
+            .is_err());
+        assert!(Endpoint::builder()
+            .address(Address::tcp(addr))
+            .address(Address::tcp(addr))
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn address_order_ignores_attributes() {
+        const NAME: AttributeKey<String> = AttributeKey::new("test.name");
+        let a = Address::new("test", "a");
+        let b = Address::new("test", "b");
+        assert!(a < b);
+        assert!(Address::new("other", "b") < a);
+        // Attributes holding equal values are distinct, but do not affect the
+        // order.
+        let a1 = a.clone().with_attr(&NAME, "x".to_string());
+        let a2 = a.clone().with_attr(&NAME, "x".to_string());
+        assert_eq!(a1.cmp(&a2), std::cmp::Ordering::Equal);
+        assert_eq!(a1.cmp(&a), std::cmp::Ordering::Equal);
+        assert!(a1 < b);
+    }
+
+    #[test]
+    fn resolver_update_builder() {
+        let addrs = ["[::1]:1".parse().unwrap(), "10.0.0.1:2".parse().unwrap()];
+        let update = ResolverUpdate::builder()
+            .endpoints(Endpoint::from_tcp_addrs(addrs))
+            .resolution_note("note")
+            .build();
+        let endpoints = update.endpoints.unwrap();
+        assert_eq!(endpoints.len(), 2);
+        assert_eq!(&*endpoints[1].addresses[0].address, "10.0.0.1:2");
+        assert_eq!(update.resolution_note.as_deref(), Some("note"));
+
+        let update = ResolverUpdate::builder().endpoints_error("boom").build();
+        assert_eq!(update.endpoints.unwrap_err().to_string(), "boom");
+    }
 }
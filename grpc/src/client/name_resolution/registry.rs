@@ -33,14 +33,14 @@ static GLOBAL_RESOLVER_REGISTRY: OnceLock<ResolverRegistry> = OnceLock::new();
 
 /// A registry to store and retrieve name resolvers.  Resolvers are indexed by
 /// the URI scheme they are intended to handle.
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct ResolverRegistry {
     inner: Arc<Mutex<HashMap<String, Arc<dyn ResolverBuilder>>>>,
 }
 
 impl ResolverRegistry {
     /// Construct an empty name resolver registry.
-    fn new() -> Self {
+    pub fn new() -> Self {
         Self {
             inner: Arc::default(),
         }
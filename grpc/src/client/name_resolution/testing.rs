@@ -0,0 +1,334 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! Utilities for testing name resolvers without a channel.
+//!
+//! [`ResolverTester`] builds a resolver with a [`TestWorkScheduler`] and calls
+//! its `work` method with a [`TestChannelController`] whenever work is
+//! scheduled, so tests can step through the resolver's updates one at a time.
+//! [`StaticResolverBuilder`] goes the other way, and provides channels under
+//! test with resolvers which push fixed updates.
+
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use tokio::sync::mpsc;
+
+use crate::{
+    attributes::Attributes,
+    client::service_config::ServiceConfig,
+    rt::{tokio::TokioRuntime, Runtime},
+};
+
+use super::{
+    ChannelController, Endpoint, Resolver, ResolverBuilder, ResolverOptions, ResolverUpdate,
+    Target, WorkScheduler,
+};
+
+/// A work scheduler which notifies a channel when work is scheduled.
+pub(crate) struct TestWorkScheduler {
+    pub(crate) tx_work: mpsc::UnboundedSender<()>,
+}
+
+impl WorkScheduler for TestWorkScheduler {
+    fn schedule_work(&self) {
+        // The receiver is gone once the test is done with the resolver.
+        let _ = self.tx_work.send(());
+    }
+}
+
+/// A channel controller which records the updates pushed by a resolver and
+/// the service configs it asks to be parsed.
+pub(crate) struct TestChannelController {
+    /// The result returned to the resolver from update.  An error causes the
+    /// resolver to re-resolve with backoff.
+    pub(crate) update_result: Result<(), String>,
+    pub(crate) updates: VecDeque<ResolverUpdate>,
+    service_configs: Mutex<Vec<String>>,
+}
+
+impl TestChannelController {
+    pub(crate) fn new() -> Self {
+        Self {
+            update_result: Ok(()),
+            updates: VecDeque::new(),
+            service_configs: Mutex::default(),
+        }
+    }
+
+    /// Returns the service configs the resolver asked to be parsed, in order.
+    pub(crate) fn service_configs(&self) -> Vec<String> {
+        self.service_configs.lock().unwrap().clone()
+    }
+}
+
+impl ChannelController for TestChannelController {
+    fn update(&mut self, update: ResolverUpdate) -> Result<(), String> {
+        println!("Received resolver update: {:?}", &update);
+        self.updates.push_back(update);
+        self.update_result.clone()
+    }
+
+    fn parse_service_config(&self, config: &str) -> Result<ServiceConfig, String> {
+        self.service_configs
+            .lock()
+            .unwrap()
+            .push(config.to_string());
+        ServiceConfig::parse(config)
+    }
+}
+
+/// Drives a resolver the way a channel would: work is performed only when the
+/// resolver schedules it.
+pub(crate) struct ResolverTester {
+    resolver: Box<dyn Resolver>,
+    rx_work: mpsc::UnboundedReceiver<()>,
+    pub(crate) controller: TestChannelController,
+}
+
+impl ResolverTester {
+    /// Builds a resolver for target, which must use the builder's scheme.
+    pub(crate) fn new(builder: &dyn ResolverBuilder, target: &str) -> Self {
+        Self::with_runtime(builder, target, Arc::new(TokioRuntime {}), |_| {})
+    }
+
+    /// Like new, except that the resolver uses runtime, and configure may
+    /// modify the options passed to the builder.
+    pub(crate) fn with_runtime(
+        builder: &dyn ResolverBuilder,
+        target: &str,
+        runtime: Arc<dyn Runtime>,
+        configure: impl FnOnce(&mut ResolverOptions),
+    ) -> Self {
+        let target: Target = target.parse().unwrap();
+        assert_eq!(
+            target.scheme(),
+            builder.scheme(),
+            "target {target} does not use the scheme of the resolver under test"
+        );
+        Self::from_fn(runtime, |mut options| {
+            options.authority = builder.default_authority(&target);
+            configure(&mut options);
+            builder.build(&target, options)
+        })
+    }
+
+    /// Creates a resolver with build, for resolvers not constructed through a
+    /// builder.
+    pub(crate) fn from_fn(
+        runtime: Arc<dyn Runtime>,
+        build: impl FnOnce(ResolverOptions) -> Box<dyn Resolver>,
+    ) -> Self {
+        let (tx_work, rx_work) = mpsc::unbounded_channel();
+        let options = ResolverOptions {
+            authority: "test.authority".to_string(),
+            runtime,
+            work_scheduler: Arc::new(TestWorkScheduler { tx_work }),
+            disable_service_config_lookup: false,
+            args: Attributes::default(),
+        };
+        Self {
+            resolver: build(options),
+            rx_work,
+            controller: TestChannelController::new(),
+        }
+    }
+
+    /// Waits for the resolver to schedule work, then performs it.
+    pub(crate) async fn work(&mut self) {
+        self.rx_work.recv().await.unwrap();
+        self.resolver.work(&mut self.controller);
+    }
+
+    /// Returns the next update pushed by the resolver, performing scheduled
+    /// work until there is one.
+    pub(crate) async fn next_update(&mut self) -> ResolverUpdate {
+        loop {
+            if let Some(update) = self.controller.updates.pop_front() {
+                return update;
+            }
+            self.work().await;
+        }
+    }
+
+    /// Asks the resolver to re-resolve.
+    pub(crate) fn resolve_now(&mut self) {
+        self.resolver.resolve_now();
+    }
+
+    /// Panics if the resolver schedules work within timeout.
+    pub(crate) async fn expect_no_work(&mut self, timeout: Duration) {
+        if let Ok(work) = tokio::time::timeout(timeout, self.rx_work.recv()).await {
+            assert!(work.is_none(), "resolver scheduled unexpected work");
+        }
+    }
+}
+
+/// A resolver builder whose resolvers push one update per service config,
+/// each with the same endpoints, when built.  Clones share the record of the
+/// resolvers built and of the re-resolution requests they received.
+#[derive(Clone)]
+pub(crate) struct StaticResolverBuilder {
+    scheme: &'static str,
+    endpoints: Vec<Endpoint>,
+    service_configs: Vec<Option<&'static str>>,
+    reresolve: bool,
+    first_only: bool,
+    record: Arc<StaticResolverRecord>,
+}
+
+#[derive(Default)]
+struct StaticResolverRecord {
+    // The authority and args of the options of each resolver built.
+    options: Mutex<Vec<(String, Attributes)>>,
+    resolve_now_calls: AtomicUsize,
+}
+
+impl StaticResolverBuilder {
+    /// Creates a builder for scheme whose resolvers push a single update with
+    /// endpoints and no service config.
+    pub(crate) fn new(scheme: &'static str, endpoints: impl IntoIterator<Item = Endpoint>) -> Self {
+        Self {
+            scheme,
+            endpoints: endpoints.into_iter().collect(),
+            service_configs: vec![None],
+            reresolve: false,
+            first_only: false,
+            record: Arc::default(),
+        }
+    }
+
+    /// Sets the service configs of the updates, which are parsed by the
+    /// channel.  Resolvers push no updates at all if there are none.
+    pub(crate) fn service_configs(
+        mut self,
+        configs: impl IntoIterator<Item = Option<&'static str>>,
+    ) -> Self {
+        self.service_configs = configs.into_iter().collect();
+        self
+    }
+
+    /// Makes resolvers push their updates again whenever re-resolution is
+    /// requested.
+    pub(crate) fn reresolve(mut self) -> Self {
+        self.reresolve = true;
+        self
+    }
+
+    /// Makes only the first resolver built push updates.
+    pub(crate) fn first_only(mut self) -> Self {
+        self.first_only = true;
+        self
+    }
+
+    /// Returns the authority and args of the options each resolver was built
+    /// with, in order.
+    pub(crate) fn built_with(&self) -> Vec<(String, Attributes)> {
+        self.record.options.lock().unwrap().clone()
+    }
+
+    /// Returns the number of re-resolution requests received by all resolvers.
+    pub(crate) fn resolve_now_calls(&self) -> usize {
+        self.record.resolve_now_calls.load(Ordering::SeqCst)
+    }
+}
+
+impl ResolverBuilder for StaticResolverBuilder {
+    fn build(&self, _: &Target, options: ResolverOptions) -> Box<dyn Resolver> {
+        let mut built = self.record.options.lock().unwrap();
+        built.push((options.authority.clone(), options.args.clone()));
+        let service_configs = if self.first_only && built.len() > 1 {
+            Vec::new()
+        } else {
+            self.service_configs.clone()
+        };
+        options.work_scheduler.schedule_work();
+        Box::new(StaticResolver {
+            endpoints: self.endpoints.clone(),
+            service_configs,
+            reresolve: self.reresolve,
+            record: self.record.clone(),
+            work_scheduler: options.work_scheduler,
+        })
+    }
+
+    fn scheme(&self) -> &str {
+        self.scheme
+    }
+
+    fn is_valid_uri(&self, _: &Target) -> bool {
+        true
+    }
+}
+
+struct StaticResolver {
+    endpoints: Vec<Endpoint>,
+    service_configs: Vec<Option<&'static str>>,
+    reresolve: bool,
+    record: Arc<StaticResolverRecord>,
+    work_scheduler: Arc<dyn WorkScheduler>,
+}
+
+impl Resolver for StaticResolver {
+    fn resolve_now(&mut self) {
+        self.record.resolve_now_calls.fetch_add(1, Ordering::SeqCst);
+        if self.reresolve {
+            self.work_scheduler.schedule_work();
+        }
+    }
+
+    fn work(&mut self, channel_controller: &mut dyn ChannelController) {
+        for config in &self.service_configs {
+            let config = config
+                .map(|config| channel_controller.parse_service_config(config))
+                .transpose();
+            let _ = channel_controller.update(
+                ResolverUpdate::builder()
+                    .endpoints(self.endpoints.clone())
+                    .service_config(config)
+                    .build(),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ResolverTester;
+    use crate::client::name_resolution::{dns, global_registry};
+
+    #[test]
+    #[should_panic(expected = "does not use the scheme")]
+    fn targets_must_match_the_scheme() {
+        dns::reg();
+        let builder = global_registry().get("dns").unwrap();
+        ResolverTester::new(builder.as_ref(), "passthrough:///localhost:1234");
+    }
+}
@@ -0,0 +1,350 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! Per-call priority classes.
+//!
+//! An application marks a call's [`Priority`] by inserting it into the
+//! request's extensions; calls without one have [`Priority::Default`].  When
+//! the channel limits the number of concurrent calls (see
+//! `ChannelOptions::call_limits`), lower priority calls are admitted only
+//! while enough capacity remains for higher priority calls, queued calls are
+//! admitted in priority order, and background calls are shed instead of
+//! queued.  This lets mixed workloads protect interactive traffic sharing a
+//! channel with batch traffic.
+//!
+//! TODO: order RPCs waiting for a picker by priority, and shed background
+//! calls first in retry throttling, once those exist.
+
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use tokio::sync::oneshot;
+use tokio_stream::Stream;
+use tonic::Status;
+
+use crate::service::{Message, Request, Response};
+
+/// The priority class of a call.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Batch or prefetch work which should yield to all other calls.
+    Background,
+    /// The priority of calls which do not specify one.
+    #[default]
+    Default,
+    /// Interactive or otherwise latency-critical calls.
+    Critical,
+}
+
+impl Priority {
+    const ALL: [Priority; 3] = [Priority::Critical, Priority::Default, Priority::Background];
+
+    /// Returns the priority of request.
+    pub fn of(request: &Request) -> Self {
+        request
+            .extensions()
+            .get::<Self>()
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Limits on the number of concurrent calls on a channel.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct CallLimits {
+    /// The maximum number of concurrent calls.
+    pub max_concurrent_calls: usize,
+    /// The percentage of max_concurrent_calls usable by Default calls; the
+    /// remainder is reserved for Critical calls.
+    pub default_percent: u8,
+    /// The percentage of max_concurrent_calls usable by Background calls.
+    /// Background calls are shed rather than queued when it is exhausted.
+    pub background_percent: u8,
+}
+
+impl CallLimits {
+    pub fn new(max_concurrent_calls: usize) -> Self {
+        Self {
+            max_concurrent_calls,
+            default_percent: 90,
+            background_percent: 50,
+        }
+    }
+
+    pub fn default_percent(self, percent: u8) -> Self {
+        Self {
+            default_percent: percent.min(100),
+            ..self
+        }
+    }
+
+    pub fn background_percent(self, percent: u8) -> Self {
+        Self {
+            background_percent: percent.min(100),
+            ..self
+        }
+    }
+
+    // Returns the number of concurrent calls usable by calls of priority.
+    fn capacity(&self, priority: Priority) -> usize {
+        let percent = match priority {
+            Priority::Critical => 100,
+            Priority::Default => self.default_percent,
+            Priority::Background => self.background_percent,
+        };
+        (self.max_concurrent_calls * percent as usize).div_ceil(100)
+    }
+}
+
+/// Counts of calls by outcome for one priority class.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PriorityStats {
+    /// Calls which were admitted and started.
+    pub started: u64,
+    /// Calls which had to wait for capacity before being admitted.
+    pub queued: u64,
+    /// Calls which were rejected because capacity was exhausted.
+    pub shed: u64,
+}
+
+/// Call statistics of a channel labeled by priority class.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CallStats {
+    by_priority: [PriorityStats; 3],
+}
+
+impl CallStats {
+    /// Returns the statistics of calls with the given priority.
+    pub fn get(&self, priority: Priority) -> PriorityStats {
+        self.by_priority[priority.index()]
+    }
+}
+
+#[derive(Default)]
+struct State {
+    in_flight: usize,
+    waiters: [VecDeque<oneshot::Sender<Permit>>; 3],
+    stats: CallStats,
+}
+
+/// Admits calls according to their priority and the channel's CallLimits.
+pub(crate) struct PriorityLimiter {
+    limits: Option<CallLimits>,
+    state: Arc<Mutex<State>>,
+}
+
+/// Represents an admitted call.  Releases its capacity when dropped.
+pub(crate) struct Permit {
+    limited: bool,
+    state: Arc<Mutex<State>>,
+}
+
+impl PriorityLimiter {
+    /// Creates a limiter.  If limits is None, all calls are admitted
+    /// immediately and only statistics are recorded.
+    pub(crate) fn new(limits: Option<CallLimits>) -> Self {
+        Self {
+            limits,
+            state: Arc::default(),
+        }
+    }
+
+    /// Waits until a call of the given priority may start.  Fails immediately
+    /// if the call is shed.
+    pub(crate) async fn acquire(&self, priority: Priority) -> Result<Permit, Status> {
+        let rx = {
+            let mut state = self.state.lock().unwrap();
+            let Some(limits) = &self.limits else {
+                state.stats.by_priority[priority.index()].started += 1;
+                return Ok(self.permit(false));
+            };
+            if state.in_flight < limits.capacity(priority) {
+                state.in_flight += 1;
+                state.stats.by_priority[priority.index()].started += 1;
+                return Ok(self.permit(true));
+            }
+            if priority == Priority::Background {
+                state.stats.by_priority[priority.index()].shed += 1;
+                return Err(Status::resource_exhausted(
+                    "channel is at capacity for background calls",
+                ));
+            }
+            state.stats.by_priority[priority.index()].queued += 1;
+            let (tx, rx) = oneshot::channel();
+            state.waiters[priority.index()].push_back(tx);
+            rx
+        };
+        // The capacity of a released permit is handed to this call.  If this
+        // future is dropped first, the permit is dropped with the channel and
+        // the capacity is released again.
+        let permit = rx
+            .await
+            .map_err(|_| Status::cancelled("channel is shutting down"))?;
+        self.state.lock().unwrap().stats.by_priority[priority.index()].started += 1;
+        Ok(permit)
+    }
+
+    /// Returns the call statistics recorded so far.
+    pub(crate) fn stats(&self) -> CallStats {
+        self.state.lock().unwrap().stats.clone()
+    }
+
+    fn permit(&self, limited: bool) -> Permit {
+        Permit {
+            limited,
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        if !self.limited {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        // Hand the capacity to the highest priority waiter, if any.  Handing
+        // over a released slot never increases the number of calls in flight.
+        for priority in Priority::ALL {
+            while let Some(tx) = state.waiters[priority.index()].pop_front() {
+                let permit = Permit {
+                    limited: true,
+                    state: self.state.clone(),
+                };
+                match tx.send(permit) {
+                    Ok(()) => return,
+                    // The waiter is gone; don't release the capacity twice.
+                    Err(mut permit) => permit.limited = false,
+                }
+            }
+        }
+        state.in_flight -= 1;
+    }
+}
+
+pin_project_lite::pin_project! {
+    // Holds a permit until the response stream completes or is dropped.
+    struct PermitStream<S> {
+        #[pin]
+        inner: S,
+        permit: Option<Permit>,
+    }
+}
+
+impl<S: Stream> Stream for PermitStream<S> {
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let item = this.inner.poll_next(cx);
+        if let Poll::Ready(None) = item {
+            this.permit.take();
+        }
+        item
+    }
+}
+
+/// Ties the lifetime of permit to the message stream of response.
+pub(crate) fn hold_until_complete(response: Response, permit: Permit) -> Response {
+    response.map(|inner| {
+        Box::pin(PermitStream {
+            inner,
+            permit: Some(permit),
+        }) as Pin<Box<dyn Stream<Item = Result<Box<dyn Message>, Status>> + Send>>
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use tonic::Code;
+
+    use super::{CallLimits, Priority, PriorityLimiter};
+    use crate::client::load_balancing::test_utils::new_request;
+
+    #[test]
+    fn priority_from_request() {
+        let mut request = new_request();
+        assert_eq!(Priority::of(&request), Priority::Default);
+        request.extensions_mut().insert(Priority::Critical);
+        assert_eq!(Priority::of(&request), Priority::Critical);
+    }
+
+    #[tokio::test]
+    async fn background_calls_are_shed() {
+        let limiter = PriorityLimiter::new(Some(CallLimits::new(4)));
+        let _a = limiter.acquire(Priority::Background).await.unwrap();
+        let _b = limiter.acquire(Priority::Background).await.unwrap();
+        let err = limiter.acquire(Priority::Background).await.err().unwrap();
+        assert_eq!(err.code(), Code::ResourceExhausted);
+        // Capacity remains for other classes.
+        let _c = limiter.acquire(Priority::Default).await.unwrap();
+        let _d = limiter.acquire(Priority::Critical).await.unwrap();
+
+        let stats = limiter.stats();
+        assert_eq!(stats.get(Priority::Background).started, 2);
+        assert_eq!(stats.get(Priority::Background).shed, 1);
+        assert_eq!(stats.get(Priority::Default).started, 1);
+        assert_eq!(stats.get(Priority::Critical).started, 1);
+    }
+
+    #[tokio::test]
+    async fn queued_calls_admitted_by_priority() {
+        let limiter = PriorityLimiter::new(Some(CallLimits::new(2).default_percent(50)));
+        let held = limiter.acquire(Priority::Default).await.unwrap();
+        let critical = limiter.acquire(Priority::Critical).await.unwrap();
+
+        // Both classes are at capacity, so these calls queue.
+        let default_waiter = limiter.acquire(Priority::Default);
+        let critical_waiter = limiter.acquire(Priority::Critical);
+        tokio::pin!(default_waiter, critical_waiter);
+        tokio::select! {
+            _ = &mut default_waiter => panic!("default call admitted"),
+            _ = &mut critical_waiter => panic!("critical call admitted"),
+            _ = tokio::time::sleep(Duration::from_millis(10)) => {}
+        }
+
+        // Releasing capacity admits the critical call first.
+        drop(held);
+        let _c = critical_waiter.await.unwrap();
+        drop(critical);
+        let _d = default_waiter.await.unwrap();
+
+        let stats = limiter.stats();
+        assert_eq!(stats.get(Priority::Default).queued, 1);
+        assert_eq!(stats.get(Priority::Critical).queued, 1);
+        assert_eq!(stats.get(Priority::Critical).started, 2);
+    }
+}
@@ -0,0 +1,219 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! Configuration of the request hash used by hash-based LB policies such as
+//! ring_hash.
+//!
+//! The channel evaluates a [`RequestHashPolicy`] for every RPC and stores the
+//! result in the request's extensions as a [`RequestHash`], where pickers can
+//! read it.  The policy is evaluated following the semantics of xDS route
+//! hash policies: each policy in the list is evaluated in order, their hashes
+//! are combined, and evaluation stops after a terminal policy produces a hash.
+
+use crate::service::Request;
+use crate::xxhash::xxh64;
+
+/// The hash computed for a request, used by hash-based LB policies to select
+/// an endpoint.
+///
+/// Applications may also insert this directly into a request's extensions to
+/// choose the hash for that request explicitly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RequestHash(pub u64);
+
+impl RequestHash {
+    /// Returns the hash attached to request, if any.
+    pub fn from_request(request: &Request) -> Option<Self> {
+        request.extensions().get::<Self>().copied()
+    }
+}
+
+/// What part of a request a [`HashPolicy`] hashes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HashSource {
+    /// Hashes the values of the named request metadata entry with XXH64.
+    /// Multiple values are joined with ",".  Produces no hash if the entry is
+    /// absent.
+    Header(String),
+    /// Uses an identifier which is random per channel as the hash, so that all
+    /// requests on a channel share the same affinity.
+    ChannelId,
+}
+
+/// A single element of a [`RequestHashPolicy`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HashPolicy {
+    pub source: HashSource,
+    /// If set and this policy produces a hash, the remaining policies are not
+    /// evaluated.
+    pub terminal: bool,
+}
+
+/// An ordered list of hash policies.
+///
+/// May be set on the channel via `ChannelOptions::request_hash_policy`, or on
+/// a single call by inserting it into the request's extensions, in which
+/// case it overrides the channel's policy.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RequestHashPolicy {
+    pub policies: Vec<HashPolicy>,
+}
+
+impl RequestHashPolicy {
+    /// Appends a policy hashing the named metadata entry.
+    pub fn header(mut self, key: impl Into<String>, terminal: bool) -> Self {
+        self.policies.push(HashPolicy {
+            source: HashSource::Header(key.into()),
+            terminal,
+        });
+        self
+    }
+
+    /// Appends a policy using the channel's identifier as the hash.
+    pub fn channel_id(mut self, terminal: bool) -> Self {
+        self.policies.push(HashPolicy {
+            source: HashSource::ChannelId,
+            terminal,
+        });
+        self
+    }
+
+    /// Computes the hash for request, or None if no policy produced a hash, in
+    /// which case LB policies pick a random hash.
+    pub(crate) fn compute(&self, request: &Request, channel_id: u64) -> Option<RequestHash> {
+        let mut result: Option<u64> = None;
+        for policy in &self.policies {
+            let hash = match &policy.source {
+                HashSource::Header(key) => {
+                    let values: Vec<_> = request
+                        .metadata()
+                        .get_all(key.as_str())
+                        .iter()
+                        .filter_map(|v| v.to_str().ok())
+                        .collect();
+                    if values.is_empty() {
+                        None
+                    } else {
+                        Some(xxh64(values.join(",").as_bytes(), 0))
+                    }
+                }
+                HashSource::ChannelId => Some(channel_id),
+            };
+            let Some(hash) = hash else {
+                continue;
+            };
+            // Combine hashes the same way as other gRPC implementations.
+            result = Some(match result {
+                Some(prev) => prev.rotate_left(1) ^ hash,
+                None => hash,
+            });
+            if policy.terminal {
+                break;
+            }
+        }
+        result.map(RequestHash)
+    }
+
+    /// Inserts a RequestHash into request's extensions, unless the request
+    /// already carries one.  A policy in the request's extensions takes
+    /// precedence over self.
+    pub(crate) fn apply(channel_policy: Option<&Self>, request: &mut Request, channel_id: u64) {
+        if request.extensions().get::<RequestHash>().is_some() {
+            return;
+        }
+        let hash = match request.extensions().get::<Self>() {
+            Some(policy) => policy.compute(request, channel_id),
+            None => channel_policy.and_then(|p| p.compute(request, channel_id)),
+        };
+        if let Some(hash) = hash {
+            request.extensions_mut().insert(hash);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{RequestHash, RequestHashPolicy};
+    use crate::client::load_balancing::test_utils::new_request;
+    use crate::xxhash::xxh64;
+
+    #[test]
+    fn header_policy() {
+        let policy = RequestHashPolicy::default().header("session", true);
+        let mut request = new_request();
+        assert_eq!(policy.compute(&request, 1), None);
+
+        request
+            .metadata_mut()
+            .insert("session", "abc".parse().unwrap());
+        assert_eq!(
+            policy.compute(&request, 1),
+            Some(RequestHash(xxh64(b"abc", 0)))
+        );
+    }
+
+    #[test]
+    fn terminal_and_fallthrough() {
+        // The missing header is skipped and the channel ID is used.
+        let policy = RequestHashPolicy::default()
+            .header("missing", true)
+            .channel_id(true)
+            .header("session", false);
+        let mut request = new_request();
+        request
+            .metadata_mut()
+            .insert("session", "abc".parse().unwrap());
+        assert_eq!(policy.compute(&request, 7), Some(RequestHash(7)));
+
+        // Non-terminal policies combine their hashes.
+        let policy = RequestHashPolicy::default()
+            .channel_id(false)
+            .header("session", false);
+        assert_eq!(
+            policy.compute(&request, 7),
+            Some(RequestHash(7u64.rotate_left(1) ^ xxh64(b"abc", 0)))
+        );
+    }
+
+    #[test]
+    fn call_overrides_channel() {
+        let channel_policy = RequestHashPolicy::default().channel_id(true);
+
+        let mut request = new_request();
+        request.extensions_mut().insert(RequestHash(42));
+        RequestHashPolicy::apply(Some(&channel_policy), &mut request, 7);
+        assert_eq!(RequestHash::from_request(&request), Some(RequestHash(42)));
+
+        let mut request = new_request();
+        request
+            .extensions_mut()
+            .insert(RequestHashPolicy::default().header("missing", true));
+        RequestHashPolicy::apply(Some(&channel_policy), &mut request, 7);
+        assert_eq!(RequestHash::from_request(&request), None);
+
+        let mut request = new_request();
+        RequestHashPolicy::apply(Some(&channel_policy), &mut request, 7);
+        assert_eq!(RequestHash::from_request(&request), Some(RequestHash(7)));
+    }
+}
@@ -0,0 +1,159 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! Throttling of the re-resolution requests made by LB policies.
+//!
+//! LB policies request re-resolution whenever a connection is lost or fails.
+//! With flapping backends that can happen many times per second, so the
+//! channel passes at most one request to the resolver per minimum interval,
+//! and backs off further while the resolver's updates are being rejected.
+
+use std::time::{Duration, Instant};
+
+use super::name_resolution::backoff::{BackoffConfig, ExponentialBackoff};
+
+/// What to do with a request for re-resolution.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Throttled {
+    /// Re-resolve immediately.
+    Now,
+    /// Re-resolve after the delay; call fire() when it elapses.
+    After(Duration),
+    /// A delayed re-resolution is already pending and covers this request.
+    Pending,
+}
+
+pub(crate) struct ResolutionThrottle {
+    min_interval: Duration,
+    backoff: ExponentialBackoff,
+    // The earliest time at which the next re-resolution may happen.
+    next_allowed: Option<Instant>,
+    pending: bool,
+}
+
+impl ResolutionThrottle {
+    pub(crate) fn new(min_interval: Duration, backoff_config: BackoffConfig) -> Self {
+        Self {
+            min_interval,
+            backoff: ExponentialBackoff::new(backoff_config).expect("backoff config must be valid"),
+            next_allowed: None,
+            pending: false,
+        }
+    }
+
+    /// Called when an LB policy requests re-resolution at now.
+    pub(crate) fn request(&mut self, now: Instant) -> Throttled {
+        if self.pending {
+            return Throttled::Pending;
+        }
+        match self.next_allowed {
+            Some(next) if next > now => {
+                self.pending = true;
+                Throttled::After(next - now)
+            }
+            _ => {
+                self.next_allowed = Some(now + self.min_interval);
+                Throttled::Now
+            }
+        }
+    }
+
+    /// Called when the delay returned by request() elapses, just before
+    /// re-resolving.
+    pub(crate) fn fire(&mut self, now: Instant) {
+        self.pending = false;
+        self.next_allowed = Some(now + self.min_interval);
+    }
+
+    /// Called with the channel's response to each resolver update.  Rejected
+    /// updates delay the next re-resolution by an exponential backoff.
+    pub(crate) fn update_result(&mut self, accepted: bool, now: Instant) {
+        if accepted {
+            self.backoff.reset();
+            return;
+        }
+        let retry_at = now + self.backoff.backoff_duration();
+        self.next_allowed = Some(self.next_allowed.map_or(retry_at, |t| t.max(retry_at)));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use crate::client::name_resolution::backoff::BackoffConfig;
+
+    use super::{ResolutionThrottle, Throttled};
+
+    const MIN_INTERVAL: Duration = Duration::from_secs(10);
+
+    fn throttle() -> ResolutionThrottle {
+        ResolutionThrottle::new(
+            MIN_INTERVAL,
+            BackoffConfig {
+                base_delay: Duration::from_secs(20),
+                multiplier: 2.0,
+                jitter: 0.0,
+                max_delay: Duration::from_secs(100),
+            },
+        )
+    }
+
+    #[test]
+    fn coalesces_requests_within_min_interval() {
+        let mut t = throttle();
+        let start = Instant::now();
+        assert_eq!(t.request(start), Throttled::Now);
+        let now = start + Duration::from_secs(4);
+        assert_eq!(t.request(now), Throttled::After(Duration::from_secs(6)));
+        assert_eq!(t.request(now), Throttled::Pending);
+
+        let now = start + MIN_INTERVAL;
+        t.fire(now);
+        assert_eq!(t.request(now), Throttled::After(MIN_INTERVAL));
+        t.fire(now + MIN_INTERVAL);
+        assert_eq!(t.request(now + 2 * MIN_INTERVAL), Throttled::Now);
+    }
+
+    #[test]
+    fn backs_off_while_updates_are_rejected() {
+        let mut t = throttle();
+        let start = Instant::now();
+        assert_eq!(t.request(start), Throttled::Now);
+        t.update_result(false, start);
+        assert_eq!(t.request(start), Throttled::After(Duration::from_secs(20)));
+
+        let now = start + Duration::from_secs(20);
+        t.fire(now);
+        t.update_result(false, now);
+        assert_eq!(t.request(now), Throttled::After(Duration::from_secs(40)));
+
+        // An accepted update resets the backoff, but not the pending delay.
+        let now = now + Duration::from_secs(40);
+        t.fire(now);
+        t.update_result(true, now);
+        t.update_result(false, now);
+        assert_eq!(t.request(now), Throttled::After(Duration::from_secs(20)));
+    }
+}
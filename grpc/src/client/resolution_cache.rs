@@ -0,0 +1,152 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+//! A cache of resolver results shared by channels.
+//!
+//! Creating a channel normally waits for its name resolver to produce the
+//! channel's first update.  Channels sharing a [`ResolutionCache`] instead
+//! start from the most recent update accepted by any channel for the same
+//! target, as long as it is younger than the cache's TTL, while their own
+//! resolver runs.  This mostly benefits applications which create many
+//! short-lived channels to the same targets, e.g. tests.
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use super::name_resolution::ResolverUpdate;
+
+/// Caches the resolver updates accepted by channels, keyed by target.
+#[derive(Debug)]
+pub struct ResolutionCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<String, Entry>>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    update: ResolverUpdate,
+    expiry: Instant,
+}
+
+impl ResolutionCache {
+    /// Creates a cache whose updates are reused for up to ttl after they are
+    /// accepted.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::default(),
+        }
+    }
+
+    /// Discards the cached update for target, so the next channel for it waits
+    /// for its resolver.
+    pub fn invalidate(&self, target: &str) {
+        self.entries.lock().unwrap().remove(target);
+    }
+
+    /// Discards every cached update.
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    /// Returns the cached update for target, if it has not expired.
+    pub(crate) fn get(&self, target: &str) -> Option<ResolverUpdate> {
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries.get(target)?;
+        if entry.expiry <= Instant::now() {
+            entries.remove(target);
+            return None;
+        }
+        Some(entry.update.clone())
+    }
+
+    /// Caches an update accepted by a channel for target.  Updates without
+    /// endpoints are not cached.
+    pub(crate) fn insert(&self, target: &str, update: &ResolverUpdate) {
+        if update.endpoints.is_err() {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        // Drop expired entries so targets which are no longer used do not
+        // accumulate.
+        let now = Instant::now();
+        entries.retain(|_, entry| entry.expiry > now);
+        entries.insert(
+            target.to_string(),
+            Entry {
+                update: update.clone(),
+                expiry: now + self.ttl,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::ResolutionCache;
+    use crate::client::name_resolution::{Address, Endpoint, ResolverUpdate};
+
+    fn update() -> ResolverUpdate {
+        let endpoint = Endpoint::builder()
+            .addresses([Address::new("tcp", "127.0.0.1:8080")])
+            .build()
+            .unwrap();
+        ResolverUpdate::builder().endpoints([endpoint]).build()
+    }
+
+    #[test]
+    fn caches_updates_by_target() {
+        let cache = ResolutionCache::new(Duration::from_secs(60));
+        assert!(cache.get("dns:///a").is_none());
+        cache.insert("dns:///a", &update());
+        assert_eq!(cache.get("dns:///a").unwrap().endpoints.unwrap().len(), 1);
+        assert!(cache.get("dns:///b").is_none());
+
+        cache.invalidate("dns:///a");
+        assert!(cache.get("dns:///a").is_none());
+
+        cache.insert("dns:///a", &update());
+        cache.insert("dns:///b", &update());
+        cache.clear();
+        assert!(cache.get("dns:///b").is_none());
+    }
+
+    #[test]
+    fn skips_expired_and_failed_updates() {
+        let cache = ResolutionCache::new(Duration::ZERO);
+        cache.insert("dns:///a", &update());
+        assert!(cache.get("dns:///a").is_none());
+
+        let cache = ResolutionCache::new(Duration::from_secs(60));
+        let failed = ResolverUpdate::builder()
+            .endpoints_error("no such host")
+            .build();
+        cache.insert("dns:///a", &failed);
+        assert!(cache.get("dns:///a").is_none());
+    }
+}
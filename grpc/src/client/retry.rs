@@ -0,0 +1,409 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! Retries of failed RPCs.
+//!
+//! Attempts which failed before the server processed them are retried
+//! transparently.  An attempt which never left the client (e.g. its
+//! subchannel disconnected after being picked) may be retried any number of
+//! times.  An attempt which reached the server's transport but not the
+//! application (REFUSED_STREAM, or a stream beyond the last one processed by a
+//! GOAWAY) is retried once.  Neither kind of retry is subject to the service
+//! config's retry policy.
+//!
+//! Attempts which the server failed with a status retried by the retry policy
+//! of their method, before sending any message, are retried after a backoff
+//! as long as the channel's retry throttler allows it.
+
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+use bytes::Bytes;
+use tokio_stream::{Stream, StreamExt};
+use tonic::{metadata::MetadataMap, Extensions};
+
+use super::{retry_throttling::RetryThrottler, service_config::RetryPolicy};
+use crate::service::{
+    details::{self, Pushback},
+    Message, Request, Response, Status,
+};
+
+/// The number of transparent retries allowed for attempts which reached the
+/// server.
+pub(crate) const MAX_REFUSED_RETRIES: u32 = 1;
+
+/// How far an attempt which failed before being processed got.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Unprocessed {
+    /// The attempt never left the client.
+    NotSent,
+    /// The attempt was refused by the server's transport.
+    Refused,
+}
+
+/// The source of a status failing an attempt which never left the client.
+#[derive(Debug)]
+pub(crate) struct NotSent;
+
+impl Display for NotSent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "the call was not sent")
+    }
+}
+
+impl Error for NotSent {}
+
+/// Returns an UNAVAILABLE status for an attempt which never left the client.
+pub(crate) fn not_sent(message: impl Into<String>) -> Status {
+    let mut status = Status::unavailable(message);
+    status.set_source(Arc::new(NotSent));
+    status
+}
+
+/// Returns whether status failed an attempt before the server processed it,
+/// by inspecting its source chain.
+pub(crate) fn unprocessed(status: &Status) -> Option<Unprocessed> {
+    let mut source = status.source();
+    while let Some(err) = source {
+        if err.is::<NotSent>() {
+            return Some(Unprocessed::NotSent);
+        }
+        if let Some(err) = err.downcast_ref::<h2::Error>() {
+            // Streams beyond the last one processed are failed with the
+            // reason of a graceful GOAWAY.
+            let refused = err.reason() == Some(h2::Reason::REFUSED_STREAM)
+                || (err.is_go_away()
+                    && err.is_remote()
+                    && err.reason() == Some(h2::Reason::NO_ERROR));
+            return refused.then_some(Unprocessed::Refused);
+        }
+        source = err.source();
+    }
+    None
+}
+
+/// Returns the status of response if it failed the attempt before the server
+/// processed it, or response otherwise.  Such failures are reported before
+/// the response is returned, so the response stream is only polled once
+/// without waiting.
+pub(crate) fn check_response(response: Response) -> Result<Response, (Unprocessed, Status)> {
+    let (metadata, mut stream, extensions) = response.into_parts();
+    let mut cx = Context::from_waker(Waker::noop());
+    let stream = match stream.as_mut().poll_next(&mut cx) {
+        Poll::Ready(Some(Err(status))) => match unprocessed(&status) {
+            Some(kind) => return Err((kind, status)),
+            None => Box::pin(tokio_stream::once(Err(status)).chain(stream)),
+        },
+        Poll::Ready(Some(Ok(msg))) => Box::pin(tokio_stream::once(Ok(msg)).chain(stream)),
+        Poll::Ready(None) => stream,
+        Poll::Pending => stream,
+    };
+    Ok(Response::from_parts(metadata, stream, extensions))
+}
+
+/// The retries of a call made by the retry policy of its method.
+pub(crate) struct PolicyRetries {
+    policy: RetryPolicy,
+    throttler: Option<Arc<RetryThrottler>>,
+    attempts: u32,
+    backoff: Duration,
+}
+
+impl PolicyRetries {
+    pub(crate) fn new(policy: RetryPolicy, throttler: Option<Arc<RetryThrottler>>) -> Self {
+        Self {
+            backoff: policy.initial_backoff,
+            policy,
+            throttler,
+            attempts: 1,
+        }
+    }
+
+    /// Returns the status of response, and the delay before the next attempt,
+    /// if response failed the attempt with a retryable status and another
+    /// attempt is allowed.  Returns response otherwise.  Like
+    /// [`check_response`], the response stream is only polled once without
+    /// waiting, so only attempts which failed before the server sent any
+    /// message are retried.
+    pub(crate) fn check(&mut self, response: Response) -> Result<Response, (Duration, Status)> {
+        let (metadata, mut stream, extensions) = response.into_parts();
+        let mut cx = Context::from_waker(Waker::noop());
+        let stream = match stream.as_mut().poll_next(&mut cx) {
+            Poll::Ready(Some(Err(status))) => match self.next_delay(&status) {
+                Some(delay) => return Err((delay, status)),
+                None => Box::pin(tokio_stream::once(Err(status)).chain(stream)),
+            },
+            Poll::Ready(Some(Ok(msg))) => Box::pin(tokio_stream::once(Ok(msg)).chain(stream)),
+            Poll::Ready(None) => stream,
+            Poll::Pending => stream,
+        };
+        Ok(Response::from_parts(metadata, stream, extensions))
+    }
+
+    // Returns the delay before retrying an attempt which failed with status,
+    // or None if it may not be retried.
+    fn next_delay(&mut self, status: &Status) -> Option<Duration> {
+        if !self.policy.retryable_status_codes.contains(&status.code())
+            || self.attempts >= self.policy.max_attempts
+        {
+            return None;
+        }
+        let pushback = details::pushback(status);
+        if pushback == Some(Pushback::DoNotRetry) {
+            return None;
+        }
+        if let Some(throttler) = &self.throttler {
+            if !throttler.record_retried_failure() {
+                return None;
+            }
+        }
+        self.attempts += 1;
+        if let Some(Pushback::RetryAfter(delay)) = pushback {
+            // The server chose the delay, so the backoff starts over.
+            self.backoff = self.policy.initial_backoff;
+            return Some(delay);
+        }
+        let delay = self.backoff.mul_f64(rand::random::<f64>());
+        self.backoff = self
+            .backoff
+            .mul_f64(self.policy.backoff_multiplier)
+            .min(self.policy.max_backoff);
+        Some(delay)
+    }
+}
+
+type RequestStream = Pin<Box<dyn Stream<Item = Box<dyn Message>> + Send + Sync>>;
+
+/// Buffers the messages of a request so it can be replayed by later attempts.
+///
+/// Only [`Bytes`] messages, which is how generated code passes messages, are
+/// buffered, up to a limit on their total size.  Once any other message is
+/// sent, or the limit is exceeded, the request can no longer be replayed.
+pub(crate) struct ReplayableRequest {
+    metadata: MetadataMap,
+    extensions: Extensions,
+    state: Arc<Mutex<ReplayState>>,
+}
+
+struct ReplayState {
+    source: RequestStream,
+    sent: Vec<Bytes>,
+    sent_bytes: usize,
+    limit: usize,
+    committed: bool,
+    attempt: u64,
+}
+
+impl ReplayableRequest {
+    pub(crate) fn new(request: Request, limit: usize) -> Self {
+        let (metadata, extensions, source) = request.into_parts();
+        Self {
+            metadata,
+            extensions,
+            state: Arc::new(Mutex::new(ReplayState {
+                source,
+                sent: Vec::new(),
+                sent_bytes: 0,
+                limit,
+                committed: false,
+                attempt: 0,
+            })),
+        }
+    }
+
+    /// Returns the request of the next attempt, or None if the messages sent
+    /// by earlier attempts can't be replayed.  The streams of earlier
+    /// attempts end when a new attempt starts.
+    pub(crate) fn attempt(&self) -> Option<Request> {
+        let mut state = self.state.lock().unwrap();
+        if state.committed {
+            return None;
+        }
+        state.attempt += 1;
+        let stream: RequestStream = Box::pin(AttemptStream {
+            state: self.state.clone(),
+            attempt: state.attempt,
+            next: 0,
+        });
+        Some(Request::from_parts(
+            self.metadata.clone(),
+            self.extensions.clone(),
+            stream,
+        ))
+    }
+}
+
+// Replays the messages sent by earlier attempts, and then sends (and buffers)
+// the remaining messages of the request.
+struct AttemptStream {
+    state: Arc<Mutex<ReplayState>>,
+    attempt: u64,
+    next: usize,
+}
+
+impl Stream for AttemptStream {
+    type Item = Box<dyn Message>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let state = self.state.clone();
+        let mut state = state.lock().unwrap();
+        if state.attempt != self.attempt {
+            return Poll::Ready(None);
+        }
+        if let Some(msg) = state.sent.get(self.next) {
+            self.next += 1;
+            return Poll::Ready(Some(Box::new(msg.clone())));
+        }
+        let msg = match state.source.as_mut().poll_next(cx) {
+            Poll::Ready(Some(msg)) => msg,
+            other => return other,
+        };
+        if !state.committed {
+            match (msg.as_ref() as &dyn std::any::Any).downcast_ref::<Bytes>() {
+                Some(bytes) if state.sent_bytes + bytes.len() <= state.limit => {
+                    state.sent_bytes += bytes.len();
+                    state.sent.push(bytes.clone());
+                    self.next += 1;
+                }
+                _ => {
+                    state.committed = true;
+                    state.sent.clear();
+                }
+            }
+        }
+        Poll::Ready(Some(msg))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use bytes::Bytes;
+    use tokio_stream::StreamExt;
+    use tonic::Code;
+
+    use super::{check_response, not_sent, unprocessed, ReplayableRequest, Unprocessed};
+    use crate::{
+        client::load_balancing::test_utils::new_request,
+        service::{status_response, Message, Request, Status},
+    };
+
+    fn bytes_request(msgs: &[&'static str]) -> Request {
+        let msgs: Vec<Box<dyn Message>> = msgs
+            .iter()
+            .map(|m| Box::new(Bytes::from_static(m.as_bytes())) as Box<dyn Message>)
+            .collect();
+        Request::new(Box::pin(tokio_stream::iter(msgs)))
+    }
+
+    async fn collect(request: Request) -> Vec<Bytes> {
+        request
+            .into_inner()
+            .map(|m| {
+                (m.as_ref() as &dyn std::any::Any)
+                    .downcast_ref::<Bytes>()
+                    .unwrap()
+                    .clone()
+            })
+            .collect()
+            .await
+    }
+
+    #[test]
+    fn classifies_unprocessed_statuses() {
+        assert_eq!(
+            unprocessed(&not_sent("disconnected")),
+            Some(Unprocessed::NotSent)
+        );
+
+        let mut refused = Status::unavailable("refused");
+        refused.set_source(Arc::new(h2::Error::from(h2::Reason::REFUSED_STREAM)));
+        assert_eq!(unprocessed(&refused), Some(Unprocessed::Refused));
+
+        let mut reset = Status::unknown("internal error");
+        reset.set_source(Arc::new(h2::Error::from(h2::Reason::INTERNAL_ERROR)));
+        assert_eq!(unprocessed(&reset), None);
+
+        assert_eq!(unprocessed(&Status::unavailable("from the server")), None);
+    }
+
+    #[tokio::test]
+    async fn check_response_preserves_other_responses() {
+        let Err((kind, status)) = check_response(status_response(not_sent("gone"))) else {
+            panic!("unsent call was not reported");
+        };
+        assert_eq!(kind, Unprocessed::NotSent);
+        assert_eq!(status.code(), Code::Unavailable);
+
+        let Ok(response) = check_response(status_response(Status::unavailable("server"))) else {
+            panic!("call failed by the server was reported as unprocessed");
+        };
+        let status = response.into_inner().next().await.unwrap().unwrap_err();
+        assert_eq!(status.message(), "server");
+    }
+
+    #[tokio::test]
+    async fn replays_sent_messages() {
+        let request = ReplayableRequest::new(bytes_request(&["a", "b", "c"]), 1024);
+        let mut first = request.attempt().unwrap().into_inner();
+        first.next().await.unwrap();
+        first.next().await.unwrap();
+
+        let second = request.attempt().unwrap();
+        // The stream of the first attempt ends once the second starts.
+        assert!(first.next().await.is_none());
+        assert_eq!(collect(second).await, vec!["a", "b", "c"]);
+        assert_eq!(
+            collect(request.attempt().unwrap()).await,
+            vec!["a", "b", "c"]
+        );
+    }
+
+    #[tokio::test]
+    async fn stops_replaying_over_limit() {
+        let request = ReplayableRequest::new(bytes_request(&["abc", "def"]), 4);
+        assert_eq!(
+            collect(request.attempt().unwrap()).await,
+            vec!["abc", "def"]
+        );
+        assert!(request.attempt().is_none());
+    }
+
+    #[tokio::test]
+    async fn stops_replaying_other_messages() {
+        let request = ReplayableRequest::new(new_request(), 1024);
+        // A request may be replayed until a message is sent.
+        request.attempt().unwrap();
+        let mut stream = request.attempt().unwrap().into_inner();
+        stream.next().await.unwrap();
+        assert!(request.attempt().is_none());
+    }
+}
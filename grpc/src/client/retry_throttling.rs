@@ -0,0 +1,256 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! Retry throttling, configured by the service config's `retryThrottling`.
+//!
+//! Each channel, and so each server name, has a token bucket shared by all
+//! of its calls.  Calls which succeed add tokens, while calls which fail with
+//! a status the method's retry or hedging policy would retry (or for which
+//! the server pushed back) remove one.  Retries and hedged attempts are
+//! suppressed while the bucket is at most half full, so that they don't
+//! amplify the load on servers which are already failing.
+
+use std::{
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use tokio_stream::Stream;
+use tonic::{Code, Status};
+
+use super::service_config::RetryThrottlingPolicy;
+use crate::service::{details, Message, Response};
+
+/// A token bucket limiting the retries and hedged attempts of a channel.
+/// Tokens are counted in thousandths.
+#[derive(Debug)]
+pub(crate) struct RetryThrottler {
+    max_tokens: u32,
+    token_ratio: u32,
+    tokens: AtomicU32,
+}
+
+impl RetryThrottler {
+    /// Creates a full bucket.
+    pub(crate) fn new(policy: &RetryThrottlingPolicy) -> Self {
+        let max_tokens = policy.max_tokens * 1000;
+        Self {
+            max_tokens,
+            token_ratio: policy.token_ratio_millis,
+            tokens: AtomicU32::new(max_tokens),
+        }
+    }
+
+    /// Records a successful call.
+    pub(crate) fn record_success(&self) {
+        let _ = self
+            .tokens
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |tokens| {
+                Some((tokens + self.token_ratio).min(self.max_tokens))
+            });
+    }
+
+    /// Records a failed call which was eligible for retry.
+    pub(crate) fn record_failure(&self) {
+        let _ = self
+            .tokens
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |tokens| {
+                Some(tokens.saturating_sub(1000))
+            });
+    }
+
+    /// Records a failed attempt which is eligible for retry, and returns
+    /// whether it may be retried: whether the bucket is still more than half
+    /// full after removing a token.  A failure which may not be retried is
+    /// not recorded, since it is recorded when its call completes.
+    pub(crate) fn record_retried_failure(&self) -> bool {
+        self.tokens
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |tokens| {
+                let tokens = tokens.saturating_sub(1000);
+                (tokens > self.max_tokens / 2).then_some(tokens)
+            })
+            .is_ok()
+    }
+
+    /// Returns the number of tokens in the bucket.
+    pub(crate) fn tokens(&self) -> f64 {
+        f64::from(self.tokens.load(Ordering::Relaxed)) / 1000.0
+    }
+
+    fn record(&self, status: Option<&Status>, failure_codes: &[Code]) {
+        match status {
+            None => self.record_success(),
+            Some(status) if status.code() == Code::Ok => self.record_success(),
+            Some(status)
+                if failure_codes.contains(&status.code())
+                    || details::pushback(status).is_some() =>
+            {
+                self.record_failure()
+            }
+            Some(_) => {}
+        }
+    }
+}
+
+pin_project_lite::pin_project! {
+    // Records the outcome of a call when its response stream completes.
+    struct ThrottledStream<S> {
+        #[pin]
+        inner: S,
+        failure_codes: Option<Vec<Code>>,
+        throttler: Arc<RetryThrottler>,
+    }
+}
+
+impl<S: Stream<Item = Result<Box<dyn Message>, Status>>> Stream for ThrottledStream<S> {
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let item = this.inner.poll_next(cx);
+        let status = match &item {
+            Poll::Ready(Some(Err(status))) => Some(status),
+            Poll::Ready(None) => None,
+            _ => return item,
+        };
+        if let Some(failure_codes) = this.failure_codes.take() {
+            this.throttler.record(status, &failure_codes);
+        }
+        item
+    }
+}
+
+/// Records the outcome of a call in throttler when the message stream of
+/// response completes.  Failures are only counted if their code is one of
+/// failure_codes.
+pub(crate) fn record_on_complete(
+    response: Response,
+    failure_codes: Vec<Code>,
+    throttler: Arc<RetryThrottler>,
+) -> Response {
+    response.map(|inner| {
+        Box::pin(ThrottledStream {
+            inner,
+            failure_codes: Some(failure_codes),
+            throttler,
+        }) as Pin<Box<dyn Stream<Item = Result<Box<dyn Message>, Status>> + Send>>
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use tokio_stream::StreamExt;
+    use tonic::{Code, Status};
+
+    use super::{record_on_complete, RetryThrottler};
+    use crate::{
+        client::service_config::RetryThrottlingPolicy,
+        service::{details::RETRY_PUSHBACK_KEY, status_response, Message, Response},
+    };
+
+    fn throttler(max_tokens: u32, token_ratio_millis: u32) -> RetryThrottler {
+        RetryThrottler::new(&RetryThrottlingPolicy {
+            max_tokens,
+            token_ratio_millis,
+        })
+    }
+
+    #[test]
+    fn throttles_at_half_capacity() {
+        let throttler = throttler(10, 500);
+        assert_eq!(throttler.tokens(), 10.0);
+        for _ in 0..3 {
+            throttler.record_failure();
+        }
+        assert!(throttler.record_retried_failure());
+        assert_eq!(throttler.tokens(), 6.0);
+        assert!(!throttler.record_retried_failure());
+        assert_eq!(throttler.tokens(), 6.0);
+
+        throttler.record_success();
+        assert_eq!(throttler.tokens(), 6.5);
+        assert!(throttler.record_retried_failure());
+        assert_eq!(throttler.tokens(), 5.5);
+    }
+
+    #[test]
+    fn tokens_are_bounded() {
+        let throttler = throttler(2, 1500);
+        throttler.record_success();
+        assert_eq!(throttler.tokens(), 2.0);
+        for _ in 0..5 {
+            throttler.record_failure();
+        }
+        assert_eq!(throttler.tokens(), 0.0);
+        throttler.record_success();
+        assert_eq!(throttler.tokens(), 1.5);
+    }
+
+    async fn complete(response: Response) {
+        let mut stream = response.into_inner();
+        while stream.next().await.is_some() {}
+    }
+
+    #[tokio::test]
+    async fn records_call_outcomes() {
+        let throttler = Arc::new(throttler(10, 100));
+        let failure_codes = vec![Code::Unavailable];
+        let record = |status: Status| {
+            record_on_complete(
+                status_response(status),
+                failure_codes.clone(),
+                throttler.clone(),
+            )
+        };
+
+        complete(record(Status::unavailable("down"))).await;
+        assert_eq!(throttler.tokens(), 9.0);
+        // Codes which would not be retried are not counted...
+        complete(record(Status::not_found("missing"))).await;
+        assert_eq!(throttler.tokens(), 9.0);
+        // ...unless the server pushed back.
+        let mut status = Status::not_found("missing");
+        status
+            .metadata_mut()
+            .insert(RETRY_PUSHBACK_KEY, "100".parse().unwrap());
+        complete(record(status)).await;
+        assert_eq!(throttler.tokens(), 8.0);
+
+        let msgs: Vec<Result<Box<dyn Message>, Status>> = vec![Ok(Box::new(()))];
+        let response = Response::new(Box::pin(tokio_stream::iter(msgs)));
+        complete(record_on_complete(
+            response,
+            failure_codes.clone(),
+            throttler.clone(),
+        ))
+        .await;
+        assert_eq!(throttler.tokens(), 8.1);
+    }
+}
@@ -21,16 +21,485 @@
  * IN THE SOFTWARE.
  *
  */
-use std::{any::Any, error::Error, sync::Arc};
+use std::{
+    any::Any,
+    error::Error,
+    fmt::{Debug, Display},
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use serde::Deserialize;
+use tonic::Code;
+
+use super::{
+    load_balancing::{LbPolicyBuilder, LbPolicyRegistry, ParsedJsonLbConfig},
+    retry_throttling::RetryThrottler,
+};
 
 /// An in-memory representation of a service config, usually provided to gRPC as
 /// a JSON object.
 #[derive(Debug, Default, Clone)]
-pub(crate) struct ServiceConfig;
+pub struct ServiceConfig {
+    pub(crate) method_configs: Vec<MethodConfig>,
+    pub(crate) retry_throttling: Option<RetryThrottlingPolicy>,
+    /// The LB policy chosen from the config's loadBalancingConfig list, or
+    /// None if the list is empty.
+    pub(crate) lb_policy: Option<LbPolicySelection>,
+}
+
+/// An LB policy chosen by a service config, and its parsed configuration.
+#[derive(Clone)]
+pub(crate) struct LbPolicySelection {
+    pub(crate) builder: Arc<dyn LbPolicyBuilder>,
+    pub(crate) config: Option<Arc<LbConfig>>,
+    // The JSON config the policy was selected with.
+    pub(crate) json: serde_json::Value,
+}
+
+impl Debug for LbPolicySelection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LbPolicySelection")
+            .field("policy", &self.builder.name())
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+/// The configuration of the methods matching any of its names.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct MethodConfig {
+    pub(crate) names: Vec<MethodName>,
+    /// The default timeout of calls.  The deadline of calls with their own
+    /// timeout is the earlier of the two.
+    pub(crate) timeout: Option<Duration>,
+    /// Whether calls wait for the channel to become ready instead of failing
+    /// while it is in TRANSIENT_FAILURE, unless the call chooses otherwise.
+    pub(crate) wait_for_ready: Option<bool>,
+    /// How calls which fail are retried.
+    pub(crate) retry_policy: Option<RetryPolicy>,
+    /// The status codes which do not stop the hedging policy from sending
+    /// further attempts.
+    pub(crate) non_fatal_status_codes: Vec<Code>,
+}
+
+/// Matches every method of service if method is None, or every method of
+/// every service if service is also None.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct MethodName {
+    pub(crate) service: Option<String>,
+    pub(crate) method: Option<String>,
+}
+
+/// Retries calls which fail with one of the retryable status codes, after an
+/// exponential backoff.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct RetryPolicy {
+    /// The maximum number of attempts of a call, including the first, in
+    /// [2, 5].
+    pub(crate) max_attempts: u32,
+    pub(crate) initial_backoff: Duration,
+    pub(crate) max_backoff: Duration,
+    pub(crate) backoff_multiplier: f64,
+    pub(crate) retryable_status_codes: Vec<Code>,
+}
+
+/// The maximum number of attempts allowed by a retry policy.  Larger values
+/// in service configs are reduced to it.
+const MAX_RETRY_ATTEMPTS: u32 = 5;
+
+/// Limits retries and hedged attempts while many calls are failing.  See
+/// [`RetryThrottler`](super::retry_throttling::RetryThrottler).
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct RetryThrottlingPolicy {
+    /// The capacity of the token bucket, in (0, 1000].
+    pub(crate) max_tokens: u32,
+    /// The tokens added by each successful call, in thousandths of a token.
+    pub(crate) token_ratio_millis: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonServiceConfig {
+    #[serde(default)]
+    method_config: Vec<JsonMethodConfig>,
+    retry_throttling: Option<JsonRetryThrottling>,
+    #[serde(default)]
+    load_balancing_config: Vec<serde_json::Map<String, serde_json::Value>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonMethodConfig {
+    #[serde(default)]
+    name: Vec<JsonMethodName>,
+    timeout: Option<String>,
+    wait_for_ready: Option<bool>,
+    retry_policy: Option<JsonRetryPolicy>,
+    hedging_policy: Option<JsonHedgingPolicy>,
+}
+
+#[derive(Deserialize)]
+struct JsonMethodName {
+    service: Option<String>,
+    method: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonRetryPolicy {
+    max_attempts: Option<f64>,
+    initial_backoff: Option<String>,
+    max_backoff: Option<String>,
+    backoff_multiplier: Option<f64>,
+    #[serde(default)]
+    retryable_status_codes: Vec<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonHedgingPolicy {
+    #[serde(default)]
+    non_fatal_status_codes: Vec<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonRetryThrottling {
+    max_tokens: f64,
+    token_ratio: f64,
+}
+
+impl ServiceConfig {
+    /// Parses the JSON representation of a service config.  Fields which
+    /// are not yet supported are ignored.
+    pub(crate) fn parse(config: &str) -> Result<Self, String> {
+        Self::parse_with_lb_registry(config, &LbPolicyRegistry::default())
+    }
+
+    /// Like parse, except that LB policies are looked up in lb_registry
+    /// before the global registry.
+    pub(crate) fn parse_with_lb_registry(
+        config: &str,
+        lb_registry: &LbPolicyRegistry,
+    ) -> Result<Self, String> {
+        let config: JsonServiceConfig =
+            serde_json::from_str(config).map_err(|e| format!("invalid service config: {e}"))?;
+        let method_configs = config
+            .method_config
+            .into_iter()
+            .map(MethodConfig::from_json)
+            .collect::<Result<_, _>>()?;
+        let retry_throttling = config
+            .retry_throttling
+            .map(RetryThrottlingPolicy::from_json)
+            .transpose()?;
+        let lb_policy = select_lb_policy(config.load_balancing_config, lb_registry)?;
+        Ok(Self {
+            method_configs,
+            retry_throttling,
+            lb_policy,
+        })
+    }
+
+    /// Returns the config of method, a path of the form "/service/method".
+    /// A config naming the method takes precedence over one naming its
+    /// service, which takes precedence over the default config.
+    pub(crate) fn method_config(&self, method: &str) -> Option<&MethodConfig> {
+        let (service, method) = method
+            .strip_prefix('/')
+            .and_then(|m| m.split_once('/'))
+            .unwrap_or_default();
+        let find = |service: Option<&str>, method: Option<&str>| {
+            self.method_configs.iter().find(|mc| {
+                mc.names.iter().any(|name| {
+                    name.service.as_deref() == service && name.method.as_deref() == method
+                })
+            })
+        };
+        find(Some(service), Some(method))
+            .or_else(|| find(Some(service), None))
+            .or_else(|| find(None, None))
+    }
+}
+
+/// The service config in effect for a channel, and the retry throttler built
+/// from its retry throttling policy.
+#[derive(Debug, Default)]
+pub(crate) struct SelectedServiceConfig {
+    pub(crate) config: ServiceConfig,
+    pub(crate) retry_throttler: Option<Arc<RetryThrottler>>,
+}
+
+/// Chooses the service config of a channel: the config of the latest resolver
+/// update, or the default config of the channel if the resolver provides
+/// none.
+#[derive(Debug)]
+pub(crate) struct ServiceConfigSelector {
+    default: Option<ServiceConfig>,
+    require_resolver_config: bool,
+    // Whether a resolver update was accepted, whose config stays in effect
+    // when later updates have invalid configs.
+    resolved: RwLock<bool>,
+    selected: RwLock<Arc<SelectedServiceConfig>>,
+}
+
+impl ServiceConfigSelector {
+    /// Creates a selector which initially selects the default config, if
+    /// any.  If require_resolver_config is set, the default config is never
+    /// used and resolver updates without a config are rejected.
+    pub(crate) fn new(default: Option<ServiceConfig>, require_resolver_config: bool) -> Self {
+        let default = default.filter(|_| !require_resolver_config);
+        let selected = Self::build(default.clone().unwrap_or_default(), None);
+        Self {
+            default,
+            require_resolver_config,
+            resolved: RwLock::new(false),
+            selected: RwLock::new(Arc::new(selected)),
+        }
+    }
+
+    /// Returns the config currently in effect.
+    pub(crate) fn current(&self) -> Arc<SelectedServiceConfig> {
+        self.selected.read().unwrap().clone()
+    }
+
+    /// Returns the config to select for the config of a resolver update, None
+    /// to keep the current one, or an error if the update must be rejected.
+    pub(crate) fn choose(
+        &self,
+        config: &Result<Option<ServiceConfig>, String>,
+    ) -> Result<Option<ServiceConfig>, String> {
+        match config {
+            Ok(Some(config)) => Ok(Some(config.clone())),
+            Ok(None) if self.require_resolver_config => {
+                Err("the resolver provided no service config, which the channel requires".into())
+            }
+            Ok(None) => Ok(Some(self.default.clone().unwrap_or_default())),
+            Err(_) if *self.resolved.read().unwrap() => Ok(None),
+            Err(_) if self.default.is_some() => Ok(self.default.clone()),
+            Err(err) => Err(err.clone()),
+        }
+    }
+
+    /// Records that a resolver update was accepted, and selects config
+    /// unless it is None.
+    pub(crate) fn select(&self, config: Option<ServiceConfig>) {
+        *self.resolved.write().unwrap() = true;
+        let Some(config) = config else {
+            return;
+        };
+        let mut selected = self.selected.write().unwrap();
+        *selected = Arc::new(Self::build(config, Some(&selected)));
+    }
+
+    // Keeps the retry throttler of the previous config if the throttling
+    // policy did not change.
+    fn build(
+        config: ServiceConfig,
+        previous: Option<&SelectedServiceConfig>,
+    ) -> SelectedServiceConfig {
+        let retry_throttler = match previous {
+            Some(previous) if previous.config.retry_throttling == config.retry_throttling => {
+                previous.retry_throttler.clone()
+            }
+            _ => config
+                .retry_throttling
+                .as_ref()
+                .map(|policy| Arc::new(RetryThrottler::new(policy))),
+        };
+        SelectedServiceConfig {
+            config,
+            retry_throttler,
+        }
+    }
+}
+
+/// Chooses the first policy of a loadBalancingConfig list which is registered
+/// and whose config parses.  Fails if there is no such policy, unless the list
+/// is empty.
+fn select_lb_policy(
+    configs: Vec<serde_json::Map<String, serde_json::Value>>,
+    lb_registry: &LbPolicyRegistry,
+) -> Result<Option<LbPolicySelection>, String> {
+    if configs.is_empty() {
+        return Ok(None);
+    }
+    let mut errors = Vec::new();
+    for entry in configs {
+        if entry.len() != 1 {
+            return Err(format!(
+                "loadBalancingConfig entries must have exactly one field, got {}",
+                entry.len()
+            ));
+        }
+        let (name, config) = entry.into_iter().next().unwrap();
+        let Some(builder) = lb_registry.get_policy_or_global(&name) else {
+            continue;
+        };
+        match builder.parse_config(&ParsedJsonLbConfig::from_value(config.clone(), lb_registry)) {
+            Ok(parsed) => {
+                return Ok(Some(LbPolicySelection {
+                    builder,
+                    config: parsed.map(Arc::new),
+                    json: config,
+                }))
+            }
+            Err(err) => errors.push(format!("{name}: {err}")),
+        }
+    }
+    if errors.is_empty() {
+        return Err(format!(
+            "loadBalancingConfig has no registered LB policy; registered policies: {}",
+            lb_registry.policy_names_or_global().join(", ")
+        ));
+    }
+    Err(format!(
+        "loadBalancingConfig has no valid LB policy config: {}",
+        errors.join("; ")
+    ))
+}
+
+impl MethodConfig {
+    fn from_json(config: JsonMethodConfig) -> Result<Self, String> {
+        let names = config
+            .name
+            .into_iter()
+            .map(|name| {
+                // Empty strings are equivalent to absent fields.
+                let service = name.service.filter(|s| !s.is_empty());
+                let method = name.method.filter(|m| !m.is_empty());
+                if let (None, Some(method)) = (&service, &method) {
+                    return Err(format!(
+                        "method config name has method {method:?} but no service"
+                    ));
+                }
+                Ok(MethodName { service, method })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            names,
+            timeout: config.timeout.as_deref().map(parse_duration).transpose()?,
+            wait_for_ready: config.wait_for_ready,
+            retry_policy: config
+                .retry_policy
+                .map(RetryPolicy::from_json)
+                .transpose()?,
+            non_fatal_status_codes: parse_codes(
+                &config
+                    .hedging_policy
+                    .map(|p| p.non_fatal_status_codes)
+                    .unwrap_or_default(),
+            )?,
+        })
+    }
+}
+
+impl RetryPolicy {
+    fn from_json(config: JsonRetryPolicy) -> Result<Self, String> {
+        let max_attempts = config
+            .max_attempts
+            .ok_or("retryPolicy.maxAttempts is required")?;
+        if max_attempts < 2.0 || max_attempts.fract() != 0.0 {
+            return Err(format!(
+                "retryPolicy.maxAttempts must be an integer greater than 1, got {max_attempts}"
+            ));
+        }
+        let backoff = |name: &str, value: Option<String>| {
+            let value = value.ok_or_else(|| format!("retryPolicy.{name} is required"))?;
+            let backoff = parse_duration(&value)?;
+            if backoff.is_zero() {
+                return Err(format!("retryPolicy.{name} must be positive, got {value}"));
+            }
+            Ok::<_, String>(backoff)
+        };
+        let backoff_multiplier = config
+            .backoff_multiplier
+            .ok_or("retryPolicy.backoffMultiplier is required")?;
+        if backoff_multiplier.is_nan() || backoff_multiplier <= 0.0 {
+            return Err(format!(
+                "retryPolicy.backoffMultiplier must be positive, got {backoff_multiplier}"
+            ));
+        }
+        let retryable_status_codes = parse_codes(&config.retryable_status_codes)?;
+        if retryable_status_codes.is_empty() {
+            return Err("retryPolicy.retryableStatusCodes must not be empty".to_string());
+        }
+        Ok(Self {
+            max_attempts: (max_attempts as u32).min(MAX_RETRY_ATTEMPTS),
+            initial_backoff: backoff("initialBackoff", config.initial_backoff)?,
+            max_backoff: backoff("maxBackoff", config.max_backoff)?,
+            backoff_multiplier,
+            retryable_status_codes,
+        })
+    }
+}
+
+impl RetryThrottlingPolicy {
+    fn from_json(config: JsonRetryThrottling) -> Result<Self, String> {
+        if !(config.max_tokens > 0.0 && config.max_tokens <= 1000.0)
+            || config.max_tokens.fract() != 0.0
+        {
+            return Err(format!(
+                "retryThrottling.maxTokens must be an integer in (0, 1000], got {}",
+                config.max_tokens
+            ));
+        }
+        // Only three decimal places of the ratio are significant.
+        let token_ratio_millis = (config.token_ratio * 1000.0).trunc();
+        if token_ratio_millis.is_nan() || token_ratio_millis < 1.0 {
+            return Err(format!(
+                "retryThrottling.tokenRatio must be at least 0.001, got {}",
+                config.token_ratio
+            ));
+        }
+        Ok(Self {
+            max_tokens: config.max_tokens as u32,
+            token_ratio_millis: token_ratio_millis as u32,
+        })
+    }
+}
+
+fn parse_codes(codes: &[serde_json::Value]) -> Result<Vec<Code>, String> {
+    codes.iter().map(parse_code).collect()
+}
+
+// Parses a status code given by its name (e.g. "UNAVAILABLE") or number.
+fn parse_code(code: &serde_json::Value) -> Result<Code, String> {
+    if let Some(n) = code.as_u64() {
+        return match n {
+            0..=16 => Ok(Code::from_i32(n as i32)),
+            _ => Err(format!("invalid status code {n}")),
+        };
+    }
+    let name = code
+        .as_str()
+        .ok_or_else(|| format!("invalid status code {code}"))?;
+    Ok(match name {
+        "OK" => Code::Ok,
+        "CANCELLED" => Code::Cancelled,
+        "UNKNOWN" => Code::Unknown,
+        "INVALID_ARGUMENT" => Code::InvalidArgument,
+        "DEADLINE_EXCEEDED" => Code::DeadlineExceeded,
+        "NOT_FOUND" => Code::NotFound,
+        "ALREADY_EXISTS" => Code::AlreadyExists,
+        "PERMISSION_DENIED" => Code::PermissionDenied,
+        "RESOURCE_EXHAUSTED" => Code::ResourceExhausted,
+        "FAILED_PRECONDITION" => Code::FailedPrecondition,
+        "ABORTED" => Code::Aborted,
+        "OUT_OF_RANGE" => Code::OutOfRange,
+        "UNIMPLEMENTED" => Code::Unimplemented,
+        "INTERNAL" => Code::Internal,
+        "UNAVAILABLE" => Code::Unavailable,
+        "DATA_LOSS" => Code::DataLoss,
+        "UNAUTHENTICATED" => Code::Unauthenticated,
+        _ => return Err(format!("invalid status code {name:?}")),
+    })
+}
 
 /// A convenience wrapper for an LB policy's configuration object.
 #[derive(Debug)]
-pub(crate) struct LbConfig {
+pub struct LbConfig {
     config: Arc<dyn Any + Send + Sync>,
 }
 
@@ -48,7 +517,403 @@ impl LbConfig {
     ) -> Result<Arc<T>, Box<dyn Error + Send + Sync>> {
         match self.config.clone().downcast::<T>() {
             Ok(c) => Ok(c),
-            Err(e) => Err("failed to downcast to config type".into()),
+            Err(_) => {
+                Err(format!("LB policy config is not a {}", std::any::type_name::<T>()).into())
+            }
+        }
+    }
+
+    /// Returns a reference to the configuration object if it is a T.
+    pub fn get<T: 'static>(&self) -> Option<&T> {
+        self.config.downcast_ref()
+    }
+}
+
+/// Implemented by the configuration objects of LB policies to convert them to
+/// and from [`LbConfig`].  All methods have default implementations, so an
+/// empty impl is sufficient:
+///
+/// ```ignore
+/// impl LbPolicyConfig for MyPolicyConfig {}
+///
+/// // In LbPolicyBuilder::parse_config:
+/// Ok(Some(MyPolicyConfig { .. }.into_lb_config()))
+///
+/// // In LbPolicy::resolver_update:
+/// let config = MyPolicyConfig::from_lb_config(config)?;
+/// ```
+pub trait LbPolicyConfig: Sized + Send + Sync + 'static {
+    /// Wraps the config in an LbConfig.
+    fn into_lb_config(self) -> LbConfig {
+        LbConfig::new(self)
+    }
+
+    /// Extracts the config from the config given to an LB policy, failing if
+    /// it is missing or of another type.
+    fn from_lb_config(
+        config: Option<&LbConfig>,
+    ) -> Result<Arc<Self>, Box<dyn Error + Send + Sync>> {
+        config
+            .ok_or_else(|| format!("missing LB policy config {}", std::any::type_name::<Self>()))?
+            .convert_to()
+    }
+}
+
+/// A format in which service configs may be written.  Converts configs to the
+/// canonical JSON representation used by gRPC.
+///
+/// Formats other than JSON are only accepted for configs provided by the
+/// application, e.g. `ChannelOptions::default_service_config`; configs
+/// delivered by name resolvers are always JSON, per the service config
+/// specification.
+pub trait ServiceConfigFormat {
+    /// Converts config to its JSON representation.
+    fn to_json(&self, config: &str) -> Result<String, String>;
+}
+
+/// Service configs written in JSON.  Validates the config's syntax.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json;
+
+impl ServiceConfigFormat for Json {
+    fn to_json(&self, config: &str) -> Result<String, String> {
+        convert(serde_json::from_str(config), "JSON")
+    }
+}
+
+/// Service configs written in YAML.
+#[cfg(feature = "yaml")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Yaml;
+
+#[cfg(feature = "yaml")]
+impl ServiceConfigFormat for Yaml {
+    fn to_json(&self, config: &str) -> Result<String, String> {
+        convert(serde_yaml::from_str(config), "YAML")
+    }
+}
+
+/// Service configs written in TOML.
+#[cfg(feature = "toml")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Toml;
+
+#[cfg(feature = "toml")]
+impl ServiceConfigFormat for Toml {
+    fn to_json(&self, config: &str) -> Result<String, String> {
+        convert(toml::from_str(config), "TOML")
+    }
+}
+
+fn convert<E: Display>(
+    value: Result<serde_json::Value, E>,
+    format: &str,
+) -> Result<String, String> {
+    let value = value.map_err(|e| format!("invalid {format} service config: {e}"))?;
+    if !value.is_object() {
+        return Err(format!("{format} service config must be an object"));
+    }
+    Ok(value.to_string())
+}
+
+/// Parses a duration in the JSON representation of google.protobuf.Duration,
+/// e.g. "1.5s".
+pub(crate) fn parse_duration(s: &str) -> Result<Duration, String> {
+    let secs = s
+        .strip_suffix('s')
+        .ok_or_else(|| format!("duration {s:?} must end in 's'"))?;
+    let secs: f64 = secs
+        .parse()
+        .map_err(|e| format!("invalid duration {s:?}: {e}"))?;
+    Duration::try_from_secs_f64(secs).map_err(|e| format!("invalid duration {s:?}: {e}"))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{sync::Arc, time::Duration};
+
+    use tonic::Code;
+
+    use super::{
+        Json, LbConfig, LbPolicyConfig, RetryPolicy, RetryThrottlingPolicy, ServiceConfig,
+        ServiceConfigFormat, ServiceConfigSelector,
+    };
+
+    const CONFIG: &str = r#"{"loadBalancingConfig":[{"round_robin":{}}],"methodConfig":[{"name":[{"service":"pkg.Svc"}],"timeout":"1.5s"}]}"#;
+
+    #[test]
+    fn json_is_validated() {
+        assert_eq!(Json.to_json(CONFIG).unwrap(), CONFIG);
+        assert!(Json.to_json("{").is_err());
+        assert!(Json
+            .to_json("[]")
+            .unwrap_err()
+            .contains("must be an object"));
+    }
+
+    #[test]
+    fn parses_method_timeouts() {
+        let config = ServiceConfig::parse(
+            r#"{"methodConfig":[
+                {"name":[{"service":"pkg.Svc"}],"timeout":"1.5s"},
+                {"name":[{"service":"pkg.Svc","method":"Get"}]}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.method_config("/pkg.Svc/Put").unwrap().timeout,
+            Some(Duration::from_millis(1500))
+        );
+        // The config of the method takes precedence, even without a timeout.
+        assert_eq!(config.method_config("/pkg.Svc/Get").unwrap().timeout, None);
+        assert!(config.method_config("/other.Svc/Get").is_none());
+
+        assert!(
+            ServiceConfig::parse(r#"{"methodConfig":[{"name":[{}],"timeout":"1.5"}]}"#).is_err()
+        );
+    }
+
+    #[test]
+    fn parses_wait_for_ready() {
+        let config = ServiceConfig::parse(
+            r#"{"methodConfig":[
+                {"name":[{"service":"pkg.Svc"}],"waitForReady":true},
+                {"name":[{"service":"pkg.Svc","method":"Get"}],"waitForReady":false},
+                {"name":[{"service":"pkg.Other"}]}
+            ]}"#,
+        )
+        .unwrap();
+        let wait_for_ready = |method| config.method_config(method).unwrap().wait_for_ready;
+        assert_eq!(wait_for_ready("/pkg.Svc/Put"), Some(true));
+        assert_eq!(wait_for_ready("/pkg.Svc/Get"), Some(false));
+        assert_eq!(wait_for_ready("/pkg.Other/Get"), None);
+
+        assert!(
+            ServiceConfig::parse(r#"{"methodConfig":[{"name":[{}],"waitForReady":"yes"}]}"#)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn parses_retry_throttling() {
+        let config =
+            ServiceConfig::parse(r#"{"retryThrottling":{"maxTokens":10,"tokenRatio":0.1239}}"#)
+                .unwrap();
+        assert_eq!(
+            config.retry_throttling,
+            Some(RetryThrottlingPolicy {
+                max_tokens: 10,
+                token_ratio_millis: 123,
+            })
+        );
+
+        for invalid in [
+            r#"{"retryThrottling":{"maxTokens":0,"tokenRatio":1}}"#,
+            r#"{"retryThrottling":{"maxTokens":1001,"tokenRatio":1}}"#,
+            r#"{"retryThrottling":{"maxTokens":1.5,"tokenRatio":1}}"#,
+            r#"{"retryThrottling":{"maxTokens":10,"tokenRatio":0.0001}}"#,
+            r#"{"retryThrottling":{"maxTokens":10}}"#,
+        ] {
+            assert!(ServiceConfig::parse(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    // Returns a method config for all methods with a retry policy retrying
+    // codes, with the other fields set to fields.
+    fn retry_config(codes: &str, fields: &str) -> String {
+        let fields = if fields.is_empty() {
+            r#""maxAttempts":3,"initialBackoff":"0.1s","maxBackoff":"1s","backoffMultiplier":2"#
+        } else {
+            fields
+        };
+        format!(
+            r#"{{"methodConfig":[{{"name":[{{}}],"retryPolicy":{{{fields},"retryableStatusCodes":{codes}}}}}]}}"#
+        )
+    }
+
+    #[test]
+    fn finds_most_specific_method_config() {
+        let config = ServiceConfig::parse(
+            r#"{"methodConfig":[
+                {"name":[{}],"timeout":"1s"},
+                {"name":[{"service":"pkg.Svc"}],"timeout":"2s"},
+                {"name":[{"service":"pkg.Svc","method":"Get"}],"hedgingPolicy":{"nonFatalStatusCodes":["INTERNAL"]}}
+            ]}"#,
+        )
+        .unwrap();
+        let get = config.method_config("/pkg.Svc/Get").unwrap();
+        assert_eq!(get.non_fatal_status_codes, vec![Code::Internal]);
+        assert_eq!(get.timeout, None);
+        let put = config.method_config("/pkg.Svc/Put").unwrap();
+        assert_eq!(put.timeout, Some(Duration::from_secs(2)));
+        let other = config.method_config("/other.Svc/Get").unwrap();
+        assert_eq!(other.timeout, Some(Duration::from_secs(1)));
+
+        assert!(ServiceConfig::parse(r#"{"methodConfig":[{"name":[{"method":"Get"}]}]}"#).is_err());
+    }
+
+    #[test]
+    fn parses_retry_policy() {
+        let config = ServiceConfig::parse(&retry_config(r#"[14, "ABORTED"]"#, "")).unwrap();
+        assert_eq!(
+            config.method_config("/pkg.Svc/Get").unwrap().retry_policy,
+            Some(RetryPolicy {
+                max_attempts: 3,
+                initial_backoff: Duration::from_millis(100),
+                max_backoff: Duration::from_secs(1),
+                backoff_multiplier: 2.0,
+                retryable_status_codes: vec![Code::Unavailable, Code::Aborted],
+            })
+        );
+
+        let config = ServiceConfig::parse(&retry_config(
+            r#"["UNAVAILABLE"]"#,
+            r#""maxAttempts":10,"initialBackoff":"1s","maxBackoff":"1s","backoffMultiplier":1"#,
+        ))
+        .unwrap();
+        let policy = config.method_config("/pkg.Svc/Get").unwrap();
+        assert_eq!(policy.retry_policy.as_ref().unwrap().max_attempts, 5);
+
+        for (codes, fields) in [
+            (r#"["BROKEN"]"#, ""),
+            ("[]", ""),
+            (
+                r#"["UNAVAILABLE"]"#,
+                r#""maxAttempts":1,"initialBackoff":"1s","maxBackoff":"1s","backoffMultiplier":1"#,
+            ),
+            (
+                r#"["UNAVAILABLE"]"#,
+                r#""maxAttempts":2,"initialBackoff":"0s","maxBackoff":"1s","backoffMultiplier":1"#,
+            ),
+            (
+                r#"["UNAVAILABLE"]"#,
+                r#""maxAttempts":2,"initialBackoff":"1s","maxBackoff":"1s","backoffMultiplier":0"#,
+            ),
+            (
+                r#"["UNAVAILABLE"]"#,
+                r#""maxAttempts":2,"initialBackoff":"1s","backoffMultiplier":1"#,
+            ),
+        ] {
+            let config = retry_config(codes, fields);
+            assert!(ServiceConfig::parse(&config).is_err(), "{config}");
         }
     }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn yaml_converted_to_json() {
+        let config = r#"
+loadBalancingConfig:
+  - round_robin: {}
+methodConfig:
+  - name:
+      - service: pkg.Svc
+    timeout: 1.5s
+"#;
+        assert_eq!(super::Yaml.to_json(config).unwrap(), CONFIG);
+        assert!(super::Yaml.to_json("- a\n- b").is_err());
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn toml_converted_to_json() {
+        let config = r#"
+[[loadBalancingConfig]]
+round_robin = {}
+
+[[methodConfig]]
+name = [{ service = "pkg.Svc" }]
+timeout = "1.5s"
+"#;
+        assert_eq!(super::Toml.to_json(config).unwrap(), CONFIG);
+        assert!(super::Toml.to_json("timeout = ").is_err());
+    }
+
+    #[test]
+    fn selector_falls_back_on_invalid_resolver_configs() {
+        let timeout = |secs: u64| {
+            ServiceConfig::parse(&format!(
+                r#"{{"methodConfig":[{{"name":[{{}}],"timeout":"{secs}s"}}],"retryThrottling":{{"maxTokens":10,"tokenRatio":0.1}}}}"#
+            ))
+            .unwrap()
+        };
+        let selected_timeout = |selector: &ServiceConfigSelector| {
+            selector
+                .current()
+                .config
+                .method_config("/svc/method")
+                .and_then(|mc| mc.timeout)
+        };
+        let invalid = Err("invalid service config".to_string());
+
+        // Without a previous or default config, invalid configs are rejected.
+        let selector = ServiceConfigSelector::new(None, false);
+        assert!(selector.choose(&invalid).is_err());
+
+        // The default config is used until the resolver provides a config.
+        let selector = ServiceConfigSelector::new(Some(timeout(1)), false);
+        assert_eq!(selected_timeout(&selector), Some(Duration::from_secs(1)));
+        let throttler = selector.current().retry_throttler.clone().unwrap();
+        selector.select(selector.choose(&Ok(Some(timeout(2)))).unwrap());
+        assert_eq!(selected_timeout(&selector), Some(Duration::from_secs(2)));
+        // The throttler is kept since its policy did not change.
+        assert!(Arc::ptr_eq(
+            &throttler,
+            selector.current().retry_throttler.as_ref().unwrap()
+        ));
+
+        // Once a config was accepted, it stays in effect.
+        assert!(matches!(selector.choose(&invalid), Ok(None)));
+        selector.select(None);
+        assert_eq!(selected_timeout(&selector), Some(Duration::from_secs(2)));
+
+        // Updates without a config fall back to the default config.
+        selector.select(selector.choose(&Ok(None)).unwrap());
+        assert_eq!(selected_timeout(&selector), Some(Duration::from_secs(1)));
+
+        let selector = ServiceConfigSelector::new(Some(timeout(1)), true);
+        assert_eq!(selected_timeout(&selector), None);
+        assert!(selector.choose(&Ok(None)).is_err());
+        assert!(selector.choose(&invalid).is_err());
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct TestConfig {
+        weight: u32,
+    }
+
+    impl LbPolicyConfig for TestConfig {}
+
+    #[test]
+    fn lb_configs_are_downcast() {
+        let config = TestConfig { weight: 3 }.into_lb_config();
+        assert_eq!(config.get::<TestConfig>(), Some(&TestConfig { weight: 3 }));
+        assert_eq!(config.get::<u32>(), None);
+        assert_eq!(TestConfig::from_lb_config(Some(&config)).unwrap().weight, 3);
+
+        let err = TestConfig::from_lb_config(None).unwrap_err();
+        assert!(
+            err.to_string().starts_with("missing LB policy config"),
+            "{err}"
+        );
+        let err = TestConfig::from_lb_config(Some(&LbConfig::new(3u32))).unwrap_err();
+        assert!(err.to_string().ends_with("::TestConfig"), "{err}");
+    }
+
+    #[test]
+    fn selects_first_usable_lb_policy() {
+        crate::client::load_balancing::pick_first::reg();
+        let parse = |configs: &str| {
+            ServiceConfig::parse(&format!(r#"{{"loadBalancingConfig":{configs}}}"#))
+                .map(|config| config.lb_policy.map(|p| p.builder.name()))
+        };
+        assert_eq!(parse("[]"), Ok(None));
+        assert_eq!(
+            parse(r#"[{"unregistered":{}},{"pick_first":{}}]"#),
+            Ok(Some("pick_first"))
+        );
+        assert!(parse(r#"[{"unregistered":{}}]"#).is_err());
+        assert!(parse(r#"[{"pick_first":{},"unregistered":{}}]"#).is_err());
+        assert!(parse(r#"[{}]"#).is_err());
+    }
 }
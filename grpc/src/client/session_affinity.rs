@@ -0,0 +1,234 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! Cookie-based stateful session affinity, following gRFC A55.
+//!
+//! A channel configured with a [`SessionCookieConfig`] reads the session
+//! cookie from the "cookie" metadata of each call.  The cookie holds the
+//! base64-encoded address of the server which handled the session, which the
+//! channel stores in the request's extensions as an [`OverrideHost`].  The
+//! `override_host` LB policy routes such calls to that address while it is
+//! ready, and otherwise picks normally.
+//!
+//! When a call is routed to a different address than its cookie names, or
+//! carries no cookie, the channel adds a "set-cookie" entry to the response
+//! headers so the application can return the new cookie to its client.
+
+use std::time::Duration;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use crate::client::name_resolution::Address;
+use crate::service::{Request, Response};
+
+/// The address a call should be routed to, if it is ready.
+///
+/// Applications may also insert this directly into a request's extensions to
+/// choose the address for that request explicitly.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OverrideHost(pub String);
+
+impl OverrideHost {
+    /// Returns the override host attached to request, if any.
+    pub fn from_request(request: &Request) -> Option<&Self> {
+        request.extensions().get::<Self>()
+    }
+}
+
+/// Configures the cookie used for session affinity.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SessionCookieConfig {
+    /// The name of the cookie.
+    pub name: String,
+    /// The path attribute of cookies set by the channel, if any.
+    pub path: Option<String>,
+    /// The lifetime of cookies set by the channel.  None produces session
+    /// cookies.
+    pub ttl: Option<Duration>,
+}
+
+impl SessionCookieConfig {
+    /// Creates a config for the cookie with the given name.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            path: None,
+            ttl: None,
+        }
+    }
+    pub fn path(self, path: impl Into<String>) -> Self {
+        Self {
+            path: Some(path.into()),
+            ..self
+        }
+    }
+    pub fn ttl(self, ttl: Duration) -> Self {
+        Self {
+            ttl: Some(ttl),
+            ..self
+        }
+    }
+
+    /// Returns the host encoded in the request's session cookie, or None if
+    /// it has no valid cookie.
+    fn host(&self, request: &Request) -> Option<OverrideHost> {
+        request
+            .metadata()
+            .get_all("cookie")
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(';'))
+            .filter_map(|c| c.trim().split_once('='))
+            .find(|(name, _)| *name == self.name)
+            .and_then(|(_, value)| STANDARD.decode(value.trim_matches('"')).ok())
+            .and_then(|host| String::from_utf8(host).ok())
+            .filter(|host| !host.is_empty())
+            .map(OverrideHost)
+    }
+
+    /// Inserts the host from the session cookie into request's extensions,
+    /// unless the request already carries one, and returns the host the call
+    /// should be routed to.
+    pub(crate) fn apply_request(&self, request: &mut Request) -> Option<OverrideHost> {
+        if let Some(host) = OverrideHost::from_request(request) {
+            return Some(host.clone());
+        }
+        let host = self.host(request)?;
+        request.extensions_mut().insert(host.clone());
+        Some(host)
+    }
+
+    /// Adds a cookie naming the picked address to the response headers, if it
+    /// differs from the host the call was meant for.
+    pub(crate) fn apply_response(
+        &self,
+        host: Option<&OverrideHost>,
+        picked: &Address,
+        response: &mut Response,
+    ) {
+        if host.is_some_and(|h| h.0 == *picked.address) {
+            return;
+        }
+        let mut cookie = format!("{}={}", self.name, STANDARD.encode(&*picked.address));
+        if let Some(path) = &self.path {
+            cookie.push_str(&format!("; Path={path}"));
+        }
+        cookie.push_str("; HttpOnly");
+        if let Some(ttl) = self.ttl {
+            cookie.push_str(&format!("; Max-Age={}", ttl.as_secs()));
+        }
+        match cookie.parse() {
+            Ok(value) => {
+                response.metadata_mut().append("set-cookie", value);
+            }
+            Err(_) => eprintln!("session affinity: invalid cookie {cookie:?}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use tokio_stream::StreamExt;
+
+    use super::{OverrideHost, SessionCookieConfig};
+    use crate::client::load_balancing::test_utils::new_request;
+    use crate::client::name_resolution::{Address, TCP_IP_NETWORK_TYPE};
+    use crate::service::Response;
+
+    fn response() -> Response {
+        Response::new(Box::pin(tokio_stream::empty().map(Ok)))
+    }
+
+    #[test]
+    fn reads_the_host_from_the_cookie() {
+        let config = SessionCookieConfig::new("session");
+        let mut request = new_request();
+        request.metadata_mut().insert(
+            "cookie",
+            // "10.0.0.1:443", base64-encoded.
+            "other=x; session=MTAuMC4wLjE6NDQz".parse().unwrap(),
+        );
+        assert_eq!(
+            config.apply_request(&mut request),
+            Some(OverrideHost("10.0.0.1:443".to_string()))
+        );
+        assert_eq!(
+            OverrideHost::from_request(&request),
+            Some(&OverrideHost("10.0.0.1:443".to_string()))
+        );
+
+        // Malformed cookies are ignored.
+        let mut request = new_request();
+        request
+            .metadata_mut()
+            .insert("cookie", "session=!!!".parse().unwrap());
+        assert_eq!(config.apply_request(&mut request), None);
+
+        // A host set by the application takes precedence.
+        request
+            .metadata_mut()
+            .insert("cookie", "session=MTAuMC4wLjE6NDQz".parse().unwrap());
+        request
+            .extensions_mut()
+            .insert(OverrideHost("10.0.0.2:443".to_string()));
+        assert_eq!(
+            config.apply_request(&mut request),
+            Some(OverrideHost("10.0.0.2:443".to_string()))
+        );
+    }
+
+    #[test]
+    fn sets_a_cookie_when_the_host_changes() {
+        let config = SessionCookieConfig::new("session")
+            .path("/")
+            .ttl(Duration::from_secs(60));
+        let picked = Address::new(TCP_IP_NETWORK_TYPE, "10.0.0.1:443");
+
+        let mut r = response();
+        config.apply_response(
+            Some(&OverrideHost("10.0.0.1:443".to_string())),
+            &picked,
+            &mut r,
+        );
+        assert!(r.metadata().get("set-cookie").is_none());
+
+        let mut r = response();
+        config.apply_response(
+            Some(&OverrideHost("10.0.0.2:443".to_string())),
+            &picked,
+            &mut r,
+        );
+        assert_eq!(
+            r.metadata().get("set-cookie").unwrap(),
+            "session=MTAuMC4wLjE6NDQz; Path=/; HttpOnly; Max-Age=60"
+        );
+
+        let mut r = response();
+        config.apply_response(None, &picked, &mut r);
+        assert!(r.metadata().get("set-cookie").is_some());
+    }
+}
@@ -8,11 +8,18 @@ use super::{
 use crate::{
     client::{
         channel::WorkQueueItem,
-        subchannel,
-        transport::{ConnectedTransport, TransportOptions},
+        error::{ConnectError, ConnectErrorKind, DisconnectReason},
+        labels::{SubchannelLabels, SubchannelStatsRecorder, GLOBAL_LABEL_REGISTRY},
+        retry, subchannel,
+        transport::{ConnectedTransport, TransportInfo, TransportOptions},
+        watchdog::{ConnectAttempt, ConnectingPhase, ConnectingWatchdogMonitor},
+        work_queue::{Coalesced, WorkItemKind},
     },
+    credentials::SECURITY_CONTEXT,
+    leak_detector::LeakTracker,
     rt::{BoxedTaskHandle, Runtime},
-    service::{Request, Response, Service},
+    service::{status_response, Request, Response, Service},
+    stats::{ConnectionEvent, ConnectionInfo},
 };
 use core::panic;
 use std::time::{Duration, Instant};
@@ -55,16 +62,19 @@ enum InternalSubchannelState {
 
 struct InternalSubchannelConnectingState {
     abort_handle: Option<BoxedTaskHandle>,
+    watchdog_handle: Option<BoxedTaskHandle>,
 }
 
 struct InternalSubchannelReadyState {
     abort_handle: Option<BoxedTaskHandle>,
     svc: SharedService,
+    info: Arc<TransportInfo>,
+    address: Address,
 }
 
 struct InternalSubchannelTransientFailureState {
     task_handle: Option<BoxedTaskHandle>,
-    error: String,
+    error: Arc<ConnectError>,
 }
 
 impl InternalSubchannelState {
@@ -80,22 +90,27 @@ impl InternalSubchannelState {
             Self::Idle => SubchannelState {
                 connectivity_state: ConnectivityState::Idle,
                 last_connection_error: None,
+                transport_info: None,
+                connected_address: None,
             },
             Self::Connecting(_) => SubchannelState {
                 connectivity_state: ConnectivityState::Connecting,
                 last_connection_error: None,
+                transport_info: None,
+                connected_address: None,
             },
-            Self::Ready(_) => SubchannelState {
+            Self::Ready(st) => SubchannelState {
                 connectivity_state: ConnectivityState::Ready,
                 last_connection_error: None,
+                transport_info: Some(st.info.clone()),
+                connected_address: Some(st.address.clone()),
+            },
+            Self::TransientFailure(st) => SubchannelState {
+                connectivity_state: ConnectivityState::TransientFailure,
+                last_connection_error: Some(st.error.clone()),
+                transport_info: None,
+                connected_address: None,
             },
-            Self::TransientFailure(st) => {
-                let arc_err: Arc<dyn Error + Send + Sync> = Arc::from(Box::from(st.error.clone()));
-                SubchannelState {
-                    connectivity_state: ConnectivityState::TransientFailure,
-                    last_connection_error: Some(arc_err),
-                }
-            }
         }
     }
 }
@@ -158,6 +173,9 @@ impl Drop for InternalSubchannelState {
                 if let Some(ah) = &st.abort_handle {
                     ah.abort();
                 }
+                if let Some(wh) = &st.watchdog_handle {
+                    wh.abort();
+                }
             }
             Self::Ready(st) => {
                 if let Some(ah) = &st.abort_handle {
@@ -180,7 +198,13 @@ pub(crate) struct InternalSubchannel {
     unregister_fn: Option<Box<dyn FnOnce(SubchannelKey) + Send + Sync>>,
     state_machine_event_sender: mpsc::UnboundedSender<SubchannelStateMachineEvent>,
     inner: Mutex<InnerSubchannel>,
+    labels: SubchannelLabels,
+    stats: Arc<SubchannelStatsRecorder>,
+    connection_info: ConnectionInfo,
+    transport_options: Arc<TransportOptions>,
+    watchdog: Arc<ConnectingWatchdogMonitor>,
     runtime: Arc<dyn Runtime>,
+    _leak_tracker: LeakTracker,
 }
 
 struct InnerSubchannel {
@@ -188,54 +212,87 @@ struct InnerSubchannel {
     watchers: Vec<Arc<SubchannelStateWatcher>>, // TODO(easwars): Revisit the choice for this data structure.
     backoff_task: Option<BoxedTaskHandle>,
     disconnect_task: Option<BoxedTaskHandle>,
+    // Why the last connection was lost, reported while the subchannel is Idle
+    // or Connecting until a new connection attempt completes.
+    last_disconnect: Option<Arc<ConnectError>>,
+    // The last connectivity state reported to watchers and when it was
+    // entered, for the state change events reported to stats handlers.
+    reported_state: (ConnectivityState, Instant),
+    // When the current connection attempt started.
+    connect_started: Option<Instant>,
+}
+
+impl InnerSubchannel {
+    fn subchannel_state(&self) -> SubchannelState {
+        let mut state = self.state.to_subchannel_state();
+        if state.last_connection_error.is_none()
+            && matches!(
+                self.state,
+                InternalSubchannelState::Idle | InternalSubchannelState::Connecting(_)
+            )
+        {
+            state.last_connection_error = self.last_disconnect.clone().map(|err| err as _);
+        }
+        state
+    }
 }
 
 #[async_trait]
 impl Service for InternalSubchannel {
     async fn call(&self, method: String, request: Request) -> Response {
         let svc = self.inner.lock().unwrap().state.connected_transport();
-        if svc.is_none() {
-            // TODO(easwars): Change the signature of this method to return a
-            // Result<Response, Error>
-            panic!("todo: handle !ready");
-        }
+        let Some(svc) = svc else {
+            // The subchannel disconnected after it was picked; the channel
+            // transparently retries the call with the next picker.
+            return status_response(retry::not_sent("subchannel is not connected"));
+        };
 
-        let svc = svc.unwrap().clone();
+        let svc = svc.clone();
         return svc.call(method, request).await;
     }
 }
 
 enum SubchannelStateMachineEvent {
     ConnectionRequested,
-    ConnectionSucceeded(SharedService, oneshot::Receiver<Result<(), String>>),
+    ConnectionSucceeded(
+        SharedService,
+        oneshot::Receiver<DisconnectReason>,
+        Arc<TransportInfo>,
+    ),
     ConnectionTimedOut,
-    ConnectionFailed(String),
-    ConnectionTerminated,
+    ConnectionFailed(ConnectError),
+    ConnectionTerminated(DisconnectReason),
     BackoffExpired,
 }
 impl Debug for SubchannelStateMachineEvent {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::ConnectionRequested => write!(f, "ConnectionRequested"),
-            Self::ConnectionSucceeded(_, _) => write!(f, "ConnectionSucceeded"),
+            Self::ConnectionSucceeded(..) => write!(f, "ConnectionSucceeded"),
             Self::ConnectionTimedOut => write!(f, "ConnectionTimedOut"),
             Self::ConnectionFailed(_) => write!(f, "ConnectionFailed"),
-            Self::ConnectionTerminated => write!(f, "ConnectionTerminated"),
+            Self::ConnectionTerminated(reason) => write!(f, "ConnectionTerminated({reason})"),
             Self::BackoffExpired => write!(f, "BackoffExpired"),
         }
     }
 }
 
 impl InternalSubchannel {
+    #[allow(clippy::too_many_arguments)]
     pub(super) fn new(
         key: SubchannelKey,
         transport: Arc<dyn Transport>,
         backoff: Arc<dyn Backoff>,
         unregister_fn: Box<dyn FnOnce(SubchannelKey) + Send + Sync>,
+        stats: Arc<SubchannelStatsRecorder>,
+        transport_options: Arc<TransportOptions>,
+        watchdog: Arc<ConnectingWatchdogMonitor>,
         runtime: Arc<dyn Runtime>,
     ) -> Arc<InternalSubchannel> {
         println!("creating new internal subchannel for: {:?}", &key);
         let (tx, mut rx) = mpsc::unbounded_channel::<SubchannelStateMachineEvent>();
+        let labels = GLOBAL_LABEL_REGISTRY.labels(&key.address.attributes);
+        let connection_info = stats.connection_info(key.address.address.to_string(), &labels);
         let isc = Arc::new(Self {
             key: key.clone(),
             transport,
@@ -247,37 +304,57 @@ impl InternalSubchannel {
                 watchers: Vec::new(),
                 backoff_task: None,
                 disconnect_task: None,
+                last_disconnect: None,
+                reported_state: (ConnectivityState::Idle, Instant::now()),
+                connect_started: None,
             }),
+            labels,
+            stats,
+            connection_info,
+            transport_options,
+            watchdog,
             runtime: runtime.clone(),
+            _leak_tracker: LeakTracker::new("InternalSubchannel"),
         });
 
-        // This long running task implements the subchannel state machine. When
-        // the subchannel is dropped, the channel from which this task reads is
-        // closed, and therefore this task exits because rx.recv() returns None
-        // in that case.
-        let arc_to_self = Arc::clone(&isc);
+        // This long running task implements the subchannel state machine. It
+        // only holds a weak reference to the subchannel, as the subchannel
+        // holds the sender of the channel this task reads from.  When the
+        // subchannel is dropped, the task exits at its next event, or once all
+        // senders are gone and rx.recv() returns None.
+        let weak_self = Arc::downgrade(&isc);
         runtime.spawn(Box::pin(async move {
             println!("starting subchannel state machine for: {:?}", &key);
             while let Some(m) = rx.recv().await {
+                let Some(arc_to_self) = weak_self.upgrade() else {
+                    break;
+                };
                 println!("subchannel {:?} received event {:?}", &key, &m);
                 match m {
                     SubchannelStateMachineEvent::ConnectionRequested => {
                         arc_to_self.move_to_connecting();
                     }
-                    SubchannelStateMachineEvent::ConnectionSucceeded(svc, rx) => {
-                        arc_to_self.move_to_ready(svc, rx);
+                    SubchannelStateMachineEvent::ConnectionSucceeded(svc, rx, info) => {
+                        arc_to_self.move_to_ready(svc, rx, info);
                     }
                     SubchannelStateMachineEvent::ConnectionTimedOut => {
-                        arc_to_self.move_to_transient_failure("connect timeout expired".into());
+                        arc_to_self.move_to_transient_failure(ConnectError::new(
+                            ConnectErrorKind::Timeout,
+                            "connect timeout expired",
+                        ));
                     }
                     SubchannelStateMachineEvent::ConnectionFailed(err) => {
                         arc_to_self.move_to_transient_failure(err);
                     }
-                    SubchannelStateMachineEvent::ConnectionTerminated => {
-                        arc_to_self.move_to_idle();
+                    SubchannelStateMachineEvent::ConnectionTerminated(reason) => {
+                        arc_to_self.stats.emit(
+                            &arc_to_self.connection_info,
+                            &ConnectionEvent::Disconnected(&reason),
+                        );
+                        arc_to_self.move_to_idle(Some(ConnectError::disconnected(reason)));
                     }
                     SubchannelStateMachineEvent::BackoffExpired => {
-                        arc_to_self.move_to_idle();
+                        arc_to_self.move_to_idle(None);
                     }
                 }
             }
@@ -290,6 +367,22 @@ impl InternalSubchannel {
         self.key.address.clone()
     }
 
+    /// Returns the connectivity state last reported to watchers.
+    pub(super) fn connectivity_state(&self) -> ConnectivityState {
+        self.inner.lock().unwrap().reported_state.0
+    }
+
+    /// Returns the service for the current connection, if the subchannel is
+    /// Ready.
+    pub(crate) fn connected_service(&self) -> Option<SharedService> {
+        self.inner.lock().unwrap().state.connected_transport()
+    }
+
+    /// Returns the runtime used by the subchannel's channel.
+    pub(crate) fn runtime(&self) -> Arc<dyn Runtime> {
+        self.runtime.clone()
+    }
+
     /// Begins connecting the subchannel asynchronously.  If now is set, does
     /// not wait for any pending connection backoff to complete.
     pub(super) fn connect(&self, now: bool) {
@@ -304,8 +397,7 @@ impl InternalSubchannel {
     pub(super) fn register_connectivity_state_watcher(&self, watcher: Arc<SubchannelStateWatcher>) {
         let mut inner = self.inner.lock().unwrap();
         inner.watchers.push(watcher.clone());
-        let state = inner.state.to_subchannel_state().clone();
-        watcher.on_state_change(state);
+        watcher.on_state_change(inner.subchannel_state());
     }
 
     pub(super) fn unregister_connectivity_state_watcher(
@@ -322,47 +414,116 @@ impl InternalSubchannel {
     fn notify_watchers(&self, state: SubchannelState) {
         let mut inner = self.inner.lock().unwrap();
         inner.state = InternalSubchannelState::Idle;
+        let (from, entered) = std::mem::replace(
+            &mut inner.reported_state,
+            (state.connectivity_state, Instant::now()),
+        );
         for w in &inner.watchers {
             w.on_state_change(state.clone());
         }
+        drop(inner);
+        if from != state.connectivity_state {
+            self.stats.emit(
+                &self.connection_info,
+                &ConnectionEvent::StateChange {
+                    from,
+                    to: state.connectivity_state,
+                    time_in_state: entered.elapsed(),
+                },
+            );
+        }
+    }
+
+    /// Reports the end of the current connection attempt, with error if it
+    /// failed.
+    fn report_connect_end(&self, error: Option<&ConnectError>) {
+        let started = self.inner.lock().unwrap().connect_started.take();
+        if let Some(started) = started {
+            self.stats.emit(
+                &self.connection_info,
+                &ConnectionEvent::ConnectEnd {
+                    error,
+                    duration: started.elapsed(),
+                },
+            );
+        }
     }
 
-    fn move_to_idle(&self) {
+    fn move_to_idle(&self, disconnect: Option<ConnectError>) {
+        let disconnect = disconnect.map(Arc::new);
+        self.inner.lock().unwrap().last_disconnect = disconnect.clone();
         self.notify_watchers(SubchannelState {
             connectivity_state: ConnectivityState::Idle,
-            last_connection_error: None,
+            last_connection_error: disconnect.map(|err| err as _),
+            transport_info: None,
+            connected_address: None,
         });
     }
 
     fn move_to_connecting(&self) {
-        {
+        let last_disconnect = {
             let mut inner = self.inner.lock().unwrap();
+            // Requests queued while a previous one was being handled do not
+            // start another attempt.
+            if !matches!(inner.state, InternalSubchannelState::Idle) {
+                return;
+            }
             inner.state = InternalSubchannelState::Connecting(InternalSubchannelConnectingState {
                 abort_handle: None,
+                watchdog_handle: None,
             });
-        }
+            inner.connect_started = Some(Instant::now());
+            inner.last_disconnect.clone()
+        };
+        self.stats.record_attempt(&self.labels);
+        self.stats
+            .emit(&self.connection_info, &ConnectionEvent::ConnectStart);
         self.notify_watchers(SubchannelState {
             connectivity_state: ConnectivityState::Connecting,
-            last_connection_error: None,
+            last_connection_error: last_disconnect.map(|err| err as _),
+            transport_info: None,
+            connected_address: None,
         });
 
         let min_connect_timeout = self.backoff.min_connect_timeout();
         let transport = self.transport.clone();
-        let address = self.address().address;
+        let address = self.address();
         let state_machine_tx = self.state_machine_event_sender.clone();
-        // TODO: All these options to be configured by users.
-        let transport_opts = TransportOptions::default();
+        let transport_opts = self.transport_options.clone();
         let runtime = self.runtime.clone();
+        let phase = Arc::new(Mutex::new(ConnectingPhase::Scheduled));
+
+        let watchdog_tx = self.state_machine_event_sender.clone();
+        let watchdog_handle = self.watchdog.watch(
+            &self.runtime,
+            ConnectAttempt {
+                address: address.address.to_string(),
+                network_type: self.key.address.network_type,
+                connect_timeout: min_connect_timeout,
+                phase: phase.clone(),
+            },
+            move || {
+                let _ = watchdog_tx.send(SubchannelStateMachineEvent::ConnectionFailed(
+                    ConnectError::new(
+                        ConnectErrorKind::Timeout,
+                        "reset by the connecting watchdog",
+                    ),
+                ));
+            },
+        );
 
         let connect_task = self.runtime.spawn(Box::pin(async move {
+            *phase.lock().unwrap() = ConnectingPhase::Connecting;
             tokio::select! {
                 _ = runtime.sleep(min_connect_timeout) => {
+                    *phase.lock().unwrap() = ConnectingPhase::Completing;
                     let _ = state_machine_tx.send(SubchannelStateMachineEvent::ConnectionTimedOut);
                 }
-                result = transport.connect(address.to_string().clone(), runtime, &transport_opts) => {
+                result = transport.connect(&address, runtime, &transport_opts) => {
+                    *phase.lock().unwrap() = ConnectingPhase::Completing;
                     match result {
                         Ok(s) => {
-                            let _ = state_machine_tx.send(SubchannelStateMachineEvent::ConnectionSucceeded(Arc::from(s.service), s.disconnection_listener));
+                            let _ = state_machine_tx.send(SubchannelStateMachineEvent::ConnectionSucceeded(Arc::from(s.service), s.disconnection_listener, Arc::new(s.info)));
                         }
                         Err(e) => {
                             let _ = state_machine_tx.send(SubchannelStateMachineEvent::ConnectionFailed(e));
@@ -374,44 +535,60 @@ impl InternalSubchannel {
         let mut inner = self.inner.lock().unwrap();
         inner.state = InternalSubchannelState::Connecting(InternalSubchannelConnectingState {
             abort_handle: Some(connect_task),
+            watchdog_handle,
         });
     }
 
-    fn move_to_ready(&self, svc: SharedService, closed_rx: oneshot::Receiver<Result<(), String>>) {
+    fn move_to_ready(
+        &self,
+        svc: SharedService,
+        closed_rx: oneshot::Receiver<DisconnectReason>,
+        info: Arc<TransportInfo>,
+    ) {
         let svc2 = svc.clone();
         {
             let mut inner = self.inner.lock().unwrap();
+            inner.last_disconnect = None;
             inner.state = InternalSubchannelState::Ready(InternalSubchannelReadyState {
                 abort_handle: None,
                 svc: svc2.clone(),
+                info: info.clone(),
+                address: self.key.address.clone(),
             });
         }
+        self.stats.record_success(&self.labels);
+        self.report_connect_end(None);
         self.notify_watchers(SubchannelState {
             connectivity_state: ConnectivityState::Ready,
             last_connection_error: None,
+            transport_info: Some(info.clone()),
+            connected_address: Some(self.key.address.clone()),
         });
 
         let state_machine_tx = self.state_machine_event_sender.clone();
+        let address = self.key.address.address.to_string();
         let task_handle = self.runtime.spawn(Box::pin(async move {
-            // TODO(easwars): Does it make sense for disconnected() to return an
-            // error string containing information about why the connection
-            // terminated? But what can we do with that error other than logging
-            // it, which the transport can do as well?
-            if let Err(e) = closed_rx.await {
-                eprintln!("Transport closed with error: {e}",)
-            };
-            let _ = state_machine_tx.send(SubchannelStateMachineEvent::ConnectionTerminated);
+            let reason = closed_rx.await.unwrap_or(DisconnectReason::Closed);
+            if reason != DisconnectReason::Closed {
+                eprintln!("warning: connection to {address} lost: {reason}");
+            }
+            let _ =
+                state_machine_tx.send(SubchannelStateMachineEvent::ConnectionTerminated(reason));
         }));
         let mut inner = self.inner.lock().unwrap();
         inner.state = InternalSubchannelState::Ready(InternalSubchannelReadyState {
             abort_handle: Some(task_handle),
             svc: svc2.clone(),
+            info,
+            address: self.key.address.clone(),
         });
     }
 
-    fn move_to_transient_failure(&self, err: String) {
+    fn move_to_transient_failure(&self, err: ConnectError) {
+        let err = Arc::new(err);
         {
             let mut inner = self.inner.lock().unwrap();
+            inner.last_disconnect = None;
             inner.state = InternalSubchannelState::TransientFailure(
                 InternalSubchannelTransientFailureState {
                     task_handle: None,
@@ -419,11 +596,14 @@ impl InternalSubchannel {
                 },
             );
         }
+        self.stats.record_failure(&self.labels);
+        self.report_connect_end(Some(&err));
 
-        let arc_err: Arc<dyn Error + Send + Sync> = Arc::from(Box::from(err.clone()));
         self.notify_watchers(SubchannelState {
             connectivity_state: ConnectivityState::TransientFailure,
-            last_connection_error: Some(arc_err.clone()),
+            last_connection_error: Some(err.clone()),
+            transport_info: None,
+            connected_address: None,
         });
 
         let backoff_interval = self.backoff.backoff_until();
@@ -456,16 +636,51 @@ impl Drop for InternalSubchannel {
     }
 }
 
-// SubchannelKey uniiquely identifies a subchannel in the pool.
-#[derive(PartialEq, PartialOrd, Eq, Ord, Clone)]
-
+// SubchannelKey uniquely identifies a subchannel in the pool.  Addresses are
+// partitioned by their security context so that connections are never shared
+// across incompatible contexts; other address attributes do not affect the
+// key.
+#[derive(Clone)]
 pub(crate) struct SubchannelKey {
     address: Address,
+    security_context: Option<String>,
 }
 
 impl SubchannelKey {
     pub(crate) fn new(address: Address) -> Self {
-        Self { address }
+        let security_context = address.attributes.get(&SECURITY_CONTEXT).cloned();
+        Self {
+            address,
+            security_context,
+        }
+    }
+
+    fn partition(&self) -> (&Option<String>, &'static str, &str) {
+        (
+            &self.security_context,
+            self.address.network_type,
+            &self.address.address,
+        )
+    }
+}
+
+impl PartialEq for SubchannelKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.partition() == other.partition()
+    }
+}
+
+impl Eq for SubchannelKey {}
+
+impl PartialOrd for SubchannelKey {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SubchannelKey {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.partition().cmp(&other.partition())
     }
 }
 
@@ -503,6 +718,17 @@ impl InternalSubchannelPool {
         None
     }
 
+    /// Returns the subchannels in the pool which are still alive, ordered by
+    /// key.
+    pub(super) fn subchannels(&self) -> Vec<Arc<InternalSubchannel>> {
+        self.subchannels
+            .read()
+            .unwrap()
+            .values()
+            .filter_map(Weak::upgrade)
+            .collect()
+    }
+
     pub(super) fn register_subchannel(
         &self,
         key: &SubchannelKey,
@@ -534,13 +760,24 @@ impl InternalSubchannelPool {
 pub(super) struct SubchannelStateWatcher {
     subchannel: Weak<ExternalSubchannel>,
     work_scheduler: WorkQueueTx,
+    // The generation of the LB policy which created the subchannel.
+    generation: u64,
+    // The state not yet delivered to the LB policy.  At most one update is
+    // queued at a time, and delivers the latest state when it runs.
+    pending: Arc<Coalesced<SubchannelState>>,
 }
 
 impl SubchannelStateWatcher {
-    pub(super) fn new(sc: Arc<ExternalSubchannel>, work_scheduler: WorkQueueTx) -> Self {
+    pub(super) fn new(
+        sc: Arc<ExternalSubchannel>,
+        work_scheduler: WorkQueueTx,
+        generation: u64,
+    ) -> Self {
         Self {
             subchannel: Arc::downgrade(&sc),
             work_scheduler,
+            generation,
+            pending: Arc::new(Coalesced::new()),
         }
     }
 
@@ -548,18 +785,72 @@ impl SubchannelStateWatcher {
         // Ignore internal subchannel state changes if the external subchannel
         // was dropped but its state watcher is still pending unregistration;
         // such updates are inconsequential.
-        if let Some(sc) = self.subchannel.upgrade() {
-            let _ = self.work_scheduler.send(WorkQueueItem::Closure(Box::new(
-                move |c: &mut InternalChannelController| {
-                    c.lb.clone()
-                        .policy
-                        .lock()
-                        .unwrap()
-                        .as_mut()
-                        .unwrap()
-                        .subchannel_update(sc, &state, c);
-                },
-            )));
+        let Some(sc) = self.subchannel.upgrade() else {
+            return;
+        };
+        if !self.pending.put(state) {
+            return;
+        }
+        let pending = self.pending.clone();
+        let generation = self.generation;
+        let _ = self.work_scheduler.send(WorkQueueItem::Closure(
+            WorkItemKind::SubchannelUpdate,
+            Box::new(move |c: &mut InternalChannelController| {
+                if let Some(state) = pending.take() {
+                    c.lb.clone().subchannel_update(generation, sc, &state, c);
+                }
+            }),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use super::SubchannelKey;
+    use crate::{
+        attributes::AttributeKey,
+        client::name_resolution::{Address, TCP_IP_NETWORK_TYPE},
+        credentials::SECURITY_CONTEXT,
+    };
+
+    const OTHER: AttributeKey<u32> = AttributeKey::new("test.other");
+
+    fn address(context: Option<&str>) -> Address {
+        let addr = Address::new(TCP_IP_NETWORK_TYPE, "127.0.0.1:8080");
+        match context {
+            Some(context) => addr.with_attr(&SECURITY_CONTEXT, context.to_string()),
+            None => addr,
+        }
+    }
+
+    #[test]
+    fn keys_are_partitioned_by_security_context() {
+        let plain = SubchannelKey::new(address(None));
+        let cert_a = SubchannelKey::new(address(Some("cert-a")));
+        let cert_b = SubchannelKey::new(address(Some("cert-b")));
+        assert_ne!(plain, cert_a);
+        assert_ne!(cert_a, cert_b);
+
+        // Equal contexts share a key even if set independently or alongside
+        // unrelated attributes.
+        assert!(SubchannelKey::new(address(Some("cert-a"))) == cert_a);
+        assert!(SubchannelKey::new(address(Some("cert-a")).with_attr(&OTHER, 1)) == cert_a);
+        assert!(SubchannelKey::new(address(None).with_attr(&OTHER, 1)) == plain);
+    }
+
+    #[test]
+    fn mixed_security_contexts_use_distinct_pool_entries() {
+        let mut pool = BTreeMap::new();
+        for (i, context) in [None, Some("cert-a"), Some("cert-b"), Some("cert-a"), None]
+            .into_iter()
+            .enumerate()
+        {
+            pool.entry(SubchannelKey::new(address(context)))
+                .or_insert(i);
         }
+        let entries: Vec<_> = pool.values().copied().collect();
+        assert_eq!(entries, vec![0, 1, 2]);
     }
 }
@@ -0,0 +1,229 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! Driving clients generated by tonic-build over a [`Channel`].
+//!
+//! [`Channel`] implements [`tower_service::Service`] for HTTP requests, and so
+//! [`tonic::client::GrpcService`], so that a generated tonic client can be
+//! created with a channel, e.g. `EchoClient::new(channel)`.  The client's calls
+//! then use the channel's name resolution and load balancing.
+
+use std::{
+    any::Any,
+    convert::Infallible,
+    future::Future,
+    pin::Pin,
+    sync::Mutex,
+    task::{ready, Context, Poll},
+};
+
+use bytes::Bytes;
+use tokio_stream::{Stream, StreamExt};
+use tonic::{body::Body, server::Grpc, Request, Response, Status, Streaming};
+use tower_service::Service as TowerService;
+
+use super::Channel;
+use crate::codec::BytesCodec;
+use crate::service::Message;
+
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+type BoxStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
+
+impl TowerService<http::Request<Body>> for Channel {
+    type Response = http::Response<Body>;
+    type Error = Infallible;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+        let call = ChannelCall {
+            channel: self.clone(),
+            method: request.uri().path().to_string(),
+        };
+        Box::pin(async move { Ok(Grpc::new(BytesCodec {}).streaming(call, request).await) })
+    }
+}
+
+// Performs one call on a channel, with messages decoded from and encoded into
+// HTTP bodies by tonic.
+struct ChannelCall {
+    channel: Channel,
+    method: String,
+}
+
+impl TowerService<Request<Streaming<Bytes>>> for ChannelCall {
+    type Response = Response<BoxStream<Bytes>>;
+    type Error = Status;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request<Streaming<Bytes>>) -> Self::Future {
+        let channel = self.channel.clone();
+        let method = std::mem::take(&mut self.method);
+        Box::pin(async move {
+            let request = request.map(|stream| Box::pin(RequestStream(Mutex::new(stream))) as _);
+            let response = channel.call(method, request).await;
+            Ok(response.map(|stream| {
+                Box::pin(stream.map(|msg| {
+                    (msg? as Box<dyn Any>)
+                        .downcast::<Bytes>()
+                        .map(|bytes| *bytes)
+                        .map_err(|_| Status::internal("message is not encoded"))
+                })) as BoxStream<Bytes>
+            }))
+        })
+    }
+}
+
+// Adapts the messages of a tonic request to a request stream.  Streaming is not
+// Sync, as request streams must be, but is only ever accessed mutably, so the
+// mutex is never locked.
+struct RequestStream(Mutex<Streaming<Bytes>>);
+
+impl Stream for RequestStream {
+    type Item = Box<dyn Message>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let stream = self.0.get_mut().unwrap();
+        match ready!(Pin::new(stream).poll_next(cx)) {
+            Some(Ok(bytes)) => Poll::Ready(Some(Box::new(bytes))),
+            // Request streams cannot fail; end the stream instead.
+            // TODO: cancel the call once cancellation is supported.
+            Some(Err(_)) | None => Poll::Ready(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::pin::Pin;
+
+    use tokio_stream::{Stream, StreamExt};
+    use tonic::{async_trait, Code, Request, Response, Status, Streaming};
+
+    use crate::client::{Channel, ChannelOptions};
+    use crate::echo_pb::echo_client::EchoClient;
+    use crate::echo_pb::echo_server::{Echo, EchoServer};
+    use crate::echo_pb::{EchoRequest, EchoResponse};
+    use crate::inmemory;
+    use crate::server::{Server, TonicAdapter};
+
+    type EchoStream = Pin<Box<dyn Stream<Item = Result<EchoResponse, Status>> + Send>>;
+
+    struct EchoService {}
+
+    #[async_trait]
+    impl Echo for EchoService {
+        async fn unary_echo(
+            &self,
+            request: Request<EchoRequest>,
+        ) -> Result<Response<EchoResponse>, Status> {
+            let message = request.into_inner().message;
+            if message.is_empty() {
+                return Err(Status::invalid_argument("empty message"));
+            }
+            Ok(Response::new(EchoResponse { message }))
+        }
+
+        type ServerStreamingEchoStream = EchoStream;
+
+        async fn server_streaming_echo(
+            &self,
+            _: Request<EchoRequest>,
+        ) -> Result<Response<EchoStream>, Status> {
+            Err(Status::unimplemented("not implemented"))
+        }
+
+        async fn client_streaming_echo(
+            &self,
+            _: Request<Streaming<EchoRequest>>,
+        ) -> Result<Response<EchoResponse>, Status> {
+            Err(Status::unimplemented("not implemented"))
+        }
+
+        type BidirectionalStreamingEchoStream = EchoStream;
+
+        async fn bidirectional_streaming_echo(
+            &self,
+            request: Request<Streaming<EchoRequest>>,
+        ) -> Result<Response<EchoStream>, Status> {
+            let stream = request.into_inner().map(|req| {
+                req.map(|req| EchoResponse {
+                    message: req.message,
+                })
+            });
+            Ok(Response::new(Box::pin(stream)))
+        }
+    }
+
+    #[tokio::test]
+    async fn tonic_client_over_channel() {
+        inmemory::reg();
+        let lis = inmemory::Listener::new();
+        let mut srv = Server::new();
+        srv.set_handler(TonicAdapter::new(EchoServer::new(EchoService {})));
+        let lis_clone = lis.clone();
+        tokio::task::spawn(async move {
+            srv.serve(&lis_clone).await;
+        });
+        let chan = Channel::new(lis.target().as_str(), None, ChannelOptions::default());
+        let mut client = EchoClient::new(chan);
+
+        let res = client
+            .unary_echo(EchoRequest {
+                message: "hello".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(res.into_inner().message, "hello");
+
+        let status = client
+            .unary_echo(EchoRequest {
+                message: String::new(),
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert_eq!(status.message(), "empty message");
+
+        let reqs = tokio_stream::iter(["x", "y"].map(|message| EchoRequest {
+            message: message.to_string(),
+        }));
+        let res = client.bidirectional_streaming_echo(reqs).await.unwrap();
+        let messages: Vec<_> = res
+            .into_inner()
+            .map(|res| res.unwrap().message)
+            .collect()
+            .await;
+        assert_eq!(messages, ["x", "y"]);
+
+        lis.close().await;
+    }
+}
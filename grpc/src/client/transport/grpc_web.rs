@@ -0,0 +1,399 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! A transport carrying gRPC-Web over HTTP/1.1 connections, for networks
+//! where HTTP/2 is unavailable, e.g. behind some proxies and serverless
+//! platforms.
+//!
+//! The transport handles addresses of the [`GRPC_WEB_NETWORK_TYPE`].  Channels
+//! created with [`ChannelOptions::grpc_web`](crate::client::ChannelOptions)
+//! set replace the TCP addresses produced by their resolver with addresses of
+//! that type.  HTTP/1.1 carries one request at a time, so calls on a
+//! connection are sent one after another, and servers may not respond to a
+//! request until its body ends, which rules out bidirectional streaming.
+
+use std::{
+    error::Error,
+    future::Future,
+    net::SocketAddr,
+    pin::Pin,
+    str::FromStr,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Instant,
+};
+
+use http::{header::HOST, uri::PathAndQuery, HeaderValue, Uri};
+use hyper::{
+    body::Incoming,
+    client::conn::http1::{Builder, SendRequest},
+};
+use tokio::sync::oneshot;
+use tonic::{
+    async_trait,
+    body::Body,
+    client::{Grpc, GrpcService},
+    Status,
+};
+use tonic_web::{GrpcWebCall, GrpcWebClientLayer};
+use tower::{
+    buffer::{future::ResponseFuture, Buffer},
+    util::BoxService,
+    ServiceBuilder,
+};
+use tower_service::Service as TowerService;
+
+use crate::{
+    client::{
+        error::{ConnectError, ConnectErrorKind, DisconnectReason},
+        name_resolution::{Address, ResolverUpdate, TCP_IP_NETWORK_TYPE},
+        transport::{
+            registry::GLOBAL_TRANSPORT_REGISTRY, ConnectedTransport, SecurityLevel, Transport,
+            TransportInfo, TransportOptions,
+        },
+    },
+    codec::{convert_request, convert_response, BytesCodec},
+    rt::{hyper_wrapper::HyperStream, BoxedTaskHandle, Runtime, TcpOptions},
+    service::{status_response, Request as GrpcRequest, Response as GrpcResponse, Service},
+};
+
+type BoxError = Box<dyn Error + Send + Sync>;
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+const DEFAULT_BUFFER_SIZE: usize = 1024;
+
+/// Indicates the address is an IPv4 or IPv6 socket address that should be
+/// connected to via TCP, speaking gRPC-Web over HTTP/1.1.
+pub const GRPC_WEB_NETWORK_TYPE: &str = "grpc-web";
+
+/// Registers the gRPC-Web transport for addresses of the
+/// [`GRPC_WEB_NETWORK_TYPE`].
+///
+/// It must be called only at application startup, before any channels are
+/// created.
+pub fn reg() {
+    GLOBAL_TRANSPORT_REGISTRY.add_transport(GRPC_WEB_NETWORK_TYPE, GrpcWebTransport {});
+}
+
+/// Replaces the TCP addresses of the update with gRPC-Web addresses of the
+/// same hosts.
+pub(crate) fn use_grpc_web(update: &mut ResolverUpdate) {
+    let Ok(endpoints) = &mut update.endpoints else {
+        return;
+    };
+    for address in endpoints.iter_mut().flat_map(|e| e.addresses.iter_mut()) {
+        if address.network_type == TCP_IP_NETWORK_TYPE {
+            address.network_type = GRPC_WEB_NETWORK_TYPE;
+        }
+    }
+}
+
+struct GrpcWebTransport {}
+
+#[async_trait]
+impl Transport for GrpcWebTransport {
+    async fn connect(
+        &self,
+        address: &Address,
+        runtime: Arc<dyn Runtime>,
+        opts: &TransportOptions,
+    ) -> Result<ConnectedTransport, ConnectError> {
+        let address = address.address.to_string();
+        let addr = SocketAddr::from_str(&address).map_err(|err| {
+            ConnectError::new(ConnectErrorKind::InvalidAddress, address.clone()).with_source(err)
+        })?;
+        let tcp_stream_fut = runtime.tcp_stream(
+            addr,
+            TcpOptions {
+                enable_nodelay: opts.tcp_nodelay,
+                keepalive: opts.tcp_keepalive,
+            },
+        );
+        let tcp_stream = if let Some(deadline) = opts.connect_deadline {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            tokio::select! {
+                _ = runtime.sleep(timeout) => {
+                    return Err(ConnectError::new(
+                        ConnectErrorKind::Timeout,
+                        "timed out waiting for TCP stream to connect",
+                    ))
+                }
+                tcp_stream = tcp_stream_fut => tcp_stream,
+            }
+        } else {
+            tcp_stream_fut.await
+        }
+        .map_err(|err| ConnectError::new(ConnectErrorKind::Refused, err))?;
+
+        let (sender, connection) = Builder::new()
+            .handshake(HyperStream::new(tcp_stream))
+            .await
+            .map_err(|err| {
+                ConnectError::new(ConnectErrorKind::Handshake, "HTTP/1.1 handshake failed")
+                    .with_source(err)
+            })?;
+        let (tx, rx) = oneshot::channel();
+        let task_handle = runtime.spawn(Box::pin(async move {
+            let reason = match connection.await {
+                Ok(()) => DisconnectReason::Closed,
+                Err(err) => DisconnectReason::Reset(err.to_string()),
+            };
+            let _ = tx.send(reason);
+        }));
+
+        let host = HeaderValue::from_str(&address).map_err(|err| {
+            ConnectError::new(ConnectErrorKind::InvalidAddress, address.clone()).with_source(err)
+        })?;
+        let service = ServiceBuilder::new()
+            .map_response(|response: http::Response<GrpcWebCall<Incoming>>| response.map(Body::new))
+            .layer(GrpcWebClientLayer::new())
+            .service(Http1Service { sender, host });
+        let (service, worker) = Buffer::pair(BoxService::new(service), DEFAULT_BUFFER_SIZE);
+        runtime.spawn(Box::pin(worker));
+        let uri = Uri::from_maybe_shared(format!("http://{address}")).map_err(|err| {
+            ConnectError::new(ConnectErrorKind::InvalidAddress, address.clone()).with_source(err)
+        })?;
+        let grpc = Grpc::with_origin(BufferedService { inner: service }, uri);
+
+        Ok(ConnectedTransport {
+            service: Box::new(GrpcWebConnection { grpc, task_handle }),
+            disconnection_listener: rx,
+            // TODO: report the security level once TLS is supported.
+            info: TransportInfo::new("http/1.1", SecurityLevel::NoSecurity, addr.to_string()),
+        })
+    }
+}
+
+struct GrpcWebConnection {
+    grpc: Grpc<BufferedService>,
+    task_handle: BoxedTaskHandle,
+}
+
+impl Drop for GrpcWebConnection {
+    fn drop(&mut self) {
+        self.task_handle.abort();
+    }
+}
+
+#[async_trait]
+impl Service for GrpcWebConnection {
+    async fn call(&self, method: String, request: GrpcRequest) -> GrpcResponse {
+        let Ok(path) = PathAndQuery::from_maybe_shared(method) else {
+            return status_response(Status::internal("Failed to parse path"));
+        };
+        let mut grpc = self.grpc.clone();
+        if let Err(e) = grpc.ready().await {
+            return status_response(Status::unknown(format!("Service was not ready: {e}")));
+        };
+        let request = convert_request(request);
+        let response = grpc.streaming(request, path, BytesCodec {}).await;
+        convert_response(response)
+    }
+}
+
+// Implements GrpcService for the buffered service, whose future is otherwise
+// too general for the compiler to prove the calls of the connection Send.
+#[derive(Clone)]
+struct BufferedService {
+    inner: Buffer<http::Request<Body>, BoxFuture<Result<http::Response<Body>, BoxError>>>,
+}
+
+impl GrpcService<Body> for BufferedService {
+    type ResponseBody = Body;
+    type Error = BoxError;
+    type Future = ResponseFuture<BoxFuture<Result<http::Response<Body>, BoxError>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        TowerService::poll_ready(&mut self.inner, cx)
+    }
+
+    fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+        TowerService::call(&mut self.inner, request)
+    }
+}
+
+// Sends HTTP requests on an HTTP/1.1 connection.  Unlike HTTP/2, HTTP/1.1
+// requires a Host header.
+struct Http1Service {
+    sender: SendRequest<GrpcWebCall<Body>>,
+    host: HeaderValue,
+}
+
+impl TowerService<http::Request<GrpcWebCall<Body>>> for Http1Service {
+    type Response = http::Response<Incoming>;
+    type Error = BoxError;
+    type Future = BoxFuture<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.sender.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, mut request: http::Request<GrpcWebCall<Body>>) -> Self::Future {
+        request
+            .headers_mut()
+            .entry(HOST)
+            .or_insert_with(|| self.host.clone());
+        let fut = self.sender.send_request(request);
+        Box::pin(async move { fut.await.map_err(Into::into) })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        convert::Infallible,
+        pin::Pin,
+        sync::Arc,
+        task::{Context, Poll},
+    };
+
+    use bytes::{BufMut, Bytes, BytesMut};
+    use http_body::{Body as HttpBody, Frame};
+    use hyper::{body::Incoming, server::conn::http1, service::service_fn};
+    use tokio::net::TcpListener;
+    use tokio_stream::StreamExt;
+
+    use super::{use_grpc_web, GrpcWebTransport, GRPC_WEB_NETWORK_TYPE};
+    use crate::{
+        client::{
+            name_resolution::{Address, Endpoint, ResolverUpdate},
+            transport::{SecurityLevel, Transport, TransportOptions},
+        },
+        rt::{hyper_wrapper::HyperStream, tokio::TokioRuntime},
+        service::{Message, Request},
+    };
+
+    // A response body made of a single data frame.
+    struct Full(Option<Bytes>);
+
+    impl HttpBody for Full {
+        type Data = Bytes;
+        type Error = Infallible;
+
+        fn poll_frame(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+        ) -> Poll<Option<Result<Frame<Bytes>, Infallible>>> {
+            Poll::Ready(self.0.take().map(|data| Ok(Frame::data(data))))
+        }
+    }
+
+    // Serves gRPC-Web requests over HTTP/1.1 by echoing the messages of their
+    // bodies, followed by a trailers frame with an OK status.
+    async fn echo_server() -> std::net::SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let service = service_fn(|request: http::Request<Incoming>| async move {
+                    assert_eq!(request.version(), http::Version::HTTP_11);
+                    assert_eq!(request.headers()["content-type"], "application/grpc-web");
+                    assert!(request.headers().contains_key("host"));
+                    let mut body = request.into_body();
+                    let mut data = BytesMut::new();
+                    while let Some(frame) =
+                        std::future::poll_fn(|cx| Pin::new(&mut body).poll_frame(cx)).await
+                    {
+                        if let Ok(chunk) = frame.unwrap().into_data() {
+                            data.put(chunk);
+                        }
+                    }
+                    let trailers = b"grpc-status:0\r\n";
+                    data.put_u8(0x80);
+                    data.put_u32(trailers.len() as u32);
+                    data.put_slice(trailers);
+                    let response = http::Response::builder()
+                        .header("content-type", "application/grpc-web+proto")
+                        .body(Full(Some(data.freeze())))
+                        .unwrap();
+                    Ok::<_, Infallible>(response)
+                });
+                tokio::spawn(
+                    http1::Builder::new()
+                        .serve_connection(HyperStream::new(Box::new(stream)), service),
+                );
+            }
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn grpc_web_transport_rpc() {
+        let addr = echo_server().await;
+        let connected = GrpcWebTransport {}
+            .connect(
+                &Address::new(GRPC_WEB_NETWORK_TYPE, addr.to_string()),
+                Arc::new(TokioRuntime {}),
+                &TransportOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(connected.info.protocol(), "http/1.1");
+        assert_eq!(connected.info.security_level(), SecurityLevel::NoSecurity);
+
+        // Calls on the connection are sent one after another.
+        for _ in 0..2 {
+            let messages: Vec<Box<dyn Message>> = vec![
+                Box::new(Bytes::from_static(b"hello")),
+                Box::new(Bytes::from_static(b"world")),
+            ];
+            let request = Request::new(Box::pin(tokio_stream::iter(messages)));
+            let mut response = connected
+                .service
+                .call("/svc/method".to_string(), request)
+                .await
+                .into_inner();
+            for want in ["hello", "world"] {
+                let msg = response.next().await.unwrap().unwrap();
+                let msg = (msg as Box<dyn std::any::Any>).downcast::<Bytes>().unwrap();
+                assert_eq!(*msg, want);
+            }
+            assert!(response.next().await.is_none());
+        }
+    }
+
+    #[test]
+    fn replaces_tcp_addresses() {
+        let endpoint = Endpoint::builder()
+            .addresses([
+                Address::new("tcp", "10.0.0.1:443"),
+                Address::new("other", "10.0.0.2:443"),
+            ])
+            .build()
+            .unwrap();
+        let mut update = ResolverUpdate::builder().endpoints([endpoint]).build();
+        use_grpc_web(&mut update);
+        let addresses = &update.endpoints.unwrap()[0].addresses;
+        assert_eq!(
+            addresses
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                format!("{GRPC_WEB_NETWORK_TYPE}:10.0.0.1:443"),
+                "other:10.0.0.2:443".to_string(),
+            ]
+        );
+    }
+}
@@ -1,22 +1,114 @@
+use crate::attributes::{AttributeKey, Attributes};
+use crate::client::error::{ConnectError, DisconnectReason};
+use crate::client::name_resolution::Address;
+use crate::compression::MessageCompression;
+use crate::http2::Http2Options;
 use crate::{rt::Runtime, service::Service};
 use std::time::Instant;
 use std::{sync::Arc, time::Duration};
 
+#[cfg(feature = "grpc-web")]
+pub(crate) mod grpc_web;
+#[cfg(feature = "quic")]
+pub(crate) mod quic;
 mod registry;
 
 // Using tower/buffer enables tokio's rt feature even though it's possible to
 // create Buffers with a user provided executor.
 #[cfg(feature = "_runtime-tokio")]
-mod tonic;
+pub(crate) mod tonic;
 
 use ::tonic::async_trait;
-pub(crate) use registry::TransportRegistry;
 pub(crate) use registry::GLOBAL_TRANSPORT_REGISTRY;
+pub(crate) use registry::{RegisteredTransport, TransportRegistry};
 use tokio::sync::oneshot;
 
 pub(crate) struct ConnectedTransport {
     pub service: Box<dyn Service>,
-    pub disconnection_listener: oneshot::Receiver<Result<(), String>>,
+    /// Receives why the connection was closed.  Dropping the sender is
+    /// equivalent to sending [`DisconnectReason::Closed`].
+    pub disconnection_listener: oneshot::Receiver<DisconnectReason>,
+    pub info: TransportInfo,
+}
+
+/// The level of security provided by a connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[non_exhaustive]
+pub enum SecurityLevel {
+    /// The connection is insecure (e.g. plaintext).
+    NoSecurity,
+    /// The connection provides integrity but not privacy.
+    IntegrityOnly,
+    /// The connection provides both privacy and integrity (e.g. TLS).
+    PrivacyAndIntegrity,
+}
+
+/// The HTTP/2 settings a connection was established with, added to the
+/// attributes of its [`TransportInfo`] by HTTP/2 transports.
+pub const HTTP2_SETTINGS: AttributeKey<Http2Options> =
+    AttributeKey::new("grpc.transport.http2_settings");
+
+/// Set by resolvers on addresses whose servers must be verified under a name
+/// other than the one the transport would otherwise use, e.g. the host of a
+/// backend reached through an IP address.
+pub const SERVER_NAME: AttributeKey<String> = AttributeKey::new("grpc.transport.server_name");
+
+/// Set by the channel on every address it passes to its LB policy: whether a
+/// transport is registered for the address's network type.  Endpoints may mix
+/// addresses of several types; connecting to an address without a transport
+/// fails, so policies may skip them.
+pub const TRANSPORT_SUPPORTED: AttributeKey<bool> = AttributeKey::new("grpc.transport.supported");
+
+/// Metadata describing an established connection, provided by the transport
+/// once it is connected.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct TransportInfo {
+    protocol: &'static str,
+    security_level: SecurityLevel,
+    remote_address: String,
+    attributes: Attributes,
+}
+
+impl TransportInfo {
+    pub(crate) fn new(
+        protocol: &'static str,
+        security_level: SecurityLevel,
+        remote_address: String,
+    ) -> Self {
+        Self {
+            protocol,
+            security_level,
+            remote_address,
+            attributes: Attributes::default(),
+        }
+    }
+
+    /// Returns this info with the transport specific attributes of the
+    /// connection, e.g. [`HTTP2_SETTINGS`] or the details of its TLS session.
+    pub(crate) fn with_attributes(self, attributes: Attributes) -> Self {
+        Self { attributes, ..self }
+    }
+
+    /// Returns the protocol negotiated for the connection, e.g. "h2".
+    pub fn protocol(&self) -> &'static str {
+        self.protocol
+    }
+
+    /// Returns the security level of the connection.
+    pub fn security_level(&self) -> SecurityLevel {
+        self.security_level
+    }
+
+    /// Returns the address of the remote peer of the connection.
+    pub fn remote_address(&self) -> &str {
+        &self.remote_address
+    }
+
+    /// Returns the transport specific attributes of the connection.
+    pub fn attributes(&self) -> &Attributes {
+        &self.attributes
+    }
 }
 
 // TODO: The following options are specific to HTTP/2. We should
@@ -30,20 +122,54 @@ pub(crate) struct TransportOptions {
     pub(crate) http2_keep_alive_timeout: Option<Duration>,
     pub(crate) http2_keep_alive_while_idle: Option<bool>,
     pub(crate) http2_max_header_list_size: Option<u32>,
+    pub(crate) http2_max_frame_size: Option<u32>,
     pub(crate) http2_adaptive_window: Option<bool>,
     pub(crate) concurrency_limit: Option<usize>,
     pub(crate) rate_limit: Option<(u64, Duration)>,
     pub(crate) tcp_keepalive: Option<Duration>,
     pub(crate) tcp_nodelay: bool,
     pub(crate) connect_deadline: Option<Instant>,
+    /// Transport-specific settings from the channel's
+    /// [`ChannelOptions::transport_options`](crate::client::ChannelOptions::transport_options).
+    pub(crate) attributes: Attributes,
+    /// The encodings and compression policy of messages, for transports
+    /// which frame messages.
+    pub(crate) compression: MessageCompression,
+}
+
+impl TransportOptions {
+    /// Returns the options of connections using the given HTTP/2 settings.
+    pub(crate) fn with_http2(http2: &Http2Options) -> Self {
+        Self {
+            init_stream_window_size: http2.initial_stream_window_size,
+            init_connection_window_size: http2.initial_connection_window_size,
+            http2_max_header_list_size: http2.max_header_list_size,
+            http2_max_frame_size: http2.clamped_max_frame_size(),
+            http2_adaptive_window: http2.adaptive_window.then_some(true),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the HTTP/2 settings of connections using these options.
+    pub(crate) fn http2_settings(&self) -> Http2Options {
+        Http2Options {
+            initial_stream_window_size: self.init_stream_window_size,
+            initial_connection_window_size: self.init_connection_window_size,
+            max_frame_size: self.http2_max_frame_size,
+            max_header_list_size: self.http2_max_header_list_size,
+            adaptive_window: self.http2_adaptive_window.unwrap_or(false),
+        }
+    }
 }
 
 #[async_trait]
 pub(crate) trait Transport: Send + Sync {
+    /// Connects to the address.  Transports honor the attributes of the
+    /// address they understand, e.g. [`SERVER_NAME`], and ignore the others.
     async fn connect(
         &self,
-        address: String,
+        address: &Address,
         runtime: Arc<dyn Runtime>,
         opts: &TransportOptions,
-    ) -> Result<ConnectedTransport, String>;
+    ) -> Result<ConnectedTransport, ConnectError>;
 }
@@ -0,0 +1,596 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! An experimental transport carrying gRPC over HTTP/3 on QUIC connections.
+//!
+//! The transport handles addresses of the [`QUIC_NETWORK_TYPE`].  Resolvers
+//! usually produce TCP addresses, and mark those of hosts which also serve
+//! HTTP/3 with [`HTTP3_PORT`].  Once the transport is registered, channels add
+//! a QUIC address ahead of each marked TCP address, so that LB policies trying
+//! the addresses of an endpoint in order prefer HTTP/3 and fall back to TCP.
+
+use std::{
+    error::Error,
+    future::Future,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr},
+    pin::Pin,
+    str::FromStr,
+    sync::Arc,
+    task::{ready, Context, Poll},
+    time::Instant,
+};
+
+use bytes::{Buf, Bytes};
+use h3::client::{RequestStream, SendRequest};
+use http::{uri::PathAndQuery, Uri};
+use http_body::{Body as HttpBody, Frame};
+use quinn::{crypto::rustls::QuicClientConfig, ClientConfig, Endpoint, TransportConfig};
+use tokio::sync::oneshot;
+use tonic::{async_trait, body::Body, client::Grpc, Status};
+use tower_service::Service as TowerService;
+
+use crate::{
+    attributes::AttributeKey,
+    client::{
+        error::{ConnectError, ConnectErrorKind, DisconnectReason},
+        name_resolution::{Address, ResolverUpdate, TCP_IP_NETWORK_TYPE},
+        transport::{
+            registry::GLOBAL_TRANSPORT_REGISTRY, ConnectedTransport, SecurityLevel, Transport,
+            TransportInfo, TransportOptions, SERVER_NAME,
+        },
+    },
+    codec::{convert_request, convert_response, BytesCodec},
+    rt::Runtime,
+    service::{status_response, Request as GrpcRequest, Response as GrpcResponse, Service},
+};
+
+type BoxError = Box<dyn Error + Send + Sync>;
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+/// Indicates the address is an IPv4 or IPv6 socket address that should be
+/// connected to via QUIC.
+pub const QUIC_NETWORK_TYPE: &str = "quic";
+
+/// Set by resolvers on TCP addresses whose hosts also serve HTTP/3, to the
+/// UDP port on which they do, e.g. as advertised by an Alt-Svc header or an
+/// HTTPS DNS record.
+pub const HTTP3_PORT: AttributeKey<u16> = AttributeKey::new("grpc.transport.http3_port");
+
+/// The ALPN protocol of HTTP/3.
+const ALPN_H3: &[u8] = b"h3";
+
+/// Configures the QUIC transport.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct QuicOptions {
+    /// The TLS configuration of connections.  Its ALPN protocols are replaced
+    /// with "h3".  QUIC requires TLS 1.3.
+    pub tls: rustls::ClientConfig,
+    /// The name used to verify the certificates of servers, unless their
+    /// addresses set [`SERVER_NAME`](crate::ext::load_balancing::SERVER_NAME).
+    // TODO: use the authority of the channel once transports receive it.
+    pub server_name: String,
+    /// Whether requests may be sent as 0-RTT early data when resuming a
+    /// session with a server.  Early data may be replayed by an attacker, so
+    /// this should only be enabled if every method called is idempotent.
+    pub enable_0rtt: bool,
+}
+
+impl QuicOptions {
+    pub fn new(tls: rustls::ClientConfig, server_name: impl Into<String>) -> Self {
+        Self {
+            tls,
+            server_name: server_name.into(),
+            enable_0rtt: false,
+        }
+    }
+
+    pub fn enable_0rtt(self, enable_0rtt: bool) -> Self {
+        Self {
+            enable_0rtt,
+            ..self
+        }
+    }
+}
+
+/// Registers the QUIC transport for addresses of the [`QUIC_NETWORK_TYPE`].
+///
+/// It must be called only at application startup, before any channels are
+/// created.
+pub fn reg(options: QuicOptions) -> Result<(), String> {
+    GLOBAL_TRANSPORT_REGISTRY.add_transport(QUIC_NETWORK_TYPE, QuicTransport::new(options)?);
+    Ok(())
+}
+
+/// Adds a QUIC address ahead of each TCP address marked with [`HTTP3_PORT`],
+/// in the same endpoint.
+pub(crate) fn prefer_http3(update: &mut ResolverUpdate) {
+    let Ok(endpoints) = &mut update.endpoints else {
+        return;
+    };
+    for endpoint in endpoints {
+        let mut addresses = Vec::with_capacity(endpoint.addresses.len());
+        for address in endpoint.addresses.drain(..) {
+            if let Some(quic) = quic_address(&address) {
+                if !addresses.contains(&quic) {
+                    addresses.push(quic);
+                }
+            }
+            addresses.push(address);
+        }
+        endpoint.addresses = addresses;
+    }
+}
+
+fn quic_address(address: &Address) -> Option<Address> {
+    if address.network_type != TCP_IP_NETWORK_TYPE {
+        return None;
+    }
+    let port = *address.attributes.get(&HTTP3_PORT)?;
+    let mut addr = SocketAddr::from_str(&address.address).ok()?;
+    addr.set_port(port);
+    Some(Address {
+        attributes: address.attributes.clone(),
+        ..Address::new(QUIC_NETWORK_TYPE, addr.to_string())
+    })
+}
+
+struct QuicTransport {
+    config: ClientConfig,
+    server_name: String,
+    enable_0rtt: bool,
+}
+
+impl QuicTransport {
+    fn new(options: QuicOptions) -> Result<Self, String> {
+        let mut tls = options.tls;
+        tls.alpn_protocols = vec![ALPN_H3.to_vec()];
+        tls.enable_early_data = options.enable_0rtt;
+        let crypto = QuicClientConfig::try_from(tls).map_err(|err| err.to_string())?;
+        Ok(Self {
+            config: ClientConfig::new(Arc::new(crypto)),
+            server_name: options.server_name,
+            enable_0rtt: options.enable_0rtt,
+        })
+    }
+}
+
+#[async_trait]
+impl Transport for QuicTransport {
+    async fn connect(
+        &self,
+        address: &Address,
+        runtime: Arc<dyn Runtime>,
+        opts: &TransportOptions,
+    ) -> Result<ConnectedTransport, ConnectError> {
+        let server_name = address
+            .attributes
+            .get(&SERVER_NAME)
+            .unwrap_or(&self.server_name)
+            .clone();
+        let address = address.address.to_string();
+        let addr = SocketAddr::from_str(&address).map_err(|err| {
+            ConnectError::new(ConnectErrorKind::InvalidAddress, address.clone()).with_source(err)
+        })?;
+        let local: SocketAddr = match addr {
+            SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+            SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+        };
+        let endpoint = Endpoint::client(local).map_err(|err| {
+            ConnectError::new(ConnectErrorKind::Refused, "failed to bind UDP socket")
+                .with_source(err)
+        })?;
+        let mut config = self.config.clone();
+        if opts.http2_keep_alive_interval.is_some() {
+            let mut transport = TransportConfig::default();
+            transport.keep_alive_interval(opts.http2_keep_alive_interval);
+            config.transport_config(Arc::new(transport));
+        }
+        let connecting = endpoint
+            .connect_with(config, addr, &server_name)
+            .map_err(|err| {
+                ConnectError::new(ConnectErrorKind::InvalidAddress, address.clone())
+                    .with_source(err)
+            })?;
+        let handshake = async move {
+            if self.enable_0rtt {
+                match connecting.into_0rtt() {
+                    // The handshake completes in the background; requests are
+                    // sent as early data until then.
+                    Ok((connection, _)) => Ok(connection),
+                    Err(connecting) => connecting.await,
+                }
+            } else {
+                connecting.await
+            }
+        };
+        let connection = if let Some(deadline) = opts.connect_deadline {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            tokio::select! {
+                _ = runtime.sleep(timeout) => {
+                    return Err(ConnectError::new(
+                        ConnectErrorKind::Timeout,
+                        "timed out waiting for QUIC handshake",
+                    ))
+                }
+                connection = handshake => connection,
+            }
+        } else {
+            handshake.await
+        }
+        .map_err(|err| {
+            ConnectError::new(ConnectErrorKind::Handshake, "QUIC handshake failed").with_source(err)
+        })?;
+
+        let (mut driver, send_request) = h3::client::new(h3_quinn::Connection::new(connection))
+            .await
+            .map_err(|err| {
+                ConnectError::new(ConnectErrorKind::Handshake, "HTTP/3 handshake failed")
+                    .with_source(err)
+            })?;
+        let (tx, rx) = oneshot::channel();
+        let task_handle = runtime.spawn(Box::pin(async move {
+            let err = driver.wait_idle().await;
+            let _ = tx.send(if err.is_h3_no_error() {
+                DisconnectReason::Closed
+            } else {
+                DisconnectReason::Reset(err.to_string())
+            });
+            // The endpoint must outlive the connection.
+            drop(endpoint);
+        }));
+        // The authority of requests must match the name the server was
+        // authenticated as.
+        let origin = Uri::from_maybe_shared(format!("https://{server_name}")).map_err(|err| {
+            ConnectError::new(ConnectErrorKind::InvalidAddress, server_name.clone())
+                .with_source(err)
+        })?;
+        let grpc = Grpc::with_origin(
+            H3Service {
+                send_request,
+                runtime,
+            },
+            origin,
+        );
+        Ok(ConnectedTransport {
+            service: Box::new(QuicConnection { grpc, task_handle }),
+            disconnection_listener: rx,
+            info: TransportInfo::new("h3", SecurityLevel::PrivacyAndIntegrity, address),
+        })
+    }
+}
+
+struct QuicConnection {
+    grpc: Grpc<H3Service>,
+    task_handle: crate::rt::BoxedTaskHandle,
+}
+
+impl Drop for QuicConnection {
+    fn drop(&mut self) {
+        self.task_handle.abort();
+    }
+}
+
+#[async_trait]
+impl Service for QuicConnection {
+    async fn call(&self, method: String, request: GrpcRequest) -> GrpcResponse {
+        let Ok(path) = PathAndQuery::from_maybe_shared(method) else {
+            return status_response(Status::internal("Failed to parse path"));
+        };
+        let mut grpc = self.grpc.clone();
+        let request = convert_request(request);
+        let response = grpc.streaming(request, path, BytesCodec {}).await;
+        convert_response(response)
+    }
+}
+
+// Sends HTTP requests as HTTP/3 requests on a connection.
+#[derive(Clone)]
+struct H3Service {
+    send_request: SendRequest<h3_quinn::OpenStreams, Bytes>,
+    runtime: Arc<dyn Runtime>,
+}
+
+impl TowerService<http::Request<Body>> for H3Service {
+    type Response = http::Response<H3Body>;
+    type Error = BoxError;
+    type Future = BoxFuture<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // Streams are opened, and limited by the peer, when requests are sent.
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+        let mut send_request = self.send_request.clone();
+        let runtime = self.runtime.clone();
+        Box::pin(async move {
+            let (parts, mut body) = request.into_parts();
+            let stream = send_request
+                .send_request(http::Request::from_parts(parts, ()))
+                .await?;
+            let (mut send, mut recv) = stream.split();
+            // Requests are streamed while the response is received.
+            runtime.spawn(Box::pin(async move {
+                while let Some(frame) =
+                    std::future::poll_fn(|cx| Pin::new(&mut body).poll_frame(cx)).await
+                {
+                    let Ok(frame) = frame else {
+                        return;
+                    };
+                    if let Ok(data) = frame.into_data() {
+                        if send.send_data(data).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+                let _ = send.finish().await;
+            }));
+            let response = recv.recv_response().await?;
+            Ok(response.map(|()| H3Body {
+                stream: recv,
+                data_done: false,
+                done: false,
+            }))
+        })
+    }
+}
+
+// The body of an HTTP/3 response.
+struct H3Body {
+    stream: RequestStream<h3_quinn::RecvStream, Bytes>,
+    data_done: bool,
+    done: bool,
+}
+
+impl HttpBody for H3Body {
+    type Data = Bytes;
+    type Error = BoxError;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, BoxError>>> {
+        if !self.data_done {
+            match ready!(self.stream.poll_recv_data(cx)) {
+                Ok(Some(mut data)) => {
+                    let data = data.copy_to_bytes(data.remaining());
+                    return Poll::Ready(Some(Ok(Frame::data(data))));
+                }
+                Ok(None) => self.data_done = true,
+                Err(err) => {
+                    self.done = true;
+                    return Poll::Ready(Some(Err(err.into())));
+                }
+            }
+        }
+        if self.done {
+            return Poll::Ready(None);
+        }
+        let trailers = ready!(self.stream.poll_recv_trailers(cx));
+        self.done = true;
+        match trailers {
+            Ok(Some(trailers)) => Poll::Ready(Some(Ok(Frame::trailers(trailers)))),
+            Ok(None) => Poll::Ready(None),
+            Err(err) => Poll::Ready(Some(Err(err.into()))),
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.done
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        net::SocketAddr,
+        sync::{Arc, Mutex},
+    };
+
+    use bytes::{Buf, Bytes};
+    use quinn::{crypto::rustls::QuicServerConfig, Endpoint, ServerConfig};
+    use rustls::pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer};
+    use tokio_stream::StreamExt;
+
+    use super::{prefer_http3, QuicOptions, QuicTransport, HTTP3_PORT, QUIC_NETWORK_TYPE};
+    use crate::{
+        client::{
+            name_resolution::{Address, Endpoint as ResolverEndpoint, ResolverUpdate},
+            transport::{SecurityLevel, Transport, TransportOptions, SERVER_NAME},
+        },
+        rt::tokio::TokioRuntime,
+        service::{Message, Request},
+    };
+
+    const TEST_DATA: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../interop/data");
+
+    fn provider() -> Arc<rustls::crypto::CryptoProvider> {
+        Arc::new(rustls::crypto::ring::default_provider())
+    }
+
+    // The authorities of the requests received by a server.
+    type Authorities = Arc<Mutex<Vec<String>>>;
+
+    // Serves HTTP/3 requests by echoing their bodies, with an OK status.
+    async fn echo_server() -> (SocketAddr, Authorities) {
+        let certs = CertificateDer::pem_file_iter(format!("{TEST_DATA}/server1.pem"))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let key = PrivateKeyDer::from_pem_file(format!("{TEST_DATA}/server1.key")).unwrap();
+        let mut tls = rustls::ServerConfig::builder_with_provider(provider())
+            .with_protocol_versions(&[&rustls::version::TLS13])
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .unwrap();
+        tls.alpn_protocols = vec![b"h3".to_vec()];
+        let config = ServerConfig::with_crypto(Arc::new(QuicServerConfig::try_from(tls).unwrap()));
+        let endpoint = Endpoint::server(config, "127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = endpoint.local_addr().unwrap();
+        let authorities = Authorities::default();
+        let received = authorities.clone();
+        tokio::spawn(async move {
+            while let Some(incoming) = endpoint.accept().await {
+                let received = received.clone();
+                tokio::spawn(async move {
+                    let connection = incoming.await.unwrap();
+                    let mut connection: h3::server::Connection<_, Bytes> =
+                        h3::server::Connection::new(h3_quinn::Connection::new(connection))
+                            .await
+                            .unwrap();
+                    while let Ok(Some(resolver)) = connection.accept().await {
+                        let (request, mut stream) = resolver.resolve_request().await.unwrap();
+                        let authority = request.uri().authority().map(|a| a.to_string());
+                        received.lock().unwrap().extend(authority);
+                        tokio::spawn(async move {
+                            let response = http::Response::builder()
+                                .header("content-type", "application/grpc")
+                                .body(())
+                                .unwrap();
+                            stream.send_response(response).await.unwrap();
+                            while let Some(mut data) = stream.recv_data().await.unwrap() {
+                                let data = data.copy_to_bytes(data.remaining());
+                                stream.send_data(data).await.unwrap();
+                            }
+                            let mut trailers = http::HeaderMap::new();
+                            trailers.insert("grpc-status", "0".parse().unwrap());
+                            stream.send_trailers(trailers).await.unwrap();
+                            stream.finish().await.unwrap();
+                        });
+                    }
+                });
+            }
+        });
+        (addr, authorities)
+    }
+
+    fn client_options() -> QuicOptions {
+        let mut roots = rustls::RootCertStore::empty();
+        for cert in CertificateDer::pem_file_iter(format!("{TEST_DATA}/ca.pem")).unwrap() {
+            roots.add(cert.unwrap()).unwrap();
+        }
+        let tls = rustls::ClientConfig::builder_with_provider(provider())
+            .with_protocol_versions(&[&rustls::version::TLS13])
+            .unwrap()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        QuicOptions::new(tls, "foo.test.google.fr")
+    }
+
+    #[tokio::test]
+    async fn quic_transport_rpc() {
+        let (addr, _) = echo_server().await;
+        let transport = QuicTransport::new(client_options()).unwrap();
+        let connected = transport
+            .connect(
+                &Address::new(QUIC_NETWORK_TYPE, addr.to_string()),
+                Arc::new(TokioRuntime {}),
+                &TransportOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(connected.info.protocol(), "h3");
+        assert_eq!(
+            connected.info.security_level(),
+            SecurityLevel::PrivacyAndIntegrity
+        );
+
+        let messages: Vec<Box<dyn Message>> = vec![
+            Box::new(Bytes::from_static(b"hello")),
+            Box::new(Bytes::from_static(b"world")),
+        ];
+        let request = Request::new(Box::pin(tokio_stream::iter(messages)));
+        let mut response = connected
+            .service
+            .call("/svc/method".to_string(), request)
+            .await
+            .into_inner();
+        for want in ["hello", "world"] {
+            let msg = response.next().await.unwrap().unwrap();
+            let msg = (msg as Box<dyn std::any::Any>).downcast::<Bytes>().unwrap();
+            assert_eq!(*msg, want);
+        }
+        assert!(response.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn honors_server_name_attribute() {
+        let (addr, authorities) = echo_server().await;
+        let transport = QuicTransport::new(QuicOptions {
+            server_name: "wrong.example.com".to_string(),
+            ..client_options()
+        })
+        .unwrap();
+        let runtime = Arc::new(TokioRuntime {});
+        let options = TransportOptions::default();
+        let address = Address::new(QUIC_NETWORK_TYPE, addr.to_string());
+        let result = transport.connect(&address, runtime.clone(), &options).await;
+        assert!(result.is_err());
+        let address = address.with_attr(&SERVER_NAME, "foo.test.google.fr".to_string());
+        let connected = transport
+            .connect(&address, runtime, &options)
+            .await
+            .unwrap();
+
+        // Requests carry the overridden name as their authority.
+        let msg: Box<dyn Message> = Box::new(Bytes::from_static(b"hello"));
+        let request = Request::new(Box::pin(tokio_stream::once(msg)));
+        let mut response = connected
+            .service
+            .call("/svc/method".to_string(), request)
+            .await
+            .into_inner();
+        while response.next().await.is_some() {}
+        assert_eq!(*authorities.lock().unwrap(), ["foo.test.google.fr"]);
+    }
+
+    #[test]
+    fn prefers_http3_addresses() {
+        let endpoint = ResolverEndpoint::builder()
+            .addresses([
+                Address::new("tcp", "10.0.0.1:443").with_attr(&HTTP3_PORT, 8443),
+                Address::new("tcp", "10.0.0.2:443"),
+                Address::new("other", "10.0.0.3:443").with_attr(&HTTP3_PORT, 8443),
+            ])
+            .build()
+            .unwrap();
+        let mut update = ResolverUpdate::builder().endpoints([endpoint]).build();
+        prefer_http3(&mut update);
+        let addresses = &update.endpoints.unwrap()[0].addresses;
+        assert_eq!(
+            addresses
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                format!("{QUIC_NETWORK_TYPE}:10.0.0.1:8443"),
+                "tcp:10.0.0.1:443".to_string(),
+                "tcp:10.0.0.2:443".to_string(),
+                "other:10.0.0.3:443".to_string(),
+            ]
+        );
+    }
+}
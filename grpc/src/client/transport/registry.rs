@@ -1,6 +1,10 @@
-use super::Transport;
+use super::{ConnectedTransport, Transport, TransportOptions, TRANSPORT_SUPPORTED};
+use crate::client::error::{ConnectError, ConnectErrorKind};
+use crate::client::name_resolution::{Address, ResolverUpdate};
+use crate::rt::Runtime;
 use std::sync::{Arc, LazyLock, Mutex};
 use std::{collections::HashMap, fmt::Debug};
+use tonic::async_trait;
 
 /// A registry to store and retrieve transports.  Transports are indexed by
 /// the address type they are intended to handle.
@@ -44,9 +48,107 @@ impl TransportRegistry {
             ))
             .cloned()
     }
+
+    /// Retrieve a transport from the registry, or from the global registry if
+    /// not found.
+    pub(crate) fn get_transport_or_global(
+        &self,
+        address_type: &str,
+    ) -> Result<Arc<dyn Transport>, String> {
+        self.get_transport(address_type)
+            .or_else(|_| GLOBAL_TRANSPORT_REGISTRY.get_transport(address_type))
+    }
+
+    /// Sets [`TRANSPORT_SUPPORTED`] on every address of update.
+    pub(crate) fn mark_supported(&self, update: &mut ResolverUpdate) {
+        let Ok(endpoints) = &mut update.endpoints else {
+            return;
+        };
+        for address in endpoints.iter_mut().flat_map(|e| e.addresses.iter_mut()) {
+            let supported = self.get_transport_or_global(address.network_type).is_ok();
+            address.attributes =
+                std::mem::take(&mut address.attributes).add(&TRANSPORT_SUPPORTED, supported);
+        }
+    }
+}
+
+/// Connects through the transport registered for the network type of each
+/// address, looked up when connecting.  Subchannels may thus be created for
+/// addresses of any type; those without a transport fail to connect.
+pub(crate) struct RegisteredTransport {
+    registry: TransportRegistry,
+}
+
+impl RegisteredTransport {
+    pub(crate) fn new(registry: TransportRegistry) -> Self {
+        Self { registry }
+    }
+}
+
+#[async_trait]
+impl Transport for RegisteredTransport {
+    async fn connect(
+        &self,
+        address: &Address,
+        runtime: Arc<dyn Runtime>,
+        opts: &TransportOptions,
+    ) -> Result<ConnectedTransport, ConnectError> {
+        let transport = self
+            .registry
+            .get_transport_or_global(address.network_type)
+            .map_err(|err| ConnectError::new(ConnectErrorKind::InvalidAddress, err))?;
+        transport.connect(address, runtime, opts).await
+    }
 }
 
 /// The registry used if a local registry is not provided to a channel or if it
 /// does not exist in the local registry.
 pub static GLOBAL_TRANSPORT_REGISTRY: LazyLock<TransportRegistry> =
     LazyLock::new(TransportRegistry::new);
+
+#[cfg(test)]
+mod test {
+    use super::{RegisteredTransport, TransportRegistry, TRANSPORT_SUPPORTED};
+    use crate::client::error::ConnectErrorKind;
+    use crate::client::name_resolution::{Address, Endpoint, ResolverUpdate};
+    use crate::client::transport::{Transport, TransportOptions};
+    use crate::rt::tokio::TokioRuntime;
+    use std::sync::Arc;
+
+    #[test]
+    fn marks_supported_addresses() {
+        let registry = TransportRegistry::new();
+        registry.add_transport("local", RegisteredTransport::new(TransportRegistry::new()));
+        let endpoint = Endpoint::builder()
+            .addresses([
+                Address::new("local", "a"),
+                Address::new("unregistered", "b"),
+            ])
+            .build()
+            .unwrap();
+        let mut update = ResolverUpdate::builder().endpoint(endpoint).build();
+        registry.mark_supported(&mut update);
+        let supported: Vec<_> = update.endpoints.unwrap()[0]
+            .addresses
+            .iter()
+            .map(|a| a.attributes.get(&TRANSPORT_SUPPORTED).copied())
+            .collect();
+        assert_eq!(supported, [Some(true), Some(false)]);
+    }
+
+    #[tokio::test]
+    async fn unregistered_network_types_fail_to_connect() {
+        let transport = RegisteredTransport::new(TransportRegistry::new());
+        let result = transport
+            .connect(
+                &Address::new("unregistered", "b"),
+                Arc::new(TokioRuntime {}),
+                &TransportOptions::default(),
+            )
+            .await;
+        let Err(err) = result else {
+            panic!("connected without a transport");
+        };
+        assert_eq!(err.kind(), ConnectErrorKind::InvalidAddress);
+    }
+}
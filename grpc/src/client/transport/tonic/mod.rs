@@ -1,16 +1,25 @@
+use crate::attributes::Attributes;
+use crate::client::error::{ConnectError, ConnectErrorKind, DisconnectReason};
 use crate::client::transport::registry::GLOBAL_TRANSPORT_REGISTRY;
 use crate::client::transport::ConnectedTransport;
+use crate::client::transport::SecurityLevel;
 use crate::client::transport::Transport;
 use crate::client::transport::TransportOptions;
-use crate::codec::BytesCodec;
+use crate::client::transport::{TransportInfo, HTTP2_SETTINGS};
+use crate::codec::{convert_request, convert_response, BytesCodec};
+use crate::compression::{framing, MessageCompression, ACCEPT_ENCODING_HEADER};
 use crate::rt::hyper_wrapper::{HyperCompatExec, HyperCompatTimer, HyperStream};
 use crate::rt::BoxedTaskHandle;
 use crate::rt::Runtime;
 use crate::rt::TcpOptions;
+use crate::service::status_response;
 use crate::service::Message;
 use crate::service::Request as GrpcRequest;
 use crate::service::Response as GrpcResponse;
-use crate::{client::name_resolution::TCP_IP_NETWORK_TYPE, service::Service};
+use crate::{
+    client::name_resolution::{Address, TCP_IP_NETWORK_TYPE},
+    service::Service,
+};
 use bytes::Bytes;
 use http::uri::PathAndQuery;
 use http::Request as HttpRequest;
@@ -19,7 +28,8 @@ use http::Uri;
 use hyper::client::conn::http2::Builder;
 use hyper::client::conn::http2::SendRequest;
 use std::any::Any;
-use std::task::{Context, Poll};
+use std::sync::Mutex;
+use std::task::{ready, Context, Poll};
 use std::time::Instant;
 use std::{error::Error, future::Future, net::SocketAddr, pin::Pin, str::FromStr, sync::Arc};
 use tokio::sync::oneshot;
@@ -66,7 +76,7 @@ impl Service for TonicTransport {
     async fn call(&self, method: String, request: GrpcRequest) -> GrpcResponse {
         let Ok(path) = PathAndQuery::from_maybe_shared(method) else {
             let err = Status::internal("Failed to parse path");
-            return create_error_response(err);
+            return status_response(err);
         };
         let mut grpc = self.grpc.clone();
         if let Err(e) = grpc.ready().await {
@@ -74,7 +84,7 @@ impl Service for TonicTransport {
             // may return an error and re-evaluate the status code returned
             // below.
             let err = Status::unknown(format!("Service was not ready: {e}"));
-            return create_error_response(err);
+            return status_response(err);
         };
         let request = convert_request(request);
         let response = grpc.streaming(request, path, BytesCodec {}).await;
@@ -82,54 +92,14 @@ impl Service for TonicTransport {
     }
 }
 
-/// Helper function to create an error response stream.
-fn create_error_response(status: Status) -> GrpcResponse {
-    let stream = tokio_stream::once(Err(status));
-    TonicResponse::new(Box::pin(stream))
-}
-
-fn convert_request(req: GrpcRequest) -> TonicRequest<Pin<Box<dyn Stream<Item = Bytes> + Send>>> {
-    let (metadata, extensions, stream) = req.into_parts();
-
-    let bytes_stream = Box::pin(stream.filter_map(|msg| {
-        if let Ok(bytes) = (msg as Box<dyn Any>).downcast::<Bytes>() {
-            Some(*bytes)
-        } else {
-            // If it fails, log the error and return None to filter it out.
-            eprintln!("A message could not be downcast to Bytes and was skipped.");
-            None
-        }
-    }));
-
-    TonicRequest::from_parts(metadata, extensions, bytes_stream as _)
-}
-
-fn convert_response(res: Result<TonicResponse<Streaming<Bytes>>, Status>) -> GrpcResponse {
-    let response = match res {
-        Ok(s) => s,
-        Err(e) => {
-            let stream = tokio_stream::once(Err(e));
-            return TonicResponse::new(Box::pin(stream));
-        }
-    };
-    let (metadata, stream, extensions) = response.into_parts();
-    let message_stream: BoxStream<Box<dyn Message>> = Box::pin(stream.map(|msg| {
-        msg.map(|b| {
-            let msg: Box<dyn Message> = Box::new(b);
-            msg
-        })
-    }));
-    TonicResponse::from_parts(metadata, message_stream, extensions)
-}
-
 #[async_trait]
 impl Transport for TransportBuilder {
     async fn connect(
         &self,
-        address: String,
+        address: &Address,
         runtime: Arc<dyn Runtime>,
         opts: &TransportOptions,
-    ) -> Result<ConnectedTransport, String> {
+    ) -> Result<ConnectedTransport, ConnectError> {
         let runtime = runtime.clone();
         let mut settings = Builder::<HyperCompatExec>::new(HyperCompatExec {
             inner: runtime.clone(),
@@ -158,7 +128,14 @@ impl Transport for TransportBuilder {
             settings.max_header_list_size(val);
         }
 
-        let addr: SocketAddr = SocketAddr::from_str(&address).map_err(|err| err.to_string())?;
+        if let Some(val) = opts.http2_max_frame_size {
+            settings.max_frame_size(val);
+        }
+
+        let address = address.address.to_string();
+        let addr: SocketAddr = SocketAddr::from_str(&address).map_err(|err| {
+            ConnectError::new(ConnectErrorKind::InvalidAddress, address.clone()).with_source(err)
+        })?;
         let tcp_stream_fut = runtime.tcp_stream(
             addr,
             TcpOptions {
@@ -170,27 +147,31 @@ impl Transport for TransportBuilder {
             let timeout = deadline.saturating_duration_since(Instant::now());
             tokio::select! {
             _ = runtime.sleep(timeout) => {
-                return Err("timed out waiting for TCP stream to connect".to_string())
+                return Err(ConnectError::new(
+                    ConnectErrorKind::Timeout,
+                    "timed out waiting for TCP stream to connect",
+                ))
             }
-            tcp_stream = tcp_stream_fut => { tcp_stream? }
+            tcp_stream = tcp_stream_fut => tcp_stream,
             }
         } else {
-            tcp_stream_fut.await?
-        };
+            tcp_stream_fut.await
+        }
+        .map_err(|err| ConnectError::new(ConnectErrorKind::Refused, err))?;
         let tcp_stream = HyperStream::new(tcp_stream);
 
-        let (sender, connection) = settings
-            .handshake(tcp_stream)
-            .await
-            .map_err(|err| err.to_string())?;
+        let (sender, connection) = settings.handshake(tcp_stream).await.map_err(|err| {
+            ConnectError::new(ConnectErrorKind::Handshake, "HTTP/2 handshake failed")
+                .with_source(err)
+        })?;
         let (tx, rx) = oneshot::channel();
 
         let task_handle = runtime.spawn(Box::pin(async move {
-            if let Err(err) = connection.await {
-                let _ = tx.send(Err(err.to_string()));
-            } else {
-                let _ = tx.send(Ok(()));
-            }
+            let reason = match connection.await {
+                Ok(()) => DisconnectReason::Closed,
+                Err(err) => disconnect_reason(&err),
+            };
+            let _ = tx.send(reason);
         }));
         let sender = SendRequestWrapper::from(sender);
 
@@ -203,18 +184,82 @@ impl Transport for TransportBuilder {
         let service = BoxService::new(service);
         let (service, worker) = Buffer::pair(service, DEFAULT_BUFFER_SIZE);
         runtime.spawn(Box::pin(worker));
-        let uri =
-            Uri::from_maybe_shared(format!("http://{}", &address)).map_err(|e| e.to_string())?; // TODO: err msg
-        let grpc = Grpc::with_origin(TonicService { inner: service }, uri);
+        let uri = Uri::from_maybe_shared(format!("http://{}", &address)).map_err(|err| {
+            ConnectError::new(ConnectErrorKind::InvalidAddress, address.clone()).with_source(err)
+        })?;
+        let service = TonicService {
+            inner: service,
+            compression: opts.compression.clone(),
+            peer_accept_encoding: Arc::default(),
+        };
+        let grpc = Grpc::with_origin(service, uri);
 
         let service = TonicTransport { grpc, task_handle };
         Ok(ConnectedTransport {
             service: Box::new(service),
             disconnection_listener: rx,
+            // TODO: report the security level once TLS is supported.
+            info: TransportInfo::new("h2", SecurityLevel::NoSecurity, addr.to_string())
+                .with_attributes(Attributes::default().add(&HTTP2_SETTINGS, opts.http2_settings())),
         })
     }
 }
 
+// Classifies the error a connection failed with.
+fn disconnect_reason(err: &hyper::Error) -> DisconnectReason {
+    if err.is_timeout() {
+        return DisconnectReason::KeepaliveTimeout;
+    }
+    let mut source = err.source();
+    while let Some(err) = source {
+        if let Some(err) = err.downcast_ref::<h2::Error>() {
+            if err.is_go_away() && err.is_remote() {
+                return DisconnectReason::GoAway {
+                    error_code: err.reason().map_or(0, u32::from),
+                    debug_data: go_away_debug_data(&err.to_string()),
+                };
+            }
+            break;
+        }
+        source = err.source();
+    }
+    DisconnectReason::Reset(err.to_string())
+}
+
+// Returns the debug data of a GOAWAY error.  h2 only exposes it through its
+// Display implementation, as a byte string literal, e.g.
+// `connection error received: ENHANCE_YOUR_CALM (b"too_many_pings")`.
+fn go_away_debug_data(msg: &str) -> String {
+    let Some(literal) = msg
+        .split_once(" (b\"")
+        .and_then(|(_, rest)| rest.strip_suffix("\")"))
+    else {
+        return String::new();
+    };
+    let mut data = Vec::with_capacity(literal.len());
+    let mut bytes = literal.bytes();
+    while let Some(b) = bytes.next() {
+        if b != b'\\' {
+            data.push(b);
+            continue;
+        }
+        match bytes.next() {
+            Some(b'n') => data.push(b'\n'),
+            Some(b'r') => data.push(b'\r'),
+            Some(b't') => data.push(b'\t'),
+            Some(b'0') => data.push(0),
+            Some(b'x') => {
+                let hex = [bytes.next().unwrap_or(b'0'), bytes.next().unwrap_or(b'0')];
+                let hex = std::str::from_utf8(&hex).unwrap_or("00");
+                data.push(u8::from_str_radix(hex, 16).unwrap_or(0));
+            }
+            Some(b) => data.push(b),
+            None => {}
+        }
+    }
+    String::from_utf8_lossy(&data).into_owned()
+}
+
 struct SendRequestWrapper {
     inner: SendRequest<Body>,
 }
@@ -243,6 +288,9 @@ impl TowerService<HttpRequest<Body>> for SendRequestWrapper {
 #[derive(Clone)]
 struct TonicService {
     inner: Buffer<http::Request<Body>, BoxFuture<'static, Result<http::Response<Body>, BoxError>>>,
+    compression: MessageCompression,
+    // The encodings the server last advertised on the connection.
+    peer_accept_encoding: Arc<Mutex<String>>,
 }
 
 impl GrpcService<Body> for TonicService {
@@ -255,8 +303,18 @@ impl GrpcService<Body> for TonicService {
     }
 
     fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+        let (mut parts, body) = request.into_parts();
+        let body = framing::encode(
+            &self.compression,
+            parts.uri.path(),
+            &self.peer_accept_encoding.lock().unwrap(),
+            &mut parts.headers,
+            body,
+        );
         ResponseFuture {
-            inner: tower::Service::call(&mut self.inner, request),
+            inner: tower::Service::call(&mut self.inner, HttpRequest::from_parts(parts, body)),
+            compression: self.compression.clone(),
+            peer_accept_encoding: self.peer_accept_encoding.clone(),
         }
     }
 }
@@ -266,12 +324,20 @@ impl GrpcService<Body> for TonicService {
 /// This is returned by the `Service::call` on [`Channel`].
 pub struct ResponseFuture {
     inner: BufferResponseFuture<BoxFuture<'static, Result<HttpResponse<Body>, BoxError>>>,
+    compression: MessageCompression,
+    peer_accept_encoding: Arc<Mutex<String>>,
 }
 
 impl Future for ResponseFuture {
     type Output = Result<http::Response<Body>, BoxError>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        Pin::new(&mut self.inner).poll(cx)
+        let response = ready!(Pin::new(&mut self.inner).poll(cx))?;
+        let (mut parts, body) = response.into_parts();
+        if parts.headers.contains_key(ACCEPT_ENCODING_HEADER) {
+            *self.peer_accept_encoding.lock().unwrap() = framing::accept_encoding(&parts.headers);
+        }
+        let body = framing::decode(&self.compression, &mut parts.headers, body);
+        Poll::Ready(Ok(HttpResponse::from_parts(parts, body)))
     }
 }
@@ -1,7 +1,11 @@
-use crate::client::name_resolution::TCP_IP_NETWORK_TYPE;
+use crate::client::error::DisconnectReason;
+use crate::client::name_resolution::{Address, TCP_IP_NETWORK_TYPE};
 use crate::client::transport::registry::GLOBAL_TRANSPORT_REGISTRY;
+use crate::client::transport::HTTP2_SETTINGS;
 use crate::echo_pb::echo_server::{Echo, EchoServer};
 use crate::echo_pb::{EchoRequest, EchoResponse};
+use crate::http2::Http2Options;
+use crate::service::details::{ErrorDetails, StatusExt};
 use crate::service::Message;
 use crate::service::Request as GrpcRequest;
 use crate::{client::transport::TransportOptions, rt::tokio::TokioRuntime};
@@ -45,7 +49,11 @@ pub async fn tonic_transport_rpc() {
         .unwrap();
     let config = Arc::new(TransportOptions::default());
     let mut connected_transport = builder
-        .connect(addr.to_string(), Arc::new(TokioRuntime {}), &config)
+        .connect(
+            &Address::new(TCP_IP_NETWORK_TYPE, addr.to_string()),
+            Arc::new(TokioRuntime {}),
+            &config,
+        )
         .await
         .unwrap();
     let conn = connected_transport.service;
@@ -105,10 +113,115 @@ pub async fn tonic_transport_rpc() {
     .await
     .unwrap()
     .unwrap();
-    assert_eq!(res, Ok(()));
+    assert_eq!(res, DisconnectReason::Closed);
     server_handle.await.unwrap();
 }
 
+// Tests that connections use the configured HTTP/2 settings by sending a
+// message larger than the default windows and frame size.
+#[tokio::test]
+pub async fn tonic_transport_http2_options() {
+    super::reg();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server_handle = tokio::spawn(async move {
+        let _ = Server::builder()
+            .add_service(EchoServer::new(EchoService {}))
+            .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener))
+            .await;
+    });
+
+    let builder = GLOBAL_TRANSPORT_REGISTRY
+        .get_transport(TCP_IP_NETWORK_TYPE)
+        .unwrap();
+    let http2 = Http2Options::default()
+        .initial_stream_window_size(1 << 20)
+        .initial_connection_window_size(2 << 20)
+        .max_frame_size(1 << 20)
+        .max_header_list_size(64 << 10);
+    let config = TransportOptions::with_http2(&http2);
+    let connected_transport = builder
+        .connect(
+            &Address::new(TCP_IP_NETWORK_TYPE, addr.to_string()),
+            Arc::new(TokioRuntime {}),
+            &config,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        connected_transport.info.attributes().get(&HTTP2_SETTINGS),
+        Some(&http2)
+    );
+
+    let request = EchoRequest {
+        message: "x".repeat(512 << 10),
+    };
+    let msg: Box<dyn Message> = Box::new(Bytes::from(request.encode_to_vec()));
+    let outbound: GrpcRequest = Request::new(Box::pin(tokio_stream::once(msg)));
+    let mut inbound = connected_transport
+        .service
+        .call(
+            "/grpc.examples.echo.Echo/BidirectionalStreamingEcho".to_string(),
+            outbound,
+        )
+        .await
+        .into_inner();
+    let resp = timeout(DEFAULT_TEST_DURATION, inbound.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    let bytes = (resp as Box<dyn Any>).downcast::<Bytes>().unwrap();
+    assert_eq!(
+        EchoResponse::decode(*bytes).unwrap().message,
+        request.message
+    );
+    server_handle.abort();
+}
+
+// Tests that the details of statuses are carried in trailers.
+#[tokio::test]
+pub async fn tonic_transport_status_details() {
+    super::reg();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server_handle = tokio::spawn(async move {
+        let _ = Server::builder()
+            .add_service(EchoServer::new(EchoService {}))
+            .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener))
+            .await;
+    });
+
+    let builder = GLOBAL_TRANSPORT_REGISTRY
+        .get_transport(TCP_IP_NETWORK_TYPE)
+        .unwrap();
+    let connected_transport = builder
+        .connect(
+            &Address::new(TCP_IP_NETWORK_TYPE, addr.to_string()),
+            Arc::new(TokioRuntime {}),
+            &TransportOptions::default(),
+        )
+        .await
+        .unwrap();
+
+    let msg: Box<dyn Message> = Box::new(Bytes::from(EchoRequest::default().encode_to_vec()));
+    let outbound: GrpcRequest = Request::new(Box::pin(tokio_stream::once(msg)));
+    let mut inbound = connected_transport
+        .service
+        .call("/grpc.examples.echo.Echo/UnaryEcho".to_string(), outbound)
+        .await
+        .into_inner();
+    let status = timeout(DEFAULT_TEST_DURATION, inbound.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    let bad_request = status.get_details_bad_request().unwrap();
+    assert_eq!(bad_request.field_violations[0].field, "message");
+    server_handle.abort();
+}
+
 #[derive(Debug)]
 pub struct EchoService {}
 
@@ -116,9 +229,17 @@ pub struct EchoService {}
 impl Echo for EchoService {
     async fn unary_echo(
         &self,
-        _: tonic::Request<EchoRequest>,
+        request: tonic::Request<EchoRequest>,
     ) -> std::result::Result<tonic::Response<EchoResponse>, tonic::Status> {
-        unimplemented!()
+        let message = request.into_inner().message;
+        if message.is_empty() {
+            return Err(Status::with_error_details(
+                tonic::Code::InvalidArgument,
+                "invalid request",
+                ErrorDetails::with_bad_request_violation("message", "must not be empty"),
+            ));
+        }
+        Ok(Response::new(EchoResponse { message }))
     }
 
     type ServerStreamingEchoStream = ReceiverStream<Result<EchoResponse, Status>>;
@@ -163,3 +284,22 @@ impl Echo for EchoService {
         ))
     }
 }
+
+// Tests that GOAWAY debug data is recovered from h2's error messages.
+#[test]
+fn go_away_debug_data_is_unescaped() {
+    assert_eq!(
+        super::go_away_debug_data(
+            "connection error received: not a result of an error (b\"too_many_pings\")"
+        ),
+        "too_many_pings"
+    );
+    assert_eq!(
+        super::go_away_debug_data("error (b\"a\\\"b\\nc\\x41\")"),
+        "a\"b\ncA"
+    );
+    assert_eq!(
+        super::go_away_debug_data("connection error received: not a result of an error"),
+        ""
+    );
+}
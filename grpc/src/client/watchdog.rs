@@ -0,0 +1,278 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! A watchdog for subchannels stuck in CONNECTING.
+//!
+//! Connection attempts are abandoned once the connect timeout expires, so a
+//! subchannel which stays CONNECTING for several times as long indicates a
+//! bug or a pathological network.  The watchdog turns such silent hangs into
+//! reports describing the attempt, and may reset the subchannel.
+
+use std::{
+    fmt::{self, Display, Formatter},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::rt::{BoxedTaskHandle, Runtime};
+
+/// The maximum number of reports retained by a channel; older reports are
+/// discarded first.
+const MAX_REPORTS: usize = 64;
+
+/// Configures the watchdog for subchannels stuck in CONNECTING.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ConnectingWatchdog {
+    /// Subchannels connecting for longer than this multiple of the connect
+    /// timeout are reported as stuck.
+    pub timeout_multiplier: u32,
+    /// Whether stuck subchannels are reset, failing their connection attempt
+    /// so that they back off and try again.
+    pub force_reset: bool,
+}
+
+impl Default for ConnectingWatchdog {
+    fn default() -> Self {
+        Self {
+            timeout_multiplier: 3,
+            force_reset: false,
+        }
+    }
+}
+
+impl ConnectingWatchdog {
+    pub fn timeout_multiplier(self, multiplier: u32) -> Self {
+        Self {
+            timeout_multiplier: multiplier,
+            ..self
+        }
+    }
+
+    pub fn force_reset(self, force_reset: bool) -> Self {
+        Self {
+            force_reset,
+            ..self
+        }
+    }
+}
+
+/// How far a connection attempt had progressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectingPhase {
+    /// The task performing the attempt has not started running.
+    Scheduled,
+    /// The transport is establishing the connection.
+    Connecting,
+    /// The attempt finished, but the subchannel has not processed its result.
+    Completing,
+}
+
+impl Display for ConnectingPhase {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            ConnectingPhase::Scheduled => "scheduled",
+            ConnectingPhase::Connecting => "connecting",
+            ConnectingPhase::Completing => "completing",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// A diagnostic describing a subchannel found stuck in CONNECTING.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct StuckConnecting {
+    /// The address being connected to.
+    pub address: String,
+    /// The network type of the address, which selects the transport.
+    pub network_type: &'static str,
+    /// How far the connection attempt had progressed.
+    pub phase: ConnectingPhase,
+    /// How long the subchannel had been connecting.
+    pub elapsed: Duration,
+    /// The connect timeout of the attempt.
+    pub connect_timeout: Duration,
+    /// Whether the subchannel was reset.
+    pub reset: bool,
+}
+
+impl Display for StuckConnecting {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "subchannel for {}:{} stuck connecting for {:?} (connect timeout {:?}) in phase {}",
+            self.network_type, self.address, self.elapsed, self.connect_timeout, self.phase
+        )?;
+        if self.reset {
+            write!(f, "; resetting")?;
+        }
+        Ok(())
+    }
+}
+
+/// The connection attempt of a subchannel watched by the watchdog.
+pub(crate) struct ConnectAttempt {
+    pub(crate) address: String,
+    pub(crate) network_type: &'static str,
+    pub(crate) connect_timeout: Duration,
+    pub(crate) phase: Arc<Mutex<ConnectingPhase>>,
+}
+
+/// Watches the connection attempts of a channel's subchannels, and retains
+/// the reports of those found stuck.
+pub(crate) struct ConnectingWatchdogMonitor {
+    config: Option<ConnectingWatchdog>,
+    reports: Mutex<Vec<StuckConnecting>>,
+}
+
+impl ConnectingWatchdogMonitor {
+    /// Creates a monitor which never reports attempts if config is None.
+    pub(crate) fn new(config: Option<ConnectingWatchdog>) -> Self {
+        Self {
+            config,
+            reports: Mutex::default(),
+        }
+    }
+
+    /// Spawns a task which reports attempt as stuck if it is still running
+    /// after the configured multiple of its connect timeout, and then calls
+    /// reset if configured to.  The task must be aborted once the attempt
+    /// completes.  Returns None if the watchdog is disabled.
+    pub(crate) fn watch(
+        self: &Arc<Self>,
+        runtime: &Arc<dyn Runtime>,
+        attempt: ConnectAttempt,
+        reset: impl FnOnce() + Send + 'static,
+    ) -> Option<BoxedTaskHandle> {
+        let config = self.config.clone()?;
+        let monitor = self.clone();
+        let sleep = runtime.sleep(attempt.connect_timeout * config.timeout_multiplier);
+        let started = Instant::now();
+        Some(runtime.spawn(Box::pin(async move {
+            sleep.await;
+            monitor.report(StuckConnecting {
+                address: attempt.address,
+                network_type: attempt.network_type,
+                phase: *attempt.phase.lock().unwrap(),
+                elapsed: started.elapsed(),
+                connect_timeout: attempt.connect_timeout,
+                reset: config.force_reset,
+            });
+            if config.force_reset {
+                reset();
+            }
+        })))
+    }
+
+    fn report(&self, report: StuckConnecting) {
+        eprintln!("warning: {report}");
+        let mut reports = self.reports.lock().unwrap();
+        if reports.len() == MAX_REPORTS {
+            reports.remove(0);
+        }
+        reports.push(report);
+    }
+
+    /// Returns the retained reports, oldest first.
+    pub(crate) fn reports(&self) -> Vec<StuckConnecting> {
+        self.reports.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, Mutex,
+        },
+        time::Duration,
+    };
+
+    use super::{ConnectAttempt, ConnectingPhase, ConnectingWatchdog, ConnectingWatchdogMonitor};
+    use crate::rt::{default_runtime, Runtime};
+
+    fn attempt(phase: ConnectingPhase) -> ConnectAttempt {
+        ConnectAttempt {
+            address: "127.0.0.1:8080".to_string(),
+            network_type: "tcp",
+            connect_timeout: Duration::from_millis(10),
+            phase: Arc::new(Mutex::new(phase)),
+        }
+    }
+
+    #[tokio::test]
+    async fn reports_stuck_attempts() {
+        let runtime: Arc<dyn Runtime> = default_runtime();
+        let monitor = Arc::new(ConnectingWatchdogMonitor::new(Some(
+            ConnectingWatchdog::default()
+                .timeout_multiplier(2)
+                .force_reset(true),
+        )));
+        let reset = Arc::new(AtomicBool::new(false));
+        let reset_clone = reset.clone();
+        let _task = monitor
+            .watch(&runtime, attempt(ConnectingPhase::Completing), move || {
+                reset_clone.store(true, Ordering::SeqCst)
+            })
+            .unwrap();
+        runtime.sleep(Duration::from_millis(100)).await;
+
+        assert!(reset.load(Ordering::SeqCst));
+        let reports = monitor.reports();
+        assert_eq!(reports.len(), 1);
+        let report = &reports[0];
+        assert_eq!(report.address, "127.0.0.1:8080");
+        assert_eq!(report.network_type, "tcp");
+        assert_eq!(report.phase, ConnectingPhase::Completing);
+        assert!(report.elapsed >= Duration::from_millis(20));
+        assert!(report.reset);
+    }
+
+    #[tokio::test]
+    async fn aborted_watch_does_not_report() {
+        let runtime: Arc<dyn Runtime> = default_runtime();
+        let monitor = Arc::new(ConnectingWatchdogMonitor::new(Some(
+            ConnectingWatchdog::default(),
+        )));
+        let task = monitor
+            .watch(&runtime, attempt(ConnectingPhase::Connecting), || {
+                panic!("reset without force_reset")
+            })
+            .unwrap();
+        task.abort();
+        runtime.sleep(Duration::from_millis(50)).await;
+        assert!(monitor.reports().is_empty());
+    }
+
+    #[test]
+    fn disabled_watchdog_does_not_watch() {
+        let runtime: Arc<dyn Runtime> = default_runtime();
+        let monitor = Arc::new(ConnectingWatchdogMonitor::new(None));
+        assert!(monitor
+            .watch(&runtime, attempt(ConnectingPhase::Scheduled), || {})
+            .is_none());
+    }
+}
@@ -0,0 +1,276 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! Instrumentation for the channel's work queue.
+//!
+//! All resolver and LB policy callbacks run serially on the channel's work
+//! queue, so a single slow callback delays everything behind it and can make
+//! a channel appear stuck.  The monitor records how long each item takes by
+//! kind and warns about items exceeding a configurable threshold.  It also
+//! counts the items which panicked, which the channel survives by catching the
+//! unwind.
+//!
+//! Updates which only matter for their latest value, such as subchannel state
+//! changes, are coalesced with [`Coalesced`] so that a burst of them is
+//! delivered as a single item.
+
+use std::{
+    any::Any,
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    sync::Mutex,
+    time::Duration,
+};
+
+/// The kind of work performed by a single item of the work queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum WorkItemKind {
+    /// The resolver produces an update and delivers it to the LB policy.
+    ResolverUpdate,
+    /// A subchannel state change is delivered to the LB policy.
+    SubchannelUpdate,
+    /// Work requested by the LB policy via its work scheduler.
+    Work,
+    /// The LB policy is asked to exit idle.
+    ExitIdle,
+    /// Bookkeeping performed by the channel itself, e.g. releasing a dropped
+    /// subchannel.
+    Cleanup,
+}
+
+impl Display for WorkItemKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            WorkItemKind::ResolverUpdate => "resolver update",
+            WorkItemKind::SubchannelUpdate => "subchannel update",
+            WorkItemKind::Work => "work",
+            WorkItemKind::ExitIdle => "exit idle",
+            WorkItemKind::Cleanup => "cleanup",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// Aggregated execution durations of one kind of work item.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct WorkItemStats {
+    pub(crate) count: u64,
+    pub(crate) total: Duration,
+    pub(crate) max: Duration,
+    /// The number of items which exceeded the slow item threshold.
+    pub(crate) slow: u64,
+    /// The number of items which panicked.
+    pub(crate) panics: u64,
+}
+
+pub(crate) struct WorkQueueMonitor {
+    slow_threshold: Option<Duration>,
+    stats: Mutex<HashMap<WorkItemKind, WorkItemStats>>,
+}
+
+impl WorkQueueMonitor {
+    /// Creates a monitor which warns about items that take longer than
+    /// slow_threshold, or never warns if it is None.
+    pub(crate) fn new(slow_threshold: Option<Duration>) -> Self {
+        Self {
+            slow_threshold,
+            stats: Mutex::default(),
+        }
+    }
+
+    /// Records that an item of the given kind took elapsed to execute, and
+    /// warns if it was slow.  policy_name identifies the LB policy running at
+    /// the time, if any.  Returns whether the item was slow.
+    pub(crate) fn record(
+        &self,
+        kind: WorkItemKind,
+        elapsed: Duration,
+        policy_name: Option<&str>,
+    ) -> bool {
+        let slow = self.slow_threshold.is_some_and(|t| elapsed > t);
+        {
+            let mut stats = self.stats.lock().unwrap();
+            let s = stats.entry(kind).or_default();
+            s.count += 1;
+            s.total += elapsed;
+            s.max = s.max.max(elapsed);
+            if slow {
+                s.slow += 1;
+            }
+        }
+        if slow {
+            eprintln!(
+                "warning: channel work queue item ({kind}) took {elapsed:?}, exceeding {:?}; LB policy: {}",
+                self.slow_threshold.unwrap(),
+                policy_name.unwrap_or("<none>")
+            );
+        }
+        slow
+    }
+
+    /// Records that an item of the given kind panicked with message.
+    pub(crate) fn record_panic(
+        &self,
+        kind: WorkItemKind,
+        message: &str,
+        policy_name: Option<&str>,
+    ) {
+        self.stats.lock().unwrap().entry(kind).or_default().panics += 1;
+        eprintln!(
+            "error: channel work queue item ({kind}) panicked: {message}; LB policy: {}",
+            policy_name.unwrap_or("<none>")
+        );
+    }
+
+    /// Returns the number of items of any kind which panicked.
+    pub(crate) fn panics(&self) -> u64 {
+        self.stats.lock().unwrap().values().map(|s| s.panics).sum()
+    }
+
+    /// Returns the statistics recorded for the given kind of item.
+    pub(crate) fn stats(&self, kind: WorkItemKind) -> WorkItemStats {
+        self.stats
+            .lock()
+            .unwrap()
+            .get(&kind)
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// Returns the message of a panic, given its payload.
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "<unknown panic>"
+    }
+}
+
+/// Holds the latest value of an update waiting on the work queue.
+pub(crate) struct Coalesced<T> {
+    pending: Mutex<Option<T>>,
+}
+
+impl<T> Coalesced<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            pending: Mutex::new(None),
+        }
+    }
+
+    /// Stores value as the pending update, replacing any previous one.
+    /// Returns true if no update was pending, in which case the caller must
+    /// queue an item which delivers the value returned by take.
+    pub(crate) fn put(&self, value: T) -> bool {
+        self.pending.lock().unwrap().replace(value).is_none()
+    }
+
+    /// Returns the pending update, if it was not taken already.
+    pub(crate) fn take(&self) -> Option<T> {
+        self.pending.lock().unwrap().take()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn records_durations_per_kind() {
+        let monitor = WorkQueueMonitor::new(Some(Duration::from_millis(50)));
+        assert!(!monitor.record(
+            WorkItemKind::Work,
+            Duration::from_millis(10),
+            Some("pick_first")
+        ));
+        assert!(monitor.record(
+            WorkItemKind::Work,
+            Duration::from_millis(60),
+            Some("pick_first")
+        ));
+        assert!(!monitor.record(WorkItemKind::ResolverUpdate, Duration::from_millis(1), None));
+
+        let work = monitor.stats(WorkItemKind::Work);
+        assert_eq!(work.count, 2);
+        assert_eq!(work.total, Duration::from_millis(70));
+        assert_eq!(work.max, Duration::from_millis(60));
+        assert_eq!(work.slow, 1);
+        assert_eq!(monitor.stats(WorkItemKind::ResolverUpdate).count, 1);
+        assert_eq!(
+            monitor.stats(WorkItemKind::SubchannelUpdate),
+            WorkItemStats::default()
+        );
+    }
+
+    #[test]
+    fn records_panics() {
+        let monitor = WorkQueueMonitor::new(None);
+        monitor.record_panic(WorkItemKind::ResolverUpdate, "boom", Some("pick_first"));
+        monitor.record_panic(WorkItemKind::Work, "boom", None);
+        assert_eq!(monitor.stats(WorkItemKind::ResolverUpdate).panics, 1);
+        assert_eq!(monitor.panics(), 2);
+
+        let payload = std::panic::catch_unwind(|| panic!("{} failed", "policy")).unwrap_err();
+        assert_eq!(panic_message(&*payload), "policy failed");
+        let payload = std::panic::catch_unwind(|| panic!("static")).unwrap_err();
+        assert_eq!(panic_message(&*payload), "static");
+    }
+
+    #[test]
+    fn no_threshold_never_warns() {
+        let monitor = WorkQueueMonitor::new(None);
+        assert!(!monitor.record(WorkItemKind::Work, Duration::from_secs(10), None));
+        assert_eq!(monitor.stats(WorkItemKind::Work).slow, 0);
+    }
+
+    #[test]
+    fn coalesced_updates_deliver_the_latest_value() {
+        let pending = Coalesced::new();
+        assert!(pending.put(1));
+        assert!(!pending.put(2));
+        assert_eq!(pending.take(), Some(2));
+        assert_eq!(pending.take(), None);
+        assert!(pending.put(3));
+    }
+
+    #[test]
+    fn storms_of_updates_queue_one_item() {
+        // A storm of updates arriving while the queue is busy, as when a
+        // backend flaps, and with each update queued separately as before
+        // updates were coalesced.
+        const UPDATES: usize = 10_000;
+        let pending = Coalesced::new();
+        let mut queued = 0;
+        for i in 0..UPDATES {
+            if pending.put(i) {
+                queued += 1;
+            }
+        }
+        assert_eq!(queued, 1);
+        assert_eq!(pending.take(), Some(UPDATES - 1));
+    }
+}
@@ -1,9 +1,23 @@
+use std::{
+    any::Any,
+    future::Future,
+    pin::{pin, Pin},
+    sync::{Arc, OnceLock},
+    task::{ready, Context, Poll},
+};
+
 use bytes::{Buf, BufMut, Bytes};
+use tokio_stream::{Stream, StreamExt};
 use tonic::{
     codec::{Codec, Decoder, EncodeBuf, Encoder},
-    Status,
+    metadata::MetadataMap,
+    Code, Request as TonicRequest, Response as TonicResponse, Status, Streaming,
 };
 
+use crate::service::{status_response, Message, Request, Response};
+
+type BoxStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
+
 /// An adapter for sending and receiving messages as bytes using tonic.
 /// Coding/decoding is handled within gRPC.
 /// TODO: Remove this when tonic allows access to bytes without requiring a
@@ -51,3 +65,139 @@ impl Decoder for BytesDecoder {
         Ok(Some(src.copy_to_bytes(src.remaining())))
     }
 }
+
+/// Converts a request into one whose messages are sent by tonic using
+/// [`BytesCodec`].
+pub(crate) fn convert_request(
+    req: Request,
+) -> TonicRequest<Pin<Box<dyn Stream<Item = Bytes> + Send>>> {
+    let (metadata, extensions, stream) = req.into_parts();
+
+    let bytes_stream = Box::pin(stream.filter_map(message_bytes));
+
+    TonicRequest::from_parts(metadata, extensions, bytes_stream as _)
+}
+
+/// Returns the bytes of a message sent using [`BytesCodec`], or None if it is
+/// not made of bytes.
+pub(crate) fn message_bytes(msg: Box<dyn Message>) -> Option<Bytes> {
+    if let Ok(bytes) = (msg as Box<dyn Any>).downcast::<Bytes>() {
+        Some(*bytes)
+    } else {
+        // If it fails, log the error and return None to filter it out.
+        eprintln!("A message could not be downcast to Bytes and was skipped.");
+        None
+    }
+}
+
+/// The trailers of a response which completed successfully, added to the
+/// extensions of responses by [`convert_response`].  Set once the message
+/// stream of the response has ended.  The trailers of failed responses are
+/// the metadata of their final status instead.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ResponseTrailers(Arc<OnceLock<MetadataMap>>);
+
+impl ResponseTrailers {
+    /// Returns the trailers received, if the response has completed.
+    pub(crate) fn get(&self) -> Option<&MetadataMap> {
+        self.0.get()
+    }
+}
+
+/// Converts the result of a call made by tonic using [`BytesCodec`] into a
+/// response.
+pub(crate) fn convert_response(res: Result<TonicResponse<Streaming<Bytes>>, Status>) -> Response {
+    let response = match res {
+        Ok(s) => s,
+        // A trailers-only response; its headers are in the status' metadata.
+        Err(e) => return status_response(e),
+    };
+    let (metadata, stream, mut extensions) = response.into_parts();
+    let trailers = ResponseTrailers::default();
+    extensions.insert(trailers.clone());
+    let stream = TrailerStream {
+        inner: stream,
+        trailers,
+    };
+    let message_stream: BoxStream<Box<dyn Message>> = Box::pin(stream.map(|msg| {
+        msg.map(|b| {
+            let msg: Box<dyn Message> = Box::new(b);
+            msg
+        })
+    }));
+    TonicResponse::from_parts(metadata, message_stream, extensions)
+}
+
+/// Converts a response produced by a server's handler into one as received
+/// by a client, for transports which call the handler directly.  Servers send
+/// trailers of successful responses as a final OK status, which is instead
+/// recorded in the [`ResponseTrailers`] of the response.
+pub(crate) fn trailers_from_status(response: Response) -> Response {
+    let (metadata, stream, mut extensions) = response.into_parts();
+    let trailers = extensions
+        .get::<ResponseTrailers>()
+        .cloned()
+        .unwrap_or_default();
+    extensions.insert(trailers.clone());
+    let stream: BoxStream<Box<dyn Message>> = Box::pin(OkStatusStream {
+        inner: stream,
+        trailers: Some(trailers),
+    });
+    TonicResponse::from_parts(metadata, stream, extensions)
+}
+
+pin_project_lite::pin_project! {
+    // Ends a response stream at an OK status, recording its metadata as the
+    // trailers of the response.
+    struct OkStatusStream<S> {
+        #[pin]
+        inner: S,
+        trailers: Option<ResponseTrailers>,
+    }
+}
+
+impl<S: Stream<Item = Result<Box<dyn Message>, Status>>> Stream for OkStatusStream<S> {
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let Some(trailers) = this.trailers.as_ref() else {
+            return Poll::Ready(None);
+        };
+        match ready!(this.inner.poll_next(cx)) {
+            Some(Err(status)) if status.code() == Code::Ok => {
+                let _ = trailers.0.set(status.metadata().clone());
+                *this.trailers = None;
+                Poll::Ready(None)
+            }
+            Some(item) => Poll::Ready(Some(item)),
+            None => {
+                *this.trailers = None;
+                Poll::Ready(None)
+            }
+        }
+    }
+}
+
+// Records the trailers of a response once its messages have been received.
+struct TrailerStream {
+    inner: Streaming<Bytes>,
+    trailers: ResponseTrailers,
+}
+
+impl Stream for TrailerStream {
+    type Item = Result<Bytes, Status>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let item = Pin::new(&mut this.inner).poll_next(cx);
+        if let Poll::Ready(None) = item {
+            // Once the stream has ended, its trailers have already been read,
+            // so fetching them completes immediately.
+            if let Poll::Ready(Ok(Some(trailers))) = pin!(this.inner.trailers()).poll(cx) {
+                let _ = this.trailers.0.set(trailers);
+            }
+        }
+        item
+    }
+}
@@ -0,0 +1,507 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! Support for the code tonic-build generates for this crate.  Not intended to
+//! be used directly.
+//!
+//! Generated clients and servers exchange messages with the channel and
+//! server as [`Bytes`], encoded and decoded by a [`Codec`].  The messages of
+//! a stream are encoded into the shared buffers of a [`BufferPool`], and are
+//! passed to the transport without being copied again.
+
+use std::{any::Any, future::Future, marker::PhantomData, pin::Pin};
+
+use bytes::BytesMut;
+use tokio_stream::StreamExt;
+
+pub use crate::client::Channel;
+pub use crate::service::Service;
+pub use bytes::Bytes;
+pub use std::sync::Arc;
+pub use tokio_stream::Stream;
+pub use tonic::{async_trait, IntoRequest, IntoStreamingRequest, Request, Response, Status};
+
+use crate::service::{self, status_response, Message};
+
+/// A stream of messages of a generated client or server.
+pub type BoxStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send + 'static>>;
+
+/// Encodes and decodes the messages of generated clients and servers.
+pub trait Codec: Default + Clone + Send + Sync + 'static {
+    /// The type of the messages sent.
+    type Encode: Send + Sync + 'static;
+    /// The type of the messages received.
+    type Decode: Send + 'static;
+
+    /// Encodes a message.
+    fn encode(&self, item: &Self::Encode) -> Bytes;
+
+    /// Encodes a message of a stream into a buffer of pool.  Codecs which can
+    /// encode into a provided buffer should override this; by default the
+    /// message is encoded with [`encode`](Codec::encode).
+    fn encode_pooled(&self, item: &Self::Encode, pool: &mut BufferPool) -> Bytes {
+        let _ = pool;
+        self.encode(item)
+    }
+
+    /// Decodes a message.
+    fn decode(&self, buf: Bytes) -> Result<Self::Decode, Status>;
+}
+
+/// A [`Codec`] for prost messages.
+#[cfg(feature = "prost")]
+pub struct ProstCodec<T, U>(PhantomData<fn(T) -> U>);
+
+#[cfg(feature = "prost")]
+impl<T, U> Default for ProstCodec<T, U> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[cfg(feature = "prost")]
+impl<T, U> Clone for ProstCodec<T, U> {
+    fn clone(&self) -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[cfg(feature = "prost")]
+impl<T, U> Codec for ProstCodec<T, U>
+where
+    T: prost::Message + 'static,
+    U: prost::Message + Default + 'static,
+{
+    type Encode = T;
+    type Decode = U;
+
+    fn encode(&self, item: &T) -> Bytes {
+        item.encode_to_vec().into()
+    }
+
+    fn encode_pooled(&self, item: &T, pool: &mut BufferPool) -> Bytes {
+        pool.encode(item.encoded_len(), |buf| {
+            item.encode(buf).expect("buffer has room for the message")
+        })
+    }
+
+    fn decode(&self, buf: Bytes) -> Result<U, Status> {
+        U::decode(buf).map_err(|err| Status::internal(err.to_string()))
+    }
+}
+
+/// Encodes messages into slices of shared buffers, so that a stream of small
+/// messages needs one allocation per buffer rather than one per message.
+///
+/// A buffer is reused once every message encoded into it has been dropped;
+/// until then, any message encoded into it keeps the whole buffer alive.
+#[derive(Debug)]
+pub struct BufferPool {
+    buf: BytesMut,
+    buffer_size: usize,
+}
+
+impl BufferPool {
+    /// The size of the buffers of [`BufferPool::new`].
+    pub const DEFAULT_BUFFER_SIZE: usize = 16 * 1024;
+
+    /// Creates a pool of buffers of [`DEFAULT_BUFFER_SIZE`](Self::DEFAULT_BUFFER_SIZE)
+    /// bytes.
+    pub fn new() -> Self {
+        Self::with_buffer_size(Self::DEFAULT_BUFFER_SIZE)
+    }
+
+    /// Creates a pool of buffers of buffer_size bytes.  Larger messages get a
+    /// buffer of their own size.
+    pub fn with_buffer_size(buffer_size: usize) -> Self {
+        Self {
+            buf: BytesMut::new(),
+            buffer_size,
+        }
+    }
+
+    /// Returns the bytes written by f, which is given a buffer with room for
+    /// at least len bytes.
+    pub fn encode(&mut self, len: usize, f: impl FnOnce(&mut BytesMut)) -> Bytes {
+        if self.buf.capacity() < len {
+            // Reclaims the current buffer if no message uses it anymore.
+            self.buf.reserve(len.max(self.buffer_size));
+        }
+        f(&mut self.buf);
+        self.buf.split().freeze()
+    }
+}
+
+impl Default for BufferPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Performs the calls of a generated client on a channel.
+#[derive(Clone)]
+pub struct Grpc {
+    channel: Channel,
+}
+
+impl Grpc {
+    pub fn new(channel: Channel) -> Self {
+        Self { channel }
+    }
+
+    pub async fn unary<C: Codec>(
+        &self,
+        path: &str,
+        request: Request<C::Encode>,
+        codec: C,
+    ) -> Result<Response<C::Decode>, Status> {
+        let request = request.map(tokio_stream::once);
+        let response = self.streaming(path, request, codec).await?;
+        into_unary(response).await
+    }
+
+    pub async fn server_streaming<C: Codec>(
+        &self,
+        path: &str,
+        request: Request<C::Encode>,
+        codec: C,
+    ) -> Result<Response<BoxStream<C::Decode>>, Status> {
+        let request = request.map(tokio_stream::once);
+        self.streaming(path, request, codec).await
+    }
+
+    pub async fn client_streaming<C, S>(
+        &self,
+        path: &str,
+        request: Request<S>,
+        codec: C,
+    ) -> Result<Response<C::Decode>, Status>
+    where
+        C: Codec,
+        S: Stream<Item = C::Encode> + Send + Sync + 'static,
+    {
+        let response = self.streaming(path, request, codec).await?;
+        into_unary(response).await
+    }
+
+    /// Performs a bidirectional streaming call.  Failures are reported by the
+    /// response stream, so that the response headers are available before the
+    /// first message.
+    pub async fn streaming<C, S>(
+        &self,
+        path: &str,
+        request: Request<S>,
+        codec: C,
+    ) -> Result<Response<BoxStream<C::Decode>>, Status>
+    where
+        C: Codec,
+        S: Stream<Item = C::Encode> + Send + Sync + 'static,
+    {
+        let encoder = codec.clone();
+        let mut pool = BufferPool::new();
+        let request = request.map(|stream| {
+            Box::pin(stream.map(move |msg| {
+                Box::new(encoder.encode_pooled(&msg, &mut pool)) as Box<dyn Message>
+            })) as Pin<Box<dyn Stream<Item = Box<dyn Message>> + Send + Sync>>
+        });
+        let response = self.channel.call(path.to_string(), request).await;
+        Ok(response.map(|stream| decode_stream(stream, codec)))
+    }
+}
+
+async fn into_unary<T: Send + 'static>(
+    response: Response<BoxStream<T>>,
+) -> Result<Response<T>, Status> {
+    let (metadata, mut stream, extensions) = response.into_parts();
+    let msg = stream
+        .next()
+        .await
+        .ok_or_else(|| Status::internal("missing response message"))??;
+    Ok(Response::from_parts(metadata, msg, extensions))
+}
+
+fn decode_stream<C, S>(stream: S, codec: C) -> BoxStream<C::Decode>
+where
+    C: Codec,
+    S: Stream<Item = Result<Box<dyn Message>, Status>> + Send + 'static,
+{
+    Box::pin(stream.map(move |msg| {
+        let bytes = (msg? as Box<dyn Any>)
+            .downcast::<Bytes>()
+            .map_err(|_| Status::internal("message is not encoded"))?;
+        codec.decode(*bytes)
+    }))
+}
+
+fn encode_response<C: Codec>(
+    result: Result<Response<BoxStream<C::Encode>>, Status>,
+    codec: C,
+) -> service::Response {
+    match result {
+        Ok(response) => response.map(|stream| {
+            let mut pool = BufferPool::new();
+            Box::pin(stream.map(move |msg| {
+                msg.map(|msg| Box::new(codec.encode_pooled(&msg, &mut pool)) as Box<dyn Message>)
+            })) as Pin<Box<dyn Stream<Item = Result<Box<dyn Message>, Status>> + Send>>
+        }),
+        Err(status) => status_response(status),
+    }
+}
+
+fn decode_request<C: Codec>(request: service::Request, codec: C) -> Request<BoxStream<C::Decode>> {
+    request.map(|stream| decode_stream(stream.map(Ok), codec))
+}
+
+async fn into_unary_request<T: Send + 'static>(
+    request: Request<BoxStream<T>>,
+) -> Result<Request<T>, Status> {
+    let (metadata, extensions, mut stream) = request.into_parts();
+    let msg = stream
+        .next()
+        .await
+        .ok_or_else(|| Status::internal("missing request message"))??;
+    Ok(Request::from_parts(metadata, extensions, msg))
+}
+
+fn once_response<T: Send + 'static>(
+    result: Result<Response<T>, Status>,
+) -> Result<Response<BoxStream<T>>, Status> {
+    result.map(|response| response.map(|msg| Box::pin(tokio_stream::once(Ok(msg))) as _))
+}
+
+/// Serves a unary method of a generated server.
+pub async fn serve_unary<C, F, Fut>(request: service::Request, codec: C, f: F) -> service::Response
+where
+    C: Codec,
+    F: FnOnce(Request<C::Decode>) -> Fut,
+    Fut: Future<Output = Result<Response<C::Encode>, Status>>,
+{
+    let request = match into_unary_request(decode_request(request, codec.clone())).await {
+        Ok(request) => request,
+        Err(status) => return status_response(status),
+    };
+    encode_response(once_response(f(request).await), codec)
+}
+
+/// Serves a server streaming method of a generated server.
+pub async fn serve_server_streaming<C, F, Fut>(
+    request: service::Request,
+    codec: C,
+    f: F,
+) -> service::Response
+where
+    C: Codec,
+    F: FnOnce(Request<C::Decode>) -> Fut,
+    Fut: Future<Output = Result<Response<BoxStream<C::Encode>>, Status>>,
+{
+    let request = match into_unary_request(decode_request(request, codec.clone())).await {
+        Ok(request) => request,
+        Err(status) => return status_response(status),
+    };
+    encode_response(f(request).await, codec)
+}
+
+/// Serves a client streaming method of a generated server.
+pub async fn serve_client_streaming<C, F, Fut>(
+    request: service::Request,
+    codec: C,
+    f: F,
+) -> service::Response
+where
+    C: Codec,
+    F: FnOnce(Request<BoxStream<C::Decode>>) -> Fut,
+    Fut: Future<Output = Result<Response<C::Encode>, Status>>,
+{
+    let request = decode_request(request, codec.clone());
+    encode_response(once_response(f(request).await), codec)
+}
+
+/// Serves a bidirectional streaming method of a generated server.
+pub async fn serve_streaming<C, F, Fut>(
+    request: service::Request,
+    codec: C,
+    f: F,
+) -> service::Response
+where
+    C: Codec,
+    F: FnOnce(Request<BoxStream<C::Decode>>) -> Fut,
+    Fut: Future<Output = Result<Response<BoxStream<C::Encode>>, Status>>,
+{
+    let request = decode_request(request, codec.clone());
+    encode_response(f(request).await, codec)
+}
+
+/// Returns the response for a method a generated server does not implement.
+pub fn unimplemented(method: &str) -> service::Response {
+    status_response(Status::unimplemented(format!("unknown method {method}")))
+}
+
+#[cfg(all(test, feature = "prost"))]
+mod test {
+    use super::*;
+    use crate::client::ChannelOptions;
+    use crate::echo_grpc::{
+        echo_client::EchoClient,
+        echo_server::{Echo, EchoServer},
+        EchoRequest, EchoResponse,
+    };
+    use crate::{inmemory, server};
+
+    struct EchoService {}
+
+    #[async_trait]
+    impl Echo for EchoService {
+        async fn unary_echo(
+            &self,
+            request: Request<EchoRequest>,
+        ) -> Result<Response<EchoResponse>, Status> {
+            let message = request.into_inner().message;
+            if message.is_empty() {
+                return Err(Status::invalid_argument("empty message"));
+            }
+            Ok(Response::new(EchoResponse { message }))
+        }
+
+        async fn server_streaming_echo(
+            &self,
+            request: Request<EchoRequest>,
+        ) -> Result<Response<BoxStream<EchoResponse>>, Status> {
+            let message = request.into_inner().message;
+            let stream = tokio_stream::iter((0..3).map(move |i| {
+                Ok(EchoResponse {
+                    message: format!("{message} {i}"),
+                })
+            }));
+            Ok(Response::new(Box::pin(stream)))
+        }
+
+        async fn client_streaming_echo(
+            &self,
+            request: Request<BoxStream<EchoRequest>>,
+        ) -> Result<Response<EchoResponse>, Status> {
+            let mut stream = request.into_inner();
+            let mut messages = Vec::new();
+            while let Some(req) = stream.next().await {
+                messages.push(req?.message);
+            }
+            Ok(Response::new(EchoResponse {
+                message: messages.join(","),
+            }))
+        }
+
+        async fn bidirectional_streaming_echo(
+            &self,
+            request: Request<BoxStream<EchoRequest>>,
+        ) -> Result<Response<BoxStream<EchoResponse>>, Status> {
+            let stream = request.into_inner().map(|req| {
+                req.map(|req| EchoResponse {
+                    message: req.message,
+                })
+            });
+            Ok(Response::new(Box::pin(stream)))
+        }
+    }
+
+    fn request(message: &str) -> EchoRequest {
+        EchoRequest {
+            message: message.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn generated_client_and_server() {
+        inmemory::reg();
+        let lis = inmemory::Listener::new();
+        let mut srv = server::Server::new();
+        srv.set_handler(EchoServer::new(EchoService {}));
+        let lis_clone = lis.clone();
+        tokio::task::spawn(async move {
+            srv.serve(&lis_clone).await;
+        });
+        let chan = Channel::new(lis.target().as_str(), None, ChannelOptions::default());
+        let client = EchoClient::new(chan);
+
+        let res = client.unary_echo(request("hello")).await.unwrap();
+        assert_eq!(res.into_inner().message, "hello");
+
+        let status = client.unary_echo(request("")).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+        let res = client.server_streaming_echo(request("hi")).await.unwrap();
+        let messages: Vec<_> = res
+            .into_inner()
+            .map(|res| res.unwrap().message)
+            .collect()
+            .await;
+        assert_eq!(messages, vec!["hi 0", "hi 1", "hi 2"]);
+
+        let reqs = tokio_stream::iter(vec![request("a"), request("b")]);
+        let res = client.client_streaming_echo(reqs).await.unwrap();
+        assert_eq!(res.into_inner().message, "a,b");
+
+        let reqs = tokio_stream::iter(vec![request("x"), request("y")]);
+        let res = client.bidirectional_streaming_echo(reqs).await.unwrap();
+        let messages: Vec<_> = res
+            .into_inner()
+            .map(|res| res.unwrap().message)
+            .collect()
+            .await;
+        assert_eq!(messages, vec!["x", "y"]);
+
+        lis.close().await;
+    }
+
+    #[tokio::test]
+    async fn unknown_method_is_unimplemented() {
+        let server = EchoServer::new(EchoService {});
+        let req = Request::new(Box::pin(tokio_stream::empty())
+            as Pin<Box<dyn Stream<Item = Box<dyn Message>> + Send + Sync>>);
+        let res = server
+            .call("/grpc.examples.echo.Echo/Unknown".to_string(), req)
+            .await;
+        let status = res.into_inner().next().await.unwrap().unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unimplemented);
+    }
+
+    #[test]
+    fn buffer_pool_shares_and_reuses_buffers() {
+        let codec = ProstCodec::<EchoRequest, EchoResponse>::default();
+        let mut pool = BufferPool::with_buffer_size(1024);
+        let a = codec.encode_pooled(&request("a"), &mut pool);
+        let b = codec.encode_pooled(&request("b"), &mut pool);
+        assert_eq!(a, codec.encode(&request("a")));
+        // Consecutive messages are slices of the same buffer.
+        assert_eq!(a.as_ptr() as usize + a.len(), b.as_ptr() as usize);
+
+        // Once its messages are dropped, the buffer is reused when more room
+        // is needed.
+        let start = a.as_ptr();
+        drop((a, b));
+        let large = EchoRequest {
+            message: "x".repeat(1020),
+        };
+        let c = codec.encode_pooled(&large, &mut pool);
+        assert_eq!(c.as_ptr(), start);
+    }
+}
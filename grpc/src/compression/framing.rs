@@ -0,0 +1,340 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! Applying compression to the length-prefixed messages of a gRPC HTTP body.
+//!
+//! Transports frame messages with tonic, which only knows the standard
+//! encodings, so messages are compressed and decompressed by rewriting the
+//! frames of the HTTP bodies passing between tonic and the connection.
+
+use std::{
+    pin::Pin,
+    sync::Arc,
+    task::{ready, Context, Poll},
+};
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use http::{HeaderMap, HeaderValue};
+use http_body::{Body as HttpBody, Frame};
+use tonic::{body::Body, Status};
+
+use super::{
+    split_accept_encoding, CompressionPolicy, Compressor, MessageCompression,
+    ACCEPT_ENCODING_HEADER, ENCODING_HEADER,
+};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+// The length of the compressed flag and message length preceding each
+// message.
+const HEADER_LEN: usize = 5;
+
+/// Advertises the encodings of compression in headers and, if the peer
+/// accepts one of them for method, compresses the messages of body with it.
+/// peer_accept_encoding is the peer's `grpc-accept-encoding` header.
+pub(crate) fn encode<B>(
+    compression: &MessageCompression,
+    method: &str,
+    peer_accept_encoding: &str,
+    headers: &mut HeaderMap,
+    body: B,
+) -> Body
+where
+    B: HttpBody<Data = Bytes> + Unpin + Send + 'static,
+    B::Error: Into<BoxError>,
+{
+    if let Some(accept) = compression.accept_encoding() {
+        if let Ok(accept) = HeaderValue::from_str(&accept) {
+            headers.insert(ACCEPT_ENCODING_HEADER, accept);
+        }
+    }
+    let Some(compressor) = compression.compressor(method, peer_accept_encoding) else {
+        return Body::new(body);
+    };
+    let Ok(name) = HeaderValue::from_str(compressor.name()) else {
+        return Body::new(body);
+    };
+    headers.insert(ENCODING_HEADER, name);
+    let policy = compression.policy.clone();
+    Body::new(Reframe::new(
+        body,
+        usize::MAX,
+        move |compressed, message| compress(&policy, &*compressor, compressed, message),
+    ))
+}
+
+/// Decompresses the messages of body if headers name an encoding of
+/// compression, removing the encoding from headers.  Other encodings are left
+/// for tonic to handle.  Messages larger than the maximum decoding message
+/// size of compression, before or after decompression, fail the body with
+/// RESOURCE_EXHAUSTED.
+pub(crate) fn decode<B>(compression: &MessageCompression, headers: &mut HeaderMap, body: B) -> Body
+where
+    B: HttpBody<Data = Bytes> + Unpin + Send + 'static,
+    B::Error: Into<BoxError>,
+{
+    let Some(compressor) = headers
+        .get(ENCODING_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|encoding| compression.decompressor(encoding))
+    else {
+        return Body::new(body);
+    };
+    headers.remove(ENCODING_HEADER);
+    let max_size = compression.max_decoding_message_size;
+    Body::new(Reframe::new(body, max_size, move |compressed, message| {
+        decompress(&*compressor, max_size, compressed, message)
+    }))
+}
+
+/// Returns the `grpc-accept-encoding` header of headers, or an empty string.
+pub(crate) fn accept_encoding(headers: &HeaderMap) -> String {
+    headers
+        .get(ACCEPT_ENCODING_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| split_accept_encoding(v).collect::<Vec<_>>().join(","))
+        .unwrap_or_default()
+}
+
+fn compress(
+    policy: &CompressionPolicy,
+    compressor: &dyn Compressor,
+    compressed: bool,
+    message: Bytes,
+) -> Result<(bool, Bytes), Status> {
+    if compressed {
+        // Already compressed by tonic with a standard encoding.
+        return Ok((true, message));
+    }
+    match policy.compress(compressor, &message) {
+        Ok(Some(message)) => Ok((true, message)),
+        Ok(None) => Ok((false, message)),
+        Err(err) => Err(Status::internal(format!(
+            "failed to compress message with {}: {err}",
+            compressor.name()
+        ))),
+    }
+}
+
+fn decompress(
+    compressor: &dyn Compressor,
+    max_size: usize,
+    compressed: bool,
+    message: Bytes,
+) -> Result<(bool, Bytes), Status> {
+    if !compressed {
+        return Ok((false, message));
+    }
+    match compressor.decompress(&message, max_size) {
+        Ok(message) if message.len() > max_size => Err(too_large(max_size)),
+        Ok(message) => Ok((false, message)),
+        Err(err) => Err(Status::internal(format!(
+            "failed to decompress message with {}: {err}",
+            compressor.name()
+        ))),
+    }
+}
+
+fn too_large(max_size: usize) -> Status {
+    Status::resource_exhausted(format!(
+        "received message larger than the maximum of {max_size} bytes"
+    ))
+}
+
+// A body whose messages are rewritten by a function of their compressed flag
+// and contents.  Messages longer than max_len fail the body without being
+// buffered.
+struct Reframe<B, F> {
+    inner: B,
+    buf: BytesMut,
+    max_len: usize,
+    rewrite: F,
+}
+
+impl<B, F> Reframe<B, F> {
+    fn new(inner: B, max_len: usize, rewrite: F) -> Self {
+        Self {
+            inner,
+            buf: BytesMut::new(),
+            max_len,
+            rewrite,
+        }
+    }
+}
+
+impl<B, F> Reframe<B, F>
+where
+    F: FnMut(bool, Bytes) -> Result<(bool, Bytes), Status>,
+{
+    // Rewrites the next message if it has been received completely.
+    fn next_message(&mut self) -> Result<Option<Bytes>, Status> {
+        if self.buf.len() < HEADER_LEN {
+            return Ok(None);
+        }
+        let len = u32::from_be_bytes([self.buf[1], self.buf[2], self.buf[3], self.buf[4]]);
+        let len = len as usize;
+        if len > self.max_len {
+            return Err(too_large(self.max_len));
+        }
+        if self.buf.len() < HEADER_LEN + len {
+            return Ok(None);
+        }
+        let compressed = self.buf.get_u8() != 0;
+        self.buf.advance(HEADER_LEN - 1);
+        let message = self.buf.split_to(len).freeze();
+        let (compressed, message) = (self.rewrite)(compressed, message)?;
+        let len = u32::try_from(message.len())
+            .map_err(|_| Status::resource_exhausted("message is too large"))?;
+        let mut frame = BytesMut::with_capacity(HEADER_LEN + message.len());
+        frame.put_u8(compressed.into());
+        frame.put_u32(len);
+        frame.put(message);
+        Ok(Some(frame.freeze()))
+    }
+}
+
+impl<B, F> HttpBody for Reframe<B, F>
+where
+    B: HttpBody<Data = Bytes> + Unpin,
+    B::Error: Into<BoxError>,
+    F: FnMut(bool, Bytes) -> Result<(bool, Bytes), Status> + Unpin,
+{
+    type Data = Bytes;
+    type Error = BoxError;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, BoxError>>> {
+        loop {
+            match self.next_message() {
+                Ok(Some(frame)) => return Poll::Ready(Some(Ok(Frame::data(frame)))),
+                Ok(None) => {}
+                Err(status) => return Poll::Ready(Some(Err(status.into()))),
+            }
+            match ready!(Pin::new(&mut self.inner).poll_frame(cx)) {
+                Some(Ok(frame)) => match frame.into_data() {
+                    Ok(data) => self.buf.extend_from_slice(&data),
+                    Err(frame) => return Poll::Ready(Some(Ok(frame))),
+                },
+                Some(Err(err)) => return Poll::Ready(Some(Err(err.into()))),
+                None if self.buf.is_empty() => return Poll::Ready(None),
+                None => {
+                    self.buf.clear();
+                    let status = Status::internal("body ended within a message");
+                    return Poll::Ready(Some(Err(status.into())));
+                }
+            }
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.buf.is_empty() && self.inner.is_end_stream()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::{BufMut, Bytes, BytesMut};
+    use http_body_util::{BodyExt, Full};
+    use tonic::{Code, Status};
+
+    use super::{decompress, Reframe};
+    use crate::compression::Compressor;
+
+    fn frame(compressed: bool, message: &[u8]) -> Bytes {
+        let mut frame = BytesMut::new();
+        frame.put_u8(compressed.into());
+        frame.put_u32(message.len() as u32);
+        frame.put_slice(message);
+        frame.freeze()
+    }
+
+    #[tokio::test]
+    async fn rewrites_each_message() {
+        let mut data = BytesMut::new();
+        data.put(frame(false, b"abc"));
+        data.put(frame(true, b"de"));
+        let body = Reframe::new(
+            Full::new(data.freeze()),
+            usize::MAX,
+            |compressed: bool, message: Bytes| {
+                Ok::<_, Status>((!compressed, message.repeat(2).into()))
+            },
+        );
+        let out = body.collect().await.unwrap().to_bytes();
+        let mut want = BytesMut::new();
+        want.put(frame(true, b"abcabc"));
+        want.put(frame(false, b"dede"));
+        assert_eq!(out, want.freeze());
+    }
+
+    #[tokio::test]
+    async fn fails_on_truncated_messages() {
+        let data = frame(false, b"abc").slice(..6);
+        let body = Reframe::new(Full::new(data), usize::MAX, |compressed, message| {
+            Ok::<_, Status>((compressed, message))
+        });
+        assert!(body.collect().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_messages_over_the_limit_before_buffering() {
+        // Only the header of the message is ever received.
+        let data = frame(false, &[0; 100]).slice(..5);
+        let body = Reframe::new(Full::new(data), 10, |compressed, message| {
+            Ok::<_, Status>((compressed, message))
+        });
+        let err = body.collect().await.unwrap_err();
+        let status = err.downcast::<Status>().unwrap();
+        assert_eq!(status.code(), Code::ResourceExhausted);
+    }
+
+    // Decompresses every message to 1000 bytes.
+    struct Inflating;
+
+    impl Compressor for Inflating {
+        fn name(&self) -> &str {
+            "inflating"
+        }
+
+        fn compress(&self, message: &[u8]) -> Result<Bytes, String> {
+            Ok(Bytes::copy_from_slice(message))
+        }
+
+        fn decompress(&self, _: &[u8], max_size: usize) -> Result<Bytes, String> {
+            Ok(vec![0; 1000.min(max_size + 1)].into())
+        }
+    }
+
+    #[test]
+    fn rejects_messages_decompressing_over_the_limit() {
+        let message = Bytes::from_static(b"small");
+        let status = decompress(&Inflating, 100, true, message.clone()).unwrap_err();
+        assert_eq!(status.code(), Code::ResourceExhausted);
+        let (compressed, message) = decompress(&Inflating, 1000, true, message).unwrap();
+        assert!(!compressed);
+        assert_eq!(message.len(), 1000);
+    }
+}
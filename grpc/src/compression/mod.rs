@@ -0,0 +1,307 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! Message compression.
+//!
+//! A [`Compressor`] implements a single value of the `grpc-encoding` header.
+//! Besides the standard encodings, the `zstd` feature enables dictionary-based
+//! zstd compression; see [`zstd_dictionary`].
+//!
+//! Negotiating an encoding does not require compressing every message: each
+//! message carries its own compressed flag.  A [`CompressionPolicy`] decides
+//! per message whether compressing is worthwhile.
+
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use bytes::Bytes;
+
+pub(crate) mod framing;
+#[cfg(feature = "zstd")]
+pub mod zstd_dictionary;
+
+/// The header naming the encoding of a message.
+pub const ENCODING_HEADER: &str = "grpc-encoding";
+/// The header listing the encodings a peer is able to decode.
+pub const ACCEPT_ENCODING_HEADER: &str = "grpc-accept-encoding";
+/// The maximum size of a received message by default, before and after
+/// decompression.  Matches the default of tonic's codecs.
+pub const DEFAULT_MAX_DECODING_MESSAGE_SIZE: usize = 4 * 1024 * 1024;
+
+/// Compresses and decompresses messages for one encoding.
+pub trait Compressor: Send + Sync {
+    /// The name of the encoding, as sent in the `grpc-encoding` header.
+    fn name(&self) -> &str;
+
+    /// Compresses a serialized message.
+    fn compress(&self, message: &[u8]) -> Result<Bytes, String>;
+
+    /// Decompresses a message received with this encoding.  Messages which
+    /// decompress to more than max_size bytes are rejected by the caller, so
+    /// implementations should stop once they have produced max_size + 1
+    /// bytes.
+    fn decompress(&self, message: &[u8], max_size: usize) -> Result<Bytes, String>;
+}
+
+/// Splits the value of a `grpc-accept-encoding` header into encoding names.
+pub(crate) fn split_accept_encoding(value: &str) -> impl Iterator<Item = &str> {
+    value.split(',').map(str::trim).filter(|s| !s.is_empty())
+}
+
+/// The number of bytes sampled when estimating the entropy of a message.
+const ENTROPY_SAMPLE_SIZE: usize = 4096;
+
+/// Decides, per message, whether to compress with the negotiated encoding or
+/// send the message uncompressed.  Compressing tiny messages, or data which is
+/// already compressed or encrypted, costs CPU without reducing its size.
+///
+/// Clones share their statistics.
+#[derive(Debug, Clone, Default)]
+pub struct CompressionPolicy {
+    min_size: usize,
+    max_entropy: Option<f64>,
+    stats: Arc<Counters>,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    compressed: AtomicU64,
+    skipped_too_small: AtomicU64,
+    skipped_high_entropy: AtomicU64,
+}
+
+/// Counts of the messages a [`CompressionPolicy`] was applied to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompressionStats {
+    /// Messages which were compressed.
+    pub compressed: u64,
+    /// Messages sent uncompressed because they were smaller than the minimum
+    /// size.
+    pub skipped_too_small: u64,
+    /// Messages sent uncompressed because their estimated entropy was above
+    /// the maximum.
+    pub skipped_high_entropy: u64,
+}
+
+impl CompressionPolicy {
+    /// Creates a policy which compresses every message.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Messages smaller than min_size bytes are sent uncompressed.
+    pub fn min_size(self, min_size: usize) -> Self {
+        Self { min_size, ..self }
+    }
+
+    /// Messages whose estimated entropy exceeds bits_per_byte (between 0 and
+    /// 8) are sent uncompressed.  The estimate is based on the byte
+    /// distribution of the start of the message; values around 7.5 detect data
+    /// which is already compressed or encrypted.
+    pub fn max_entropy(self, bits_per_byte: f64) -> Self {
+        Self {
+            max_entropy: Some(bits_per_byte),
+            ..self
+        }
+    }
+
+    /// Compresses message with compressor unless the policy skips it.  Returns
+    /// None if the message should be sent uncompressed.
+    pub fn compress(
+        &self,
+        compressor: &dyn Compressor,
+        message: &[u8],
+    ) -> Result<Option<Bytes>, String> {
+        if message.len() < self.min_size {
+            self.stats.skipped_too_small.fetch_add(1, Ordering::Relaxed);
+            return Ok(None);
+        }
+        if self
+            .max_entropy
+            .is_some_and(|max| estimate_entropy(message) > max)
+        {
+            self.stats
+                .skipped_high_entropy
+                .fetch_add(1, Ordering::Relaxed);
+            return Ok(None);
+        }
+        let compressed = compressor.compress(message)?;
+        self.stats.compressed.fetch_add(1, Ordering::Relaxed);
+        Ok(Some(compressed))
+    }
+
+    /// Returns the counts of messages this policy was applied to.
+    pub fn stats(&self) -> CompressionStats {
+        CompressionStats {
+            compressed: self.stats.compressed.load(Ordering::Relaxed),
+            skipped_too_small: self.stats.skipped_too_small.load(Ordering::Relaxed),
+            skipped_high_entropy: self.stats.skipped_high_entropy.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// The compression settings of a channel or server: the encodings it is able
+/// to send and receive messages with, and the policy deciding which outgoing
+/// messages are compressed.  Clones share their statistics and dictionaries.
+#[derive(Clone)]
+pub struct MessageCompression {
+    pub(crate) policy: CompressionPolicy,
+    #[cfg(feature = "zstd")]
+    pub(crate) dictionaries: Option<Arc<zstd_dictionary::DictionaryRegistry>>,
+    // The maximum size of received messages, compressed or not.
+    pub(crate) max_decoding_message_size: usize,
+}
+
+impl Default for MessageCompression {
+    fn default() -> Self {
+        Self {
+            policy: CompressionPolicy::default(),
+            #[cfg(feature = "zstd")]
+            dictionaries: None,
+            max_decoding_message_size: DEFAULT_MAX_DECODING_MESSAGE_SIZE,
+        }
+    }
+}
+
+impl MessageCompression {
+    /// Returns the value to advertise in `grpc-accept-encoding`, if any
+    /// encodings are available.
+    pub(crate) fn accept_encoding(&self) -> Option<String> {
+        #[cfg(feature = "zstd")]
+        if let Some(dictionaries) = &self.dictionaries {
+            let accept = dictionaries.accept_encoding();
+            return (!accept.is_empty()).then_some(accept);
+        }
+        None
+    }
+
+    /// Selects the compressor for the messages sent on method, given the
+    /// value of the peer's `grpc-accept-encoding` header.
+    #[cfg_attr(not(feature = "zstd"), allow(unused_variables))]
+    pub(crate) fn compressor(
+        &self,
+        method: &str,
+        peer_accept_encoding: &str,
+    ) -> Option<Arc<dyn Compressor>> {
+        #[cfg(feature = "zstd")]
+        if let Some(dictionaries) = &self.dictionaries {
+            return dictionaries
+                .select(method, peer_accept_encoding)
+                .map(|c| c as Arc<dyn Compressor>);
+        }
+        None
+    }
+
+    /// Returns the compressor for messages received with the provided
+    /// `grpc-encoding`.
+    #[cfg_attr(not(feature = "zstd"), allow(unused_variables))]
+    pub(crate) fn decompressor(&self, encoding: &str) -> Option<Arc<dyn Compressor>> {
+        #[cfg(feature = "zstd")]
+        if let Some(dictionaries) = &self.dictionaries {
+            return dictionaries
+                .for_encoding(encoding)
+                .map(|c| c as Arc<dyn Compressor>);
+        }
+        None
+    }
+}
+
+/// Estimates the Shannon entropy, in bits per byte, of the start of message.
+fn estimate_entropy(message: &[u8]) -> f64 {
+    let sample = &message[..message.len().min(ENTROPY_SAMPLE_SIZE)];
+    if sample.is_empty() {
+        return 0.0;
+    }
+    let mut counts = [0usize; 256];
+    for &b in sample {
+        counts[b as usize] += 1;
+    }
+    let len = sample.len() as f64;
+    counts
+        .iter()
+        .filter(|&&c| c > 0)
+        .map(|&c| {
+            let p = c as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+
+    use super::{estimate_entropy, CompressionPolicy, CompressionStats, Compressor};
+
+    // Compresses runs of a repeated byte to the byte and the run length.
+    struct TestCompressor;
+
+    impl Compressor for TestCompressor {
+        fn name(&self) -> &str {
+            "test"
+        }
+
+        fn compress(&self, message: &[u8]) -> Result<Bytes, String> {
+            Ok(Bytes::from(vec![message[0], message.len() as u8]))
+        }
+
+        fn decompress(&self, message: &[u8], _: usize) -> Result<Bytes, String> {
+            Ok(Bytes::from(vec![message[0]; message[1] as usize]))
+        }
+    }
+
+    #[test]
+    fn entropy() {
+        assert_eq!(estimate_entropy(b""), 0.0);
+        assert_eq!(estimate_entropy(&[7; 100]), 0.0);
+        assert_eq!(estimate_entropy(&[0, 1, 0, 1]), 1.0);
+        let all: Vec<u8> = (0..=255).collect();
+        assert_eq!(estimate_entropy(&all), 8.0);
+    }
+
+    #[test]
+    fn skips_small_and_high_entropy_messages() {
+        let policy = CompressionPolicy::new().min_size(10).max_entropy(7.5);
+        let clone = policy.clone();
+
+        assert_eq!(policy.compress(&TestCompressor, &[1; 5]), Ok(None));
+        assert_eq!(
+            policy.compress(&TestCompressor, &[1; 20]),
+            Ok(Some(Bytes::from_static(&[1, 20])))
+        );
+        let random: Vec<u8> = (0..=255).collect();
+        assert_eq!(policy.compress(&TestCompressor, &random), Ok(None));
+
+        assert_eq!(
+            clone.stats(),
+            CompressionStats {
+                compressed: 1,
+                skipped_too_small: 1,
+                skipped_high_entropy: 1,
+            }
+        );
+    }
+}
@@ -0,0 +1,393 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! Zstandard compression with pre-shared dictionaries.
+//!
+//! Small, homogeneous messages (telemetry, for example) compress poorly on
+//! their own but very well against a dictionary trained on representative
+//! payloads.  Dictionaries are registered with a [`DictionaryRegistry`] under
+//! a numeric ID, either for the whole channel or for a single method, and are
+//! negotiated as the encoding `zstd-dict-<id>`: a peer advertises the IDs it
+//! holds in `grpc-accept-encoding` and the sender only uses a dictionary the
+//! receiver accepts.  A registry is installed on a channel with
+//! [`ChannelOptions::zstd_dictionaries`] and on a server with
+//! [`Server::set_zstd_dictionaries`].
+//!
+//! Dictionaries are rotated by registering a new ID for the same scope.  The
+//! newest dictionary is used for compression while older ones remain available
+//! for decompressing messages from peers which have not rotated yet, until
+//! they are retired.
+//!
+//! [`ChannelOptions::zstd_dictionaries`]: crate::client::ChannelOptions::zstd_dictionaries
+//! [`Server::set_zstd_dictionaries`]: crate::server::Server::set_zstd_dictionaries
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::Read,
+    sync::{Arc, RwLock},
+};
+
+use bytes::Bytes;
+use zstd::dict::{DecoderDictionary, EncoderDictionary};
+
+use super::{split_accept_encoding, Compressor};
+
+const ENCODING_PREFIX: &str = "zstd-dict-";
+const DEFAULT_LEVEL: i32 = 3;
+
+/// The set of RPCs a dictionary applies to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DictionaryScope {
+    /// All methods on the channel or server without a method-specific
+    /// dictionary.
+    Channel,
+    /// A single method, identified by its full path, e.g.
+    /// "/grpc.examples.echo.Echo/UnaryEcho".
+    Method(String),
+}
+
+/// Compresses with a single zstd dictionary.
+pub struct ZstdDictionaryCompressor {
+    id: u32,
+    name: String,
+    encoder: EncoderDictionary<'static>,
+    decoder: DecoderDictionary<'static>,
+}
+
+impl ZstdDictionaryCompressor {
+    /// Creates a compressor for the dictionary with the provided ID.
+    pub fn new(id: u32, dictionary: &[u8], level: i32) -> Self {
+        Self {
+            id,
+            name: encoding_name(id),
+            encoder: EncoderDictionary::copy(dictionary, level),
+            decoder: DecoderDictionary::copy(dictionary),
+        }
+    }
+
+    /// The ID of the dictionary.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+}
+
+impl Compressor for ZstdDictionaryCompressor {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn compress(&self, message: &[u8]) -> Result<Bytes, String> {
+        let mut encoder =
+            zstd::stream::read::Encoder::with_prepared_dictionary(message, &self.encoder)
+                .map_err(|e| e.to_string())?;
+        let mut out = Vec::new();
+        encoder.read_to_end(&mut out).map_err(|e| e.to_string())?;
+        Ok(out.into())
+    }
+
+    fn decompress(&self, message: &[u8], max_size: usize) -> Result<Bytes, String> {
+        let decoder = zstd::stream::read::Decoder::with_prepared_dictionary(message, &self.decoder)
+            .map_err(|e| e.to_string())?;
+        // Stop past the limit, so that small messages decompressing to large
+        // ones do not exhaust memory.
+        let limit = u64::try_from(max_size)
+            .unwrap_or(u64::MAX)
+            .saturating_add(1);
+        let mut out = Vec::new();
+        decoder
+            .take(limit)
+            .read_to_end(&mut out)
+            .map_err(|e| e.to_string())?;
+        Ok(out.into())
+    }
+}
+
+/// Returns the encoding name used for the dictionary with the provided ID.
+pub fn encoding_name(id: u32) -> String {
+    format!("{ENCODING_PREFIX}{id}")
+}
+
+/// Parses the dictionary ID out of an encoding name, if it names a zstd
+/// dictionary encoding.
+pub fn parse_encoding_name(name: &str) -> Option<u32> {
+    name.strip_prefix(ENCODING_PREFIX)?.parse().ok()
+}
+
+#[derive(Default)]
+struct Inner {
+    // All registered dictionaries by ID, used for decompression.
+    by_id: BTreeMap<u32, Arc<ZstdDictionaryCompressor>>,
+    // The dictionary currently used for compression in each scope.
+    current: HashMap<DictionaryScope, u32>,
+}
+
+/// Holds the dictionaries known to a channel or server.
+#[derive(Default)]
+pub struct DictionaryRegistry {
+    inner: RwLock<Inner>,
+}
+
+impl DictionaryRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a dictionary and makes it the one used for compression in
+    /// scope.  Any dictionary previously used for scope remains available for
+    /// decompression until retired.
+    ///
+    /// Returns an error if the ID is already registered.
+    pub fn register(
+        &self,
+        scope: DictionaryScope,
+        id: u32,
+        dictionary: &[u8],
+    ) -> Result<(), String> {
+        let mut inner = self.inner.write().unwrap();
+        if inner.by_id.contains_key(&id) {
+            return Err(format!("dictionary ID {id} is already registered"));
+        }
+        inner.by_id.insert(
+            id,
+            Arc::new(ZstdDictionaryCompressor::new(id, dictionary, DEFAULT_LEVEL)),
+        );
+        inner.current.insert(scope, id);
+        Ok(())
+    }
+
+    /// Removes a dictionary.  Messages using it can no longer be decompressed,
+    /// and scopes which used it for compression stop compressing with a
+    /// dictionary.
+    pub fn retire(&self, id: u32) {
+        let mut inner = self.inner.write().unwrap();
+        inner.by_id.remove(&id);
+        inner.current.retain(|_, v| *v != id);
+    }
+
+    /// Returns the compressor for the dictionary with the provided ID.
+    pub fn get(&self, id: u32) -> Option<Arc<ZstdDictionaryCompressor>> {
+        self.inner.read().unwrap().by_id.get(&id).cloned()
+    }
+
+    /// Returns the compressor to use for decompressing a message received with
+    /// the provided `grpc-encoding`.
+    pub fn for_encoding(&self, encoding: &str) -> Option<Arc<ZstdDictionaryCompressor>> {
+        self.get(parse_encoding_name(encoding)?)
+    }
+
+    /// Returns the value to advertise in `grpc-accept-encoding`, listing every
+    /// registered dictionary, newest ID first.
+    pub fn accept_encoding(&self) -> String {
+        let inner = self.inner.read().unwrap();
+        inner
+            .by_id
+            .keys()
+            .rev()
+            .map(|id| encoding_name(*id))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Selects the compressor for a message on method, given the value of the
+    /// peer's `grpc-accept-encoding` header.  Returns None if the current
+    /// dictionary for the method is not accepted by the peer, in which case
+    /// another encoding must be used.
+    pub fn select(
+        &self,
+        method: &str,
+        peer_accept_encoding: &str,
+    ) -> Option<Arc<ZstdDictionaryCompressor>> {
+        let inner = self.inner.read().unwrap();
+        let id = inner
+            .current
+            .get(&DictionaryScope::Method(method.to_string()))
+            .or_else(|| inner.current.get(&DictionaryScope::Channel))?;
+        let name = encoding_name(*id);
+        if !split_accept_encoding(peer_accept_encoding).any(|e| e == name) {
+            return None;
+        }
+        inner.by_id.get(id).cloned()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::compression::Compressor;
+
+    use super::{DictionaryRegistry, DictionaryScope};
+
+    const DICT: &[u8] = b"{\"cpu\":0.5,\"memory\":0.25,\"host\":\"backend.example.com\"}";
+
+    #[test]
+    fn round_trip() {
+        let registry = DictionaryRegistry::new();
+        registry
+            .register(DictionaryScope::Channel, 1, DICT)
+            .unwrap();
+        let msg = b"{\"cpu\":0.7,\"memory\":0.1,\"host\":\"backend.example.com\"}";
+        let compressor = registry.select("/svc/Method", "gzip, zstd-dict-1").unwrap();
+        assert_eq!(compressor.name(), "zstd-dict-1");
+        let compressed = compressor.compress(msg).unwrap();
+        let decompressor = registry.for_encoding("zstd-dict-1").unwrap();
+        assert_eq!(
+            &decompressor.decompress(&compressed, usize::MAX).unwrap()[..],
+            msg
+        );
+    }
+
+    #[test]
+    fn decompression_stops_past_the_limit() {
+        let registry = DictionaryRegistry::new();
+        registry
+            .register(DictionaryScope::Channel, 1, DICT)
+            .unwrap();
+        let compressor = registry.get(1).unwrap();
+        let compressed = compressor.compress(&[0; 1 << 20]).unwrap();
+        assert!(compressed.len() < 1024);
+        assert_eq!(
+            compressor.decompress(&compressed, 1024).unwrap().len(),
+            1025
+        );
+    }
+
+    #[test]
+    fn negotiation_and_rotation() {
+        let registry = DictionaryRegistry::new();
+        registry
+            .register(DictionaryScope::Channel, 1, DICT)
+            .unwrap();
+        registry
+            .register(DictionaryScope::Method("/svc/A".to_string()), 2, DICT)
+            .unwrap();
+        assert!(registry
+            .register(DictionaryScope::Channel, 1, DICT)
+            .is_err());
+
+        // Method-specific dictionaries take precedence, and are only used if
+        // the peer accepts them.
+        assert_eq!(registry.select("/svc/A", "zstd-dict-2").unwrap().id(), 2);
+        assert!(registry.select("/svc/A", "zstd-dict-1").is_none());
+        assert_eq!(registry.select("/svc/B", "zstd-dict-1").unwrap().id(), 1);
+
+        // Rotating keeps the old dictionary for decompression.
+        registry
+            .register(DictionaryScope::Channel, 3, DICT)
+            .unwrap();
+        assert_eq!(
+            registry.accept_encoding(),
+            "zstd-dict-3,zstd-dict-2,zstd-dict-1"
+        );
+        assert_eq!(registry.select("/svc/B", "zstd-dict-3").unwrap().id(), 3);
+        assert!(registry.for_encoding("zstd-dict-1").is_some());
+
+        registry.retire(1);
+        assert!(registry.for_encoding("zstd-dict-1").is_none());
+        registry.retire(3);
+        assert!(registry.select("/svc/B", "zstd-dict-3").is_none());
+    }
+
+    #[cfg(feature = "_runtime-tokio")]
+    mod end_to_end {
+        use std::sync::Arc;
+
+        use bytes::Bytes;
+        use tokio_stream::StreamExt;
+        use tonic::async_trait;
+
+        use super::DICT;
+        use crate::client::{Channel, ChannelOptions};
+        use crate::codec::message_bytes;
+        use crate::compression::zstd_dictionary::{DictionaryRegistry, DictionaryScope};
+        use crate::compression::{CompressionPolicy, CompressionStats};
+        use crate::server::tcp::TcpListener;
+        use crate::server::Server;
+        use crate::service::{Message, Request, Response, Service};
+
+        struct Echo {}
+
+        #[async_trait]
+        impl Service for Echo {
+            async fn call(&self, _method: String, request: Request) -> Response {
+                Response::new(Box::pin(request.into_inner().map(Ok)))
+            }
+        }
+
+        async fn echo(chan: &Channel, message: &'static [u8]) -> Bytes {
+            let msg: Box<dyn Message> = Box::new(Bytes::from_static(message));
+            let req = Request::new(Box::pin(tokio_stream::once(msg)));
+            let res = chan.call("/svc/Echo".to_string(), req).await;
+            let msg = res.into_inner().next().await.unwrap().unwrap();
+            message_bytes(msg).unwrap()
+        }
+
+        #[tokio::test]
+        async fn negotiates_and_compresses_calls() {
+            crate::client::reg();
+            let msg = b"{\"cpu\":0.7,\"memory\":0.1,\"host\":\"backend.example.com\"}";
+
+            // The server uses the dictionary for a single method, the channel
+            // for all of them.
+            let server_dictionaries = Arc::new(DictionaryRegistry::new());
+            server_dictionaries
+                .register(DictionaryScope::Method("/svc/Echo".to_string()), 1, DICT)
+                .unwrap();
+            let lis = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+            let target = format!("dns:///{}", lis.local_addr());
+            let mut srv = Server::new();
+            srv.set_handler(Echo {});
+            srv.set_zstd_dictionaries(server_dictionaries);
+            let srv = Arc::new(srv);
+            tokio::spawn({
+                let srv = srv.clone();
+                async move { srv.serve(&lis).await }
+            });
+
+            let channel_dictionaries = Arc::new(DictionaryRegistry::new());
+            channel_dictionaries
+                .register(DictionaryScope::Channel, 1, DICT)
+                .unwrap();
+            let options = ChannelOptions::default()
+                .zstd_dictionaries(channel_dictionaries)
+                .compression_policy(CompressionPolicy::new().min_size(10));
+            let chan = Channel::new(&target, None, options);
+
+            // The channel learns which dictionaries the server holds from its
+            // first response, while the server compresses every response.
+            assert_eq!(&echo(&chan, msg).await[..], msg);
+            assert_eq!(chan.compression_stats(), CompressionStats::default());
+            assert_eq!(&echo(&chan, msg).await[..], msg);
+            assert_eq!(&echo(&chan, b"tiny").await[..], b"tiny");
+            assert_eq!(
+                chan.compression_stats(),
+                CompressionStats {
+                    compressed: 1,
+                    skipped_too_small: 1,
+                    skipped_high_entropy: 0,
+                }
+            );
+            assert_eq!(srv.compression_stats().compressed, 3);
+        }
+    }
+}
@@ -1 +1,10 @@
+use crate::attributes::AttributeKey;
+
 pub trait Credentials {}
+
+/// Identifies the security context (e.g. the client certificate) that must be
+/// used when connecting to an address.  Set it on each address whose
+/// connections require a distinct context; subchannels are only shared
+/// between addresses whose security contexts are equal.
+pub const SECURITY_CONTEXT: AttributeKey<String> =
+    AttributeKey::new("grpc.credentials.security_context");
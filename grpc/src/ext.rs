@@ -0,0 +1,85 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! APIs for extending gRPC, e.g. with custom name resolvers.
+//!
+//! Applications using gRPC only need the types in the crate root and
+//! [`prelude`](crate::prelude).  The modules here group the APIs used by the
+//! authors of extensions, which are expected to change more often.
+
+/// Name resolvers, which produce the addresses of a channel's target.
+///
+/// Resolvers are registered into a [`ResolverRegistry`] given to channels with
+/// `ChannelOptions::name_resolver_registry`.  See `examples/custom_resolver.rs`
+/// for a resolver whose addresses are pushed by the application.
+///
+/// [`ResolverRegistry`]: name_resolution::ResolverRegistry
+pub mod name_resolution {
+    pub use crate::client::name_resolution::{
+        global_registry, Address, ChannelController, Endpoint, EndpointBuilder, Resolver,
+        ResolverBuilder, ResolverOptions, ResolverRegistry, ResolverUpdate, ResolverUpdateBuilder,
+        Target, WorkScheduler, TCP_IP_NETWORK_TYPE,
+    };
+}
+
+/// Load balancing policies, which route the calls of a channel to its
+/// subchannels.
+///
+/// A policy is made of three parts:
+///
+/// - An [`LbPolicyBuilder`], which names the policy, parses its JSON config
+///   from the service config's `loadBalancingConfig`, and builds instances.
+/// - An [`LbPolicy`], which creates subchannels for the addresses produced by
+///   the name resolver, follows their states, and publishes an [`LbState`].
+///   Its callbacks are serialized by the channel; a policy that needs to act
+///   outside of them, e.g. after a timer or when a call completes, asks the
+///   [`WorkScheduler`] for a call into [`LbPolicy::work`].
+/// - A [`Picker`], which routes each call to a subchannel without blocking,
+///   and may observe the call's outcome through [`Pick::on_complete`].
+///
+/// Policies are registered into an [`LbPolicyRegistry`] given to channels
+/// with `ChannelOptions::lb_policy_registry`.  See `examples/custom_lb.rs`
+/// for a complete policy.
+///
+/// [`LbPolicyBuilder`]: load_balancing::LbPolicyBuilder
+/// [`LbPolicy`]: load_balancing::LbPolicy
+/// [`LbState`]: load_balancing::LbState
+/// [`WorkScheduler`]: load_balancing::WorkScheduler
+/// [`LbPolicy::work`]: load_balancing::LbPolicy::work
+/// [`Picker`]: load_balancing::Picker
+/// [`Pick::on_complete`]: load_balancing::Pick::on_complete
+/// [`LbPolicyRegistry`]: load_balancing::LbPolicyRegistry
+pub mod load_balancing {
+    pub use crate::client::load_balancing::{
+        CallOutcome, ChannelController, CompletionCallback, LbPolicy, LbPolicyBuilder,
+        LbPolicyOptions, LbPolicyRegistry, LbState, ParsedJsonLbConfig, Pick, PickResult, Picker,
+        PickerBenchmark, PickerBenchmarkResult, ScheduledWork, Subchannel, SubchannelState,
+        WorkScheduler,
+    };
+    pub use crate::client::service_config::{LbConfig, LbPolicyConfig};
+    pub use crate::client::transport::{
+        SecurityLevel, TransportInfo, HTTP2_SETTINGS, SERVER_NAME, TRANSPORT_SUPPORTED,
+    };
+    pub use crate::orca::{BackendMetrics, OobMetricsListener, OobMetricsStream};
+}
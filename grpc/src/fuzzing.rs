@@ -0,0 +1,112 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! Entry points of the fuzz targets in `fuzz/`, which feed them arbitrary
+//! bytes.  None of them may panic, whatever their input.
+//!
+//! This module is only built with the internal `_fuzzing` feature, and is not
+//! part of the API.
+
+use http::{HeaderMap, HeaderName, HeaderValue};
+use tonic::metadata::MetadataMap;
+
+use crate::{
+    client::{name_resolution::Target, service_config::ServiceConfig, Channel, ChannelOptions},
+    orca::{self, BackendMetrics},
+    service::details::{self, RETRY_PUSHBACK_KEY, STATUS_DETAILS_KEY},
+    trace_context::{
+        BinaryPropagator, TracePropagator, W3cPropagator, GRPC_TRACE_BIN_HEADER, TRACEPARENT_HEADER,
+    },
+    Code, Status,
+};
+
+/// Parses data as a JSON service config.
+pub fn service_config(data: &[u8]) {
+    let Ok(config) = std::str::from_utf8(data) else {
+        return;
+    };
+    let _ = ServiceConfig::parse(config);
+}
+
+/// Parses data as the target of a channel.
+pub fn target(data: &[u8]) {
+    let Ok(target) = std::str::from_utf8(data) else {
+        return;
+    };
+    if let Ok(parsed) = target.parse::<Target>() {
+        let _ = (parsed.scheme(), parsed.authority_host_port(), parsed.path());
+    }
+    // Channels are idle until used, so creating one only parses its target.
+    let _ = Channel::new(target, None, ChannelOptions::default());
+}
+
+/// Reads data as the value of each metadata entry the channel parses,
+/// including the base64 encoded binary entries.
+pub fn metadata(data: &[u8]) {
+    let Ok(value) = HeaderValue::from_bytes(data) else {
+        return;
+    };
+    let mut headers = HeaderMap::new();
+    for key in [
+        TRACEPARENT_HEADER,
+        GRPC_TRACE_BIN_HEADER,
+        STATUS_DETAILS_KEY,
+        RETRY_PUSHBACK_KEY,
+        orca::METADATA_KEY,
+    ] {
+        headers.insert(HeaderName::from_static(key), value.clone());
+    }
+    let metadata = MetadataMap::from_headers(headers);
+    let _ = W3cPropagator.extract(&metadata);
+    let _ = BinaryPropagator.extract(&metadata);
+    let _ = BackendMetrics::from_metadata(&metadata);
+    let status = Status::with_metadata(Code::Unknown, "", metadata);
+    let _ = details::pushback(&status);
+    let _ = details::normalize_details(status, details::DEFAULT_MAX_STATUS_DETAILS_SIZE);
+}
+
+#[cfg(test)]
+mod test {
+    use super::{metadata, service_config, target};
+
+    #[test]
+    fn malformed_inputs_do_not_panic() {
+        for data in [
+            &b""[..],
+            b"\xff\xfe",
+            b"not a target",
+            b"dns:///[::1",
+            b"{\"methodConfig\":[{\"timeout\":\"1e400s\"}]}",
+            b"{\"loadBalancingConfig\":[{}]}",
+            b"{\"retryThrottling\":{\"maxTokens\":-1,\"tokenRatio\":1e40}}",
+            b"AAAA",
+            b"00-zz-00-00",
+            b"=",
+        ] {
+            service_config(data);
+            target(data);
+            metadata(data);
+        }
+    }
+}
@@ -0,0 +1,79 @@
+// This file is @generated by codegen.
+// 
+// 
+//  Copyright 2015 gRPC authors.
+// 
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+// 
+//      http://www.apache.org/licenses/LICENSE-2.0
+// 
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+// 
+//  A subset of grpc/testing/benchmark_service.proto and the messages it uses,
+//  wire compatible with the originals so the service interoperates with other
+//  gRPC implementations' benchmark clients and servers.
+// 
+/// Byte encoded FILE_DESCRIPTOR_SET.
+pub const FILE_DESCRIPTOR_SET: &[u8] = &[
+    10u8, 171u8, 5u8, 10u8, 33u8, 98u8, 101u8, 110u8, 99u8, 104u8, 109u8, 97u8, 114u8,
+    107u8, 47u8, 98u8, 101u8, 110u8, 99u8, 104u8, 109u8, 97u8, 114u8, 107u8, 95u8, 115u8,
+    101u8, 114u8, 118u8, 105u8, 99u8, 101u8, 46u8, 112u8, 114u8, 111u8, 116u8, 111u8,
+    18u8, 12u8, 103u8, 114u8, 112u8, 99u8, 46u8, 116u8, 101u8, 115u8, 116u8, 105u8,
+    110u8, 103u8, 34u8, 29u8, 10u8, 7u8, 80u8, 97u8, 121u8, 108u8, 111u8, 97u8, 100u8,
+    18u8, 18u8, 10u8, 4u8, 98u8, 111u8, 100u8, 121u8, 24u8, 2u8, 32u8, 1u8, 40u8, 12u8,
+    82u8, 4u8, 98u8, 111u8, 100u8, 121u8, 34u8, 101u8, 10u8, 13u8, 83u8, 105u8, 109u8,
+    112u8, 108u8, 101u8, 82u8, 101u8, 113u8, 117u8, 101u8, 115u8, 116u8, 18u8, 35u8,
+    10u8, 13u8, 114u8, 101u8, 115u8, 112u8, 111u8, 110u8, 115u8, 101u8, 95u8, 115u8,
+    105u8, 122u8, 101u8, 24u8, 2u8, 32u8, 1u8, 40u8, 5u8, 82u8, 12u8, 114u8, 101u8,
+    115u8, 112u8, 111u8, 110u8, 115u8, 101u8, 83u8, 105u8, 122u8, 101u8, 18u8, 47u8,
+    10u8, 7u8, 112u8, 97u8, 121u8, 108u8, 111u8, 97u8, 100u8, 24u8, 3u8, 32u8, 1u8, 40u8,
+    11u8, 50u8, 21u8, 46u8, 103u8, 114u8, 112u8, 99u8, 46u8, 116u8, 101u8, 115u8, 116u8,
+    105u8, 110u8, 103u8, 46u8, 80u8, 97u8, 121u8, 108u8, 111u8, 97u8, 100u8, 82u8, 7u8,
+    112u8, 97u8, 121u8, 108u8, 111u8, 97u8, 100u8, 34u8, 65u8, 10u8, 14u8, 83u8, 105u8,
+    109u8, 112u8, 108u8, 101u8, 82u8, 101u8, 115u8, 112u8, 111u8, 110u8, 115u8, 101u8,
+    18u8, 47u8, 10u8, 7u8, 112u8, 97u8, 121u8, 108u8, 111u8, 97u8, 100u8, 24u8, 1u8,
+    32u8, 1u8, 40u8, 11u8, 50u8, 21u8, 46u8, 103u8, 114u8, 112u8, 99u8, 46u8, 116u8,
+    101u8, 115u8, 116u8, 105u8, 110u8, 103u8, 46u8, 80u8, 97u8, 121u8, 108u8, 111u8,
+    97u8, 100u8, 82u8, 7u8, 112u8, 97u8, 121u8, 108u8, 111u8, 97u8, 100u8, 50u8, 166u8,
+    3u8, 10u8, 16u8, 66u8, 101u8, 110u8, 99u8, 104u8, 109u8, 97u8, 114u8, 107u8, 83u8,
+    101u8, 114u8, 118u8, 105u8, 99u8, 101u8, 18u8, 70u8, 10u8, 9u8, 85u8, 110u8, 97u8,
+    114u8, 121u8, 67u8, 97u8, 108u8, 108u8, 18u8, 27u8, 46u8, 103u8, 114u8, 112u8, 99u8,
+    46u8, 116u8, 101u8, 115u8, 116u8, 105u8, 110u8, 103u8, 46u8, 83u8, 105u8, 109u8,
+    112u8, 108u8, 101u8, 82u8, 101u8, 113u8, 117u8, 101u8, 115u8, 116u8, 26u8, 28u8,
+    46u8, 103u8, 114u8, 112u8, 99u8, 46u8, 116u8, 101u8, 115u8, 116u8, 105u8, 110u8,
+    103u8, 46u8, 83u8, 105u8, 109u8, 112u8, 108u8, 101u8, 82u8, 101u8, 115u8, 112u8,
+    111u8, 110u8, 115u8, 101u8, 18u8, 78u8, 10u8, 13u8, 83u8, 116u8, 114u8, 101u8, 97u8,
+    109u8, 105u8, 110u8, 103u8, 67u8, 97u8, 108u8, 108u8, 18u8, 27u8, 46u8, 103u8, 114u8,
+    112u8, 99u8, 46u8, 116u8, 101u8, 115u8, 116u8, 105u8, 110u8, 103u8, 46u8, 83u8,
+    105u8, 109u8, 112u8, 108u8, 101u8, 82u8, 101u8, 113u8, 117u8, 101u8, 115u8, 116u8,
+    26u8, 28u8, 46u8, 103u8, 114u8, 112u8, 99u8, 46u8, 116u8, 101u8, 115u8, 116u8, 105u8,
+    110u8, 103u8, 46u8, 83u8, 105u8, 109u8, 112u8, 108u8, 101u8, 82u8, 101u8, 115u8,
+    112u8, 111u8, 110u8, 115u8, 101u8, 40u8, 1u8, 48u8, 1u8, 18u8, 82u8, 10u8, 19u8,
+    83u8, 116u8, 114u8, 101u8, 97u8, 109u8, 105u8, 110u8, 103u8, 70u8, 114u8, 111u8,
+    109u8, 67u8, 108u8, 105u8, 101u8, 110u8, 116u8, 18u8, 27u8, 46u8, 103u8, 114u8,
+    112u8, 99u8, 46u8, 116u8, 101u8, 115u8, 116u8, 105u8, 110u8, 103u8, 46u8, 83u8,
+    105u8, 109u8, 112u8, 108u8, 101u8, 82u8, 101u8, 113u8, 117u8, 101u8, 115u8, 116u8,
+    26u8, 28u8, 46u8, 103u8, 114u8, 112u8, 99u8, 46u8, 116u8, 101u8, 115u8, 116u8, 105u8,
+    110u8, 103u8, 46u8, 83u8, 105u8, 109u8, 112u8, 108u8, 101u8, 82u8, 101u8, 115u8,
+    112u8, 111u8, 110u8, 115u8, 101u8, 40u8, 1u8, 18u8, 82u8, 10u8, 19u8, 83u8, 116u8,
+    114u8, 101u8, 97u8, 109u8, 105u8, 110u8, 103u8, 70u8, 114u8, 111u8, 109u8, 83u8,
+    101u8, 114u8, 118u8, 101u8, 114u8, 18u8, 27u8, 46u8, 103u8, 114u8, 112u8, 99u8, 46u8,
+    116u8, 101u8, 115u8, 116u8, 105u8, 110u8, 103u8, 46u8, 83u8, 105u8, 109u8, 112u8,
+    108u8, 101u8, 82u8, 101u8, 113u8, 117u8, 101u8, 115u8, 116u8, 26u8, 28u8, 46u8,
+    103u8, 114u8, 112u8, 99u8, 46u8, 116u8, 101u8, 115u8, 116u8, 105u8, 110u8, 103u8,
+    46u8, 83u8, 105u8, 109u8, 112u8, 108u8, 101u8, 82u8, 101u8, 115u8, 112u8, 111u8,
+    110u8, 115u8, 101u8, 48u8, 1u8, 18u8, 82u8, 10u8, 17u8, 83u8, 116u8, 114u8, 101u8,
+    97u8, 109u8, 105u8, 110u8, 103u8, 66u8, 111u8, 116u8, 104u8, 87u8, 97u8, 121u8,
+    115u8, 18u8, 27u8, 46u8, 103u8, 114u8, 112u8, 99u8, 46u8, 116u8, 101u8, 115u8, 116u8,
+    105u8, 110u8, 103u8, 46u8, 83u8, 105u8, 109u8, 112u8, 108u8, 101u8, 82u8, 101u8,
+    113u8, 117u8, 101u8, 115u8, 116u8, 26u8, 28u8, 46u8, 103u8, 114u8, 112u8, 99u8, 46u8,
+    116u8, 101u8, 115u8, 116u8, 105u8, 110u8, 103u8, 46u8, 83u8, 105u8, 109u8, 112u8,
+    108u8, 101u8, 82u8, 101u8, 115u8, 112u8, 111u8, 110u8, 115u8, 101u8, 40u8, 1u8, 48u8,
+    1u8, 98u8, 6u8, 112u8, 114u8, 111u8, 116u8, 111u8, 51u8,
+];
@@ -0,0 +1,181 @@
+// This file is @generated by prost-build.
+/// EchoRequest is the request for echo.
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct EchoRequest {
+    #[prost(string, tag = "1")]
+    pub message: ::prost::alloc::string::String,
+}
+/// EchoResponse is the response for echo.
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct EchoResponse {
+    #[prost(string, tag = "1")]
+    pub message: ::prost::alloc::string::String,
+}
+/// Generated client implementations for the grpc crate.
+pub mod echo_client {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::wildcard_imports)]
+    use grpc::codegen::*;
+    /// Echo is the echo service.
+    #[derive(Clone)]
+    pub struct EchoClient {
+        inner: Grpc,
+    }
+    impl EchoClient {
+        pub fn new(channel: Channel) -> Self {
+            Self { inner: Grpc::new(channel) }
+        }
+        /// UnaryEcho is unary echo.
+        pub async fn unary_echo(
+            &self,
+            request: impl IntoRequest<super::EchoRequest>,
+        ) -> std::result::Result<Response<super::EchoResponse>, Status> {
+            let codec = grpc::codegen::ProstCodec::default();
+            self.inner
+                .unary(
+                    "/grpc.examples.echo.Echo/UnaryEcho",
+                    request.into_request(),
+                    codec,
+                )
+                .await
+        }
+        /// ServerStreamingEcho is server side streaming.
+        pub async fn server_streaming_echo(
+            &self,
+            request: impl IntoRequest<super::EchoRequest>,
+        ) -> std::result::Result<Response<BoxStream<super::EchoResponse>>, Status> {
+            let codec = grpc::codegen::ProstCodec::default();
+            self.inner
+                .server_streaming(
+                    "/grpc.examples.echo.Echo/ServerStreamingEcho",
+                    request.into_request(),
+                    codec,
+                )
+                .await
+        }
+        /// ClientStreamingEcho is client side streaming.
+        pub async fn client_streaming_echo<S>(
+            &self,
+            request: S,
+        ) -> std::result::Result<Response<super::EchoResponse>, Status>
+        where
+            S: IntoStreamingRequest<Message = super::EchoRequest>,
+            S::Stream: std::marker::Sync,
+        {
+            let codec = grpc::codegen::ProstCodec::default();
+            self.inner
+                .client_streaming(
+                    "/grpc.examples.echo.Echo/ClientStreamingEcho",
+                    request.into_streaming_request(),
+                    codec,
+                )
+                .await
+        }
+        /// BidirectionalStreamingEcho is bidi streaming.
+        pub async fn bidirectional_streaming_echo<S>(
+            &self,
+            request: S,
+        ) -> std::result::Result<Response<BoxStream<super::EchoResponse>>, Status>
+        where
+            S: IntoStreamingRequest<Message = super::EchoRequest>,
+            S::Stream: std::marker::Sync,
+        {
+            let codec = grpc::codegen::ProstCodec::default();
+            self.inner
+                .streaming(
+                    "/grpc.examples.echo.Echo/BidirectionalStreamingEcho",
+                    request.into_streaming_request(),
+                    codec,
+                )
+                .await
+        }
+    }
+}
+/// Generated server implementations for the grpc crate.
+pub mod echo_server {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::wildcard_imports)]
+    use grpc::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with EchoServer.
+    #[async_trait]
+    pub trait Echo: std::marker::Send + std::marker::Sync + 'static {
+        /// UnaryEcho is unary echo.
+        async fn unary_echo(
+            &self,
+            request: Request<super::EchoRequest>,
+        ) -> std::result::Result<Response<super::EchoResponse>, Status>;
+        /// ServerStreamingEcho is server side streaming.
+        async fn server_streaming_echo(
+            &self,
+            request: Request<super::EchoRequest>,
+        ) -> std::result::Result<Response<BoxStream<super::EchoResponse>>, Status>;
+        /// ClientStreamingEcho is client side streaming.
+        async fn client_streaming_echo(
+            &self,
+            request: Request<BoxStream<super::EchoRequest>>,
+        ) -> std::result::Result<Response<super::EchoResponse>, Status>;
+        /// BidirectionalStreamingEcho is bidi streaming.
+        async fn bidirectional_streaming_echo(
+            &self,
+            request: Request<BoxStream<super::EchoRequest>>,
+        ) -> std::result::Result<Response<BoxStream<super::EchoResponse>>, Status>;
+    }
+    /// Echo is the echo service.
+    pub struct EchoServer<T> {
+        inner: Arc<T>,
+    }
+    impl<T> EchoServer<T> {
+        pub fn new(inner: T) -> Self {
+            Self::from_arc(Arc::new(inner))
+        }
+        pub fn from_arc(inner: Arc<T>) -> Self {
+            Self { inner }
+        }
+    }
+    impl<T> Clone for EchoServer<T> {
+        fn clone(&self) -> Self {
+            Self { inner: self.inner.clone() }
+        }
+    }
+    #[async_trait]
+    impl<T: Echo> Service for EchoServer<T> {
+        async fn call(
+            &self,
+            method: String,
+            request: grpc::service::Request,
+        ) -> grpc::service::Response {
+            match method.as_str() {
+                "/grpc.examples.echo.Echo/UnaryEcho" => {
+                    let codec = grpc::codegen::ProstCodec::default();
+                    serve_unary(request, codec, |req| self.inner.unary_echo(req)).await
+                }
+                "/grpc.examples.echo.Echo/ServerStreamingEcho" => {
+                    let codec = grpc::codegen::ProstCodec::default();
+                    serve_server_streaming(
+                            request,
+                            codec,
+                            |req| self.inner.server_streaming_echo(req),
+                        )
+                        .await
+                }
+                "/grpc.examples.echo.Echo/ClientStreamingEcho" => {
+                    let codec = grpc::codegen::ProstCodec::default();
+                    serve_client_streaming(
+                            request,
+                            codec,
+                            |req| self.inner.client_streaming_echo(req),
+                        )
+                        .await
+                }
+                "/grpc.examples.echo.Echo/BidirectionalStreamingEcho" => {
+                    let codec = grpc::codegen::ProstCodec::default();
+                    serve_streaming(
+                            request,
+                            codec,
+                            |req| self.inner.bidirectional_streaming_echo(req),
+                        )
+                        .await
+                }
+                _ => unimplemented(&method),
+            }
+        }
+    }
+}
@@ -0,0 +1,681 @@
+// This file is @generated by prost-build.
+/// A block of data, to simply increase gRPC message size.
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct Payload {
+    /// Primary contents of payload.
+    #[prost(bytes = "vec", tag = "2")]
+    pub body: ::prost::alloc::vec::Vec<u8>,
+}
+/// Unary request.
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct SimpleRequest {
+    /// Desired payload size in the response from the server.
+    #[prost(int32, tag = "2")]
+    pub response_size: i32,
+    /// Optional input payload sent along with the request.
+    #[prost(message, optional, tag = "3")]
+    pub payload: ::core::option::Option<Payload>,
+}
+/// Unary response, as configured by the request.
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct SimpleResponse {
+    /// Payload to increase message size.
+    #[prost(message, optional, tag = "1")]
+    pub payload: ::core::option::Option<Payload>,
+}
+/// Generated client implementations.
+pub mod benchmark_service_client {
+    #![allow(
+        unused_variables,
+        dead_code,
+        missing_docs,
+        clippy::wildcard_imports,
+        clippy::let_unit_value,
+    )]
+    use tonic::codegen::*;
+    use tonic::codegen::http::Uri;
+    #[derive(Debug, Clone)]
+    pub struct BenchmarkServiceClient<T> {
+        inner: tonic::client::Grpc<T>,
+    }
+    impl<T> BenchmarkServiceClient<T>
+    where
+        T: tonic::client::GrpcService<tonic::body::Body>,
+        T::Error: Into<StdError>,
+        T::ResponseBody: Body<Data = Bytes> + std::marker::Send + 'static,
+        <T::ResponseBody as Body>::Error: Into<StdError> + std::marker::Send,
+    {
+        pub fn new(inner: T) -> Self {
+            let inner = tonic::client::Grpc::new(inner);
+            Self { inner }
+        }
+        pub fn with_origin(inner: T, origin: Uri) -> Self {
+            let inner = tonic::client::Grpc::with_origin(inner, origin);
+            Self { inner }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> BenchmarkServiceClient<InterceptedService<T, F>>
+        where
+            F: tonic::service::Interceptor,
+            T::ResponseBody: Default,
+            T: tonic::codegen::Service<
+                http::Request<tonic::body::Body>,
+                Response = http::Response<
+                    <T as tonic::client::GrpcService<tonic::body::Body>>::ResponseBody,
+                >,
+            >,
+            <T as tonic::codegen::Service<
+                http::Request<tonic::body::Body>,
+            >>::Error: Into<StdError> + std::marker::Send + std::marker::Sync,
+        {
+            BenchmarkServiceClient::new(InterceptedService::new(inner, interceptor))
+        }
+        /// Compress requests with the given encoding.
+        ///
+        /// This requires the server to support it otherwise it might respond with an
+        /// error.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.send_compressed(encoding);
+            self
+        }
+        /// Enable decompressing responses.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.accept_compressed(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.inner = self.inner.max_decoding_message_size(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.inner = self.inner.max_encoding_message_size(limit);
+            self
+        }
+        /// One request followed by one response.
+        /// The server returns the client payload as-is.
+        pub async fn unary_call(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SimpleRequest>,
+        ) -> std::result::Result<tonic::Response<super::SimpleResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/grpc.testing.BenchmarkService/UnaryCall",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("grpc.testing.BenchmarkService", "UnaryCall"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Repeated sequence of one request followed by one response.
+        /// Should be called streaming ping-pong
+        /// The server returns the client payload as-is on each response
+        pub async fn streaming_call(
+            &mut self,
+            request: impl tonic::IntoStreamingRequest<Message = super::SimpleRequest>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::SimpleResponse>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/grpc.testing.BenchmarkService/StreamingCall",
+            );
+            let mut req = request.into_streaming_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("grpc.testing.BenchmarkService", "StreamingCall"),
+                );
+            self.inner.streaming(req, path, codec).await
+        }
+        /// Single-sided unbounded streaming from client to server
+        /// The server returns the client payload as-is once the client does WritesDone
+        pub async fn streaming_from_client(
+            &mut self,
+            request: impl tonic::IntoStreamingRequest<Message = super::SimpleRequest>,
+        ) -> std::result::Result<tonic::Response<super::SimpleResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/grpc.testing.BenchmarkService/StreamingFromClient",
+            );
+            let mut req = request.into_streaming_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new(
+                        "grpc.testing.BenchmarkService",
+                        "StreamingFromClient",
+                    ),
+                );
+            self.inner.client_streaming(req, path, codec).await
+        }
+        /// Single-sided unbounded streaming from server to client
+        /// The server repeatedly returns the client payload as-is
+        pub async fn streaming_from_server(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SimpleRequest>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::SimpleResponse>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/grpc.testing.BenchmarkService/StreamingFromServer",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new(
+                        "grpc.testing.BenchmarkService",
+                        "StreamingFromServer",
+                    ),
+                );
+            self.inner.server_streaming(req, path, codec).await
+        }
+        /// Two-sided unbounded streaming between server to client
+        /// Both sides send the content of their own choice to the other
+        pub async fn streaming_both_ways(
+            &mut self,
+            request: impl tonic::IntoStreamingRequest<Message = super::SimpleRequest>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::SimpleResponse>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/grpc.testing.BenchmarkService/StreamingBothWays",
+            );
+            let mut req = request.into_streaming_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("grpc.testing.BenchmarkService", "StreamingBothWays"),
+                );
+            self.inner.streaming(req, path, codec).await
+        }
+    }
+}
+/// Generated server implementations.
+pub mod benchmark_service_server {
+    #![allow(
+        unused_variables,
+        dead_code,
+        missing_docs,
+        clippy::wildcard_imports,
+        clippy::let_unit_value,
+    )]
+    use tonic::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with BenchmarkServiceServer.
+    #[async_trait]
+    pub trait BenchmarkService: std::marker::Send + std::marker::Sync + 'static {
+        /// One request followed by one response.
+        /// The server returns the client payload as-is.
+        async fn unary_call(
+            &self,
+            request: tonic::Request<super::SimpleRequest>,
+        ) -> std::result::Result<tonic::Response<super::SimpleResponse>, tonic::Status>;
+        /// Server streaming response type for the StreamingCall method.
+        type StreamingCallStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::SimpleResponse, tonic::Status>,
+            >
+            + std::marker::Send
+            + 'static;
+        /// Repeated sequence of one request followed by one response.
+        /// Should be called streaming ping-pong
+        /// The server returns the client payload as-is on each response
+        async fn streaming_call(
+            &self,
+            request: tonic::Request<tonic::Streaming<super::SimpleRequest>>,
+        ) -> std::result::Result<
+            tonic::Response<Self::StreamingCallStream>,
+            tonic::Status,
+        >;
+        /// Single-sided unbounded streaming from client to server
+        /// The server returns the client payload as-is once the client does WritesDone
+        async fn streaming_from_client(
+            &self,
+            request: tonic::Request<tonic::Streaming<super::SimpleRequest>>,
+        ) -> std::result::Result<tonic::Response<super::SimpleResponse>, tonic::Status>;
+        /// Server streaming response type for the StreamingFromServer method.
+        type StreamingFromServerStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::SimpleResponse, tonic::Status>,
+            >
+            + std::marker::Send
+            + 'static;
+        /// Single-sided unbounded streaming from server to client
+        /// The server repeatedly returns the client payload as-is
+        async fn streaming_from_server(
+            &self,
+            request: tonic::Request<super::SimpleRequest>,
+        ) -> std::result::Result<
+            tonic::Response<Self::StreamingFromServerStream>,
+            tonic::Status,
+        >;
+        /// Server streaming response type for the StreamingBothWays method.
+        type StreamingBothWaysStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::SimpleResponse, tonic::Status>,
+            >
+            + std::marker::Send
+            + 'static;
+        /// Two-sided unbounded streaming between server to client
+        /// Both sides send the content of their own choice to the other
+        async fn streaming_both_ways(
+            &self,
+            request: tonic::Request<tonic::Streaming<super::SimpleRequest>>,
+        ) -> std::result::Result<
+            tonic::Response<Self::StreamingBothWaysStream>,
+            tonic::Status,
+        >;
+    }
+    #[derive(Debug)]
+    pub struct BenchmarkServiceServer<T> {
+        inner: Arc<T>,
+        accept_compression_encodings: EnabledCompressionEncodings,
+        send_compression_encodings: EnabledCompressionEncodings,
+        max_decoding_message_size: Option<usize>,
+        max_encoding_message_size: Option<usize>,
+    }
+    impl<T> BenchmarkServiceServer<T> {
+        pub fn new(inner: T) -> Self {
+            Self::from_arc(Arc::new(inner))
+        }
+        pub fn from_arc(inner: Arc<T>) -> Self {
+            Self {
+                inner,
+                accept_compression_encodings: Default::default(),
+                send_compression_encodings: Default::default(),
+                max_decoding_message_size: None,
+                max_encoding_message_size: None,
+            }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> InterceptedService<Self, F>
+        where
+            F: tonic::service::Interceptor,
+        {
+            InterceptedService::new(Self::new(inner), interceptor)
+        }
+        /// Enable decompressing requests with the given encoding.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.accept_compression_encodings.enable(encoding);
+            self
+        }
+        /// Compress responses with the given encoding, if the client supports it.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.send_compression_encodings.enable(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.max_decoding_message_size = Some(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.max_encoding_message_size = Some(limit);
+            self
+        }
+    }
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for BenchmarkServiceServer<T>
+    where
+        T: BenchmarkService,
+        B: Body + std::marker::Send + 'static,
+        B::Error: Into<StdError> + std::marker::Send + 'static,
+    {
+        type Response = http::Response<tonic::body::Body>;
+        type Error = std::convert::Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+        fn poll_ready(
+            &mut self,
+            _cx: &mut Context<'_>,
+        ) -> Poll<std::result::Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            match req.uri().path() {
+                "/grpc.testing.BenchmarkService/UnaryCall" => {
+                    #[allow(non_camel_case_types)]
+                    struct UnaryCallSvc<T: BenchmarkService>(pub Arc<T>);
+                    impl<
+                        T: BenchmarkService,
+                    > tonic::server::UnaryService<super::SimpleRequest>
+                    for UnaryCallSvc<T> {
+                        type Response = super::SimpleResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SimpleRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as BenchmarkService>::unary_call(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = UnaryCallSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/grpc.testing.BenchmarkService/StreamingCall" => {
+                    #[allow(non_camel_case_types)]
+                    struct StreamingCallSvc<T: BenchmarkService>(pub Arc<T>);
+                    impl<
+                        T: BenchmarkService,
+                    > tonic::server::StreamingService<super::SimpleRequest>
+                    for StreamingCallSvc<T> {
+                        type Response = super::SimpleResponse;
+                        type ResponseStream = T::StreamingCallStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<
+                                tonic::Streaming<super::SimpleRequest>,
+                            >,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as BenchmarkService>::streaming_call(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = StreamingCallSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/grpc.testing.BenchmarkService/StreamingFromClient" => {
+                    #[allow(non_camel_case_types)]
+                    struct StreamingFromClientSvc<T: BenchmarkService>(pub Arc<T>);
+                    impl<
+                        T: BenchmarkService,
+                    > tonic::server::ClientStreamingService<super::SimpleRequest>
+                    for StreamingFromClientSvc<T> {
+                        type Response = super::SimpleResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<
+                                tonic::Streaming<super::SimpleRequest>,
+                            >,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as BenchmarkService>::streaming_from_client(
+                                        &inner,
+                                        request,
+                                    )
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = StreamingFromClientSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.client_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/grpc.testing.BenchmarkService/StreamingFromServer" => {
+                    #[allow(non_camel_case_types)]
+                    struct StreamingFromServerSvc<T: BenchmarkService>(pub Arc<T>);
+                    impl<
+                        T: BenchmarkService,
+                    > tonic::server::ServerStreamingService<super::SimpleRequest>
+                    for StreamingFromServerSvc<T> {
+                        type Response = super::SimpleResponse;
+                        type ResponseStream = T::StreamingFromServerStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SimpleRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as BenchmarkService>::streaming_from_server(
+                                        &inner,
+                                        request,
+                                    )
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = StreamingFromServerSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/grpc.testing.BenchmarkService/StreamingBothWays" => {
+                    #[allow(non_camel_case_types)]
+                    struct StreamingBothWaysSvc<T: BenchmarkService>(pub Arc<T>);
+                    impl<
+                        T: BenchmarkService,
+                    > tonic::server::StreamingService<super::SimpleRequest>
+                    for StreamingBothWaysSvc<T> {
+                        type Response = super::SimpleResponse;
+                        type ResponseStream = T::StreamingBothWaysStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<
+                                tonic::Streaming<super::SimpleRequest>,
+                            >,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as BenchmarkService>::streaming_both_ways(
+                                        &inner,
+                                        request,
+                                    )
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = StreamingBothWaysSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                _ => {
+                    Box::pin(async move {
+                        let mut response = http::Response::new(
+                            tonic::body::Body::default(),
+                        );
+                        let headers = response.headers_mut();
+                        headers
+                            .insert(
+                                tonic::Status::GRPC_STATUS,
+                                (tonic::Code::Unimplemented as i32).into(),
+                            );
+                        headers
+                            .insert(
+                                http::header::CONTENT_TYPE,
+                                tonic::metadata::GRPC_CONTENT_TYPE,
+                            );
+                        Ok(response)
+                    })
+                }
+            }
+        }
+    }
+    impl<T> Clone for BenchmarkServiceServer<T> {
+        fn clone(&self) -> Self {
+            let inner = self.inner.clone();
+            Self {
+                inner,
+                accept_compression_encodings: self.accept_compression_encodings,
+                send_compression_encodings: self.send_compression_encodings,
+                max_decoding_message_size: self.max_decoding_message_size,
+                max_encoding_message_size: self.max_encoding_message_size,
+            }
+        }
+    }
+    /// Generated gRPC service name
+    pub const SERVICE_NAME: &str = "grpc.testing.BenchmarkService";
+    impl<T> tonic::server::NamedService for BenchmarkServiceServer<T> {
+        const NAME: &'static str = SERVICE_NAME;
+    }
+}
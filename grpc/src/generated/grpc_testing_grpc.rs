@@ -0,0 +1,231 @@
+// This file is @generated by prost-build.
+/// A block of data, to simply increase gRPC message size.
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct Payload {
+    /// Primary contents of payload.
+    #[prost(bytes = "vec", tag = "2")]
+    pub body: ::prost::alloc::vec::Vec<u8>,
+}
+/// Unary request.
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct SimpleRequest {
+    /// Desired payload size in the response from the server.
+    #[prost(int32, tag = "2")]
+    pub response_size: i32,
+    /// Optional input payload sent along with the request.
+    #[prost(message, optional, tag = "3")]
+    pub payload: ::core::option::Option<Payload>,
+}
+/// Unary response, as configured by the request.
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct SimpleResponse {
+    /// Payload to increase message size.
+    #[prost(message, optional, tag = "1")]
+    pub payload: ::core::option::Option<Payload>,
+}
+/// Generated client implementations for the grpc crate.
+pub mod benchmark_service_client {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::wildcard_imports)]
+    use grpc::codegen::*;
+    #[derive(Clone)]
+    pub struct BenchmarkServiceClient {
+        inner: Grpc,
+    }
+    impl BenchmarkServiceClient {
+        pub fn new(channel: Channel) -> Self {
+            Self { inner: Grpc::new(channel) }
+        }
+        /// One request followed by one response.
+        /// The server returns the client payload as-is.
+        pub async fn unary_call(
+            &self,
+            request: impl IntoRequest<super::SimpleRequest>,
+        ) -> std::result::Result<Response<super::SimpleResponse>, Status> {
+            let codec = grpc::codegen::ProstCodec::default();
+            self.inner
+                .unary(
+                    "/grpc.testing.BenchmarkService/UnaryCall",
+                    request.into_request(),
+                    codec,
+                )
+                .await
+        }
+        /// Repeated sequence of one request followed by one response.
+        /// Should be called streaming ping-pong
+        /// The server returns the client payload as-is on each response
+        pub async fn streaming_call<S>(
+            &self,
+            request: S,
+        ) -> std::result::Result<Response<BoxStream<super::SimpleResponse>>, Status>
+        where
+            S: IntoStreamingRequest<Message = super::SimpleRequest>,
+            S::Stream: std::marker::Sync,
+        {
+            let codec = grpc::codegen::ProstCodec::default();
+            self.inner
+                .streaming(
+                    "/grpc.testing.BenchmarkService/StreamingCall",
+                    request.into_streaming_request(),
+                    codec,
+                )
+                .await
+        }
+        /// Single-sided unbounded streaming from client to server
+        /// The server returns the client payload as-is once the client does WritesDone
+        pub async fn streaming_from_client<S>(
+            &self,
+            request: S,
+        ) -> std::result::Result<Response<super::SimpleResponse>, Status>
+        where
+            S: IntoStreamingRequest<Message = super::SimpleRequest>,
+            S::Stream: std::marker::Sync,
+        {
+            let codec = grpc::codegen::ProstCodec::default();
+            self.inner
+                .client_streaming(
+                    "/grpc.testing.BenchmarkService/StreamingFromClient",
+                    request.into_streaming_request(),
+                    codec,
+                )
+                .await
+        }
+        /// Single-sided unbounded streaming from server to client
+        /// The server repeatedly returns the client payload as-is
+        pub async fn streaming_from_server(
+            &self,
+            request: impl IntoRequest<super::SimpleRequest>,
+        ) -> std::result::Result<Response<BoxStream<super::SimpleResponse>>, Status> {
+            let codec = grpc::codegen::ProstCodec::default();
+            self.inner
+                .server_streaming(
+                    "/grpc.testing.BenchmarkService/StreamingFromServer",
+                    request.into_request(),
+                    codec,
+                )
+                .await
+        }
+        /// Two-sided unbounded streaming between server to client
+        /// Both sides send the content of their own choice to the other
+        pub async fn streaming_both_ways<S>(
+            &self,
+            request: S,
+        ) -> std::result::Result<Response<BoxStream<super::SimpleResponse>>, Status>
+        where
+            S: IntoStreamingRequest<Message = super::SimpleRequest>,
+            S::Stream: std::marker::Sync,
+        {
+            let codec = grpc::codegen::ProstCodec::default();
+            self.inner
+                .streaming(
+                    "/grpc.testing.BenchmarkService/StreamingBothWays",
+                    request.into_streaming_request(),
+                    codec,
+                )
+                .await
+        }
+    }
+}
+/// Generated server implementations for the grpc crate.
+pub mod benchmark_service_server {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::wildcard_imports)]
+    use grpc::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with BenchmarkServiceServer.
+    #[async_trait]
+    pub trait BenchmarkService: std::marker::Send + std::marker::Sync + 'static {
+        /// One request followed by one response.
+        /// The server returns the client payload as-is.
+        async fn unary_call(
+            &self,
+            request: Request<super::SimpleRequest>,
+        ) -> std::result::Result<Response<super::SimpleResponse>, Status>;
+        /// Repeated sequence of one request followed by one response.
+        /// Should be called streaming ping-pong
+        /// The server returns the client payload as-is on each response
+        async fn streaming_call(
+            &self,
+            request: Request<BoxStream<super::SimpleRequest>>,
+        ) -> std::result::Result<Response<BoxStream<super::SimpleResponse>>, Status>;
+        /// Single-sided unbounded streaming from client to server
+        /// The server returns the client payload as-is once the client does WritesDone
+        async fn streaming_from_client(
+            &self,
+            request: Request<BoxStream<super::SimpleRequest>>,
+        ) -> std::result::Result<Response<super::SimpleResponse>, Status>;
+        /// Single-sided unbounded streaming from server to client
+        /// The server repeatedly returns the client payload as-is
+        async fn streaming_from_server(
+            &self,
+            request: Request<super::SimpleRequest>,
+        ) -> std::result::Result<Response<BoxStream<super::SimpleResponse>>, Status>;
+        /// Two-sided unbounded streaming between server to client
+        /// Both sides send the content of their own choice to the other
+        async fn streaming_both_ways(
+            &self,
+            request: Request<BoxStream<super::SimpleRequest>>,
+        ) -> std::result::Result<Response<BoxStream<super::SimpleResponse>>, Status>;
+    }
+    pub struct BenchmarkServiceServer<T> {
+        inner: Arc<T>,
+    }
+    impl<T> BenchmarkServiceServer<T> {
+        pub fn new(inner: T) -> Self {
+            Self::from_arc(Arc::new(inner))
+        }
+        pub fn from_arc(inner: Arc<T>) -> Self {
+            Self { inner }
+        }
+    }
+    impl<T> Clone for BenchmarkServiceServer<T> {
+        fn clone(&self) -> Self {
+            Self { inner: self.inner.clone() }
+        }
+    }
+    #[async_trait]
+    impl<T: BenchmarkService> Service for BenchmarkServiceServer<T> {
+        async fn call(
+            &self,
+            method: String,
+            request: grpc::service::Request,
+        ) -> grpc::service::Response {
+            match method.as_str() {
+                "/grpc.testing.BenchmarkService/UnaryCall" => {
+                    let codec = grpc::codegen::ProstCodec::default();
+                    serve_unary(request, codec, |req| self.inner.unary_call(req)).await
+                }
+                "/grpc.testing.BenchmarkService/StreamingCall" => {
+                    let codec = grpc::codegen::ProstCodec::default();
+                    serve_streaming(request, codec, |req| self.inner.streaming_call(req))
+                        .await
+                }
+                "/grpc.testing.BenchmarkService/StreamingFromClient" => {
+                    let codec = grpc::codegen::ProstCodec::default();
+                    serve_client_streaming(
+                            request,
+                            codec,
+                            |req| self.inner.streaming_from_client(req),
+                        )
+                        .await
+                }
+                "/grpc.testing.BenchmarkService/StreamingFromServer" => {
+                    let codec = grpc::codegen::ProstCodec::default();
+                    serve_server_streaming(
+                            request,
+                            codec,
+                            |req| self.inner.streaming_from_server(req),
+                        )
+                        .await
+                }
+                "/grpc.testing.BenchmarkService/StreamingBothWays" => {
+                    let codec = grpc::codegen::ProstCodec::default();
+                    serve_streaming(
+                            request,
+                            codec,
+                            |req| self.inner.streaming_both_ways(req),
+                        )
+                        .await
+                }
+                _ => unimplemented(&method),
+            }
+        }
+    }
+}
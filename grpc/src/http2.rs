@@ -0,0 +1,115 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! HTTP/2 settings of channels and servers.
+//!
+//! Flow control limits how much data a peer may send before the receiver
+//! grants it more window.  Small windows cap the throughput of each stream to
+//! about window / round trip time, so links with a large bandwidth-delay
+//! product (BDP) need larger windows.  Adaptive windows grow the windows of a
+//! connection to match an estimate of its BDP instead.
+
+/// The smallest permitted value of the HTTP/2 SETTINGS_MAX_FRAME_SIZE.
+pub const MIN_MAX_FRAME_SIZE: u32 = 16_384;
+/// The largest permitted value of the HTTP/2 SETTINGS_MAX_FRAME_SIZE.
+pub const MAX_MAX_FRAME_SIZE: u32 = 16_777_215;
+
+/// HTTP/2 settings of a connection.  Unset values use the transport's
+/// defaults.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Http2Options {
+    /// The initial flow control window of each stream, in bytes.
+    pub initial_stream_window_size: Option<u32>,
+    /// The initial flow control window of the connection, in bytes.
+    pub initial_connection_window_size: Option<u32>,
+    /// The largest frame the peer may send, in bytes.  Clamped to
+    /// [`MIN_MAX_FRAME_SIZE`]..=[`MAX_MAX_FRAME_SIZE`].
+    pub max_frame_size: Option<u32>,
+    /// The largest header list the peer may send, in bytes.
+    pub max_header_list_size: Option<u32>,
+    /// Scales the windows of the connection and its streams based on an
+    /// estimate of the connection's BDP.  Overrides the initial window sizes.
+    pub adaptive_window: bool,
+}
+
+impl Http2Options {
+    pub fn initial_stream_window_size(self, size: u32) -> Self {
+        Self {
+            initial_stream_window_size: Some(size),
+            ..self
+        }
+    }
+
+    pub fn initial_connection_window_size(self, size: u32) -> Self {
+        Self {
+            initial_connection_window_size: Some(size),
+            ..self
+        }
+    }
+
+    pub fn max_frame_size(self, size: u32) -> Self {
+        Self {
+            max_frame_size: Some(size),
+            ..self
+        }
+    }
+
+    pub fn max_header_list_size(self, size: u32) -> Self {
+        Self {
+            max_header_list_size: Some(size),
+            ..self
+        }
+    }
+
+    pub fn adaptive_window(self, enabled: bool) -> Self {
+        Self {
+            adaptive_window: enabled,
+            ..self
+        }
+    }
+
+    /// Returns max_frame_size clamped to the range permitted by HTTP/2.
+    pub(crate) fn clamped_max_frame_size(&self) -> Option<u32> {
+        self.max_frame_size
+            .map(|size| size.clamp(MIN_MAX_FRAME_SIZE, MAX_MAX_FRAME_SIZE))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Http2Options, MAX_MAX_FRAME_SIZE, MIN_MAX_FRAME_SIZE};
+
+    #[test]
+    fn max_frame_size_is_clamped() {
+        let options = Http2Options::default();
+        assert_eq!(options.clamped_max_frame_size(), None);
+        let options = options.max_frame_size(1024);
+        assert_eq!(options.clamped_max_frame_size(), Some(MIN_MAX_FRAME_SIZE));
+        let options = options.max_frame_size(u32::MAX);
+        assert_eq!(options.clamped_max_frame_size(), Some(MAX_MAX_FRAME_SIZE));
+        let options = options.max_frame_size(65_536);
+        assert_eq!(options.clamped_max_frame_size(), Some(65_536));
+    }
+}
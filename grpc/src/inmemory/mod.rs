@@ -1,15 +1,22 @@
 use std::sync::atomic::{AtomicU32, Ordering};
-use std::sync::{Arc, LazyLock, Mutex};
+use std::sync::{Arc, LazyLock, Mutex, Weak};
 use std::{collections::HashMap, ops::Add};
 
 use crate::{
+    attributes::AttributeKey,
     client::{
+        error::{ConnectError, ConnectErrorKind, DisconnectReason},
         name_resolution::{
             self, global_registry, Address, ChannelController, Endpoint, Resolver, ResolverBuilder,
             ResolverOptions, ResolverUpdate,
         },
-        transport::{self, ConnectedTransport, TransportOptions, GLOBAL_TRANSPORT_REGISTRY},
+        transport::{
+            self, ConnectedTransport, SecurityLevel, TransportInfo, TransportOptions,
+            GLOBAL_TRANSPORT_REGISTRY,
+        },
+        ChannelOptions,
     },
+    codec::trailers_from_status,
     rt::Runtime,
     server,
     service::{Request, Response, Service},
@@ -23,28 +30,85 @@ pub struct Listener {
     r: Arc<AsyncMutex<mpsc::Receiver<Option<server::Call>>>>,
     // List of notifiers to call when closed.
     #[allow(clippy::type_complexity)]
-    closed_tx: Arc<Mutex<Vec<oneshot::Sender<Result<(), String>>>>>,
+    closed_tx: Arc<Mutex<Vec<oneshot::Sender<DisconnectReason>>>>,
+    // The handler of the server serving this listener, if any, used by
+    // connections made to the direct target.
+    direct: Mutex<Option<Arc<dyn Service>>>,
+    // The listeners of the network the listener belongs to.
+    network: Weak<Mutex<HashMap<String, Arc<Listener>>>>,
 }
 
 static ID: AtomicU32 = AtomicU32::new(0);
 
-impl Listener {
-    pub fn new() -> Arc<Self> {
+/// A namespace of listeners.  Channels connect to the listeners of the network
+/// set in their transport options under [`NETWORK`], or of the default network
+/// if none is set, so that tests using their own networks are isolated from
+/// each other.
+#[derive(Clone, Default)]
+pub struct Network {
+    listeners: Arc<Mutex<HashMap<String, Arc<Listener>>>>,
+}
+
+/// The attribute of [`ChannelOptions::transport_options`] selecting the
+/// network of a channel.
+pub static NETWORK: AttributeKey<Network> = AttributeKey::new("grpc.inmemory.network");
+
+static DEFAULT_NETWORK: LazyLock<Network> = LazyLock::new(Network::default);
+
+impl Network {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a listener reachable by channels using this network.
+    pub fn listener(&self) -> Arc<Listener> {
         let (tx, rx) = mpsc::channel(1);
-        let s = Arc::new(Self {
+        let s = Arc::new(Listener {
             id: format!("{}", ID.fetch_add(1, Ordering::Relaxed)),
             s: Box::new(tx),
             r: Arc::new(AsyncMutex::new(rx)),
             closed_tx: Arc::new(Mutex::new(Vec::new())),
+            direct: Mutex::new(None),
+            network: Arc::downgrade(&self.listeners),
         });
-        LISTENERS.lock().unwrap().insert(s.id.clone(), s.clone());
+        self.listeners
+            .lock()
+            .unwrap()
+            .insert(s.id.clone(), s.clone());
         s
     }
 
+    /// Returns options which make channels use this network.
+    pub fn channel_options(&self, options: ChannelOptions) -> ChannelOptions {
+        let attributes = options
+            .transport_options
+            .clone()
+            .add(&NETWORK, self.clone());
+        options.transport_options(attributes)
+    }
+
+    fn get(&self, id: &str) -> Option<Arc<Listener>> {
+        self.listeners.lock().unwrap().get(id).cloned()
+    }
+}
+
+impl Listener {
+    /// Creates a listener in the default network.
+    pub fn new() -> Arc<Self> {
+        DEFAULT_NETWORK.listener()
+    }
+
     pub fn target(&self) -> String {
         format!("inmemory:///{}", self.id)
     }
 
+    /// Returns a target whose calls are passed to the server's handler in the
+    /// calling task, without queueing them for the server's accept loop.
+    /// Calls made before the listener is served are queued as usual.
+    pub fn direct_target(&self) -> String {
+        format!("inmemory:///{}?mode=direct", self.id)
+    }
+
     pub fn id(&self) -> String {
         self.id.clone()
     }
@@ -58,9 +122,11 @@ impl Drop for Listener {
     fn drop(&mut self) {
         let txs = std::mem::take(&mut *self.closed_tx.lock().unwrap());
         for rx in txs {
-            let _ = rx.send(Ok(()));
+            let _ = rx.send(DisconnectReason::Closed);
+        }
+        if let Some(listeners) = self.network.upgrade() {
+            listeners.lock().unwrap().remove(&self.id);
         }
-        LISTENERS.lock().unwrap().remove(&self.id);
     }
 }
 
@@ -71,7 +137,7 @@ impl Service for Arc<Listener> {
         // 2. return what that func had
         let (s, r) = oneshot::channel();
         self.s.send(Some((method, request, s))).await.unwrap();
-        r.await.unwrap()
+        trailers_from_status(r.await.unwrap())
     }
 }
 
@@ -83,15 +149,35 @@ impl crate::server::Listener for Arc<Listener> {
         // Listener may be closed.
         r?
     }
+
+    fn set_direct_handler(&self, handler: Option<Arc<dyn Service>>) {
+        *self.direct.lock().unwrap() = handler;
+    }
 }
 
-static LISTENERS: LazyLock<Mutex<HashMap<String, Arc<Listener>>>> = LazyLock::new(Mutex::default);
+// A connection to a listener that calls the server's handler directly.
+struct DirectConnection {
+    lis: Arc<Listener>,
+}
 
-struct ClientTransport {}
+#[async_trait]
+impl Service for DirectConnection {
+    async fn call(&self, method: String, request: Request) -> Response {
+        let handler = self.lis.direct.lock().unwrap().clone();
+        match handler {
+            Some(handler) => trailers_from_status(handler.call(method, request).await),
+            None => self.lis.call(method, request).await,
+        }
+    }
+}
+
+struct ClientTransport {
+    direct: bool,
+}
 
 impl ClientTransport {
-    fn new() -> Self {
-        Self {}
+    fn new(direct: bool) -> Self {
+        Self { direct }
     }
 }
 
@@ -99,29 +185,49 @@ impl ClientTransport {
 impl transport::Transport for ClientTransport {
     async fn connect(
         &self,
-        address: String,
+        address: &Address,
         _: Arc<dyn Runtime>,
-        _: &TransportOptions,
-    ) -> Result<ConnectedTransport, String> {
-        let lis = LISTENERS
-            .lock()
-            .unwrap()
-            .get(&address)
-            .ok_or(format!("Could not find listener for address {address}"))?
-            .clone();
+        options: &TransportOptions,
+    ) -> Result<ConnectedTransport, ConnectError> {
+        // Resolvers may place individual addresses in other networks.
+        let network = address
+            .attributes
+            .get(&NETWORK)
+            .or_else(|| options.attributes.get(&NETWORK))
+            .unwrap_or(&DEFAULT_NETWORK);
+        let address = address.address.to_string();
+        let lis = network.get(&address).ok_or_else(|| {
+            ConnectError::new(
+                ConnectErrorKind::Refused,
+                format!("Could not find listener for address {address}"),
+            )
+        })?;
         let (tx, rx) = oneshot::channel();
         lis.closed_tx.lock().unwrap().push(tx);
+        let (network_type, service): (_, Box<dyn Service>) = if self.direct {
+            (
+                INMEMORY_DIRECT_NETWORK_TYPE,
+                Box::new(DirectConnection { lis }),
+            )
+        } else {
+            (INMEMORY_NETWORK_TYPE, Box::new(lis))
+        };
         Ok(ConnectedTransport {
-            service: Box::new(lis),
+            service,
             disconnection_listener: rx,
+            // In-process connections cannot be observed by other parties.
+            info: TransportInfo::new(network_type, SecurityLevel::PrivacyAndIntegrity, address),
         })
     }
 }
 
 static INMEMORY_NETWORK_TYPE: &str = "inmemory";
+static INMEMORY_DIRECT_NETWORK_TYPE: &str = "inmemory-direct";
 
 pub fn reg() {
-    GLOBAL_TRANSPORT_REGISTRY.add_transport(INMEMORY_NETWORK_TYPE, ClientTransport::new());
+    GLOBAL_TRANSPORT_REGISTRY.add_transport(INMEMORY_NETWORK_TYPE, ClientTransport::new(false));
+    GLOBAL_TRANSPORT_REGISTRY
+        .add_transport(INMEMORY_DIRECT_NETWORK_TYPE, ClientTransport::new(true));
     global_registry().add_builder(Box::new(InMemoryResolverBuilder));
 }
 
@@ -138,8 +244,12 @@ impl ResolverBuilder for InMemoryResolverBuilder {
         options: ResolverOptions,
     ) -> Box<dyn Resolver> {
         let id = target.path().strip_prefix("/").unwrap().to_string();
+        let network_type = match target.query_param("mode").as_deref() {
+            Some("direct") => INMEMORY_DIRECT_NETWORK_TYPE,
+            _ => INMEMORY_NETWORK_TYPE,
+        };
         options.work_scheduler.schedule_work();
-        Box::new(NopResolver { id })
+        Box::new(NopResolver { id, network_type })
     }
 
     fn is_valid_uri(&self, uri: &crate::client::name_resolution::Target) -> bool {
@@ -149,27 +259,115 @@ impl ResolverBuilder for InMemoryResolverBuilder {
 
 struct NopResolver {
     id: String,
+    network_type: &'static str,
 }
 
 impl Resolver for NopResolver {
     fn work(&mut self, channel_controller: &mut dyn ChannelController) {
-        let mut addresses: Vec<Address> = Vec::new();
-        for addr in LISTENERS.lock().unwrap().keys() {
-            addresses.push(Address {
-                network_type: INMEMORY_NETWORK_TYPE,
-                address: addr.clone().into(),
-                ..Default::default()
-            });
+        let endpoint = Endpoint::builder()
+            .addresses([Address::new(self.network_type, self.id.clone())])
+            .build();
+        let update = match endpoint {
+            Ok(endpoint) => ResolverUpdate::builder().endpoint(endpoint),
+            Err(err) => ResolverUpdate::builder().endpoints_error(err),
+        };
+        let _ = channel_controller.update(update.build());
+    }
+
+    fn resolve_now(&mut self) {}
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use tokio_stream::StreamExt;
+    use tonic::async_trait;
+
+    use super::{reg, Listener, Network, DEFAULT_NETWORK};
+    use crate::client::{Channel, ChannelOptions};
+    use crate::server::Server;
+    use crate::service::{Request, Response, Service};
+
+    // Records the task each call is handled in.
+    #[derive(Default)]
+    struct TaskRecorder {
+        tasks: Mutex<Vec<Option<tokio::task::Id>>>,
+    }
+
+    #[async_trait]
+    impl Service for Arc<TaskRecorder> {
+        async fn call(&self, _method: String, _request: Request) -> Response {
+            self.tasks.lock().unwrap().push(tokio::task::try_id());
+            Response::new(Box::pin(tokio_stream::empty()))
         }
+    }
 
-        let _ = channel_controller.update(ResolverUpdate {
-            endpoints: Ok(vec![Endpoint {
-                addresses,
-                ..Default::default()
-            }]),
-            ..Default::default()
+    #[tokio::test]
+    async fn direct_calls_run_in_calling_task() {
+        reg();
+        let lis = Listener::new();
+        let recorder = Arc::new(TaskRecorder::default());
+        let mut srv = Server::new();
+        srv.set_handler(recorder.clone());
+        let srv = Arc::new(srv);
+        let serve = tokio::spawn({
+            let srv = srv.clone();
+            let lis = lis.clone();
+            async move { srv.serve(&lis).await }
         });
+
+        let caller = tokio::spawn({
+            let target = lis.direct_target();
+            async move {
+                let chan = Channel::new(&target, None, ChannelOptions::default());
+                // The first call may be queued if the server is not serving
+                // the listener yet.
+                for _ in 0..2 {
+                    let req = Request::new(Box::pin(tokio_stream::empty()));
+                    let res = chan.call("/svc/Method".to_string(), req).await;
+                    assert!(res.into_inner().next().await.is_none());
+                }
+                tokio::task::id()
+            }
+        });
+        let caller = caller.await.unwrap();
+        assert_eq!(recorder.tasks.lock().unwrap().last(), Some(&Some(caller)));
+
+        srv.graceful_shutdown().await;
+        serve.await.unwrap();
+        assert!(lis.direct.lock().unwrap().is_none());
+        lis.close().await;
     }
 
-    fn resolve_now(&mut self) {}
+    #[tokio::test]
+    async fn networks_are_isolated() {
+        reg();
+        let net = Network::new();
+        let lis = net.listener();
+        assert!(net.get(&lis.id).is_some());
+        assert!(DEFAULT_NETWORK.get(&lis.id).is_none());
+
+        let mut srv = Server::new();
+        srv.set_handler(Arc::new(TaskRecorder::default()));
+        let srv = Arc::new(srv);
+        let serve = tokio::spawn({
+            let srv = srv.clone();
+            let lis = lis.clone();
+            async move { srv.serve(&lis).await }
+        });
+
+        let chan = Channel::new(
+            &lis.target(),
+            None,
+            net.channel_options(ChannelOptions::default()),
+        );
+        let req = Request::new(Box::pin(tokio_stream::empty()));
+        let res = chan.call("/svc/Method".to_string(), req).await;
+        assert!(res.into_inner().next().await.is_none());
+
+        srv.graceful_shutdown().await;
+        serve.await.unwrap();
+        lis.close().await;
+    }
 }
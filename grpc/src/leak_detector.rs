@@ -0,0 +1,133 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! Counts live instances of the library's long-lived types so that tests can
+//! verify that everything is cleaned up once a channel is dropped.
+//!
+//! Counting is only performed when the `_leak-detector` feature is enabled;
+//! otherwise `LeakTracker` is a zero-sized no-op and the counts are always
+//! empty.
+
+use std::{collections::BTreeMap, time::Duration};
+
+/// Embedded in a tracked type to count its live instances.  The count for the
+/// tracked name is incremented when the tracker is created and decremented
+/// when it is dropped.
+#[derive(Debug)]
+pub(crate) struct LeakTracker {
+    #[cfg(feature = "_leak-detector")]
+    name: &'static str,
+}
+
+impl LeakTracker {
+    pub(crate) fn new(name: &'static str) -> Self {
+        #[cfg(feature = "_leak-detector")]
+        {
+            *imp::COUNTS.lock().unwrap().entry(name).or_default() += 1;
+            Self { name }
+        }
+        #[cfg(not(feature = "_leak-detector"))]
+        Self {}
+    }
+}
+
+#[cfg(feature = "_leak-detector")]
+impl Drop for LeakTracker {
+    fn drop(&mut self) {
+        let mut counts = imp::COUNTS.lock().unwrap();
+        let count = counts.get_mut(self.name).unwrap();
+        *count -= 1;
+        if *count == 0 {
+            counts.remove(self.name);
+        }
+    }
+}
+
+#[cfg(feature = "_leak-detector")]
+mod imp {
+    use std::{
+        collections::BTreeMap,
+        sync::{LazyLock, Mutex},
+    };
+
+    pub(super) static COUNTS: LazyLock<Mutex<BTreeMap<&'static str, usize>>> =
+        LazyLock::new(Mutex::default);
+}
+
+/// Returns the number of live instances of every tracked type that has any.
+pub fn live_instances() -> BTreeMap<&'static str, usize> {
+    #[cfg(feature = "_leak-detector")]
+    {
+        imp::COUNTS.lock().unwrap().clone()
+    }
+    #[cfg(not(feature = "_leak-detector"))]
+    BTreeMap::new()
+}
+
+/// Waits up to timeout for all tracked instances to be dropped, and panics
+/// listing the remaining ones if they are not.  Cleanup is often performed by
+/// background tasks, so some delay after dropping a channel is expected.
+///
+/// Counts are global to the process, so this should only be called when no
+/// other test is concurrently using the library, e.g. from an integration test
+/// whose tests do not run concurrently.
+#[cfg(feature = "_leak-detector")]
+pub async fn assert_no_leaks(timeout: Duration) {
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        let live = live_instances();
+        if live.is_empty() {
+            return;
+        }
+        if tokio::time::Instant::now() >= deadline {
+            panic!("leaked instances after {timeout:?}: {live:?}");
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
+
+#[cfg(all(test, feature = "_leak-detector"))]
+mod test {
+    use std::time::Duration;
+
+    use super::{assert_no_leaks, live_instances, LeakTracker};
+
+    #[tokio::test]
+    async fn counts_live_instances() {
+        let a = LeakTracker::new("leak_detector::test");
+        let b = LeakTracker::new("leak_detector::test");
+        assert_eq!(live_instances().get("leak_detector::test"), Some(&2));
+        drop(a);
+        assert_eq!(live_instances().get("leak_detector::test"), Some(&1));
+        drop(b);
+        assert_eq!(live_instances().get("leak_detector::test"), None);
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "leaked instances")]
+    async fn reports_leaks() {
+        let _leaked = LeakTracker::new("leak_detector::leaked");
+        assert_no_leaks(Duration::from_millis(20)).await;
+    }
+}
@@ -31,17 +31,52 @@
 //! [gRPC]: https://grpc.io
 #![allow(dead_code, unused_variables, unused_imports)]
 
+#[cfg(feature = "benchmark")]
+pub mod benchmark;
+pub mod binlog;
 pub mod client;
+pub mod codegen;
+pub mod compression;
 pub mod credentials;
+pub mod ext;
+#[cfg(feature = "_fuzzing")]
+#[doc(hidden)]
+pub mod fuzzing;
+pub mod http2;
 pub mod inmemory;
 mod macros;
+pub mod orca;
 pub mod rt;
 pub mod server;
 pub mod service;
+pub mod stats;
+pub mod trace_context;
+
+pub use client::{Channel, ChannelOptions, ConnectivityState};
+pub use server::Server;
+pub use service::{status_response, Code, Message, Request, Response, Service, Status};
+
+/// The types most applications need, for glob importing:
+///
+/// ```
+/// use grpc::prelude::*;
+/// ```
+pub mod prelude {
+    pub use crate::client::{Channel, ChannelOptions, ConnectivityState};
+    pub use crate::credentials::Credentials;
+    pub use crate::server::Server;
+    pub use crate::service::{status_response, Code, Message, Request, Response, Service, Status};
+}
 
 pub(crate) mod attributes;
 pub(crate) mod byte_str;
 pub(crate) mod codec;
+#[cfg(feature = "_leak-detector")]
+#[doc(hidden)]
+pub mod leak_detector;
+#[cfg(not(feature = "_leak-detector"))]
+pub(crate) mod leak_detector;
+pub(crate) mod xxhash;
 #[cfg(test)]
 pub(crate) mod echo_pb {
     include!(concat!(
@@ -49,3 +84,13 @@ pub(crate) mod echo_pb {
         "/src/generated/grpc_examples_echo.rs"
     ));
 }
+// Lets the generated code below refer to this crate as `grpc`.
+#[cfg(any(all(test, feature = "prost"), feature = "benchmark"))]
+extern crate self as grpc;
+#[cfg(all(test, feature = "prost"))]
+pub(crate) mod echo_grpc {
+    include!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/generated/grpc_examples_echo_grpc.rs"
+    ));
+}
@@ -0,0 +1,391 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! Open Request Cost Aggregation (ORCA) backend metrics, as described in
+//! [gRFC A51].
+//!
+//! Servers record per-call metrics with a [`CallMetricsRecorder`], which are
+//! sent to the client in the `endpoint-load-metrics-bin` trailer of the
+//! response.  LB policies may read them from the status of the outcome passed
+//! to `Pick::on_complete` using [`BackendMetrics::from_metadata`], or receive
+//! periodic out-of-band reports by starting an [`OobMetricsStream`] on a
+//! subchannel.
+//!
+//! [gRFC A51]: https://github.com/grpc/proposal/blob/master/A51-custom-backend-metrics.md
+
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{ready, Context, Poll},
+};
+
+use bytes::{Bytes, BytesMut};
+use tokio_stream::Stream;
+use tonic::{
+    metadata::{MetadataMap, MetadataValue},
+    Code, Status,
+};
+
+use crate::service::{Message, Request, Response};
+
+mod oob;
+mod wire;
+
+pub use oob::{OobMetricsListener, OobMetricsStream};
+
+/// The metadata key used to carry per-call backend metrics.
+pub const METADATA_KEY: &str = "endpoint-load-metrics-bin";
+
+/// A set of backend metrics reported by a server.  Mirrors the
+/// `xds.data.orca.v3.OrcaLoadReport` message.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BackendMetrics {
+    /// CPU utilization, normally in the range [0, 1].
+    pub cpu_utilization: f64,
+    /// Memory utilization in the range [0, 1].
+    pub mem_utilization: f64,
+    /// Application-specific utilization, normally in the range [0, 1].
+    pub application_utilization: f64,
+    /// Queries per second handled by the server.
+    pub qps: f64,
+    /// Errors per second returned by the server.
+    pub eps: f64,
+    /// Per-call costs, e.g. the number of database rows read.
+    pub request_cost: HashMap<String, f64>,
+    /// Named utilization metrics in the range [0, 1].
+    pub utilization: HashMap<String, f64>,
+    /// Arbitrary named metrics.
+    pub named_metrics: HashMap<String, f64>,
+}
+
+impl BackendMetrics {
+    /// Returns true if no metric has been set.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Serializes the metrics as an `OrcaLoadReport` protobuf message.
+    pub fn encode(&self) -> Bytes {
+        let mut buf = BytesMut::new();
+        wire::put_double(&mut buf, 1, self.cpu_utilization);
+        wire::put_double(&mut buf, 2, self.mem_utilization);
+        for (k, v) in &self.request_cost {
+            wire::put_map_entry(&mut buf, 4, k, *v);
+        }
+        for (k, v) in &self.utilization {
+            wire::put_map_entry(&mut buf, 5, k, *v);
+        }
+        wire::put_double(&mut buf, 6, self.qps);
+        wire::put_double(&mut buf, 7, self.eps);
+        for (k, v) in &self.named_metrics {
+            wire::put_map_entry(&mut buf, 8, k, *v);
+        }
+        wire::put_double(&mut buf, 9, self.application_utilization);
+        buf.freeze()
+    }
+
+    /// Parses an `OrcaLoadReport` protobuf message.
+    pub fn decode(mut buf: Bytes) -> Result<Self, String> {
+        let mut metrics = BackendMetrics::default();
+        while let Some((field, value)) = wire::next_field(&mut buf)? {
+            match field {
+                1 => metrics.cpu_utilization = value.as_double()?,
+                2 => metrics.mem_utilization = value.as_double()?,
+                // Field 3 is the deprecated integer rps; prefer field 6.
+                3 if metrics.qps == 0.0 => metrics.qps = value.as_varint()? as f64,
+                4 => {
+                    let (k, v) = wire::get_map_entry(value.as_bytes()?)?;
+                    metrics.request_cost.insert(k, v);
+                }
+                5 => {
+                    let (k, v) = wire::get_map_entry(value.as_bytes()?)?;
+                    metrics.utilization.insert(k, v);
+                }
+                6 => metrics.qps = value.as_double()?,
+                7 => metrics.eps = value.as_double()?,
+                8 => {
+                    let (k, v) = wire::get_map_entry(value.as_bytes()?)?;
+                    metrics.named_metrics.insert(k, v);
+                }
+                9 => metrics.application_utilization = value.as_double()?,
+                _ => {}
+            }
+        }
+        Ok(metrics)
+    }
+
+    /// Extracts the per-call backend metrics from response metadata.  Returns
+    /// Ok(None) if the server did not report any.
+    pub fn from_metadata(metadata: &MetadataMap) -> Result<Option<Self>, String> {
+        let Some(value) = metadata.get_bin(METADATA_KEY) else {
+            return Ok(None);
+        };
+        let bytes = value.to_bytes().map_err(|e| e.to_string())?;
+        Self::decode(bytes).map(Some)
+    }
+
+    /// Writes the metrics into metadata under [`METADATA_KEY`].
+    pub fn to_metadata(&self, metadata: &mut MetadataMap) {
+        metadata.insert_bin(METADATA_KEY, MetadataValue::from_bytes(&self.encode()));
+    }
+}
+
+/// Records backend metrics for a single call on the server.
+///
+/// The server inserts a recorder into the extensions of every request it
+/// dispatches; handlers retrieve it with [`CallMetricsRecorder::from_request`].
+/// Anything recorded before the response stream completes is sent to the
+/// client in the trailers of the response.
+#[derive(Debug, Clone, Default)]
+pub struct CallMetricsRecorder {
+    metrics: Arc<Mutex<BackendMetrics>>,
+}
+
+impl CallMetricsRecorder {
+    /// Returns the recorder attached to request, if any.
+    pub fn from_request(request: &Request) -> Option<&Self> {
+        request.extensions().get::<Self>()
+    }
+
+    pub fn record_cpu_utilization(&self, v: f64) {
+        self.metrics.lock().unwrap().cpu_utilization = v;
+    }
+
+    pub fn record_mem_utilization(&self, v: f64) {
+        self.metrics.lock().unwrap().mem_utilization = v;
+    }
+
+    pub fn record_application_utilization(&self, v: f64) {
+        self.metrics.lock().unwrap().application_utilization = v;
+    }
+
+    pub fn record_qps(&self, v: f64) {
+        self.metrics.lock().unwrap().qps = v;
+    }
+
+    pub fn record_eps(&self, v: f64) {
+        self.metrics.lock().unwrap().eps = v;
+    }
+
+    pub fn record_request_cost(&self, name: impl Into<String>, v: f64) {
+        self.metrics
+            .lock()
+            .unwrap()
+            .request_cost
+            .insert(name.into(), v);
+    }
+
+    pub fn record_utilization(&self, name: impl Into<String>, v: f64) {
+        self.metrics
+            .lock()
+            .unwrap()
+            .utilization
+            .insert(name.into(), v);
+    }
+
+    pub fn record_named_metric(&self, name: impl Into<String>, v: f64) {
+        self.metrics
+            .lock()
+            .unwrap()
+            .named_metrics
+            .insert(name.into(), v);
+    }
+
+    /// Returns a snapshot of the metrics recorded so far.
+    pub fn metrics(&self) -> BackendMetrics {
+        self.metrics.lock().unwrap().clone()
+    }
+
+    /// Sends the metrics recorded once response completes in its trailers,
+    /// i.e. in the metadata of its final status.  A response which completes
+    /// successfully ends with an OK status carrying the metrics.
+    pub(crate) fn send_in_trailers(self, response: Response) -> Response {
+        response.map(|inner| {
+            Box::pin(TrailerMetricsStream {
+                inner,
+                recorder: Some(self),
+            }) as Pin<Box<dyn Stream<Item = Result<Box<dyn Message>, Status>> + Send>>
+        })
+    }
+}
+
+pin_project_lite::pin_project! {
+    // Adds the metrics recorded for a call to the final status of its
+    // response.
+    struct TrailerMetricsStream<S> {
+        #[pin]
+        inner: S,
+        recorder: Option<CallMetricsRecorder>,
+    }
+}
+
+impl<S: Stream<Item = Result<Box<dyn Message>, Status>>> Stream for TrailerMetricsStream<S> {
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let Some(recorder) = this.recorder.as_ref() else {
+            return Poll::Ready(None);
+        };
+        let mut status = match ready!(this.inner.poll_next(cx)) {
+            Some(Ok(msg)) => return Poll::Ready(Some(Ok(msg))),
+            Some(Err(status)) => status,
+            None => Status::new(Code::Ok, ""),
+        };
+        let metrics = recorder.metrics();
+        *this.recorder = None;
+        if !metrics.is_empty() {
+            metrics.to_metadata(status.metadata_mut());
+        } else if status.code() == Code::Ok {
+            return Poll::Ready(None);
+        }
+        Poll::Ready(Some(Err(status)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tonic::metadata::MetadataMap;
+
+    use super::BackendMetrics;
+
+    #[test]
+    fn round_trip() {
+        let mut metrics = BackendMetrics {
+            cpu_utilization: 0.5,
+            mem_utilization: 0.25,
+            application_utilization: 0.75,
+            qps: 100.0,
+            eps: 1.5,
+            ..Default::default()
+        };
+        metrics.request_cost.insert("db_rows".to_string(), 12.0);
+        metrics.utilization.insert("gpu".to_string(), 0.1);
+        metrics.named_metrics.insert("queue".to_string(), 3.0);
+
+        let decoded = BackendMetrics::decode(metrics.encode()).unwrap();
+        assert_eq!(decoded, metrics);
+
+        let mut md = MetadataMap::new();
+        assert_eq!(BackendMetrics::from_metadata(&md), Ok(None));
+        metrics.to_metadata(&mut md);
+        assert_eq!(BackendMetrics::from_metadata(&md), Ok(Some(metrics)));
+    }
+
+    #[test]
+    fn decode_errors() {
+        // A double field (1) truncated after the tag.
+        assert!(BackendMetrics::decode(vec![0x09, 0x00].into()).is_err());
+        // Unknown fields are skipped.
+        let decoded = BackendMetrics::decode(vec![0x50, 0x01].into()).unwrap();
+        assert!(decoded.is_empty());
+    }
+
+    #[cfg(feature = "_runtime-tokio")]
+    #[tokio::test]
+    async fn sends_call_metrics_in_trailers() {
+        use std::sync::Arc;
+
+        use bytes::Bytes;
+        use tokio_stream::StreamExt;
+        use tonic::{async_trait, Code, Status};
+
+        use super::CallMetricsRecorder;
+        use crate::client::name_resolution::{Address, TCP_IP_NETWORK_TYPE};
+        use crate::client::transport::{TransportOptions, GLOBAL_TRANSPORT_REGISTRY};
+        use crate::codec::ResponseTrailers;
+        use crate::rt::tokio::TokioRuntime;
+        use crate::server::{tcp::TcpListener, Server};
+        use crate::service::{Message, Request, Response, Service};
+
+        // Replies with a message, recording the load once it has been sent,
+        // and fails calls to the Fail method afterwards.
+        struct Reporter {}
+
+        #[async_trait]
+        impl Service for Reporter {
+            async fn call(&self, method: String, request: Request) -> Response {
+                let recorder = CallMetricsRecorder::from_request(&request).unwrap().clone();
+                let msg: Box<dyn Message> = Box::new(Bytes::from_static(b"reply"));
+                let fail = method.ends_with("/Fail");
+                let end = tokio_stream::iter([()]).filter_map(move |()| {
+                    recorder.record_cpu_utilization(0.5);
+                    fail.then(|| Err(Status::internal("failed")))
+                });
+                Response::new(Box::pin(tokio_stream::once(Ok(msg)).chain(end)))
+            }
+        }
+
+        crate::client::reg();
+        let lis = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let address = Address::new(TCP_IP_NETWORK_TYPE, lis.local_addr().to_string());
+        let mut srv = Server::new();
+        srv.set_handler(Reporter {});
+        tokio::spawn(async move { srv.serve(&lis).await });
+
+        let transport = GLOBAL_TRANSPORT_REGISTRY
+            .get_transport(TCP_IP_NETWORK_TYPE)
+            .unwrap()
+            .connect(
+                &address,
+                Arc::new(TokioRuntime {}),
+                &TransportOptions::default(),
+            )
+            .await
+            .unwrap();
+        let expected = BackendMetrics {
+            cpu_utilization: 0.5,
+            ..Default::default()
+        };
+        let call = |method: &str| {
+            let msg: Box<dyn Message> = Box::new(Bytes::new());
+            let req = Request::new(Box::pin(tokio_stream::once(msg)));
+            transport.service.call(method.to_string(), req)
+        };
+
+        // Successful calls carry the metrics in their trailers, not their
+        // headers.
+        let res = call("/svc/Ok").await;
+        assert_eq!(BackendMetrics::from_metadata(res.metadata()), Ok(None));
+        let trailers = res.extensions().get::<ResponseTrailers>().unwrap().clone();
+        let items: Vec<_> = res.into_inner().collect().await;
+        assert_eq!(items.len(), 1);
+        assert!(items[0].is_ok());
+        assert_eq!(
+            BackendMetrics::from_metadata(trailers.get().unwrap()),
+            Ok(Some(expected.clone()))
+        );
+
+        // Failed calls carry them in the metadata of their status.
+        let res = call("/svc/Fail").await;
+        let status = res.into_inner().filter_map(Result::err).next().await;
+        let status = status.unwrap();
+        assert_eq!(status.code(), Code::Internal);
+        assert_eq!(
+            BackendMetrics::from_metadata(status.metadata()),
+            Ok(Some(expected))
+        );
+    }
+}
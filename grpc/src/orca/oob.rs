@@ -0,0 +1,136 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! An out-of-band client for the `xds.service.orca.v3.OpenRcaService` service,
+//! which streams backend metrics over a dedicated RPC at a fixed interval.
+
+use std::{sync::Arc, time::Duration};
+
+use bytes::{Bytes, BytesMut};
+use tokio_stream::StreamExt;
+
+use crate::{
+    client::load_balancing::{ExternalSubchannel, Subchannel},
+    rt::BoxedTaskHandle,
+    service::{Message, Request},
+};
+
+use super::{wire, BackendMetrics};
+
+const STREAM_CORE_METRICS_METHOD: &str = "/xds.service.orca.v3.OpenRcaService/StreamCoreMetrics";
+
+/// Receives backend metrics reported by an [`OobMetricsStream`].
+pub trait OobMetricsListener: Send + Sync {
+    /// Called for every report received from the server.
+    fn on_metrics(&self, metrics: &BackendMetrics);
+
+    /// Called once when the stream terminates, with a description of the
+    /// error if it did not end cleanly.
+    fn on_stream_end(&self, _error: Option<String>) {}
+}
+
+/// A running out-of-band metrics stream on a single subchannel.  The stream
+/// is cancelled when this value is dropped.
+pub struct OobMetricsStream {
+    task: BoxedTaskHandle,
+}
+
+impl OobMetricsStream {
+    /// Starts streaming metrics from the server on the other end of
+    /// subchannel, asking for a report every interval.
+    ///
+    /// The subchannel must be Ready; LB policies typically start a stream when
+    /// the subchannel reports Ready and drop it when it leaves that state.
+    pub fn start(
+        subchannel: &Arc<dyn Subchannel>,
+        interval: Duration,
+        listener: Arc<dyn OobMetricsListener>,
+    ) -> Result<Self, String> {
+        let Some(esc) = subchannel.downcast_ref::<ExternalSubchannel>() else {
+            return Err("subchannel was not created by the channel".to_string());
+        };
+        let Some(isc) = esc.isc.as_ref() else {
+            return Err(format!("{subchannel} is not ready"));
+        };
+        let Some(svc) = isc.connected_service() else {
+            return Err(format!("{subchannel} is not ready"));
+        };
+        let request = Request::new(Box::pin(tokio_stream::once(
+            Box::new(encode_request(interval)) as Box<dyn Message>,
+        )));
+        let task = isc.runtime().spawn(Box::pin(async move {
+            let mut stream = svc
+                .call(STREAM_CORE_METRICS_METHOD.to_string(), request)
+                .await
+                .into_inner();
+            while let Some(msg) = stream.next().await {
+                let msg = match msg {
+                    Ok(msg) => msg,
+                    Err(status) => {
+                        listener.on_stream_end(Some(status.to_string()));
+                        return;
+                    }
+                };
+                let Some(bytes) = (msg as Box<dyn std::any::Any>)
+                    .downcast_ref::<Bytes>()
+                    .cloned()
+                else {
+                    listener.on_stream_end(Some("unexpected message type".to_string()));
+                    return;
+                };
+                match BackendMetrics::decode(bytes) {
+                    Ok(metrics) => listener.on_metrics(&metrics),
+                    Err(err) => {
+                        listener.on_stream_end(Some(err));
+                        return;
+                    }
+                }
+            }
+            listener.on_stream_end(None);
+        }));
+        Ok(Self { task })
+    }
+}
+
+impl Drop for OobMetricsStream {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Encodes an `OrcaLoadReportRequest` with the given report interval.
+fn encode_request(interval: Duration) -> Bytes {
+    let mut duration = BytesMut::new();
+    if interval.as_secs() != 0 {
+        wire::put_tag(&mut duration, 1, wire::WIRE_VARINT);
+        wire::put_varint(&mut duration, interval.as_secs());
+    }
+    if interval.subsec_nanos() != 0 {
+        wire::put_tag(&mut duration, 2, wire::WIRE_VARINT);
+        wire::put_varint(&mut duration, interval.subsec_nanos() as u64);
+    }
+    let mut buf = BytesMut::new();
+    wire::put_len_delimited(&mut buf, 1, &duration);
+    buf.freeze()
+}
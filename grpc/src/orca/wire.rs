@@ -0,0 +1,166 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! Minimal protobuf wire-format helpers for the ORCA messages.  The messages
+//! involved are small and stable, so they are encoded by hand rather than
+//! pulling a protobuf runtime into the crate.
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+pub(super) const WIRE_VARINT: u8 = 0;
+pub(super) const WIRE_FIXED64: u8 = 1;
+pub(super) const WIRE_LEN: u8 = 2;
+pub(super) const WIRE_FIXED32: u8 = 5;
+
+pub(super) fn put_varint(buf: &mut BytesMut, mut v: u64) {
+    while v >= 0x80 {
+        buf.put_u8((v as u8) | 0x80);
+        v >>= 7;
+    }
+    buf.put_u8(v as u8);
+}
+
+pub(super) fn put_tag(buf: &mut BytesMut, field: u32, wire_type: u8) {
+    put_varint(buf, ((field as u64) << 3) | wire_type as u64);
+}
+
+/// Writes a double field, omitting it if it holds the default value.
+pub(super) fn put_double(buf: &mut BytesMut, field: u32, v: f64) {
+    if v == 0.0 {
+        return;
+    }
+    put_tag(buf, field, WIRE_FIXED64);
+    buf.put_f64_le(v);
+}
+
+pub(super) fn put_len_delimited(buf: &mut BytesMut, field: u32, data: &[u8]) {
+    put_tag(buf, field, WIRE_LEN);
+    put_varint(buf, data.len() as u64);
+    buf.put_slice(data);
+}
+
+/// Writes a map<string, double> entry.
+pub(super) fn put_map_entry(buf: &mut BytesMut, field: u32, key: &str, value: f64) {
+    let mut entry = BytesMut::new();
+    put_len_delimited(&mut entry, 1, key.as_bytes());
+    put_double(&mut entry, 2, value);
+    put_len_delimited(buf, field, &entry);
+}
+
+/// A single decoded field.
+pub(super) enum Field {
+    Varint(u64),
+    Fixed64(u64),
+    Len(Bytes),
+    Fixed32(u32),
+}
+
+impl Field {
+    pub(super) fn as_double(&self) -> Result<f64, String> {
+        match self {
+            Field::Fixed64(v) => Ok(f64::from_bits(*v)),
+            _ => Err("expected a double field".to_string()),
+        }
+    }
+
+    pub(super) fn as_varint(&self) -> Result<u64, String> {
+        match self {
+            Field::Varint(v) => Ok(*v),
+            _ => Err("expected a varint field".to_string()),
+        }
+    }
+
+    pub(super) fn as_bytes(&self) -> Result<Bytes, String> {
+        match self {
+            Field::Len(b) => Ok(b.clone()),
+            _ => Err("expected a length-delimited field".to_string()),
+        }
+    }
+
+    pub(super) fn as_string(&self) -> Result<String, String> {
+        String::from_utf8(self.as_bytes()?.to_vec()).map_err(|e| e.to_string())
+    }
+}
+
+fn get_varint(buf: &mut Bytes) -> Result<u64, String> {
+    let mut v = 0u64;
+    for shift in (0..64).step_by(7) {
+        if !buf.has_remaining() {
+            return Err("truncated varint".to_string());
+        }
+        let b = buf.get_u8();
+        v |= ((b & 0x7f) as u64) << shift;
+        if b & 0x80 == 0 {
+            return Ok(v);
+        }
+    }
+    Err("varint overflow".to_string())
+}
+
+/// Reads the next field number and value from buf, or None at end of input.
+pub(super) fn next_field(buf: &mut Bytes) -> Result<Option<(u32, Field)>, String> {
+    if !buf.has_remaining() {
+        return Ok(None);
+    }
+    let tag = get_varint(buf)?;
+    let field = (tag >> 3) as u32;
+    let value = match (tag & 0x7) as u8 {
+        WIRE_VARINT => Field::Varint(get_varint(buf)?),
+        WIRE_FIXED64 => {
+            if buf.remaining() < 8 {
+                return Err("truncated fixed64".to_string());
+            }
+            Field::Fixed64(buf.get_u64_le())
+        }
+        WIRE_LEN => {
+            let len = get_varint(buf)? as usize;
+            if buf.remaining() < len {
+                return Err("truncated length-delimited field".to_string());
+            }
+            Field::Len(buf.split_to(len))
+        }
+        WIRE_FIXED32 => {
+            if buf.remaining() < 4 {
+                return Err("truncated fixed32".to_string());
+            }
+            Field::Fixed32(buf.get_u32_le())
+        }
+        t => return Err(format!("unsupported wire type {t}")),
+    };
+    Ok(Some((field, value)))
+}
+
+/// Decodes a map<string, double> entry.
+pub(super) fn get_map_entry(mut entry: Bytes) -> Result<(String, f64), String> {
+    let mut key = String::new();
+    let mut value = 0.0;
+    while let Some((field, v)) = next_field(&mut entry)? {
+        match field {
+            1 => key = v.as_string()?,
+            2 => value = v.as_double()?,
+            _ => {}
+        }
+    }
+    Ok((key, value))
+}
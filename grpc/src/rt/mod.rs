@@ -76,6 +76,20 @@ pub(super) trait DnsResolver: Send + Sync {
     /// Perform a TXT record lookup. If a txt record contains multiple strings,
     /// they are concatenated.
     async fn lookup_txt(&self, name: &str) -> Result<Vec<String>, String>;
+    /// Perform an SRV record lookup.
+    async fn lookup_srv(&self, name: &str) -> Result<Vec<SrvRecord>, String> {
+        Err("SRV record lookup unavailable.".to_string())
+    }
+}
+
+/// A DNS SRV record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SrvRecord {
+    pub(crate) priority: u16,
+    pub(crate) weight: u16,
+    pub(crate) port: u16,
+    /// The host name serving the service.
+    pub(crate) target: String,
 }
 
 #[derive(Default)]
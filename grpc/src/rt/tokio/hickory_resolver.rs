@@ -67,6 +67,23 @@ impl rt::DnsResolver for DnsResolver {
             .collect();
         Ok(response)
     }
+
+    async fn lookup_srv(&self, name: &str) -> Result<Vec<rt::SrvRecord>, String> {
+        let response = self
+            .resolver
+            .srv_lookup(name)
+            .await
+            .map_err(|err| err.to_string())?
+            .iter()
+            .map(|srv| rt::SrvRecord {
+                priority: srv.priority(),
+                weight: srv.weight(),
+                port: srv.port(),
+                target: srv.target().to_utf8(),
+            })
+            .collect();
+        Ok(response)
+    }
 }
 
 impl DnsResolver {
@@ -102,7 +119,7 @@ mod tests {
     use hickory_server::{
         authority::{Catalog, ZoneType},
         proto::rr::{
-            rdata::{A, TXT},
+            rdata::{A, SRV, TXT},
             LowerName, RData, Record,
         },
         store::in_memory::InMemoryAuthority,
@@ -110,7 +127,7 @@ mod tests {
     };
     use tokio::{net::UdpSocket, sync::oneshot, task::JoinHandle};
 
-    use crate::rt::{tokio::TokioDefaultDnsResolver, DnsResolver, ResolverOptions};
+    use crate::rt::{tokio::TokioDefaultDnsResolver, DnsResolver, ResolverOptions, SrvRecord};
 
     #[tokio::test]
     async fn compare_hickory_and_default() {
@@ -171,6 +188,39 @@ mod tests {
         dns.shutdown().await;
     }
 
+    #[tokio::test]
+    async fn resolve_srv() {
+        let record = Record::from_rdata(
+            Name::from_ascii("_grpclb._tcp.test.local.").unwrap(),
+            300,
+            RData::SRV(SRV::new(
+                10,
+                20,
+                1234,
+                Name::from_ascii("lb.test.local.").unwrap(),
+            )),
+        );
+        let dns = start_in_memory_dns_server("test.local.", vec![record]).await;
+        let opts = ResolverOptions {
+            server_addr: Some(dns.addr),
+        };
+        let hickory_dns = super::DnsResolver::new(opts).unwrap();
+        let srv = hickory_dns
+            .lookup_srv("_grpclb._tcp.test.local")
+            .await
+            .unwrap();
+        assert_eq!(
+            srv,
+            vec![SrvRecord {
+                priority: 10,
+                weight: 20,
+                port: 1234,
+                target: "lb.test.local.".to_string(),
+            }]
+        );
+        dns.shutdown().await;
+    }
+
     #[tokio::test]
     async fn custom_authority() {
         let record = Record::from_rdata(
@@ -166,6 +166,16 @@ impl AsyncWrite for TokioTcpStream {
 
 impl super::TcpStream for TokioTcpStream {}
 
+// Connections accepted by server listeners are served over HTTP/2 the same way
+// as the TCP connections made by clients.
+impl super::TcpStream for tokio::net::TcpStream {}
+
+#[cfg(unix)]
+impl super::TcpStream for tokio::net::UnixStream {}
+
+#[cfg(feature = "tls")]
+impl super::TcpStream for tokio_rustls::server::TlsStream<tokio::net::TcpStream> {}
+
 #[cfg(test)]
 mod tests {
     use super::{DnsResolver, ResolverOptions, Runtime, TokioDefaultDnsResolver, TokioRuntime};
@@ -0,0 +1,225 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! Serving the calls of HTTP/2 connections accepted by listeners.
+
+use std::convert::Infallible;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use hyper::body::Incoming;
+use hyper::server::conn::http2::Builder;
+use hyper::service::service_fn;
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::{Stream, StreamExt};
+use tonic::server::{Grpc, StreamingService};
+use tonic::{Status, Streaming};
+
+use super::Call;
+use crate::codec::{message_bytes, BytesCodec};
+use crate::compression::{framing, MessageCompression};
+use crate::rt::hyper_wrapper::{HyperCompatExec, HyperCompatTimer, HyperStream};
+use crate::rt::{Runtime, TcpStream};
+use crate::service::{Message, Request};
+
+type BoxStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+// How long listeners wait before accepting again after an error.  Errors such
+// as EMFILE persist until connections close, so retrying immediately would
+// spin.
+pub(super) const ACCEPT_ERROR_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Serves the calls made on an HTTP/2 connection, queueing them on calls.
+/// The extensions are added to each call's request, and messages are
+/// compressed and decompressed with the current compression settings.
+pub(super) async fn serve_connection(
+    stream: Box<dyn TcpStream>,
+    extensions: http::Extensions,
+    calls: mpsc::Sender<Call>,
+    compression: watch::Receiver<MessageCompression>,
+    runtime: Arc<dyn Runtime>,
+) {
+    let builder = Builder::new(HyperCompatExec {
+        inner: runtime.clone(),
+    })
+    .timer(HyperCompatTimer {
+        inner: runtime.clone(),
+    })
+    .clone();
+    let service = service_fn(move |request: http::Request<Incoming>| {
+        let compression = compression.borrow().clone();
+        let method = request.uri().path().to_string();
+        let (mut parts, body) = request.into_parts();
+        let peer_accept_encoding = framing::accept_encoding(&parts.headers);
+        let body = framing::decode(&compression, &mut parts.headers, body);
+        let request = http::Request::from_parts(parts, body);
+        let forwarder = CallForwarder {
+            method: method.clone(),
+            extensions: extensions.clone(),
+            calls: calls.clone(),
+            runtime: runtime.clone(),
+        };
+        async move {
+            let mut grpc = Grpc::new(BytesCodec {})
+                .max_decoding_message_size(compression.max_decoding_message_size);
+            let response = grpc.streaming(forwarder, request).await;
+            let (mut parts, body) = response.into_parts();
+            let body = framing::encode(
+                &compression,
+                &method,
+                &peer_accept_encoding,
+                &mut parts.headers,
+                body,
+            );
+            Ok::<_, Infallible>(http::Response::from_parts(parts, body))
+        }
+    });
+    let result = builder
+        .serve_connection(HyperStream::new(stream), service)
+        .await;
+    if let Err(err) = result {
+        eprintln!("warning: server connection failed: {err}");
+    }
+}
+
+// Queues the calls of a connection for the listener's accept.
+struct CallForwarder {
+    method: String,
+    extensions: http::Extensions,
+    calls: mpsc::Sender<Call>,
+    runtime: Arc<dyn Runtime>,
+}
+
+impl StreamingService<Bytes> for CallForwarder {
+    type Response = Bytes;
+    type ResponseStream = BoxStream<Bytes>;
+    type Future = BoxFuture<Result<tonic::Response<Self::ResponseStream>, Status>>;
+
+    fn call(&mut self, request: tonic::Request<Streaming<Bytes>>) -> Self::Future {
+        let (metadata, mut extensions, mut messages) = request.into_parts();
+        extensions.extend(self.extensions.clone());
+        // Requests must be Sync, which the decoded stream is not, so its
+        // messages are passed through a channel.
+        let (tx, rx) = mpsc::channel(1);
+        self.runtime.spawn(Box::pin(async move {
+            while let Some(Ok(msg)) = messages.next().await {
+                let msg: Box<dyn Message> = Box::new(msg);
+                if tx.send(msg).await.is_err() {
+                    break;
+                }
+            }
+        }));
+        let request = Request::from_parts(metadata, extensions, Box::pin(ReceiverStream::new(rx)));
+        let method = std::mem::take(&mut self.method);
+        let calls = self.calls.clone();
+        Box::pin(async move {
+            let (reply_tx, reply_rx) = oneshot::channel();
+            calls
+                .send((method, request, reply_tx))
+                .await
+                .map_err(|_| Status::unavailable("listener is closed"))?;
+            let response = reply_rx
+                .await
+                .map_err(|_| Status::unavailable("server stopped before handling the call"))?;
+            let (metadata, messages, extensions) = response.into_parts();
+            let messages: BoxStream<Bytes> = Box::pin(messages.filter_map(|msg| match msg {
+                Ok(msg) => message_bytes(msg).map(Ok),
+                Err(status) => Some(Err(status)),
+            }));
+            Ok(tonic::Response::from_parts(metadata, messages, extensions))
+        })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test_utils {
+    use std::sync::{Arc, Mutex};
+
+    use bytes::{Buf, Bytes, BytesMut};
+    use tokio::io::{AsyncRead, AsyncWrite};
+    use tokio_stream::StreamExt;
+    use tonic::async_trait;
+
+    use crate::codec::message_bytes;
+    use crate::service::{Message, Request, Response, Service};
+
+    // Echoes the messages of each call, recording the extensions of requests.
+    #[derive(Default)]
+    pub(crate) struct Echo {
+        pub(crate) requests: Mutex<Vec<http::Extensions>>,
+    }
+
+    #[async_trait]
+    impl Service for Arc<Echo> {
+        async fn call(&self, _method: String, request: Request) -> Response {
+            self.requests
+                .lock()
+                .unwrap()
+                .push(request.extensions().clone());
+            let messages = request.into_inner().map(|msg| {
+                let msg: Box<dyn Message> = Box::new(message_bytes(msg).unwrap());
+                Ok(msg)
+            });
+            Response::new(Box::pin(messages))
+        }
+    }
+
+    // Makes a unary call on stream, returning the response message and the
+    // grpc-status trailer.
+    pub(crate) async fn unary<S>(stream: S, message: &[u8]) -> (Bytes, String)
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let (mut client, conn) = h2::client::handshake(stream).await.unwrap();
+        tokio::spawn(conn);
+        let request = http::Request::post("http://localhost/svc/Echo")
+            .header("content-type", "application/grpc")
+            .header("te", "trailers")
+            .body(())
+            .unwrap();
+        let (response, mut send) = client.send_request(request, false).unwrap();
+        let mut frame = BytesMut::new();
+        frame.extend_from_slice(&[0]);
+        frame.extend_from_slice(&(message.len() as u32).to_be_bytes());
+        frame.extend_from_slice(message);
+        send.send_data(frame.freeze(), true).unwrap();
+
+        let mut body = response.await.unwrap().into_body();
+        let mut data = BytesMut::new();
+        while let Some(chunk) = body.data().await {
+            let chunk = chunk.unwrap();
+            body.flow_control().release_capacity(chunk.len()).unwrap();
+            data.extend_from_slice(&chunk);
+        }
+        let trailers = body.trailers().await.unwrap().unwrap();
+        let status = trailers["grpc-status"].to_str().unwrap().to_string();
+        data.advance(5);
+        (data.freeze(), status)
+    }
+}
@@ -0,0 +1,314 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! Draining of in-flight calls during graceful shutdown.
+//!
+//! When a server shuts down gracefully it stops accepting calls and applies
+//! the [`DrainPolicy`] of each call's method to the calls still in flight:
+//! short calls can be allowed to finish, while long-lived streams (e.g.
+//! watches) can be ended immediately with a status telling the client when to
+//! retry elsewhere.
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use tokio::sync::{oneshot, Notify};
+use tokio_stream::Stream;
+use tonic::{metadata::MetadataValue, Status};
+
+use crate::service::details::{Pushback, RETRY_PUSHBACK_KEY};
+use crate::service::{Message, Response};
+
+/// How a server handles a call which is still in flight when it shuts down
+/// gracefully.
+#[derive(Debug, Clone, Default)]
+pub enum DrainPolicy {
+    /// Lets the call run to completion.
+    #[default]
+    Finish,
+    /// Ends the call immediately with a status.
+    Cancel {
+        /// The status the call fails with.
+        status: Status,
+        /// The retry instruction sent to the client in the
+        /// `grpc-retry-pushback-ms` trailer, if any.
+        pushback: Option<Pushback>,
+    },
+}
+
+impl DrainPolicy {
+    /// Returns a policy which ends calls with `status`.
+    pub fn cancel(status: Status) -> Self {
+        DrainPolicy::Cancel {
+            status,
+            pushback: None,
+        }
+    }
+
+    /// Sets the retry instruction sent with the status of cancelled calls.
+    /// Has no effect on [`DrainPolicy::Finish`].
+    pub fn with_pushback(self, pushback: Pushback) -> Self {
+        match self {
+            DrainPolicy::Cancel { status, .. } => DrainPolicy::Cancel {
+                status,
+                pushback: Some(pushback),
+            },
+            finish => finish,
+        }
+    }
+
+    fn cancel_status(&self) -> Option<Status> {
+        let DrainPolicy::Cancel { status, pushback } = self else {
+            return None;
+        };
+        let mut status = status.clone();
+        if let Some(pushback) = pushback {
+            let value = match pushback {
+                Pushback::RetryAfter(delay) => {
+                    MetadataValue::from(delay.as_millis().min(i64::MAX as u128) as i64)
+                }
+                Pushback::DoNotRetry => MetadataValue::from(-1),
+            };
+            status.metadata_mut().insert(RETRY_PUSHBACK_KEY, value);
+        }
+        Some(status)
+    }
+}
+
+struct Call {
+    method: String,
+    policy: DrainPolicy,
+    cancel: Option<oneshot::Sender<Status>>,
+}
+
+#[derive(Default)]
+struct State {
+    next_id: u64,
+    calls: HashMap<u64, Call>,
+    draining: bool,
+}
+
+/// Tracks the calls a server is handling by method.
+#[derive(Default)]
+pub(crate) struct InFlightCalls {
+    state: Mutex<State>,
+    idle: Notify,
+}
+
+/// Represents a call in flight.  The call is removed from its InFlightCalls
+/// when dropped.
+pub(crate) struct InFlightCall {
+    id: u64,
+    calls: Arc<InFlightCalls>,
+    cancelled: oneshot::Receiver<Status>,
+}
+
+impl InFlightCalls {
+    /// Records the start of a call to method, which is drained according to
+    /// policy.
+    pub(crate) fn start(self: &Arc<Self>, method: &str, policy: DrainPolicy) -> InFlightCall {
+        let (tx, rx) = oneshot::channel();
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_id;
+        state.next_id += 1;
+        let mut call = Call {
+            method: method.to_string(),
+            policy,
+            cancel: Some(tx),
+        };
+        if state.draining {
+            call.cancel_if_needed();
+        }
+        state.calls.insert(id, call);
+        InFlightCall {
+            id,
+            calls: self.clone(),
+            cancelled: rx,
+        }
+    }
+
+    /// Returns the number of calls in flight for each method.
+    pub(crate) fn by_method(&self) -> HashMap<String, usize> {
+        let state = self.state.lock().unwrap();
+        let mut counts = HashMap::new();
+        for call in state.calls.values() {
+            *counts.entry(call.method.clone()).or_default() += 1;
+        }
+        counts
+    }
+
+    /// Applies the drain policy of every call in flight and waits until all
+    /// calls have completed.  Calls started afterwards are drained
+    /// immediately.
+    pub(crate) async fn drain(&self) {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.draining = true;
+            for call in state.calls.values_mut() {
+                call.cancel_if_needed();
+            }
+        }
+        loop {
+            let idle = self.idle.notified();
+            if self.state.lock().unwrap().calls.is_empty() {
+                return;
+            }
+            idle.await;
+        }
+    }
+
+    fn finish(&self, id: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.calls.remove(&id);
+        if state.calls.is_empty() {
+            self.idle.notify_waiters();
+        }
+    }
+}
+
+impl Call {
+    fn cancel_if_needed(&mut self) {
+        if let Some(status) = self.policy.cancel_status() {
+            if let Some(tx) = self.cancel.take() {
+                let _ = tx.send(status);
+            }
+        }
+    }
+}
+
+impl InFlightCall {
+    /// Ties the call to the message stream of response: the call completes
+    /// when the stream does, and the stream fails if the call is cancelled.
+    pub(crate) fn hold_until_complete(self, response: Response) -> Response {
+        response.map(|inner| {
+            Box::pin(DrainStream {
+                inner,
+                call: Some(self),
+            }) as Pin<Box<dyn Stream<Item = Result<Box<dyn Message>, Status>> + Send>>
+        })
+    }
+}
+
+impl Drop for InFlightCall {
+    fn drop(&mut self) {
+        self.calls.finish(self.id);
+    }
+}
+
+pin_project_lite::pin_project! {
+    struct DrainStream<S> {
+        #[pin]
+        inner: S,
+        call: Option<InFlightCall>,
+    }
+}
+
+impl<S: Stream<Item = Result<Box<dyn Message>, Status>>> Stream for DrainStream<S> {
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let Some(call) = this.call.as_mut() else {
+            return Poll::Ready(None);
+        };
+        if let Poll::Ready(Ok(status)) = Pin::new(&mut call.cancelled).poll(cx) {
+            this.call.take();
+            return Poll::Ready(Some(Err(status)));
+        }
+        let item = this.inner.poll_next(cx);
+        if let Poll::Ready(None) = item {
+            this.call.take();
+        }
+        item
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{sync::Arc, time::Duration};
+
+    use tokio_stream::StreamExt;
+    use tonic::{Code, Status};
+
+    use super::{DrainPolicy, InFlightCalls};
+    use crate::service::details::{pushback, Pushback};
+    use crate::service::Response;
+
+    fn pending_response() -> Response {
+        Response::new(Box::pin(tokio_stream::pending()))
+    }
+
+    #[tokio::test]
+    async fn drain_cancels_and_finishes_by_policy() {
+        let calls = Arc::new(InFlightCalls::default());
+        let watch = calls
+            .start(
+                "/svc/Watch",
+                DrainPolicy::cancel(Status::unavailable("shutting down"))
+                    .with_pushback(Pushback::RetryAfter(Duration::from_millis(250))),
+            )
+            .hold_until_complete(pending_response());
+        let unary = calls.start("/svc/Get", DrainPolicy::Finish);
+        assert_eq!(calls.by_method().get("/svc/Watch"), Some(&1));
+        assert_eq!(calls.by_method().get("/svc/Get"), Some(&1));
+
+        let drain = tokio::spawn({
+            let calls = calls.clone();
+            async move { calls.drain().await }
+        });
+
+        let status = watch.into_inner().next().await.unwrap().unwrap_err();
+        assert_eq!(status.code(), Code::Unavailable);
+        assert_eq!(
+            pushback(&status),
+            Some(Pushback::RetryAfter(Duration::from_millis(250)))
+        );
+        assert_eq!(calls.by_method().get("/svc/Watch"), None);
+
+        tokio::task::yield_now().await;
+        assert!(!drain.is_finished());
+        drop(unary);
+        drain.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn calls_started_while_draining_are_cancelled() {
+        let calls = Arc::new(InFlightCalls::default());
+        calls.drain().await;
+        let call = calls
+            .start(
+                "/svc/Watch",
+                DrainPolicy::cancel(Status::unavailable("draining"))
+                    .with_pushback(Pushback::DoNotRetry),
+            )
+            .hold_until_complete(pending_response());
+        let status = call.into_inner().next().await.unwrap().unwrap_err();
+        assert_eq!(pushback(&status), Some(Pushback::DoNotRetry));
+    }
+}
@@ -1,12 +1,52 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
-use tokio::sync::oneshot;
-use tonic::async_trait;
+use tokio::sync::{oneshot, watch};
+use tonic::{async_trait, Status};
 
-use crate::service::{Request, Response, Service};
+use crate::binlog::{BinaryLogger, Side};
+use crate::compression::MessageCompression;
+#[cfg(feature = "zstd")]
+use crate::compression::{
+    zstd_dictionary::DictionaryRegistry, CompressionPolicy, CompressionStats,
+};
+use crate::http2::Http2Options;
+use crate::orca::CallMetricsRecorder;
+use crate::service::{details, status_response, Request, Response, Service};
+use crate::stats::{RpcEvent, RpcInfo, RpcStats, StatsHandler};
+use crate::trace_context::TracePropagation;
+
+#[cfg(feature = "_runtime-tokio")]
+mod connection;
+mod drain;
+mod rate_limit;
+mod router;
+#[cfg(feature = "_runtime-tokio")]
+pub mod tcp;
+mod tonic_adapter;
+#[cfg(all(unix, feature = "_runtime-tokio"))]
+pub mod unix;
+
+pub use drain::DrainPolicy;
+use drain::InFlightCalls;
+use rate_limit::RateLimiter;
+pub use rate_limit::{RateLimit, RateLimitStats};
+pub use router::{CodecRoutes, Router};
+pub use tonic_adapter::TonicAdapter;
 
 pub struct Server {
     handler: Option<Arc<dyn Service>>,
+    compression: MessageCompression,
+    http2_options: Http2Options,
+    drain_policies: HashMap<String, DrainPolicy>,
+    default_drain_policy: DrainPolicy,
+    in_flight: Arc<InFlightCalls>,
+    rate_limiter: Arc<RateLimiter>,
+    shutdown: watch::Sender<bool>,
+    stats_handlers: Arc<[Arc<dyn StatsHandler>]>,
+    binary_logger: Option<Arc<BinaryLogger>>,
+    max_status_details_size: usize,
+    trace_propagation: Option<TracePropagation>,
 }
 
 pub type Call = (String, Request, oneshot::Sender<Response>);
@@ -14,23 +54,255 @@ pub type Call = (String, Request, oneshot::Sender<Response>);
 #[async_trait]
 pub trait Listener {
     async fn accept(&self) -> Option<Call>;
+
+    /// Called with the service handling the server's calls when the server
+    /// starts serving the listener, and with None when it stops.  In-process
+    /// listeners may call it directly instead of queueing calls for accept.
+    fn set_direct_handler(&self, _handler: Option<Arc<dyn Service>>) {}
+
+    /// Called with the compression settings of the server when it starts
+    /// serving the listener.  Listeners whose connections frame messages
+    /// apply them to their calls.
+    fn set_compression(&self, _compression: MessageCompression) {}
 }
 
 impl Server {
     pub fn new() -> Self {
-        Self { handler: None }
+        Self {
+            handler: None,
+            compression: MessageCompression::default(),
+            http2_options: Http2Options::default(),
+            drain_policies: HashMap::new(),
+            default_drain_policy: DrainPolicy::default(),
+            in_flight: Arc::default(),
+            rate_limiter: Arc::default(),
+            shutdown: watch::Sender::new(false),
+            stats_handlers: Arc::new([]),
+            binary_logger: None,
+            max_status_details_size: details::DEFAULT_MAX_STATUS_DETAILS_SIZE,
+            trace_propagation: None,
+        }
     }
 
     pub fn set_handler(&mut self, f: impl Service + 'static) {
         self.handler = Some(Arc::new(f))
     }
 
+    /// Sets the policy deciding which outgoing messages are compressed when an
+    /// encoding is negotiated.  By default every message is compressed.
+    #[cfg(feature = "zstd")]
+    pub fn set_compression_policy(&mut self, policy: CompressionPolicy) {
+        self.compression.policy = policy;
+    }
+
+    /// Sets the zstd dictionaries the server decompresses requests and
+    /// compresses responses with.  Their IDs are advertised to clients, and a
+    /// dictionary is only used for a response if the client advertised it.
+    #[cfg(feature = "zstd")]
+    pub fn set_zstd_dictionaries(&mut self, dictionaries: Arc<DictionaryRegistry>) {
+        self.compression.dictionaries = Some(dictionaries);
+    }
+
+    /// Returns the number of messages compressed, and skipped by the server's
+    /// compression policy.
+    #[cfg(feature = "zstd")]
+    pub fn compression_stats(&self) -> CompressionStats {
+        self.compression.policy.stats()
+    }
+
+    /// Sets the HTTP/2 flow control and frame settings of the server's
+    /// connections.
+    // TODO: apply the settings once the server has an HTTP/2 transport.
+    pub fn set_http2_options(&mut self, options: Http2Options) {
+        self.http2_options = options;
+    }
+
+    /// Returns the HTTP/2 settings of the server's connections.
+    pub fn http2_options(&self) -> &Http2Options {
+        &self.http2_options
+    }
+
+    /// Sets how calls to method (e.g. "/pkg.Service/Method") that are still in
+    /// flight are handled by [`graceful_shutdown`](Server::graceful_shutdown).
+    pub fn set_drain_policy(&mut self, method: impl Into<String>, policy: DrainPolicy) {
+        self.drain_policies.insert(method.into(), policy);
+    }
+
+    /// Sets the drain policy of methods without their own.  By default calls
+    /// are allowed to finish.
+    pub fn set_default_drain_policy(&mut self, policy: DrainPolicy) {
+        self.default_drain_policy = policy;
+    }
+
+    /// Limits the rate of calls to method (e.g. "/pkg.Service/Method").  Calls
+    /// over the limit fail with RESOURCE_EXHAUSTED.  Stats handlers observe
+    /// the outcome of each check as an [`RpcEvent::RateLimit`].
+    pub fn set_rate_limit(&mut self, method: impl Into<String>, limit: RateLimit) {
+        self.rate_limiter.set_limit(method.into(), limit);
+    }
+
+    /// Limits the rate of all calls to the server, in addition to the limits
+    /// of their methods.
+    pub fn set_global_rate_limit(&mut self, limit: RateLimit) {
+        self.rate_limiter.set_global_limit(limit);
+    }
+
+    /// Adds a handler notified of the events of every call served.
+    pub fn add_stats_handler(&mut self, handler: Arc<dyn StatsHandler>) {
+        self.stats_handlers = self
+            .stats_handlers
+            .iter()
+            .cloned()
+            .chain([handler])
+            .collect();
+    }
+
+    /// Sets the logger of the calls served to the methods matched by its
+    /// filter.
+    pub fn set_binary_logger(&mut self, logger: Arc<BinaryLogger>) {
+        self.binary_logger = Some(logger);
+    }
+
+    /// Sets the maximum size of the messages the server receives, before and
+    /// after decompression.  Calls sending larger messages fail with
+    /// RESOURCE_EXHAUSTED.  Defaults to
+    /// [`DEFAULT_MAX_DECODING_MESSAGE_SIZE`](crate::compression::DEFAULT_MAX_DECODING_MESSAGE_SIZE).
+    pub fn set_max_decoding_message_size(&mut self, size: usize) {
+        self.compression.max_decoding_message_size = size;
+    }
+
+    /// Sets the maximum size of the serialized details of the statuses sent
+    /// by the server.  Larger details are dropped.
+    pub fn set_max_status_details_size(&mut self, size: usize) {
+        self.max_status_details_size = size;
+    }
+
+    /// Extracts the trace context of each call from its metadata into the
+    /// extensions of its request, where handlers find it with
+    /// [`TraceContext::from_request`](crate::trace_context::TraceContext::from_request).
+    pub fn set_trace_propagation(&mut self, propagation: TracePropagation) {
+        self.trace_propagation = Some(propagation);
+    }
+
+    /// Returns the number of calls in flight for each method.
+    pub fn in_flight_calls(&self) -> HashMap<String, usize> {
+        self.in_flight.by_method()
+    }
+
+    /// Stops accepting calls, applies the drain policy of each call in flight
+    /// and waits until they have all completed.
+    pub async fn graceful_shutdown(&self) {
+        self.shutdown.send_replace(true);
+        self.in_flight.drain().await;
+    }
+
     pub async fn serve(&self, l: &impl Listener) {
-        while let Some((method, req, reply_on)) = l.accept().await {
-            reply_on
-                .send(self.handler.as_ref().unwrap().call(method, req).await)
-                .ok(); // TODO: log error
+        let mut shutdown = self.shutdown.subscribe();
+        let handler = Arc::new(CallHandler {
+            handler: self.handler.clone().unwrap(),
+            drain_policies: self.drain_policies.clone(),
+            default_drain_policy: self.default_drain_policy.clone(),
+            in_flight: self.in_flight.clone(),
+            rate_limiter: self.rate_limiter.clone(),
+            stats_handlers: self.stats_handlers.clone(),
+            binary_logger: self.binary_logger.clone(),
+            max_status_details_size: self.max_status_details_size,
+            trace_propagation: self.trace_propagation.clone(),
+            shutdown: shutdown.clone(),
+        });
+        l.set_direct_handler(Some(handler.clone()));
+        l.set_compression(self.compression.clone());
+        loop {
+            let (method, req, reply_on) = tokio::select! {
+                call = l.accept() => match call {
+                    Some(call) => call,
+                    None => break,
+                },
+                _ = shutdown.wait_for(|shutdown| *shutdown) => break,
+            };
+            reply_on.send(handler.call(method, req).await).ok(); // TODO: log error
         }
+        l.set_direct_handler(None);
+    }
+}
+
+// Handles the calls of a server, whether accepted from a listener or made
+// directly by an in-process client.
+struct CallHandler {
+    handler: Arc<dyn Service>,
+    drain_policies: HashMap<String, DrainPolicy>,
+    default_drain_policy: DrainPolicy,
+    in_flight: Arc<InFlightCalls>,
+    rate_limiter: Arc<RateLimiter>,
+    stats_handlers: Arc<[Arc<dyn StatsHandler>]>,
+    binary_logger: Option<Arc<BinaryLogger>>,
+    max_status_details_size: usize,
+    trace_propagation: Option<TracePropagation>,
+    shutdown: watch::Receiver<bool>,
+}
+
+#[async_trait]
+impl Service for CallHandler {
+    async fn call(&self, method: String, mut req: Request) -> Response {
+        if *self.shutdown.borrow() {
+            return status_response(Status::unavailable("server is shutting down"));
+        }
+        let policy = self
+            .drain_policies
+            .get(&method)
+            .unwrap_or(&self.default_drain_policy)
+            .clone();
+        let call = self.in_flight.start(&method, policy);
+        if let Some(propagation) = &self.trace_propagation {
+            propagation.extract(&mut req);
+        }
+        let stats = RpcStats::begin(
+            &self.stats_handlers,
+            RpcInfo {
+                method: method.clone(),
+                is_client: false,
+                attempt: 1,
+            },
+        );
+        let log = self
+            .binary_logger
+            .as_ref()
+            .and_then(|logger| logger.start(&method, Side::Server));
+        if let Some(log) = &log {
+            req = log.request(req);
+        }
+        if let Some(stats) = &stats {
+            req = stats.request(req, false);
+        }
+        let recorder = CallMetricsRecorder::default();
+        req.extensions_mut().insert(recorder.clone());
+        let admitted = match self.rate_limiter.check(&method) {
+            None => Ok(()),
+            Some(decision) => {
+                if let Some(stats) = &stats {
+                    stats.emit(&RpcEvent::RateLimit {
+                        admitted: decision.result.is_ok(),
+                        stats: decision.stats,
+                    });
+                }
+                decision.result
+            }
+        };
+        let res = match admitted {
+            Ok(()) => self.handler.call(method, req).await,
+            Err(status) => status_response(status),
+        };
+        let mut res = details::normalize_response_details(res, self.max_status_details_size);
+        if let Some(stats) = &stats {
+            res = stats.response(res, true);
+        }
+        if let Some(log) = &log {
+            res = log.response(res);
+        }
+        // Added last, as other layers treat any status in the stream as a
+        // failure.
+        let res = recorder.send_in_trailers(res);
+        call.hold_until_complete(res)
     }
 }
 
@@ -39,3 +311,194 @@ impl Default for Server {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use tokio_stream::StreamExt;
+    use tonic::{async_trait, Code, Status};
+
+    use super::{DrainPolicy, RateLimit, RateLimitStats, Server};
+    use crate::client::{Channel, ChannelOptions};
+    use crate::inmemory;
+    use crate::service::{details, Request, Response, Service};
+    use crate::stats::{RpcEvent, RpcInfo, StatsHandler};
+    use crate::trace_context::{TraceContext, TracePropagation};
+
+    struct Watcher {}
+
+    #[async_trait]
+    impl Service for Watcher {
+        async fn call(&self, method: String, request: Request) -> Response {
+            Response::new(Box::pin(tokio_stream::pending()))
+        }
+    }
+
+    #[tokio::test]
+    async fn graceful_shutdown_drains_calls() {
+        inmemory::reg();
+        let lis = inmemory::Listener::new();
+        let mut srv = Server::new();
+        srv.set_handler(Watcher {});
+        srv.set_drain_policy(
+            "/svc/Watch",
+            DrainPolicy::cancel(Status::unavailable("bye")),
+        );
+        let srv = Arc::new(srv);
+        let serve = tokio::spawn({
+            let srv = srv.clone();
+            let lis = lis.clone();
+            async move { srv.serve(&lis).await }
+        });
+
+        let chan = Channel::new(lis.target().as_str(), None, ChannelOptions::default());
+        let req = Request::new(Box::pin(tokio_stream::empty()));
+        let res = chan.call("/svc/Watch".to_string(), req).await;
+        assert_eq!(srv.in_flight_calls().get("/svc/Watch"), Some(&1));
+
+        let shutdown = tokio::spawn({
+            let srv = srv.clone();
+            async move { srv.graceful_shutdown().await }
+        });
+        let status = res.into_inner().next().await.unwrap().unwrap_err();
+        assert_eq!(status.code(), Code::Unavailable);
+        shutdown.await.unwrap();
+        serve.await.unwrap();
+        assert!(srv.in_flight_calls().is_empty());
+        lis.close().await;
+    }
+
+    struct Empty {}
+
+    #[async_trait]
+    impl Service for Empty {
+        async fn call(&self, method: String, request: Request) -> Response {
+            Response::new(Box::pin(tokio_stream::empty()))
+        }
+    }
+
+    // Records the method, outcome and stats of each rate limit check.
+    #[derive(Default)]
+    struct RateLimitRecorder {
+        checks: Mutex<Vec<(String, bool, RateLimitStats)>>,
+    }
+
+    impl StatsHandler for RateLimitRecorder {
+        fn handle_rpc(&self, info: &RpcInfo, event: &RpcEvent<'_>) {
+            if let RpcEvent::RateLimit { admitted, stats } = event {
+                self.checks
+                    .lock()
+                    .unwrap()
+                    .push((info.method.clone(), *admitted, *stats));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn rate_limited_calls_fail() {
+        inmemory::reg();
+        let lis = inmemory::Listener::new();
+        let recorder = Arc::new(RateLimitRecorder::default());
+        let mut srv = Server::new();
+        srv.set_handler(Empty {});
+        srv.add_stats_handler(recorder.clone());
+        srv.set_rate_limit("/svc/Limited", RateLimit::new(0.001, 1));
+        let srv = Arc::new(srv);
+        let serve = tokio::spawn({
+            let srv = srv.clone();
+            let lis = lis.clone();
+            async move { srv.serve(&lis).await }
+        });
+
+        let chan = Channel::new(lis.target().as_str(), None, ChannelOptions::default());
+        let call = |method: &'static str| {
+            let chan = &chan;
+            async move {
+                let req = Request::new(Box::pin(tokio_stream::empty()));
+                let res = chan.call(method.to_string(), req).await;
+                match res.into_inner().next().await {
+                    Some(Err(status)) => status.code(),
+                    _ => Code::Ok,
+                }
+            }
+        };
+        assert_eq!(call("/svc/Limited").await, Code::Ok);
+        assert_eq!(call("/svc/Limited").await, Code::ResourceExhausted);
+        assert_eq!(call("/svc/Other").await, Code::Ok);
+        let stats = |admitted, rejected| RateLimitStats { admitted, rejected };
+        assert_eq!(
+            *recorder.checks.lock().unwrap(),
+            [
+                ("/svc/Limited".to_string(), true, stats(1, 0)),
+                ("/svc/Limited".to_string(), false, stats(1, 1)),
+                ("/svc/Other".to_string(), true, stats(1, 0)),
+            ]
+        );
+
+        srv.graceful_shutdown().await;
+        serve.await.unwrap();
+        lis.close().await;
+    }
+
+    // Records the trace context of each call.
+    #[derive(Clone, Default)]
+    struct TraceRecorder {
+        contexts: Arc<Mutex<Vec<Option<TraceContext>>>>,
+    }
+
+    #[async_trait]
+    impl Service for TraceRecorder {
+        async fn call(&self, method: String, request: Request) -> Response {
+            self.contexts
+                .lock()
+                .unwrap()
+                .push(TraceContext::from_request(&request));
+            Response::new(Box::pin(tokio_stream::empty()))
+        }
+    }
+
+    #[tokio::test]
+    async fn trace_context_is_propagated() {
+        inmemory::reg();
+        let lis = inmemory::Listener::new();
+        let recorder = TraceRecorder::default();
+        let mut srv = Server::new();
+        srv.set_handler(recorder.clone());
+        srv.set_trace_propagation(TracePropagation::default());
+        let srv = Arc::new(srv);
+        let serve = tokio::spawn({
+            let srv = srv.clone();
+            let lis = lis.clone();
+            async move { srv.serve(&lis).await }
+        });
+
+        let current = TraceContext::new([1; 16], [2; 8], true).unwrap();
+        let explicit = TraceContext::new([3; 16], [4; 8], false).unwrap();
+        let options = ChannelOptions::default()
+            .trace_propagation(TracePropagation::default().current_context(move || Some(current)));
+        let chan = Channel::new(lis.target().as_str(), None, options);
+        let call = |context: Option<TraceContext>| {
+            let chan = &chan;
+            async move {
+                let mut req = Request::new(Box::pin(tokio_stream::empty()));
+                if let Some(context) = context {
+                    req.extensions_mut().insert(context);
+                }
+                let res = chan.call("/svc/Method".to_string(), req).await;
+                assert!(res.into_inner().next().await.is_none());
+            }
+        };
+        // Contexts attached to requests take precedence over the current one.
+        call(None).await;
+        call(Some(explicit)).await;
+        assert_eq!(
+            *recorder.contexts.lock().unwrap(),
+            [Some(current), Some(explicit)]
+        );
+
+        srv.graceful_shutdown().await;
+        serve.await.unwrap();
+        lis.close().await;
+    }
+}
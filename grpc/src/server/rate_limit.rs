@@ -0,0 +1,271 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! Load shedding with token buckets.
+//!
+//! A [`RateLimit`] can be applied to a single method, and one can be applied
+//! to every call of the server.  Calls arriving when their bucket is empty
+//! fail with RESOURCE_EXHAUSTED without reaching the handler, optionally with
+//! a [`RetryInfo`](crate::service::details::RetryInfo) detail telling the
+//! client when a token will be available.
+//!
+//! The outcome of each check is reported to the server's stats handlers as an
+//! [`RpcEvent::RateLimit`](crate::stats::RpcEvent::RateLimit), along with the
+//! counts of the calls checked so far.
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use tonic::{Code, Status};
+
+use crate::service::details::{ErrorDetails, StatusExt};
+
+/// The parameters of a token bucket.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    calls_per_second: f64,
+    burst: u32,
+    retry_info: bool,
+}
+
+impl RateLimit {
+    /// Admits calls at calls_per_second on average, and up to burst calls at
+    /// once.  The bucket starts full.
+    pub fn new(calls_per_second: f64, burst: u32) -> Self {
+        Self {
+            calls_per_second,
+            burst,
+            retry_info: false,
+        }
+    }
+
+    /// Whether rejected calls carry a RetryInfo detail with the time until the
+    /// bucket has a token again.  Disabled by default.
+    pub fn with_retry_info(self, retry_info: bool) -> Self {
+        Self { retry_info, ..self }
+    }
+}
+
+/// Counts of the calls checked against the rate limits of a method with its
+/// own limit, or of all the methods without one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RateLimitStats {
+    /// Calls passed on to the handler.
+    pub admitted: u64,
+    /// Calls failed with RESOURCE_EXHAUSTED.
+    pub rejected: u64,
+}
+
+struct TokenBucket {
+    limit: RateLimit,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(limit: RateLimit, now: Instant) -> Self {
+        Self {
+            limit,
+            tokens: limit.burst as f64,
+            last_refill: now,
+        }
+    }
+
+    // Takes a token, or returns how long until one is available.
+    fn take(&mut self, now: Instant) -> Result<(), Duration> {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * self.limit.calls_per_second)
+            .min(self.limit.burst as f64);
+        self.last_refill = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Ok(());
+        }
+        let wait = (1.0 - self.tokens) / self.limit.calls_per_second;
+        Err(Duration::try_from_secs_f64(wait).unwrap_or(Duration::MAX))
+    }
+
+    fn rejection(&self, scope: &str, wait: Duration) -> Status {
+        let message = format!("rate limit of {scope} exceeded");
+        if self.limit.retry_info {
+            let details = ErrorDetails::with_retry_info(Some(wait));
+            Status::with_error_details(Code::ResourceExhausted, message, details)
+        } else {
+            Status::resource_exhausted(message)
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    global: Option<TokenBucket>,
+    methods: HashMap<String, TokenBucket>,
+    // Only methods with a limit are counted separately, so that clients
+    // cannot grow the map by calling arbitrary methods.
+    stats: HashMap<String, RateLimitStats>,
+    other_stats: RateLimitStats,
+}
+
+/// The outcome of checking a call against the rate limits.
+pub(crate) struct Decision {
+    /// Ok if the call is admitted, or the status it must fail with.
+    pub(crate) result: Result<(), Status>,
+    /// The counts of the calls checked, including this one.
+    pub(crate) stats: RateLimitStats,
+}
+
+/// The rate limits of a server.
+#[derive(Default)]
+pub(crate) struct RateLimiter {
+    state: Mutex<State>,
+}
+
+impl RateLimiter {
+    pub(crate) fn set_limit(&self, method: String, limit: RateLimit) {
+        let bucket = TokenBucket::new(limit, Instant::now());
+        let mut state = self.state.lock().unwrap();
+        state.stats.entry(method.clone()).or_default();
+        state.methods.insert(method, bucket);
+    }
+
+    pub(crate) fn set_global_limit(&self, limit: RateLimit) {
+        self.state.lock().unwrap().global = Some(TokenBucket::new(limit, Instant::now()));
+    }
+
+    /// Decides whether a call to method is admitted, or returns None if no
+    /// limits are set.  The limit of the method is checked before the global
+    /// limit, so a call rejected by the global limit still uses a token of
+    /// its method.
+    pub(crate) fn check(&self, method: &str) -> Option<Decision> {
+        self.check_at(method, Instant::now())
+    }
+
+    fn check_at(&self, method: &str, now: Instant) -> Option<Decision> {
+        let mut guard = self.state.lock().unwrap();
+        let state = &mut *guard;
+        if state.global.is_none() && state.methods.is_empty() {
+            return None;
+        }
+        let mut result = Ok(());
+        if let Some(bucket) = state.methods.get_mut(method) {
+            result = bucket
+                .take(now)
+                .map_err(|wait| bucket.rejection(method, wait));
+        }
+        if result.is_ok() {
+            if let Some(bucket) = &mut state.global {
+                result = bucket
+                    .take(now)
+                    .map_err(|wait| bucket.rejection("the server", wait));
+            }
+        }
+        let stats = state
+            .stats
+            .get_mut(method)
+            .unwrap_or(&mut state.other_stats);
+        match result {
+            Ok(()) => stats.admitted += 1,
+            Err(_) => stats.rejected += 1,
+        }
+        Some(Decision {
+            result,
+            stats: *stats,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use tonic::{Code, Status};
+
+    use super::{RateLimit, RateLimitStats, RateLimiter};
+    use crate::service::details::StatusExt;
+
+    fn check(limiter: &RateLimiter, method: &str, now: Instant) -> Result<(), Status> {
+        limiter.check_at(method, now).unwrap().result
+    }
+
+    #[test]
+    fn buckets_refill_over_time() {
+        let limiter = RateLimiter::default();
+        assert!(limiter.check_at("/svc/a", Instant::now()).is_none());
+        limiter.set_limit("/svc/a".to_string(), RateLimit::new(10.0, 2));
+        let now = Instant::now();
+        assert!(check(&limiter, "/svc/a", now).is_ok());
+        assert!(check(&limiter, "/svc/a", now).is_ok());
+        let status = check(&limiter, "/svc/a", now).unwrap_err();
+        assert_eq!(status.code(), Code::ResourceExhausted);
+        assert!(status.get_details_retry_info().is_none());
+
+        // A token is added every 100ms.
+        let later = now + Duration::from_millis(100);
+        assert!(check(&limiter, "/svc/a", later).is_ok());
+        let decision = limiter.check_at("/svc/a", later).unwrap();
+        assert!(decision.result.is_err());
+        assert_eq!(
+            decision.stats,
+            RateLimitStats {
+                admitted: 3,
+                rejected: 2
+            }
+        );
+
+        // Other methods are not limited.
+        assert!(check(&limiter, "/svc/b", now).is_ok());
+    }
+
+    #[test]
+    fn methods_without_limits_share_stats() {
+        let limiter = RateLimiter::default();
+        limiter.set_limit("/svc/a".to_string(), RateLimit::new(1.0, 1));
+        let now = Instant::now();
+        for i in 0..100 {
+            check(&limiter, &format!("/svc/other{i}"), now).unwrap();
+        }
+        let decision = limiter.check_at("/svc/b", now).unwrap();
+        assert_eq!(decision.stats.admitted, 101);
+        assert_eq!(limiter.state.lock().unwrap().stats.len(), 1);
+    }
+
+    #[test]
+    fn global_limit_applies_to_every_method() {
+        let limiter = RateLimiter::default();
+        limiter.set_global_limit(RateLimit::new(1.0, 1).with_retry_info(true));
+        let now = Instant::now();
+        assert!(check(&limiter, "/svc/a", now).is_ok());
+        let decision = limiter.check_at("/svc/b", now).unwrap();
+        let retry_info = decision
+            .result
+            .unwrap_err()
+            .get_details_retry_info()
+            .unwrap();
+        assert_eq!(retry_info.retry_delay, Some(Duration::from_secs(1)));
+        assert_eq!(decision.stats.rejected, 1);
+    }
+}
@@ -0,0 +1,524 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! Serving methods with typed handlers, without generated code.
+//!
+//! A [`Router`] dispatches each call to the handler registered for its method
+//! and fails calls to other methods with UNIMPLEMENTED.  Handlers receive and
+//! return typed messages.  By default the messages are passed as they are, as
+//! by in-process channels and the typed helpers of
+//! [`Channel`](crate::client::Channel); handlers registered through
+//! [`Router::codec`] instead decode and encode the messages with a
+//! [`Codec`], as required by transports which exchange encoded messages.
+//!
+//! ```ignore
+//! let mut router = Router::new();
+//! router.unary("/pkg.Svc/Method", |req: Req| async move { Ok(Res::from(req)) });
+//! server.set_handler(router);
+//! ```
+
+use std::{
+    any::Any, collections::HashMap, future::Future, marker::PhantomData, pin::Pin, sync::Arc,
+};
+
+use tokio_stream::{Stream, StreamExt};
+use tonic::{async_trait, Status};
+
+use crate::codegen::{unimplemented, BoxStream, Codec};
+use crate::service::{Message, Request, Response, Service};
+
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+type Handler = Arc<dyn Fn(Request) -> BoxFuture<Response> + Send + Sync>;
+
+/// A service dispatching calls to the handlers registered for their methods.
+#[derive(Clone, Default)]
+pub struct Router {
+    handlers: HashMap<String, Handler>,
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a registrar for handlers whose messages are encoded with
+    /// codec.
+    pub fn codec<C: Codec>(&mut self, codec: C) -> CodecRoutes<'_, C> {
+        CodecRoutes {
+            router: self,
+            codec,
+        }
+    }
+
+    /// Serves method (e.g. "/pkg.Service/Method") with f, which responds to
+    /// the single request message with a single message.
+    ///
+    /// # Panics
+    ///
+    /// Panics if method already has a handler, as do the other registration
+    /// methods.
+    pub fn unary<Req, Res, F, Fut>(&mut self, method: impl Into<String>, f: F) -> &mut Self
+    where
+        Req: Message,
+        Res: Message,
+        F: Fn(Req) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Res, Status>> + Send + 'static,
+    {
+        self.add_unary(method.into(), Typed::default(), f)
+    }
+
+    /// Serves method with f, which responds to the single request message
+    /// with a stream of messages.
+    pub fn server_streaming<Req, Res, S, F, Fut>(
+        &mut self,
+        method: impl Into<String>,
+        f: F,
+    ) -> &mut Self
+    where
+        Req: Message,
+        Res: Message,
+        S: Stream<Item = Result<Res, Status>> + Send + 'static,
+        F: Fn(Req) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<S, Status>> + Send + 'static,
+    {
+        self.add_server_streaming(method.into(), Typed::default(), f)
+    }
+
+    /// Serves method with f, which responds to a stream of request messages
+    /// with a single message.
+    pub fn client_streaming<Req, Res, F, Fut>(
+        &mut self,
+        method: impl Into<String>,
+        f: F,
+    ) -> &mut Self
+    where
+        Req: Message,
+        Res: Message,
+        F: Fn(BoxStream<Req>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Res, Status>> + Send + 'static,
+    {
+        self.add_client_streaming(method.into(), Typed::default(), f)
+    }
+
+    /// Serves method with f, which responds to a stream of request messages
+    /// with a stream of messages.
+    pub fn bidi<Req, Res, S, F, Fut>(&mut self, method: impl Into<String>, f: F) -> &mut Self
+    where
+        Req: Message,
+        Res: Message,
+        S: Stream<Item = Result<Res, Status>> + Send + 'static,
+        F: Fn(BoxStream<Req>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<S, Status>> + Send + 'static,
+    {
+        self.add_bidi(method.into(), Typed::default(), f)
+    }
+
+    fn add(&mut self, method: String, handler: Handler) -> &mut Self {
+        if self.handlers.insert(method.clone(), handler).is_some() {
+            panic!("method {method} already has a handler");
+        }
+        self
+    }
+
+    fn add_unary<Req, Res, M, F, Fut>(&mut self, method: String, messages: M, f: F) -> &mut Self
+    where
+        Req: Send + 'static,
+        Res: Send + 'static,
+        M: Messages<Req, Res>,
+        F: Fn(Req) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Res, Status>> + Send + 'static,
+    {
+        let f = Arc::new(f);
+        self.add(
+            method,
+            Arc::new(move |request| {
+                let (f, messages) = (f.clone(), messages.clone());
+                Box::pin(async move {
+                    let result = match single_request(request, &messages).await {
+                        Ok(req) => f(req).await,
+                        Err(status) => Err(status),
+                    };
+                    messages.response(result.map(|res| tokio_stream::once(Ok(res))))
+                })
+            }),
+        )
+    }
+
+    fn add_server_streaming<Req, Res, M, S, F, Fut>(
+        &mut self,
+        method: String,
+        messages: M,
+        f: F,
+    ) -> &mut Self
+    where
+        Req: Send + 'static,
+        Res: Send + 'static,
+        M: Messages<Req, Res>,
+        S: Stream<Item = Result<Res, Status>> + Send + 'static,
+        F: Fn(Req) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<S, Status>> + Send + 'static,
+    {
+        let f = Arc::new(f);
+        self.add(
+            method,
+            Arc::new(move |request| {
+                let (f, messages) = (f.clone(), messages.clone());
+                Box::pin(async move {
+                    let result = match single_request(request, &messages).await {
+                        Ok(req) => f(req).await,
+                        Err(status) => Err(status),
+                    };
+                    messages.response(result)
+                })
+            }),
+        )
+    }
+
+    fn add_client_streaming<Req, Res, M, F, Fut>(
+        &mut self,
+        method: String,
+        messages: M,
+        f: F,
+    ) -> &mut Self
+    where
+        Req: Send + 'static,
+        Res: Send + 'static,
+        M: Messages<Req, Res>,
+        F: Fn(BoxStream<Req>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Res, Status>> + Send + 'static,
+    {
+        let f = Arc::new(f);
+        self.add(
+            method,
+            Arc::new(move |request| {
+                let (f, messages) = (f.clone(), messages.clone());
+                Box::pin(async move {
+                    let result = f(messages.requests(request)).await;
+                    messages.response(result.map(|res| tokio_stream::once(Ok(res))))
+                })
+            }),
+        )
+    }
+
+    fn add_bidi<Req, Res, M, S, F, Fut>(&mut self, method: String, messages: M, f: F) -> &mut Self
+    where
+        Req: Send + 'static,
+        Res: Send + 'static,
+        M: Messages<Req, Res>,
+        S: Stream<Item = Result<Res, Status>> + Send + 'static,
+        F: Fn(BoxStream<Req>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<S, Status>> + Send + 'static,
+    {
+        let f = Arc::new(f);
+        self.add(
+            method,
+            Arc::new(move |request| {
+                let (f, messages) = (f.clone(), messages.clone());
+                Box::pin(async move {
+                    let result = f(messages.requests(request)).await;
+                    messages.response(result)
+                })
+            }),
+        )
+    }
+}
+
+#[async_trait]
+impl Service for Router {
+    async fn call(&self, method: String, request: Request) -> Response {
+        match self.handlers.get(&method) {
+            Some(handler) => handler(request).await,
+            None => unimplemented(&method),
+        }
+    }
+}
+
+/// Registers the handlers of a [`Router`] whose messages are encoded with a
+/// codec.
+pub struct CodecRoutes<'a, C> {
+    router: &'a mut Router,
+    codec: C,
+}
+
+impl<C: Codec> CodecRoutes<'_, C> {
+    /// Like [`Router::unary`].
+    pub fn unary<F, Fut>(&mut self, method: impl Into<String>, f: F) -> &mut Self
+    where
+        F: Fn(C::Decode) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<C::Encode, Status>> + Send + 'static,
+    {
+        self.router
+            .add_unary(method.into(), Encoded(self.codec.clone()), f);
+        self
+    }
+
+    /// Like [`Router::server_streaming`].
+    pub fn server_streaming<S, F, Fut>(&mut self, method: impl Into<String>, f: F) -> &mut Self
+    where
+        S: Stream<Item = Result<C::Encode, Status>> + Send + 'static,
+        F: Fn(C::Decode) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<S, Status>> + Send + 'static,
+    {
+        self.router
+            .add_server_streaming(method.into(), Encoded(self.codec.clone()), f);
+        self
+    }
+
+    /// Like [`Router::client_streaming`].
+    pub fn client_streaming<F, Fut>(&mut self, method: impl Into<String>, f: F) -> &mut Self
+    where
+        F: Fn(BoxStream<C::Decode>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<C::Encode, Status>> + Send + 'static,
+    {
+        self.router
+            .add_client_streaming(method.into(), Encoded(self.codec.clone()), f);
+        self
+    }
+
+    /// Like [`Router::bidi`].
+    pub fn bidi<S, F, Fut>(&mut self, method: impl Into<String>, f: F) -> &mut Self
+    where
+        S: Stream<Item = Result<C::Encode, Status>> + Send + 'static,
+        F: Fn(BoxStream<C::Decode>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<S, Status>> + Send + 'static,
+    {
+        self.router
+            .add_bidi(method.into(), Encoded(self.codec.clone()), f);
+        self
+    }
+}
+
+// Converts between the messages of calls and those of handlers.
+trait Messages<Req: Send + 'static, Res: Send + 'static>: Clone + Send + Sync + 'static {
+    fn decode(&self, msg: Box<dyn Message>) -> Result<Req, Status>;
+
+    fn encode(&self, msg: Res) -> Box<dyn Message>;
+
+    fn requests(&self, request: Request) -> BoxStream<Req> {
+        let messages = self.clone();
+        Box::pin(request.into_inner().map(move |msg| messages.decode(msg)))
+    }
+
+    fn response<S>(&self, result: Result<S, Status>) -> Response
+    where
+        S: Stream<Item = Result<Res, Status>> + Send + 'static,
+    {
+        let messages = self.clone();
+        match result {
+            Ok(stream) => Response::new(Box::pin(
+                stream.map(move |msg| msg.map(|msg| messages.encode(msg))),
+            )),
+            Err(status) => crate::service::status_response(status),
+        }
+    }
+}
+
+// Passes messages as they are.
+struct Typed<Req, Res>(PhantomData<fn(Req) -> Res>);
+
+impl<Req, Res> Default for Typed<Req, Res> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<Req, Res> Clone for Typed<Req, Res> {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl<Req: Message, Res: Message> Messages<Req, Res> for Typed<Req, Res> {
+    fn decode(&self, msg: Box<dyn Message>) -> Result<Req, Status> {
+        (msg as Box<dyn Any>)
+            .downcast::<Req>()
+            .map(|msg| *msg)
+            .map_err(|_| {
+                Status::internal(format!(
+                    "request message is not a {}",
+                    std::any::type_name::<Req>()
+                ))
+            })
+    }
+
+    fn encode(&self, msg: Res) -> Box<dyn Message> {
+        Box::new(msg)
+    }
+}
+
+// Decodes and encodes messages with a codec.
+#[derive(Clone)]
+struct Encoded<C>(C);
+
+impl<C: Codec> Messages<C::Decode, C::Encode> for Encoded<C> {
+    fn decode(&self, msg: Box<dyn Message>) -> Result<C::Decode, Status> {
+        let bytes = (msg as Box<dyn Any>)
+            .downcast::<bytes::Bytes>()
+            .map_err(|_| Status::internal("message is not encoded"))?;
+        self.0.decode(*bytes)
+    }
+
+    fn encode(&self, msg: C::Encode) -> Box<dyn Message> {
+        Box::new(self.0.encode(&msg))
+    }
+}
+
+// Returns the single message of request.
+async fn single_request<Req: Send + 'static, Res: Send + 'static>(
+    request: Request,
+    messages: &impl Messages<Req, Res>,
+) -> Result<Req, Status> {
+    let mut stream = request.into_inner();
+    let msg = stream
+        .next()
+        .await
+        .ok_or_else(|| Status::internal("missing request message"))?;
+    messages.decode(msg)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{any::Any, sync::Arc};
+
+    use bytes::Bytes;
+    use tokio_stream::StreamExt;
+    use tonic::{Code, Status};
+
+    use super::Router;
+    use crate::client::{call::CallOptions, Channel, ChannelOptions};
+    use crate::codegen::Codec;
+    use crate::inmemory;
+    use crate::server::Server;
+    use crate::service::{Message, Request, Service};
+
+    #[tokio::test]
+    async fn typed_handlers() {
+        let mut router = Router::new();
+        router
+            .unary("/svc/Double", |n: u32| async move { Ok(2 * n) })
+            .server_streaming("/svc/Count", |n: u32| async move {
+                Ok(tokio_stream::iter((0..n).map(Ok)))
+            })
+            .client_streaming("/svc/Sum", |stream| async move {
+                stream
+                    .collect::<Result<Vec<u32>, Status>>()
+                    .await
+                    .map(|v| v.iter().sum::<u32>())
+            })
+            .bidi("/svc/Echo", |stream: super::BoxStream<String>| async move {
+                Ok(stream)
+            });
+
+        inmemory::reg();
+        let lis = inmemory::Listener::new();
+        let mut srv = Server::new();
+        srv.set_handler(router);
+        let srv = Arc::new(srv);
+        let serve = tokio::spawn({
+            let srv = srv.clone();
+            let lis = lis.clone();
+            async move { srv.serve(&lis).await }
+        });
+        let chan = Channel::new(lis.target().as_str(), None, ChannelOptions::default());
+        let options = CallOptions::default;
+
+        let res: u32 = chan.unary("/svc/Double", 21u32, options()).await.unwrap();
+        assert_eq!(res, 42);
+        let got: Vec<u32> = chan
+            .server_streaming("/svc/Count", 3u32, options())
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(got, [0, 1, 2]);
+        let call = chan.client_streaming::<u32, u32>("/svc/Sum", options());
+        for i in 1..=4 {
+            call.send(i).await.unwrap();
+        }
+        assert_eq!(call.finish().await.unwrap(), 10);
+        let (sink, mut stream) = chan.bidi_streaming::<String, String>("/svc/Echo", options());
+        sink.send("hi".to_string()).await.unwrap();
+        assert_eq!(stream.next().await.unwrap().unwrap(), "hi");
+
+        // Requests of the wrong type and unknown methods fail.
+        let status = chan
+            .unary::<_, u32>("/svc/Double", "21", options())
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::Internal);
+        let status = chan
+            .unary::<_, u32>("/svc/Unknown", 21u32, options())
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::Unimplemented);
+
+        drop((sink, stream));
+        srv.graceful_shutdown().await;
+        serve.await.unwrap();
+        lis.close().await;
+    }
+
+    #[derive(Clone, Default)]
+    struct StringCodec;
+
+    impl Codec for StringCodec {
+        type Encode = String;
+        type Decode = String;
+
+        fn encode(&self, item: &String) -> Bytes {
+            Bytes::from(item.clone())
+        }
+
+        fn decode(&self, buf: Bytes) -> Result<String, Status> {
+            String::from_utf8(buf.to_vec()).map_err(|err| Status::internal(err.to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn codec_handlers() {
+        let mut router = Router::new();
+        router
+            .codec(StringCodec)
+            .unary("/svc/Upper", |s| async move { Ok(s.to_uppercase()) });
+        let request = Request::new(Box::pin(tokio_stream::once(
+            Box::new(Bytes::from("hello")) as Box<dyn Message>
+        )));
+        let mut response = router
+            .call("/svc/Upper".to_string(), request)
+            .await
+            .into_inner();
+        let msg = (response.next().await.unwrap().unwrap() as Box<dyn Any>)
+            .downcast::<Bytes>()
+            .unwrap();
+        assert_eq!(*msg, "HELLO");
+        assert!(response.next().await.is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "already has a handler")]
+    fn duplicate_methods_panic() {
+        let mut router = Router::new();
+        router.unary("/svc/M", |n: u32| async move { Ok(n) });
+        router.unary("/svc/M", |n: u32| async move { Ok(n) });
+    }
+}
@@ -0,0 +1,524 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! A [`Listener`] accepting calls on TCP connections.
+//!
+//! With the `tls` feature, connections may be secured with TLS.  The details
+//! of each connection's handshake are then attached to its requests as
+//! [`TlsInfo`].
+//!
+//! Besides binding its own socket, a listener may adopt one bound elsewhere,
+//! e.g. inherited through systemd socket activation, with
+//! [`TcpListener::from_std`].
+
+use std::future::Future;
+use std::io;
+use std::net::{SocketAddr, TcpListener as StdTcpListener};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use socket2::{Domain, Protocol, Socket, Type};
+use tokio::net::{TcpListener as TokioTcpListener, TcpStream};
+use tokio::sync::{mpsc, watch, Mutex};
+use tonic::async_trait;
+
+use super::connection::{serve_connection, ACCEPT_ERROR_BACKOFF};
+use super::{Call, Listener};
+use crate::compression::MessageCompression;
+use crate::rt::{default_runtime, BoxedTaskHandle, Runtime};
+#[cfg(feature = "tls")]
+use crate::service::Request;
+
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+/// The time allowed for a client to complete the TLS handshake by default.
+#[cfg(feature = "tls")]
+pub const DEFAULT_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Options for terminating TLS on the connections of a [`TcpListener`].
+#[cfg(feature = "tls")]
+#[derive(Clone)]
+#[non_exhaustive]
+pub struct TlsOptions {
+    /// The configuration of the server's side of the handshake.  Client
+    /// certificates are requested and verified by its client certificate
+    /// verifier, e.g. `rustls::server::WebPkiClientVerifier`.
+    pub config: Arc<rustls::ServerConfig>,
+    /// Connections which have not completed the handshake within this time are
+    /// closed.
+    pub handshake_timeout: Duration,
+}
+
+#[cfg(feature = "tls")]
+impl TlsOptions {
+    /// Creates options for config, whose ALPN protocols are replaced by `h2`.
+    pub fn new(mut config: rustls::ServerConfig) -> Self {
+        config.alpn_protocols = vec![b"h2".to_vec()];
+        Self {
+            config: Arc::new(config),
+            handshake_timeout: DEFAULT_HANDSHAKE_TIMEOUT,
+        }
+    }
+
+    pub fn handshake_timeout(self, handshake_timeout: Duration) -> Self {
+        Self {
+            handshake_timeout,
+            ..self
+        }
+    }
+}
+
+/// The outcome of the TLS handshake of the connection a call was made on.
+#[cfg(feature = "tls")]
+#[derive(Clone, Debug)]
+pub struct TlsInfo {
+    negotiated_protocol: Option<Vec<u8>>,
+    peer_certificates: Arc<[rustls::pki_types::CertificateDer<'static>]>,
+}
+
+#[cfg(feature = "tls")]
+impl TlsInfo {
+    /// Returns the handshake details attached to request, if it was made on a
+    /// TLS connection accepted by a [`TcpListener`].
+    pub fn from_request(request: &Request) -> Option<&Self> {
+        request.extensions().get::<Self>()
+    }
+
+    /// The protocol negotiated using ALPN, if the client offered any.
+    pub fn negotiated_protocol(&self) -> Option<&[u8]> {
+        self.negotiated_protocol.as_deref()
+    }
+
+    /// The certificate chain presented by the client, starting with its own
+    /// certificate.  Empty if the client did not present one.
+    pub fn peer_certificates(&self) -> &[rustls::pki_types::CertificateDer<'static>] {
+        &self.peer_certificates
+    }
+}
+
+/// Options for binding the socket of a [`TcpListener`].
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct BindOptions {
+    /// Whether SO_REUSEPORT is set on the socket, allowing several listeners,
+    /// possibly in different processes, to bind the same address and share
+    /// its connections.  Only supported on Unix.
+    pub reuse_port: bool,
+}
+
+impl BindOptions {
+    pub fn reuse_port(self, reuse_port: bool) -> Self {
+        Self { reuse_port }
+    }
+
+    fn bind(&self, addr: SocketAddr) -> io::Result<StdTcpListener> {
+        if !self.reuse_port {
+            return StdTcpListener::bind(addr);
+        }
+        if cfg!(not(unix)) {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "SO_REUSEPORT is only supported on Unix",
+            ));
+        }
+        let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+        #[cfg(unix)]
+        socket.set_reuse_port(true)?;
+        socket.bind(&addr.into())?;
+        socket.listen(1024)?;
+        Ok(socket.into())
+    }
+}
+
+#[derive(Clone)]
+enum Security {
+    Insecure,
+    #[cfg(feature = "tls")]
+    Tls(TlsOptions),
+}
+
+/// A listener accepting calls made on TCP connections.
+///
+/// Connections are accepted as soon as the listener is bound; their calls are
+/// queued until they are accepted by a [`Server`](super::Server).
+pub struct TcpListener {
+    calls: Mutex<mpsc::Receiver<Call>>,
+    compression: watch::Sender<MessageCompression>,
+    accept_task: BoxedTaskHandle,
+    local_addr: SocketAddr,
+}
+
+impl TcpListener {
+    /// Binds a listener accepting plaintext connections to addr.  Must be
+    /// called within a tokio runtime.
+    pub fn bind(addr: SocketAddr) -> io::Result<Self> {
+        Self::bind_with_options(addr, BindOptions::default())
+    }
+
+    /// Like [`bind`](Self::bind), binding the socket with options.
+    pub fn bind_with_options(addr: SocketAddr, options: BindOptions) -> io::Result<Self> {
+        Self::start(options.bind(addr)?, Security::Insecure)
+    }
+
+    /// Binds a listener accepting TLS connections to addr.  Must be called
+    /// within a tokio runtime.
+    #[cfg(feature = "tls")]
+    pub fn bind_tls(addr: SocketAddr, options: TlsOptions) -> io::Result<Self> {
+        Self::bind_tls_with_options(addr, BindOptions::default(), options)
+    }
+
+    /// Like [`bind_tls`](Self::bind_tls), binding the socket with
+    /// bind_options.
+    #[cfg(feature = "tls")]
+    pub fn bind_tls_with_options(
+        addr: SocketAddr,
+        bind_options: BindOptions,
+        options: TlsOptions,
+    ) -> io::Result<Self> {
+        Self::start(bind_options.bind(addr)?, Security::Tls(options))
+    }
+
+    /// Creates a listener accepting plaintext connections on listener, which
+    /// must already be bound and listening.  Must be called within a tokio
+    /// runtime.
+    pub fn from_std(listener: StdTcpListener) -> io::Result<Self> {
+        Self::start(listener, Security::Insecure)
+    }
+
+    /// Creates a listener accepting TLS connections on listener, which must
+    /// already be bound and listening.  Must be called within a tokio
+    /// runtime.
+    #[cfg(feature = "tls")]
+    pub fn from_std_tls(listener: StdTcpListener, options: TlsOptions) -> io::Result<Self> {
+        Self::start(listener, Security::Tls(options))
+    }
+
+    /// Returns the address the listener is bound to, e.g. to find the port
+    /// picked when binding to port 0.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    fn start(listener: StdTcpListener, security: Security) -> io::Result<Self> {
+        listener.set_nonblocking(true)?;
+        let listener = TokioTcpListener::from_std(listener)?;
+        let local_addr = listener.local_addr()?;
+        let (tx, rx) = mpsc::channel(1);
+        let compression = watch::Sender::default();
+        let runtime = default_runtime();
+        let accept_task = runtime.spawn(Box::pin(accept_loop(
+            listener,
+            security,
+            tx,
+            compression.subscribe(),
+            runtime.clone(),
+        )));
+        Ok(Self {
+            calls: Mutex::new(rx),
+            compression,
+            accept_task,
+            local_addr,
+        })
+    }
+}
+
+impl Drop for TcpListener {
+    fn drop(&mut self) {
+        self.accept_task.abort();
+    }
+}
+
+#[async_trait]
+impl Listener for TcpListener {
+    async fn accept(&self) -> Option<Call> {
+        self.calls.lock().await.recv().await
+    }
+
+    fn set_compression(&self, compression: MessageCompression) {
+        self.compression.send_replace(compression);
+    }
+}
+
+async fn accept_loop(
+    listener: TokioTcpListener,
+    security: Security,
+    calls: mpsc::Sender<Call>,
+    compression: watch::Receiver<MessageCompression>,
+    runtime: Arc<dyn Runtime>,
+) {
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(err) => {
+                tracing::warn!("failed to accept a TCP connection: {err}");
+                runtime.sleep(ACCEPT_ERROR_BACKOFF).await;
+                continue;
+            }
+        };
+        let _ = stream.set_nodelay(true);
+        let calls = calls.clone();
+        let compression = compression.clone();
+        let task: BoxFuture<()> = match &security {
+            Security::Insecure => Box::pin(serve_connection(
+                Box::new(stream),
+                http::Extensions::new(),
+                calls,
+                compression,
+                runtime.clone(),
+            )),
+            #[cfg(feature = "tls")]
+            Security::Tls(options) => Box::pin(serve_tls_connection(
+                stream,
+                options.clone(),
+                calls,
+                compression,
+                runtime.clone(),
+            )),
+        };
+        runtime.spawn(task);
+    }
+}
+
+#[cfg(feature = "tls")]
+async fn serve_tls_connection(
+    stream: TcpStream,
+    options: TlsOptions,
+    calls: mpsc::Sender<Call>,
+    compression: watch::Receiver<MessageCompression>,
+    runtime: Arc<dyn Runtime>,
+) {
+    let acceptor = tokio_rustls::TlsAcceptor::from(options.config);
+    let stream = tokio::select! {
+        _ = runtime.sleep(options.handshake_timeout) => {
+            eprintln!("warning: TLS handshake timed out");
+            return;
+        }
+        stream = acceptor.accept(stream) => match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                eprintln!("warning: TLS handshake failed: {err}");
+                return;
+            }
+        },
+    };
+    let (_, connection) = stream.get_ref();
+    let info = TlsInfo {
+        negotiated_protocol: connection.alpn_protocol().map(<[u8]>::to_vec),
+        peer_certificates: connection
+            .peer_certificates()
+            .unwrap_or_default()
+            .iter()
+            .map(|cert| cert.clone().into_owned())
+            .collect(),
+    };
+    let mut extensions = http::Extensions::new();
+    extensions.insert(info);
+    serve_connection(Box::new(stream), extensions, calls, compression, runtime).await;
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use bytes::Bytes;
+    use tokio::net::TcpStream;
+
+    use super::{BindOptions, TcpListener};
+    use crate::server::connection::test_utils::{unary, Echo};
+    use crate::server::Server;
+
+    fn serve(lis: TcpListener) -> Arc<Echo> {
+        let echo = Arc::new(Echo::default());
+        let mut srv = Server::new();
+        srv.set_handler(echo.clone());
+        tokio::spawn(async move { srv.serve(&lis).await });
+        echo
+    }
+
+    #[tokio::test]
+    async fn serves_plaintext_calls() {
+        let lis = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = lis.local_addr();
+        serve(lis);
+
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let (message, status) = unary(stream, b"hello").await;
+        assert_eq!(message, Bytes::from_static(b"hello"));
+        assert_eq!(status, "0");
+    }
+
+    #[tokio::test]
+    async fn serves_calls_on_adopted_listeners() {
+        let std_lis = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = std_lis.local_addr().unwrap();
+        let lis = TcpListener::from_std(std_lis).unwrap();
+        assert_eq!(lis.local_addr(), addr);
+        serve(lis);
+
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let (message, status) = unary(stream, b"adopted").await;
+        assert_eq!(message, Bytes::from_static(b"adopted"));
+        assert_eq!(status, "0");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn listeners_may_share_ports() {
+        let options = BindOptions::default().reuse_port(true);
+        let lis = TcpListener::bind_with_options("127.0.0.1:0".parse().unwrap(), options.clone())
+            .unwrap();
+        let addr = lis.local_addr();
+        // Without the option, the port is taken.
+        assert!(TcpListener::bind(addr).is_err());
+        let shared = TcpListener::bind_with_options(addr, options).unwrap();
+        assert_eq!(shared.local_addr(), addr);
+        serve(lis);
+        serve(shared);
+
+        // Connections are served by either listener.
+        for _ in 0..4 {
+            let stream = TcpStream::connect(addr).await.unwrap();
+            let (message, status) = unary(stream, b"shared").await;
+            assert_eq!(message, Bytes::from_static(b"shared"));
+            assert_eq!(status, "0");
+        }
+    }
+
+    #[cfg(feature = "tls")]
+    mod tls {
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        use bytes::Bytes;
+        use rustls::pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer, ServerName};
+        use rustls::server::WebPkiClientVerifier;
+        use tokio::io::AsyncReadExt;
+        use tokio::net::TcpStream;
+        use tokio_rustls::TlsConnector;
+
+        use super::serve;
+        use crate::server::connection::test_utils::unary;
+        use crate::server::tcp::{TcpListener, TlsInfo, TlsOptions};
+
+        const TEST_DATA: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../interop/data");
+
+        fn provider() -> Arc<rustls::crypto::CryptoProvider> {
+            Arc::new(rustls::crypto::ring::default_provider())
+        }
+
+        fn roots() -> rustls::RootCertStore {
+            let mut roots = rustls::RootCertStore::empty();
+            for cert in CertificateDer::pem_file_iter(format!("{TEST_DATA}/ca.pem")).unwrap() {
+                roots.add(cert.unwrap()).unwrap();
+            }
+            roots
+        }
+
+        // Binds a listener with the test server certificate, requiring client
+        // certificates if require_client_certs is set.
+        fn listener(require_client_certs: bool) -> TcpListener {
+            let certs = CertificateDer::pem_file_iter(format!("{TEST_DATA}/server1.pem"))
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            let key = PrivateKeyDer::from_pem_file(format!("{TEST_DATA}/server1.key")).unwrap();
+            let builder = rustls::ServerConfig::builder_with_provider(provider())
+                .with_safe_default_protocol_versions()
+                .unwrap();
+            let builder = if require_client_certs {
+                let verifier =
+                    WebPkiClientVerifier::builder_with_provider(Arc::new(roots()), provider())
+                        .build()
+                        .unwrap();
+                builder.with_client_cert_verifier(verifier)
+            } else {
+                builder.with_no_client_auth()
+            };
+            let config = builder.with_single_cert(certs, key).unwrap();
+            let options = TlsOptions::new(config).handshake_timeout(Duration::from_millis(100));
+            TcpListener::bind_tls("127.0.0.1:0".parse().unwrap(), options).unwrap()
+        }
+
+        fn connector() -> TlsConnector {
+            let mut config = rustls::ClientConfig::builder_with_provider(provider())
+                .with_safe_default_protocol_versions()
+                .unwrap()
+                .with_root_certificates(roots())
+                .with_no_client_auth();
+            config.alpn_protocols = vec![b"h2".to_vec()];
+            TlsConnector::from(Arc::new(config))
+        }
+
+        fn server_name() -> ServerName<'static> {
+            ServerName::try_from("foo.test.google.fr").unwrap()
+        }
+
+        #[tokio::test]
+        async fn serves_tls_calls_with_handshake_info() {
+            let lis = listener(false);
+            let addr = lis.local_addr();
+            let echo = serve(lis);
+
+            let stream = TcpStream::connect(addr).await.unwrap();
+            let stream = connector().connect(server_name(), stream).await.unwrap();
+            let (message, status) = unary(stream, b"secure").await;
+            assert_eq!(message, Bytes::from_static(b"secure"));
+            assert_eq!(status, "0");
+            let requests = echo.requests.lock().unwrap();
+            let info = requests[0].get::<TlsInfo>().unwrap();
+            assert_eq!(info.negotiated_protocol(), Some(&b"h2"[..]));
+            assert!(info.peer_certificates().is_empty());
+        }
+
+        #[tokio::test]
+        async fn rejects_clients_without_required_certs() {
+            let lis = listener(true);
+            let addr = lis.local_addr();
+            serve(lis);
+
+            // With TLS 1.3, the client only learns of the rejection once it
+            // reads from the connection.
+            let stream = TcpStream::connect(addr).await.unwrap();
+            let result = match connector().connect(server_name(), stream).await {
+                Ok(mut stream) => stream.read(&mut [0; 1]).await.map(|_| ()),
+                Err(err) => Err(err),
+            };
+            assert!(result.is_err());
+        }
+
+        #[tokio::test]
+        async fn closes_connections_which_do_not_complete_the_handshake() {
+            let lis = listener(false);
+            let addr = lis.local_addr();
+            serve(lis);
+
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            let read = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut [0; 1]))
+                .await
+                .unwrap();
+            assert!(matches!(read, Ok(0) | Err(_)));
+        }
+    }
+}
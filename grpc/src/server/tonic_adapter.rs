@@ -0,0 +1,219 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! Running services generated by tonic-build on a [`Server`](super::Server).
+//!
+//! [`TonicAdapter`] wraps a generated tonic server (or any other tower service
+//! implementing [`NamedService`]) so that it can be set as the handler of a
+//! server.  This lets existing services move to the new stack without
+//! regenerating their code.
+
+use bytes::Bytes;
+use http::uri::PathAndQuery;
+use tonic::{
+    async_trait,
+    body::Body,
+    client::{Grpc, GrpcService},
+    server::NamedService,
+    Status,
+};
+
+use crate::codec::{convert_request, convert_response, BytesCodec};
+use crate::service::{status_response, Request, Response, Service};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A [`Service`] which performs calls on a tonic service.
+///
+/// Messages are passed to the tonic service encoded, as [`Bytes`], and are
+/// decoded by the tonic service itself.  Calls to methods of other services
+/// than `S::NAME` fail with `UNIMPLEMENTED`.
+pub struct TonicAdapter<S> {
+    grpc: Grpc<S>,
+}
+
+impl<S> TonicAdapter<S> {
+    /// Creates an adapter for `service`, e.g. `EchoServer::new(...)` as
+    /// generated by tonic-build.
+    pub fn new(service: S) -> Self {
+        Self {
+            grpc: Grpc::new(service),
+        }
+    }
+}
+
+#[async_trait]
+impl<S> Service for TonicAdapter<S>
+where
+    S: GrpcService<Body> + NamedService + Clone + Send + Sync + 'static,
+    S::Error: Into<BoxError>,
+    S::Future: Send,
+    S::ResponseBody: Send + 'static,
+    <S::ResponseBody as http_body::Body>::Error: Into<BoxError>,
+{
+    async fn call(&self, method: String, request: Request) -> Response {
+        let in_service = method
+            .strip_prefix('/')
+            .and_then(|method| method.strip_prefix(S::NAME))
+            .is_some_and(|method| method.starts_with('/'));
+        if !in_service {
+            return status_response(Status::unimplemented(format!("unknown method {method}")));
+        }
+        let Ok(path) = PathAndQuery::from_maybe_shared(method) else {
+            return status_response(Status::internal("Failed to parse path"));
+        };
+        let mut grpc = self.grpc.clone();
+        if let Err(e) = grpc.ready().await {
+            let e: BoxError = e.into();
+            return status_response(Status::unknown(format!("Service was not ready: {e}")));
+        }
+        let response = grpc
+            .streaming(convert_request(request), path, BytesCodec {})
+            .await;
+        convert_response(response)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::any::Any;
+    use std::pin::Pin;
+
+    use bytes::Bytes;
+    use tokio_stream::{Stream, StreamExt};
+    use tonic::{async_trait, Code, Request, Response, Status, Streaming};
+    use tonic_prost::prost::Message as ProstMessage;
+
+    use super::TonicAdapter;
+    use crate::client::{Channel, ChannelOptions};
+    use crate::echo_pb::echo_server::{Echo, EchoServer};
+    use crate::echo_pb::{EchoRequest, EchoResponse};
+    use crate::inmemory;
+    use crate::server::Server;
+    use crate::service::Message;
+
+    type EchoStream = Pin<Box<dyn Stream<Item = Result<EchoResponse, Status>> + Send>>;
+
+    struct EchoService {}
+
+    #[async_trait]
+    impl Echo for EchoService {
+        async fn unary_echo(
+            &self,
+            request: Request<EchoRequest>,
+        ) -> Result<Response<EchoResponse>, Status> {
+            let message = request.into_inner().message;
+            if message.is_empty() {
+                return Err(Status::invalid_argument("empty message"));
+            }
+            Ok(Response::new(EchoResponse { message }))
+        }
+
+        type ServerStreamingEchoStream = EchoStream;
+
+        async fn server_streaming_echo(
+            &self,
+            request: Request<EchoRequest>,
+        ) -> Result<Response<EchoStream>, Status> {
+            let message = request.into_inner().message;
+            let stream = tokio_stream::iter((0..3).map(move |i| {
+                Ok(EchoResponse {
+                    message: format!("{message} {i}"),
+                })
+            }));
+            Ok(Response::new(Box::pin(stream)))
+        }
+
+        async fn client_streaming_echo(
+            &self,
+            _: Request<Streaming<EchoRequest>>,
+        ) -> Result<Response<EchoResponse>, Status> {
+            Err(Status::unimplemented("not implemented"))
+        }
+
+        type BidirectionalStreamingEchoStream = EchoStream;
+
+        async fn bidirectional_streaming_echo(
+            &self,
+            _: Request<Streaming<EchoRequest>>,
+        ) -> Result<Response<EchoStream>, Status> {
+            Err(Status::unimplemented("not implemented"))
+        }
+    }
+
+    async fn call(chan: &Channel, method: &str, message: &str) -> Vec<Result<String, Status>> {
+        let request = EchoRequest {
+            message: message.to_string(),
+        };
+        let msg: Box<dyn Message> = Box::new(Bytes::from(request.encode_to_vec()));
+        let req = Request::new(Box::pin(tokio_stream::once(msg)) as _);
+        let res = chan.call(method.to_string(), req).await;
+        res.into_inner()
+            .map(|msg| {
+                let bytes = (msg? as Box<dyn Any>).downcast::<Bytes>().unwrap();
+                Ok(EchoResponse::decode(*bytes).unwrap().message)
+            })
+            .collect()
+            .await
+    }
+
+    #[tokio::test]
+    async fn serves_tonic_service() {
+        inmemory::reg();
+        let lis = inmemory::Listener::new();
+        let mut srv = Server::new();
+        srv.set_handler(TonicAdapter::new(EchoServer::new(EchoService {})));
+        let lis_clone = lis.clone();
+        tokio::task::spawn(async move {
+            srv.serve(&lis_clone).await;
+        });
+        let chan = Channel::new(lis.target().as_str(), None, ChannelOptions::default());
+
+        let res = call(&chan, "/grpc.examples.echo.Echo/UnaryEcho", "hello").await;
+        assert_eq!(
+            res.into_iter().collect::<Result<Vec<_>, _>>().unwrap(),
+            ["hello"]
+        );
+
+        let res = call(&chan, "/grpc.examples.echo.Echo/ServerStreamingEcho", "hi").await;
+        assert_eq!(
+            res.into_iter().collect::<Result<Vec<_>, _>>().unwrap(),
+            ["hi 0", "hi 1", "hi 2"]
+        );
+
+        let mut res = call(&chan, "/grpc.examples.echo.Echo/UnaryEcho", "").await;
+        assert_eq!(
+            res.pop().unwrap().unwrap_err().code(),
+            Code::InvalidArgument
+        );
+
+        let mut res = call(&chan, "/grpc.examples.echo.Echo/Unknown", "hello").await;
+        assert_eq!(res.pop().unwrap().unwrap_err().code(), Code::Unimplemented);
+
+        let mut res = call(&chan, "/other.Service/UnaryEcho", "hello").await;
+        assert_eq!(res.pop().unwrap().unwrap_err().code(), Code::Unimplemented);
+
+        lis.close().await;
+    }
+}
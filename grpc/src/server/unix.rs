@@ -0,0 +1,328 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! A [`Listener`] accepting calls on Unix domain sockets.
+//!
+//! Calls are accepted over HTTP/2 connections made to a socket bound in the
+//! filesystem or, on Linux, in the abstract namespace.  The credentials of the
+//! peer process are attached to the requests as [`PeerCredentials`].
+
+use std::io;
+use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+use std::os::unix::net::UnixListener as StdUnixListener;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use tokio::net::UnixListener as TokioUnixListener;
+use tokio::sync::{mpsc, watch, Mutex};
+use tonic::async_trait;
+
+use super::connection::{serve_connection, ACCEPT_ERROR_BACKOFF};
+use super::{Call, Listener};
+use crate::compression::MessageCompression;
+use crate::rt::{default_runtime, BoxedTaskHandle, Runtime};
+use crate::service::Request;
+
+/// Options for binding a [`UnixListener`] to a path.
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct UnixListenerOptions {
+    /// The permissions, e.g. `0o600`, given to the socket file.  The socket
+    /// is bound in a private directory next to the path and linked into
+    /// place once its permissions are set, so it is never reachable with
+    /// other permissions.  If unset, the file is created with the permissions
+    /// allowed by the process' umask.
+    pub mode: Option<u32>,
+    /// Removes a file already present at the path before binding, e.g. a
+    /// socket left behind by a previous instance of the server.
+    pub remove_existing: bool,
+}
+
+impl UnixListenerOptions {
+    pub fn mode(self, mode: u32) -> Self {
+        Self {
+            mode: Some(mode),
+            ..self
+        }
+    }
+
+    pub fn remove_existing(self, remove_existing: bool) -> Self {
+        Self {
+            remove_existing,
+            ..self
+        }
+    }
+}
+
+/// The credentials of the process which made a call, as reported by the
+/// kernel for the connection when it was accepted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PeerCredentials {
+    pub uid: u32,
+    pub gid: u32,
+    /// The process ID of the peer, if the platform reports it.
+    pub pid: Option<i32>,
+}
+
+impl PeerCredentials {
+    /// Returns the credentials attached to request, if it was accepted by a
+    /// [`UnixListener`].
+    pub fn from_request(request: &Request) -> Option<Self> {
+        request.extensions().get::<Self>().copied()
+    }
+}
+
+/// A listener accepting calls made on a Unix domain socket.
+///
+/// Connections are accepted as soon as the listener is bound; their calls are
+/// queued until they are accepted by a [`Server`](super::Server).  A socket
+/// file bound by the listener is removed when it is dropped.
+pub struct UnixListener {
+    calls: Mutex<mpsc::Receiver<Call>>,
+    compression: watch::Sender<MessageCompression>,
+    accept_task: BoxedTaskHandle,
+    path: Option<PathBuf>,
+}
+
+impl UnixListener {
+    /// Binds a listener to the socket file at path.  Must be called within a
+    /// tokio runtime.
+    pub fn bind(path: impl AsRef<Path>, options: UnixListenerOptions) -> io::Result<Self> {
+        let path = path.as_ref();
+        if options.remove_existing {
+            match std::fs::remove_file(path) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+                _ => {}
+            }
+        }
+        let listener = match options.mode {
+            Some(mode) => bind_with_mode(path, mode)?,
+            None => TokioUnixListener::bind(path)?,
+        };
+        Ok(Self::start(listener, Some(path.to_path_buf())))
+    }
+
+    /// Binds a listener to name in the abstract namespace.  Abstract sockets
+    /// have no file, so they are not subject to filesystem permissions.  Must
+    /// be called within a tokio runtime.
+    #[cfg(target_os = "linux")]
+    pub fn bind_abstract(name: impl AsRef<[u8]>) -> io::Result<Self> {
+        use std::os::linux::net::SocketAddrExt;
+
+        let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+        let listener = StdUnixListener::bind_addr(&addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self::start(TokioUnixListener::from_std(listener)?, None))
+    }
+
+    fn start(listener: TokioUnixListener, path: Option<PathBuf>) -> Self {
+        let (tx, rx) = mpsc::channel(1);
+        let compression = watch::Sender::default();
+        let runtime = default_runtime();
+        let accept_task = runtime.spawn(Box::pin(accept_loop(
+            listener,
+            tx,
+            compression.subscribe(),
+            runtime.clone(),
+        )));
+        Self {
+            calls: Mutex::new(rx),
+            compression,
+            accept_task,
+            path,
+        }
+    }
+}
+
+// Binds a socket with the given permissions at path.  Setting the permissions
+// after binding at path would leave the socket reachable with the umask's
+// permissions in between, so it is bound in a directory only this process can
+// access and hard linked to path once its permissions are set.
+fn bind_with_mode(path: &Path, mode: u32) -> io::Result<TokioUnixListener> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let dir = parent.join(format!(".grpc-{:016x}", rand::random::<u64>()));
+    std::fs::DirBuilder::new().mode(0o700).create(&dir)?;
+    let tmp = dir.join("s");
+    let result = (|| {
+        let listener = TokioUnixListener::bind(&tmp)?;
+        std::fs::set_permissions(&tmp, std::fs::Permissions::from_mode(mode))?;
+        // Unlike a rename, this fails if path already exists, as bind does.
+        std::fs::hard_link(&tmp, path)?;
+        Ok(listener)
+    })();
+    let _ = std::fs::remove_file(&tmp);
+    let _ = std::fs::remove_dir(&dir);
+    result
+}
+
+impl Drop for UnixListener {
+    fn drop(&mut self) {
+        self.accept_task.abort();
+        if let Some(path) = &self.path {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+#[async_trait]
+impl Listener for UnixListener {
+    async fn accept(&self) -> Option<Call> {
+        self.calls.lock().await.recv().await
+    }
+
+    fn set_compression(&self, compression: MessageCompression) {
+        self.compression.send_replace(compression);
+    }
+}
+
+async fn accept_loop(
+    listener: TokioUnixListener,
+    calls: mpsc::Sender<Call>,
+    compression: watch::Receiver<MessageCompression>,
+    runtime: Arc<dyn Runtime>,
+) {
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(err) => {
+                tracing::warn!("failed to accept a unix socket connection: {err}");
+                runtime.sleep(ACCEPT_ERROR_BACKOFF).await;
+                continue;
+            }
+        };
+        let peer = stream.peer_cred().ok().map(|cred| PeerCredentials {
+            uid: cred.uid(),
+            gid: cred.gid(),
+            pid: cred.pid(),
+        });
+        let mut extensions = http::Extensions::new();
+        if let Some(peer) = peer {
+            extensions.insert(peer);
+        }
+        runtime.spawn(Box::pin(serve_connection(
+            Box::new(stream),
+            extensions,
+            calls.clone(),
+            compression.clone(),
+            runtime.clone(),
+        )));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+    use std::sync::Arc;
+
+    use bytes::Bytes;
+    use tokio::net::UnixStream;
+
+    use super::{PeerCredentials, UnixListener, UnixListenerOptions};
+    use crate::server::connection::test_utils::{unary, Echo};
+    use crate::server::Server;
+
+    fn serve(lis: UnixListener) -> Arc<Echo> {
+        let echo = Arc::new(Echo::default());
+        let mut srv = Server::new();
+        srv.set_handler(echo.clone());
+        tokio::spawn(async move { srv.serve(&lis).await });
+        echo
+    }
+
+    #[tokio::test]
+    async fn serves_calls_with_peer_credentials() {
+        let path = std::env::temp_dir().join(format!("grpc-unix-{}.sock", std::process::id()));
+        std::fs::write(&path, "stale").unwrap();
+        let options = UnixListenerOptions::default()
+            .remove_existing(true)
+            .mode(0o600);
+        let lis = UnixListener::bind(&path, options).unwrap();
+        let metadata = std::fs::metadata(&path).unwrap();
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+        let echo = serve(lis);
+
+        let stream = UnixStream::connect(&path).await.unwrap();
+        let (message, status) = unary(stream, b"hello").await;
+        assert_eq!(message, Bytes::from_static(b"hello"));
+        assert_eq!(status, "0");
+        let peer = echo.requests.lock().unwrap()[0]
+            .get::<PeerCredentials>()
+            .copied()
+            .unwrap();
+        assert_eq!(peer.uid, metadata.uid());
+        assert_eq!(peer.gid, metadata.gid());
+        assert_eq!(peer.pid, Some(std::process::id() as i32));
+    }
+
+    #[tokio::test]
+    async fn bind_fails_on_existing_file() {
+        let path =
+            std::env::temp_dir().join(format!("grpc-unix-existing-{}.sock", std::process::id()));
+        std::fs::write(&path, "stale").unwrap();
+        assert!(UnixListener::bind(&path, UnixListenerOptions::default()).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn bind_with_mode_leaves_only_the_socket() {
+        let dir = std::env::temp_dir().join(format!("grpc-unix-mode-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("server.sock");
+        let entries = || std::fs::read_dir(&dir).unwrap().count();
+
+        let lis = UnixListener::bind(&path, UnixListenerOptions::default().mode(0o640)).unwrap();
+        let metadata = std::fs::metadata(&path).unwrap();
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o640);
+        assert_eq!(entries(), 1);
+        assert!(UnixListener::bind(&path, UnixListenerOptions::default().mode(0o640)).is_err());
+        assert_eq!(entries(), 1);
+        serve(lis);
+
+        let stream = UnixStream::connect(&path).await.unwrap();
+        let (_, status) = unary(stream, b"hello").await;
+        assert_eq!(status, "0");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn serves_abstract_sockets() {
+        use std::os::linux::net::SocketAddrExt;
+
+        let name = format!("grpc-unix-abstract-{}", std::process::id());
+        let lis = UnixListener::bind_abstract(&name).unwrap();
+        serve(lis);
+
+        let addr = std::os::unix::net::SocketAddr::from_abstract_name(&name).unwrap();
+        let stream = std::os::unix::net::UnixStream::connect_addr(&addr).unwrap();
+        stream.set_nonblocking(true).unwrap();
+        let stream = UnixStream::from_std(stream).unwrap();
+        let (message, status) = unary(stream, b"abstract").await;
+        assert_eq!(message, Bytes::from_static(b"abstract"));
+        assert_eq!(status, "0");
+    }
+}
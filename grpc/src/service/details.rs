@@ -0,0 +1,237 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! The standard error model of `google.rpc.Status`, which lets servers attach
+//! typed detail messages such as [`RetryInfo`], [`BadRequest`] and
+//! [`QuotaFailure`] to a [`Status`].
+//!
+//! Details are attached with [`StatusExt::with_error_details`] and parsed with
+//! [`StatusExt::get_error_details`] or the typed getters of [`StatusExt`].
+//!
+//! The serialized details are carried in the `grpc-status-details-bin`
+//! trailer.  Channels and servers move details set as binary metadata of a
+//! status into its details, and drop details larger than their limit (see
+//! [`DEFAULT_MAX_STATUS_DETAILS_SIZE`]) since they could exceed the peer's
+//! limit on the size of trailers.
+
+use std::{pin::Pin, time::Duration};
+
+use bytes::Bytes;
+use tokio_stream::{Stream, StreamExt};
+
+use super::{Message, Response, Status};
+
+pub use tonic_types::{
+    BadRequest, ErrorDetail, ErrorDetails, FieldViolation, QuotaFailure, QuotaViolation, RetryInfo,
+    StatusExt,
+};
+
+/// The metadata key servers use to push back on retries, per [gRFC A6].
+///
+/// [gRFC A6]: https://github.com/grpc/proposal/blob/master/A6-client-retries.md
+pub const RETRY_PUSHBACK_KEY: &str = "grpc-retry-pushback-ms";
+
+/// A server's instruction about retrying a failed RPC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pushback {
+    /// The RPC may be retried after the delay, instead of the delay computed
+    /// from the retry policy.
+    RetryAfter(Duration),
+    /// The RPC must not be retried.
+    DoNotRetry,
+}
+
+/// Returns the server's instruction about retrying the RPC which failed with
+/// `status`, or None if the server did not send one.
+///
+/// The `grpc-retry-pushback-ms` trailer takes precedence over a [`RetryInfo`]
+/// detail.  A trailer which is not a non-negative integer means the RPC must
+/// not be retried.
+// TODO: consult this from the retry subsystem once it exists.
+pub fn pushback(status: &Status) -> Option<Pushback> {
+    if let Some(value) = status.metadata().get(RETRY_PUSHBACK_KEY) {
+        let millis = value.to_str().ok().and_then(|v| v.parse::<u64>().ok());
+        return Some(match millis {
+            Some(millis) => Pushback::RetryAfter(Duration::from_millis(millis)),
+            None => Pushback::DoNotRetry,
+        });
+    }
+    let delay = status.get_details_retry_info()?.retry_delay?;
+    Some(Pushback::RetryAfter(delay))
+}
+
+/// The trailer carrying the serialized details of a status.
+pub const STATUS_DETAILS_KEY: &str = "grpc-status-details-bin";
+
+/// The default limit on the size of the serialized details of a status.
+pub const DEFAULT_MAX_STATUS_DETAILS_SIZE: usize = 8 * 1024;
+
+/// Returns status with details set as `grpc-status-details-bin` metadata moved
+/// into its details, and without details larger than max_size.
+pub(crate) fn normalize_details(mut status: Status, max_size: usize) -> Status {
+    let mut details = None;
+    if let Some(value) = status.metadata_mut().remove_bin(STATUS_DETAILS_KEY) {
+        if status.details().is_empty() {
+            match value.to_bytes() {
+                Ok(bytes) => details = Some(bytes),
+                Err(_) => eprintln!("warning: ignoring invalid {STATUS_DETAILS_KEY} metadata"),
+            }
+        }
+    }
+    if details.as_ref().map_or(status.details().len(), Bytes::len) > max_size {
+        eprintln!(
+            "warning: dropping status details larger than the limit of {max_size} bytes: {}",
+            status.message()
+        );
+        details = Some(Bytes::new());
+    }
+    match details {
+        // Statuses are only rebuilt when their details change, since that
+        // drops their source.
+        Some(details) => Status::with_details_and_metadata(
+            status.code(),
+            status.message(),
+            details,
+            status.metadata().clone(),
+        ),
+        None => status,
+    }
+}
+
+/// Applies [`normalize_details`] to the status of response.
+pub(crate) fn normalize_response_details(response: Response, max_size: usize) -> Response {
+    response.map(|inner| {
+        Box::pin(inner.map(move |item| item.map_err(|status| normalize_details(status, max_size))))
+            as Pin<Box<dyn Stream<Item = Result<Box<dyn Message>, Status>> + Send>>
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use tonic::{
+        metadata::{MetadataMap, MetadataValue},
+        Code, Status,
+    };
+
+    use super::{
+        normalize_details, pushback, ErrorDetails, Pushback, StatusExt, RETRY_PUSHBACK_KEY,
+        STATUS_DETAILS_KEY,
+    };
+
+    #[test]
+    fn details_round_trip() {
+        let mut details = ErrorDetails::with_retry_info(Some(Duration::from_secs(2)));
+        details
+            .add_bad_request_violation("name", "must not be empty")
+            .add_quota_failure_violation("project:1", "daily limit exceeded");
+        let status = Status::with_error_details(Code::ResourceExhausted, "slow down", details);
+
+        let details = status.get_error_details();
+        assert_eq!(
+            details.retry_info().unwrap().retry_delay,
+            Some(Duration::from_secs(2))
+        );
+        let bad_request = details.bad_request().unwrap();
+        assert_eq!(bad_request.field_violations[0].field, "name");
+        let quota_failure = details.quota_failure().unwrap();
+        assert_eq!(quota_failure.violations[0].subject, "project:1");
+    }
+
+    #[test]
+    fn pushback_from_retry_info() {
+        let status = Status::unavailable("try later");
+        assert_eq!(pushback(&status), None);
+
+        let details = ErrorDetails::with_retry_info(Some(Duration::from_millis(1500)));
+        let status = Status::with_error_details(Code::Unavailable, "try later", details);
+        assert_eq!(
+            pushback(&status),
+            Some(Pushback::RetryAfter(Duration::from_millis(1500)))
+        );
+    }
+
+    #[test]
+    fn pushback_metadata_takes_precedence() {
+        let details = ErrorDetails::with_retry_info(Some(Duration::from_secs(5)));
+        let mut status = Status::with_error_details(Code::Unavailable, "try later", details);
+        status
+            .metadata_mut()
+            .insert(RETRY_PUSHBACK_KEY, "250".parse().unwrap());
+        assert_eq!(
+            pushback(&status),
+            Some(Pushback::RetryAfter(Duration::from_millis(250)))
+        );
+
+        let mut metadata = MetadataMap::new();
+        metadata.insert(RETRY_PUSHBACK_KEY, "-1".parse().unwrap());
+        let status = Status::with_metadata(Code::Unavailable, "go away", metadata);
+        assert_eq!(pushback(&status), Some(Pushback::DoNotRetry));
+    }
+
+    #[test]
+    fn details_metadata_moved_into_details() {
+        let mut status = Status::unavailable("down");
+        status
+            .metadata_mut()
+            .insert_bin(STATUS_DETAILS_KEY, MetadataValue::from_bytes(&[1, 2, 3]));
+        let status = normalize_details(status, 1024);
+        assert_eq!(status.details(), [1, 2, 3]);
+        assert!(status.metadata().get_bin(STATUS_DETAILS_KEY).is_none());
+
+        // Details of the status take precedence over metadata.
+        let mut status = Status::with_details(Code::Unavailable, "down", vec![4].into());
+        status
+            .metadata_mut()
+            .insert_bin(STATUS_DETAILS_KEY, MetadataValue::from_bytes(&[1, 2, 3]));
+        let status = normalize_details(status, 1024);
+        assert_eq!(status.details(), [4]);
+        assert!(status.metadata().is_empty());
+    }
+
+    #[test]
+    fn oversized_details_dropped() {
+        let mut status = Status::with_details(Code::Internal, "broken", vec![0; 10].into());
+        status
+            .metadata_mut()
+            .insert("key", "value".parse().unwrap());
+        let status = normalize_details(status, 9);
+        assert_eq!(status.code(), Code::Internal);
+        assert_eq!(status.message(), "broken");
+        assert!(status.details().is_empty());
+        assert_eq!(status.metadata().get("key").unwrap(), "value");
+
+        let status = Status::with_details(Code::Internal, "broken", vec![0; 10].into());
+        assert_eq!(normalize_details(status, 10).details().len(), 10);
+    }
+
+    #[test]
+    fn unchanged_statuses_keep_their_source() {
+        let mut status = Status::unavailable("down");
+        status.set_source(std::sync::Arc::new(std::fmt::Error));
+        let status = normalize_details(status, 0);
+        assert!(std::error::Error::source(&status).is_some());
+    }
+}
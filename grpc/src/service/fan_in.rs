@@ -0,0 +1,404 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! Merges the responses of several upstream calls into a single Response.
+//!
+//! This supports the scatter-gather pattern in server handlers, which fan out a
+//! request to multiple upstream services and return the combined results:
+//!
+//! ```ignore
+//! let calls = backends.iter().map(|b| b.call(method.clone(), make_request()));
+//! FanIn::new()
+//!     .order(MergeOrder::AsCompleted)
+//!     .on_failure(FailureStrategy::BestEffort)
+//!     .merge(calls)
+//! ```
+
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use tokio_stream::Stream;
+use tonic::Status;
+
+use super::{Message, Response};
+
+type ResponseStream = Pin<Box<dyn Stream<Item = Result<Box<dyn Message>, Status>> + Send>>;
+
+/// Determines the order in which messages from the upstream responses are
+/// produced.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MergeOrder {
+    /// All messages of the first upstream are produced, followed by all
+    /// messages of the second, and so on.
+    #[default]
+    Ordered,
+    /// Messages are produced as soon as any upstream produces them.
+    AsCompleted,
+    /// The upstreams race until one of them produces a message or completes
+    /// successfully.  That upstream is committed to and all others are
+    /// cancelled, so only the committed upstream's messages are produced, in
+    /// its order.  Use this for upstreams producing the same messages (e.g.
+    /// hedged attempts), which would otherwise be duplicated or interleaved.
+    ///
+    /// Failures before an upstream is committed to are handled by the
+    /// [`FailureStrategy`]; a failure of the committed upstream always fails
+    /// the merged response.
+    Committed,
+}
+
+/// Determines how failures of individual upstreams are handled.  Regardless of
+/// the strategy, if every upstream fails, the merged response fails with the
+/// status of the last failure.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FailureStrategy {
+    /// The merged response fails with the status of the first upstream
+    /// failure, and all other upstreams are cancelled.
+    #[default]
+    FailFast,
+    /// Up to the given number of upstream failures are ignored (the messages
+    /// already produced by failed upstreams are kept); the next failure fails
+    /// the merged response.
+    MaxFailures(usize),
+    /// Upstream failures are ignored as long as at least one upstream
+    /// succeeds.
+    BestEffort,
+}
+
+/// Merges the responses of several upstream calls into one Response.
+#[derive(Debug, Clone, Default)]
+pub struct FanIn {
+    order: MergeOrder,
+    on_failure: FailureStrategy,
+}
+
+impl FanIn {
+    /// Returns a FanIn which produces messages in order and fails fast.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the order in which messages are produced.
+    pub fn order(self, order: MergeOrder) -> Self {
+        Self { order, ..self }
+    }
+
+    /// Sets how upstream failures are handled.
+    pub fn on_failure(self, on_failure: FailureStrategy) -> Self {
+        Self { on_failure, ..self }
+    }
+
+    /// Returns a Response whose message stream merges the message streams of
+    /// the responses produced by calls.  The calls are driven by polling the
+    /// returned stream; no tasks are spawned.  Dropping the returned response
+    /// cancels all outstanding calls.
+    pub fn merge<F>(self, calls: impl IntoIterator<Item = F>) -> Response
+    where
+        F: Future<Output = Response> + Send + 'static,
+    {
+        let upstreams: Vec<_> = calls
+            .into_iter()
+            .map(|call| Upstream::Calling(Box::pin(call)))
+            .collect();
+        Response::new(Box::pin(FanInStream {
+            max_failures: match self.on_failure {
+                FailureStrategy::FailFast => 0,
+                FailureStrategy::MaxFailures(n) => n,
+                FailureStrategy::BestEffort => usize::MAX,
+            },
+            order: self.order,
+            upstreams,
+            next: 0,
+            committed: None,
+            failures: 0,
+            last_error: None,
+        }))
+    }
+}
+
+enum Upstream<F> {
+    Calling(Pin<Box<F>>),
+    Streaming(ResponseStream),
+    Done,
+}
+
+struct FanInStream<F> {
+    upstreams: Vec<Upstream<F>>,
+    order: MergeOrder,
+    // The upstream to poll first for AsCompleted and Committed, for fairness.
+    next: usize,
+    // The upstream committed to for Committed.
+    committed: Option<usize>,
+    max_failures: usize,
+    failures: usize,
+    last_error: Option<Status>,
+}
+
+impl<F: Future<Output = Response>> FanInStream<F> {
+    // Polls upstream i for its next item.  Returns None once it is done.
+    fn poll_upstream(
+        &mut self,
+        i: usize,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Box<dyn Message>, Status>>> {
+        loop {
+            match &mut self.upstreams[i] {
+                Upstream::Calling(call) => {
+                    let response = ready!(call.as_mut().poll(cx));
+                    self.upstreams[i] = Upstream::Streaming(response.into_inner());
+                }
+                Upstream::Streaming(stream) => {
+                    let item = ready!(stream.as_mut().poll_next(cx));
+                    if !matches!(item, Some(Ok(_))) {
+                        self.upstreams[i] = Upstream::Done;
+                    }
+                    return Poll::Ready(item);
+                }
+                Upstream::Done => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+impl<F> FanInStream<F> {
+    // Commits to upstream i, cancelling all others.
+    fn commit(&mut self, i: usize) {
+        self.committed = Some(i);
+        for (j, upstream) in self.upstreams.iter_mut().enumerate() {
+            if j != i {
+                *upstream = Upstream::Done;
+            }
+        }
+    }
+}
+
+impl<F: Future<Output = Response>> Stream for FanInStream<F> {
+    type Item = Result<Box<dyn Message>, Status>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if let Some(i) = this.committed {
+            return this.poll_upstream(i, cx);
+        }
+        let n = this.upstreams.len();
+        let start = match this.order {
+            MergeOrder::Ordered => 0,
+            MergeOrder::AsCompleted | MergeOrder::Committed => this.next,
+        };
+        let mut pending = false;
+        for k in 0..n {
+            let i = (start + k) % n;
+            match this.poll_upstream(i, cx) {
+                Poll::Ready(Some(Ok(msg))) => {
+                    this.next = (i + 1) % n;
+                    if this.order == MergeOrder::Committed {
+                        this.commit(i);
+                    }
+                    return Poll::Ready(Some(Ok(msg)));
+                }
+                Poll::Ready(Some(Err(status))) => {
+                    this.failures += 1;
+                    if this.failures > this.max_failures {
+                        // Cancel all other upstreams.
+                        this.upstreams.clear();
+                        return Poll::Ready(Some(Err(status)));
+                    }
+                    this.last_error = Some(status);
+                }
+                Poll::Ready(None) if this.order == MergeOrder::Committed => {
+                    this.commit(i);
+                    return Poll::Ready(None);
+                }
+                Poll::Ready(None) => {}
+                Poll::Pending => {
+                    if this.order == MergeOrder::Ordered {
+                        return Poll::Pending;
+                    }
+                    pending = true;
+                }
+            }
+        }
+        if pending {
+            return Poll::Pending;
+        }
+        // All upstreams are done.
+        if n > 0 && this.failures == n {
+            if let Some(status) = this.last_error.take() {
+                return Poll::Ready(Some(Err(status)));
+            }
+        }
+        Poll::Ready(None)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::future::{self, Future};
+
+    use tokio::sync::mpsc;
+    use tokio_stream::{wrappers::UnboundedReceiverStream, StreamExt};
+    use tonic::{Code, Status};
+
+    use crate::service::{Message, Response};
+
+    use super::{FailureStrategy, FanIn, MergeOrder};
+
+    type Item = Result<Box<dyn Message>, Status>;
+
+    fn msg(v: i32) -> Item {
+        Ok(Box::new(v))
+    }
+
+    fn response(items: Vec<Item>) -> impl Future<Output = Response> + Send {
+        future::ready(Response::new(Box::pin(tokio_stream::iter(items))))
+    }
+
+    // Like response, but with the same type as responses of other streams.
+    fn stream_response(items: Vec<Item>) -> future::Ready<Response> {
+        future::ready(Response::new(
+            Box::pin(tokio_stream::iter(items)) as super::ResponseStream
+        ))
+    }
+
+    // Collects the merged messages, formatted for comparison, ending with the
+    // status code of the failure if the response failed.
+    async fn collect(response: Response) -> Vec<String> {
+        let mut stream = response.into_inner();
+        let mut out = vec![];
+        while let Some(item) = stream.next().await {
+            match item {
+                Ok(m) => out.push(format!("{m:?}")),
+                Err(s) => out.push(format!("{:?}", s.code())),
+            }
+        }
+        out
+    }
+
+    #[tokio::test]
+    async fn ordered() {
+        let merged = FanIn::new().merge([
+            response(vec![msg(1), msg(2)]),
+            response(vec![]),
+            response(vec![msg(3)]),
+        ]);
+        assert_eq!(collect(merged).await, ["1", "2", "3"]);
+    }
+
+    #[tokio::test]
+    async fn as_completed() {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let slow = future::ready(Response::new(
+            Box::pin(UnboundedReceiverStream::new(rx)) as super::ResponseStream
+        ));
+        let fast = future::ready(Response::new(
+            Box::pin(tokio_stream::iter(vec![msg(1), msg(2)])) as super::ResponseStream,
+        ));
+        let mut stream = FanIn::new()
+            .order(MergeOrder::AsCompleted)
+            .merge([slow, fast])
+            .into_inner();
+        assert_eq!(format!("{:?}", stream.next().await.unwrap().unwrap()), "1");
+        assert_eq!(format!("{:?}", stream.next().await.unwrap().unwrap()), "2");
+        tx.send(msg(3)).unwrap();
+        drop(tx);
+        assert_eq!(format!("{:?}", stream.next().await.unwrap().unwrap()), "3");
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn committed() {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let slow = future::ready(Response::new(
+            Box::pin(UnboundedReceiverStream::new(rx)) as super::ResponseStream
+        ));
+        let merged = FanIn::new()
+            .order(MergeOrder::Committed)
+            .merge([slow, stream_response(vec![msg(1), msg(2)])]);
+        // The slow upstream is cancelled once the other is committed to, so
+        // its messages are never produced.
+        assert_eq!(collect(merged).await, ["1", "2"]);
+        assert!(tx.send(msg(1)).is_err());
+
+        // An upstream completing without messages is committed to as well.
+        let (tx, rx) = mpsc::unbounded_channel::<Item>();
+        let slow = future::ready(Response::new(
+            Box::pin(UnboundedReceiverStream::new(rx)) as super::ResponseStream
+        ));
+        let merged = FanIn::new()
+            .order(MergeOrder::Committed)
+            .merge([slow, stream_response(vec![])]);
+        assert!(collect(merged).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn committed_failures() {
+        let merged = FanIn::new()
+            .order(MergeOrder::Committed)
+            .on_failure(FailureStrategy::BestEffort)
+            .merge([
+                response(vec![Err(Status::unavailable("down"))]),
+                response(vec![msg(1), Err(Status::internal("broken"))]),
+                response(vec![msg(2)]),
+            ]);
+        // The failure before committing is ignored, but the committed
+        // upstream's failure is not, even though another upstream would have
+        // succeeded.
+        assert_eq!(collect(merged).await, ["1", "Internal"]);
+    }
+
+    #[tokio::test]
+    async fn fail_fast() {
+        let merged = FanIn::new().merge([
+            response(vec![msg(1), Err(Status::unavailable("down"))]),
+            response(vec![msg(2)]),
+        ]);
+        assert_eq!(collect(merged).await, ["1", "Unavailable"]);
+    }
+
+    #[tokio::test]
+    async fn max_failures() {
+        let failing = || response(vec![Err(Status::unavailable("down"))]);
+        let merged = FanIn::new()
+            .on_failure(FailureStrategy::MaxFailures(1))
+            .merge([failing(), response(vec![msg(1)]), failing()]);
+        assert_eq!(collect(merged).await, ["1", "Unavailable"]);
+    }
+
+    #[tokio::test]
+    async fn best_effort() {
+        let merged = FanIn::new().on_failure(FailureStrategy::BestEffort).merge([
+            response(vec![Err(Status::unavailable("down"))]),
+            response(vec![msg(1)]),
+        ]);
+        assert_eq!(collect(merged).await, ["1"]);
+
+        let merged = FanIn::new().on_failure(FailureStrategy::BestEffort).merge([
+            response(vec![Err(Status::unavailable("down"))]),
+            response(vec![Err(Status::internal("broken"))]),
+        ]);
+        assert_eq!(collect(merged).await, [format!("{:?}", Code::Internal)]);
+    }
+}
@@ -25,7 +25,13 @@
 use std::{any::Any, fmt::Debug, pin::Pin};
 
 use tokio_stream::Stream;
-use tonic::{async_trait, Request as TonicRequest, Response as TonicResponse, Status};
+use tonic::{async_trait, Request as TonicRequest, Response as TonicResponse};
+
+pub use tonic::{Code, Status};
+
+pub mod details;
+pub mod fan_in;
+pub mod response_writer;
 
 pub type Request = TonicRequest<Pin<Box<dyn Stream<Item = Box<dyn Message>> + Send + Sync>>>;
 pub type Response =
@@ -33,10 +39,50 @@ pub type Response =
 
 #[async_trait]
 pub trait Service: Send + Sync {
+    /// Performs an RPC.  The metadata of the returned response is sent as the
+    /// response headers as soon as it is returned; see
+    /// [`response_writer`] for sending them
+    /// before any message is ready.
     async fn call(&self, method: String, request: Request) -> Response;
 }
 
+/// Returns a response which fails the RPC with `status` without any messages,
+/// i.e. a trailers-only response.  The metadata of `status` becomes the
+/// metadata of the response.
+///
+/// Services fail RPCs by returning such a response, or by yielding an error
+/// from the response stream after sending some messages.
+pub fn status_response(status: Status) -> Response {
+    let metadata = status.metadata().clone();
+    let mut response = Response::new(Box::pin(tokio_stream::once(Err(status))));
+    *response.metadata_mut() = metadata;
+    response
+}
+
 // TODO: define methods that will allow serialization/deserialization.
 pub trait Message: Any + Send + Sync + Debug {}
 
 impl<T> Message for T where T: Any + Send + Sync + Debug {}
+
+#[cfg(test)]
+mod test {
+    use tokio_stream::StreamExt;
+    use tonic::metadata::MetadataMap;
+
+    use super::{status_response, Code, Status};
+
+    #[tokio::test]
+    async fn status_response_is_trailers_only() {
+        let mut metadata = MetadataMap::new();
+        metadata.insert("retry-pushback-ms", "100".parse().unwrap());
+        let status = Status::with_metadata(Code::ResourceExhausted, "over quota", metadata);
+
+        let response = status_response(status);
+        assert_eq!(response.metadata().get("retry-pushback-ms").unwrap(), "100");
+        let results: Vec<_> = response.into_inner().collect().await;
+        assert_eq!(results.len(), 1);
+        let status = results.into_iter().next().unwrap().unwrap_err();
+        assert_eq!(status.code(), Code::ResourceExhausted);
+        assert_eq!(status.message(), "over quota");
+    }
+}
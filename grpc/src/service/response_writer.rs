@@ -0,0 +1,221 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! Lets server handlers send response headers before producing messages.
+//!
+//! A handler's response headers are sent when it returns its [`Response`].
+//! Handlers which want clients to receive initial metadata promptly, before
+//! the first message is ready (e.g. for auth challenges or progress
+//! indication), produce the response with a [`ResponseWriter`] from a task:
+//!
+//! ```ignore
+//! let (mut writer, response) = response_writer(4);
+//! runtime.spawn(Box::pin(async move {
+//!     writer.send_headers(metadata).unwrap();
+//!     let msg = compute().await;
+//!     let _ = writer.send(Box::new(msg)).await;
+//! }));
+//! response.await
+//! ```
+
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use tokio::sync::{mpsc, oneshot};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{metadata::MetadataMap, Status};
+
+use super::{status_response, Message, Response};
+
+type Item = Result<Box<dyn Message>, Status>;
+
+/// Returns a writer used to produce a response, and a future which resolves
+/// to the response once the writer sends headers.  `buffer` is the number of
+/// messages buffered before [`ResponseWriter::send`] waits for the client.
+pub fn response_writer(buffer: usize) -> (ResponseWriter, PendingResponse) {
+    let (headers_tx, headers_rx) = oneshot::channel();
+    let (tx, rx) = mpsc::channel(buffer);
+    (
+        ResponseWriter {
+            headers: Some(headers_tx),
+            tx,
+        },
+        PendingResponse {
+            headers: headers_rx,
+            rx: Some(rx),
+        },
+    )
+}
+
+/// Produces the headers, messages and status of a response.
+///
+/// If no headers were sent explicitly, empty headers are sent with the first
+/// message, or when the writer is dropped.
+#[derive(Debug)]
+pub struct ResponseWriter {
+    headers: Option<oneshot::Sender<Result<MetadataMap, Status>>>,
+    tx: mpsc::Sender<Item>,
+}
+
+impl ResponseWriter {
+    /// Sends the response headers immediately, independently of the first
+    /// message.  Fails if headers were already sent.
+    pub fn send_headers(&mut self, metadata: MetadataMap) -> Result<(), Status> {
+        let headers = self
+            .headers
+            .take()
+            .ok_or_else(|| Status::internal("response headers already sent"))?;
+        // The client may be gone; that is reported by the next send.
+        let _ = headers.send(Ok(metadata));
+        Ok(())
+    }
+
+    /// Sends a message, sending empty headers first if none were sent.  Fails
+    /// if the client is no longer receiving the response.
+    pub async fn send(&mut self, msg: Box<dyn Message>) -> Result<(), Status> {
+        if self.headers.is_some() {
+            self.send_headers(MetadataMap::new())?;
+        }
+        self.tx
+            .send(Ok(msg))
+            .await
+            .map_err(|_| Status::cancelled("response is no longer received"))
+    }
+
+    /// Fails the RPC with `status`.  If no headers were sent, the response is
+    /// trailers-only.
+    pub async fn fail(mut self, status: Status) {
+        if let Some(headers) = self.headers.take() {
+            let _ = headers.send(Err(status));
+            return;
+        }
+        let _ = self.tx.send(Err(status)).await;
+    }
+}
+
+impl Drop for ResponseWriter {
+    fn drop(&mut self) {
+        if let Some(headers) = self.headers.take() {
+            let _ = headers.send(Ok(MetadataMap::new()));
+        }
+    }
+}
+
+/// Resolves to the response produced by a [`ResponseWriter`] once its headers
+/// are sent.
+#[derive(Debug)]
+pub struct PendingResponse {
+    headers: oneshot::Receiver<Result<MetadataMap, Status>>,
+    rx: Option<mpsc::Receiver<Item>>,
+}
+
+impl Future for PendingResponse {
+    type Output = Response;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Response> {
+        let headers = match ready!(Pin::new(&mut self.headers).poll(cx)) {
+            Ok(Ok(headers)) => headers,
+            Ok(Err(status)) => return Poll::Ready(status_response(status)),
+            // The writer always resolves headers before it is dropped.
+            Err(_) => unreachable!("response writer dropped without headers"),
+        };
+        let rx = self.rx.take().expect("polled after completion");
+        let mut response = Response::new(Box::pin(ReceiverStream::new(rx)));
+        *response.metadata_mut() = headers;
+        Poll::Ready(response)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use tokio::sync::Notify;
+    use tokio_stream::StreamExt;
+    use tonic::{metadata::MetadataMap, Code, Status};
+
+    use super::response_writer;
+
+    #[tokio::test]
+    async fn headers_are_sent_before_messages() {
+        let (mut writer, response) = response_writer(1);
+        let ready = Arc::new(Notify::new());
+        let ready_clone = ready.clone();
+        tokio::spawn(async move {
+            let mut metadata = MetadataMap::new();
+            metadata.insert("x-progress", "started".parse().unwrap());
+            writer.send_headers(metadata).unwrap();
+            assert!(writer.send_headers(MetadataMap::new()).is_err());
+            ready_clone.notified().await;
+            writer.send(Box::new(7)).await.unwrap();
+        });
+
+        // The response resolves while the handler is still blocked.
+        let response = response.await;
+        assert_eq!(response.metadata().get("x-progress").unwrap(), "started");
+        ready.notify_one();
+        let msgs: Vec<_> = response.into_inner().collect().await;
+        assert_eq!(msgs.len(), 1);
+        assert_eq!(format!("{:?}", msgs[0].as_ref().unwrap()), "7");
+    }
+
+    #[tokio::test]
+    async fn implicit_headers() {
+        let (mut writer, response) = response_writer(1);
+        tokio::spawn(async move {
+            writer.send(Box::new(1)).await.unwrap();
+        });
+        let response = response.await;
+        assert!(response.metadata().is_empty());
+        let msgs: Vec<_> = response.into_inner().collect().await;
+        assert_eq!(msgs.len(), 1);
+
+        let (writer, response) = response_writer(1);
+        drop(writer);
+        let msgs: Vec<_> = response.await.into_inner().collect().await;
+        assert!(msgs.is_empty());
+    }
+
+    #[tokio::test]
+    async fn failures() {
+        // Failing before headers produces a trailers-only response.
+        let (writer, response) = response_writer(1);
+        writer.fail(Status::unauthenticated("no token")).await;
+        let msgs: Vec<_> = response.await.into_inner().collect().await;
+        assert_eq!(msgs.len(), 1);
+        assert_eq!(msgs[0].as_ref().unwrap_err().code(), Code::Unauthenticated);
+
+        let (mut writer, response) = response_writer(2);
+        tokio::spawn(async move {
+            writer.send(Box::new(1)).await.unwrap();
+            writer.fail(Status::aborted("conflict")).await;
+        });
+        let msgs: Vec<_> = response.await.into_inner().collect().await;
+        assert_eq!(msgs.len(), 2);
+        assert_eq!(msgs[1].as_ref().unwrap_err().code(), Code::Aborted);
+    }
+}
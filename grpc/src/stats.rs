@@ -0,0 +1,519 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! Hooks observing the lifecycle of RPCs.
+//!
+//! A [`StatsHandler`] configured on a channel (via
+//! [`ChannelOptions::stats_handler`](crate::client::ChannelOptions::stats_handler))
+//! or a server (via [`Server::add_stats_handler`](crate::server::Server::add_stats_handler))
+//! is notified of an [`RpcEvent`] at each step of every call.  On clients,
+//! each attempt of a call, including transparent retries, is reported
+//! separately.  Handlers are the basis for metrics and tracing plugins, and
+//! for custom accounting.
+//!
+//! Handlers configured on a channel are also notified of a
+//! [`ConnectionEvent`] at each step of the lifecycle of its subchannels:
+//! connection attempts and their outcome, lost connections, and connectivity
+//! state changes along with the time spent in the previous state.
+
+use std::{
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+use tokio_stream::{Stream, StreamExt};
+use tonic::{metadata::MetadataMap, Code, Status};
+
+use crate::client::error::{ConnectError, DisconnectReason};
+use crate::client::labels::SubchannelLabels;
+use crate::client::ConnectivityState;
+use crate::server::RateLimitStats;
+use crate::service::{Message, Request, Response};
+
+/// Observes the lifecycle of RPCs.  Handlers are called synchronously on the
+/// RPC's path, so they must not block.
+pub trait StatsHandler: Send + Sync {
+    /// Called for each event of the RPC described by info.
+    fn handle_rpc(&self, info: &RpcInfo, event: &RpcEvent<'_>);
+
+    /// Called for each event of the subchannel described by info.  Only
+    /// called on clients.
+    fn handle_connection(&self, _info: &ConnectionInfo, _event: &ConnectionEvent<'_>) {}
+}
+
+/// Describes the RPC, or the attempt of an RPC, an event belongs to.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct RpcInfo {
+    /// The full method name, e.g. "/pkg.Service/Method".
+    pub method: String,
+    /// Whether the event was observed by a client rather than a server.
+    pub is_client: bool,
+    /// The number of the attempt, starting at 1.  Always 1 on servers.
+    pub attempt: u32,
+}
+
+/// A step in the lifecycle of an RPC (or an attempt on clients).  Begin is
+/// always first and End always last.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum RpcEvent<'a> {
+    /// The RPC started.
+    Begin,
+    /// Headers were sent.
+    OutHeader(&'a MetadataMap),
+    /// Headers were received.
+    InHeader(&'a MetadataMap),
+    /// A message was sent.  Its size is only known for serialized messages.
+    OutPayload { size: Option<usize> },
+    /// A message was received.  Its size is only known for serialized
+    /// messages.
+    InPayload { size: Option<usize> },
+    /// The RPC was checked against the rate limits of a server, and admitted
+    /// or rejected.  stats counts the calls checked so far for the RPC's
+    /// method, or for all the methods without their own limit.  Only reported
+    /// on servers with rate limits.
+    RateLimit {
+        admitted: bool,
+        stats: RateLimitStats,
+    },
+    /// The RPC ended with status, which is OK if it succeeded.
+    End {
+        status: &'a Status,
+        duration: Duration,
+    },
+}
+
+/// Describes the subchannel a connection event belongs to.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ConnectionInfo {
+    /// The target of the channel owning the subchannel.
+    pub target: String,
+    /// The address the subchannel connects to.
+    pub address: String,
+    /// The labels of the subchannel.
+    pub labels: SubchannelLabels,
+}
+
+/// A step in the lifecycle of a subchannel.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ConnectionEvent<'a> {
+    /// A connection attempt started.
+    ConnectStart,
+    /// A connection attempt ended, with error if it failed.  duration covers
+    /// the whole attempt, including any handshakes.
+    ConnectEnd {
+        error: Option<&'a ConnectError>,
+        duration: Duration,
+    },
+    /// An established connection was lost.
+    Disconnected(&'a DisconnectReason),
+    /// The subchannel moved from one connectivity state to another after
+    /// spending time_in_state in the former.
+    StateChange {
+        from: ConnectivityState,
+        to: ConnectivityState,
+        time_in_state: Duration,
+    },
+}
+
+/// Returns the size of msg if it is serialized.
+pub(crate) fn message_size(msg: &dyn Message) -> Option<usize> {
+    (msg as &dyn std::any::Any)
+        .downcast_ref::<Bytes>()
+        .map(Bytes::len)
+}
+
+/// Reports the events of one RPC (or attempt) to the handlers of a channel
+/// or server.  Reports End with CANCELLED if dropped before the RPC ended.
+pub(crate) struct RpcStats {
+    handlers: Arc<[Arc<dyn StatsHandler>]>,
+    info: RpcInfo,
+    start: Instant,
+    ended: AtomicBool,
+}
+
+impl RpcStats {
+    /// Reports the beginning of an RPC, or returns None if there are no
+    /// handlers.
+    pub(crate) fn begin(
+        handlers: &Arc<[Arc<dyn StatsHandler>]>,
+        info: RpcInfo,
+    ) -> Option<Arc<Self>> {
+        if handlers.is_empty() {
+            return None;
+        }
+        let stats = Arc::new(Self {
+            handlers: handlers.clone(),
+            info,
+            start: Instant::now(),
+            ended: AtomicBool::new(false),
+        });
+        stats.emit(&RpcEvent::Begin);
+        Some(stats)
+    }
+
+    pub(crate) fn emit(&self, event: &RpcEvent<'_>) {
+        for handler in self.handlers.iter() {
+            handler.handle_rpc(&self.info, event);
+        }
+    }
+
+    fn end(&self, status: &Status) {
+        if !self.ended.swap(true, Ordering::AcqRel) {
+            self.emit(&RpcEvent::End {
+                status,
+                duration: self.start.elapsed(),
+            });
+        }
+    }
+
+    /// Reports the headers of request, and each of its messages, as sent if
+    /// outgoing or received otherwise.
+    pub(crate) fn request(self: &Arc<Self>, request: Request, outgoing: bool) -> Request {
+        self.emit(&if outgoing {
+            RpcEvent::OutHeader(request.metadata())
+        } else {
+            RpcEvent::InHeader(request.metadata())
+        });
+        let stats = self.clone();
+        request.map(|inner| {
+            Box::pin(inner.map(move |msg| {
+                let size = message_size(msg.as_ref());
+                stats.emit(&if outgoing {
+                    RpcEvent::OutPayload { size }
+                } else {
+                    RpcEvent::InPayload { size }
+                });
+                msg
+            })) as Pin<Box<dyn Stream<Item = Box<dyn Message>> + Send + Sync>>
+        })
+    }
+
+    /// Reports the headers of response and each of its messages, as sent if
+    /// outgoing or received otherwise, and then the end of the RPC.
+    pub(crate) fn response(self: &Arc<Self>, response: Response, outgoing: bool) -> Response {
+        self.emit(&if outgoing {
+            RpcEvent::OutHeader(response.metadata())
+        } else {
+            RpcEvent::InHeader(response.metadata())
+        });
+        let stats = self.clone();
+        response.map(|inner| {
+            Box::pin(StatsStream {
+                inner,
+                stats,
+                outgoing,
+            }) as Pin<Box<dyn Stream<Item = Result<Box<dyn Message>, Status>> + Send>>
+        })
+    }
+}
+
+impl Drop for RpcStats {
+    fn drop(&mut self) {
+        self.end(&Status::cancelled("RPC abandoned before completion"));
+    }
+}
+
+pin_project_lite::pin_project! {
+    // Reports the messages of a response stream, and the end of the RPC when
+    // the stream completes.
+    struct StatsStream<S> {
+        #[pin]
+        inner: S,
+        stats: Arc<RpcStats>,
+        outgoing: bool,
+    }
+}
+
+impl<S: Stream<Item = Result<Box<dyn Message>, Status>>> Stream for StatsStream<S> {
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let item = this.inner.poll_next(cx);
+        match &item {
+            Poll::Ready(Some(Ok(msg))) => {
+                let size = message_size(msg.as_ref());
+                this.stats.emit(&if *this.outgoing {
+                    RpcEvent::OutPayload { size }
+                } else {
+                    RpcEvent::InPayload { size }
+                });
+            }
+            Poll::Ready(Some(Err(status))) => this.stats.end(status),
+            Poll::Ready(None) => this.stats.end(&Status::new(Code::Ok, "")),
+            Poll::Pending => {}
+        }
+        item
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        any::Any,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use bytes::Bytes;
+    use tokio_stream::StreamExt;
+    use tonic::{async_trait, Code};
+
+    use super::{ConnectionEvent, ConnectionInfo, RpcEvent, RpcInfo, StatsHandler};
+    use crate::{
+        client::{Channel, ChannelOptions, ConnectivityState},
+        inmemory,
+        server::Server,
+        service::{Message, Request, Response, Service},
+    };
+
+    #[derive(Default)]
+    struct Recorder {
+        events: Mutex<Vec<String>>,
+    }
+
+    impl StatsHandler for Recorder {
+        fn handle_rpc(&self, info: &RpcInfo, event: &RpcEvent<'_>) {
+            let event = match event {
+                RpcEvent::Begin => "begin".to_string(),
+                RpcEvent::OutHeader(_) => "out header".to_string(),
+                RpcEvent::InHeader(_) => "in header".to_string(),
+                RpcEvent::OutPayload { size } => format!("out payload {size:?}"),
+                RpcEvent::InPayload { size } => format!("in payload {size:?}"),
+                RpcEvent::RateLimit { admitted, stats } => {
+                    format!(
+                        "rate limit {admitted} {}/{}",
+                        stats.admitted, stats.rejected
+                    )
+                }
+                RpcEvent::End { status, .. } => format!("end {:?}", status.code()),
+            };
+            let side = if info.is_client { "client" } else { "server" };
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("{side} {} #{}: {event}", info.method, info.attempt));
+        }
+
+        fn handle_connection(&self, info: &ConnectionInfo, event: &ConnectionEvent<'_>) {
+            let event = match event {
+                ConnectionEvent::ConnectStart => "connect start".to_string(),
+                ConnectionEvent::ConnectEnd { error: None, .. } => "connect ok".to_string(),
+                ConnectionEvent::ConnectEnd {
+                    error: Some(err), ..
+                } => format!("connect failed {:?}", err.kind()),
+                ConnectionEvent::Disconnected(reason) => format!("disconnected {reason}"),
+                ConnectionEvent::StateChange { from, to, .. } => format!("{from:?} -> {to:?}"),
+            };
+            self.events.lock().unwrap().push(format!(
+                "connection {} {}: {event}",
+                info.target, info.address
+            ));
+        }
+    }
+
+    impl Recorder {
+        fn events(&self, side: &str) -> Vec<String> {
+            self.events
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|e| e.starts_with(side))
+                .cloned()
+                .collect()
+        }
+
+        async fn wait_for_events(&self, side: &str, n: usize) {
+            tokio::time::timeout(Duration::from_secs(5), async {
+                while self.events(side).len() < n {
+                    tokio::time::sleep(Duration::from_millis(1)).await;
+                }
+            })
+            .await
+            .unwrap();
+        }
+    }
+
+    struct Echo {}
+
+    #[async_trait]
+    impl Service for Echo {
+        async fn call(&self, _: String, request: Request) -> Response {
+            Response::new(Box::pin(request.into_inner().map(Ok)))
+        }
+    }
+
+    #[tokio::test]
+    async fn reports_rpc_lifecycle() {
+        inmemory::reg();
+        let lis = inmemory::Listener::new();
+        let recorder = Arc::new(Recorder::default());
+        let mut srv = Server::new();
+        srv.set_handler(Echo {});
+        srv.add_stats_handler(recorder.clone());
+        let serve = tokio::spawn({
+            let lis = lis.clone();
+            async move { srv.serve(&lis).await }
+        });
+
+        let chan = Channel::new(
+            lis.target().as_str(),
+            None,
+            ChannelOptions::default().stats_handler(recorder.clone()),
+        );
+        let msg: Box<dyn Message> = Box::new(Bytes::from_static(b"hello"));
+        let req = Request::new(Box::pin(tokio_stream::once(msg)));
+        let mut res = chan.call("/svc/Echo".to_string(), req).await.into_inner();
+        let msg = res.next().await.unwrap().unwrap();
+        assert_eq!(
+            (msg.as_ref() as &dyn Any).downcast_ref::<Bytes>().unwrap(),
+            "hello"
+        );
+        assert!(res.next().await.is_none());
+
+        assert_eq!(
+            recorder.events("client"),
+            [
+                "begin",
+                "out header",
+                "in header",
+                "out payload Some(5)",
+                "in payload Some(5)",
+                "end Ok",
+            ]
+            .map(|e| format!("client /svc/Echo #1: {e}"))
+        );
+        assert_eq!(
+            recorder.events("server"),
+            [
+                "begin",
+                "in header",
+                "out header",
+                "in payload Some(5)",
+                "out payload Some(5)",
+                "end Ok",
+            ]
+            .map(|e| format!("server /svc/Echo #1: {e}"))
+        );
+        lis.close().await;
+        serve.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn reports_connection_lifecycle() {
+        inmemory::reg();
+        let lis = inmemory::Listener::new();
+        let mut srv = Server::new();
+        srv.set_handler(Echo {});
+        let serve = tokio::spawn({
+            let lis = lis.clone();
+            async move { srv.serve(&lis).await }
+        });
+
+        let recorder = Arc::new(Recorder::default());
+        let target = lis.target();
+        let chan = Channel::new(
+            target.as_str(),
+            None,
+            ChannelOptions::default().stats_handler(recorder.clone()),
+        );
+        chan.connect().await.unwrap();
+        recorder.wait_for_events("connection", 4).await;
+        let address = target.trim_start_matches("inmemory:///");
+        assert_eq!(
+            recorder.events("connection"),
+            [
+                "connect start",
+                "Idle -> Connecting",
+                "connect ok",
+                "Connecting -> Ready",
+            ]
+            .map(|e| format!("connection {target} {address}: {e}"))
+        );
+        lis.close().await;
+        serve.await.unwrap();
+
+        // Connections to listeners which do not exist are refused.
+        let recorder = Arc::new(Recorder::default());
+        let chan = Channel::new(
+            "inmemory:///missing",
+            None,
+            ChannelOptions::default().stats_handler(recorder.clone()),
+        );
+        assert!(chan.connect().await.is_err());
+        recorder.wait_for_events("connection", 4).await;
+        assert_eq!(
+            recorder.events("connection")[..4],
+            [
+                "connect start",
+                "Idle -> Connecting",
+                "connect failed Refused",
+                "Connecting -> TransientFailure",
+            ]
+            .map(|e| format!("connection inmemory:///missing missing: {e}"))
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_abandoned_rpcs_as_cancelled() {
+        let recorder: Arc<Recorder> = Arc::default();
+        let handlers: Arc<[Arc<dyn StatsHandler>]> = Arc::new([recorder.clone() as _]);
+        let stats = super::RpcStats::begin(
+            &handlers,
+            RpcInfo {
+                method: "/svc/Watch".to_string(),
+                is_client: true,
+                attempt: 2,
+            },
+        );
+        drop(stats);
+        assert_eq!(
+            recorder.events("client"),
+            [
+                "client /svc/Watch #2: begin",
+                "client /svc/Watch #2: end Cancelled"
+            ]
+        );
+        let no_handlers: Arc<[Arc<dyn StatsHandler>]> = Arc::new([]);
+        assert!(super::RpcStats::begin(
+            &no_handlers,
+            RpcInfo {
+                method: String::new(),
+                is_client: true,
+                attempt: 1,
+            }
+        )
+        .is_none());
+    }
+}
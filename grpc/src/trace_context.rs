@@ -0,0 +1,357 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! Propagation of trace context across RPCs.
+//!
+//! Channels configured with a [`TracePropagation`] add the [`TraceContext`]
+//! of each call to its request metadata, and servers configured with one
+//! extract it into the extensions of the requests they handle.  The context
+//! of a call is the one inserted into its request's extensions, or otherwise
+//! the one returned by the
+//! [`current_context`](TracePropagation::current_context) hook, which
+//! applications bridge to their tracing library, e.g. by returning the
+//! context of the OpenTelemetry span of `tracing::Span::current()`.
+//!
+//! By default, the context is propagated in both the W3C `traceparent`
+//! header and gRPC's binary `grpc-trace-bin` header.
+
+use std::{
+    fmt::{self, Debug, Display, Formatter},
+    sync::Arc,
+};
+
+use tonic::metadata::{MetadataMap, MetadataValue};
+
+use crate::service::Request;
+
+/// The identity of a span, as propagated to the servers of the calls made
+/// within it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct TraceContext {
+    /// The ID of the trace the span belongs to; never all zeros.
+    pub trace_id: [u8; 16],
+    /// The ID of the span; never all zeros.
+    pub span_id: [u8; 8],
+    /// Whether the span is sampled.
+    pub sampled: bool,
+}
+
+impl TraceContext {
+    /// Returns a context with the given IDs, or None if either is all zeros,
+    /// which is invalid.
+    pub fn new(trace_id: [u8; 16], span_id: [u8; 8], sampled: bool) -> Option<Self> {
+        if trace_id == [0; 16] || span_id == [0; 8] {
+            return None;
+        }
+        Some(Self {
+            trace_id,
+            span_id,
+            sampled,
+        })
+    }
+
+    /// Returns the context attached to request, if any.
+    pub fn from_request(request: &Request) -> Option<Self> {
+        request.extensions().get::<Self>().copied()
+    }
+}
+
+impl Display for TraceContext {
+    // Formats the context as a W3C traceparent header value.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "00-")?;
+        for b in self.trace_id {
+            write!(f, "{b:02x}")?;
+        }
+        write!(f, "-")?;
+        for b in self.span_id {
+            write!(f, "{b:02x}")?;
+        }
+        write!(f, "-{:02x}", self.sampled as u8)
+    }
+}
+
+/// Writes trace contexts to, and reads them from, request metadata in one
+/// format.
+pub trait TracePropagator: Send + Sync {
+    /// Adds context to metadata, replacing any context already present in
+    /// this propagator's format.
+    fn inject(&self, context: &TraceContext, metadata: &mut MetadataMap);
+
+    /// Returns the context in metadata, or None if it has none in this
+    /// propagator's format or it is malformed.
+    fn extract(&self, metadata: &MetadataMap) -> Option<TraceContext>;
+}
+
+/// Propagates trace contexts in the W3C Trace Context `traceparent` header.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct W3cPropagator;
+
+/// The header carrying W3C trace contexts.
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+
+impl TracePropagator for W3cPropagator {
+    fn inject(&self, context: &TraceContext, metadata: &mut MetadataMap) {
+        // The header consists of hex digits and dashes, which are always
+        // valid metadata.
+        let value = context.to_string().parse().unwrap();
+        metadata.insert(TRACEPARENT_HEADER, value);
+    }
+
+    fn extract(&self, metadata: &MetadataMap) -> Option<TraceContext> {
+        parse_traceparent(metadata.get(TRACEPARENT_HEADER)?.to_str().ok()?)
+    }
+}
+
+// Parses a traceparent header value.  Versions after 00 may append fields,
+// which are ignored.
+fn parse_traceparent(value: &str) -> Option<TraceContext> {
+    let mut fields = value.split('-');
+    let version = hex::<1>(fields.next()?)?;
+    let trace_id = hex::<16>(fields.next()?)?;
+    let span_id = hex::<8>(fields.next()?)?;
+    let flags = hex::<1>(fields.next()?)?;
+    if version == [0xff] || (version == [0] && fields.next().is_some()) {
+        return None;
+    }
+    TraceContext::new(trace_id, span_id, flags[0] & 1 == 1)
+}
+
+// Decodes exactly N bytes from lowercase hex digits.
+fn hex<const N: usize>(s: &str) -> Option<[u8; N]> {
+    if s.len() != 2 * N || !s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')) {
+        return None;
+    }
+    let mut out = [0; N];
+    for (i, b) in out.iter_mut().enumerate() {
+        *b = u8::from_str_radix(&s[2 * i..2 * i + 2], 16).ok()?;
+    }
+    Some(out)
+}
+
+/// Propagates trace contexts in gRPC's binary `grpc-trace-bin` header, in
+/// the OpenCensus binary format.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BinaryPropagator;
+
+/// The header carrying binary trace contexts.
+pub const GRPC_TRACE_BIN_HEADER: &str = "grpc-trace-bin";
+
+// The field IDs of the binary format, each of which precedes its value.
+const TRACE_ID_FIELD: u8 = 0;
+const SPAN_ID_FIELD: u8 = 1;
+const OPTIONS_FIELD: u8 = 2;
+
+impl TracePropagator for BinaryPropagator {
+    fn inject(&self, context: &TraceContext, metadata: &mut MetadataMap) {
+        let mut value = Vec::with_capacity(29);
+        value.extend([0, TRACE_ID_FIELD]);
+        value.extend(context.trace_id);
+        value.push(SPAN_ID_FIELD);
+        value.extend(context.span_id);
+        value.extend([OPTIONS_FIELD, context.sampled as u8]);
+        metadata.insert_bin(GRPC_TRACE_BIN_HEADER, MetadataValue::from_bytes(&value));
+    }
+
+    fn extract(&self, metadata: &MetadataMap) -> Option<TraceContext> {
+        let value = metadata.get_bin(GRPC_TRACE_BIN_HEADER)?.to_bytes().ok()?;
+        let (&version, mut rest) = value.split_first()?;
+        if version != 0 {
+            return None;
+        }
+        let mut trace_id = None;
+        let mut span_id = None;
+        let mut sampled = false;
+        // Fields appear in increasing order of ID; parsing stops at unknown
+        // fields, which later versions of the format may append.
+        while let Some((&field, value)) = rest.split_first() {
+            match field {
+                TRACE_ID_FIELD => {
+                    let (id, tail) = value.split_first_chunk::<16>()?;
+                    trace_id = Some(*id);
+                    rest = tail;
+                }
+                SPAN_ID_FIELD => {
+                    let (id, tail) = value.split_first_chunk::<8>()?;
+                    span_id = Some(*id);
+                    rest = tail;
+                }
+                OPTIONS_FIELD => {
+                    let (options, tail) = value.split_first()?;
+                    sampled = options & 1 == 1;
+                    rest = tail;
+                }
+                _ => break,
+            }
+        }
+        TraceContext::new(trace_id?, span_id?, sampled)
+    }
+}
+
+/// Configures the propagation of trace contexts by a channel or server.
+#[derive(Clone)]
+#[non_exhaustive]
+pub struct TracePropagation {
+    /// The formats contexts are propagated in.  Channels inject the context
+    /// of a call in every format; servers extract it from the first format
+    /// present in the request.
+    pub propagators: Vec<Arc<dyn TracePropagator>>,
+    /// Returns the context of the current span, used by channels for calls
+    /// without a [`TraceContext`] in their request's extensions.
+    pub current_context: Option<Arc<dyn Fn() -> Option<TraceContext> + Send + Sync>>,
+}
+
+impl Default for TracePropagation {
+    fn default() -> Self {
+        Self {
+            propagators: vec![Arc::new(W3cPropagator), Arc::new(BinaryPropagator)],
+            current_context: None,
+        }
+    }
+}
+
+impl Debug for TracePropagation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TracePropagation")
+            .field("propagators", &self.propagators.len())
+            .field("current_context", &self.current_context.is_some())
+            .finish()
+    }
+}
+
+impl TracePropagation {
+    pub fn propagators(self, propagators: Vec<Arc<dyn TracePropagator>>) -> Self {
+        Self {
+            propagators,
+            ..self
+        }
+    }
+
+    pub fn current_context(
+        self,
+        current_context: impl Fn() -> Option<TraceContext> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            current_context: Some(Arc::new(current_context)),
+            ..self
+        }
+    }
+
+    /// Adds the context of the call to the metadata of request.
+    pub(crate) fn inject(&self, request: &mut Request) {
+        let context = TraceContext::from_request(request)
+            .or_else(|| self.current_context.as_ref().and_then(|current| current()));
+        if let Some(context) = context {
+            for propagator in &self.propagators {
+                propagator.inject(&context, request.metadata_mut());
+            }
+        }
+    }
+
+    /// Attaches the context in the metadata of request, if any, to its
+    /// extensions.
+    pub(crate) fn extract(&self, request: &mut Request) {
+        let context = self
+            .propagators
+            .iter()
+            .find_map(|propagator| propagator.extract(request.metadata()));
+        if let Some(context) = context {
+            request.extensions_mut().insert(context);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tonic::metadata::{MetadataMap, MetadataValue};
+
+    use super::{
+        BinaryPropagator, TraceContext, TracePropagator, W3cPropagator, GRPC_TRACE_BIN_HEADER,
+        TRACEPARENT_HEADER,
+    };
+
+    fn context() -> TraceContext {
+        TraceContext::new(
+            *b"\x4b\xf9\x2f\x35\x77\xb3\x4d\xa6\xa3\xce\x92\x9d\x0e\x0e\x47\x36",
+            *b"\x00\xf0\x67\xaa\x0b\xa9\x02\xb7",
+            true,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn traceparent_round_trips() {
+        let mut metadata = MetadataMap::new();
+        W3cPropagator.inject(&context(), &mut metadata);
+        assert_eq!(
+            metadata.get(TRACEPARENT_HEADER).unwrap(),
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+        );
+        assert_eq!(W3cPropagator.extract(&metadata), Some(context()));
+    }
+
+    #[test]
+    fn malformed_traceparents_are_ignored() {
+        let valid = "4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        let extract = |value: &str| {
+            let mut metadata = MetadataMap::new();
+            metadata.insert(TRACEPARENT_HEADER, value.parse().unwrap());
+            W3cPropagator.extract(&metadata)
+        };
+        assert_eq!(extract(&format!("00-{valid}")), Some(context()));
+        // Later versions may append fields.
+        assert_eq!(extract(&format!("01-{valid}-extra")), Some(context()));
+        for invalid in [
+            format!("ff-{valid}"),
+            format!("00-{valid}-extra"),
+            format!("00-{}", valid.to_uppercase()),
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01".to_string(),
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01".to_string(),
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7".to_string(),
+        ] {
+            assert_eq!(extract(&invalid), None, "{invalid}");
+        }
+    }
+
+    #[test]
+    fn grpc_trace_bin_round_trips() {
+        let mut metadata = MetadataMap::new();
+        BinaryPropagator.inject(&context(), &mut metadata);
+        let value = metadata.get_bin(GRPC_TRACE_BIN_HEADER).unwrap();
+        assert_eq!(value.to_bytes().unwrap().len(), 29);
+        assert_eq!(BinaryPropagator.extract(&metadata), Some(context()));
+
+        // Unknown trailing fields are ignored, but truncated ones are not.
+        let mut bytes = value.to_bytes().unwrap().to_vec();
+        bytes.extend([3, 0xaa]);
+        metadata.insert_bin(GRPC_TRACE_BIN_HEADER, MetadataValue::from_bytes(&bytes));
+        assert_eq!(BinaryPropagator.extract(&metadata), Some(context()));
+        metadata.insert_bin(
+            GRPC_TRACE_BIN_HEADER,
+            MetadataValue::from_bytes(&bytes[..20]),
+        );
+        assert_eq!(BinaryPropagator.extract(&metadata), None);
+    }
+}
@@ -0,0 +1,113 @@
+//! Tests that channels release everything they created once dropped.  Live
+//! instances are counted per process, so the tests in this file take turns.
+
+#![cfg(feature = "_leak-detector")]
+
+use std::time::Duration;
+
+use bytes::Bytes;
+use grpc::client::{Channel, ChannelOptions};
+use grpc::inmemory;
+use grpc::leak_detector::assert_no_leaks;
+use grpc::server::{tcp::TcpListener, Server};
+use grpc::service::{Message, Request, Response, Service};
+use tokio::sync::Mutex;
+use tokio_stream::StreamExt;
+use tonic::async_trait;
+
+const LEAK_TIMEOUT: Duration = Duration::from_secs(5);
+
+static SERIAL: Mutex<()> = Mutex::const_new(());
+
+struct Echo {}
+
+#[async_trait]
+impl Service for Echo {
+    async fn call(&self, method: String, request: Request) -> Response {
+        if method == "/test/Endless" {
+            return Response::new(Box::pin(tokio_stream::pending()));
+        }
+        Response::new(Box::pin(request.into_inner().map(Ok)))
+    }
+}
+
+fn request(data: &'static [u8]) -> Request {
+    let msg: Box<dyn Message> = Box::new(Bytes::from_static(data));
+    Request::new(Box::pin(tokio_stream::once(msg)))
+}
+
+async fn echo(channel: &Channel) {
+    let response = channel.call("/test/Echo".to_string(), request(b"hi")).await;
+    let items: Vec<_> = response.into_inner().collect().await;
+    assert_eq!(items.len(), 1);
+    assert!(items[0].is_ok());
+}
+
+#[tokio::test]
+async fn inmemory_channel_does_not_leak() {
+    let _serial = SERIAL.lock().await;
+    inmemory::reg();
+    let lis = inmemory::Listener::new();
+    let mut server = Server::new();
+    server.set_handler(Echo {});
+    let serve = tokio::spawn({
+        let lis = lis.clone();
+        async move { server.serve(&lis).await }
+    });
+
+    let channel = Channel::new(&lis.target(), None, ChannelOptions::default());
+    for _ in 0..3 {
+        echo(&channel).await;
+    }
+    drop(channel);
+    assert_no_leaks(LEAK_TIMEOUT).await;
+
+    serve.abort();
+    lis.close().await;
+}
+
+#[tokio::test]
+async fn dns_channel_does_not_leak() {
+    let _serial = SERIAL.lock().await;
+    grpc::client::reg();
+    let lis = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+    let target = format!("dns:///{}", lis.local_addr());
+    let mut server = Server::new();
+    server.set_handler(Echo {});
+    let serve = tokio::spawn(async move { server.serve(&lis).await });
+
+    let channel = Channel::new(&target, None, ChannelOptions::default());
+    for _ in 0..3 {
+        echo(&channel).await;
+    }
+    drop(channel);
+    assert_no_leaks(LEAK_TIMEOUT).await;
+
+    serve.abort();
+}
+
+#[tokio::test]
+async fn channel_dropped_during_call_does_not_leak() {
+    let _serial = SERIAL.lock().await;
+    inmemory::reg();
+    let lis = inmemory::Listener::new();
+    let mut server = Server::new();
+    server.set_handler(Echo {});
+    let serve = tokio::spawn({
+        let lis = lis.clone();
+        async move { server.serve(&lis).await }
+    });
+
+    // The call outlives the channel it was made on.
+    let channel = Channel::new(&lis.target(), None, ChannelOptions::default());
+    echo(&channel).await;
+    let response = channel
+        .call("/test/Endless".to_string(), request(b"hi"))
+        .await;
+    drop(channel);
+    drop(response);
+    assert_no_leaks(LEAK_TIMEOUT).await;
+
+    serve.abort();
+    lis.close().await;
+}
@@ -0,0 +1,259 @@
+//! End-to-end tests of streaming calls over the in-memory transport, pinning
+//! down how the request and response streams of each side behave.
+
+use std::any::Any;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use grpc::client::{Channel, ChannelOptions};
+use grpc::inmemory;
+use grpc::server::Server;
+use grpc::service::{Message, Request, Response, Service};
+use tokio::sync::{mpsc, oneshot};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio_stream::{Stream, StreamExt};
+use tonic::{async_trait, Code, Status};
+
+type ResponseStream = Pin<Box<dyn Stream<Item = Result<Box<dyn Message>, Status>> + Send>>;
+
+fn msg(data: impl Into<String>) -> Box<dyn Message> {
+    Box::new(Bytes::from(data.into()))
+}
+
+fn text(msg: Box<dyn Message>) -> String {
+    let bytes = (msg as Box<dyn Any>).downcast::<Bytes>().unwrap();
+    String::from_utf8(bytes.to_vec()).unwrap()
+}
+
+// Sends on its channel when dropped.
+struct DropSignal(Option<oneshot::Sender<()>>);
+
+impl Drop for DropSignal {
+    fn drop(&mut self) {
+        let _ = self.0.take().unwrap().send(());
+    }
+}
+
+struct Handler {
+    // Signals the end of each /test/Endless response stream.
+    endless_dropped: mpsc::UnboundedSender<oneshot::Receiver<()>>,
+}
+
+#[async_trait]
+impl Service for Handler {
+    async fn call(&self, method: String, request: Request) -> Response {
+        let mut requests = request.into_inner();
+        let output: ResponseStream = match method.as_str() {
+            // Client streaming: replies with the requests joined together.
+            "/test/Join" => Box::pin(async_stream::try_stream! {
+                let mut joined = Vec::new();
+                while let Some(req) = requests.next().await {
+                    joined.push(text(req));
+                }
+                yield msg(joined.join(","));
+            }),
+            // Server streaming: replies with as many messages as the first
+            // request asks for, ignoring any later requests.
+            "/test/Count" => Box::pin(async_stream::try_stream! {
+                let n: usize = text(requests.next().await.unwrap()).parse().unwrap();
+                for i in 0..n {
+                    yield msg(i.to_string());
+                }
+            }),
+            // Bidi streaming: replies to each request as it arrives, and
+            // reports how many it received once the client is done.
+            "/test/Echo" => Box::pin(async_stream::try_stream! {
+                let mut received = 0;
+                while let Some(req) = requests.next().await {
+                    received += 1;
+                    yield msg(format!("echo:{}", text(req)));
+                }
+                yield msg(format!("done:{received}"));
+            }),
+            // Fails without reading the requests.
+            "/test/Reject" => Box::pin(tokio_stream::once(Err(Status::failed_precondition(
+                "rejected",
+            )))),
+            // Replies forever, signalling when the response stream is dropped.
+            "/test/Endless" => {
+                let (tx, rx) = oneshot::channel();
+                let _ = self.endless_dropped.send(rx);
+                let signal = DropSignal(Some(tx));
+                Box::pin(async_stream::try_stream! {
+                    let _signal = signal;
+                    for i in 0.. {
+                        yield msg(i.to_string());
+                        tokio::task::yield_now().await;
+                    }
+                })
+            }
+            _ => Box::pin(tokio_stream::once(Err(Status::unimplemented(method)))),
+        };
+        Response::new(output)
+    }
+}
+
+struct Fixture {
+    channel: Channel,
+    endless_dropped: mpsc::UnboundedReceiver<oneshot::Receiver<()>>,
+    lis: Arc<inmemory::Listener>,
+    server: Arc<Server>,
+    serve: tokio::task::JoinHandle<()>,
+}
+
+impl Fixture {
+    async fn new() -> Self {
+        inmemory::reg();
+        let (tx, endless_dropped) = mpsc::unbounded_channel();
+        let lis = inmemory::Listener::new();
+        let mut server = Server::new();
+        server.set_handler(Handler {
+            endless_dropped: tx,
+        });
+        let server = Arc::new(server);
+        let serve = tokio::spawn({
+            let server = server.clone();
+            let lis = lis.clone();
+            async move { server.serve(&lis).await }
+        });
+        let channel = Channel::new(&lis.target(), None, ChannelOptions::default());
+        Self {
+            channel,
+            endless_dropped,
+            lis,
+            server,
+            serve,
+        }
+    }
+
+    // Starts a call whose requests are sent through the returned sender.  The
+    // request stream ends once the sender is dropped.
+    async fn start(
+        &self,
+        method: &str,
+    ) -> (mpsc::UnboundedSender<Box<dyn Message>>, ResponseStream) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let request = Request::new(Box::pin(UnboundedReceiverStream::new(rx)));
+        let response = self.channel.call(method.to_string(), request).await;
+        (tx, response.into_inner())
+    }
+
+    async fn stop(self) {
+        self.server.graceful_shutdown().await;
+        self.serve.await.unwrap();
+        self.lis.close().await;
+    }
+}
+
+// Collects the messages of a response, and the status it ended with.
+async fn collect(mut response: ResponseStream) -> (Vec<String>, Code) {
+    let mut messages = Vec::new();
+    while let Some(item) = response.next().await {
+        match item {
+            Ok(m) => messages.push(text(m)),
+            Err(status) => return (messages, status.code()),
+        }
+    }
+    (messages, Code::Ok)
+}
+
+#[tokio::test]
+async fn client_streaming() {
+    let f = Fixture::new().await;
+    let (tx, response) = f.start("/test/Join").await;
+    for m in ["a", "b", "c"] {
+        tx.send(msg(m)).unwrap();
+    }
+    drop(tx);
+    assert_eq!(
+        collect(response).await,
+        (vec!["a,b,c".to_string()], Code::Ok)
+    );
+    f.stop().await;
+}
+
+#[tokio::test]
+async fn server_streaming() {
+    let f = Fixture::new().await;
+    let (tx, response) = f.start("/test/Count").await;
+    tx.send(msg("3")).unwrap();
+    // The server may reply before the client finishes sending.
+    let (messages, code) = collect(response).await;
+    assert_eq!(messages, ["0", "1", "2"]);
+    assert_eq!(code, Code::Ok);
+    drop(tx);
+    f.stop().await;
+}
+
+#[tokio::test]
+async fn bidi_streaming_interleaves_messages() {
+    let f = Fixture::new().await;
+    let (tx, mut response) = f.start("/test/Echo").await;
+    // Each reply is received before the next request is sent.
+    for m in ["x", "y"] {
+        tx.send(msg(m)).unwrap();
+        let reply = response.next().await.unwrap().unwrap();
+        assert_eq!(text(reply), format!("echo:{m}"));
+    }
+    drop(tx);
+    assert_eq!(
+        collect(response).await,
+        (vec!["done:2".to_string()], Code::Ok)
+    );
+    f.stop().await;
+}
+
+#[tokio::test]
+async fn early_client_close_ends_request_stream() {
+    let f = Fixture::new().await;
+    let (tx, response) = f.start("/test/Echo").await;
+    // Closing the request stream without sending anything still lets the
+    // server reply.
+    drop(tx);
+    assert_eq!(
+        collect(response).await,
+        (vec!["done:0".to_string()], Code::Ok)
+    );
+    f.stop().await;
+}
+
+#[tokio::test]
+async fn early_server_close_drops_request_stream() {
+    let f = Fixture::new().await;
+    let (tx, response) = f.start("/test/Reject").await;
+    assert_eq!(collect(response).await, (vec![], Code::FailedPrecondition));
+    // The server dropped the requests it never read.
+    tokio::time::timeout(Duration::from_secs(5), tx.closed())
+        .await
+        .unwrap();
+    assert!(tx.send(msg("late")).is_err());
+    f.stop().await;
+}
+
+#[tokio::test]
+async fn cancellation_mid_stream_drops_server_stream() {
+    let mut f = Fixture::new().await;
+    let (_tx, mut response) = f.start("/test/Endless").await;
+    for want in ["0", "1"] {
+        let reply = response.next().await.unwrap().unwrap();
+        assert_eq!(text(reply), want);
+    }
+    let dropped = f.endless_dropped.recv().await.unwrap();
+    // Dropping the response cancels the call, dropping the server's stream.
+    drop(response);
+    tokio::time::timeout(Duration::from_secs(5), dropped)
+        .await
+        .unwrap()
+        .unwrap();
+    f.stop().await;
+}
+
+#[tokio::test]
+async fn unknown_methods_fail() {
+    let f = Fixture::new().await;
+    let (_tx, response) = f.start("/test/Missing").await;
+    assert_eq!(collect(response).await, (vec![], Code::Unimplemented));
+    f.stop().await;
+}
@@ -0,0 +1,105 @@
+use interop::client::{InteropTest, InteropTestUnimplemented};
+use interop::client_grpc;
+use std::str::FromStr;
+
+#[derive(Debug)]
+struct Opts {
+    server_host: String,
+    server_port: u16,
+    test_case: Vec<Testcase>,
+}
+
+impl Opts {
+    fn parse() -> Result<Self, pico_args::Error> {
+        let mut pargs = pico_args::Arguments::from_env();
+        Ok(Self {
+            server_host: pargs
+                .opt_value_from_str("--server_host")?
+                .unwrap_or_else(|| "localhost".to_string()),
+            server_port: pargs.opt_value_from_str("--server_port")?.unwrap_or(10000),
+            test_case: pargs.value_from_fn("--test_case", |test_case| {
+                test_case.split(',').map(Testcase::from_str).collect()
+            })?,
+        })
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    interop::trace_init();
+
+    let matches = Opts::parse()?;
+
+    grpc::client::reg();
+    let target = format!("dns:///{}:{}", matches.server_host, matches.server_port);
+    let channel = grpc::client::Channel::new(&target, None, Default::default());
+
+    let mut client = client_grpc::TestClient::new(channel.clone());
+    let mut unimplemented_client = client_grpc::UnimplementedClient::new(channel);
+
+    let mut failures = Vec::new();
+
+    for test_case in matches.test_case {
+        println!("{test_case:?}:");
+        let mut test_results = Vec::new();
+
+        match test_case {
+            Testcase::EmptyUnary => client.empty_unary(&mut test_results).await,
+            Testcase::LargeUnary => client.large_unary(&mut test_results).await,
+            Testcase::ClientStreaming => client.client_streaming(&mut test_results).await,
+            Testcase::ServerStreaming => client.server_streaming(&mut test_results).await,
+            Testcase::PingPong => client.ping_pong(&mut test_results).await,
+            Testcase::EmptyStream => client.empty_stream(&mut test_results).await,
+            Testcase::StatusCodeAndMessage => {
+                client.status_code_and_message(&mut test_results).await
+            }
+            Testcase::SpecialStatusMessage => {
+                client.special_status_message(&mut test_results).await
+            }
+            Testcase::UnimplementedMethod => client.unimplemented_method(&mut test_results).await,
+            Testcase::UnimplementedService => {
+                unimplemented_client
+                    .unimplemented_service(&mut test_results)
+                    .await
+            }
+            Testcase::CustomMetadata => client.custom_metadata(&mut test_results).await,
+            Testcase::TimeoutOnSleepingServer => {
+                client.timeout_on_sleeping_server(&mut test_results).await
+            }
+        }
+
+        for result in test_results {
+            println!("  {result}");
+
+            if result.is_failed() {
+                failures.push(result);
+            }
+        }
+    }
+
+    if !failures.is_empty() {
+        println!("{} tests failed", failures.len());
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+/// The test cases supported by the grpc client.  TLS, credentials and
+/// compression are not supported by the channel yet.
+#[derive(Debug, strum::EnumString)]
+#[strum(serialize_all = "snake_case")]
+enum Testcase {
+    EmptyUnary,
+    LargeUnary,
+    ClientStreaming,
+    ServerStreaming,
+    PingPong,
+    EmptyStream,
+    CustomMetadata,
+    StatusCodeAndMessage,
+    SpecialStatusMessage,
+    UnimplementedMethod,
+    UnimplementedService,
+    TimeoutOnSleepingServer,
+}
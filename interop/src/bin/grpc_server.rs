@@ -0,0 +1,69 @@
+use std::net::SocketAddr;
+
+use grpc::server::tcp::TcpListener;
+use grpc::server::{Server, TonicAdapter};
+use grpc::service::{Request, Response, Service};
+use interop::server;
+use tonic::async_trait;
+
+#[derive(Debug)]
+struct Opts {
+    port: u16,
+}
+
+impl Opts {
+    fn parse() -> Result<Self, pico_args::Error> {
+        let mut pargs = pico_args::Arguments::from_env();
+        Ok(Self {
+            port: pargs.opt_value_from_str("--port")?.unwrap_or(10000),
+        })
+    }
+}
+
+type TestService = server::EchoHeadersSvc<server::TestServiceServer<server::TestService>>;
+type UnimplementedService = server::UnimplementedServiceServer<server::UnimplementedService>;
+
+/// Routes calls to the test service, or to the unimplemented service.  Calls
+/// to other services fail with UNIMPLEMENTED.
+struct Router {
+    test_service: TonicAdapter<TestService>,
+    unimplemented_service: TonicAdapter<UnimplementedService>,
+}
+
+#[async_trait]
+impl Service for Router {
+    async fn call(&self, method: String, request: Request) -> Response {
+        if method.starts_with("/grpc.testing.UnimplementedService/") {
+            self.unimplemented_service.call(method, request).await
+        } else {
+            self.test_service.call(method, request).await
+        }
+    }
+}
+
+/// Serves the interop test services with the grpc crate's server.  TLS is not
+/// supported yet.
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    interop::trace_init();
+
+    let matches = Opts::parse()?;
+
+    let addr = SocketAddr::from(([127, 0, 0, 1], matches.port));
+    let listener = TcpListener::bind(addr)?;
+
+    let mut srv = Server::new();
+    srv.set_handler(Router {
+        // Wrap the test service with a service that will echo headers as
+        // trailers.
+        test_service: TonicAdapter::new(server::EchoHeadersSvc::new(
+            server::TestServiceServer::new(server::TestService::default()),
+        )),
+        unimplemented_service: TonicAdapter::new(server::UnimplementedServiceServer::new(
+            server::UnimplementedService::default(),
+        )),
+    });
+    srv.serve(&listener).await;
+
+    Ok(())
+}
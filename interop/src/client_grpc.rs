@@ -0,0 +1,593 @@
+/*
+ *
+ * Copyright 2025 gRPC authors.
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to
+ * deal in the Software without restriction, including without limitation the
+ * rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+ * sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+ * IN THE SOFTWARE.
+ *
+ */
+
+//! An implementation of the interop test client on top of
+//! [`grpc::client::Channel`] instead of tonic's channel, for validating the
+//! wire compatibility of the new stack.
+
+use crate::client::{InteropTest, InteropTestUnimplemented};
+use crate::{pb::*, test_assert, TestAssertion};
+use bytes::Bytes;
+use grpc::client::Channel;
+use grpc::service::Message as GrpcMessage;
+use std::any::Any;
+use std::pin::Pin;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_stream::{Stream, StreamExt};
+use tonic::async_trait;
+use tonic::{metadata::MetadataValue, Code, Request, Response, Status};
+
+const LARGE_REQ_SIZE: usize = 271_828;
+const LARGE_RSP_SIZE: i32 = 314_159;
+const REQUEST_LENGTHS: &[i32] = &[27182, 8, 1828, 45904];
+const RESPONSE_LENGTHS: &[i32] = &[31415, 9, 2653, 58979];
+const TEST_STATUS_MESSAGE: &str = "test status message";
+const SPECIAL_TEST_STATUS_MESSAGE: &str =
+    "\t\ntest with whitespace\r\nand Unicode BMP ☺ and non-BMP 😈\t\n";
+
+const TEST_SERVICE: &str = "/grpc.testing.TestService";
+const UNIMPLEMENTED_SERVICE: &str = "/grpc.testing.UnimplementedService";
+
+type ResponseStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
+
+/// Performs calls on a channel, encoding and decoding messages with prost.
+#[derive(Clone)]
+struct Client {
+    channel: Channel,
+}
+
+impl Client {
+    async fn streaming<S, Req, Res>(
+        &self,
+        method: String,
+        request: Request<S>,
+    ) -> Response<ResponseStream<Res>>
+    where
+        S: Stream<Item = Req> + Send + Sync + 'static,
+        Req: prost::Message + 'static,
+        Res: prost::Message + Default + 'static,
+    {
+        let request = request.map(|stream| {
+            Box::pin(
+                stream
+                    .map(|msg| Box::new(Bytes::from(msg.encode_to_vec())) as Box<dyn GrpcMessage>),
+            ) as Pin<Box<dyn Stream<Item = Box<dyn GrpcMessage>> + Send + Sync>>
+        });
+        let response = self.channel.call(method, request).await;
+        response.map(|stream| {
+            Box::pin(stream.map(|msg| {
+                let msg = (msg? as Box<dyn Any>)
+                    .downcast::<Bytes>()
+                    .map_err(|_| Status::internal("response message is not encoded"))?;
+                Res::decode(*msg).map_err(|err| Status::internal(err.to_string()))
+            })) as ResponseStream<Res>
+        })
+    }
+
+    async fn unary<Req, Res>(
+        &self,
+        method: String,
+        request: Request<Req>,
+    ) -> Result<Response<Res>, Status>
+    where
+        Req: prost::Message + 'static,
+        Res: prost::Message + Default + 'static,
+    {
+        let response = self
+            .streaming(method, request.map(tokio_stream::once))
+            .await;
+        let (metadata, mut stream, extensions) = response.into_parts();
+        let msg = stream
+            .next()
+            .await
+            .ok_or_else(|| Status::internal("missing response message"))??;
+        Ok(Response::from_parts(metadata, msg, extensions))
+    }
+}
+
+/// A client for `grpc.testing.TestService`.
+pub struct TestClient {
+    client: Client,
+}
+
+impl TestClient {
+    pub fn new(channel: Channel) -> Self {
+        Self {
+            client: Client { channel },
+        }
+    }
+
+    async fn empty_call(&self, request: Request<Empty>) -> Result<Response<Empty>, Status> {
+        self.client
+            .unary(format!("{TEST_SERVICE}/EmptyCall"), request)
+            .await
+    }
+
+    async fn unary_call(
+        &self,
+        request: Request<SimpleRequest>,
+    ) -> Result<Response<SimpleResponse>, Status> {
+        self.client
+            .unary(format!("{TEST_SERVICE}/UnaryCall"), request)
+            .await
+    }
+
+    async fn unimplemented_call(&self, request: Request<Empty>) -> Result<Response<Empty>, Status> {
+        self.client
+            .unary(format!("{TEST_SERVICE}/UnimplementedCall"), request)
+            .await
+    }
+
+    async fn streaming_input_call<S>(
+        &self,
+        request: Request<S>,
+    ) -> Result<Response<StreamingInputCallResponse>, Status>
+    where
+        S: Stream<Item = StreamingInputCallRequest> + Send + Sync + 'static,
+    {
+        let response = self
+            .client
+            .streaming(format!("{TEST_SERVICE}/StreamingInputCall"), request)
+            .await;
+        let (metadata, mut stream, extensions) = response.into_parts();
+        let msg = stream
+            .next()
+            .await
+            .ok_or_else(|| Status::internal("missing response message"))??;
+        Ok(Response::from_parts(metadata, msg, extensions))
+    }
+
+    async fn streaming_output_call(
+        &self,
+        request: Request<StreamingOutputCallRequest>,
+    ) -> Response<ResponseStream<StreamingOutputCallResponse>> {
+        self.client
+            .streaming(
+                format!("{TEST_SERVICE}/StreamingOutputCall"),
+                request.map(tokio_stream::once),
+            )
+            .await
+    }
+
+    async fn full_duplex_call<S>(
+        &self,
+        request: Request<S>,
+    ) -> Response<ResponseStream<StreamingOutputCallResponse>>
+    where
+        S: Stream<Item = StreamingOutputCallRequest> + Send + Sync + 'static,
+    {
+        self.client
+            .streaming(format!("{TEST_SERVICE}/FullDuplexCall"), request)
+            .await
+    }
+
+    /// Runs the `timeout_on_sleeping_server` test case, which tonic's clients
+    /// don't implement.  The channel only enforces deadlines while calls wait
+    /// to be routed, so this relies on the server enforcing the call's
+    /// `grpc-timeout`.
+    pub async fn timeout_on_sleeping_server(&mut self, assertions: &mut Vec<TestAssertion>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        tx.send(StreamingOutputCallRequest {
+            payload: Some(crate::client_payload(27182)),
+            ..Default::default()
+        })
+        .unwrap();
+        let mut request = Request::new(tokio_stream::wrappers::UnboundedReceiverStream::new(rx));
+        request.set_timeout(Duration::from_millis(1));
+
+        // Bound the wait, so that a server which ignores the deadline fails
+        // the test instead of hanging it.
+        let result = tokio::time::timeout(Duration::from_secs(10), async {
+            self.full_duplex_call(request)
+                .await
+                .into_inner()
+                .next()
+                .await
+        })
+        .await;
+        assertions.push(test_assert!(
+            "call must fail with deadline exceeded status code",
+            matches!(&result, Ok(Some(Err(status))) if status.code() == Code::DeadlineExceeded),
+            format!("result={:?}", result)
+        ));
+        drop(tx);
+    }
+}
+
+#[async_trait]
+impl InteropTest for TestClient {
+    async fn empty_unary(&mut self, assertions: &mut Vec<TestAssertion>) {
+        let result = self.empty_call(Request::new(Empty {})).await;
+
+        assertions.push(test_assert!(
+            "call must be successful",
+            result.is_ok(),
+            format!("result={:?}", result)
+        ));
+
+        if let Ok(response) = result {
+            let body = response.into_inner();
+            assertions.push(test_assert!(
+                "body must not be null",
+                body == Empty {},
+                format!("body={:?}", body)
+            ));
+        }
+    }
+
+    async fn large_unary(&mut self, assertions: &mut Vec<TestAssertion>) {
+        let payload = crate::client_payload(LARGE_REQ_SIZE);
+        let req = SimpleRequest {
+            response_type: PayloadType::Compressable as i32,
+            response_size: LARGE_RSP_SIZE,
+            payload: Some(payload),
+            ..Default::default()
+        };
+
+        let result = self.unary_call(Request::new(req)).await;
+
+        assertions.push(test_assert!(
+            "call must be successful",
+            result.is_ok(),
+            format!("result={:?}", result.as_ref().map(|_| ()))
+        ));
+
+        if let Ok(response) = result {
+            let body = response.into_inner();
+            let payload_len = body.payload.as_ref().map(|p| p.body.len()).unwrap_or(0);
+
+            assertions.push(test_assert!(
+                "body must be 314159 bytes",
+                payload_len == LARGE_RSP_SIZE as usize,
+                format!("payload_len={:?}", payload_len)
+            ));
+        }
+    }
+
+    async fn client_streaming(&mut self, assertions: &mut Vec<TestAssertion>) {
+        let requests: Vec<_> = REQUEST_LENGTHS
+            .iter()
+            .map(make_streaming_input_request)
+            .collect();
+
+        let stream = tokio_stream::iter(requests);
+
+        let result = self.streaming_input_call(Request::new(stream)).await;
+
+        assertions.push(test_assert!(
+            "call must be successful",
+            result.is_ok(),
+            format!("result={:?}", result)
+        ));
+
+        if let Ok(response) = result {
+            let body = response.into_inner();
+
+            assertions.push(test_assert!(
+                "aggregated payload size must be 74922 bytes",
+                body.aggregated_payload_size == 74922,
+                format!("aggregated_payload_size={:?}", body.aggregated_payload_size)
+            ));
+        }
+    }
+
+    async fn server_streaming(&mut self, assertions: &mut Vec<TestAssertion>) {
+        let req = StreamingOutputCallRequest {
+            response_parameters: RESPONSE_LENGTHS
+                .iter()
+                .map(|len| ResponseParameters::with_size(*len))
+                .collect(),
+            ..Default::default()
+        };
+
+        let results = self
+            .streaming_output_call(Request::new(req))
+            .await
+            .into_inner()
+            .collect::<Vec<_>>()
+            .await;
+
+        assertions.push(test_assert!(
+            "call must be successful",
+            results.iter().all(Result::is_ok),
+            format!("results={:?}", results)
+        ));
+
+        let responses = results
+            .into_iter()
+            .filter_map(Result::ok)
+            .collect::<Vec<_>>();
+        let actual_response_lengths = crate::response_lengths(&responses);
+        assertions.push(test_assert!(
+            "there should be four responses",
+            responses.len() == 4,
+            format!("responses.len()={:?}", responses.len())
+        ));
+        assertions.push(test_assert!(
+            "the response payload sizes should match input",
+            RESPONSE_LENGTHS == actual_response_lengths.as_slice(),
+            format!("{:?}={:?}", RESPONSE_LENGTHS, actual_response_lengths)
+        ));
+    }
+
+    async fn ping_pong(&mut self, assertions: &mut Vec<TestAssertion>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        tx.send(make_ping_pong_request(0)).unwrap();
+
+        let mut response = self
+            .full_duplex_call(Request::new(
+                tokio_stream::wrappers::UnboundedReceiverStream::new(rx),
+            ))
+            .await
+            .into_inner();
+
+        let mut responses = Vec::new();
+        loop {
+            match response.next().await {
+                Some(Ok(msg)) => {
+                    responses.push(msg);
+                    if responses.len() == REQUEST_LENGTHS.len() {
+                        drop(tx);
+                        break;
+                    } else {
+                        tx.send(make_ping_pong_request(responses.len())).unwrap();
+                    }
+                }
+                Some(Err(status)) => {
+                    assertions.push(test_assert!(
+                        "call must be successful",
+                        false,
+                        format!("status={:?}", status)
+                    ));
+                    break;
+                }
+                None => {
+                    assertions.push(TestAssertion::Failed {
+                        description:
+                            "server should keep the stream open until the client closes it",
+                        expression: "Stream terminated unexpectedly early",
+                        why: None,
+                    });
+                    break;
+                }
+            }
+        }
+
+        let actual_response_lengths = crate::response_lengths(&responses);
+        assertions.push(test_assert!(
+            "there should be four responses",
+            responses.len() == RESPONSE_LENGTHS.len(),
+            format!("{:?}={:?}", responses.len(), RESPONSE_LENGTHS.len())
+        ));
+        assertions.push(test_assert!(
+            "the response payload sizes should match input",
+            RESPONSE_LENGTHS == actual_response_lengths.as_slice(),
+            format!("{:?}={:?}", RESPONSE_LENGTHS, actual_response_lengths)
+        ));
+    }
+
+    async fn empty_stream(&mut self, assertions: &mut Vec<TestAssertion>) {
+        let stream = tokio_stream::empty();
+        let responses = self
+            .full_duplex_call(Request::new(stream))
+            .await
+            .into_inner()
+            .collect::<Vec<_>>()
+            .await;
+
+        assertions.push(test_assert!(
+            "there should be no responses",
+            responses.is_empty(),
+            format!("responses={:?}", responses)
+        ));
+    }
+
+    async fn status_code_and_message(&mut self, assertions: &mut Vec<TestAssertion>) {
+        fn validate_response<T>(result: Result<T, Status>, assertions: &mut Vec<TestAssertion>)
+        where
+            T: std::fmt::Debug,
+        {
+            assertions.push(test_assert!(
+                "call must fail with unknown status code",
+                match &result {
+                    Err(status) => status.code() == Code::Unknown,
+                    _ => false,
+                },
+                format!("result={:?}", result)
+            ));
+
+            assertions.push(test_assert!(
+                "call must respsond with expected status message",
+                match &result {
+                    Err(status) => status.message() == TEST_STATUS_MESSAGE,
+                    _ => false,
+                },
+                format!("result={:?}", result)
+            ));
+        }
+
+        let simple_req = SimpleRequest {
+            response_status: Some(EchoStatus {
+                code: 2,
+                message: TEST_STATUS_MESSAGE.to_string(),
+            }),
+            ..Default::default()
+        };
+
+        let duplex_req = StreamingOutputCallRequest {
+            response_status: Some(EchoStatus {
+                code: 2,
+                message: TEST_STATUS_MESSAGE.to_string(),
+            }),
+            ..Default::default()
+        };
+
+        let result = self.unary_call(Request::new(simple_req)).await;
+        validate_response(result, assertions);
+
+        let stream = tokio_stream::once(duplex_req);
+        let result = self
+            .full_duplex_call(Request::new(stream))
+            .await
+            .into_inner()
+            .collect::<Result<Vec<_>, _>>()
+            .await;
+        validate_response(result, assertions);
+    }
+
+    async fn special_status_message(&mut self, assertions: &mut Vec<TestAssertion>) {
+        let req = SimpleRequest {
+            response_status: Some(EchoStatus {
+                code: 2,
+                message: SPECIAL_TEST_STATUS_MESSAGE.to_string(),
+            }),
+            ..Default::default()
+        };
+
+        let result = self.unary_call(Request::new(req)).await;
+
+        assertions.push(test_assert!(
+            "call must fail with unknown status code",
+            match &result {
+                Err(status) => status.code() == Code::Unknown,
+                _ => false,
+            },
+            format!("result={:?}", result)
+        ));
+
+        assertions.push(test_assert!(
+            "call must respsond with expected status message",
+            match &result {
+                Err(status) => status.message() == SPECIAL_TEST_STATUS_MESSAGE,
+                _ => false,
+            },
+            format!("result={:?}", result)
+        ));
+    }
+
+    async fn unimplemented_method(&mut self, assertions: &mut Vec<TestAssertion>) {
+        let result = self.unimplemented_call(Request::new(Empty {})).await;
+        assertions.push(test_assert!(
+            "call must fail with unimplemented status code",
+            match &result {
+                Err(status) => status.code() == Code::Unimplemented,
+                _ => false,
+            },
+            format!("result={:?}", result)
+        ));
+    }
+
+    async fn custom_metadata(&mut self, assertions: &mut Vec<TestAssertion>) {
+        let key1 = "x-grpc-test-echo-initial";
+        let value1: MetadataValue<_> = "test_initial_metadata_value".parse().unwrap();
+        let key2 = "x-grpc-test-echo-trailing-bin";
+        let value2 = MetadataValue::from_bytes(&[0xab, 0xab, 0xab]);
+
+        let req = SimpleRequest {
+            response_type: PayloadType::Compressable as i32,
+            response_size: LARGE_RSP_SIZE,
+            payload: Some(crate::client_payload(LARGE_REQ_SIZE)),
+            ..Default::default()
+        };
+        let mut req_unary = Request::new(req);
+        req_unary.metadata_mut().insert(key1, value1.clone());
+        req_unary.metadata_mut().insert_bin(key2, value2.clone());
+
+        let stream = tokio_stream::once(make_ping_pong_request(0));
+        let mut req_stream = Request::new(stream);
+        req_stream.metadata_mut().insert(key1, value1.clone());
+        req_stream.metadata_mut().insert_bin(key2, value2);
+
+        let response = self.unary_call(req_unary).await.expect("call should pass.");
+
+        assertions.push(test_assert!(
+            "metadata string must match in unary",
+            response.metadata().get(key1) == Some(&value1),
+            format!("result={:?}", response.metadata().get(key1))
+        ));
+
+        let response = self.full_duplex_call(req_stream).await;
+
+        assertions.push(test_assert!(
+            "metadata string must match in streaming",
+            response.metadata().get(key1) == Some(&value1),
+            format!("result={:?}", response.metadata().get(key1))
+        ));
+
+        // TODO: check the echoed trailing metadata once grpc::client::Channel
+        // exposes trailers.
+    }
+}
+
+/// A client for `grpc.testing.UnimplementedService`.
+pub struct UnimplementedClient {
+    client: Client,
+}
+
+impl UnimplementedClient {
+    pub fn new(channel: Channel) -> Self {
+        Self {
+            client: Client { channel },
+        }
+    }
+}
+
+#[async_trait]
+impl InteropTestUnimplemented for UnimplementedClient {
+    async fn unimplemented_service(&mut self, assertions: &mut Vec<TestAssertion>) {
+        let result: Result<Response<Empty>, _> = self
+            .client
+            .unary(
+                format!("{UNIMPLEMENTED_SERVICE}/UnimplementedCall"),
+                Request::new(Empty {}),
+            )
+            .await;
+        assertions.push(test_assert!(
+            "call must fail with unimplemented status code",
+            match &result {
+                Err(status) => status.code() == Code::Unimplemented,
+                _ => false,
+            },
+            format!("result={:?}", result)
+        ));
+    }
+}
+
+fn make_ping_pong_request(idx: usize) -> StreamingOutputCallRequest {
+    let req_len = REQUEST_LENGTHS[idx];
+    let resp_len = RESPONSE_LENGTHS[idx];
+    StreamingOutputCallRequest {
+        response_parameters: vec![ResponseParameters::with_size(resp_len)],
+        payload: Some(crate::client_payload(req_len as usize)),
+        ..Default::default()
+    }
+}
+
+fn make_streaming_input_request(len: &i32) -> StreamingInputCallRequest {
+    StreamingInputCallRequest {
+        payload: Some(crate::client_payload(*len as usize)),
+        ..Default::default()
+    }
+}
@@ -1,6 +1,8 @@
 #![recursion_limit = "256"]
 
 pub mod client;
+#[cfg(feature = "grpc-client")]
+pub mod client_grpc;
 pub mod client_prost;
 pub mod client_protobuf;
 pub mod server;
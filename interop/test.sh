@@ -62,6 +62,13 @@ sleep 1
 # Test a grpc rust client against a Go server.
 ./target/debug/client --codec=protobuf --test_case="${JOINED_TEST_CASES}" ${ARG}
 
+# Test a client built on the grpc crate's channel against a Go server. The
+# channel does not support TLS yet.
+if [ -z "${ARG}" ]; then
+  (cd interop && cargo build --bin grpc_client --features grpc-client)
+  ./target/debug/grpc_client --test_case="${JOINED_TEST_CASES},timeout_on_sleeping_server"
+fi
+
 echo ":; killing test server"; kill "${SERVER_PID}";
 
 # run the test server
@@ -96,3 +103,20 @@ for CASE in "${TEST_CASES[@]}"; do
   interop/bin/client_"${OS}"_amd64"${EXT}" "${flags[@]}"
 done
 
+# Test the clients against a server built on the grpc crate's server, which
+# does not support TLS yet.
+if [ -z "${ARG}" ]; then
+  echo ":; killing test server"; kill "${SERVER_PID}";
+
+  (cd interop && cargo build --bin grpc_server --features grpc-server)
+  ./target/debug/grpc_server &
+  SERVER_PID=$!
+  echo ":; started grpc test server."
+
+  trap 'echo ":; killing test server"; kill ${SERVER_PID};' EXIT
+
+  sleep 1
+
+  ./target/debug/client --codec=prost --test_case="${JOINED_TEST_CASES}"
+  ./target/debug/grpc_client --test_case="${JOINED_TEST_CASES}"
+fi
@@ -108,6 +108,37 @@ impl CodeGenBuilder {
             self.generate_default_stubs,
         )
     }
+
+    /// Generate client code for the `grpc` crate based on `Service`.
+    ///
+    /// This takes some `Service` and will generate a `TokenStream` that contains
+    /// a public module with a client on top of `grpc::client::Channel`.
+    pub fn generate_grpc_client(&self, service: &impl Service, proto_path: &str) -> TokenStream {
+        crate::grpc::generate_client(
+            service,
+            self.emit_package,
+            proto_path,
+            self.compile_well_known_types,
+            &self.attributes,
+            &self.disable_comments,
+        )
+    }
+
+    /// Generate server code for the `grpc` crate based on `Service`.
+    ///
+    /// This takes some `Service` and will generate a `TokenStream` that contains
+    /// a public module with the service trait and a `grpc::service::Service`
+    /// implementation for it.
+    pub fn generate_grpc_server(&self, service: &impl Service, proto_path: &str) -> TokenStream {
+        crate::grpc::generate_server(
+            service,
+            self.emit_package,
+            proto_path,
+            self.compile_well_known_types,
+            &self.attributes,
+            &self.disable_comments,
+        )
+    }
 }
 
 impl Default for CodeGenBuilder {
@@ -0,0 +1,279 @@
+use std::collections::HashSet;
+
+use super::{Attributes, Method, Service};
+use crate::{
+    format_method_name, format_method_path, format_service_name, generate_deprecated,
+    generate_doc_comment, generate_doc_comments, naive_snake_case,
+};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+pub(crate) fn generate_client<T: Service>(
+    service: &T,
+    emit_package: bool,
+    proto_path: &str,
+    compile_well_known_types: bool,
+    attributes: &Attributes,
+    disable_comments: &HashSet<String>,
+) -> TokenStream {
+    let service_ident = format_ident!("{}Client", service.name());
+    let client_mod = format_ident!("{}_client", naive_snake_case(service.name()));
+    let package = if emit_package { service.package() } else { "" };
+    let service_name = format_service_name(service, emit_package);
+
+    let service_doc = if disable_comments.contains(&service_name) {
+        TokenStream::new()
+    } else {
+        generate_doc_comments(service.comment())
+    };
+
+    let mod_attributes = attributes.for_mod(package);
+    let struct_attributes = attributes.for_struct(&service_name);
+
+    let mut methods = TokenStream::new();
+    for method in service.methods() {
+        methods.extend(generate_method_doc(
+            service,
+            method,
+            emit_package,
+            disable_comments,
+        ));
+
+        let codec_name = syn::parse_str::<syn::Path>(method.codec_path()).unwrap();
+        let ident = format_ident!("{}", method.name());
+        let (request, response) =
+            method.request_response_name(proto_path, compile_well_known_types);
+        let path = format_method_path(service, method, emit_package);
+
+        methods.extend(
+            match (method.client_streaming(), method.server_streaming()) {
+                (false, false) => quote! {
+                    pub async fn #ident(
+                        &self,
+                        request: impl IntoRequest<#request>,
+                    ) -> std::result::Result<Response<#response>, Status> {
+                        let codec = #codec_name::default();
+                        self.inner.unary(#path, request.into_request(), codec).await
+                    }
+                },
+                (false, true) => quote! {
+                    pub async fn #ident(
+                        &self,
+                        request: impl IntoRequest<#request>,
+                    ) -> std::result::Result<Response<BoxStream<#response>>, Status> {
+                        let codec = #codec_name::default();
+                        self.inner.server_streaming(#path, request.into_request(), codec).await
+                    }
+                },
+                (true, false) => quote! {
+                    pub async fn #ident<S>(
+                        &self,
+                        request: S,
+                    ) -> std::result::Result<Response<#response>, Status>
+                    where
+                        S: IntoStreamingRequest<Message = #request>,
+                        S::Stream: std::marker::Sync,
+                    {
+                        let codec = #codec_name::default();
+                        self.inner
+                            .client_streaming(#path, request.into_streaming_request(), codec)
+                            .await
+                    }
+                },
+                (true, true) => quote! {
+                    pub async fn #ident<S>(
+                        &self,
+                        request: S,
+                    ) -> std::result::Result<Response<BoxStream<#response>>, Status>
+                    where
+                        S: IntoStreamingRequest<Message = #request>,
+                        S::Stream: std::marker::Sync,
+                    {
+                        let codec = #codec_name::default();
+                        self.inner
+                            .streaming(#path, request.into_streaming_request(), codec)
+                            .await
+                    }
+                },
+            },
+        );
+    }
+
+    quote! {
+        /// Generated client implementations for the grpc crate.
+        #(#mod_attributes)*
+        pub mod #client_mod {
+            #![allow(
+                unused_variables,
+                dead_code,
+                missing_docs,
+                clippy::wildcard_imports,
+            )]
+            use grpc::codegen::*;
+
+            #service_doc
+            #(#struct_attributes)*
+            #[derive(Clone)]
+            pub struct #service_ident {
+                inner: Grpc,
+            }
+
+            impl #service_ident {
+                pub fn new(channel: Channel) -> Self {
+                    Self { inner: Grpc::new(channel) }
+                }
+
+                #methods
+            }
+        }
+    }
+}
+
+pub(crate) fn generate_server<T: Service>(
+    service: &T,
+    emit_package: bool,
+    proto_path: &str,
+    compile_well_known_types: bool,
+    attributes: &Attributes,
+    disable_comments: &HashSet<String>,
+) -> TokenStream {
+    let server_service = format_ident!("{}Server", service.name());
+    let server_trait = format_ident!("{}", service.name());
+    let server_mod = format_ident!("{}_server", naive_snake_case(service.name()));
+    let package = if emit_package { service.package() } else { "" };
+    let service_name = format_service_name(service, emit_package);
+
+    let service_doc = if disable_comments.contains(&service_name) {
+        TokenStream::new()
+    } else {
+        generate_doc_comments(service.comment())
+    };
+
+    let mod_attributes = attributes.for_mod(package);
+    let struct_attributes = attributes.for_struct(&service_name);
+    let trait_attributes = attributes.for_trait(service.name());
+    let trait_doc = generate_doc_comment(format!(
+        " Generated trait containing gRPC methods that should be implemented for use with {}Server.",
+        service.name()
+    ));
+
+    let mut trait_methods = TokenStream::new();
+    let mut arms = TokenStream::new();
+    for method in service.methods() {
+        trait_methods.extend(generate_method_doc(
+            service,
+            method,
+            emit_package,
+            disable_comments,
+        ));
+
+        let codec_name = syn::parse_str::<syn::Path>(method.codec_path()).unwrap();
+        let ident = format_ident!("{}", method.name());
+        let (request, response) =
+            method.request_response_name(proto_path, compile_well_known_types);
+        let path = format_method_path(service, method, emit_package);
+
+        let request = if method.client_streaming() {
+            quote!(Request<BoxStream<#request>>)
+        } else {
+            quote!(Request<#request>)
+        };
+        let response = if method.server_streaming() {
+            quote!(Response<BoxStream<#response>>)
+        } else {
+            quote!(Response<#response>)
+        };
+        trait_methods.extend(quote! {
+            async fn #ident(
+                &self,
+                request: #request,
+            ) -> std::result::Result<#response, Status>;
+        });
+
+        let serve = match (method.client_streaming(), method.server_streaming()) {
+            (false, false) => quote!(serve_unary),
+            (false, true) => quote!(serve_server_streaming),
+            (true, false) => quote!(serve_client_streaming),
+            (true, true) => quote!(serve_streaming),
+        };
+        arms.extend(quote! {
+            #path => {
+                let codec = #codec_name::default();
+                #serve(request, codec, |req| self.inner.#ident(req)).await
+            }
+        });
+    }
+
+    quote! {
+        /// Generated server implementations for the grpc crate.
+        #(#mod_attributes)*
+        pub mod #server_mod {
+            #![allow(
+                unused_variables,
+                dead_code,
+                missing_docs,
+                clippy::wildcard_imports,
+            )]
+            use grpc::codegen::*;
+
+            #trait_doc
+            #(#trait_attributes)*
+            #[async_trait]
+            pub trait #server_trait: std::marker::Send + std::marker::Sync + 'static {
+                #trait_methods
+            }
+
+            #service_doc
+            #(#struct_attributes)*
+            pub struct #server_service<T> {
+                inner: Arc<T>,
+            }
+
+            impl<T> #server_service<T> {
+                pub fn new(inner: T) -> Self {
+                    Self::from_arc(Arc::new(inner))
+                }
+
+                pub fn from_arc(inner: Arc<T>) -> Self {
+                    Self { inner }
+                }
+            }
+
+            impl<T> Clone for #server_service<T> {
+                fn clone(&self) -> Self {
+                    Self { inner: self.inner.clone() }
+                }
+            }
+
+            #[async_trait]
+            impl<T: #server_trait> Service for #server_service<T> {
+                async fn call(
+                    &self,
+                    method: String,
+                    request: grpc::service::Request,
+                ) -> grpc::service::Response {
+                    match method.as_str() {
+                        #arms
+                        _ => unimplemented(&method),
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn generate_method_doc<T: Service>(
+    service: &T,
+    method: &T::Method,
+    emit_package: bool,
+    disable_comments: &HashSet<String>,
+) -> TokenStream {
+    let mut stream = TokenStream::new();
+    if !disable_comments.contains(&format_method_name(service, method, emit_package)) {
+        stream.extend(generate_doc_comments(method.comment()));
+    }
+    if method.deprecated() {
+        stream.extend(generate_deprecated());
+    }
+    stream
+}
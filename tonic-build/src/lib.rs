@@ -16,6 +16,8 @@ pub mod manual;
 
 /// Service code generation for client
 mod client;
+/// Service code generation for the grpc crate
+mod grpc;
 /// Service code generation for Server
 mod server;
 
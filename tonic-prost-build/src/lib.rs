@@ -83,6 +83,7 @@ pub fn configure() -> Builder {
         use_arc_self: false,
         generate_default_stubs: false,
         codec_path: "tonic_prost::ProstCodec".to_string(),
+        grpc_stack: false,
         skip_debug: HashSet::default(),
     }
 }
@@ -311,6 +312,7 @@ struct ServiceGenerator {
     proto_path: String,
     compile_well_known_types: bool,
     codec_path: String,
+    grpc_stack: bool,
     disable_comments: HashSet<String>,
 }
 
@@ -328,6 +330,7 @@ impl ServiceGenerator {
         proto_path: String,
         compile_well_known_types: bool,
         codec_path: String,
+        grpc_stack: bool,
         disable_comments: HashSet<String>,
     ) -> Self {
         ServiceGenerator {
@@ -341,6 +344,7 @@ impl ServiceGenerator {
             proto_path,
             compile_well_known_types,
             codec_path,
+            grpc_stack,
             disable_comments,
         }
     }
@@ -363,13 +367,21 @@ impl prost_build::ServiceGenerator for ServiceGenerator {
 
         if self.build_client {
             builder.attributes(self.client_attributes.clone());
-            let client_code = builder.generate_client(&tonic_service, &self.proto_path);
+            let client_code = if self.grpc_stack {
+                builder.generate_grpc_client(&tonic_service, &self.proto_path)
+            } else {
+                builder.generate_client(&tonic_service, &self.proto_path)
+            };
             tokens.extend(client_code);
         }
 
         if self.build_server {
             builder.attributes(self.server_attributes.clone());
-            let server_code = builder.generate_server(&tonic_service, &self.proto_path);
+            let server_code = if self.grpc_stack {
+                builder.generate_grpc_server(&tonic_service, &self.proto_path)
+            } else {
+                builder.generate_server(&tonic_service, &self.proto_path)
+            };
             tokens.extend(server_code);
         }
 
@@ -407,6 +419,7 @@ pub struct Builder {
     use_arc_self: bool,
     generate_default_stubs: bool,
     codec_path: String,
+    grpc_stack: bool,
     skip_debug: HashSet<String>,
 }
 
@@ -674,6 +687,20 @@ impl Builder {
         self
     }
 
+    /// Generate clients and servers for the `grpc` crate instead of tonic.
+    ///
+    /// Clients are built on `grpc::client::Channel` and servers implement
+    /// `grpc::service::Service`. Enabling this also sets the codec path to
+    /// `grpc::codegen::ProstCodec`, which requires the `prost` feature of
+    /// `grpc`.
+    pub fn grpc_stack(mut self, enable: bool) -> Self {
+        self.grpc_stack = enable;
+        if enable {
+            self.codec_path = "grpc::codegen::ProstCodec".to_string();
+        }
+        self
+    }
+
     /// Configure the code generator not to strip the `Debug` implementation for the request and
     /// response types from the generated code.
     ///
@@ -789,6 +816,7 @@ impl Builder {
                 self.proto_path,
                 self.compile_well_known_types,
                 self.codec_path.clone(),
+                self.grpc_stack,
                 self.disable_comments,
             );
 
@@ -891,6 +919,7 @@ impl Builder {
                 self.proto_path,
                 self.compile_well_known_types,
                 self.codec_path.clone(),
+                self.grpc_stack,
                 self.disable_comments,
             );
 
@@ -916,6 +945,7 @@ impl Builder {
             self.proto_path,
             self.compile_well_known_types,
             self.codec_path.clone(),
+            self.grpc_stack,
             self.disable_comments,
         ))
     }
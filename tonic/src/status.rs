@@ -472,10 +472,13 @@ impl Status {
         };
 
         let details = match header_map.get(Self::GRPC_STATUS_DETAILS) {
-            Some(header) => crate::util::base64::STANDARD
-                .decode(header.as_bytes())
-                .expect("Invalid status header, expected base64 encoded value")
-                .into(),
+            Some(header) => match crate::util::base64::STANDARD.decode(header.as_bytes()) {
+                Ok(details) => details.into(),
+                Err(e) => {
+                    warn!("Ignoring invalid status details header: {e}");
+                    Bytes::new()
+                }
+            },
             None => Bytes::new(),
         };
 
@@ -1047,6 +1050,23 @@ mod tests {
 
         assert_eq!(status.details(), DETAILS);
     }
+
+    #[test]
+    fn invalid_details_are_ignored() {
+        let mut header_map = Status::unavailable("some message")
+            .to_header_map()
+            .unwrap();
+        header_map.insert(
+            Status::GRPC_STATUS_DETAILS,
+            HeaderValue::from_static("not base64!"),
+        );
+
+        let status = Status::from_header_map(&header_map).unwrap();
+
+        assert_eq!(status.code(), Code::Unavailable);
+        assert_eq!(status.message(), "some message");
+        assert!(status.details().is_empty());
+    }
 }
 
 /// Error returned if a request didn't complete within the configured timeout.